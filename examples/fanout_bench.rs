@@ -0,0 +1,100 @@
+//! Compares the pub/sub delivery of `fanout` with `tokio::sync::broadcast`
+//!
+//! Each round publishes a batch of messages to 1, 100 and 5000 subscribers,
+//! then lets every subscriber receive the batch. The time spent publishing
+//! and the total time are reported per message.
+//!
+//! Run it in release mode, the numbers of a debug build are meaningless:
+//!
+//!     cargo run --release --example fanout_bench
+
+#![warn(rust_2018_idioms)]
+
+use my_mini_redis::fanout::{self, Overflow};
+
+use bytes::Bytes;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Messages published per round, fewer than the capacity of the queues so
+/// that no subscriber lags.
+const BATCH: usize = 512;
+
+const ROUNDS: usize = 20;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let message = Bytes::from_static(&[b'x'; 64]);
+
+    println!("{:>11}  {:>9}  {:>14}  {:>14}", "subscribers", "channel", "publish/msg", "total/msg");
+
+    for subscribers in [1, 100, 5000] {
+        let (publish, total) = bench_broadcast(subscribers, &message).await;
+        report(subscribers, "broadcast", publish, total);
+
+        let (publish, total) = bench_fanout(subscribers, &message).await;
+        report(subscribers, "fanout", publish, total);
+    }
+}
+
+async fn bench_broadcast(subscribers: usize, message: &Bytes) -> (Duration, Duration) {
+    let (tx, _) = broadcast::channel(fanout::DEFAULT_CAPACITY);
+    let mut receivers: Vec<_> = (0..subscribers).map(|_| tx.subscribe()).collect();
+
+    let mut publish = Duration::ZERO;
+    let mut total = Duration::ZERO;
+
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        for _ in 0..BATCH {
+            tx.send(message.clone()).unwrap();
+        }
+        publish += start.elapsed();
+
+        for rx in &mut receivers {
+            for _ in 0..BATCH {
+                rx.recv().await.unwrap();
+            }
+        }
+        total += start.elapsed();
+    }
+
+    (publish, total)
+}
+
+async fn bench_fanout(subscribers: usize, message: &Bytes) -> (Duration, Duration) {
+    let mut tx = fanout::Sender::new(fanout::DEFAULT_CAPACITY, Overflow::DropOldest);
+    let mut receivers: Vec<_> = (0..subscribers).map(|_| tx.subscribe()).collect();
+
+    let mut publish = Duration::ZERO;
+    let mut total = Duration::ZERO;
+
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        for _ in 0..BATCH {
+            tx.send(message.clone());
+        }
+        publish += start.elapsed();
+
+        for rx in &mut receivers {
+            for _ in 0..BATCH {
+                rx.recv().await.unwrap();
+            }
+        }
+        total += start.elapsed();
+    }
+
+    (publish, total)
+}
+
+fn report(subscribers: usize, channel: &str, publish: Duration, total: Duration) {
+    let messages = (ROUNDS * BATCH) as u32;
+
+    println!(
+        "{:>11}  {:>9}  {:>14?}  {:>14?}",
+        subscribers,
+        channel,
+        publish / messages,
+        total / messages,
+    );
+}
@@ -0,0 +1,106 @@
+//! Compares the PING throughput of the epoll and io_uring backends of the
+//! server
+//!
+//! Both servers run on a thread of their own, with a single-threaded runtime:
+//! `server::run` serves with io_uring when the `uring` feature is enabled,
+//! while `server::run_with_acceptor` keeps serving with epoll. Each client
+//! sends PINGs in a loop, waiting for each reply, over 1, 16 and 128
+//! connections. The PINGs replied per second are reported.
+//!
+//! Run it in release mode, the numbers of a debug build are meaningless:
+//!
+//!     cargo run --release --features uring --example uring_bench
+
+#![warn(rust_2018_idioms)]
+
+use my_mini_redis::clients::Client;
+use my_mini_redis::server::{self, Config};
+
+use std::future;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+
+/// Time spent sending PINGs for each backend and number of connections.
+const DURATION: Duration = Duration::from_secs(3);
+
+#[tokio::main]
+async fn main() {
+    let epoll = start_epoll();
+    let uring = start_uring().await;
+
+    println!("{:>11}  {:>8}  {:>12}", "connections", "backend", "pings/s");
+
+    for connections in [1, 16, 128] {
+        for (backend, addr) in [("epoll", epoll), ("io_uring", uring)] {
+            let pings = bench(addr, connections).await;
+            println!(
+                "{:>11}  {:>8}  {:>12.0}",
+                connections,
+                backend,
+                pings as f64 / DURATION.as_secs_f64()
+            );
+        }
+    }
+}
+
+/// Starts a server served with epoll, on a current-thread runtime of its own
+/// like the io_uring backend.
+fn start_epoll() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async move {
+            let listener = TcpListener::from_std(listener).unwrap();
+            server::run_with_acceptor(listener, future::pending::<()>(), Config::default(), |socket| {
+                future::ready(Ok(socket))
+            })
+            .await
+        });
+    });
+
+    addr
+}
+
+/// Starts a server served with io_uring, on the thread `server::run` spawns.
+async fn start_uring() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(server::run(listener, future::pending::<()>()));
+
+    addr
+}
+
+/// Returns the number of PINGs replied over `connections` during `DURATION`.
+async fn bench(addr: SocketAddr, connections: usize) -> u64 {
+    let deadline = Instant::now() + DURATION;
+
+    let mut tasks = Vec::with_capacity(connections);
+    for _ in 0..connections {
+        let mut client = Client::connect(addr).await.unwrap();
+
+        tasks.push(tokio::spawn(async move {
+            let mut pings = 0;
+            while Instant::now() < deadline {
+                client.ping(None).await.unwrap();
+                pings += 1;
+            }
+            pings
+        }));
+    }
+
+    let mut pings = 0;
+    for task in tasks {
+        pings += task.await.unwrap();
+    }
+    pings
+}
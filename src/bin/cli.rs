@@ -1,8 +1,9 @@
 use my_mini_redis::{clients::Client, DEFAULT_PORT};
 
 use bytes::Bytes;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::convert::Infallible;
+use std::io::{self, Write};
 use std::num::ParseIntError;
 use std::str;
 use std::time::Duration;
@@ -37,6 +38,37 @@ enum Command {
         /// Name of key to get
         key: String,
     },
+    /// List the keys matching a glob-style pattern
+    Keys {
+        /// Pattern to match, e.g. `h*llo`
+        pattern: String,
+    },
+    /// Get the length of the value stored at key
+    Strlen {
+        /// Name of key to measure
+        key: String,
+    },
+    /// Get a substring of the value stored at key
+    Getrange {
+        /// Name of key to read
+        key: String,
+
+        /// Offset of the first byte, negative to count from the end
+        #[clap(allow_negative_numbers = true)]
+        start: i64,
+
+        /// Offset of the last byte, negative to count from the end
+        #[clap(allow_negative_numbers = true)]
+        end: i64,
+    },
+    /// Atomically swap the values of two keys
+    Exchange {
+        /// First key
+        key1: String,
+
+        /// Second key
+        key2: String,
+    },
     /// Set key to hold the string value
     Set {
         /// Name of key to set
@@ -60,12 +92,28 @@ enum Command {
         message: Bytes,
     },
     /// Subscribe a client to a specific channel or channels
-    Subcribe {
+    #[command(alias = "subcribe")]
+    Subscribe {
         /// Specific channel or channels
         channels: Vec<String>,
+
+        /// How received messages are printed
+        #[clap(long, value_enum, default_value_t = Format::Pretty)]
+        format: Format,
     }
 }
 
+/// Output format of the messages received by `subscribe`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    /// Human readable description of the message
+    Pretty,
+    /// One JSON object per line, with the payload encoded in base64
+    Json,
+    /// The payload as is, followed by a newline
+    Raw,
+}
+
 /// Entry point for CLI tool.
 /// 
 /// The `[tokio::main]` annotation signals that the Tokio runtime should be 
@@ -111,6 +159,31 @@ async fn main() -> my_mini_redis::Result<()> {
                 println!("(nil)");
             }
         },
+        Command::Keys { pattern } => {
+            let keys = client.keys(&pattern).await?;
+            if keys.is_empty() {
+                println!("(empty array)");
+            }
+            for (i, key) in keys.iter().enumerate() {
+                println!("{}) \"{}\"", i + 1, key);
+            }
+        },
+        Command::Strlen { key } => {
+            let len = client.strlen(&key).await?;
+            println!("(integer) {}", len);
+        },
+        Command::Getrange { key, start, end } => {
+            let value = client.getrange(&key, start, end).await?;
+            if let Ok(string) = str::from_utf8(&value) {
+                println!("\"{}\"", string);
+            } else {
+                println!("{:?}", value);
+            }
+        },
+        Command::Exchange { key1, key2 } => {
+            client.exchange(&key1, &key2).await?;
+            println!("OK");
+        },
         Command::Set { key, value, expires: None } => {
             client.set(&key, value).await?;
             println!("OK");
@@ -123,16 +196,33 @@ async fn main() -> my_mini_redis::Result<()> {
             client.publish(&channel, message).await?;
             println!("Publish OK");
         },
-        Command::Subcribe { channels } => {
+        Command::Subscribe { channels, format } => {
             if channels.is_empty() {
                 return Err("channel(s) must be provided".into());
             }
             let mut subscriber = client.subscribe(channels).await?;
 
+            let mut stdout = io::stdout().lock();
+
             while let Some(msg) = subscriber.next_message().await? {
-                println!("got message from the channel: {}; message = {:?}",
-                msg.channel, msg.content
-                );
+                match format {
+                    Format::Pretty => {
+                        writeln!(stdout, "got message from the channel: {}; message = {:?}",
+                        msg.channel, msg.content
+                        )?;
+                    },
+                    Format::Json => {
+                        writeln!(stdout, "{{\"channel\":{},\"payload\":\"{}\"}}",
+                        json_string(&msg.channel), base64(&msg.content)
+                        )?;
+                    },
+                    Format::Raw => {
+                        stdout.write_all(&msg.content)?;
+                        stdout.write_all(b"\n")?;
+                    },
+                }
+                // 输出可能被管道传给其他程序，每条消息都需要立刻写出
+                stdout.flush()?;
             }
         }
     }
@@ -146,4 +236,43 @@ fn duration_from_ms_str(src: &str) -> Result<Duration, ParseIntError> {
 
 fn bytes_from_str(src: &str) -> Result<Bytes, Infallible> {
     Ok(Bytes::from(src.to_string()))
-}
\ No newline at end of file
+}
+/// Encodes `src` as a JSON string literal, including the quotes.
+fn json_string(src: &str) -> String {
+    let mut out = String::with_capacity(src.len() + 2);
+    out.push('"');
+    for c in src.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Encodes `src` in standard base64, with padding.
+fn base64(src: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(src.len().div_ceil(3) * 4);
+    for chunk in src.chunks(3) {
+        // 每3个字节被编码为4个字符，不足3个字节时用`=`补齐
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
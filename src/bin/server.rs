@@ -6,9 +6,13 @@
 //! 
 //! The `clap` crate is used for parsing arguments.
 
+use my_mini_redis::db::{CounterOverflow, KeyspaceEvents};
+use my_mini_redis::fanout::Overflow;
+use my_mini_redis::pubsub::Strictness;
 use my_mini_redis::{server, DEFAULT_PORT};
 
 use clap::Parser;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use  tokio::signal;
 
@@ -32,7 +36,63 @@ pub async fn main() -> my_mini_redis::Result<()> {
 
     let listener = TcpListener::bind(&format!("127.0.0.1:{}",port)).await?;
 
-    server::run(listener, signal::ctrl_c()).await;
+    let mut config = server::Config {
+        connection_warm_up: cli.connection_warm_up_ms.map(Duration::from_millis),
+        command_deadline: cli.command_deadline_ms.map(Duration::from_millis),
+        notify_keyspace_events: cli.notify_keyspace_events.unwrap_or_default(),
+        counter_overflow: cli.counter_overflow.unwrap_or_default(),
+        pubsub_overflow: cli.pubsub_overflow.unwrap_or_default(),
+        max_subscribe_churn: cli.max_subscribe_churn,
+        pubsub_strictness: cli.pubsub_strictness.unwrap_or(Strictness::Strict),
+        idle_timeout: cli.idle_timeout_secs.map(Duration::from_secs),
+        write_timeout: cli.write_timeout_ms.map(Duration::from_millis),
+        hotkeys: cli.hotkeys,
+        ..server::Config::default()
+    };
+
+    if let Some(max_connections) = cli.max_connections {
+        config.max_connections = max_connections;
+    }
+
+    if let Some(initial_connections) = cli.initial_connections {
+        config.initial_connections = initial_connections;
+    }
+
+    if let Some(pubsub_queue_capacity) = cli.pubsub_queue_capacity {
+        config.pubsub_queue_capacity = pubsub_queue_capacity;
+    }
+
+    if let Some(read_buffer_capacity) = cli.read_buffer_capacity {
+        config.read_buffer_capacity = read_buffer_capacity;
+    }
+
+    if let Some(max_read_buffer_capacity) = cli.max_read_buffer_capacity {
+        config.max_read_buffer_capacity = max_read_buffer_capacity;
+    }
+
+    if let Some(max_frame_len) = cli.max_frame_len {
+        config.max_frame_len = max_frame_len;
+    }
+
+    if let Some(memory_histogram_slice_us) = cli.memory_histogram_slice_us {
+        config.memory_histogram_slice = Duration::from_micros(memory_histogram_slice_us);
+    }
+
+    if let Some(server_name) = cli.server_name {
+        config.server_name = server_name;
+    }
+
+    if let Some(server_version) = cli.server_version {
+        config.server_version = server_version;
+    }
+
+    config.bridges = cli
+        .bridge
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+
+    server::run_with_config(listener, signal::ctrl_c(), config).await;
 
     Ok(())
 }
@@ -41,7 +101,92 @@ pub async fn main() -> my_mini_redis::Result<()> {
 #[clap(name = "my-mini-redis-server", version, author, about = "A Redis server")]
 struct Cli {
     #[clap(long)]
-    port: Option<u16>
+    port: Option<u16>,
+
+    /// Maximum number of concurrent connections
+    #[clap(long)]
+    max_connections: Option<usize>,
+
+    /// Grow the connection limit up to the maximum over this many
+    /// milliseconds after startup
+    #[clap(long)]
+    connection_warm_up_ms: Option<u64>,
+
+    /// Connection limit right after startup, when warming up
+    #[clap(long)]
+    initial_connections: Option<usize>,
+
+    /// Maximum time in milliseconds a single command may take to execute
+    #[clap(long)]
+    command_deadline_ms: Option<u64>,
+
+    #[clap(long)]
+    notify_keyspace_events: Option<KeyspaceEvents>,
+
+    /// Behavior of INCR and DECR on overflow: `error` or `saturating`
+    #[clap(long)]
+    counter_overflow: Option<CounterOverflow>,
+
+    /// Number of messages a pub/sub subscriber may have pending
+    #[clap(long)]
+    pubsub_queue_capacity: Option<usize>,
+
+    /// Behavior of PUBLISH when a subscriber has too many messages pending:
+    /// `drop-oldest`, `drop-newest` or `disconnect`
+    #[clap(long)]
+    pubsub_overflow: Option<Overflow>,
+
+    /// Maximum number of channels a subscribed client may subscribe to or
+    /// unsubscribe from per second
+    #[clap(long)]
+    max_subscribe_churn: Option<u32>,
+
+    /// Whether SUBSCRIBE rejects empty channel names: `strict`, the default,
+    /// or `lenient`
+    #[clap(long)]
+    pubsub_strictness: Option<Strictness>,
+
+    /// Initial size in bytes of the read buffer of each connection
+    #[clap(long)]
+    read_buffer_capacity: Option<usize>,
+
+    /// Size in bytes the read buffer of a connection may grow to
+    #[clap(long)]
+    max_read_buffer_capacity: Option<usize>,
+
+    /// Maximum length in bytes of a bulk string sent by a client
+    #[clap(long)]
+    max_frame_len: Option<usize>,
+
+    /// Close connections waiting for a command for this many seconds
+    #[clap(long)]
+    idle_timeout_secs: Option<u64>,
+
+    /// Close clients not reading their replies for this many milliseconds
+    #[clap(long)]
+    write_timeout_ms: Option<u64>,
+
+    /// Start with hot key tracking enabled
+    #[clap(long)]
+    hotkeys: bool,
+
+    /// Maximum time in microseconds MEMORY HISTOGRAM may hold the lock of
+    /// the database for at once
+    #[clap(long)]
+    memory_histogram_slice_us: Option<u64>,
+
+    /// Server name reported by HELLO
+    #[clap(long)]
+    server_name: Option<String>,
+
+    /// Server version reported by HELLO, e.g. to pass as a given Redis
+    /// release
+    #[clap(long)]
+    server_version: Option<String>,
+
+    /// Republish the messages of channel SRC on channel DST, may be repeated
+    #[clap(long, num_args = 2, value_names = ["SRC", "DST"])]
+    bridge: Vec<String>,
 }
 
 #[cfg(not(feature = "otel"))]
@@ -3,6 +3,7 @@
 //! Provides a blocking connection and methods for issuing the supported commands.
 
 use bytes::Bytes;
+use std::future::Future;
 use std::time::Duration;
 // ToSocketAddrs trait
 // 为对象提供了将自身转换为一系列 socket 地址的能力。
@@ -10,9 +11,11 @@ use std::time::Duration;
 // 如字符串或 (host, port) 对。例如，一个实现了 ToSocketAddrs 的字符串
 // 可以直接用于指定网络连接的目标地址。
 use tokio::net::ToSocketAddrs;
-use tokio::runtime::Runtime;
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
+use tokio::task;
 
-pub use crate::clients::Message;
+use crate::clients::ClientError;
+use crate::clients::Message;
 
 /// Established connection with a Redis server.
 /// 
@@ -28,7 +31,7 @@ pub struct BlockingClient {
     /// A `current_thread` runtime for executing operations on the asynchronous
     /// client in a blocking manner.
     /// 用于在异步客户端上以阻塞方式执行操作的 "current_thread runtime".
-    rt: Runtime,
+    rt: BlockingRuntime,
 }
 
 /// A client that has entered pub/sub mode.
@@ -42,7 +45,46 @@ pub struct BlockingSubscriber {
 
     /// A `current_thread` runtime for executing operatioins on the asynchronous
     /// `Subscriber` in a blocking manner.
-    rt: Runtime,
+    rt: BlockingRuntime,
+}
+
+/// A `current_thread` runtime which can be used from within an asynchronous
+/// context.
+///
+/// Tokio panics when a runtime is blocked on or dropped from within an
+/// asynchronous context, which happens when the blocking client is mixed into
+/// asynchronous code.
+struct BlockingRuntime(Option<Runtime>);
+
+impl BlockingRuntime {
+    /// Run `future` to completion, blocking the current thread.
+    ///
+    /// On a `multi_thread` runtime, the worker thread is handed over with
+    /// `block_in_place` before blocking. On a `current_thread` runtime this is
+    /// not possible, as every other task would be blocked, and
+    /// `ClientError::CalledFromAsyncContext` is returned instead.
+    fn block_on<T>(&self, future: impl Future<Output = crate::Result<T>>) -> crate::Result<T> {
+        let rt = self.0.as_ref().unwrap();
+
+        match Handle::try_current() {
+            Err(_) => rt.block_on(future),
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                task::block_in_place(|| rt.block_on(future))
+            }
+            Ok(_) => Err(ClientError::CalledFromAsyncContext.into()),
+        }
+    }
+}
+
+impl Drop for BlockingRuntime {
+    fn drop(&mut self) {
+        // 在异步上下文中不能等待运行时关闭，只能在后台关闭
+        if let Some(rt) = self.0.take() {
+            if Handle::try_current().is_ok() {
+                rt.shutdown_background();
+            }
+        }
+    }
 }
 
 /// The iterator returned by `Subscriber::into_iter`.
@@ -52,7 +94,7 @@ struct SubscriberIterator {
 
     /// A `current_thread` runtime for executing operations on the asynchronous
     /// `Subscriber` in a blocking manner.
-    rt: Runtime,
+    rt: BlockingRuntime,
 }
 
 impl BlockingClient {
@@ -66,11 +108,15 @@ impl BlockingClient {
     /// 
     /// Demonstrates basic usage.
     /// 
-    /// ```no_run
+    /// ```
     /// use my_mini_redis::clients::BlockingClient;
     /// 
     /// fn main() {
-    ///     let mut client = match BlockingClient::connect("localhost:6379") {
+    /// #     let rt = tokio::runtime::Runtime::new().unwrap();
+    /// #     let listener = rt.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     rt.spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = match BlockingClient::connect(addr) {
     ///         Ok(client) => client,
     ///         Err(_) => panic!("failed to establish connection"),
     ///     };
@@ -94,6 +140,7 @@ impl BlockingClient {
         //block_on 是运行时上的一个方法，用于运行一个 Future 并等待它完成。
         //这个调用会阻塞当前线程，直到 Future 完成为止。
         //在这个例子中，block_on 方法被用来执行 Client::connect 函数并等待其结果。
+        let rt = BlockingRuntime(Some(rt));
         let inner = rt.block_on(crate::clients::Client::connect(addr))?;
 
         Ok(BlockingClient { inner, rt })
@@ -106,13 +153,17 @@ impl BlockingClient {
     /// 
     /// Demonstrates basic usage.
     /// 
-    /// ```no_run
+    /// ```
     /// use my_mini_redis::clients::BlockingClient;
     /// 
     /// fn main() {
-    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    /// #     let rt = tokio::runtime::Runtime::new().unwrap();
+    /// #     let listener = rt.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     rt.spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = BlockingClient::connect(addr).unwrap();
     /// 
-    ///     let val - client.get("foo").unwrap();
+    ///     let val = client.get("foo").unwrap();
     ///     println!("Got = {:?}", val);
     /// }
     /// ```
@@ -132,11 +183,15 @@ impl BlockingClient {
     /// 
     /// Demonstrates basic usage.
     /// 
-    /// ```no_run
+    /// ```
     /// use my_mini_redis::clients::BlockingClient;
     /// 
     /// fn main() {
-    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    /// #     let rt = tokio::runtime::Runtime::new().unwrap();
+    /// #     let listener = rt.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     rt.spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = BlockingClient::connect(addr).unwrap();
     /// 
     ///     client.set("foo", "bar".into()).unwrap();
     /// 
@@ -167,24 +222,29 @@ impl BlockingClient {
     /// 演示基本用法。这个示例并不能保证总是有效，因为它依赖于基于时间的逻辑，
     /// 并假设客户端和服务器在时间上保持相对同步。实际情况往往并非如此
     /// 
-    /// ```no_run
+    /// ```
     /// use my_mini_redis::clients::BlockingClient;
     /// use std::thread;
     /// use std::time::Duration;
     /// 
     /// fn main() {
+    /// #     let rt = tokio::runtime::Runtime::new().unwrap();
+    /// #     let listener = rt.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     rt.spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
     ///     let ttl = Duration::from_millis(500);
-    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    ///     let mut client = BlockingClient::connect(addr).unwrap();
     /// 
-    ///     client.set_expires("foo", "bar".int(), ttl).unwrap();
+    ///     client.set_expires("foo", "bar".into(), ttl).unwrap();
     /// 
     ///     let val = client.get("foo").unwrap().unwrap();
-    ///     assert_wq(val, "bar");
+    ///     assert_eq!(val, "bar");
     /// 
-    ///     thread::sleep(ttl);
+    ///     // 等待TTL过期
+    ///     thread::sleep(ttl * 2);
     /// 
     ///     let val = client.get("foo").unwrap();
-    ///     assert!(val.is_some());
+    ///     assert!(val.is_none());
     /// }
     pub fn set_expires(
         &mut self,
@@ -205,11 +265,15 @@ impl BlockingClient {
     /// 
     /// Demonstrates basic usage.
     /// 
-    /// ```no_run
+    /// ```
     /// use my_mini_redis::clients::BlockingClient;
     /// 
     /// fn main() {
-    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    /// #     let rt = tokio::runtime::Runtime::new().unwrap();
+    /// #     let listener = rt.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     rt.spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = BlockingClient::connect(addr).unwrap();
     /// 
     ///     let val = client.publish("foo", "bar".into()).unwrap();
     ///     println!("Got = {:?}", val);   
@@ -261,7 +325,7 @@ impl BlockingSubscriber {
 
     /// Subscribe to a list of new channels
     pub fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
-        self.rt.block_on(self.inner.subscibe(channels))
+        self.rt.block_on(self.inner.subscribe(channels))
     }
 
     /// Unsubscribe to a list of new channels
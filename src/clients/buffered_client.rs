@@ -1,36 +1,84 @@
-use crate::clients::Client;
-use crate::Result;
+use crate::clients::supervisor::TaskSupervisor;
+use crate::clients::{Client, FromFrame};
+use crate::cmd::{Del, Exists, Get, Keys, Ping, Publish, Scan, Set};
+use crate::{Frame, Result};
 
 use bytes::Bytes;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::oneshot;
-// 枚举，用于将请求的命令从 "缓冲客户端 "句柄中传递出去
+use tokio::time::{self, Instant};
+
+// 通过通道发送给链接任务的信息类型
 #[derive(Debug)]
-enum Command {
-    Get(String),
-    Set(String, Bytes),
+struct Message {
+    /// The command to forward to the connection, already encoded by the
+    /// `BufferedClient` handle. The reply is decoded by the handle as well, so
+    /// the connection task does not need to know about each command.
+    cmd: Frame,
+
+    /// The command is dropped instead of being sent if it is still queued when
+    /// this instant is reached.
+    deadline: Option<Instant>,
+
+    /// `oneshot::Sender` is a channel type that sends a **single** value. It is
+    /// used here to send the response received from the connection back to
+    /// the original requester.
+    tx: oneshot::Sender<Result<Frame>>,
 }
 
-// 通过通道发送给链接任务的信息类型
-// 
-// `Command` is the command to forward to the connection.
-//
-// `oneshot::Sender` is a channel type that sends a **single** value. It is used
-// here to send the response received from the connection back to the original
-// requester.
-type Message = (Command, oneshot::Sender<Result<Option<Bytes>>>);
+/// Counters of the requests that were dropped by the connection task before
+/// being sent.
+#[derive(Debug, Default)]
+struct Counters {
+    cancelled: AtomicU64,
+    expired: AtomicU64,
+
+    /// Set by `BufferedClient::panic_on_next_request`.
+    #[cfg(feature = "test-util")]
+    panic_on_next_request: std::sync::atomic::AtomicBool,
+}
+
+/// Snapshot of the requests dropped by a `BufferedClient` before being sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferedStats {
+    /// Requests whose caller stopped waiting for the response, for example
+    /// because the call was wrapped in `tokio::time::timeout`.
+    pub cancelled: u64,
+
+    /// Requests whose deadline elapsed while they were queued.
+    pub expired: u64,
+}
 
 /// Receive commands sent through the channel and forward them to client. The
 /// response is returned back to the caller via a `oneshot`.
-async fn run(mut client: Client, mut rx: Receiver<Message>) {
+///
+/// Requests that are cancelled or expired while queued are skipped, as the
+/// response would be discarded anyway and the command may not be idempotent.
+async fn run(mut client: Client, mut rx: Receiver<Message>, counters: Arc<Counters>) {
     // 不断从channel中弹出消息。 返回值`None`表示所有 `BufferedClient` 句柄都已经被
     // 释放，并且channel中绝不会发送其他消息。
-    while let Some((cmd, tx)) = rx.recv().await {
-        let response = match cmd {
-            Command::Get(key) => client.get(&key).await,
-            // client.set返回的是Result<()>，但是由于get返回的是Result<Option<Bytes>>，所以要将()改为None
-            Command::Set(key, value) => client.set(&key, value).await.map(|_| None)
-        };
+    while let Some(Message { cmd, deadline, tx }) = rx.recv().await {
+        #[cfg(feature = "test-util")]
+        if counters.panic_on_next_request.load(Ordering::Relaxed) {
+            panic!("panic injected by `BufferedClient::panic_on_next_request`");
+        }
+
+        if deadline.map(|deadline| deadline <= Instant::now()).unwrap_or(false) {
+            counters.expired.fetch_add(1, Ordering::Relaxed);
+            let _ = tx.send(Err("request deadline elapsed".into()));
+            continue;
+        }
+
+        // 调用者已经不再等待回复，命令不需要发送
+        if tx.is_closed() {
+            counters.cancelled.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+
+        let response = client.request(&cmd).await;
 
         // 将回复发送给调用者
         //
@@ -42,6 +90,12 @@ async fn run(mut client: Client, mut rx: Receiver<Message>) {
 #[derive(Clone)]
 pub struct BufferedClient {
     tx: Sender<Message>,
+
+    /// Shared with the connection task, which updates the counters.
+    counters: Arc<Counters>,
+
+    /// The connection task, aborted once every handle is dropped.
+    task: Arc<TaskSupervisor>,
 }
 
 impl BufferedClient {
@@ -64,51 +118,231 @@ impl BufferedClient {
     /// 当buffer client收到Redis connection的回复后将其转发给原始请求者
     /// 
     /// The returned `BufferedClient` handle may be cloned before passing the 
-    /// new handle to separate tasks.
+    /// new handle to separate tasks. If the connection task panics, the
+    /// requests fail with `ClientError::BackgroundTaskPanicked`.
     pub fn buffer(client: Client) -> BufferedClient {
         // 将信息数设定为固定值32. 在真实的应用中buffer的大小应该是可配置的，
         // 但是这里我们不需要这么做
         let (tx, rx) = channel(32);
 
+        let counters = Arc::new(Counters::default());
+
         // 创建一个线程来处理对连接的请求
-        tokio::spawn( async move { run(client, rx).await });
+        let task_counters = counters.clone();
+        let task = TaskSupervisor::spawn(async move { run(client, rx, task_counters).await });
 
         // 返回句柄
-        BufferedClient{ tx }
+        BufferedClient{ tx, counters, task: Arc::new(task) }
+    }
+
+    /// Returns how many requests were dropped before being sent to the server.
+    pub fn stats(&self) -> BufferedStats {
+        BufferedStats {
+            cancelled: self.counters.cancelled.load(Ordering::Relaxed),
+            expired: self.counters.expired.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Makes the connection task panic when it receives the next request, to
+    /// test how the failure is reported.
+    #[cfg(feature = "test-util")]
+    pub fn panic_on_next_request(&self) {
+        self.counters.panic_on_next_request.store(true, Ordering::Relaxed);
     }
 
     /// Get the value of a key.
     /// 
     /// Same as `Client::get` but requests are **buffered** until the associated
     /// connection has the ability to send the request.
+    ///
+    /// If the returned future is dropped while the request is still queued,
+    /// the request is not sent.
     pub async fn get(&mut self, key: &str) -> Result<Option<Bytes>> {
-        
-        let get = Command::Get(key.into());
-
-        let (tx, rx) = oneshot::channel();
+        self.get_cmd(key, None).await
+    }
 
-        self.tx.send((get, tx)).await?;
+    /// Get the value of a key, giving up at `deadline`.
+    ///
+    /// Same as `get`, but if `deadline` is reached before the response is
+    /// received an error is returned. A request still queued at `deadline` is
+    /// never sent.
+    pub async fn get_with_deadline(
+        &mut self,
+        key: &str,
+        deadline: Instant,
+    ) -> Result<Option<Bytes>> {
+        self.get_cmd(key, Some(deadline)).await
+    }
 
-        match rx.await {
-            Ok(res) => res,
-            Err(err) => Err(err.into()),
-        }
+    async fn get_cmd(&mut self, key: &str, deadline: Option<Instant>) -> Result<Option<Bytes>> {
+        let response = self.request(Get::new(key).into_frame(), deadline).await?;
+        FromFrame::from_frame(response)
     }
 
     /// Set `key` to hold the given `value`.
     /// 
     /// Same as `Client::set` but requests are **buffered** until the associated
     /// connection has the ability to send the request
+    ///
+    /// If the returned future is dropped while the request is still queued,
+    /// the request is not sent.
     pub async fn set(&mut self, key: &str, value: Bytes) -> Result<()> {
-        let set = Command::Set(key.into(), value);
+        self.set_cmd(key, value, None).await
+    }
+
+    /// Set `key` to hold the given `value`, giving up at `deadline`.
+    ///
+    /// Same as `set`, but if `deadline` is reached before the response is
+    /// received an error is returned. A request still queued at `deadline` is
+    /// never sent.
+    pub async fn set_with_deadline(
+        &mut self,
+        key: &str,
+        value: Bytes,
+        deadline: Instant,
+    ) -> Result<()> {
+        self.set_cmd(key, value, Some(deadline)).await
+    }
+
+    async fn set_cmd(&mut self, key: &str, value: Bytes, deadline: Option<Instant>) -> Result<()> {
+        let frame = Set::new(key, value, None).into_frame();
+
+        match self.request(frame, deadline).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `expiration`.
+    ///
+    /// Same as `Client::set_expires` but requests are **buffered** until the
+    /// associated connection has the ability to send the request.
+    pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> Result<()> {
+        let frame = Set::new(key, value, Some(expiration)).into_frame();
+
+        match self.request(frame, None).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Removes the given keys.
+    ///
+    /// Same as `Client::del` but requests are **buffered** until the
+    /// associated connection has the ability to send the request.
+    pub async fn del(&mut self, keys: &[&str]) -> Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        let response = self.request(Del::new(keys).into_frame(), None).await?;
+        FromFrame::from_frame(response)
+    }
+
+    /// Returns how many of the given keys exist.
+    ///
+    /// Same as `Client::exists` but requests are **buffered** until the
+    /// associated connection has the ability to send the request.
+    pub async fn exists(&mut self, keys: &[&str]) -> Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        let response = self.request(Exists::new(keys).into_frame(), None).await?;
+        FromFrame::from_frame(response)
+    }
+
+    /// Returns all keys matching the glob-style `pattern`.
+    ///
+    /// Same as `Client::keys` but requests are **buffered** until the
+    /// associated connection has the ability to send the request.
+    pub async fn keys(&mut self, pattern: &str) -> Result<Vec<String>> {
+        let response = self.request(Keys::new(pattern).into_frame(), None).await?;
+        FromFrame::from_frame(response)
+    }
 
+    /// Returns the next batch of keys of an incremental iteration.
+    ///
+    /// Same as `Client::scan` but requests are **buffered** until the
+    /// associated connection has the ability to send the request.
+    pub async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<usize>,
+    ) -> Result<(u64, Vec<String>)> {
+        let mut cmd = Scan::new(cursor);
+        if let Some(pattern) = pattern {
+            cmd = cmd.with_pattern(pattern);
+        }
+        if let Some(count) = count {
+            cmd = cmd.with_count(count);
+        }
+
+        let response = self.request(cmd.into_frame(), None).await?;
+        FromFrame::from_frame(response)
+    }
+
+    /// Posts `message` to the given `channel`.
+    ///
+    /// Same as `Client::publish` but requests are **buffered** until the
+    /// associated connection has the ability to send the request.
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> Result<u64> {
+        let response = self.request(Publish::new(channel, message).into_frame(), None).await?;
+        FromFrame::from_frame(response)
+    }
+
+    /// Ping the server.
+    ///
+    /// Same as `Client::ping` but requests are **buffered** until the
+    /// associated connection has the ability to send the request.
+    pub async fn ping(&mut self, msg: Option<Bytes>) -> Result<Bytes> {
+        match self.request(Ping::new(msg).into_frame(), None).await? {
+            Frame::Simple(value) => Ok(value.into()),
+            Frame::Bulk(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Queue `cmd` and wait for its response.
+    async fn request(&mut self, cmd: Frame, deadline: Option<Instant>) -> Result<Frame> {
         let (tx, rx) = oneshot::channel();
 
-        self.tx.send((set, tx)).await?;
+        let message = Message { cmd, deadline, tx };
+
+        match deadline {
+            Some(deadline) => {
+                // 入队和等待回复都受`deadline`限制。超时后`rx`被drop，
+                // 连接任务就不会再发送这个请求
+                let response = time::timeout_at(deadline, async {
+                    if let Err(err) = self.tx.send(message).await {
+                        return Err(self.task_error(err.into()).await);
+                    }
+                    match rx.await {
+                        Ok(res) => res,
+                        Err(err) => Err(self.task_error(err.into()).await),
+                    }
+                })
+                .await;
 
-        match rx.await {
-            Ok(res) => res.map(|_| ()),
-            Err(err) => Err(err.into())
+                match response {
+                    Ok(res) => res,
+                    Err(_) => Err("request deadline elapsed".into()),
+                }
+            }
+            None => {
+                if let Err(err) = self.tx.send(message).await {
+                    return Err(self.task_error(err.into()).await);
+                }
+
+                match rx.await {
+                    Ok(res) => res,
+                    Err(err) => Err(self.task_error(err.into()).await),
+                }
+            }
         }
     }
-}
\ No newline at end of file
+
+    /// Returns the error to report for a request the connection task did not
+    /// answer: the panic of the task if it panicked, `err` otherwise.
+    async fn task_error(&self, err: crate::Error) -> crate::Error {
+        match self.task.failure().await {
+            Some(failure) => failure.into(),
+            None => err,
+        }
+    }
+}
@@ -3,14 +3,27 @@
 //! Provides an async connect and methods for issuing the supported commands.
 
 
-use crate::cmd::{Get, Ping, Publish, Set, Subscribe, Unsubscribe};
+use crate::cmd::{
+    Append, Cas, ChannelStats, CommandInfo, Compress, Config, DbSize, Del, Dump, Exchange, Exists, ExpireCondition, ExpireMany, FlushDb, Get, GetEx, GetRange, HDel, HGet, HGetAll, HSet, Health, HealthReport, Hello, HotKey, HotKeys, IncrBy, IncrByFloat, Info, Keys, Lcs, LcsIdx, LPop, LPush, LRange, Memory, MGet, MSet, Object, PSubscribe, PTtl, Ping, PubSub, Publish, Restore, RPop, RPush, Scan, Set, SetCondition, SetEx, SetRange, StreamEntry, StreamId, Strlen, Subscribe, Touch, Ttl, Type, TypeHistogram, Unlink, Unsubscribe, Wait, XAdd, XRange, XSubscribe,
+};
+use crate::cmd::fields_from_frame;
+use crate::clients::{FromFrame, Message, Pipeline, SetOptions, SetReply};
+use crate::pubsub::{PubSubReply, Strictness};
+use crate::connection::{BulkHeader, Transport};
 use crate::{Connection, Frame};
 
 use async_stream::try_stream;
 use bytes::Bytes;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
 use std::io::{Error, ErrorKind};
-use std::time::Duration;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, ReadBuf};
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time;
 use tokio_stream::Stream;
 use tracing::{debug, instrument};
 
@@ -18,18 +31,66 @@ use tracing::{debug, instrument};
 /// 
 /// Backed by a single `TcpStream`, `Client` provides basic network client
 /// functionality (no pooling, retrying, ...). Connections are established using
-/// the [`connect`](fn@connect) function.
+/// the [`connect`](fn@connect) function. Use a `Pool` to share connections
+/// between tasks.
 /// 
 /// Requests are issued using the various methods of `Client`.
 pub struct Client {
-    /// The TCP connection decorated with the redis protocol encoder / decoder
-    /// implemented using a buffered `TcpStream`.
+    /// The connection decorated with the redis protocol encoder / decoder
+    /// implemented using a buffered stream, usually a `TcpStream`.
     /// 
-    /// When `Listener` receives an inbound connection, the `TcpStream` is
-    /// passed to `Connection::new`, which initializes the associated buffers
-    /// `Connection` allows the handler to operate at the "frame" level and keep
-    /// the byte level protocol parsing details encapsulated in `Connection`.
+    /// The stream is passed to `Connection::new`, which initializes the
+    /// associated buffers. `Connection` allows the client to operate at the
+    /// "frame" level and keep the byte level protocol parsing details
+    /// encapsulated in `Connection`.
     connection: Connection,
+
+    /// How strictly pub/sub requests and replies are checked. See
+    /// `Strictness` for details.
+    strictness: Strictness,
+
+    /// Set once a protocol error or a reply of an unexpected shape has been
+    /// encountered, after which the connection is no longer used.
+    poisoned: bool,
+
+    /// Maximum number of keys sent in a single `MGET` or `MSET` frame.
+    chunk_size: usize,
+
+    /// Time after which writing a request, or waiting for a reply, fails with
+    /// `ClientError::TimedOut`. `None` waits indefinitely.
+    timeout: Option<Duration>,
+}
+
+/// Number of keys sent in a single `MGET` or `MSET` frame by default.
+const DEFAULT_CHUNK_SIZE: usize = 1000;
+
+/// Errors reported by `Client` itself rather than by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// A previous operation failed with a protocol error or received a reply
+    /// of an unexpected shape. The replies can no longer be matched with the
+    /// requests, so the connection must be replaced.
+    ConnectionPoisoned,
+
+    /// A `BlockingClient` was used from within an asynchronous context on a
+    /// `current_thread` runtime, where blocking would stall the runtime. Use
+    /// `Client` instead.
+    CalledFromAsyncContext,
+
+    /// Writing a request, or waiting for its reply, took longer than the
+    /// timeout set with `Client::set_timeout`. The connection is poisoned, as
+    /// the reply may still arrive.
+    TimedOut,
+
+    /// The keys of a command sent with a `ShardedClient` are stored on
+    /// different shards, which a single request cannot reach. Use hash tags
+    /// to keep the keys used together on the same shard.
+    CrossShard,
+
+    /// A background task of the client, such as the connection task of a
+    /// `BufferedClient`, panicked with the given message. The client can no
+    /// longer serve requests and must be replaced.
+    BackgroundTaskPanicked(String),
 }
 
 /// A client that has entered pub/sub mode
@@ -41,12 +102,55 @@ pub struct Subscriber {
     client: Client,
 
     subscribed_channels: Vec<String>,
+
+    subscribed_patterns: Vec<String>,
+
+    /// Messages received while waiting for the confirmation of a `SUBSCRIBE`,
+    /// `PSUBSCRIBE` or `UNSUBSCRIBE`, returned by `next_message` before
+    /// reading new ones.
+    pending: VecDeque<Message>,
+}
+
+/// A client that has subscribed to the entries of a stream with
+/// `Client::xsubscribe`.
+///
+/// Like a `Subscriber`, it can only receive. It keeps the id of the last entry
+/// received, to be persisted by the caller once the entry is processed, and
+/// given to `Client::xsubscribe` to resume after a disconnection.
+pub struct StreamSubscriber {
+    client: Client,
+
+    key: String,
+
+    /// Id of the last entry received, or the one the subscription started
+    /// after.
+    last_id: StreamId,
 }
 
-#[derive(Debug, Clone)]
-pub struct Message {
+/// Number of messages published on a channel since the timestamp given to
+/// `Client::subscribe_since`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlog {
     pub channel: String,
-    pub content: Bytes,
+
+    /// Number of messages published since the timestamp.
+    pub published: u64,
+
+    /// Set when the server did not remember all the messages published, in
+    /// which case `published` is a lower bound.
+    pub truncated: bool,
+}
+
+/// The value of a key read incrementally from the connection, returned by
+/// `Client::get_streaming`.
+///
+/// The client is borrowed until the reader is dropped. Dropping the reader
+/// before the end of the value is fine: the rest of the value is discarded
+/// before the reply to the next command is read.
+pub struct ValueReader<'a> {
+    client: &'a mut Client,
+
+    len: usize,
 }
 
 impl Client {
@@ -58,12 +162,15 @@ impl Client {
     /// 
     /// # Examples
     /// 
-    /// ```no_run
-    /// use mini_redis::clients::Client;
+    /// ```
+    /// use my_mini_redis::clients::Client;
     /// 
     /// #[tokio::main]
     /// async fn main() {
-    ///     let client = match Client::connect("localhost:6379").await {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let client = match Client::connect(addr).await {
     ///         Ok(client) => client,
     ///         Err(_) => panic!("failed to establish connection"),
     ///     };
@@ -76,10 +183,99 @@ impl Client {
         //并向 `mini_redis` connect 的调用者通报。
         let socket = TcpStream::connect(addr).await?;
 
+        Ok(Client::new(socket))
+    }
+
+    /// Establish a TLS connection with the Redis server located at `addr`.
+    ///
+    /// The TLS handshake is performed before the connection is used, with the
+    /// server authenticated as `domain` according to `config`. The commands
+    /// are then the same as over plain TCP.
+    ///
+    /// Requires the `tls` feature. `rustls` is re-exported at the root of the
+    /// crate to build `config`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::rustls::pki_types::pem::PemObject;
+    /// use my_mini_redis::rustls::pki_types::CertificateDer;
+    /// use my_mini_redis::rustls::{ClientConfig, RootCertStore};
+    /// use std::sync::Arc;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut roots = RootCertStore::empty();
+    ///     for cert in CertificateDer::pem_file_iter("ca.pem").unwrap() {
+    ///         roots.add(cert.unwrap()).unwrap();
+    ///     }
+    ///
+    ///     let config = ClientConfig::builder()
+    ///         .with_root_certificates(roots)
+    ///         .with_no_client_auth();
+    ///
+    ///     let mut client = Client::connect_tls("localhost:6379", "localhost", Arc::new(config))
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     client.ping(None).await.unwrap();
+    /// }
+    /// ```
+    #[cfg(feature = "tls")]
+    pub async fn connect_tls<T: ToSocketAddrs>(
+        addr: T,
+        domain: &str,
+        config: std::sync::Arc<tokio_rustls::rustls::ClientConfig>,
+    ) -> crate::Result<Client> {
+        use tokio_rustls::rustls::pki_types::ServerName;
+        use tokio_rustls::TlsConnector;
+
+        let domain = ServerName::try_from(domain.to_string())?;
+
+        let socket = TcpStream::connect(addr).await?;
+
+        // 握手完成之后才创建连接，握手失败时返回错误
+        let stream = TlsConnector::from(config).connect(domain, socket).await?;
+
+        Ok(Client::new(stream))
+    }
+
+    /// Create a client running on an established `stream`.
+    ///
+    /// This allows using another transport than plain TCP. For example, to
+    /// connect over TLS, perform the handshake, e.g. with
+    /// `tokio_rustls::TlsConnector::connect`, and pass the resulting stream,
+    /// which is what `connect_tls` does with the `tls` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use tokio::net::TcpStream;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let stream = TcpStream::connect(addr).await.unwrap();
+    ///     let mut client = Client::new(stream);
+    ///
+    ///     client.ping(None).await.unwrap();
+    /// }
+    /// ```
+    pub fn new<S: Transport + 'static>(stream: S) -> Client {
         // 初始化连接状态。为read/write buffers开辟空间，来执行redis协议中frame的解析
-        let connection = Connection::new(socket);
+        let connection = Connection::new(Box::new(stream) as Box<dyn Transport>);
 
-        Ok(Client { connection })
+        Client {
+            connection,
+            strictness: Strictness::default(),
+            poisoned: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            timeout: None,
+        }
     }
 
     /// Ping to the server.
@@ -93,12 +289,15 @@ impl Client {
     /// # Example
     /// 
     /// Demonstrates basic usage
-    /// ```no_run
-    /// use mini_redis::clients::Client;
+    /// ```
+    /// use my_mini_redis::clients::Client;
     /// 
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
     ///     
     ///     let pong = client.ping(None).await.unwrap();
     ///     assert_eq!(b"PONG", &pong[..]);
@@ -108,228 +307,3149 @@ impl Client {
     pub async fn ping(&mut self, msg: Option<Bytes>) -> crate::Result<Bytes> {
         let frame = Ping::new(msg).into_frame();
         debug!(request = ?frame);
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(value.into()),
+            Frame::Bulk(value) => Ok(value),
+            frame => Err(self.unexpected(frame))
+        }
+    }
+
+    /// Negotiates the protocol version of the connection.
+    ///
+    /// `protover` is 2 or 3; without it, the version is left unchanged. The
+    /// server replies with a flat array of field names and values describing
+    /// itself, including the `proto` in use. Frames are encoded the same way in
+    /// both versions, so the client keeps working after switching.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::Frame;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let info = client.hello(Some(3)).await.unwrap();
+    ///     assert!(matches!(info[5], Frame::Integer(3)));
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn hello(&mut self, protover: Option<u64>) -> crate::Result<Vec<Frame>> {
+        let frame = Hello::new(protover).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Return information about the server, as `field:value` lines grouped in
+    /// sections.
+    ///
+    /// Only the `server` section is supported, with the `redis_version` and
+    /// `server_name` fields. Without a section, the default ones are returned.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let info = client.info(Some("server")).await.unwrap();
+    ///     assert!(info.contains("redis_version:"));
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn info(&mut self, section: Option<&str>) -> crate::Result<String> {
+        let frame = Info::new(section).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Offer the server to compress the bulk strings of at least `threshold`
+    /// bytes with LZ4, in both directions.
+    ///
+    /// Returns `false`, and leaves the connection uncompressed, if the server
+    /// declines, for example because it does not know the `COMPRESS` command.
+    /// This pays off on slow links, for large and repetitive values.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::connection::DEFAULT_COMPRESSION_THRESHOLD;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     assert!(client.compress(DEFAULT_COMPRESSION_THRESHOLD).await.unwrap());
+    ///     client.set("foo", "bar".repeat(1000).into()).await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn compress(&mut self, threshold: usize) -> crate::Result<bool> {
+        let frame = Compress::lz4(threshold).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        // 服务器回复之后才启用压缩，之前的frame都没有被压缩
+        match self.read_reply().await? {
+            Frame::Simple(response) if response == "OK" => {
+                self.connection.set_compression(Some(threshold));
+                Ok(true)
+            }
+            Frame::Error(_) => Ok(false),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Returns the health of the server.
+    ///
+    /// Unlike `ping`, the reply tells how long the server has been running and
+    /// how many clients are connected.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let health = client.health().await.unwrap();
+    ///     println!("up for {:?}", health.uptime);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn health(&mut self) -> crate::Result<HealthReport> {
+        let frame = Health::new().into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Get the value of key
+    /// 
+    /// If the key does not exist the special value `None` is returned.
+    /// 
+    /// # Examples
+    /// 
+    /// Demonstrates basic usage.
+    /// 
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///     
+    ///     let val = client.get("foo").await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        let frame = Get::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Get the value of key and set it to expire after `expire`.
+    ///
+    /// If the key does not exist the special value `None` is returned, and no
+    /// key is created.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let val = client.get_expire("foo", Duration::from_secs(10)).await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn get_expire(&mut self, key: &str, expire: Duration) -> crate::Result<Option<Bytes>> {
+        let frame = GetEx::new(key, expire).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Get the value of key and remove its time to live.
+    ///
+    /// If the key does not exist the special value `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let val = client.get_persist("foo").await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn get_persist(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        let frame = GetEx::persist(key).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Get the value of key as a stream of bytes, without holding the whole
+    /// value in memory.
+    ///
+    /// The payload is read from the connection as the returned `ValueReader`
+    /// is read, e.g. to copy a large value to a file. If the key does not
+    /// exist the special value `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///
+    ///     let mut value = vec![];
+    ///     if let Some(mut reader) = client.get_streaming("foo").await.unwrap() {
+    ///         reader.read_to_end(&mut value).await.unwrap();
+    ///     }
+    ///     assert_eq!(b"bar", &value[..]);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn get_streaming(&mut self, key: &str) -> crate::Result<Option<ValueReader<'_>>> {
+        let frame = Get::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+        self.check_poisoned()?;
+
+        let header = with_timeout(self.timeout, self.connection.read_bulk_header())
+            .await
+            .map_err(|err| self.poison(err))?;
+
+        debug!(?header);
+
+        match header {
+            Some(BulkHeader::Bulk(len)) => Ok(Some(ValueReader { client: self, len })),
+            Some(BulkHeader::Frame(Frame::Null)) => Ok(None),
+            Some(BulkHeader::Frame(Frame::Error(msg))) => Err(msg.into()),
+            Some(BulkHeader::Frame(frame)) => Err(self.unexpected(frame)),
+            None => Err(Error::new(ErrorKind::ConnectionReset, "connection reset by server").into()),
+        }
+    }
+
+    /// Get the remaining time to live of key.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::cmd::Ttl;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     assert_eq!(Ttl::Missing, client.pttl("foo").await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn pttl(&mut self, key: &str) -> crate::Result<Ttl> {
+        let frame = PTtl::new(key).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Get the values of all the given keys.
+    ///
+    /// For every key that does not exist, `None` is returned in its place.
+    ///
+    /// Batches larger than the chunk size, see `set_chunk_size`, are split
+    /// into several `MGET` commands which are pipelined, and the values are
+    /// returned in the order of `keys`. Each command reads its keys
+    /// atomically, but the batch as a whole is not.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let values = client.mget(&["foo", "bar"]).await.unwrap();
+    ///     println!("Got = {:?}", values);
+    /// }
+    /// ```
+    #[instrument(skip(self, keys))]
+    pub async fn mget(&mut self, keys: &[&str]) -> crate::Result<Vec<Option<Bytes>>> {
+        let frames = keys
+            .chunks(self.chunk_size)
+            .map(|chunk| {
+                let keys = chunk.iter().map(|key| key.to_string()).collect();
+                MGet::new(keys).into_frame()
+            })
+            .collect();
+
+        let mut values = Vec::with_capacity(keys.len());
+
+        for response in self.pipelined(frames).await? {
+            let chunk: Vec<Option<Bytes>> = self.decode(response)?;
+            values.extend(chunk);
+        }
+
+        Ok(values)
+    }
+
+    /// Set each key to its value.
+    ///
+    /// As with `mget`, batches larger than the chunk size are split into
+    /// several pipelined `MSET` commands. Each command is applied atomically,
+    /// but other clients may observe a batch between two of its commands.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.mset(&[("foo", "1".into()), ("bar", "2".into())]).await.unwrap();
+    ///
+    ///     let values = client.mget(&["foo", "bar"]).await.unwrap();
+    ///     assert_eq!(vec![Some("1".into()), Some("2".into())], values);
+    /// }
+    /// ```
+    #[instrument(skip(self, pairs))]
+    pub async fn mset(&mut self, pairs: &[(&str, Bytes)]) -> crate::Result<()> {
+        let frames = pairs
+            .chunks(self.chunk_size)
+            .map(|chunk| {
+                let pairs = chunk
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.clone()))
+                    .collect();
+                MSet::new(pairs).into_frame()
+            })
+            .collect();
+
+        for response in self.pipelined(frames).await? {
+            match response {
+                Frame::Simple(response) if response == "OK" => {}
+                frame => return Err(self.unexpected(frame)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the maximum number of keys sent in a single `MGET` or `MSET`
+    /// frame by `mget` and `mset`.
+    ///
+    /// Defaults to 1000. Larger batches are split into several commands.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        assert!(chunk_size > 0, "chunk size must be greater than zero");
+        self.chunk_size = chunk_size;
+    }
+
+    /// Sets the time after which writing a request, or waiting for a reply,
+    /// fails with `ClientError::TimedOut`.
+    ///
+    /// This bounds how long a hung server can block a command, the
+    /// confirmation of a `subscribe` included. Waiting for the messages of a
+    /// `Subscriber` is not bounded. A command that timed out poisons the
+    /// connection, see `is_poisoned`. `None`, the default, waits indefinitely.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns all keys matching the glob-style `pattern`.
+    ///
+    /// The whole key space is inspected while the server holds its lock, so
+    /// this is meant for debugging only.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let keys = client.keys("h*llo").await.unwrap();
+    ///     println!("Got = {:?}", keys);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn keys(&mut self, pattern: &str) -> crate::Result<Vec<String>> {
+        let frame = Keys::new(pattern).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        let response = self.read_response().await?;
+        self.decode(response)
+    }
+
+    /// Returns the next batch of keys of an incremental iteration.
+    ///
+    /// Start the iteration with cursor 0 and pass the returned cursor to the
+    /// next call, until it is 0 again. When `pattern` is given, only the keys
+    /// matching it are returned, so a batch may be empty before the iteration
+    /// is complete. See `cmd::Scan` for the guarantees of the iteration.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let mut cursor = 0;
+    ///     loop {
+    ///         let (next, keys) = client.scan(cursor, Some("h*"), None).await.unwrap();
+    ///         println!("Got = {:?}", keys);
+    ///
+    ///         if next == 0 {
+    ///             break;
+    ///         }
+    ///         cursor = next;
+    ///     }
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<usize>,
+    ) -> crate::Result<(u64, Vec<String>)> {
+        let mut cmd = Scan::new(cursor);
+        if let Some(pattern) = pattern {
+            cmd = cmd.with_pattern(pattern);
+        }
+        if let Some(count) = count {
+            cmd = cmd.with_count(count);
+        }
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        let response = self.read_response().await?;
+        self.decode(response)
+    }
+
+    /// Iterate over all the keys matching `pattern`, driving a `SCAN` cursor
+    /// to completion.
+    ///
+    /// The keys are requested in batches of `count` keys as the stream is
+    /// consumed. Like `scan`, every key present during the whole iteration is
+    /// yielded at least once.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///     client.set("hello", "world".into()).await.unwrap();
+    ///
+    ///     let keys = client.scan_iter(Some("h*"), None);
+    ///     tokio::pin!(keys);
+    ///
+    ///     while let Some(key) = keys.next().await {
+    ///         println!("Got = {}", key.unwrap());
+    ///     }
+    /// }
+    /// ```
+    pub fn scan_iter<'a>(
+        &'a mut self,
+        pattern: Option<&'a str>,
+        count: Option<usize>,
+    ) -> impl Stream<Item = crate::Result<String>> + 'a {
+        try_stream! {
+            let mut cursor = 0;
+
+            loop {
+                let (next, keys) = self.scan(cursor, pattern, count).await?;
+
+                for key in keys {
+                    yield key;
+                }
+
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+    }
+
+    /// Returns the number of keys in the database.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let size = client.dbsize().await.unwrap();
+    ///     println!("Got = {}", size);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn dbsize(&mut self) -> crate::Result<u64> {
+        let frame = DbSize::new().into_frame();
+        self.integer_cmd(frame).await
+    }
+
+    /// Wait for the previous writes to be acknowledged by `numreplicas`
+    /// replicas, for at most `timeout`.
+    ///
+    /// Returns the number of replicas which acknowledged the writes. The server
+    /// has no replicas, so this is always 0 and returns immediately.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     let replicas = client.wait(1, Duration::from_secs(1)).await.unwrap();
+    ///     assert_eq!(0, replicas);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn wait(&mut self, numreplicas: u64, timeout: Duration) -> crate::Result<u64> {
+        let frame = Wait::new(numreplicas, timeout).into_frame();
+        self.integer_cmd(frame).await
+    }
+
+    /// Returns the number of commands supported by the server.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let count = client.command_count().await.unwrap();
+    ///     println!("Got = {}", count);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn command_count(&mut self) -> crate::Result<u64> {
+        let frame = CommandInfo::count().into_frame();
+        self.integer_cmd(frame).await
+    }
+
+    /// Removes all the keys of the database.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.flushdb().await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn flushdb(&mut self) -> crate::Result<()> {
+        let frame = FlushDb::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Atomically swap the values of two keys, along with their time to live.
+    ///
+    /// Both keys must exist, otherwise an error is returned and neither key is
+    /// modified. This is not a standard Redis command.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("config:blue", "v1".into()).await.unwrap();
+    ///     client.set("config:green", "v2".into()).await.unwrap();
+    ///
+    ///     client.exchange("config:blue", "config:green").await.unwrap();
+    ///
+    ///     let val = client.get("config:blue").await.unwrap().unwrap();
+    ///     assert_eq!(val, "v2");
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn exchange(&mut self, key1: &str, key2: &str) -> crate::Result<()> {
+        let frame = Exchange::new(key1, key2).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Get the type of the value stored at key.
+    ///
+    /// Returns `"none"` if the key does not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let key_type = client.key_type("foo").await.unwrap();
+    ///     println!("Got = {}", key_type);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn key_type(&mut self, key: &str) -> crate::Result<String> {
+        let frame = Type::new(key).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Get the length of the value stored at key.
+    ///
+    /// If the key does not exist, 0 is returned. The value itself is not
+    /// transferred.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let len = client.strlen("foo").await.unwrap();
+    ///     println!("Got = {}", len);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn strlen(&mut self, key: &str) -> crate::Result<u64> {
+        let frame = Strlen::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Get the substring of the value stored at key, between the offsets
+    /// `start` and `end`, both inclusive.
+    ///
+    /// Negative offsets count from the end of the value, `-1` being the last
+    /// byte. Offsets out of range are clamped to the value. An empty value is
+    /// returned when the range is empty or the key does not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "Hello World".into()).await.unwrap();
+    ///
+    ///     let val = client.getrange("foo", -5, -1).await.unwrap();
+    ///     assert_eq!(&b"World"[..], &val[..]);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn getrange(&mut self, key: &str, start: i64, end: i64) -> crate::Result<Bytes> {
+        let frame = GetRange::new(key, start, end).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(value) => Ok(value),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Returns the value stored at `key` serialized into an opaque payload,
+    /// or `None` if the key does not exist.
+    ///
+    /// The payload can be given to `restore`, on this server or another one,
+    /// to recreate the value. The time to live of the key is not part of it.
+    ///
+    /// # Examples
+    ///
+    /// Moving a key between two servers.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let mut addrs = vec![];
+    /// #     for _ in 0..2 {
+    /// #         let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #         addrs.push(listener.local_addr().unwrap());
+    /// #         tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    /// #     }
+    ///     let mut src = Client::connect(addrs[0]).await.unwrap();
+    ///     let mut dst = Client::connect(addrs[1]).await.unwrap();
+    ///
+    ///     src.set("foo", "bar".into()).await.unwrap();
+    ///
+    ///     let payload = src.dump("foo").await.unwrap().unwrap();
+    ///     dst.restore("foo", None, payload).await.unwrap();
+    ///     src.del(&["foo"]).await.unwrap();
+    ///
+    ///     assert_eq!(Some("bar".into()), dst.get("foo").await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn dump(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        let frame = Dump::new(key).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Create `key` holding the value serialized in `payload`, a payload
+    /// returned by `dump`, expiring after `ttl` if given.
+    ///
+    /// Fails with a `BUSYKEY` error if the key already exists, see
+    /// `restore_replace`, and with an error if the payload is corrupted.
+    #[instrument(skip(self, payload))]
+    pub async fn restore(&mut self, key: &str, ttl: Option<Duration>, payload: Bytes) -> crate::Result<()> {
+        self.restore_cmd(Restore::new(key, ttl, payload)).await
+    }
+
+    /// Like `restore`, but replaces the value of `key` if it already exists.
+    #[instrument(skip(self, payload))]
+    pub async fn restore_replace(&mut self, key: &str, ttl: Option<Duration>, payload: Bytes) -> crate::Result<()> {
+        self.restore_cmd(Restore::new(key, ttl, payload).with_replace()).await
+    }
+
+    /// The core `RESTORE` logic, used by both restore fns.
+    async fn restore_cmd(&mut self, cmd: Restore) -> crate::Result<()> {
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Returns the longest common subsequence of the values stored at `key1`
+    /// and `key2`.
+    ///
+    /// A key that does not exist is treated as an empty value.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("key1", "ohmytext".into()).await.unwrap();
+    ///     client.set("key2", "mynewtext".into()).await.unwrap();
+    ///
+    ///     let lcs = client.lcs("key1", "key2").await.unwrap();
+    ///     assert_eq!(&b"mytext"[..], &lcs[..]);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn lcs(&mut self, key1: &str, key2: &str) -> crate::Result<Bytes> {
+        let frame = Lcs::new(key1, key2).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns the length of the longest common subsequence of the values
+    /// stored at `key1` and `key2`.
+    #[instrument(skip(self))]
+    pub async fn lcs_len(&mut self, key1: &str, key2: &str) -> crate::Result<u64> {
+        let frame = Lcs::new(key1, key2).with_len().into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns where the longest common subsequence of the values stored at
+    /// `key1` and `key2` is found in them.
+    ///
+    /// The runs shorter than `min_match_len` bytes are left out, 0 keeps them
+    /// all.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::lcs::LcsMatch;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("key1", "ohmytext".into()).await.unwrap();
+    ///     client.set("key2", "mynewtext".into()).await.unwrap();
+    ///
+    ///     let idx = client.lcs_idx("key1", "key2", 4).await.unwrap();
+    ///     assert_eq!(6, idx.len);
+    ///     assert_eq!(vec![LcsMatch { a: (4, 7), b: (5, 8) }], idx.matches);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn lcs_idx(&mut self, key1: &str, key2: &str, min_match_len: u64) -> crate::Result<LcsIdx> {
+        let frame = Lcs::new(key1, key2).with_idx(min_match_len).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Set `key` to hold the given `value`.
+    /// 
+    /// The `value` is associated with `key` until it is overwritten by the next
+    /// call to `set` or it is removed.
+    /// 
+    /// If key already holds a value, it is overwritten. Any previous time to live
+    /// associated with the key is discarded on successful SET operation.
+    /// 
+    /// # Examples
+    /// 
+    /// Demonstrates basic usage.
+    /// 
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    /// 
+    ///     // Getting the value immediately works
+    ///     let val = client.get("foo").await.unwrap().unwrap();
+    ///     assert_eq!(val, "bar");
+    /// }
+    #[instrument(skip(self))]
+    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, None)).await
+    }
+
+    /// Set `key` to hold the `len` bytes read from `reader`.
+    ///
+    /// Like `set`, but the value is copied from `reader` to the connection as
+    /// it is read, so a large value, e.g. a file, never has to be held in
+    /// memory by the client. The server still receives the whole command
+    /// before applying it, and rejects it if it exceeds its maximum frame
+    /// length.
+    ///
+    /// # Errors
+    ///
+    /// If `reader` fails, or ends before `len` bytes, the command has been
+    /// partially sent and the connection is poisoned: a new connection must be
+    /// established. The state of `key` on the server is then undefined. With
+    /// this server, the incomplete command is discarded when the connection is
+    /// closed, but a proxy or another server may behave differently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let value = tokio::io::repeat(b'x');
+    ///     client.set_streaming("foo", 1024, value).await.unwrap();
+    ///
+    ///     assert_eq!(1024, client.strlen("foo").await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self, reader))]
+    pub async fn set_streaming<R>(&mut self, key: &str, len: u64, reader: R) -> crate::Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.check_poisoned()?;
+
+        let head = [
+            Frame::Bulk(Bytes::from_static(b"set")),
+            Frame::Bulk(Bytes::copy_from_slice(key.as_bytes())),
+        ];
+
+        // 失败时命令只发送了一部分，服务器无法再解析这个连接上的数据
+        with_timeout(self.timeout, self.connection.write_array_with_reader(&head, len, reader))
+            .await
+            .map_err(|err| self.poison(err))?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+    /// Set `key` to hold the given `value`. The value expires after `expiration`
+    ///
+    /// The `value` is associated with `key` until one of the following:
+    /// - it expires.
+    /// - it is overwritten by the next call to `set`.
+    /// - it is removed.
+    ///
+    /// If key already holds a value, it is overwritten. Any previous time to
+    /// live associated with the key is discarded on a successful SET operation.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage. The server runs in the same runtime as the
+    /// client, so the example pauses the clock and advances it past the TTL
+    /// instead of relying on the client and server staying synchronized in
+    /// time. Pausing the clock requires the `test-util` feature of Tokio.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use tokio::time;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let ttl = Duration::from_millis(500);
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set_expires("foo", "bar".into(), ttl).await.unwrap();
+    ///
+    ///     // Getting the value immediately works
+    ///     let val = client.get("foo").await.unwrap().unwrap();
+    ///     assert_eq!(val, "bar");
+    ///
+    ///     // Let the TTL elapse
+    ///     time::pause();
+    ///     time::advance(ttl * 2).await;
+    ///     time::resume();
+    /// #     // 启用`uring`时服务器运行在自己的runtime上，时钟不受暂停影响
+    /// #     #[cfg(feature = "uring")]
+    /// #     time::sleep(ttl * 2).await;
+    ///
+    ///     let val = client.get("foo").await.unwrap();
+    ///     assert!(val.is_none());
+    /// }
+    /// ```
+    pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, Some(expiration))).await
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `expiration`, using
+    /// the dedicated `PSETEX` command.
+    ///
+    /// This behaves as `set_expires`, except that the server rejects an
+    /// `expiration` of zero.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.psetex("foo", "bar".into(), Duration::from_secs(1)).await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn psetex(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
+        let frame = SetEx::new(key, value, expiration).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Replace the value of `key` with `new` if it currently holds `expected`.
+    ///
+    /// `None` as `expected` requires the key to be missing, and `None` as `new`
+    /// removes the key. Returns `true` if the value was replaced.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     assert!(client.cas("foo", None, Some("1".into())).await.unwrap());
+    ///     assert!(!client.cas("foo", None, Some("2".into())).await.unwrap());
+    ///     assert!(client.cas("foo", Some("1".into()), Some("2".into())).await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn cas(&mut self, key: &str, expected: Option<Bytes>, new: Option<Bytes>) -> crate::Result<bool> {
+        let frame = Cas::new(key, expected, new).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(swapped) => Ok(swapped == 1),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Set `key` to hold the given `value`, only if `key` does not already
+    /// exist.
+    ///
+    /// Returns `true` if the value was set.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     assert!(client.set_nx("foo", "bar".into()).await.unwrap());
+    ///     assert!(!client.set_nx("foo", "baz".into()).await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_nx(&mut self, key: &str, value: Bytes) -> crate::Result<bool> {
+        self.set_cond_cmd(Set::new(key, value, None).with_condition(SetCondition::Nx))
+            .await
+    }
+
+    /// Set `key` to hold the given `value`, only if `key` already exists.
+    ///
+    /// Returns `true` if the value was set.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     assert!(!client.set_xx("foo", "bar".into()).await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_xx(&mut self, key: &str, value: Bytes) -> crate::Result<bool> {
+        self.set_cond_cmd(Set::new(key, value, None).with_condition(SetCondition::Xx))
+            .await
+    }
+
+    /// Set `key` to hold the given `value`, retaining the time to live
+    /// associated with `key`.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set_expires("foo", "bar".into(), Duration::from_secs(60)).await.unwrap();
+    ///
+    ///     // "foo"仍然会在60秒后过期
+    ///     client.set_keep_ttl("foo", "baz".into()).await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_keep_ttl(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, None).with_keep_ttl()).await
+    }
+
+    /// Set `key` to hold the given `value`, returning the value previously
+    /// stored at `key`.
+    ///
+    /// Returns `None` if the key did not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     let prev = client.set_and_get("foo", "baz".into()).await.unwrap();
+    ///     assert_eq!(prev.unwrap(), "bar");
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_and_get(&mut self, key: &str, value: Bytes) -> crate::Result<Option<Bytes>> {
+        let frame = Set::new(key, value, None).with_get().into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        let response = self.read_response().await?;
+        self.decode(response)
+    }
+
+    /// Set `key` to hold the given `value`, returning the old value stored at
+    /// `key`, or `None` if the key did not exist.
+    ///
+    /// The value is swapped atomically with `SET ... GET`, so no other client
+    /// can write `key` between the read and the write. This is the same as
+    /// [`set_and_get`](Client::set_and_get).
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     assert!(client.set_get_old("foo", "bar".into()).await.unwrap().is_none());
+    ///
+    ///     let old = client.set_get_old("foo", "baz".into()).await.unwrap();
+    ///     assert_eq!(old.unwrap(), "bar");
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_get_old(&mut self, key: &str, value: Bytes) -> crate::Result<Option<Bytes>> {
+        self.set_and_get(key, value).await
+    }
+
+    /// Set `key` to hold the given `value`, with the options of `SET` given
+    /// by `options`.
+    ///
+    /// Returns `SetReply::Previous` with the value previously stored at `key`
+    /// if `GET` was requested. Otherwise returns whether the value was
+    /// written, which only depends on the `NX` or `XX` condition.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::{Client, SetOptions, SetReply};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let options = SetOptions::new().expire(Duration::from_secs(60)).nx();
+    ///
+    ///     let reply = client.set_options("foo", "bar".into(), options.clone()).await.unwrap();
+    ///     assert_eq!(SetReply::Written, reply);
+    ///
+    ///     let reply = client.set_options("foo", "baz".into(), options).await.unwrap();
+    ///     assert_eq!(SetReply::Skipped, reply);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_options(&mut self, key: &str, value: Bytes, options: SetOptions) -> crate::Result<SetReply> {
+        let cmd = options.into_cmd(key, value);
+        let get = cmd.get();
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        let response = self.read_response().await?;
+
+        if get {
+            return Ok(SetReply::Previous(self.decode(response)?));
+        }
+
+        match response {
+            Frame::Simple(response) if response == "OK" => Ok(SetReply::Written),
+            Frame::Null => Ok(SetReply::Skipped),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    async fn set_cond_cmd(&mut self, cmd: Set) -> crate::Result<bool> {
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        // 条件不满足时服务端回复`(nil)`
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(true),
+            Frame::Null => Ok(false),
+            frame => Err(self.unexpected(frame))
+        }
+    }
+
+    async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame))
+        }
+    }
+
+    /// Append `value` at the end of the value stored at `key`.
+    ///
+    /// A missing key is created with `value`. Returns the length of the value
+    /// after the append.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     let len = client.append("foo", "baz".into()).await.unwrap();
+    ///     assert_eq!(len, 6);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn append(&mut self, key: &str, value: Bytes) -> crate::Result<u64> {
+        self.integer_cmd(Append::new(key, value).into_frame()).await
+    }
+
+    /// Overwrite part of the value stored at `key`, starting at `offset`, with
+    /// `value`.
+    ///
+    /// The value is padded with zero bytes if it is shorter than `offset`.
+    /// Returns the length of the value after the write.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "Hello World".into()).await.unwrap();
+    ///     client.setrange("foo", 6, "Redis".into()).await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn setrange(&mut self, key: &str, offset: u64, value: Bytes) -> crate::Result<u64> {
+        self.integer_cmd(SetRange::new(key, offset, value).into_frame()).await
+    }
+
+    /// Increment the integer stored at `key` by `delta`, which may be
+    /// negative.
+    ///
+    /// A missing key is treated as 0. Returns the value after the increment.
+    /// An error is returned if the value is not an integer, or if the result
+    /// overflows and the server is not configured to saturate.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "10".into()).await.unwrap();
+    ///     assert_eq!(5, client.incr_by("foo", -5).await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn incr_by(&mut self, key: &str, delta: i64) -> crate::Result<i64> {
+        let frame = IncrBy::new(key, delta).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Increment the integer stored at `key` by one, see `incr_by`.
+    #[instrument(skip(self))]
+    pub async fn incr(&mut self, key: &str) -> crate::Result<i64> {
+        self.incr_by(key, 1).await
+    }
+
+    /// Decrement the integer stored at `key` by one, see `incr_by`.
+    #[instrument(skip(self))]
+    pub async fn decr(&mut self, key: &str) -> crate::Result<i64> {
+        self.incr_by(key, -1).await
+    }
+
+    /// Increment the floating point number stored at `key` by `increment`.
+    ///
+    /// A missing key is treated as 0. Returns the value after the increment.
+    /// Any time to live associated with the key is kept.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "10.5".into()).await.unwrap();
+    ///     let val = client.incr_by_float("foo", 0.1).await.unwrap();
+    ///     assert_eq!(val, 10.6);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn incr_by_float(&mut self, key: &str, increment: f64) -> crate::Result<f64> {
+        let frame = IncrByFloat::new(key, increment).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(ref value) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .ok_or_else(|| "protocol error; invalid float".into()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Get the internal encoding of the value stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "123".into()).await.unwrap();
+    ///     let encoding = client.object_encoding("foo").await.unwrap();
+    ///     assert_eq!(encoding.as_deref(), Some("int"));
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn object_encoding(&mut self, key: &str) -> crate::Result<Option<String>> {
+        let frame = Object::encoding(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value)),
+            Frame::Bulk(value) => Ok(Some(String::from_utf8_lossy(&value).into_owned())),
+            Frame::Null => Ok(None),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Get the reference count of the value stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let count = client.object_refcount("foo").await.unwrap();
+    ///     println!("Got = {:?}", count);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn object_refcount(&mut self, key: &str) -> crate::Result<Option<u64>> {
+        let frame = Object::refcount(key).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Get the number of seconds since `key` was last accessed.
+    ///
+    /// Returns `None` if the key does not exist. Asking for the idle time does
+    /// not count as an access.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let idle = client.object_idletime("foo").await.unwrap();
+    ///     println!("Got = {:?}", idle);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn object_idletime(&mut self, key: &str) -> crate::Result<Option<Duration>> {
+        let frame = Object::idle_time(key).into_frame();
+        let response = self.request(&frame).await?;
+        let secs: Option<u64> = self.decode(response)?;
+        Ok(secs.map(Duration::from_secs))
+    }
+
+    /// Removes the given keys.
+    ///
+    /// Returns the number of keys that were removed.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     assert_eq!(1, client.del(&["foo", "missing"]).await.unwrap());
+    ///     assert!(client.get("foo").await.unwrap().is_none());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn del(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        let frame = Del::new(keys).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Removes the given keys, like `del`, without making the server free
+    /// their values while other clients wait.
+    ///
+    /// Returns the number of keys that were removed.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     assert_eq!(1, client.unlink(&["foo", "missing"]).await.unwrap());
+    ///     assert!(client.get("foo").await.unwrap().is_none());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn unlink(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        let frame = Unlink::new(keys).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns how many of the given keys exist. A key given several times is
+    /// counted each time.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     assert_eq!(2, client.exists(&["foo", "missing", "foo"]).await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn exists(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        let frame = Exists::new(keys).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns, for each of the given keys, whether it exists.
+    ///
+    /// `EXISTS` only replies with a total, so one `EXISTS` per key is sent,
+    /// pipelined. The keys are checked one after the other: another client may
+    /// modify them in between.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     let present = client.exists_map(&["foo", "missing"]).await.unwrap();
+    ///     assert_eq!(vec![true, false], present);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn exists_map(&mut self, keys: &[&str]) -> crate::Result<Vec<bool>> {
+        let frames = keys
+            .iter()
+            .map(|key| Exists::new(vec![key.to_string()]).into_frame())
+            .collect();
+
+        let mut present = Vec::with_capacity(keys.len());
+
+        for response in self.pipelined(frames).await? {
+            let count: u64 = self.decode(response)?;
+            present.push(count > 0);
+        }
+
+        Ok(present)
+    }
+
+    /// Refresh the last access time of the given keys.
+    ///
+    /// Returns the number of keys that exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let touched = client.touch(&["foo", "bar"]).await.unwrap();
+    ///     println!("Touched = {}", touched);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn touch(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        let frame = Touch::new(keys).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Set the given keys to expire after `ttl`, truncated to whole seconds,
+    /// with a single `EXPIREMANY` command. With a `condition`, only the keys
+    /// for which it holds are updated.
+    ///
+    /// Returns, for each key in order, whether its time to live was set. It
+    /// is not set for the keys which do not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::cmd::ExpireCondition;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("session:1", "a".into()).await.unwrap();
+    ///     client.set_expires("session:2", "b".into(), Duration::from_secs(60)).await.unwrap();
+    ///
+    ///     let keys = ["session:1", "session:2", "session:3"];
+    ///     let set = client.expire_many(&keys, Duration::from_secs(3600), Some(ExpireCondition::Nx)).await.unwrap();
+    ///     assert_eq!(vec![true, false, false], set);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn expire_many(
+        &mut self,
+        keys: &[&str],
+        ttl: Duration,
+        condition: Option<ExpireCondition>,
+    ) -> crate::Result<Vec<bool>> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+
+        let mut cmd = ExpireMany::new(keys, ttl);
+        if let Some(condition) = condition {
+            cmd = cmd.with_condition(condition);
+        }
+
+        let response = self.request(&cmd.into_frame()).await?;
+        let set: Vec<u64> = self.decode(response)?;
+
+        Ok(set.into_iter().map(|set| set == 1).collect())
+    }
+
+    /// Insert `values` at the head of the list stored at `key`, one after the
+    /// other, so that the last value ends up first.
+    ///
+    /// Returns the length of the list after the push. An error is returned if
+    /// `key` holds a value which is not a list.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.lpush("queue", &["a".into(), "b".into()]).await.unwrap();
+    ///     let len = client.lpush("queue", &["c".into()]).await.unwrap();
+    ///     assert_eq!(3, len);
+    ///
+    ///     let values = client.lrange("queue", 0, -1).await.unwrap();
+    ///     assert_eq!(vec!["c", "b", "a"], values);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn lpush(&mut self, key: &str, values: &[Bytes]) -> crate::Result<u64> {
+        let frame = LPush::new(key, values.to_vec()).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Insert `values` at the tail of the list stored at `key`, in the order
+    /// they are given.
+    ///
+    /// Returns the length of the list after the push. An error is returned if
+    /// `key` holds a value which is not a list.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.rpush("queue", &["a".into(), "b".into(), "c".into()]).await.unwrap();
+    ///
+    ///     let values = client.lrange("queue", 0, -1).await.unwrap();
+    ///     assert_eq!(vec!["a", "b", "c"], values);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn rpush(&mut self, key: &str, values: &[Bytes]) -> crate::Result<u64> {
+        let frame = RPush::new(key, values.to_vec()).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Remove and return the first value of the list stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist. The key is removed along with
+    /// the last value of the list.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.rpush("queue", &["a".into(), "b".into()]).await.unwrap();
+    ///
+    ///     assert_eq!(Some("a".into()), client.lpop("queue").await.unwrap());
+    ///     assert_eq!(Some("b".into()), client.lpop("queue").await.unwrap());
+    ///     assert_eq!(None, client.lpop("queue").await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn lpop(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        let frame = LPop::new(key).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Remove and return the last value of the list stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist. The key is removed along with
+    /// the last value of the list.
+    #[instrument(skip(self))]
+    pub async fn rpop(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        let frame = RPop::new(key).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Remove and return up to `count` values from the head of the list stored
+    /// at `key`, in the order they were stored.
+    ///
+    /// Returns `None` if the key does not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.rpush("queue", &["a".into(), "b".into(), "c".into()]).await.unwrap();
+    ///
+    ///     let values = client.lpop_count("queue", 2).await.unwrap();
+    ///     assert_eq!(Some(vec!["a".into(), "b".into()]), values);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn lpop_count(&mut self, key: &str, count: u64) -> crate::Result<Option<Vec<Bytes>>> {
+        let frame = LPop::new(key).with_count(count).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Remove and return up to `count` values from the tail of the list stored
+    /// at `key`, the last value first.
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn rpop_count(&mut self, key: &str, count: u64) -> crate::Result<Option<Vec<Bytes>>> {
+        let frame = RPop::new(key).with_count(count).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns the values of the list stored at `key` between the indexes
+    /// `start` and `stop`, both inclusive.
+    ///
+    /// Negative indexes count from the end of the list, `-1` being the last
+    /// value. An empty list is returned if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn lrange(&mut self, key: &str, start: i64, stop: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = LRange::new(key, start, stop).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Set `fields` of the hash stored at `key` to the given values.
+    ///
+    /// Returns the number of fields added to the hash, not counting those
+    /// which were updated. An error is returned if `key` holds a value which
+    /// is not a hash.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let added = client.hset("user:1", &[("name", "ada".into()), ("lang", "en".into())]).await.unwrap();
+    ///     assert_eq!(2, added);
+    ///
+    ///     let added = client.hset("user:1", &[("lang", "fr".into())]).await.unwrap();
+    ///     assert_eq!(0, added);
+    ///
+    ///     let lang = client.hget("user:1", "lang").await.unwrap();
+    ///     assert_eq!(Some("fr".into()), lang);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn hset(&mut self, key: &str, fields: &[(&str, Bytes)]) -> crate::Result<u64> {
+        let fields = fields.iter().map(|(field, value)| (field.to_string(), value.clone())).collect();
+
+        let frame = HSet::new(key, fields).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns the value of `field` of the hash stored at `key`, `None` if
+    /// the key or the field does not exist.
+    #[instrument(skip(self))]
+    pub async fn hget(&mut self, key: &str, field: &str) -> crate::Result<Option<Bytes>> {
+        let frame = HGet::new(key, field).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Remove `fields` from the hash stored at `key`.
+    ///
+    /// Returns the number of fields removed. The key is removed along with the
+    /// last field of the hash.
+    #[instrument(skip(self))]
+    pub async fn hdel(&mut self, key: &str, fields: &[&str]) -> crate::Result<u64> {
+        let fields = fields.iter().map(|field| field.to_string()).collect();
+
+        let frame = HDel::new(key, fields).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns the fields of the hash stored at `key` along with their values,
+    /// in no particular order.
+    ///
+    /// An empty list is returned if the key does not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.hset("user:1", &[("name", "ada".into()), ("lang", "en".into())]).await.unwrap();
+    ///
+    ///     let mut fields = client.hgetall("user:1").await.unwrap();
+    ///     fields.sort();
+    ///     assert_eq!(vec![("lang".to_string(), "en".into()), ("name".to_string(), "ada".into())], fields);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn hgetall(&mut self, key: &str) -> crate::Result<Vec<(String, Bytes)>> {
+        let frame = HGetAll::new(key).into_frame();
+        let response = self.request(&frame).await?;
+        let flat: Vec<Bytes> = self.decode(response)?;
+
+        // 回复中每个字段后面跟着它的值
+        flat.chunks_exact(2)
+            .map(|pair| Ok((String::from_utf8(pair[0].to_vec())?, pair[1].clone())))
+            .collect()
+    }
+
+    /// Appends an entry holding `fields` to the stream stored at `key`, and
+    /// returns its id.
+    ///
+    /// The id is generated by the server from its clock if `id` is `None`.
+    /// Otherwise it must be greater than the id of the last entry of the
+    /// stream.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::cmd::StreamId;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let id = client.xadd("events", Some(StreamId::new(1, 0)), &[("kind", "login".into())]).await.unwrap();
+    ///     assert_eq!(StreamId::new(1, 0), id);
+    ///
+    ///     let next = client.xadd("events", None, &[("kind", "logout".into())]).await.unwrap();
+    ///     assert!(next > id);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn xadd(&mut self, key: &str, id: Option<StreamId>, fields: &[(&str, Bytes)]) -> crate::Result<StreamId> {
+        let fields = fields.iter().map(|(field, value)| (field.to_string(), value.clone())).collect();
+
+        let frame = XAdd::new(key, id, fields).into_frame();
+        let response = self.request(&frame).await?;
+        let id: String = self.decode(response)?;
+        id.parse()
+    }
+
+    /// Returns the entries of the stream stored at `key` whose ids are within
+    /// `start..=end`, in the order of their ids, each along with its fields.
+    ///
+    /// `StreamId::MIN` and `StreamId::MAX` cover the whole stream. An empty
+    /// list is returned if the key does not exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::cmd::StreamId;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let id = client.xadd("events", None, &[("kind", "login".into())]).await.unwrap();
+    ///
+    ///     let entries = client.xrange("events", StreamId::MIN, StreamId::MAX).await.unwrap();
+    ///     assert_eq!(vec![(id, vec![("kind".to_string(), "login".into())])], entries);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn xrange(
+        &mut self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+    ) -> crate::Result<Vec<StreamEntry>> {
+        let frame = XRange::new(key, start, end).into_frame();
+        let response = self.request(&frame).await?;
+
+        let entries = match response {
+            Frame::Array(entries) => entries,
+            frame => return Err(self.unexpected(frame)),
+        };
+
+        // 每个条目是它的id和字段组成的数组
+        let decoded = entries
+            .iter()
+            .map(|entry| match entry {
+                Frame::Array(parts) if parts.len() == 2 => {
+                    let id: String = String::from_frame(parts[0].clone())?;
+                    Ok((id.parse()?, fields_from_frame(&parts[1])?))
+                }
+                entry => Err(entry.to_error()),
+            })
+            .collect::<crate::Result<_>>();
+
+        decoded.map_err(|err| self.poison(err))
+    }
+
+    /// Returns the value of the runtime configuration `parameter`, `None` if
+    /// the server does not know the parameter.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let value = client.config_get("hotkeys").await.unwrap();
+    ///     assert_eq!(Some("no".to_string()), value);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn config_get(&mut self, parameter: &str) -> crate::Result<Option<String>> {
+        let frame = Config::get(parameter).into_frame();
+        let response = self.request(&frame).await?;
+        let pair: Vec<String> = self.decode(response)?;
+        Ok(pair.into_iter().nth(1))
+    }
+
+    /// Sets the runtime configuration `parameter` to `value`.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.config_set("hotkeys", "yes").await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn config_set(&mut self, parameter: &str, value: &str) -> crate::Result<()> {
+        let frame = Config::set(parameter, value).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Returns the `count` most accessed keys, the most accessed first.
+    ///
+    /// Hot key tracking must have been enabled with
+    /// `config_set("hotkeys", "yes")`, otherwise an error is returned. The
+    /// counts are approximate, see `cmd::HotKeys`.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.config_set("hotkeys", "yes").await.unwrap();
+    ///     client.get("foo").await.unwrap();
+    ///
+    ///     let hotkeys = client.hotkeys(10).await.unwrap();
+    ///     assert_eq!("foo", hotkeys[0].key);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn hotkeys(&mut self, count: usize) -> crate::Result<Vec<HotKey>> {
+        let frame = HotKeys::top(count).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Clears the access counts reported by `hotkeys`.
+    #[instrument(skip(self))]
+    pub async fn hotkeys_reset(&mut self) -> crate::Result<()> {
+        let frame = HotKeys::reset().into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Computes the histogram of the lengths of the values stored on the
+    /// server, per value type. See `cmd::Memory` for the buckets.
+    ///
+    /// Fails with a `BUSY` error while another histogram is being computed.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///
+    ///     let histograms = client.memory_histogram().await.unwrap();
+    ///     assert_eq!("string", histograms[0].type_name);
+    ///     assert_eq!(vec![(3, 1)], histograms[0].buckets);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn memory_histogram(&mut self) -> crate::Result<Vec<TypeHistogram>> {
+        let frame = Memory::histogram().into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Send an arbitrary command and decode the reply as a `T`.
+    ///
+    /// `args` holds the command name followed by its arguments. This allows
+    /// issuing commands that `Client` has no dedicated method for.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use bytes::Bytes;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let values: Vec<Option<Bytes>> = client
+    ///         .query(&["mget".into(), "foo".into(), "bar".into()])
+    ///         .await
+    ///         .unwrap();
+    ///     println!("Got = {:?}", values);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn query<T: FromFrame>(&mut self, args: &[Bytes]) -> crate::Result<T> {
+        let mut frame = Frame::array();
+        for arg in args {
+            frame.push_bulk(arg.clone());
+        }
+
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Start a pipeline of commands, sent to the server in a single round
+    /// trip.
+    ///
+    /// Commands are added to the returned `Pipeline`, then sent all at once by
+    /// `Pipeline::execute`, which reads back their replies. This saves a round
+    /// trip per command, for example when loading many keys.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let mut pipeline = client.pipeline();
+    ///     pipeline.set("foo", "bar".into()).get("foo");
+    ///
+    ///     let responses = pipeline.execute().await.unwrap();
+    ///     assert_eq!(responses[0], "OK");
+    ///     assert_eq!(responses[1], "bar");
+    /// }
+    /// ```
+    pub fn pipeline(&mut self) -> Pipeline<'_> {
+        Pipeline::new(self)
+    }
+
+    /// Send an already encoded command and read back the reply.
+    ///
+    /// This is used by `BufferedClient`, which encodes the commands before
+    /// passing them to the connection task.
+    pub(crate) async fn request(&mut self, frame: &Frame) -> crate::Result<Frame> {
+        debug!(request = ?frame);
+
+        self.send(frame).await?;
+
+        self.read_response().await
+    }
+
+    /// Write `frame` to the connection, unless it is poisoned.
+    async fn send(&mut self, frame: &Frame) -> crate::Result<()> {
+        self.check_poisoned()?;
+
+        // 写入失败时，frame可能只有一部分被发送
+        with_timeout(self.timeout, self.connection.write_frame(frame))
+            .await
+            .map_err(|err| self.poison(err))
+    }
+
+    /// Send all the `frames` before reading their replies, in order.
+    ///
+    /// Error replies are returned as `Frame::Error`, and do not stop the
+    /// replies after them from being read, so the connection can still be
+    /// used afterwards. `Err` is only returned when the connection fails.
+    pub(crate) async fn request_all(&mut self, frames: &[Frame]) -> crate::Result<Vec<Frame>> {
+        for frame in frames {
+            debug!(request = ?frame);
+
+            self.send(frame).await?;
+        }
+
+        let mut responses = Vec::with_capacity(frames.len());
+
+        for _ in 0..frames.len() {
+            responses.push(self.read_reply().await?);
+        }
+
+        Ok(responses)
+    }
+
+    /// Send all the `frames` before reading their replies, in order.
+    ///
+    /// All the replies are read even if one of them is an error, so that the
+    /// connection can still be used afterwards. The first error is returned.
+    async fn pipelined(&mut self, frames: Vec<Frame>) -> crate::Result<Vec<Frame>> {
+        let responses = self.request_all(&frames).await?;
+
+        if let Some(Frame::Error(msg)) = responses.iter().find(|response| matches!(response, Frame::Error(_))) {
+            return Err(msg.clone().into());
+        }
+
+        Ok(responses)
+    }
+
+    /// Decode a reply, poisoning the connection if it has an unexpected shape.
+    pub(crate) fn decode<T: FromFrame>(&mut self, frame: Frame) -> crate::Result<T> {
+        T::from_frame(frame).map_err(|err| self.poison(err))
+    }
+
+    /// Returns the error for a reply of an unexpected shape, poisoning the
+    /// connection.
+    ///
+    /// A reply that does not match the request means the requests and the
+    /// replies are no longer paired, so a later reply could otherwise be
+    /// attributed to the wrong request.
+    pub(crate) fn unexpected(&mut self, frame: Frame) -> crate::Error {
+        self.poison(frame.to_error())
+    }
+
+    /// Mark the connection as poisoned and return `err`.
+    fn poison(&mut self, err: impl Into<crate::Error>) -> crate::Error {
+        self.poisoned = true;
+        err.into()
+    }
+
+    /// Returns `Err` if the connection has been poisoned.
+    fn check_poisoned(&self) -> crate::Result<()> {
+        if self.poisoned {
+            return Err(ClientError::ConnectionPoisoned.into());
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the connection has been poisoned by a protocol error
+    /// or a reply of an unexpected shape.
+    ///
+    /// Once poisoned, every operation fails with
+    /// `ClientError::ConnectionPoisoned` and a new connection must be
+    /// established.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Send `frame` and read back an integer reply.
+    async fn integer_cmd(&mut self, frame: Frame) -> crate::Result<u64> {
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Posts `message` to the given `channel`.
+    ///
+    /// Returns the number of subscribers currently listening on the channel.
+    /// There is no guarantee that these subscribers receive the message as they
+    /// may disconnect at any time.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let val = client.publish("foo", "bar".into()).await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        let frame = Publish::new(channel, message).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(self.unexpected(frame)),
+        }
+    }
+
+    /// Subscribes the client to the specified channels.
+    ///
+    /// Once a client issues a subscribe command, it may no longer issue any
+    /// non-pub/sub commands. The function consumes `self` and returns a `Subscriber`.
+    ///
+    /// The `Subscriber` value is used to receive messages as well as manage the
+    /// list of channels the client is subscribed to.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let client = Client::connect(addr).await.unwrap();
+    ///     let mut subscriber = client.subscribe(vec!["foo".into()]).await.unwrap();
+    ///
+    ///     let mut publisher = Client::connect(addr).await.unwrap();
+    ///     publisher.publish("foo", "bar".into()).await.unwrap();
+    ///
+    ///     let message = subscriber.next_message().await.unwrap().unwrap();
+    ///     assert_eq!(message.content, "bar");
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn subscribe(mut self, channels: Vec<String>) -> crate::Result<Subscriber> {
+        let mut pending = VecDeque::new();
+        self.subscribe_cmd(&channels, &[], &[], &mut pending).await?;
+
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: channels,
+            subscribed_patterns: vec![],
+            pending,
+        })
+    }
+
+    /// Subscribes the client to the specified channels, like `subscribe`, and
+    /// returns how many messages were published on each of them since
+    /// `since`.
+    ///
+    /// A client reconnecting after a disconnection can use this to decide
+    /// whether it missed messages and needs to refresh its state.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use std::time::SystemTime;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let disconnected_at = SystemTime::now();
+    ///
+    ///     let client = Client::connect(addr).await.unwrap();
+    ///     let (subscriber, backlogs) = client
+    ///         .subscribe_since(vec!["foo".into()], disconnected_at)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     if backlogs[0].published > 0 {
+    ///         println!("missed messages on {}", backlogs[0].channel);
+    ///     }
+    /// # drop(subscriber);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn subscribe_since(
+        mut self,
+        channels: Vec<String>,
+        since: SystemTime,
+    ) -> crate::Result<(Subscriber, Vec<Backlog>)> {
+        let since = since
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let mut pending = VecDeque::new();
+        let backlogs = self
+            .subscribe_since_cmd(&channels, Some(since), &[], &[], &mut pending)
+            .await?;
+
+        let subscriber = Subscriber {
+            client: self,
+            subscribed_channels: channels,
+            subscribed_patterns: vec![],
+            pending,
+        };
 
-        match self.read_response().await? {
-            Frame::Simple(value) => Ok(value.into()),
-            Frame::Bulk(value) => Ok(value),
-            frame => Err(frame.to_error())
-        }
+        Ok((subscriber, backlogs))
     }
 
-    /// Get the value of key
-    /// 
-    /// If the key does not exist the special value `None` is returned.
-    /// 
+    /// Subscribes the client to the channels matching the specified glob-style
+    /// patterns.
+    ///
+    /// Like `subscribe`, the function consumes `self` and returns a
+    /// `Subscriber`. The messages it receives carry the pattern their channel
+    /// matched.
+    ///
     /// # Examples
-    /// 
+    ///
     /// Demonstrates basic usage.
-    /// 
-    /// ```no_run
+    ///
+    /// ```
     /// use my_mini_redis::clients::Client;
-    /// 
+    ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
-    ///     
-    ///     let val = client.get("foo").await.unwrap();
-    ///     println!("Got = {:?}", val);
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let client = Client::connect(addr).await.unwrap();
+    ///     let mut subscriber = client.subscribe_patterns(vec!["news.*".into()]).await.unwrap();
+    ///
+    ///     let mut publisher = Client::connect(addr).await.unwrap();
+    ///     publisher.publish("news.tech", "bar".into()).await.unwrap();
+    ///
+    ///     let message = subscriber.next_message().await.unwrap().unwrap();
+    ///     assert_eq!(message.channel, "news.tech");
+    ///     assert_eq!(message.pattern.as_deref(), Some("news.*"));
     /// }
     /// ```
     #[instrument(skip(self))]
-    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
-        let frame = Get::new(key).into_frame();
-
-        debug!(request = ?frame);
-
-        self.connection.write_frame(&frame).await?;
-
-        match self.read_response().await? {
-            Frame::Simple(value) => Ok(Some(value.into())),
-            Frame::Bulk(value) => Ok(Some(value)),
-            Frame::Null => Ok(None),
-            frame => Err(frame.to_error()),
-        }
+    pub async fn subscribe_patterns(self, patterns: Vec<String>) -> crate::Result<Subscriber> {
+        self.subscribe_mixed(vec![], patterns).await
     }
 
-    /// Set `key` to hold the given `value`.
-    /// 
-    /// The `value` is associated with `key` until it is overwritten by the next
-    /// call to `set` or it is removed.
-    /// 
-    /// If key already holds a value, it is overwritten. Any previous time to live
-    /// associated with the key is discarded on successful SET operation.
-    /// 
+    /// Subscribes the client to the specified channels and to the channels
+    /// matching the specified patterns, issuing a `SUBSCRIBE` and a
+    /// `PSUBSCRIBE`. An empty list is not sent.
+    ///
+    /// A message published on a channel both subscribed to and matching a
+    /// pattern is received twice, once with its pattern.
+    ///
     /// # Examples
-    /// 
+    ///
     /// Demonstrates basic usage.
-    /// 
-    /// ```no_run
+    ///
+    /// ```
     /// use my_mini_redis::clients::Client;
-    /// 
+    ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
-    ///     client.set("foo", "bar".into()).await.unwrap();
-    /// 
-    ///     // Getting the value immediately works
-    ///     let val = client.get("foo").await.unwrap().unwrap();
-    ///     assert_eq!(val, "bar");
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let client = Client::connect(addr).await.unwrap();
+    ///     let mut subscriber = client
+    ///         .subscribe_mixed(vec!["alerts".into()], vec!["news.*".into()])
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let mut publisher = Client::connect(addr).await.unwrap();
+    ///     publisher.publish("alerts", "fire".into()).await.unwrap();
+    ///
+    ///     let message = subscriber.next_message().await.unwrap().unwrap();
+    ///     assert_eq!(message.channel, "alerts");
+    ///     assert_eq!(message.pattern, None);
     /// }
+    /// ```
     #[instrument(skip(self))]
-    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
-        self.set_cmd(Set::new(key, value, None)).await
+    pub async fn subscribe_mixed(mut self, channels: Vec<String>, patterns: Vec<String>) -> crate::Result<Subscriber> {
+        let mut pending = VecDeque::new();
+
+        if !channels.is_empty() {
+            self.subscribe_cmd(&channels, &[], &[], &mut pending).await?;
+        }
+
+        if !patterns.is_empty() {
+            self.psubscribe_cmd(&patterns, &channels, &[], &mut pending).await?;
+        }
+
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: channels,
+            subscribed_patterns: patterns,
+            pending,
+        })
     }
-    /// Set `key` to hold the given `value`. The value expires after `expiration`
-    ///
-    /// The `value` is associated with `key` until one of the following:
-    /// - it expires.
-    /// - it is overwritten by the next call to `set`.
-    /// - it is removed.
+
+    /// Subscribes the client to the entries of the stream stored at `key`
+    /// added after `last_id`, in the order of their ids.
     ///
-    /// If key already holds a value, it is overwritten. Any previous time to
-    /// live associated with the key is discarded on a successful SET operation.
+    /// The entries already in the stream are received first, then those added
+    /// afterwards as they come. A consumer which persists
+    /// `StreamSubscriber::last_id` once it has processed an entry, and
+    /// subscribes again with it after a disconnection, receives every entry
+    /// exactly once. `StreamId::MIN` subscribes from the start of the stream.
     ///
     /// # Examples
     ///
-    /// Demonstrates basic usage. This example is not **guaranteed** to always
-    /// work as it relies on time based logic and assumes the client and server
-    /// stay relatively synchronized in time. The real world tends to not be so
-    /// favorable.
+    /// Demonstrates basic usage.
     ///
-    /// ```no_run
-    /// use mini_redis::clients::Client;
-    /// use tokio::time;
-    /// use std::time::Duration;
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use my_mini_redis::cmd::StreamId;
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let ttl = Duration::from_millis(500);
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
-    ///
-    ///     client.set_expires("foo", "bar".into(), ttl).await.unwrap();
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut producer = Client::connect(addr).await.unwrap();
+    ///     let first = producer.xadd("events", None, &[("kind", "login".into())]).await.unwrap();
     ///
-    ///     // Getting the value immediately works
-    ///     let val = client.get("foo").await.unwrap().unwrap();
-    ///     assert_eq!(val, "bar");
+    ///     let client = Client::connect(addr).await.unwrap();
+    ///     let mut subscriber = client.xsubscribe("events", StreamId::MIN).await.unwrap();
     ///
-    ///     // Wait for the TTL to expire
-    ///     time::sleep(ttl).await;
+    ///     // 已有的条目先被重放，之后添加的条目随后送达
+    ///     let second = producer.xadd("events", None, &[("kind", "logout".into())]).await.unwrap();
     ///
-    ///     let val = client.get("foo").await.unwrap();
-    ///     assert!(val.is_some());
+    ///     assert_eq!(first, subscriber.next_entry().await.unwrap().unwrap().0);
+    ///     assert_eq!(second, subscriber.next_entry().await.unwrap().unwrap().0);
+    ///     assert_eq!(second, subscriber.last_id());
     /// }
     /// ```
-    pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
-        self.set_cmd(Set::new(key, value, Some(expiration))).await
-    }
-
-    async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
-        let frame = cmd.into_frame();
+    #[instrument(skip(self))]
+    pub async fn xsubscribe(mut self, key: &str, last_id: StreamId) -> crate::Result<StreamSubscriber> {
+        let frame = XSubscribe::new(key, Some(last_id)).into_frame();
 
         debug!(request = ?frame);
 
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
 
-        match self.read_response().await? {
-            Frame::Simple(response) if response == "OK" => Ok(()),
-            frame => Err(frame.to_error())
+        let response = self.read_response().await?;
+
+        let reply = PubSubReply::try_from_frame_with(&response, self.strictness).map_err(|err| self.poison(err))?;
+
+        match reply {
+            PubSubReply::XSubscribe { key: subscribed, last_id } if subscribed == key => Ok(StreamSubscriber {
+                client: self,
+                key: subscribed,
+                last_id,
+            }),
+            _ => Err(self.unexpected(response)),
         }
     }
 
-    /// Posts `message` to the given `channel`.
+    /// Returns the channels with at least one subscriber, only those matching
+    /// the glob-style `pattern` if given, in no particular order.
     ///
-    /// Returns the number of subscribers currently listening on the channel.
-    /// There is no guarantee that these subscribers receive the message as they
-    /// may disconnect at any time.
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let channels = client.pubsub_channels(Some("news.*")).await.unwrap();
+    ///     println!("{} news channels", channels.len());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn pubsub_channels(&mut self, pattern: Option<&str>) -> crate::Result<Vec<String>> {
+        let frame = PubSub::channels(pattern.map(|pattern| pattern.to_string())).into_frame();
+        let response = self.request(&frame).await?;
+        self.decode(response)
+    }
+
+    /// Returns the statistics of the traffic on `channel`.
     ///
     /// # Examples
     ///
     /// Demonstrates basic usage.
     ///
-    /// ```no_run
-    /// use mini_redis::clients::Client;
+    /// ```
+    /// use my_mini_redis::clients::Client;
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
     ///
-    ///     let val = client.publish("foo", "bar".into()).await.unwrap();
-    ///     println!("Got = {:?}", val);
+    ///     let stats = client.pubsub_stats("foo").await.unwrap();
+    ///     println!("{} messages published", stats.published);
     /// }
     /// ```
     #[instrument(skip(self))]
-    pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
-        let frame = Publish::new(channel, message).into_frame();
+    pub async fn pubsub_stats(&mut self, channel: &str) -> crate::Result<ChannelStats> {
+        let frame = PubSub::stats(channel).into_frame();
 
         debug!(request = ?frame);
 
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
 
-        match self.read_response().await? {
-            Frame::Integer(response) => Ok(response),
-            frame => Err(frame.to_error()),
-        }
+        let response = self.read_response().await?;
+        self.decode(response)
     }
 
-    /// Subscribes the client to the specified channels.
+    /// Sets how strictly pub/sub requests and replies are checked.
     ///
-    /// Once a client issues a subscribe command, it may no longer issue any
-    /// non-pub/sub commands. The function consumes `self` and returns a `Subscriber`.
+    /// Defaults to `Strictness::Lenient`. The setting is carried over to the
+    /// `Subscriber` returned by `subscribe`.
+    pub fn set_strictness(&mut self, strictness: Strictness) {
+        self.strictness = strictness;
+    }
+
+    async fn subscribe_cmd(
+        &mut self,
+        channels: &[String],
+        subscribed: &[String],
+        patterns: &[String],
+        pending: &mut VecDeque<Message>,
+    ) -> crate::Result<()> {
+        self.subscribe_since_cmd(channels, None, subscribed, patterns, pending).await?;
+        Ok(())
+    }
+
+    /// Sends a `PSUBSCRIBE` request and reads the confirmations.
     ///
-    /// The `Subscriber` value is used to receive messages as well as manage the
-    /// list of channels the client is subscribed to.
-    #[instrument(skip(self))]
-    pub async fn subscribe(mut self, channels: Vec<String>) -> crate::Result<Subscriber> {
-        self.subscribe_cmd(&channels).await?;
+    /// Messages published on the channels already `subscribed` to, or
+    /// matching the subscribed `patterns` and those confirmed by this request,
+    /// may be interleaved with the confirmations. They are queued in
+    /// `pending`.
+    async fn psubscribe_cmd(
+        &mut self,
+        new_patterns: &[String],
+        subscribed: &[String],
+        patterns: &[String],
+        pending: &mut VecDeque<Message>,
+    ) -> crate::Result<()> {
+        let frame = PSubscribe::new(new_patterns.to_vec()).into_frame();
 
-        Ok(Subscriber {
-            client: self,
-            subscribed_channels: channels,
-        })
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        let mut patterns = patterns.to_vec();
+
+        // 和`SUBSCRIBE`一样，每个模式都有一条确认
+        for pattern in new_patterns {
+            let (response, reply) = self.read_pubsub_reply(subscribed, &patterns, pending).await?;
+
+            match reply {
+                PubSubReply::PSubscribe { pattern: spattern, .. } if spattern == *pattern => {}
+                _ => return Err(self.unexpected(response)),
+            }
+
+            patterns.push(pattern.clone());
+        }
+
+        Ok(())
     }
 
-    async fn subscribe_cmd(&mut self, channels: &[String]) -> crate::Result<()> {
-        let frame = Subscribe::new(channels.to_vec()).into_frame();
+    /// Sends a `SUBSCRIBE` request, with `SINCE` if `since` is given, and
+    /// reads the confirmations. Returns the backlogs replied for `SINCE`.
+    ///
+    /// Messages published on the channels already `subscribed` to, or
+    /// confirmed by this request, may be interleaved with the confirmations.
+    /// They are queued in `pending`.
+    async fn subscribe_since_cmd(
+        &mut self,
+        channels: &[String],
+        since: Option<u64>,
+        subscribed: &[String],
+        patterns: &[String],
+        pending: &mut VecDeque<Message>,
+    ) -> crate::Result<Vec<Backlog>> {
+        let strictness = self.strictness;
+
+        // 在严格模式下，非法的频道名在发送之前就被拒绝
+        for channel in channels {
+            strictness.check_channel(channel)?;
+        }
+
+        let frame = match since {
+            Some(since) => Subscribe::since(channels.to_vec(), since).into_frame(),
+            None => Subscribe::new(channels.to_vec()).into_frame(),
+        };
 
         debug!(request = ?frame);
 
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
+
+        let mut backlogs = vec![];
+        let mut subscribed = subscribed.to_vec();
 
         // 对于订阅的每个频道，服务器都会回复一条确认订阅该频道的信息。
         for channel in channels {
-            let response = self.read_response().await?;
+            // 服务端用一个frame数组回复，回复格式如下：
+            //
+            // ```
+            // [ "subscribe", channel, num-subscribed ]
+            // ```
+            //
+            // 当频道名是所订阅频道名并且num-subscribed为当前订阅
+            let (response, reply) = self.read_pubsub_reply(&subscribed, patterns, pending).await?;
 
-            match response {
-                // as_slice()返回不可变切片
-                Frame::Array(ref frame) => match frame.as_slice() {
-                    // 服务端用一个frame数组回复，回复格式如下：
-                    //
-                    // ```
-                    // [ "subscribe", channel, num-subscribed ]
-                    // ```
-                    //
-                    // 当频道名是所订阅频道名并且num-subscribed为当前订阅
-                    // 这里能直接比较是因为实现了PartialEq<&str>特征
-                    [subscribe, schannel, ..] if *subscribe == "subscribe"  && *schannel == channel => {},
-                    _ => return Err(response.to_error()),
-                },
-                frame => return Err(frame.to_error())
-            };
+            match reply {
+                PubSubReply::Subscribe { channel: schannel, .. } if schannel == *channel => {}
+                _ => return Err(self.unexpected(response)),
+            }
+
+            subscribed.push(channel.clone());
+
+            if since.is_none() {
+                continue;
+            }
+
+            // 使用`SINCE`时，确认之后紧跟着一个`smeta`回复
+            let (response, reply) = self.read_pubsub_reply(&subscribed, patterns, pending).await?;
+
+            match reply {
+                PubSubReply::Backlog {
+                    channel: schannel,
+                    published,
+                    truncated,
+                } if schannel == *channel => backlogs.push(Backlog {
+                    channel: schannel,
+                    published,
+                    truncated,
+                }),
+                _ => return Err(self.unexpected(response)),
+            }
         }
 
-        Ok(())
+        Ok(backlogs)
+    }
+    /// Read the next pub/sub reply which is not a message, along with its
+    /// frame.
+    ///
+    /// The messages published on the `subscribed` channels, or received
+    /// through the subscribed `patterns`, before it are queued in `pending`.
+    /// Any other message can not precede the reply, and poisons the
+    /// connection.
+    async fn read_pubsub_reply(
+        &mut self,
+        subscribed: &[String],
+        patterns: &[String],
+        pending: &mut VecDeque<Message>,
+    ) -> crate::Result<(Frame, PubSubReply)> {
+        loop {
+            let response = self.read_response().await?;
+
+            let reply = PubSubReply::try_from_frame_with(&response, self.strictness)
+                .map_err(|err| self.poison(err))?;
+
+            match reply {
+                PubSubReply::Message { channel, content } if subscribed.contains(&channel) => {
+                    pending.push_back(Message {
+                        channel,
+                        content,
+                        pattern: None,
+                    });
+                }
+                PubSubReply::PMessage {
+                    pattern,
+                    channel,
+                    content,
+                } if patterns.contains(&pattern) => {
+                    pending.push_back(Message {
+                        channel,
+                        content,
+                        pattern: Some(pattern),
+                    });
+                }
+                PubSubReply::Message { .. } | PubSubReply::PMessage { .. } => return Err(self.unexpected(response)),
+                reply => return Ok((response, reply)),
+            }
+        }
     }
+
     /// Read a response frame from the socket.
     /// 
     /// If an `Error` frame is receive, it is converted to `Err`
     async fn read_response(&mut self) -> crate::Result<Frame> {
-        let response = self.connection.read_frame().await?;
+        match self.read_reply().await? {
+            Frame::Error(msg) => Err(msg.into()),
+            frame => Ok(frame),
+        }
+    }
+
+    /// Read a response frame from the socket, `Error` frames included.
+    async fn read_reply(&mut self) -> crate::Result<Frame> {
+        self.check_poisoned()?;
+
+        // 读取失败时，缓冲区中可能残留着这个回复的剩余部分，连接无法再使用
+        let response = with_timeout(self.timeout, self.connection.read_frame())
+            .await
+            .map_err(|err| self.poison(err))?;
 
         debug!(?response);
 
         match response {
-            Some(Frame::Error(msg)) => Err(msg.into()),
             Some(frame) => Ok(frame),
             None => {
                 // 收到`None`表示服务器已经关闭连接，并且没有发送frame。
@@ -342,30 +3462,97 @@ impl Client {
     }
 }
 
+impl fmt::Display for ClientError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::ConnectionPoisoned => {
+                "connection poisoned by a previous protocol error".fmt(fmt)
+            }
+            ClientError::CalledFromAsyncContext => {
+                "blocking client called from within an asynchronous context; use `Client` instead".fmt(fmt)
+            }
+            ClientError::TimedOut => "request timed out".fmt(fmt),
+            ClientError::CrossShard => "keys of the command are stored on different shards".fmt(fmt),
+            ClientError::BackgroundTaskPanicked(reason) => write!(fmt, "background task panicked: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+/// Runs `fut`, an operation on the connection, to completion, or fails with
+/// `ClientError::TimedOut` if it takes longer than `timeout`.
+async fn with_timeout<F, T, E>(timeout: Option<Duration>, fut: F) -> crate::Result<T>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<crate::Error>,
+{
+    match timeout {
+        Some(timeout) => match time::timeout(timeout, fut).await {
+            Ok(res) => res.map_err(Into::into),
+            Err(_) => Err(ClientError::TimedOut.into()),
+        },
+        None => fut.await.map_err(Into::into),
+    }
+}
+
 impl Subscriber {
     /// Returns the set of channels currently subscribed to.
     pub fn get_subscribed(&self) -> &[String] {
         &self.subscribed_channels
     }
 
-    /// Receive the next message published on a subscribed channel, waiting if
-    /// necessary.
+    /// Returns the set of patterns currently subscribed to.
+    pub fn get_subscribed_patterns(&self) -> &[String] {
+        &self.subscribed_patterns
+    }
+
+    /// Sets how strictly pub/sub requests and replies are checked.
+    pub fn set_strictness(&mut self, strictness: Strictness) {
+        self.client.strictness = strictness;
+    }
+
+    /// Receive the next message published on a subscribed channel, or on a
+    /// channel matching a subscribed pattern, waiting if necessary.
     /// 
     /// `None` indicates the subscription has been terminated.
     pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
-        match self.client.connection.read_frame().await? {
+        self.client.check_poisoned()?;
+
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(Some(message));
+        }
+
+        let frame = self
+            .client
+            .connection
+            .read_frame()
+            .await
+            .map_err(|err| self.client.poison(err))?;
+
+        match frame {
             Some(mframe) => {
                 debug!(?mframe);
 
-                match mframe {
-                    Frame::Array(ref frame) => match frame.as_slice() {
-                        [message, channel, content] if *message == "message" => Ok(Some(Message{
-                            channel: channel.to_string(),
-                            content: Bytes::from(content.to_string()),
-                        })),
-                        _ => Err(mframe.to_error()),
-                    },
-                    frame => Err(frame.to_error()),
+                let reply = PubSubReply::try_from_frame_with(&mframe, self.client.strictness)
+                    .map_err(|err| self.client.poison(err))?;
+
+                match reply {
+                    PubSubReply::Message { channel, content } => Ok(Some(Message {
+                        channel,
+                        content,
+                        pattern: None,
+                    })),
+                    PubSubReply::PMessage {
+                        pattern,
+                        channel,
+                        content,
+                    } => Ok(Some(Message {
+                        channel,
+                        content,
+                        pattern: Some(pattern),
+                    })),
+                    _ => Err(self.client.unexpected(mframe)),
                 }
             }
             None => Ok(None)
@@ -383,6 +3570,7 @@ impl Subscriber {
     /// 订阅者 "本身并不实现流，因为使用安全代码实现流并非易事。如果使用 async/await，
     /// 则需要手动实现流以使用`不安全`代码。取而代之的是提供一个转换函数，
     /// 并在 `async-stream` crate 的帮助下实现返回的流。
+    #[allow(dead_code)]
     fn into_stream(mut self) -> impl Stream<Item = crate::Result<Message>> {
         // 使用`async-stream`包中的`try_stream`宏。在Rust中
         // 生成器并不稳定。该板块使用宏来模拟 async/await 上的生成器。
@@ -396,8 +3584,10 @@ impl Subscriber {
 
     /// Subscribe to a list of new channels
     #[instrument(skip(self))]
-    pub async fn subscibe(&mut self, channels: &[String]) -> crate::Result<()> {
-        self.client.subscribe_cmd(channels).await?;
+    pub async fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
+        self.client
+            .subscribe_cmd(channels, &self.subscribed_channels, &self.subscribed_patterns, &mut self.pending)
+            .await?;
         // channels.iter().map(Clone::clone) 创建了一个新的迭代器，
         // 这个迭代器在每次迭代时都会返回 channels 中元素的一个克隆。
         self.subscribed_channels.extend(channels.iter().map(Clone::clone));
@@ -405,13 +3595,19 @@ impl Subscriber {
         Ok(())
     }
 
+    /// Misspelled name of `subscribe`, kept for existing callers.
+    #[deprecated(note = "renamed to `subscribe`")]
+    pub async fn subscibe(&mut self, channels: &[String]) -> crate::Result<()> {
+        self.subscribe(channels).await
+    }
+
     #[instrument(skip(self))]
     pub async fn unsubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         let frame = Unsubscribe::new(channels).into_frame();
 
         debug!(request = ?frame);
 
-        self.client.connection.write_frame(&frame).await?;
+        self.client.send(&frame).await?;
 
         // 如果输入channel list为空，服务器确认取消订阅所有频道
         // 所以我们断言收到的取消订阅列表和客户端订阅列表一致
@@ -422,28 +3618,127 @@ impl Subscriber {
         };
 
         for _ in 0..num {
-            let response = self.client.read_response().await?;
+            // 在确认之前，仍然订阅的频道上可能收到消息
+            let (response, reply) = self
+                .client
+                .read_pubsub_reply(&self.subscribed_channels, &self.subscribed_patterns, &mut self.pending)
+                .await?;
 
-            match response {
-                Frame::Array(ref frame) => match frame.as_slice() {
-                    [unsubscribe, channel, ..] if *unsubscribe == "unsubscribe" => {
-                        let len =  self.subscribed_channels.len();
+            match reply {
+                PubSubReply::Unsubscribe { channel, .. } => {
+                    let len = self.subscribed_channels.len();
 
-                        if len == 0 {
-                            return Err(response.to_error());
-                        }
+                    if len == 0 {
+                        return Err(self.client.unexpected(response));
+                    }
 
-                        self.subscribed_channels.retain(|c| *channel != &c[..]);
+                    self.subscribed_channels.retain(|c| *c != channel);
 
-                        if self.subscribed_channels.len() != len - 1 {
-                            return Err(response.to_error());
-                        }
+                    if self.subscribed_channels.len() != len - 1 {
+                        return Err(self.client.unexpected(response));
                     }
-                    _ => return Err(response.to_error()),
-                },
-                frame => return Err(frame.to_error()),
+                }
+                _ => return Err(self.client.unexpected(response)),
             };
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl StreamSubscriber {
+    /// Returns the key of the stream subscribed to.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the id of the last entry received, or the one given to
+    /// `Client::xsubscribe` if none was received yet.
+    ///
+    /// This is the cursor to persist once the entry is processed: subscribing
+    /// again after it resumes with the next entry.
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// Receive the next entry of the stream, along with its id, waiting if
+    /// necessary.
+    ///
+    /// `None` indicates the subscription has been terminated. An error is
+    /// returned if the key no longer holds a stream, after which the server
+    /// closes the subscription.
+    pub async fn next_entry(&mut self) -> crate::Result<Option<StreamEntry>> {
+        self.client.check_poisoned()?;
+
+        let frame = self
+            .client
+            .connection
+            .read_frame()
+            .await
+            .map_err(|err| self.client.poison(err))?;
+
+        let frame = match frame {
+            Some(Frame::Error(msg)) => return Err(msg.into()),
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+
+        debug!(?frame);
+
+        let reply = PubSubReply::try_from_frame_with(&frame, self.client.strictness)
+            .map_err(|err| self.client.poison(err))?;
+
+        // 条目按id的顺序送达，更小的id意味着重复的条目
+        match reply {
+            PubSubReply::XMessage { key, id, fields } if key == self.key && id > self.last_id => {
+                self.last_id = id;
+                Ok(Some((id, fields)))
+            }
+            _ => Err(self.client.unexpected(frame)),
+        }
+    }
+
+    /// Convert the subscriber into a `Stream` yielding the entries of the
+    /// stream along with their ids, see `next_entry`.
+    ///
+    /// The ids are the cursors to persist, as `last_id` is no longer
+    /// reachable.
+    pub fn into_stream(mut self) -> impl Stream<Item = crate::Result<StreamEntry>> {
+        try_stream! {
+            while let Some(entry) = self.next_entry().await? {
+                yield entry;
+            }
+        }
+    }
+}
+
+impl ValueReader<'_> {
+    /// Returns the length of the whole value, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the value is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes of the value left to read.
+    pub fn remaining(&self) -> usize {
+        self.client.connection.bulk_remaining()
+    }
+}
+
+impl AsyncRead for ValueReader<'_> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let client = &mut *self.get_mut().client;
+
+        // 读取失败时值的剩余部分无法被跳过，连接无法再使用
+        match client.connection.poll_read_bulk(cx, buf) {
+            Poll::Ready(Err(err)) => {
+                client.poisoned = true;
+                Poll::Ready(Err(err))
+            }
+            poll => poll,
+        }
+    }
+}
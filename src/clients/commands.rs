@@ -0,0 +1,176 @@
+use crate::clients::{BufferedClient, Client, PooledClient};
+
+use bytes::Bytes;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// The future returned by the methods of `Commands`.
+pub type CommandFuture<'a, T> = Pin<Box<dyn Future<Output = crate::Result<T>> + Send + 'a>>;
+
+/// The common operations of the clients, for code which does not need to know
+/// which client it talks to.
+///
+/// Accepting `impl Commands`, or `&mut dyn Commands`, instead of a concrete
+/// client lets an application unit-test its code against a `MockClient`,
+/// available with the `test-util` feature, instead of a running server. Each
+/// method behaves as the inherent method of the same name of `Client`.
+///
+/// Unlike `KeyCommands`, the methods return boxed futures so that the trait
+/// can be used as a trait object.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::clients::{Client, Commands};
+///
+/// async fn visit(client: &mut impl Commands, page: &str) -> my_mini_redis::Result<bool> {
+///     let first = client.exists(&[page]).await? == 0;
+///     client.set(page, "visited".into()).await?;
+///     Ok(first)
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+/// #     let addr = listener.local_addr().unwrap();
+/// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+///     let mut client = Client::connect(addr).await.unwrap();
+///
+///     assert!(visit(&mut client, "home").await.unwrap());
+///     assert!(!visit(&mut client, "home").await.unwrap());
+/// }
+/// ```
+pub trait Commands: Send {
+    /// Get the value of key. See `Client::get`.
+    fn get<'a>(&'a mut self, key: &'a str) -> CommandFuture<'a, Option<Bytes>>;
+
+    /// Set `key` to hold the given `value`. See `Client::set`.
+    fn set<'a>(&'a mut self, key: &'a str, value: Bytes) -> CommandFuture<'a, ()>;
+
+    /// Set `key` to hold the given `value`, expiring after `expiration`. See
+    /// `Client::set_expires`.
+    fn set_expires<'a>(
+        &'a mut self,
+        key: &'a str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> CommandFuture<'a, ()>;
+
+    /// Removes the given keys. See `Client::del`.
+    fn del<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64>;
+
+    /// Returns how many of the given keys exist. See `Client::exists`.
+    fn exists<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64>;
+
+    /// Posts `message` to the given `channel`. See `Client::publish`.
+    fn publish<'a>(&'a mut self, channel: &'a str, message: Bytes) -> CommandFuture<'a, u64>;
+
+    /// Ping the server. See `Client::ping`.
+    fn ping(&mut self, msg: Option<Bytes>) -> CommandFuture<'_, Bytes>;
+}
+
+impl Commands for Client {
+    fn get<'a>(&'a mut self, key: &'a str) -> CommandFuture<'a, Option<Bytes>> {
+        Box::pin(Client::get(self, key))
+    }
+
+    fn set<'a>(&'a mut self, key: &'a str, value: Bytes) -> CommandFuture<'a, ()> {
+        Box::pin(Client::set(self, key, value))
+    }
+
+    fn set_expires<'a>(
+        &'a mut self,
+        key: &'a str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> CommandFuture<'a, ()> {
+        Box::pin(Client::set_expires(self, key, value, expiration))
+    }
+
+    fn del<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(Client::del(self, keys))
+    }
+
+    fn exists<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(Client::exists(self, keys))
+    }
+
+    fn publish<'a>(&'a mut self, channel: &'a str, message: Bytes) -> CommandFuture<'a, u64> {
+        Box::pin(Client::publish(self, channel, message))
+    }
+
+    fn ping(&mut self, msg: Option<Bytes>) -> CommandFuture<'_, Bytes> {
+        Box::pin(Client::ping(self, msg))
+    }
+}
+
+impl Commands for BufferedClient {
+    fn get<'a>(&'a mut self, key: &'a str) -> CommandFuture<'a, Option<Bytes>> {
+        Box::pin(BufferedClient::get(self, key))
+    }
+
+    fn set<'a>(&'a mut self, key: &'a str, value: Bytes) -> CommandFuture<'a, ()> {
+        Box::pin(BufferedClient::set(self, key, value))
+    }
+
+    fn set_expires<'a>(
+        &'a mut self,
+        key: &'a str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> CommandFuture<'a, ()> {
+        Box::pin(BufferedClient::set_expires(self, key, value, expiration))
+    }
+
+    fn del<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(BufferedClient::del(self, keys))
+    }
+
+    fn exists<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(BufferedClient::exists(self, keys))
+    }
+
+    fn publish<'a>(&'a mut self, channel: &'a str, message: Bytes) -> CommandFuture<'a, u64> {
+        Box::pin(BufferedClient::publish(self, channel, message))
+    }
+
+    fn ping(&mut self, msg: Option<Bytes>) -> CommandFuture<'_, Bytes> {
+        Box::pin(BufferedClient::ping(self, msg))
+    }
+}
+
+impl Commands for PooledClient {
+    fn get<'a>(&'a mut self, key: &'a str) -> CommandFuture<'a, Option<Bytes>> {
+        Commands::get(&mut **self, key)
+    }
+
+    fn set<'a>(&'a mut self, key: &'a str, value: Bytes) -> CommandFuture<'a, ()> {
+        Commands::set(&mut **self, key, value)
+    }
+
+    fn set_expires<'a>(
+        &'a mut self,
+        key: &'a str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> CommandFuture<'a, ()> {
+        Commands::set_expires(&mut **self, key, value, expiration)
+    }
+
+    fn del<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Commands::del(&mut **self, keys)
+    }
+
+    fn exists<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Commands::exists(&mut **self, keys)
+    }
+
+    fn publish<'a>(&'a mut self, channel: &'a str, message: Bytes) -> CommandFuture<'a, u64> {
+        Commands::publish(&mut **self, channel, message)
+    }
+
+    fn ping(&mut self, msg: Option<Bytes>) -> CommandFuture<'_, Bytes> {
+        Commands::ping(&mut **self, msg)
+    }
+}
@@ -0,0 +1,114 @@
+//! Decoding of reply frames into Rust types.
+//!
+//! Used by `Client::query` to issue arbitrary commands and get the reply back
+//! as the type the caller asks for.
+
+use crate::Frame;
+
+use bytes::Bytes;
+
+/// Conversion of a reply `Frame` into a Rust value.
+///
+/// Error frames never reach `from_frame`, they are turned into `Err` when the
+/// reply is read.
+pub trait FromFrame: Sized {
+    /// Decode `frame`, returning `Err` if it does not represent a `Self`.
+    fn from_frame(frame: Frame) -> crate::Result<Self>;
+}
+
+impl FromFrame for Frame {
+    fn from_frame(frame: Frame) -> crate::Result<Frame> {
+        Ok(frame)
+    }
+}
+
+impl FromFrame for Bytes {
+    fn from_frame(frame: Frame) -> crate::Result<Bytes> {
+        match frame {
+            Frame::Simple(s) => Ok(Bytes::from(s)),
+            Frame::Bulk(data) => Ok(data),
+            frame => Err(frame.to_error()),
+        }
+    }
+}
+
+impl FromFrame for String {
+    fn from_frame(frame: Frame) -> crate::Result<String> {
+        match frame {
+            Frame::Simple(s) => Ok(s),
+            Frame::Bulk(data) => String::from_utf8(data.to_vec())
+                .map_err(|_| "protocol error; invalid string".into()),
+            frame => Err(frame.to_error()),
+        }
+    }
+}
+
+impl FromFrame for u64 {
+    fn from_frame(frame: Frame) -> crate::Result<u64> {
+        use atoi::atoi;
+
+        match frame {
+            Frame::Integer(num) => Ok(num),
+            Frame::Simple(s) => {
+                atoi::<u64>(s.as_bytes()).ok_or_else(|| "protocol error; invalid number".into())
+            }
+            Frame::Bulk(data) => {
+                atoi::<u64>(&data).ok_or_else(|| "protocol error; invalid number".into())
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+}
+
+impl FromFrame for i64 {
+    fn from_frame(frame: Frame) -> crate::Result<i64> {
+        use atoi::atoi;
+
+        match frame {
+            Frame::Integer(num) => {
+                i64::try_from(num).map_err(|_| "protocol error; number out of range".into())
+            }
+            Frame::SignedInteger(num) => Ok(num),
+            Frame::Simple(s) => {
+                atoi::<i64>(s.as_bytes()).ok_or_else(|| "protocol error; invalid number".into())
+            }
+            Frame::Bulk(data) => {
+                atoi::<i64>(&data).ok_or_else(|| "protocol error; invalid number".into())
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+}
+
+impl<T: FromFrame> FromFrame for Option<T> {
+    fn from_frame(frame: Frame) -> crate::Result<Option<T>> {
+        match frame {
+            Frame::Null => Ok(None),
+            frame => T::from_frame(frame).map(Some),
+        }
+    }
+}
+
+impl<T: FromFrame> FromFrame for Vec<T> {
+    fn from_frame(frame: Frame) -> crate::Result<Vec<T>> {
+        match frame {
+            Frame::Array(parts) => parts.into_iter().map(T::from_frame).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+}
+
+impl<A: FromFrame, B: FromFrame> FromFrame for (A, B) {
+    fn from_frame(frame: Frame) -> crate::Result<(A, B)> {
+        match frame {
+            Frame::Array(parts) if parts.len() == 2 => {
+                let mut parts = parts.into_iter();
+                // 长度已检查，`unwrap()`是安全的
+                let a = A::from_frame(parts.next().unwrap())?;
+                let b = B::from_frame(parts.next().unwrap())?;
+                Ok((a, b))
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::clients::{BufferedClient, Client};
+
+use bytes::Bytes;
+use std::future::Future;
+
+/// The key-value and publish operations shared by the concrete clients.
+///
+/// This allows wrappers such as `Namespaced` to work on top of either a
+/// `Client` or a `BufferedClient`. Each method behaves as the inherent method
+/// of the same name.
+pub trait KeyCommands {
+    /// Get the value of key. See `Client::get`.
+    fn get(&mut self, key: &str) -> impl Future<Output = crate::Result<Option<Bytes>>> + Send;
+
+    /// Set `key` to hold the given `value`. See `Client::set`.
+    fn set(&mut self, key: &str, value: Bytes) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Returns all keys matching `pattern`. See `Client::keys`.
+    fn keys(&mut self, pattern: &str) -> impl Future<Output = crate::Result<Vec<String>>> + Send;
+
+    /// Returns the next batch of keys of an iteration. See `Client::scan`.
+    fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<usize>,
+    ) -> impl Future<Output = crate::Result<(u64, Vec<String>)>> + Send;
+
+    /// Posts `message` to the given `channel`. See `Client::publish`.
+    fn publish(
+        &mut self,
+        channel: &str,
+        message: Bytes,
+    ) -> impl Future<Output = crate::Result<u64>> + Send;
+}
+
+impl KeyCommands for Client {
+    async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        Client::get(self, key).await
+    }
+
+    async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        Client::set(self, key, value).await
+    }
+
+    async fn keys(&mut self, pattern: &str) -> crate::Result<Vec<String>> {
+        Client::keys(self, pattern).await
+    }
+
+    async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<usize>,
+    ) -> crate::Result<(u64, Vec<String>)> {
+        Client::scan(self, cursor, pattern, count).await
+    }
+
+    async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        Client::publish(self, channel, message).await
+    }
+}
+
+impl KeyCommands for BufferedClient {
+    async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        BufferedClient::get(self, key).await
+    }
+
+    async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        BufferedClient::set(self, key, value).await
+    }
+
+    async fn keys(&mut self, pattern: &str) -> crate::Result<Vec<String>> {
+        BufferedClient::keys(self, pattern).await
+    }
+
+    async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<usize>,
+    ) -> crate::Result<(u64, Vec<String>)> {
+        BufferedClient::scan(self, cursor, pattern, count).await
+    }
+
+    async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        BufferedClient::publish(self, channel, message).await
+    }
+}
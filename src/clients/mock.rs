@@ -0,0 +1,220 @@
+use crate::clients::{CommandFuture, Commands};
+
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::{self, Instant};
+
+/// An in-memory client, for unit tests of code written against `Commands`.
+///
+/// The keys are stored in a `HashMap`, no server is involved. Errors can be
+/// scripted with `fail_next`, and latency added to every call with
+/// `set_latency`, to exercise the error handling and the timeouts of the code
+/// under test. Expirations and latency follow the Tokio clock, so they can be
+/// driven with `tokio::time::pause` and `advance`.
+///
+/// Clones share the same store and script, so a test can keep a clone to
+/// script and inspect the client owned by the code under test.
+///
+/// Only available with the `test-util` feature.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::clients::{Commands, MockClient};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut client = MockClient::new();
+///
+///     client.set("foo", "bar".into()).await.unwrap();
+///     assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+///
+///     client.fail_next("get", "ERR injected");
+///     let err = client.get("foo").await.unwrap_err();
+///     assert_eq!("ERR injected", err.to_string());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClient {
+    shared: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    entries: HashMap<String, MockEntry>,
+
+    /// Messages published, per channel, in order.
+    published: HashMap<String, Vec<Bytes>>,
+
+    /// Errors returned by the next calls, per command name.
+    errors: HashMap<String, VecDeque<String>>,
+
+    /// Time every call waits before running.
+    latency: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct MockEntry {
+    data: Bytes,
+
+    expires_at: Option<Instant>,
+}
+
+impl MockClient {
+    /// Create an empty `MockClient`.
+    pub fn new() -> MockClient {
+        MockClient::default()
+    }
+
+    /// Make the next call of `command`, the name of a method of `Commands`
+    /// such as `"get"` or `"set_expires"`, fail with `message` instead of
+    /// running. Scripting several errors for the same command fails as many
+    /// calls, in order.
+    pub fn fail_next(&self, command: &str, message: impl ToString) {
+        let mut state = self.shared.lock().unwrap();
+        state
+            .errors
+            .entry(command.to_lowercase())
+            .or_default()
+            .push_back(message.to_string());
+    }
+
+    /// Make every call wait for `latency` before running, or remove the
+    /// latency with `None`. Scripted errors are returned after the latency as
+    /// well.
+    pub fn set_latency(&self, latency: Option<Duration>) {
+        self.shared.lock().unwrap().latency = latency;
+    }
+
+    /// Returns the messages published to `channel`, in order.
+    pub fn published(&self, channel: &str) -> Vec<Bytes> {
+        let state = self.shared.lock().unwrap();
+        state.published.get(channel).cloned().unwrap_or_default()
+    }
+
+    /// Waits for the latency, then returns the next error scripted for
+    /// `command`, if any.
+    async fn call(&self, command: &'static str) -> crate::Result<()> {
+        let latency = self.shared.lock().unwrap().latency;
+
+        if let Some(latency) = latency {
+            time::sleep(latency).await;
+        }
+
+        let mut state = self.shared.lock().unwrap();
+
+        match state.errors.get_mut(command).and_then(VecDeque::pop_front) {
+            Some(message) => Err(message.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn insert(&self, key: &str, data: Bytes, expiration: Option<Duration>) {
+        let expires_at = expiration.map(|expiration| Instant::now() + expiration);
+
+        let mut state = self.shared.lock().unwrap();
+        state.entries.insert(key.to_string(), MockEntry { data, expires_at });
+    }
+
+    /// Removes `key` if it has expired, and returns whether it exists.
+    fn purge(state: &mut MockState, key: &str) -> bool {
+        let expired = match state.entries.get(key) {
+            Some(entry) => entry.expires_at.map(|when| when <= Instant::now()).unwrap_or(false),
+            None => return false,
+        };
+
+        if expired {
+            state.entries.remove(key);
+        }
+
+        !expired
+    }
+}
+
+impl Commands for MockClient {
+    fn get<'a>(&'a mut self, key: &'a str) -> CommandFuture<'a, Option<Bytes>> {
+        Box::pin(async move {
+            self.call("get").await?;
+
+            let mut state = self.shared.lock().unwrap();
+
+            if !MockClient::purge(&mut state, key) {
+                return Ok(None);
+            }
+
+            Ok(state.entries.get(key).map(|entry| entry.data.clone()))
+        })
+    }
+
+    fn set<'a>(&'a mut self, key: &'a str, value: Bytes) -> CommandFuture<'a, ()> {
+        Box::pin(async move {
+            self.call("set").await?;
+            self.insert(key, value, None);
+            Ok(())
+        })
+    }
+
+    fn set_expires<'a>(
+        &'a mut self,
+        key: &'a str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> CommandFuture<'a, ()> {
+        Box::pin(async move {
+            self.call("set_expires").await?;
+            self.insert(key, value, Some(expiration));
+            Ok(())
+        })
+    }
+
+    fn del<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(async move {
+            self.call("del").await?;
+
+            let mut state = self.shared.lock().unwrap();
+
+            let removed = keys
+                .iter()
+                .filter(|key| MockClient::purge(&mut state, key) && state.entries.remove(**key).is_some())
+                .count();
+
+            Ok(removed as u64)
+        })
+    }
+
+    fn exists<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(async move {
+            self.call("exists").await?;
+
+            let mut state = self.shared.lock().unwrap();
+
+            let present = keys
+                .iter()
+                .filter(|key| MockClient::purge(&mut state, key))
+                .count();
+
+            Ok(present as u64)
+        })
+    }
+
+    fn publish<'a>(&'a mut self, channel: &'a str, message: Bytes) -> CommandFuture<'a, u64> {
+        Box::pin(async move {
+            self.call("publish").await?;
+
+            let mut state = self.shared.lock().unwrap();
+            state.published.entry(channel.to_string()).or_default().push(message);
+
+            // 没有订阅者
+            Ok(0)
+        })
+    }
+
+    fn ping(&mut self, msg: Option<Bytes>) -> CommandFuture<'_, Bytes> {
+        Box::pin(async move {
+            self.call("ping").await?;
+            Ok(msg.unwrap_or_else(|| Bytes::from_static(b"PONG")))
+        })
+    }
+}
@@ -1,8 +1,76 @@
+//! Clients for the server.
+//!
+//! `Client` is the asynchronous client the others are built on. The
+//! `BlockingClient` wraps it with its own runtime and is only available with
+//! the `blocking` feature, enabled by default. The `ReconnectingClient`
+//! survives the restarts of the server, and the `ShardedClient` spreads the
+//! keys across several servers.
+//!
+//! Code written against the `Commands` trait runs on any of the clients, and
+//! can be unit-tested with the in-memory `MockClient` of the `test-util`
+//! feature.
+
+use bytes::Bytes;
+
+/// A message received on a subscribed channel, or on a channel matching a
+/// subscribed pattern.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub channel: String,
+    pub content: Bytes,
+
+    /// The pattern matching `channel` the message was received through, `None`
+    /// when the channel itself is subscribed to.
+    pub pattern: Option<String>,
+}
+
 mod client;
-pub use client::{Client, Message, Subscriber};
+pub use client::{Backlog, Client, ClientError, StreamSubscriber, Subscriber, ValueReader};
+
+mod pipeline;
+pub use pipeline::Pipeline;
 
+mod set_options;
+pub use set_options::{SetOptions, SetReply};
+
+#[cfg(feature = "blocking")]
 mod blocking_client;
+#[cfg(feature = "blocking")]
 pub use blocking_client::BlockingClient;
 
+mod from_frame;
+pub use from_frame::FromFrame;
+
 mod buffered_client;
-pub use buffered_client::BufferedClient;
+pub use buffered_client::{BufferedClient, BufferedStats};
+
+mod pool;
+pub use pool::{ConnectOptions, Pool, PooledClient, ResolveStrategy};
+
+mod key_commands;
+pub use key_commands::KeyCommands;
+
+mod commands;
+pub use commands::{CommandFuture, Commands};
+
+#[cfg(feature = "test-util")]
+mod mock;
+#[cfg(feature = "test-util")]
+pub use mock::MockClient;
+
+mod namespaced;
+pub use namespaced::Namespaced;
+
+mod reconnecting;
+pub use reconnecting::{ReconnectingClient, ReconnectingSubscriber, RetryPolicy};
+
+mod sharded;
+pub use sharded::ShardedClient;
+
+mod typed;
+pub use typed::{Codec, TypedError};
+
+mod modify;
+pub use modify::ModifyError;
+
+mod supervisor;
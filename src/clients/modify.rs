@@ -0,0 +1,144 @@
+//! Optimistic read-modify-write cycles built on `CAS`.
+//!
+//! `Client::modify` reads the value of a key, computes the new value with a
+//! closure and writes it back with `CAS`. When another client modified the key
+//! in between, the cycle starts over after a jittered backoff, so that
+//! contending clients do not keep retrying in lockstep.
+
+use crate::clients::Client;
+
+use bytes::Bytes;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::time;
+
+/// Error returned by `Client::modify`.
+#[derive(Debug)]
+pub enum ModifyError {
+    /// The key kept being modified by other clients and no attempt succeeded.
+    RetriesExhausted { key: String, attempts: usize },
+
+    /// The closure returned an error. The key has not been modified.
+    Aborted(crate::Error),
+
+    /// A request failed, see `Client::get` and `Client::cas`.
+    Client(crate::Error),
+}
+
+impl fmt::Display for ModifyError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModifyError::RetriesExhausted { key, attempts } => write!(
+                fmt,
+                "key '{}' was modified concurrently during {} attempts",
+                key, attempts
+            ),
+            ModifyError::Aborted(err) => write!(fmt, "modification aborted: {}", err),
+            ModifyError::Client(err) => err.fmt(fmt),
+        }
+    }
+}
+
+impl std::error::Error for ModifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModifyError::RetriesExhausted { .. } => None,
+            ModifyError::Aborted(err) | ModifyError::Client(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+impl Client {
+    /// Atomically replace the value of `key` with the value computed by `f`.
+    ///
+    /// `f` receives the current value, `None` if the key does not exist, and
+    /// returns the new value, `None` to remove the key. The new value is only
+    /// written if the key was not modified since it was read. Otherwise `f` is
+    /// called again with the new current value, after waiting for `backoff`,
+    /// doubled after every failed attempt and randomly shortened by up to a
+    /// half. `f` must therefore not have side effects.
+    ///
+    /// Returns the value written and the number of attempts it took. Fails
+    /// with `ModifyError::RetriesExhausted` after `max_retries` retries, and
+    /// with `ModifyError::Aborted` if `f` returns an error.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::Client;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     let (value, _) = client
+    ///         .modify("counter", 10, Duration::from_millis(1), |value| {
+    ///             let count: u64 = match value {
+    ///                 Some(value) => std::str::from_utf8(&value)?.parse()?,
+    ///                 None => 0,
+    ///             };
+    ///             Ok(Some((count + 1).to_string().into()))
+    ///         })
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(Some("1".into()), value);
+    /// }
+    /// ```
+    pub async fn modify<F>(
+        &mut self,
+        key: &str,
+        max_retries: usize,
+        backoff: Duration,
+        mut f: F,
+    ) -> Result<(Option<Bytes>, usize), ModifyError>
+    where
+        F: FnMut(Option<Bytes>) -> crate::Result<Option<Bytes>>,
+    {
+        let mut delay = backoff;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            let current = self.get(key).await.map_err(ModifyError::Client)?;
+            let new = f(current.clone()).map_err(ModifyError::Aborted)?;
+
+            let swapped = self
+                .cas(key, current, new.clone())
+                .await
+                .map_err(ModifyError::Client)?;
+
+            if swapped {
+                return Ok((new, attempts));
+            }
+
+            if attempts > max_retries {
+                return Err(ModifyError::RetriesExhausted {
+                    key: key.to_string(),
+                    attempts,
+                });
+            }
+
+            time::sleep(jitter(delay)).await;
+            delay = delay.saturating_mul(2);
+        }
+    }
+}
+
+/// Randomly shorten `delay` by up to a half.
+fn jitter(delay: Duration) -> Duration {
+    // `RandomState`每次创建时使用不同的随机种子，足以用于打散重试时间
+    let random = RandomState::new().build_hasher().finish();
+    let half = delay / 2;
+
+    half + half.mul_f64((random % 1024) as f64 / 1024.0)
+}
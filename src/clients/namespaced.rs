@@ -0,0 +1,220 @@
+use crate::clients::{CommandFuture, Commands, KeyCommands};
+
+use bytes::Bytes;
+use std::time::Duration;
+
+/// Separator placed between the namespace and the key when none is given.
+const DEFAULT_SEPARATOR: &str = ":";
+
+/// A client that confines all its keys to a namespace.
+///
+/// Services sharing a server can each use their own namespace without their
+/// keys colliding. Every key is prefixed with the namespace followed by the
+/// separator before being sent, and the prefix is stripped from the keys
+/// returned by `keys` and `scan`, which only ever return keys of the
+/// namespace. As every key goes through the prefixing, there is no way to
+/// address a key of another namespace through a `Namespaced` client.
+///
+/// Pub/sub uses a separate key space on the server, so channels are only
+/// prefixed when a channel prefix is set with `with_channel_prefix`.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::clients::{Client, Namespaced};
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+/// #     let addr = listener.local_addr().unwrap();
+/// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+///     let client = Client::connect(addr).await.unwrap();
+///     let mut client = Namespaced::new(client, "billing");
+///
+///     // 实际写入的key是"billing:foo"
+///     client.set("foo", "bar".into()).await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Namespaced<C> {
+    inner: C,
+
+    namespace: String,
+
+    /// Namespace of the channels, if they are prefixed.
+    channel_namespace: Option<String>,
+
+    separator: String,
+}
+
+impl<C> Namespaced<C> {
+    /// Wrap `inner` so that its keys are confined to `namespace`.
+    ///
+    /// The namespace and the key are separated by `:`.
+    pub fn new(inner: C, namespace: &str) -> Namespaced<C> {
+        Namespaced {
+            inner,
+            namespace: namespace.to_string(),
+            channel_namespace: None,
+            separator: DEFAULT_SEPARATOR.to_string(),
+        }
+    }
+
+    /// Use `separator` between the namespace and the key instead of `:`.
+    ///
+    /// The separator also applies to the channel prefix.
+    pub fn with_separator(mut self, separator: &str) -> Namespaced<C> {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Prefix the channels published to with `prefix` followed by the
+    /// separator.
+    pub fn with_channel_prefix(mut self, prefix: &str) -> Namespaced<C> {
+        self.channel_namespace = Some(prefix.to_string());
+        self
+    }
+
+    /// Returns the name of `key` as stored on the server.
+    pub fn key_name(&self, key: &str) -> String {
+        format!("{}{}", self.prefix(), key)
+    }
+
+    /// Returns the name of `channel` as seen by the server.
+    ///
+    /// This is the name to subscribe to in order to receive the messages
+    /// published through this client.
+    pub fn channel_name(&self, channel: &str) -> String {
+        match &self.channel_namespace {
+            Some(namespace) => format!("{}{}{}", namespace, self.separator, channel),
+            None => channel.to_string(),
+        }
+    }
+
+    /// Returns the wrapped client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    /// Returns the namespace followed by the separator.
+    fn prefix(&self) -> String {
+        format!("{}{}", self.namespace, self.separator)
+    }
+}
+
+impl<C: KeyCommands> Namespaced<C> {
+    /// Get the value of key within the namespace.
+    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        let key = self.key_name(key);
+        self.inner.get(&key).await
+    }
+
+    /// Set `key` within the namespace to hold the given `value`.
+    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        let key = self.key_name(key);
+        self.inner.set(&key, value).await
+    }
+
+    /// Returns all keys of the namespace matching `pattern`, without the
+    /// prefix.
+    pub async fn keys(&mut self, pattern: &str) -> crate::Result<Vec<String>> {
+        let pattern = self.pattern(Some(pattern));
+        let keys = self.inner.keys(&pattern).await?;
+        Ok(self.strip(keys))
+    }
+
+    /// Returns the next batch of keys of the namespace, without the prefix.
+    ///
+    /// The iteration is performed over the whole key space of the server, so
+    /// batches may be empty while the iteration is not complete.
+    pub async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<usize>,
+    ) -> crate::Result<(u64, Vec<String>)> {
+        let pattern = self.pattern(pattern);
+        let (cursor, keys) = self.inner.scan(cursor, Some(&pattern), count).await?;
+        Ok((cursor, self.strip(keys)))
+    }
+
+    /// Posts `message` to `channel`, prefixed with the channel prefix if any.
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        let channel = self.channel_name(channel);
+        self.inner.publish(&channel, message).await
+    }
+
+    /// Returns the pattern matching the keys of the namespace which match
+    /// `pattern`.
+    fn pattern(&self, pattern: Option<&str>) -> String {
+        // 前缀中的glob特殊字符需要被转义，否则可能匹配到其他namespace的key
+        let mut escaped = String::new();
+        for c in self.prefix().chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+
+        escaped.push_str(pattern.unwrap_or("*"));
+        escaped
+    }
+
+    /// Strips the prefix from `keys`, dropping those outside the namespace.
+    fn strip(&self, keys: Vec<String>) -> Vec<String> {
+        let prefix = self.prefix();
+
+        keys.into_iter()
+            .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+            .collect()
+    }
+}
+
+/// The keys are confined to the namespace, and the channels prefixed with the
+/// channel prefix if any, as by the inherent methods of `Namespaced`.
+impl<C: Commands> Commands for Namespaced<C> {
+    fn get<'a>(&'a mut self, key: &'a str) -> CommandFuture<'a, Option<Bytes>> {
+        let key = self.key_name(key);
+        Box::pin(async move { self.inner.get(&key).await })
+    }
+
+    fn set<'a>(&'a mut self, key: &'a str, value: Bytes) -> CommandFuture<'a, ()> {
+        let key = self.key_name(key);
+        Box::pin(async move { self.inner.set(&key, value).await })
+    }
+
+    fn set_expires<'a>(
+        &'a mut self,
+        key: &'a str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> CommandFuture<'a, ()> {
+        let key = self.key_name(key);
+        Box::pin(async move { self.inner.set_expires(&key, value, expiration).await })
+    }
+
+    fn del<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        let keys: Vec<String> = keys.iter().map(|key| self.key_name(key)).collect();
+        Box::pin(async move {
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            self.inner.del(&keys).await
+        })
+    }
+
+    fn exists<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        let keys: Vec<String> = keys.iter().map(|key| self.key_name(key)).collect();
+        Box::pin(async move {
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            self.inner.exists(&keys).await
+        })
+    }
+
+    fn publish<'a>(&'a mut self, channel: &'a str, message: Bytes) -> CommandFuture<'a, u64> {
+        let channel = self.channel_name(channel);
+        Box::pin(async move { self.inner.publish(&channel, message).await })
+    }
+
+    fn ping(&mut self, msg: Option<Bytes>) -> CommandFuture<'_, Bytes> {
+        self.inner.ping(msg)
+    }
+}
@@ -0,0 +1,112 @@
+use crate::clients::Client;
+use crate::cmd::{Del, Exists, Get, IncrBy, Ping, Publish, Set};
+use crate::Frame;
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::instrument;
+
+/// A batch of commands sent to the server in a single round trip.
+///
+/// Created by `Client::pipeline`. Each method adds a command to the batch and
+/// returns the pipeline, so that calls can be chained. Nothing is sent until
+/// `execute` is called.
+///
+/// The replies are returned as raw frames, in the order of the commands. The
+/// commands are not applied atomically: commands of other clients may run in
+/// between.
+pub struct Pipeline<'a> {
+    client: &'a mut Client,
+
+    frames: Vec<Frame>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub(crate) fn new(client: &'a mut Client) -> Pipeline<'a> {
+        Pipeline {
+            client,
+            frames: vec![],
+        }
+    }
+
+    /// Add a `GET key` command.
+    pub fn get(&mut self, key: &str) -> &mut Pipeline<'a> {
+        self.push(Get::new(key).into_frame())
+    }
+
+    /// Add a `SET key value` command.
+    pub fn set(&mut self, key: &str, value: Bytes) -> &mut Pipeline<'a> {
+        self.push(Set::new(key, value, None).into_frame())
+    }
+
+    /// Add a `SET key value PX milliseconds` command.
+    pub fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> &mut Pipeline<'a> {
+        self.push(Set::new(key, value, Some(expiration)).into_frame())
+    }
+
+    /// Add a `DEL key [key ...]` command.
+    pub fn del(&mut self, keys: &[&str]) -> &mut Pipeline<'a> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        self.push(Del::new(keys).into_frame())
+    }
+
+    /// Add an `EXISTS key [key ...]` command.
+    pub fn exists(&mut self, keys: &[&str]) -> &mut Pipeline<'a> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        self.push(Exists::new(keys).into_frame())
+    }
+
+    /// Add an `INCRBY key delta` command. A negative result is replied as a
+    /// simple string, see `Client::incr_by`.
+    pub fn incr_by(&mut self, key: &str, delta: i64) -> &mut Pipeline<'a> {
+        self.push(IncrBy::new(key, delta).into_frame())
+    }
+
+    /// Add a `PUBLISH channel message` command.
+    pub fn publish(&mut self, channel: &str, message: Bytes) -> &mut Pipeline<'a> {
+        self.push(Publish::new(channel, message).into_frame())
+    }
+
+    /// Add a `PING [message]` command.
+    pub fn ping(&mut self, msg: Option<Bytes>) -> &mut Pipeline<'a> {
+        self.push(Ping::new(msg).into_frame())
+    }
+
+    /// Add an arbitrary command. `args` holds the command name followed by
+    /// its arguments, as with `Client::query`.
+    pub fn command(&mut self, args: &[Bytes]) -> &mut Pipeline<'a> {
+        let mut frame = Frame::array();
+        for arg in args {
+            frame.push_bulk(arg.clone());
+        }
+
+        self.push(frame)
+    }
+
+    /// Returns the number of commands in the pipeline.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if no command was added to the pipeline.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Send all the commands, then read their replies.
+    ///
+    /// Returns a reply per command, in order. A command failing on the server
+    /// does not fail the pipeline: its reply is a `Frame::Error`, and the
+    /// replies of the following commands are still read. `Err` is only
+    /// returned when the connection fails, in which case it may no longer be
+    /// used, see `Client::is_poisoned`.
+    #[instrument(skip(self), fields(commands = self.frames.len()))]
+    pub async fn execute(self) -> crate::Result<Vec<Frame>> {
+        self.client.request_all(&self.frames).await
+    }
+
+    fn push(&mut self, frame: Frame) -> &mut Pipeline<'a> {
+        self.frames.push(frame);
+        self
+    }
+}
@@ -0,0 +1,342 @@
+//! Minimal Redis connection pool
+//!
+//! Keeps idle `Client` connections around so that requests do not pay the DNS
+//! and TCP handshake cost every time.
+
+use crate::clients::Client;
+
+use std::io;
+use std::net::SocketAddr;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant};
+use tracing::debug;
+
+/// How the pool resolves the address of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveStrategy {
+    /// DNS is resolved every time a connection is established.
+    PerConnection,
+
+    /// DNS is resolved once and the addresses are cached for `ttl`. Successive
+    /// connections rotate across the resolved addresses, which spreads them
+    /// across the A records of the host.
+    Cached { ttl: Duration },
+}
+
+/// Options used by a `Pool` to establish connections.
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// How the address of the server is resolved.
+    pub resolve_strategy: ResolveStrategy,
+
+    /// Maximum duration of a single connection attempt.
+    pub connect_timeout: Duration,
+
+    /// Maximum number of connections `Pool::warm_up` establishes concurrently.
+    pub warm_up_parallelism: usize,
+
+    /// Number of connections the pool keeps established. When it holds fewer,
+    /// `Pool::get` establishes the missing ones before returning. Defaults to
+    /// 0, connections are only established on demand.
+    pub min_size: usize,
+
+    /// Maximum number of connections taken from the pool at once. `Pool::get`
+    /// waits for a connection to be returned once it is reached. `None`, the
+    /// default, does not limit the connections.
+    pub max_size: Option<usize>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        ConnectOptions {
+            resolve_strategy: ResolveStrategy::PerConnection,
+            connect_timeout: Duration::from_secs(5),
+            warm_up_parallelism: 8,
+            min_size: 0,
+            max_size: None,
+        }
+    }
+}
+
+/// A pool of connections to a Redis server.
+///
+/// Connections are taken from the pool with `get` and are returned to it when
+/// the `PooledClient` is dropped. New connections are only established when
+/// no idle connection is available, or to keep `ConnectOptions::min_size`
+/// connections. At most `ConnectOptions::max_size` connections are taken at
+/// once. The pool is a handle to shared state, so cloning it is cheap.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::clients::{ConnectOptions, Pool};
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+/// #     let addr = listener.local_addr().unwrap();
+/// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+///     let options = ConnectOptions {
+///         max_size: Some(2),
+///         ..ConnectOptions::default()
+///     };
+///     let pool = Pool::new(addr, options);
+///
+///     let mut client = pool.get().await.unwrap();
+///     client.set("foo", "bar".into()).await.unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Pool {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// Address of the server, as given by the user.
+    addr: String,
+
+    options: ConnectOptions,
+
+    /// Addresses cached by `ResolveStrategy::Cached`.
+    resolved: Mutex<Option<Resolved>>,
+
+    /// Connections not currently in use.
+    idle: Mutex<Vec<Client>>,
+
+    /// Number of connections established, idle or in use.
+    size: AtomicUsize,
+
+    /// A permit per connection which may be taken at once, see
+    /// `ConnectOptions::max_size`.
+    permits: Arc<Semaphore>,
+}
+
+/// Result of a DNS resolution cached by the pool.
+struct Resolved {
+    addrs: Vec<SocketAddr>,
+
+    /// Instant at which the addresses must be resolved again.
+    expires_at: Instant,
+
+    /// Index of the address the next connection is established to.
+    next: usize,
+}
+
+/// A connection taken from a `Pool`.
+///
+/// Dereferences to `Client`. The connection is returned to the pool when the
+/// value is dropped, unless it has been poisoned.
+pub struct PooledClient {
+    /// Always `Some` until dropped.
+    client: Option<Client>,
+
+    shared: Arc<Shared>,
+
+    /// Released once the connection is returned to the pool.
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Pool {
+    /// Create a new pool of connections to the server located at `addr`.
+    ///
+    /// No connection is established until `get` or `warm_up` is called.
+    pub fn new(addr: impl ToString, options: ConnectOptions) -> Pool {
+        let permits = options.max_size.unwrap_or(Semaphore::MAX_PERMITS);
+
+        Pool {
+            shared: Arc::new(Shared {
+                addr: addr.to_string(),
+                options,
+                resolved: Mutex::new(None),
+                idle: Mutex::new(vec![]),
+                size: AtomicUsize::new(0),
+                permits: Arc::new(Semaphore::new(permits)),
+            }),
+        }
+    }
+
+    /// Establish `n` connections ahead of time and add them to the pool.
+    ///
+    /// At most `ConnectOptions::warm_up_parallelism` connections are
+    /// established concurrently, and each attempt is bounded by
+    /// `ConnectOptions::connect_timeout`. The pool never grows past
+    /// `ConnectOptions::max_size`. Failures are not fatal, the number of
+    /// connections successfully established is returned.
+    pub async fn warm_up(&self, n: usize) -> usize {
+        let n = match self.shared.options.max_size {
+            Some(max_size) => n.min(max_size.saturating_sub(self.size())),
+            None => n,
+        };
+
+        let permits = Arc::new(Semaphore::new(self.shared.options.warm_up_parallelism.max(1)));
+        let mut tasks = JoinSet::new();
+
+        for _ in 0..n {
+            let shared = self.shared.clone();
+            let permits = permits.clone();
+
+            tasks.spawn(async move {
+                // semaphore从不关闭，所以`unwrap()`是安全的
+                let _permit = permits.acquire_owned().await.unwrap();
+                shared.connect().await
+            });
+        }
+
+        let mut established = 0;
+
+        while let Some(res) = tasks.join_next().await {
+            match res {
+                Ok(Ok(client)) => {
+                    self.shared.size.fetch_add(1, Ordering::SeqCst);
+                    self.shared.idle.lock().unwrap().push(client);
+                    established += 1;
+                }
+                Ok(Err(err)) => debug!(cause = ?err, "warm up connection failed"),
+                Err(err) => debug!(cause = ?err, "warm up task failed"),
+            }
+        }
+
+        established
+    }
+
+    /// Take a connection from the pool, establishing a new one if none is
+    /// idle.
+    ///
+    /// Waits for a connection to be returned if `ConnectOptions::max_size`
+    /// connections are already taken. Connections are established first if
+    /// the pool holds fewer than `ConnectOptions::min_size`, see `warm_up`.
+    pub async fn get(&self) -> crate::Result<PooledClient> {
+        // semaphore从不关闭，所以`unwrap()`是安全的
+        let permit = self.shared.permits.clone().acquire_owned().await.unwrap();
+
+        let missing = self.shared.options.min_size.saturating_sub(self.size());
+        if missing > 0 {
+            self.warm_up(missing).await;
+        }
+
+        let idle = self.shared.idle.lock().unwrap().pop();
+
+        let client = match idle {
+            Some(client) => client,
+            None => {
+                let client = self.shared.connect().await?;
+                self.shared.size.fetch_add(1, Ordering::SeqCst);
+                client
+            }
+        };
+
+        Ok(PooledClient {
+            client: Some(client),
+            shared: self.shared.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Returns the number of idle connections in the pool.
+    pub fn idle(&self) -> usize {
+        self.shared.idle.lock().unwrap().len()
+    }
+
+    /// Returns the number of connections established by the pool, idle or
+    /// taken.
+    pub fn size(&self) -> usize {
+        self.shared.size.load(Ordering::SeqCst)
+    }
+}
+
+impl Shared {
+    /// Establish a new connection, bounded by the connect timeout.
+    async fn connect(&self) -> crate::Result<Client> {
+        let timeout = self.options.connect_timeout;
+
+        let addrs = self.resolve().await?;
+
+        match time::timeout(timeout, Client::connect(&addrs[..])).await {
+            Ok(res) => res,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "connect timed out").into()),
+        }
+    }
+
+    /// Resolve the address of the server according to the resolve strategy.
+    ///
+    /// All addresses are returned so that the connection can fall back to the
+    /// next one. In cached mode the list is rotated so that successive
+    /// connections start with a different address.
+    async fn resolve(&self) -> io::Result<Vec<SocketAddr>> {
+        let ttl = match self.options.resolve_strategy {
+            ResolveStrategy::PerConnection => {
+                return Ok(net::lookup_host(&self.addr).await?.collect());
+            }
+            ResolveStrategy::Cached { ttl } => ttl,
+        };
+
+        if let Some(addrs) = self.cached_addrs() {
+            return Ok(addrs);
+        }
+
+        // 解析时不持有锁。并发的解析只会使缓存被覆盖，这是无害的
+        let addrs: Vec<SocketAddr> = net::lookup_host(&self.addr).await?.collect();
+
+        *self.resolved.lock().unwrap() = Some(Resolved {
+            addrs,
+            expires_at: Instant::now() + ttl,
+            next: 0,
+        });
+
+        Ok(self.cached_addrs().unwrap_or_default())
+    }
+
+    /// Returns the cached addresses rotated to the next one, if the cache is
+    /// still fresh.
+    fn cached_addrs(&self) -> Option<Vec<SocketAddr>> {
+        let mut resolved = self.resolved.lock().unwrap();
+
+        let resolved = resolved
+            .as_mut()
+            .filter(|resolved| resolved.expires_at > Instant::now())?;
+
+        if resolved.addrs.is_empty() {
+            return Some(vec![]);
+        }
+
+        let start = resolved.next % resolved.addrs.len();
+        resolved.next = resolved.next.wrapping_add(1);
+
+        let mut addrs = resolved.addrs.clone();
+        addrs.rotate_left(start);
+        Some(addrs)
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        // 将连接放回连接池，供后续请求复用。被污染的连接无法再使用，直接丢弃
+        if let Some(client) = self.client.take() {
+            if client.is_poisoned() {
+                self.shared.size.fetch_sub(1, Ordering::SeqCst);
+            } else {
+                self.shared.idle.lock().unwrap().push(client);
+            }
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! A client surviving the restarts of the server.
+//!
+//! `ReconnectingClient` re-establishes its connection when the server closes
+//! it, and sends the failed command again. `ReconnectingSubscriber` does the
+//! same for a subscription, subscribing again to its channels.
+
+use crate::clients::{Client, CommandFuture, Commands, FromFrame, Message, Subscriber};
+use crate::cmd::{Del, Exists, Get, Ping, Publish, Set};
+use crate::Frame;
+
+use bytes::Bytes;
+use std::io;
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, instrument};
+
+/// How `ReconnectingClient` and `ReconnectingSubscriber` retry after the
+/// connection is lost.
+///
+/// Before the `n`th retry, the client waits for `base_delay` doubled `n - 1`
+/// times, capped at `max_delay`, then reconnects. A failed reconnection counts
+/// as a retry.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of retries of a command, after which the error is
+    /// returned.
+    pub max_retries: usize,
+
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+
+    /// Upper bound of the delay between two retries.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the `retry`th retry, starting from 1.
+    fn delay(&self, retry: usize) -> Duration {
+        let factor = 1u32 << retry.saturating_sub(1).min(31);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// A client reconnecting to the server when the connection is lost.
+///
+/// A command failing because the server closed the connection, e.g. when it
+/// restarts, is sent again on a new connection, as allowed by the
+/// `RetryPolicy`. Other errors, including the error replies of the server,
+/// are returned as is.
+///
+/// A command may have been applied by the server before the connection was
+/// lost, so a retried command may be applied twice. This is harmless for
+/// `GET` or `SET`, but a retried `PUBLISH` may deliver a message twice.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::clients::{ReconnectingClient, RetryPolicy};
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+/// #     let addr = listener.local_addr().unwrap();
+/// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+///     let mut client = ReconnectingClient::connect(addr, RetryPolicy::default()).await.unwrap();
+///
+///     client.set("foo", "bar".into()).await.unwrap();
+///     assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+/// }
+/// ```
+pub struct ReconnectingClient {
+    /// Address of the server, resolved again on every reconnection.
+    addr: String,
+
+    policy: RetryPolicy,
+
+    /// `None` while disconnected.
+    client: Option<Client>,
+}
+
+/// A subscription reconnecting to the server when the connection is lost.
+///
+/// Created by `ReconnectingClient::subscribe`. When the connection is lost,
+/// `next_message` reconnects and subscribes again to the same channels. The
+/// messages published while disconnected are not received.
+pub struct ReconnectingSubscriber {
+    addr: String,
+
+    policy: RetryPolicy,
+
+    channels: Vec<String>,
+
+    /// `None` while disconnected.
+    subscriber: Option<Subscriber>,
+}
+
+impl ReconnectingClient {
+    /// Establish a connection with the server located at `addr`.
+    ///
+    /// The first connection is not retried: an unreachable server is reported
+    /// right away.
+    pub async fn connect(addr: impl ToString, policy: RetryPolicy) -> crate::Result<ReconnectingClient> {
+        let addr = addr.to_string();
+        let client = Client::connect(&addr[..]).await?;
+
+        Ok(ReconnectingClient {
+            addr,
+            policy,
+            client: Some(client),
+        })
+    }
+
+    /// Get the value of key. See `Client::get`.
+    #[instrument(skip(self))]
+    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.request(Get::new(key).into_frame()).await
+    }
+
+    /// Set `key` to hold the given `value`. See `Client::set`.
+    #[instrument(skip(self))]
+    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, None)).await
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `expiration`. See
+    /// `Client::set_expires`.
+    #[instrument(skip(self))]
+    pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, Some(expiration))).await
+    }
+
+    /// Removes the given keys. See `Client::del`.
+    #[instrument(skip(self))]
+    pub async fn del(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        self.request(Del::new(keys).into_frame()).await
+    }
+
+    /// Returns how many of the given keys exist. See `Client::exists`.
+    #[instrument(skip(self))]
+    pub async fn exists(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        self.request(Exists::new(keys).into_frame()).await
+    }
+
+    /// Posts `message` to the given `channel`. See `Client::publish`.
+    #[instrument(skip(self))]
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        self.request(Publish::new(channel, message).into_frame()).await
+    }
+
+    /// Ping the server. See `Client::ping`.
+    #[instrument(skip(self))]
+    pub async fn ping(&mut self, msg: Option<Bytes>) -> crate::Result<Bytes> {
+        self.request(Ping::new(msg).into_frame()).await
+    }
+
+    /// Subscribe to the given channels, see `Client::subscribe`.
+    ///
+    /// The subscription is established with the same retries as a command,
+    /// and is established again whenever the connection is lost.
+    pub async fn subscribe(mut self, channels: Vec<String>) -> crate::Result<ReconnectingSubscriber> {
+        let client = self.client.take();
+
+        let mut subscriber = ReconnectingSubscriber {
+            addr: self.addr,
+            policy: self.policy,
+            channels,
+            subscriber: None,
+        };
+
+        subscriber.resubscribe(client).await?;
+
+        Ok(subscriber)
+    }
+
+    async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
+        let response: Frame = self.request(cmd.into_frame()).await?;
+
+        match response {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            // 只有在连接存在时才会收到回复
+            frame => Err(self.client.as_mut().unwrap().unexpected(frame)),
+        }
+    }
+
+    /// Send `frame` and decode its reply, reconnecting and sending it again
+    /// when the connection is lost.
+    async fn request<T: FromFrame>(&mut self, frame: Frame) -> crate::Result<T> {
+        let mut retries = 0;
+
+        loop {
+            let res = match &mut self.client {
+                Some(client) => match client.request(&frame).await {
+                    Ok(response) => return client.decode(response),
+                    Err(err) => err,
+                },
+                None => not_connected(),
+            };
+
+            if !is_disconnect(&res) || retries >= self.policy.max_retries {
+                return Err(res);
+            }
+
+            retries += 1;
+            self.client = None;
+
+            let delay = self.policy.delay(retries);
+            debug!(cause = %res, ?delay, retries, "connection lost, reconnecting");
+            time::sleep(delay).await;
+
+            // 重连失败时`client`保持为`None`，下一轮循环会再次重试
+            match Client::connect(&self.addr[..]).await {
+                Ok(client) => self.client = Some(client),
+                Err(err) => debug!(cause = %err, "reconnection failed"),
+            }
+        }
+    }
+}
+
+impl ReconnectingSubscriber {
+    /// Returns the set of channels subscribed to.
+    pub fn get_subscribed(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Receive the next message published on a subscribed channel, waiting if
+    /// necessary.
+    ///
+    /// When the connection is lost, the subscription is established again on
+    /// a new connection before waiting for the next message. An error is
+    /// returned when the retries are exhausted.
+    pub async fn next_message(&mut self) -> crate::Result<Message> {
+        loop {
+            let res = match &mut self.subscriber {
+                Some(subscriber) => match subscriber.next_message().await {
+                    Ok(Some(message)) => return Ok(message),
+                    // 服务器关闭了连接，例如在重启时
+                    Ok(None) => connection_closed(),
+                    Err(err) => err,
+                },
+                None => not_connected(),
+            };
+
+            if !is_disconnect(&res) {
+                return Err(res);
+            }
+
+            debug!(cause = %res, "subscription lost, resubscribing");
+            self.resubscribe(None).await?;
+        }
+    }
+
+    /// Subscribe to the channels on `client`, or on a new connection if
+    /// `None` or if the connection is lost, as allowed by the retry policy.
+    async fn resubscribe(&mut self, mut client: Option<Client>) -> crate::Result<()> {
+        self.subscriber = None;
+
+        let mut retries = 0;
+
+        loop {
+            let res = match client.take() {
+                Some(client) => match client.subscribe(self.channels.clone()).await {
+                    Ok(subscriber) => {
+                        self.subscriber = Some(subscriber);
+                        return Ok(());
+                    }
+                    Err(err) => err,
+                },
+                None => not_connected(),
+            };
+
+            if !is_disconnect(&res) || retries >= self.policy.max_retries {
+                return Err(res);
+            }
+
+            retries += 1;
+            time::sleep(self.policy.delay(retries)).await;
+
+            match Client::connect(&self.addr[..]).await {
+                Ok(connected) => client = Some(connected),
+                Err(err) => debug!(cause = %err, "reconnection failed"),
+            }
+        }
+    }
+}
+
+impl Commands for ReconnectingClient {
+    fn get<'a>(&'a mut self, key: &'a str) -> CommandFuture<'a, Option<Bytes>> {
+        Box::pin(ReconnectingClient::get(self, key))
+    }
+
+    fn set<'a>(&'a mut self, key: &'a str, value: Bytes) -> CommandFuture<'a, ()> {
+        Box::pin(ReconnectingClient::set(self, key, value))
+    }
+
+    fn set_expires<'a>(
+        &'a mut self,
+        key: &'a str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> CommandFuture<'a, ()> {
+        Box::pin(ReconnectingClient::set_expires(self, key, value, expiration))
+    }
+
+    fn del<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(ReconnectingClient::del(self, keys))
+    }
+
+    fn exists<'a>(&'a mut self, keys: &'a [&str]) -> CommandFuture<'a, u64> {
+        Box::pin(ReconnectingClient::exists(self, keys))
+    }
+
+    fn publish<'a>(&'a mut self, channel: &'a str, message: Bytes) -> CommandFuture<'a, u64> {
+        Box::pin(ReconnectingClient::publish(self, channel, message))
+    }
+
+    fn ping(&mut self, msg: Option<Bytes>) -> CommandFuture<'_, Bytes> {
+        Box::pin(ReconnectingClient::ping(self, msg))
+    }
+}
+
+/// Returns `true` if `err` means that the connection was lost, or could not
+/// be established, rather than that the command failed.
+fn is_disconnect(err: &crate::Error) -> bool {
+    use io::ErrorKind::*;
+
+    match err.downcast_ref::<io::Error>() {
+        Some(err) => matches!(
+            err.kind(),
+            ConnectionReset | ConnectionAborted | ConnectionRefused | BrokenPipe | UnexpectedEof | NotConnected
+        ),
+        None => false,
+    }
+}
+
+/// The error of a command issued while disconnected.
+fn not_connected() -> crate::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "not connected to the server").into()
+}
+
+/// The error of a subscription closed by the server.
+fn connection_closed() -> crate::Error {
+    io::Error::new(io::ErrorKind::ConnectionReset, "connection reset by server").into()
+}
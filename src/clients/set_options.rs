@@ -0,0 +1,122 @@
+use crate::cmd::{Set, SetCondition};
+
+use bytes::Bytes;
+use std::time::{Duration, SystemTime};
+
+/// The options of a `SET`, for `Client::set_options`.
+///
+/// Built by chaining calls from `SetOptions::new()`. The expire options, i.e.
+/// `expire`, `expire_at` and `keep_ttl`, exclude one another: the last one
+/// called wins. Likewise for `nx` and `xx`.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::clients::SetOptions;
+/// use std::time::Duration;
+///
+/// // SET key value PX 60000 NX GET
+/// let options = SetOptions::new()
+///     .expire(Duration::from_secs(60))
+///     .nx()
+///     .get();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SetOptions {
+    expire: Option<Duration>,
+
+    expire_at: Option<SystemTime>,
+
+    condition: Option<SetCondition>,
+
+    keep_ttl: bool,
+
+    get: bool,
+}
+
+/// The reply of `Client::set_options`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetReply {
+    /// The value was written.
+    Written,
+
+    /// The value was not written, because the `NX` or `XX` condition did not
+    /// hold.
+    Skipped,
+
+    /// `GET` was requested: the value previously stored at the key, or `None`
+    /// if it did not exist. With `NX` or `XX`, the value may not have been
+    /// written.
+    Previous(Option<Bytes>),
+}
+
+impl SetOptions {
+    /// Create options for a plain `SET`, which discards any time to live of
+    /// the key.
+    pub fn new() -> SetOptions {
+        SetOptions::default()
+    }
+
+    /// Expire the key after `expire`.
+    pub fn expire(mut self, expire: Duration) -> SetOptions {
+        self.expire = Some(expire);
+        self.expire_at = None;
+        self.keep_ttl = false;
+        self
+    }
+
+    /// Expire the key at the time `at`. A time in the past expires the key
+    /// immediately.
+    pub fn expire_at(mut self, at: SystemTime) -> SetOptions {
+        self.expire = None;
+        self.expire_at = Some(at);
+        self.keep_ttl = false;
+        self
+    }
+
+    /// Retain the time to live of the key.
+    pub fn keep_ttl(mut self) -> SetOptions {
+        self.expire = None;
+        self.expire_at = None;
+        self.keep_ttl = true;
+        self
+    }
+
+    /// Only set the key if it does not already exist.
+    pub fn nx(mut self) -> SetOptions {
+        self.condition = Some(SetCondition::Nx);
+        self
+    }
+
+    /// Only set the key if it already exists.
+    pub fn xx(mut self) -> SetOptions {
+        self.condition = Some(SetCondition::Xx);
+        self
+    }
+
+    /// Return the value previously stored at the key.
+    pub fn get(mut self) -> SetOptions {
+        self.get = true;
+        self
+    }
+
+    /// Build the `Set` command setting `key` to `value` with these options.
+    pub(crate) fn into_cmd(self, key: &str, value: Bytes) -> Set {
+        let mut cmd = Set::new(key, value, self.expire);
+
+        if let Some(at) = self.expire_at {
+            cmd = cmd.with_expire_at(at);
+        }
+        if let Some(condition) = self.condition {
+            cmd = cmd.with_condition(condition);
+        }
+        if self.keep_ttl {
+            cmd = cmd.with_keep_ttl();
+        }
+        if self.get {
+            cmd = cmd.with_get();
+        }
+
+        cmd
+    }
+}
@@ -0,0 +1,152 @@
+use crate::clients::{Client, ClientError, FromFrame};
+use crate::cluster::{crc16_hash_slot, SLOT_COUNT};
+use crate::{Command, Frame};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::net::ToSocketAddrs;
+use tracing::{debug, instrument};
+
+/// A client spreading the keys across several servers.
+///
+/// Each key is stored on one of the servers, the shard, chosen from its hash
+/// slot as computed by Redis cluster, see `cluster::crc16_hash_slot`. The
+/// slots are split into contiguous ranges of the same size, one per server,
+/// in the order the servers are given. The same servers must therefore always
+/// be given in the same order.
+///
+/// A command touching several keys is only sent if all of them are on the
+/// same shard, otherwise it fails with `ClientError::CrossShard`. Keys sharing
+/// a hash tag, like `{user1000}.following` and `{user1000}.followers`, are on
+/// the same slot, and so on the same shard.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::clients::ShardedClient;
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #     let mut addrs = vec![];
+/// #     for _ in 0..2 {
+/// #         let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+/// #         addrs.push(listener.local_addr().unwrap());
+/// #         tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+/// #     }
+///     let mut client = ShardedClient::connect(addrs).await.unwrap();
+///
+///     client.set("{user1000}.name", "Ada".into()).await.unwrap();
+///     client.set("{user1000}.city", "London".into()).await.unwrap();
+///     assert_eq!(2, client.del(&["{user1000}.name", "{user1000}.city"]).await.unwrap());
+/// }
+/// ```
+pub struct ShardedClient {
+    /// One client per shard, in the order of their ranges of slots.
+    shards: Vec<Client>,
+}
+
+impl ShardedClient {
+    /// Connect to each of the servers at `addrs`, one per shard.
+    ///
+    /// Fails if `addrs` is empty, or if any of the servers is unreachable.
+    pub async fn connect<T: ToSocketAddrs>(addrs: impl IntoIterator<Item = T>) -> crate::Result<ShardedClient> {
+        let mut shards = vec![];
+        for addr in addrs {
+            shards.push(Client::connect(addr).await?);
+        }
+
+        ShardedClient::new(shards)
+    }
+
+    /// Create a client over established connections, one per shard.
+    ///
+    /// Fails if `shards` is empty.
+    pub fn new(shards: Vec<Client>) -> crate::Result<ShardedClient> {
+        if shards.is_empty() {
+            return Err("a sharded client needs at least one server".into());
+        }
+
+        Ok(ShardedClient { shards })
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the index of the shard `key` is stored on.
+    pub fn shard_of(&self, key: &[u8]) -> usize {
+        crc16_hash_slot(key) as usize * self.shards.len() / SLOT_COUNT as usize
+    }
+
+    /// Returns the shard all the `keys` are stored on, the first one if there
+    /// are no keys.
+    fn route<'a>(&self, keys: impl IntoIterator<Item = &'a [u8]>) -> crate::Result<usize> {
+        let mut shards = keys.into_iter().map(|key| self.shard_of(key));
+
+        let first = shards.next().unwrap_or(0);
+        if shards.any(|shard| shard != first) {
+            return Err(ClientError::CrossShard.into());
+        }
+
+        Ok(first)
+    }
+
+    /// Get the value of key. See `Client::get`.
+    #[instrument(skip(self))]
+    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        let shard = self.shard_of(key.as_bytes());
+        self.shards[shard].get(key).await
+    }
+
+    /// Set `key` to hold the given `value`. See `Client::set`.
+    #[instrument(skip(self))]
+    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        let shard = self.shard_of(key.as_bytes());
+        self.shards[shard].set(key, value).await
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `expiration`. See
+    /// `Client::set_expires`.
+    #[instrument(skip(self))]
+    pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
+        let shard = self.shard_of(key.as_bytes());
+        self.shards[shard].set_expires(key, value, expiration).await
+    }
+
+    /// Removes the given keys, which must be on the same shard. See
+    /// `Client::del`.
+    #[instrument(skip(self))]
+    pub async fn del(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let shard = self.route(keys.iter().map(|key| key.as_bytes()))?;
+        self.shards[shard].del(keys).await
+    }
+
+    /// Returns how many of the given keys exist. The keys must be on the same
+    /// shard. See `Client::exists`.
+    #[instrument(skip(self))]
+    pub async fn exists(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let shard = self.route(keys.iter().map(|key| key.as_bytes()))?;
+        self.shards[shard].exists(keys).await
+    }
+
+    /// Send an arbitrary command to the shard of its keys, and decode the
+    /// reply as a `T`. See `Client::query`.
+    ///
+    /// The keys are found with `Command::keys`, so the command must be one
+    /// the server supports. A command without keys, like `PING`, is sent to
+    /// the first shard. Commands relying on the state of the connection, like
+    /// `MULTI`, are not supported, as the next ones may be sent to another
+    /// shard.
+    #[instrument(skip(self))]
+    pub async fn query<T: FromFrame>(&mut self, args: &[Bytes]) -> crate::Result<T> {
+        let frame = Frame::Array(args.iter().cloned().map(Frame::Bulk).collect());
+        let cmd = Command::from_frame(frame)?;
+
+        let shard = self.route(cmd.keys())?;
+
+        debug!(shard, command = cmd.get_name());
+
+        self.shards[shard].query(args).await
+    }
+}
@@ -0,0 +1,98 @@
+//! Supervision of the background tasks spawned by the clients.
+//!
+//! A client forwarding its requests to a task stops answering if that task
+//! panics: the requests fail with a closed channel error, or wait forever,
+//! without telling why. `TaskSupervisor` keeps the handle of the task, aborts
+//! it when dropped, and records the panic so that the client can return it.
+
+use crate::clients::ClientError;
+
+use std::any::Any;
+use std::future::Future;
+use tokio::sync::watch;
+use tokio::task::AbortHandle;
+
+/// Owner of a background task of a client.
+///
+/// The task is aborted when the supervisor is dropped. A panic of the task is
+/// caught by a second, small task waiting for it to end, and reported by
+/// `failure`.
+#[derive(Debug)]
+pub(crate) struct TaskSupervisor {
+    task: AbortHandle,
+
+    state: watch::Receiver<TaskState>,
+}
+
+#[derive(Debug)]
+enum TaskState {
+    Running,
+
+    /// The task returned, or was aborted.
+    Finished,
+
+    /// The task panicked, with the message of the panic.
+    Panicked(String),
+}
+
+impl TaskSupervisor {
+    /// Spawn `task` on the runtime, supervised by the returned value.
+    pub(crate) fn spawn<F>(task: F) -> TaskSupervisor
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (tx, state) = watch::channel(TaskState::Running);
+
+        let handle = tokio::spawn(task);
+        let task = handle.abort_handle();
+
+        // 任务被abort时`handle`也会返回，这个任务随之结束
+        tokio::spawn(async move {
+            let state = match handle.await {
+                Err(err) if err.is_panic() => TaskState::Panicked(panic_message(err.into_panic())),
+                _ => TaskState::Finished,
+            };
+            let _ = tx.send(state);
+        });
+
+        TaskSupervisor { task, state }
+    }
+
+    /// Returns the error to report for a request the task failed to answer,
+    /// `None` if the task did not panic.
+    ///
+    /// Waits for the task to end, so it must only be called once the task
+    /// stopped answering, for example when the channel used to reach it is
+    /// closed. This way a panic is never missed because it is being recorded
+    /// while the request fails.
+    pub(crate) async fn failure(&self) -> Option<ClientError> {
+        let mut state = self.state.clone();
+
+        let failure = match state.wait_for(|state| !matches!(state, TaskState::Running)).await {
+            Ok(state) => match &*state {
+                TaskState::Panicked(reason) => Some(ClientError::BackgroundTaskPanicked(reason.clone())),
+                _ => None,
+            },
+            // 运行时正在关闭，监视任务没有机会记录结果
+            Err(_) => None,
+        };
+        failure
+    }
+}
+
+impl Drop for TaskSupervisor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Returns the message given to `panic!`.
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => "non-string panic payload".to_string(),
+        },
+    }
+}
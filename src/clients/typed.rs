@@ -0,0 +1,129 @@
+//! Typed values stored through a `Codec`.
+//!
+//! Values are plain bytes on the server. Storing Rust structs requires
+//! encoding them on write and decoding them on read. Instead of each caller
+//! hand-rolling this, `Client::set_typed` and `Client::get_typed` do it with
+//! a `Codec`, and report a value which cannot be decoded separately from a
+//! missing key.
+
+use crate::clients::Client;
+
+use bytes::Bytes;
+use std::fmt;
+
+/// Encoding of values of type `T` into the bytes stored on the server.
+///
+/// A codec is usually a unit struct implementing `Codec` for all the types
+/// of a serialization format, and is passed as a type parameter:
+/// `client.get_typed::<MyCodec, _>("key")`.
+pub trait Codec<T> {
+    /// Encode `value` into the bytes to store.
+    fn encode(value: &T) -> crate::Result<Bytes>;
+
+    /// Decode a value from the stored bytes.
+    fn decode(src: &[u8]) -> crate::Result<T>;
+}
+
+/// Error returned when reading a typed value.
+#[derive(Debug)]
+pub enum TypedError {
+    /// The key does not exist.
+    Missing { key: String },
+
+    /// The stored value could not be decoded. The value may have been stored
+    /// by a different version of the type or by another codec.
+    Decode { key: String, source: crate::Error },
+
+    /// The request failed, see `Client::get`.
+    Client(crate::Error),
+}
+
+impl TypedError {
+    /// Returns `true` if the key does not exist.
+    pub fn is_missing(&self) -> bool {
+        matches!(self, TypedError::Missing { .. })
+    }
+}
+
+impl fmt::Display for TypedError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypedError::Missing { key } => write!(fmt, "key '{}' does not exist", key),
+            TypedError::Decode { key, source } => write!(
+                fmt,
+                "failed to decode the value of key '{}' (stored by a different schema?): {}",
+                key, source
+            ),
+            TypedError::Client(err) => err.fmt(fmt),
+        }
+    }
+}
+
+impl std::error::Error for TypedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TypedError::Missing { .. } => None,
+            TypedError::Decode { source, .. } => Some(source.as_ref()),
+            TypedError::Client(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+impl Client {
+    /// Set `key` to hold `value`, encoded with the codec `C`.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```
+    /// use my_mini_redis::clients::{Client, Codec};
+    /// use bytes::Bytes;
+    ///
+    /// struct Decimal;
+    ///
+    /// impl Codec<u32> for Decimal {
+    ///     fn encode(value: &u32) -> my_mini_redis::Result<Bytes> {
+    ///         Ok(value.to_string().into())
+    ///     }
+    ///
+    ///     fn decode(src: &[u8]) -> my_mini_redis::Result<u32> {
+    ///         Ok(std::str::from_utf8(src)?.parse()?)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    /// #     let addr = listener.local_addr().unwrap();
+    /// #     tokio::spawn(my_mini_redis::server::run(listener, std::future::pending::<()>()));
+    ///     let mut client = Client::connect(addr).await.unwrap();
+    ///
+    ///     client.set_typed::<Decimal, _>("foo", &42).await.unwrap();
+    ///     let value: u32 = client.get_typed::<Decimal, _>("foo").await.unwrap();
+    ///     assert_eq!(42, value);
+    /// }
+    /// ```
+    pub async fn set_typed<C: Codec<T>, T>(&mut self, key: &str, value: &T) -> crate::Result<()> {
+        let value = C::encode(value)?;
+        self.set(key, value).await
+    }
+
+    /// Get the value of `key`, decoded with the codec `C`.
+    ///
+    /// Returns `TypedError::Missing` if the key does not exist and
+    /// `TypedError::Decode` if the stored value cannot be decoded, both
+    /// carrying the name of the key.
+    pub async fn get_typed<C: Codec<T>, T>(&mut self, key: &str) -> Result<T, TypedError> {
+        let value = match self.get(key).await {
+            Ok(Some(value)) => value,
+            Ok(None) => return Err(TypedError::Missing { key: key.to_string() }),
+            Err(err) => return Err(TypedError::Client(err)),
+        };
+
+        C::decode(&value).map_err(|source| TypedError::Decode {
+            key: key.to_string(),
+            source,
+        })
+    }
+}
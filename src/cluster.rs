@@ -0,0 +1,87 @@
+//! Hash slots of the Redis cluster specification.
+//!
+//! The server has no cluster mode, but computing the slot of a key the way a
+//! Redis cluster does lets keys be spread across several servers, e.g. with
+//! `clients::ShardedClient`, and keeps them on the same slots if the data is
+//! later moved to a real cluster.
+
+/// Number of hash slots of a Redis cluster.
+pub const SLOT_COUNT: u16 = 16384;
+
+/// Lookup table of the CRC16 variant used by Redis cluster (XMODEM:
+/// polynomial `0x1021`, initial value `0`), indexed by the high byte of the
+/// CRC xor the next input byte.
+const CRC16_TABLE: [u16; 256] = crc16_table();
+
+const fn crc16_table() -> [u16; 256] {
+    let mut table = [0u16; 256];
+
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// Computes the CRC16 of `data` used by Redis cluster.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::cluster::crc16;
+///
+/// // The reference value of the specification
+/// assert_eq!(0x31c3, crc16(b"123456789"));
+/// ```
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &byte| {
+        (crc << 8) ^ CRC16_TABLE[((crc >> 8) as u8 ^ byte) as usize]
+    })
+}
+
+/// Returns the part of `key` which is hashed to find its slot.
+///
+/// When the key contains a `{` followed by a `}` with at least one byte in
+/// between, only the bytes between the first `{` and the first `}` after it,
+/// the hash tag, are hashed. Otherwise the whole key is. Keys sharing a hash
+/// tag, like `{user1000}.following` and `{user1000}.followers`, are thus in
+/// the same slot.
+pub fn hash_tag(key: &[u8]) -> &[u8] {
+    let Some(open) = key.iter().position(|&byte| byte == b'{') else {
+        return key;
+    };
+
+    match key[open + 1..].iter().position(|&byte| byte == b'}') {
+        // `{}`为空时哈希整个key
+        Some(0) | None => key,
+        Some(len) => &key[open + 1..open + 1 + len],
+    }
+}
+
+/// Returns the hash slot of `key`, between `0` and `SLOT_COUNT - 1`, as
+/// computed by Redis cluster.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::cluster::crc16_hash_slot;
+///
+/// assert_eq!(12182, crc16_hash_slot(b"foo"));
+/// assert_eq!(
+///     crc16_hash_slot(b"{user1000}.following"),
+///     crc16_hash_slot(b"{user1000}.followers"),
+/// );
+/// ```
+pub fn crc16_hash_slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key)) % SLOT_COUNT
+}
@@ -0,0 +1,131 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Atomically replace the value of `key` if it still holds an expected value.
+///
+/// This allows clients to perform read-modify-write cycles without holding
+/// anything on the server: the value is read with `GET`, modified, and written
+/// back with `CAS`, which fails if another client modified the key in
+/// between. The comparison is performed on the value itself.
+///
+/// Either value may be nil: a nil `expected` requires the key to be missing,
+/// and a nil `new` removes the key. The reply is 1 if the value was replaced
+/// and 0 otherwise.
+#[derive(Debug)]
+pub struct Cas {
+    key: String,
+
+    expected: Option<Bytes>,
+
+    new: Option<Bytes>,
+}
+
+impl Cas {
+    /// Create a new `Cas` command which replaces the value of `key` with `new`
+    /// if it is `expected`.
+    pub fn new(key: impl ToString, expected: Option<Bytes>, new: Option<Bytes>) -> Cas {
+        Cas {
+            key: key.to_string(),
+            expected,
+            new,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the expected value, `None` if the key is expected to be missing
+    pub fn expected(&self) -> Option<&Bytes> {
+        self.expected.as_ref()
+    }
+
+    /// Get the new value, `None` if the key is removed
+    pub fn new_value(&self) -> Option<&Bytes> {
+        self.new.as_ref()
+    }
+
+    /// Parse a `Cas` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `CAS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Cas` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries. `expected` and `new`
+    /// may be nil bulk strings.
+    ///
+    /// ```text
+    /// CAS key expected new
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Cas> {
+        let key = parse.next_string()?;
+
+        let expected = next_value(parse)?;
+
+        let new = next_value(parse)?;
+
+        Ok(Cas { key, expected, new })
+    }
+
+    /// Apply the `Cas` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Cas` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        let swapped = view.compare_and_swap(&self.key, self.expected.as_ref(), self.new);
+
+        Frame::Integer(swapped as u64)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Cas` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        // 数组中的nil可以直接被编码，用`Frame::Null`表示没有值
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from("cas".as_bytes())),
+            Frame::Bulk(Bytes::from(self.key.into_bytes())),
+            self.expected.map(Frame::Bulk).unwrap_or(Frame::Null),
+            self.new.map(Frame::Bulk).unwrap_or(Frame::Null),
+        ])
+    }
+}
+
+/// Read a value which may be nil.
+fn next_value(parse: &mut Parse) -> crate::Result<Option<Bytes>> {
+    match parse.next()? {
+        Frame::Null => Ok(None),
+        Frame::Bulk(data) => Ok(Some(data)),
+        Frame::Simple(s) => Ok(Some(Bytes::from(s))),
+        frame => Err(format!("protocol error; expected bulk frame but got {:?}", frame).into()),
+    }
+}
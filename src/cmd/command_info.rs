@@ -0,0 +1,192 @@
+use crate::cmd::COMMANDS;
+use crate::{Connection, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Describe the commands supported by the server.
+///
+/// Client libraries call `COMMAND` on connect to learn the capabilities of the
+/// server. The `COUNT` and `DOCS` subcommands are supported, and `COMMAND`
+/// without a subcommand behaves as `COMMAND DOCS`.
+///
+/// `DOCS` replies with an array of command names each followed by an array of
+/// fields describing the command:
+///
+/// ```text
+/// 1) "get"
+/// 2) 1) "arity"
+///    2) (integer) 2
+///    3) "variadic"
+///    4) (integer) 0
+/// ```
+///
+/// The arity is the number of arguments, including the name of the command.
+/// Integers are unsigned in the protocol spoken by the server, so instead of
+/// a negative arity as in Redis, commands taking at least `arity` arguments
+/// are flagged as `variadic`.
+#[derive(Debug)]
+pub struct CommandInfo {
+    subcommand: CommandInfoSubcommand,
+}
+
+#[derive(Debug)]
+enum CommandInfoSubcommand {
+    /// COMMAND COUNT
+    Count,
+
+    /// COMMAND DOCS [name ...], describing all the commands when no name is
+    /// given.
+    Docs(Vec<String>),
+
+    /// Any subcommand that is not supported. An error is returned to the
+    /// client instead of closing the connection.
+    Unknown(String),
+}
+
+impl CommandInfo {
+    /// Create a new `CommandInfo` command which fetches the number of
+    /// supported commands.
+    pub fn count() -> CommandInfo {
+        CommandInfo {
+            subcommand: CommandInfoSubcommand::Count,
+        }
+    }
+
+    /// Create a new `CommandInfo` command which describes the commands named
+    /// `names`, or all of them if `names` is empty.
+    pub fn docs(names: Vec<String>) -> CommandInfo {
+        CommandInfo {
+            subcommand: CommandInfoSubcommand::Docs(names),
+        }
+    }
+
+    /// Parse a `CommandInfo` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `COMMAND` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `CommandInfo` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing an optional subcommand and its
+    /// arguments.
+    ///
+    /// ```text
+    /// COMMAND
+    /// COMMAND COUNT
+    /// COMMAND DOCS [name ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<CommandInfo> {
+        use ParseError::EndOfStream;
+
+        let subcommand = match parse.next_string() {
+            Ok(subcommand) => subcommand,
+            Err(EndOfStream) => return Ok(CommandInfo::docs(vec![])),
+            Err(err) => return Err(err.into()),
+        };
+
+        let subcommand = match &subcommand.to_uppercase()[..] {
+            "COUNT" => CommandInfoSubcommand::Count,
+            "DOCS" => {
+                let mut names = vec![];
+
+                loop {
+                    match parse.next_string() {
+                        Ok(name) => names.push(name.to_lowercase()),
+                        Err(EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                CommandInfoSubcommand::Docs(names)
+            }
+            _ => {
+                // 忽略剩下的参数，以便`finish()`不会失败
+                while parse.next().is_ok() {}
+                CommandInfoSubcommand::Unknown(subcommand)
+            }
+        };
+
+        Ok(CommandInfo { subcommand })
+    }
+
+    /// Apply the `CommandInfo` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute();
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `CommandInfo` command, and returns the reply.
+    ///
+    /// The reply does not depend on the keyspace, so the command can be queued
+    /// in a transaction as well.
+    pub(crate) fn execute(self) -> Frame {
+        match self.subcommand {
+            CommandInfoSubcommand::Count => Frame::Integer(COMMANDS.len() as u64),
+            CommandInfoSubcommand::Docs(names) => {
+                // 不存在的命令被忽略，和 Redis 一样
+                let commands = COMMANDS
+                    .iter()
+                    .filter(|(name, ..)| names.is_empty() || names.iter().any(|n| n == name));
+
+                let mut docs = vec![];
+                for (name, arity, ..) in commands {
+                    let mut doc = Frame::array();
+                    doc.push_bulk(Bytes::from("arity".as_bytes()));
+                    doc.push_int(arity.unsigned_abs());
+                    doc.push_bulk(Bytes::from("variadic".as_bytes()));
+                    doc.push_int((*arity < 0) as u64);
+
+                    docs.push(Frame::Bulk(Bytes::from(name.as_bytes())));
+                    docs.push(doc);
+                }
+
+                Frame::Array(docs)
+            }
+            CommandInfoSubcommand::Unknown(name) => {
+                Frame::Error(format!("ERR unknown subcommand '{}'", name))
+            }
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `CommandInfo` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("command".as_bytes()));
+        match self.subcommand {
+            CommandInfoSubcommand::Count => {
+                frame.push_bulk(Bytes::from("count".as_bytes()));
+            }
+            CommandInfoSubcommand::Docs(names) => {
+                frame.push_bulk(Bytes::from("docs".as_bytes()));
+                for name in names {
+                    frame.push_bulk(Bytes::from(name.into_bytes()));
+                }
+            }
+            CommandInfoSubcommand::Unknown(name) => {
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+        }
+        frame
+    }
+}
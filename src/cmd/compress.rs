@@ -0,0 +1,133 @@
+use crate::connection::DEFAULT_COMPRESSION_THRESHOLD;
+use crate::{Connection, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Negotiate the compression of the bulk strings sent on the connection.
+///
+/// Only `lz4` is supported. Once the server replied `OK`, both sides write the
+/// bulk strings of at least `threshold` bytes LZ4 compressed, and accept
+/// compressed ones. `none` goes back to plain bulk strings. The reply itself
+/// is never compressed.
+///
+/// A compressed bulk string is encoded like a bulk string, with `&` instead of
+/// `$`, its payload being the length of the original data, a little-endian
+/// `u32`, followed by an LZ4 block:
+///
+/// ```text
+/// &<length>\r\n<original length><lz4 block>\r\n
+/// ```
+///
+/// A server which does not know the command replies with an error, so a
+/// client can offer compression to any server and go on without it.
+#[derive(Debug)]
+pub struct Compress {
+    algorithm: Algorithm,
+}
+
+#[derive(Debug)]
+enum Algorithm {
+    None,
+
+    /// LZ4, with the threshold
+    Lz4(usize),
+
+    /// Any other algorithm, rejected when the command is applied
+    Unsupported(String),
+}
+
+impl Compress {
+    /// Create a new `Compress` command enabling LZ4 compression of the bulk
+    /// strings of at least `threshold` bytes.
+    pub fn lz4(threshold: usize) -> Compress {
+        Compress {
+            algorithm: Algorithm::Lz4(threshold),
+        }
+    }
+
+    /// Create a new `Compress` command disabling compression.
+    pub fn none() -> Compress {
+        Compress { algorithm: Algorithm::None }
+    }
+
+    /// Parse a `Compress` instance from a received frame.
+    ///
+    /// The `COMPRESS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `COMPRESS`, the algorithm and, for
+    /// `lz4`, an optional threshold defaulting to
+    /// `DEFAULT_COMPRESSION_THRESHOLD` bytes.
+    ///
+    /// ```text
+    /// COMPRESS lz4 [threshold]
+    /// COMPRESS none
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Compress> {
+        let algorithm = parse.next_token()?;
+
+        if algorithm.is("none") {
+            return Ok(Compress::none());
+        }
+
+        if !algorithm.is("lz4") {
+            let algorithm = Algorithm::Unsupported(String::from_utf8_lossy(algorithm.as_bytes()).into_owned());
+            return Ok(Compress { algorithm });
+        }
+
+        match parse.next_int() {
+            Ok(threshold) => Ok(Compress::lz4(threshold.try_into()?)),
+            Err(ParseError::EndOfStream) => Ok(Compress::lz4(DEFAULT_COMPRESSION_THRESHOLD)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Apply the `Compress` command to the connection.
+    ///
+    /// The response is written to `dst` before compression is enabled, so
+    /// that the client can read it whether it knows about compression or not.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let threshold = match self.algorithm {
+            Algorithm::None => None,
+            Algorithm::Lz4(threshold) => Some(threshold),
+            Algorithm::Unsupported(algorithm) => {
+                let response = Frame::Error(format!("ERR unsupported compression algorithm '{}'", algorithm));
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        // 之后的frame才按照新的设置编码
+        dst.set_compression(threshold);
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Compress` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("compress".as_bytes()));
+        match self.algorithm {
+            Algorithm::None => frame.push_bulk(Bytes::from("none".as_bytes())),
+            Algorithm::Lz4(threshold) => {
+                frame.push_bulk(Bytes::from("lz4".as_bytes()));
+                frame.push_int(threshold as u64);
+            }
+            Algorithm::Unsupported(algorithm) => frame.push_bulk(Bytes::from(algorithm.into_bytes())),
+        }
+        frame
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Read and change the runtime configuration of the server.
+///
+/// Only the `hotkeys` parameter is supported. It enables hot key tracking,
+/// reported by `HOTKEYS`, when set to `yes`, and disables it when set to `no`.
+///
+/// `CONFIG GET` replies with an array of the parameter name followed by its
+/// value, empty if the parameter is unknown.
+#[derive(Debug)]
+pub struct Config {
+    subcommand: ConfigSubcommand,
+}
+
+#[derive(Debug)]
+enum ConfigSubcommand {
+    /// CONFIG GET parameter
+    Get(String),
+
+    /// CONFIG SET parameter value
+    Set(String, String),
+
+    /// Any subcommand that is not supported. An error is returned to the
+    /// client instead of closing the connection.
+    Unknown(String),
+}
+
+impl Config {
+    /// Create a new `Config` command which fetches the value of `parameter`.
+    pub fn get(parameter: impl ToString) -> Config {
+        Config {
+            subcommand: ConfigSubcommand::Get(parameter.to_string()),
+        }
+    }
+
+    /// Create a new `Config` command which sets `parameter` to `value`.
+    pub fn set(parameter: impl ToString, value: impl ToString) -> Config {
+        Config {
+            subcommand: ConfigSubcommand::Set(parameter.to_string(), value.to_string()),
+        }
+    }
+
+    /// Parse a `Config` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `CONFIG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Config` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a subcommand and its arguments.
+    ///
+    /// ```text
+    /// CONFIG GET parameter
+    /// CONFIG SET parameter value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Config> {
+        let subcommand = parse.next_string()?;
+
+        let subcommand = match &subcommand.to_uppercase()[..] {
+            "GET" => ConfigSubcommand::Get(parse.next_string()?),
+            "SET" => ConfigSubcommand::Set(parse.next_string()?, parse.next_string()?),
+            _ => {
+                // 忽略剩下的参数，以便`finish()`不会失败
+                while parse.next().is_ok() {}
+                ConfigSubcommand::Unknown(subcommand)
+            }
+        };
+
+        Ok(Config { subcommand })
+    }
+
+    /// Apply the `Config` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            ConfigSubcommand::Get(parameter) => {
+                let mut response = Frame::array();
+
+                if parameter.to_lowercase() == "hotkeys" {
+                    let value = if db.hotkeys_tracking() { "yes" } else { "no" };
+                    response.push_bulk(Bytes::from("hotkeys".as_bytes()));
+                    response.push_bulk(Bytes::from(value.as_bytes()));
+                }
+
+                response
+            }
+            ConfigSubcommand::Set(parameter, value) if parameter.to_lowercase() == "hotkeys" => {
+                match &value.to_lowercase()[..] {
+                    "yes" => {
+                        db.set_hotkeys_tracking(true);
+                        Frame::Simple("OK".to_string())
+                    }
+                    "no" => {
+                        db.set_hotkeys_tracking(false);
+                        Frame::Simple("OK".to_string())
+                    }
+                    _ => Frame::Error(format!(
+                        "ERR invalid argument '{}' for CONFIG SET '{}'",
+                        value, parameter
+                    )),
+                }
+            }
+            ConfigSubcommand::Set(parameter, _) => {
+                Frame::Error(format!("ERR unknown option '{}'", parameter))
+            }
+            ConfigSubcommand::Unknown(name) => {
+                Frame::Error(format!("ERR unknown subcommand '{}'", name))
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Config` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("config".as_bytes()));
+        match self.subcommand {
+            ConfigSubcommand::Get(parameter) => {
+                frame.push_bulk(Bytes::from("get".as_bytes()));
+                frame.push_bulk(Bytes::from(parameter.into_bytes()));
+            }
+            ConfigSubcommand::Set(parameter, value) => {
+                frame.push_bulk(Bytes::from("set".as_bytes()));
+                frame.push_bulk(Bytes::from(parameter.into_bytes()));
+                frame.push_bulk(Bytes::from(value.into_bytes()));
+            }
+            ConfigSubcommand::Unknown(name) => {
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+        }
+        frame
+    }
+}
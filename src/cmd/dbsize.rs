@@ -0,0 +1,75 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the number of keys in the database.
+///
+/// Keys that have expired but were not purged yet are not counted.
+#[derive(Debug, Default)]
+pub struct DbSize;
+
+impl DbSize {
+    /// Create a new `DbSize` command.
+    pub fn new() -> DbSize {
+        DbSize
+    }
+
+    /// Parse a `DbSize` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `DBSIZE` string has already been consumed, and the command takes no
+    /// arguments. Extra arguments are rejected by `Parse::finish`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `DbSize` value.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// DBSIZE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<DbSize> {
+        Ok(DbSize)
+    }
+
+    /// Apply the `DbSize` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `DbSize` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        Frame::Integer(view.dbsize() as u64)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `DbSize` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("dbsize".as_bytes()));
+        frame
+    }
+}
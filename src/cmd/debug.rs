@@ -0,0 +1,118 @@
+use crate::cmd::incrbyfloat::parse_float;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use std::thread;
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, instrument};
+
+/// Debugging commands.
+///
+/// Only the `SLEEP` subcommand is supported. It blocks the connection for the
+/// given number of seconds before replying, which is useful to exercise
+/// timeouts.
+///
+/// With the `LOCKED` flag, the sleep happens while holding the lock of the
+/// `Db` state, which blocks every other command touching the keys. This allows
+/// tests to induce lock contention on purpose. It also blocks the thread
+/// running the connection, so the flag is only accepted by debug builds of the
+/// server.
+#[derive(Debug)]
+pub struct Debug {
+    subcommand: DebugSubcommand,
+}
+
+#[derive(Debug)]
+enum DebugSubcommand {
+    /// DEBUG SLEEP seconds
+    Sleep(Duration),
+
+    /// DEBUG SLEEP seconds LOCKED
+    SleepLocked(Duration),
+
+    /// Any subcommand that is not supported. An error is returned to the
+    /// client instead of closing the connection.
+    Unknown(String),
+}
+
+impl Debug {
+    /// Parse a `Debug` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `DEBUG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Debug` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a subcommand and its arguments.
+    /// `seconds` may have a fractional part.
+    ///
+    /// ```text
+    /// DEBUG SLEEP seconds [LOCKED]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Debug> {
+        let subcommand = parse.next_string()?;
+
+        let subcommand = match &subcommand.to_uppercase()[..] {
+            "SLEEP" => {
+                let secs = parse_float(&parse.next_bytes()?)
+                    .ok_or("ERR value is not a valid float")?;
+                let duration = Duration::from_secs_f64(secs.max(0.0));
+
+                match parse.next_string() {
+                    Ok(flag) if flag.to_uppercase() == "LOCKED" => {
+                        DebugSubcommand::SleepLocked(duration)
+                    }
+                    Ok(_) => return Err("ERR syntax error".into()),
+                    Err(ParseError::EndOfStream) => DebugSubcommand::Sleep(duration),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            _ => {
+                // 忽略剩下的参数，以便`finish()`不会失败
+                while parse.next().is_ok() {}
+                DebugSubcommand::Unknown(subcommand)
+            }
+        };
+
+        Ok(Debug { subcommand })
+    }
+
+    /// Apply the `Debug` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            DebugSubcommand::Sleep(duration) => {
+                time::sleep(duration).await;
+                Frame::Simple("OK".to_string())
+            }
+            DebugSubcommand::SleepLocked(_) if !cfg!(debug_assertions) => {
+                Frame::Error("ERR DEBUG SLEEP LOCKED is only available in debug builds".to_string())
+            }
+            DebugSubcommand::SleepLocked(duration) => {
+                // 持有锁时阻塞线程，而不是让出执行权，这样锁在整个过程中都不会被释放
+                db.atomic(|_| thread::sleep(duration));
+                Frame::Simple("OK".to_string())
+            }
+            DebugSubcommand::Unknown(name) => {
+                Frame::Error(format!("ERR unknown subcommand '{}'", name))
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use crate::cmd::SetCondition;
+use crate::db::{SetOptions, StateView};
+use crate::serialize;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Returns the value stored at `key` serialized into an opaque payload, or
+/// nil if the key does not exist.
+///
+/// The payload is only meant to be given to `RESTORE`, on this server or
+/// another one, see the `serialize` module for its format. The time to live
+/// of the key is not part of it.
+#[derive(Debug)]
+pub struct Dump {
+    key: String,
+}
+
+/// Creates `key` holding the value serialized in a payload returned by
+/// `DUMP`.
+///
+/// The key expires after `ttl` milliseconds, or never if `ttl` is `0`. The
+/// command fails with a `BUSYKEY` error if the key already exists, unless
+/// `REPLACE` is given, and with an error if the payload is corrupted or was
+/// written by another version of the format.
+#[derive(Debug)]
+pub struct Restore {
+    key: String,
+
+    /// Time to live in milliseconds, `0` for none. Negative values are
+    /// rejected when applying the command, replying with an error instead of
+    /// closing the connection.
+    ttl: i64,
+
+    payload: Bytes,
+
+    replace: bool,
+}
+
+impl Dump {
+    /// Create a new `Dump` command which serializes the value of `key`.
+    pub fn new(key: impl ToString) -> Dump {
+        Dump {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Dump` instance from a received frame.
+    ///
+    /// The `DUMP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// DUMP key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Dump> {
+        let key = parse.next_string()?;
+
+        Ok(Dump { key })
+    }
+
+    /// Apply the `Dump` command to the specified `Db` instance.
+    ///
+    /// The payload is written to `dst` as a bulk string.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Dump` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.get_string(&self.key) {
+            Ok(Some(value)) => Frame::Bulk(serialize::encode(&value)),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Dump` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("dump".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
+
+impl Restore {
+    /// Create a new `Restore` command which creates `key` from `payload`,
+    /// expiring after `ttl` if given.
+    pub fn new(key: impl ToString, ttl: Option<Duration>, payload: Bytes) -> Restore {
+        Restore {
+            key: key.to_string(),
+            ttl: ttl.map(|ttl| ttl.as_millis() as i64).unwrap_or(0),
+            payload,
+            replace: false,
+        }
+    }
+
+    /// Replace the value of the key if it already exists, as with `REPLACE`.
+    pub fn with_replace(mut self) -> Restore {
+        self.replace = true;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Restore` instance from a received frame.
+    ///
+    /// The `RESTORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// RESTORE key ttl payload [REPLACE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Restore> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        // 负数的TTL在执行时回复错误
+        let ttl = parse.next_int_signed()?;
+
+        let payload = parse.next_bytes()?;
+
+        let mut replace = false;
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "REPLACE" => replace = true,
+                Ok(_) => return Err("ERR syntax error".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Restore {
+            key,
+            ttl,
+            payload,
+            replace,
+        })
+    }
+
+    /// Apply the `Restore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Restore` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        if self.ttl < 0 {
+            return Frame::Error("ERR Invalid TTL value, must be >= 0".to_string());
+        }
+
+        let value = match serialize::decode(self.payload) {
+            Ok(value) => value,
+            Err(err) => return Frame::Error(err.to_string()),
+        };
+
+        // 检查key是否存在和写入在同一把锁内完成
+        let options = SetOptions {
+            expire: (self.ttl > 0).then(|| Duration::from_millis(self.ttl as u64)),
+            condition: (!self.replace).then_some(SetCondition::Nx),
+            keep_ttl: false,
+            get: false,
+        };
+
+        match view.set_with_options(self.key, value, options) {
+            Ok(outcome) if outcome.written => Frame::Simple("OK".to_string()),
+            Ok(_) => Frame::Error("BUSYKEY Target key name already exists.".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Restore` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("restore".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.ttl.to_string()));
+        frame.push_bulk(self.payload);
+        if self.replace {
+            frame.push_bulk(Bytes::from("replace".as_bytes()));
+        }
+        frame
+    }
+}
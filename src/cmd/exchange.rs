@@ -0,0 +1,101 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Atomically swaps the values of two keys, along with their time to live.
+///
+/// This is not a standard Redis command. Both keys must exist; if one of them
+/// is missing, an error naming it is returned and neither key is modified.
+/// On success, the `OK` simple string is returned.
+#[derive(Debug)]
+pub struct Exchange {
+    key1: String,
+    key2: String,
+}
+
+impl Exchange {
+    /// Create a new `Exchange` command which swaps the values of `key1` and
+    /// `key2`.
+    pub fn new(key1: impl ToString, key2: impl ToString) -> Exchange {
+        Exchange {
+            key1: key1.to_string(),
+            key2: key2.to_string(),
+        }
+    }
+
+    /// Get the first key
+    pub fn key1(&self) -> &str {
+        &self.key1
+    }
+
+    /// Get the second key
+    pub fn key2(&self) -> &str {
+        &self.key2
+    }
+
+    /// Parse an `Exchange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EXCHANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Exchange` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// EXCHANGE key1 key2
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Exchange> {
+        let key1 = parse.next_string()?;
+        let key2 = parse.next_string()?;
+        Ok(Exchange { key1, key2 })
+    }
+
+    /// Apply the `Exchange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Exchange` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.exchange(&self.key1, &self.key2) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(missing) => Frame::Error(format!("ERR no such key '{}'", missing)),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Exchange` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("exchange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key1.into_bytes()));
+        frame.push_bulk(Bytes::from(self.key2.into_bytes()));
+        frame
+    }
+}
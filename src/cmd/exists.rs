@@ -0,0 +1,99 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Count how many of the given keys exist.
+///
+/// A key given several times is counted each time, so the reply does not
+/// tell which keys exist. `Client::exists_map` sends an `EXISTS` per key to
+/// find out. Checking a key does not count as an access for `OBJECT IDLETIME`.
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<String>,
+}
+
+impl Exists {
+    /// Create a new `Exists` command which checks `keys`.
+    pub fn new(keys: Vec<String>) -> Exists {
+        Exists { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `Exists` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EXISTS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Exists` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// EXISTS key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Exists> {
+        use ParseError::EndOfStream;
+
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Exists { keys })
+    }
+
+    /// Apply the `Exists` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Exists` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        Frame::Integer(view.exists(&self.keys) as u64)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Exists` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("exists".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
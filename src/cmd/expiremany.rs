@@ -0,0 +1,184 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Set the same time to live on a batch of keys.
+///
+/// An extension of mini-redis, behaving like an `EXPIRE` per key. The keys
+/// are updated in batches, each under a single acquisition of the lock of the
+/// `Db`, and the server replies with an array holding, for each key in the
+/// order of the request, `1` if its time to live was set, or `0` if the key
+/// does not exist or the condition does not hold.
+#[derive(Debug)]
+pub struct ExpireMany {
+    seconds: u64,
+
+    keys: Vec<String>,
+
+    condition: Option<ExpireCondition>,
+}
+
+/// Condition under which the time to live of a key is set, as with the
+/// options of `EXPIRE`.
+///
+/// A key without a time to live is considered to never expire: `GT` leaves
+/// it untouched, while `LT` sets it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// NX -- only set the time to live if the key has none.
+    Nx,
+
+    /// XX -- only set the time to live if the key has one.
+    Xx,
+
+    /// GT -- only set the time to live if it expires later than the current
+    /// one.
+    Gt,
+
+    /// LT -- only set the time to live if it expires earlier than the current
+    /// one.
+    Lt,
+}
+
+impl ExpireMany {
+    /// Create a new `ExpireMany` command which sets `keys` to expire after
+    /// `ttl`, truncated to whole seconds.
+    pub fn new(keys: Vec<String>, ttl: Duration) -> ExpireMany {
+        ExpireMany {
+            seconds: ttl.as_secs(),
+            keys,
+            condition: None,
+        }
+    }
+
+    /// Only set the time to live of the keys for which `condition` holds.
+    pub fn with_condition(mut self, condition: ExpireCondition) -> ExpireMany {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse an `ExpireMany` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EXPIREMANY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ExpireMany` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries, with exactly
+    /// `numkeys` keys.
+    ///
+    /// ```text
+    /// EXPIREMANY seconds numkeys key [key ...] [NX|XX|GT|LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ExpireMany> {
+        use ParseError::EndOfStream;
+
+        let seconds = parse.next_int()?;
+
+        let numkeys = parse.next_int()?;
+        if numkeys == 0 {
+            return Err("ERR numkeys should be greater than 0".into());
+        }
+
+        let mut keys = vec![];
+        for _ in 0..numkeys {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => return Err("ERR Number of keys can't be greater than number of args".into()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let condition = match parse.next_token() {
+            Ok(s) if s.is("NX") => Some(ExpireCondition::Nx),
+            Ok(s) if s.is("XX") => Some(ExpireCondition::Xx),
+            Ok(s) if s.is("GT") => Some(ExpireCondition::Gt),
+            Ok(s) if s.is("LT") => Some(ExpireCondition::Lt),
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ExpireMany {
+            seconds,
+            keys,
+            condition,
+        })
+    }
+
+    /// Apply the `ExpireMany` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let ttl = Duration::from_secs(self.seconds);
+
+        // 按批次加锁，避免一次性长时间持有锁
+        let response = Frame::Array(
+            db.expire_many(&self.keys, ttl, self.condition)
+                .into_iter()
+                .map(|set| Frame::Integer(set as u64))
+                .collect(),
+        );
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `ExpireMany` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        let ttl = Duration::from_secs(self.seconds);
+
+        Frame::Array(
+            view.expire_many(&self.keys, ttl, self.condition)
+                .into_iter()
+                .map(|set| Frame::Integer(set as u64))
+                .collect(),
+        )
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `ExpireMany` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("expiremany".as_bytes()));
+        frame.push_int(self.seconds);
+        frame.push_int(self.keys.len() as u64);
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        match self.condition {
+            Some(ExpireCondition::Nx) => frame.push_bulk(Bytes::from("nx".as_bytes())),
+            Some(ExpireCondition::Xx) => frame.push_bulk(Bytes::from("xx".as_bytes())),
+            Some(ExpireCondition::Gt) => frame.push_bulk(Bytes::from("gt".as_bytes())),
+            Some(ExpireCondition::Lt) => frame.push_bulk(Bytes::from("lt".as_bytes())),
+            None => {}
+        }
+        frame
+    }
+}
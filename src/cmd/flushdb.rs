@@ -0,0 +1,77 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes all the keys of the database.
+///
+/// Pub/sub channels are not keys, so subscribers are not affected.
+#[derive(Debug, Default)]
+pub struct FlushDb;
+
+impl FlushDb {
+    /// Create a new `FlushDb` command.
+    pub fn new() -> FlushDb {
+        FlushDb
+    }
+
+    /// Parse a `FlushDb` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `FLUSHDB` string has already been consumed, and the command takes no
+    /// arguments. Extra arguments are rejected by `Parse::finish`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `FlushDb` value.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// FLUSHDB
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<FlushDb> {
+        Ok(FlushDb)
+    }
+
+    /// Apply the `FlushDb` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `FlushDb` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        view.flush();
+
+        Frame::Simple("OK".to_string())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `FlushDb` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("flushdb".as_bytes()));
+        frame
+    }
+}
@@ -1,3 +1,4 @@
+use crate::db::StateView;
 use crate::{Connection, Db, Frame, Parse};
 
 use bytes::Bytes;
@@ -44,6 +45,8 @@ impl Get{
     /// ```text
     /// GET key
     /// ```
+    // 保持公开，缩小可见性会破坏已有的调用方
+    #[allow(private_interfaces)]
     pub fn parse_frames(parse: &mut Parse) -> crate::Result<Get> {
         let key = parse.next_string()?;
         Ok(Get{ key })
@@ -55,11 +58,7 @@ impl Get{
     /// to execute a received command
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        let response = if let Some(value) = db.get(&self.key) {
-            Frame::Bulk(value)
-        } else {
-            Frame::Null
-        };
+        let response = db.atomic(|view| self.execute(view));
 
         debug!(?response);
         // 将回应写回客户端
@@ -67,6 +66,19 @@ impl Get{
 
         Ok(())
     }
+
+    /// Executes the `Get` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.get_string(&self.key) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
     /// Converts the command into an equivalent `Frame`.
     ///
     /// This is called by the client when encoding a `Get` command to send to
@@ -0,0 +1,148 @@
+use crate::db::{StateView, TtlUpdate};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Get the value of key and optionally update its time to live.
+///
+/// Behaves as `GET`, and when the key exists, the time to live is set or
+/// removed according to the option given.
+///
+/// # Options
+///
+/// Currently, the following options are supported:
+///
+/// * EX `seconds` -- Set the specified expire time, in seconds.
+/// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * PERSIST -- Remove the time to live associated with the key.
+#[derive(Debug)]
+pub struct GetEx {
+    key: String,
+
+    /// How the time to live is updated, `None` to leave it untouched.
+    ttl: Option<TtlUpdate>,
+}
+
+impl GetEx {
+    /// Create a new `GetEx` command which fetches `key` and sets its time to
+    /// live to `expire`.
+    pub fn new(key: impl ToString, expire: Duration) -> GetEx {
+        GetEx {
+            key: key.to_string(),
+            ttl: Some(TtlUpdate::Expire(expire)),
+        }
+    }
+
+    /// Create a new `GetEx` command which fetches `key` and removes its time
+    /// to live.
+    pub fn persist(key: impl ToString) -> GetEx {
+        GetEx {
+            key: key.to_string(),
+            ttl: Some(TtlUpdate::Persist),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the expire, `None` if the time to live is removed or left untouched
+    pub fn expire(&self) -> Option<Duration> {
+        match self.ttl {
+            Some(TtlUpdate::Expire(expire)) => Some(expire),
+            _ => None,
+        }
+    }
+
+    /// Parse a `GetEx` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETEX` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetEx` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// GETEX key [EX seconds|PX milliseconds|PERSIST]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetEx> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        let ttl = match parse.next_token() {
+            Ok(s) if s.is("EX") => {
+                let secs = parse.next_int()?;
+                Some(TtlUpdate::Expire(Duration::from_secs(secs)))
+            }
+            Ok(s) if s.is("PX") => {
+                let ms = parse.next_int()?;
+                Some(TtlUpdate::Expire(Duration::from_millis(ms)))
+            }
+            Ok(s) if s.is("PERSIST") => Some(TtlUpdate::Persist),
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(GetEx { key, ttl })
+    }
+
+    /// Apply the `GetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `GetEx` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.get_ex(&self.key, self.ttl) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetEx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getex".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        match self.ttl {
+            Some(TtlUpdate::Expire(expire)) => {
+                frame.push_bulk(Bytes::from("px".as_bytes()));
+                frame.push_int(expire.as_millis() as u64);
+            }
+            Some(TtlUpdate::Persist) => frame.push_bulk(Bytes::from("persist".as_bytes())),
+            None => {}
+        }
+        frame
+    }
+}
@@ -0,0 +1,142 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the substring of the string stored at `key` between the offsets
+/// `start` and `end`, both inclusive.
+///
+/// Negative offsets count from the end of the string, `-1` being the last
+/// byte. Offsets out of range are clamped to the string, so the command never
+/// fails: an empty string is returned when the range is empty or the key does
+/// not exist.
+#[derive(Debug)]
+pub struct GetRange {
+    key: String,
+
+    start: i64,
+
+    end: i64,
+}
+
+impl GetRange {
+    /// Create a new `GetRange` command which reads `key` from `start` to
+    /// `end`.
+    pub fn new(key: impl ToString, start: i64, end: i64) -> GetRange {
+        GetRange {
+            key: key.to_string(),
+            start,
+            end,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start offset
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the end offset
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    /// Parse a `GetRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetRange` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// GETRANGE key start end
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_int_signed()?;
+        let end = parse.next_int_signed()?;
+
+        Ok(GetRange { key, start, end })
+    }
+
+    /// Apply the `GetRange` command to the specified `Db` instance.
+    ///
+    /// The substring is written to `dst` as a bulk string, empty rather than
+    /// null for a missing key. This is called by the server in order to
+    /// execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `GetRange` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.get_string(&self.key) {
+            Ok(value) => {
+                let value = value.unwrap_or_default();
+
+                match range(value.len(), self.start, self.end) {
+                    Some((start, end)) => Frame::Bulk(value.slice(start..=end)),
+                    None => Frame::Bulk(Bytes::new()),
+                }
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string()));
+        frame.push_bulk(Bytes::from(self.end.to_string()));
+        frame
+    }
+}
+
+/// Resolves the offsets `start` and `end` against a string of `len` bytes,
+/// or a list of `len` values, returning the inclusive range to reply, or
+/// `None` if it is empty.
+pub(crate) fn range(len: usize, start: i64, end: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+
+    // 负数偏移量从末尾开始计算，之后把两端都限制在字符串之内
+    let resolve = |offset: i64| if offset < 0 { (len + offset).max(0) } else { offset };
+
+    let start = resolve(start);
+    let end = resolve(end).min(len - 1);
+
+    if len == 0 || start > end {
+        return None;
+    }
+
+    Some((start as usize, end as usize))
+}
@@ -0,0 +1,373 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Set fields of the hash stored at key to the given values.
+///
+/// A missing key is created holding an empty hash first. Replies with the
+/// number of fields which were added, not counting those which already
+/// existed and were updated, or an error if the key holds a value which is
+/// not a hash.
+#[derive(Debug)]
+pub struct HSet {
+    key: String,
+
+    fields: Vec<(String, Bytes)>,
+}
+
+/// Get the value of a field of the hash stored at key.
+///
+/// Nil is replied if the key or the field does not exist.
+#[derive(Debug)]
+pub struct HGet {
+    key: String,
+
+    field: String,
+}
+
+/// Remove fields from the hash stored at key.
+///
+/// Replies with the number of fields which were removed, ignoring those which
+/// did not exist. The key is removed along with the last field of the hash.
+#[derive(Debug)]
+pub struct HDel {
+    key: String,
+
+    fields: Vec<String>,
+}
+
+/// Returns all the fields of the hash stored at key, along with their values.
+///
+/// The reply is a flat array, each field followed by its value, in no
+/// particular order. An empty array is replied if the key does not exist.
+#[derive(Debug)]
+pub struct HGetAll {
+    key: String,
+}
+
+impl HSet {
+    /// Create a new `HSet` command which sets `fields` of the hash stored at
+    /// `key`.
+    pub fn new(key: impl ToString, fields: Vec<(String, Bytes)>) -> HSet {
+        HSet {
+            key: key.to_string(),
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `HSet` instance from a received frame.
+    ///
+    /// The `HSET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries, with a value
+    /// for each field.
+    ///
+    /// ```text
+    /// HSET key field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HSet> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        let mut fields = vec![];
+
+        loop {
+            let field = match parse.next_string() {
+                Ok(field) => field,
+                Err(EndOfStream) if !fields.is_empty() => break,
+                Err(EndOfStream) => return Err("ERR wrong number of arguments for 'hset' command".into()),
+                Err(err) => return Err(err.into()),
+            };
+
+            match parse.next_bytes() {
+                Ok(value) => fields.push((field, value)),
+                Err(EndOfStream) => return Err("ERR wrong number of arguments for 'hset' command".into()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HSet { key, fields })
+    }
+
+    /// Apply the `HSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `HSet` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.hset(self.key, self.fields) {
+            Ok(added) => Frame::Integer(added as u64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hset".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for (field, value) in self.fields {
+            frame.push_bulk(Bytes::from(field.into_bytes()));
+            frame.push_bulk(value);
+        }
+        frame
+    }
+}
+
+impl HGet {
+    /// Create a new `HGet` command which reads `field` of the hash stored at
+    /// `key`.
+    pub fn new(key: impl ToString, field: impl ToString) -> HGet {
+        HGet {
+            key: key.to_string(),
+            field: field.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `HGet` instance from a received frame.
+    ///
+    /// The `HGET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// HGET key field
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGet> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+
+        Ok(HGet { key, field })
+    }
+
+    /// Apply the `HGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `HGet` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.hget(&self.key, &self.field) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HGet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hget".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.field.into_bytes()));
+        frame
+    }
+}
+
+impl HDel {
+    /// Create a new `HDel` command which removes `fields` from the hash stored
+    /// at `key`.
+    pub fn new(key: impl ToString, fields: Vec<String>) -> HDel {
+        HDel {
+            key: key.to_string(),
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `HDel` instance from a received frame.
+    ///
+    /// The `HDEL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// HDEL key field [field ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HDel> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        let mut fields = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(field) => fields.push(field),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HDel { key, fields })
+    }
+
+    /// Apply the `HDel` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `HDel` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.hdel(&self.key, &self.fields) {
+            Ok(removed) => Frame::Integer(removed as u64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HDel` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hdel".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for field in self.fields {
+            frame.push_bulk(Bytes::from(field.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl HGetAll {
+    /// Create a new `HGetAll` command which reads the hash stored at `key`.
+    pub fn new(key: impl ToString) -> HGetAll {
+        HGetAll { key: key.to_string() }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `HGetAll` instance from a received frame.
+    ///
+    /// The `HGETALL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HGETALL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGetAll> {
+        let key = parse.next_string()?;
+
+        Ok(HGetAll { key })
+    }
+
+    /// Apply the `HGetAll` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `HGetAll` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.hgetall(&self.key) {
+            Ok(fields) => {
+                let mut response = Frame::array();
+                for (field, value) in fields {
+                    response.push_bulk(Bytes::from(field.into_bytes()));
+                    response.push_bulk(value);
+                }
+                response
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HGetAll` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hgetall".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,143 @@
+use crate::clients::FromFrame;
+use crate::server::Stats;
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Report the health of the server.
+///
+/// Unlike `PING`, which only tells that the server answers, the reply carries
+/// the status of the server, how long it has been running, how many clients
+/// are connected and how many commands exceeded the execution deadline, which
+/// allows load balancers to perform richer health checks.
+///
+/// The reply is an array of field names each followed by its value:
+///
+/// ```text
+/// 1) "status"
+/// 2) "ok"
+/// 3) "uptime_in_seconds"
+/// 4) (integer) 42
+/// 5) "connected_clients"
+/// 6) (integer) 3
+/// 7) "timed_out_commands"
+/// 8) (integer) 0
+/// ```
+#[derive(Debug, Default)]
+pub struct Health;
+
+/// Health of the server, as replied to a `HEALTH` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    /// `ok` while the server is serving requests.
+    pub status: String,
+
+    /// How long the server has been running, in whole seconds.
+    pub uptime: Duration,
+
+    /// Number of clients currently connected, including the one asking.
+    pub connected_clients: u64,
+
+    /// Number of commands which exceeded the execution deadline of the
+    /// server, see `server::Config::command_deadline`.
+    pub timed_out_commands: u64,
+}
+
+impl Health {
+    /// Create a new `Health` command.
+    pub fn new() -> Health {
+        Health
+    }
+
+    /// Parse a `Health` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HEALTH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Health` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// HEALTH
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Health> {
+        Ok(Health)
+    }
+
+    /// Apply the `Health` command with the statistics of the server.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, stats, dst))]
+    pub(crate) async fn apply(self, stats: &Stats, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        response.push_bulk(Bytes::from("status".as_bytes()));
+        response.push_bulk(Bytes::from("ok".as_bytes()));
+        response.push_bulk(Bytes::from("uptime_in_seconds".as_bytes()));
+        response.push_int(stats.uptime().as_secs());
+        response.push_bulk(Bytes::from("connected_clients".as_bytes()));
+        response.push_int(stats.connected_clients());
+        response.push_bulk(Bytes::from("timed_out_commands".as_bytes()));
+        response.push_int(stats.timed_out_commands());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Health` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("health".as_bytes()));
+        frame
+    }
+}
+
+impl FromFrame for HealthReport {
+    fn from_frame(frame: Frame) -> crate::Result<HealthReport> {
+        let mut fields = Vec::<Frame>::from_frame(frame)?.into_iter();
+
+        let mut status = None;
+        let mut uptime = None;
+        let mut connected_clients = None;
+        let mut timed_out_commands = 0;
+
+        // 忽略未知的字段，以便之后可以添加新的字段
+        while let (Some(name), Some(value)) = (fields.next(), fields.next()) {
+            match &String::from_frame(name)?[..] {
+                "status" => status = Some(String::from_frame(value)?),
+                "uptime_in_seconds" => uptime = Some(Duration::from_secs(u64::from_frame(value)?)),
+                "connected_clients" => connected_clients = Some(u64::from_frame(value)?),
+                // 之前的版本没有这个字段，缺失时当作0
+                "timed_out_commands" => timed_out_commands = u64::from_frame(value)?,
+                _ => {}
+            }
+        }
+
+        match (status, uptime, connected_clients) {
+            (Some(status), Some(uptime), Some(connected_clients)) => Ok(HealthReport {
+                status,
+                uptime,
+                connected_clients,
+                timed_out_commands,
+            }),
+            _ => Err("protocol error; incomplete health report".into()),
+        }
+    }
+}
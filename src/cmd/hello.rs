@@ -0,0 +1,110 @@
+use crate::connection::Protocol;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Switch the protocol version of the connection and return information about
+/// the server.
+///
+/// Versions 2 and 3 are supported. Without a version, the protocol of the
+/// connection is left unchanged. The reply is a flat array of field names and
+/// values, in both versions, as there is no map frame:
+///
+/// ```text
+/// [ "server", <server name>, "version", <server version>, "proto", <version> ]
+/// ```
+///
+/// The server name and version default to those of the crate, see
+/// `server::Config::server_version`.
+#[derive(Debug, Default)]
+pub struct Hello {
+    /// requested protocol version
+    protover: Option<u64>,
+}
+
+impl Hello {
+    /// Create a new `Hello` command switching to `protover`, if any.
+    pub fn new(protover: Option<u64>) -> Hello {
+        Hello { protover }
+    }
+
+    /// Parse a `Hello` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HELLO` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Hello` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `HELLO` and an optional version.
+    ///
+    /// ```text
+    /// HELLO [protover]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hello> {
+        match parse.next_int() {
+            Ok(protover) => Ok(Hello::new(Some(protover))),
+            Err(ParseError::EndOfStream) => Ok(Hello::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Apply the `Hello` command to the connection.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let protocol = match self.protover {
+            None => Some(dst.protocol()),
+            Some(2) => Some(Protocol::Resp2),
+            Some(3) => Some(Protocol::Resp3),
+            Some(_) => None,
+        };
+
+        let response = match protocol {
+            Some(protocol) => {
+                dst.set_protocol(protocol);
+
+                let (name, version) = db.server_info();
+
+                let mut response = Frame::array();
+                response.push_bulk(Bytes::from_static(b"server"));
+                response.push_bulk(Bytes::from(name.into_bytes()));
+                response.push_bulk(Bytes::from_static(b"version"));
+                response.push_bulk(Bytes::from(version.into_bytes()));
+                response.push_bulk(Bytes::from_static(b"proto"));
+                response.push_int(protocol.version());
+                response
+            }
+            None => Frame::Error("NOPROTO unsupported protocol version".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Hello` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hello".as_bytes()));
+        if let Some(protover) = self.protover {
+            frame.push_int(protover);
+        }
+        frame
+    }
+}
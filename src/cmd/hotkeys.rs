@@ -0,0 +1,185 @@
+use crate::clients::FromFrame;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument};
+
+/// Number of keys returned by `HOTKEYS` when no count is given.
+const DEFAULT_COUNT: usize = 10;
+
+/// Report the most accessed keys.
+///
+/// This is an extension, and requires hot key tracking to be enabled with
+/// `CONFIG SET hotkeys yes`. Every `GET` and `SET` of a key is then counted,
+/// see `Db::set_hotkeys_tracking`. The reply is an array with an entry per
+/// key, the most accessed first:
+///
+/// ```text
+/// 1) 1) "key"
+///    2) (integer) 1200
+///    3) (integer) 1700000000000
+/// ```
+///
+/// The second field is the approximate number of accesses, the third one the
+/// Unix timestamp of the last access in milliseconds. `HOTKEYS RESET` clears
+/// the counts.
+#[derive(Debug)]
+pub struct HotKeys {
+    subcommand: HotKeysSubcommand,
+}
+
+#[derive(Debug)]
+enum HotKeysSubcommand {
+    /// HOTKEYS [count]
+    Top(usize),
+
+    /// HOTKEYS RESET
+    Reset,
+}
+
+/// A key reported by `HOTKEYS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotKey {
+    /// The accessed key.
+    pub key: String,
+
+    /// Approximate number of accesses since the counts were last reset. The
+    /// count of a key which started being tracked late may be overestimated.
+    pub hits: u64,
+
+    /// When the key was last accessed, with a millisecond precision.
+    pub last_hit: SystemTime,
+}
+
+impl HotKeys {
+    /// Create a new `HotKeys` command which fetches the `count` most accessed
+    /// keys.
+    pub fn top(count: usize) -> HotKeys {
+        HotKeys {
+            subcommand: HotKeysSubcommand::Top(count),
+        }
+    }
+
+    /// Create a new `HotKeys` command which clears the access counts.
+    pub fn reset() -> HotKeys {
+        HotKeys {
+            subcommand: HotKeysSubcommand::Reset,
+        }
+    }
+
+    /// Parse a `HotKeys` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HOTKEYS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HotKeys` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `HOTKEYS` and an optional count, or
+    /// the `RESET` subcommand.
+    ///
+    /// ```text
+    /// HOTKEYS [count]
+    /// HOTKEYS RESET
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HotKeys> {
+        let subcommand = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "RESET" => HotKeysSubcommand::Reset,
+            Ok(s) => match s.parse() {
+                Ok(count) => HotKeysSubcommand::Top(count),
+                Err(_) => return Err("ERR value is not an integer or out of range".into()),
+            },
+            Err(ParseError::EndOfStream) => HotKeysSubcommand::Top(DEFAULT_COUNT),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(HotKeys { subcommand })
+    }
+
+    /// Apply the `HotKeys` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            HotKeysSubcommand::Top(count) => match db.hotkeys(count) {
+                Some(hotkeys) => {
+                    let entries = hotkeys
+                        .into_iter()
+                        .map(|hotkey| {
+                            let last_hit = hotkey
+                                .last_hit
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_millis() as u64;
+
+                            let mut entry = Frame::array();
+                            entry.push_bulk(Bytes::from(hotkey.key.into_bytes()));
+                            entry.push_int(hotkey.hits);
+                            entry.push_int(last_hit);
+                            entry
+                        })
+                        .collect();
+
+                    Frame::Array(entries)
+                }
+                None => disabled(),
+            },
+            HotKeysSubcommand::Reset if db.reset_hotkeys() => Frame::Simple("OK".to_string()),
+            HotKeysSubcommand::Reset => disabled(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HotKeys` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hotkeys".as_bytes()));
+        match self.subcommand {
+            HotKeysSubcommand::Top(count) => {
+                frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+            }
+            HotKeysSubcommand::Reset => {
+                frame.push_bulk(Bytes::from("reset".as_bytes()));
+            }
+        }
+        frame
+    }
+}
+
+/// The error replied while hot key tracking is disabled.
+fn disabled() -> Frame {
+    Frame::Error("ERR hot key tracking is disabled, enable it with CONFIG SET hotkeys yes".to_string())
+}
+
+impl FromFrame for HotKey {
+    fn from_frame(frame: Frame) -> crate::Result<HotKey> {
+        let mut fields = Vec::<Frame>::from_frame(frame)?.into_iter();
+
+        match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(key), Some(hits), Some(last_hit), None) => Ok(HotKey {
+                key: String::from_frame(key)?,
+                hits: u64::from_frame(hits)?,
+                last_hit: UNIX_EPOCH + Duration::from_millis(u64::from_frame(last_hit)?),
+            }),
+            _ => Err("protocol error; invalid hot key".into()),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increment the integer stored at `key`.
+///
+/// Handles `INCR`, `DECR`, `INCRBY` and `DECRBY`, which only differ by how the
+/// increment is given. A missing key is treated as 0. When the result does
+/// not fit in a 64 bit signed integer, an error is returned unless the server
+/// is configured to saturate, see `db::CounterOverflow`.
+#[derive(Debug)]
+pub struct IncrBy {
+    key: String,
+
+    /// `None` when the `DECRBY` decrement cannot be negated, in which case an
+    /// error is replied instead of modifying the key.
+    delta: Option<i64>,
+
+    /// Name of the command as received.
+    name: &'static str,
+}
+
+impl IncrBy {
+    /// Create a new `IncrBy` command which increments `key` by `delta`.
+    pub fn new(key: impl ToString, delta: i64) -> IncrBy {
+        IncrBy {
+            key: key.to_string(),
+            delta: Some(delta),
+            name: "incrby",
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the increment, `None` if the `DECRBY` decrement overflows
+    pub fn delta(&self) -> Option<i64> {
+        self.delta
+    }
+
+    /// Returns the name of the command: `incr`, `decr`, `incrby` or `decrby`.
+    pub(crate) fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Parse an `IncrBy` instance from a received `INCR` frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INCR` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `IncrBy` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// INCR key
+    /// ```
+    pub(crate) fn parse_incr_frames(parse: &mut Parse) -> crate::Result<IncrBy> {
+        let key = parse.next_string()?;
+        Ok(IncrBy { key, delta: Some(1), name: "incr" })
+    }
+
+    /// Parse an `IncrBy` instance from a received `DECR` frame.
+    ///
+    /// The `DECR` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// DECR key
+    /// ```
+    pub(crate) fn parse_decr_frames(parse: &mut Parse) -> crate::Result<IncrBy> {
+        let key = parse.next_string()?;
+        Ok(IncrBy { key, delta: Some(-1), name: "decr" })
+    }
+
+    /// Parse an `IncrBy` instance from a received `INCRBY` frame.
+    ///
+    /// The `INCRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// INCRBY key increment
+    /// ```
+    pub(crate) fn parse_incrby_frames(parse: &mut Parse) -> crate::Result<IncrBy> {
+        let key = parse.next_string()?;
+        let delta = parse.next_int_signed()?;
+        Ok(IncrBy { key, delta: Some(delta), name: "incrby" })
+    }
+
+    /// Parse an `IncrBy` instance from a received `DECRBY` frame.
+    ///
+    /// The `DECRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// DECRBY key decrement
+    /// ```
+    pub(crate) fn parse_decrby_frames(parse: &mut Parse) -> crate::Result<IncrBy> {
+        let key = parse.next_string()?;
+        // `i64::MIN`无法取反
+        let delta = parse.next_int_signed()?.checked_neg();
+        Ok(IncrBy { key, delta, name: "decrby" })
+    }
+
+    /// Apply the `IncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `IncrBy` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        let result = match self.delta {
+            Some(delta) => view.incr_by(self.key, delta),
+            None => Err("ERR decrement would overflow"),
+        };
+
+        match result {
+            Ok(value) => match u64::try_from(value) {
+                Ok(value) => Frame::Integer(value),
+                Err(_) => Frame::SignedInteger(value),
+            },
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `IncrBy` command to send
+    /// to the server. The command is always sent as `INCRBY`.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("incrby".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.delta.unwrap_or_default().to_string()));
+        frame
+    }
+}
@@ -0,0 +1,139 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increment the floating point number stored at `key` by `increment`.
+///
+/// A missing key is treated as 0. The result is stored and returned as a
+/// string, formatted like Redis does: never in scientific notation and
+/// without trailing zeros. Any time to live associated with the key is kept.
+///
+/// Redis computes the increment with a `long double`, while `mini-redis` uses
+/// an `f64`. The result is formatted with the shortest representation that
+/// parses back to the same `f64`, which is at most 17 significant digits.
+/// Because of the lower precision, some results differ from Redis: `0.1`
+/// incremented by `0.2` gives `0.30000000000000004` here and `0.3` in Redis.
+#[derive(Debug)]
+pub struct IncrByFloat {
+    key: String,
+
+    increment: f64,
+}
+
+impl IncrByFloat {
+    /// Create a new `IncrByFloat` command which increments `key` by
+    /// `increment`.
+    pub fn new(key: impl ToString, increment: f64) -> IncrByFloat {
+        IncrByFloat {
+            key: key.to_string(),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    /// Parse an `IncrByFloat` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INCRBYFLOAT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `IncrByFloat` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// INCRBYFLOAT key increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<IncrByFloat> {
+        let key = parse.next_string()?;
+
+        let increment = parse.next_float()?;
+
+        if !increment.is_finite() {
+            return Err("ERR value is not a valid float".into());
+        }
+
+        Ok(IncrByFloat { key, increment })
+    }
+
+    /// Apply the `IncrByFloat` command to the specified `Db` instance.
+    ///
+    /// The new value is written to `dst`. This is called by the server in
+    /// order to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `IncrByFloat` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.incr_by_float(self.key, self.increment) {
+            // 与存储的值使用相同的格式
+            Ok(value) => Frame::Bulk(Bytes::from(format_float(value))),
+            Err(msg) => Frame::Error(msg.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `IncrByFloat` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("incrbyfloat".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(format_float(self.increment)));
+        frame
+    }
+}
+
+/// Parses `src` as a finite `f64`.
+///
+/// `nan` and `inf` are accepted by `f64::from_str` but rejected here, as Redis
+/// does.
+pub(crate) fn parse_float(src: &[u8]) -> Option<f64> {
+    std::str::from_utf8(src)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|num| num.is_finite())
+}
+
+/// Formats `value` the way Redis formats `INCRBYFLOAT` results.
+///
+/// The `Display` implementation of `f64` already prints the shortest string
+/// that parses back to `value`, and never uses scientific notation. Only the
+/// negative zero needs to be normalized.
+pub(crate) fn format_float(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+
+    value.to_string()
+}
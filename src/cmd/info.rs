@@ -0,0 +1,116 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Return information about the server, as a bulk string of `field:value`
+/// lines grouped in sections.
+///
+/// Only the `server` section is supported, reporting the name and version the
+/// server presents itself as, the same as `HELLO`:
+///
+/// ```text
+/// # Server
+/// redis_version:<server version>
+/// server_name:<server name>
+/// ```
+///
+/// Without a section, or with `default`, `all` or `everything`, the supported
+/// sections are returned. Unknown sections are ignored, as in Redis, so the
+/// reply may be empty.
+#[derive(Debug, Default)]
+pub struct Info {
+    /// The requested sections, lowercase. Empty for the default sections.
+    sections: Vec<String>,
+}
+
+impl Info {
+    /// Create a new `Info` command returning `section`, or the default
+    /// sections if `None`.
+    pub fn new(section: Option<&str>) -> Info {
+        Info {
+            sections: section.map(str::to_lowercase).into_iter().collect(),
+        }
+    }
+
+    /// Parse an `Info` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INFO` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Info` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `INFO` and optional sections.
+    ///
+    /// ```text
+    /// INFO [section [section ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Info> {
+        let mut sections = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(section) => sections.push(section.to_lowercase()),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Info { sections })
+    }
+
+    /// Apply the `Info` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut info = String::new();
+
+        if self.includes("server") {
+            let (name, version) = db.server_info();
+
+            info.push_str("# Server\r\n");
+            info.push_str(&format!("redis_version:{}\r\n", version));
+            info.push_str(&format!("server_name:{}\r\n", name));
+        }
+
+        let response = Frame::Bulk(Bytes::from(info));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Returns `true` if the reply includes `section`.
+    fn includes(&self, section: &str) -> bool {
+        self.sections.is_empty()
+            || self
+                .sections
+                .iter()
+                .any(|s| s == section || s == "default" || s == "all" || s == "everything")
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Info` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("info".as_bytes()));
+        for section in self.sections {
+            frame.push_bulk(Bytes::from(section.into_bytes()));
+        }
+        frame
+    }
+}
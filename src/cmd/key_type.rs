@@ -0,0 +1,87 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the type of the value stored at key.
+///
+/// All values are strings for now, so the reply is either `string`, or `none`
+/// when the key does not exist.
+#[derive(Debug)]
+pub struct Type {
+    key: String,
+}
+
+impl Type {
+    /// Create a new `Type` command which fetches the type of `key`.
+    pub fn new(key: impl ToString) -> Type {
+        Type {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Type` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `TYPE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Type` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// TYPE key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Type> {
+        let key = parse.next_string()?;
+        Ok(Type { key })
+    }
+
+    /// Apply the `Type` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Type` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        Frame::Simple(view.key_type(&self.key).to_string())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Type` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("type".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
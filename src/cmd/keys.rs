@@ -0,0 +1,100 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns all keys matching `pattern`.
+///
+/// Supported glob-style patterns:
+///
+/// * `h?llo` matches `hello`, `hallo` and `hxllo`
+/// * `h*llo` matches `hllo` and `heeeello`
+/// * `h[ae]llo` matches `hello` and `hallo`, but not `hillo`
+/// * `h[^e]llo` matches `hallo`, `hbllo`, ... but not `hello`
+/// * `h[a-b]llo` matches `hallo` and `hbllo`
+///
+/// Use `\` to escape special characters. The whole key space is inspected
+/// while holding the lock, so this is meant for debugging only.
+#[derive(Debug)]
+pub struct Keys {
+    pattern: String,
+}
+
+impl Keys {
+    /// Create a new `Keys` command which lists the keys matching `pattern`.
+    pub fn new(pattern: impl ToString) -> Keys {
+        Keys {
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// Get the pattern
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Parse a `Keys` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `KEYS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Keys` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// KEYS pattern
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Keys> {
+        let pattern = parse.next_string()?;
+        Ok(Keys { pattern })
+    }
+
+    /// Apply the `Keys` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Keys` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        let mut response = Frame::array();
+        for key in view.keys(&self.pattern) {
+            response.push_bulk(Bytes::from(key));
+        }
+
+        response
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Keys` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("keys".as_bytes()));
+        frame.push_bulk(Bytes::from(self.pattern.into_bytes()));
+        frame
+    }
+}
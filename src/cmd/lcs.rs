@@ -0,0 +1,298 @@
+use crate::clients::FromFrame;
+use crate::db::StateView;
+use crate::frame::DEFAULT_MAX_FRAME_LEN;
+use crate::lcs::{self, LcsMatch, Subsequence};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Find the longest common subsequence of the strings stored at two keys.
+///
+/// A key that does not exist is treated as an empty string. By default, the
+/// subsequence is returned as a bulk string. With `LEN`, only its length is
+/// returned. With `IDX`, the runs the subsequence is made of are returned,
+/// last first, along with its length:
+///
+/// ```text
+/// 1) "matches"
+/// 2) 1) 1) 1) (integer) 4
+///          2) (integer) 7
+///       2) 1) (integer) 5
+///          2) (integer) 8
+///    2) 1) 1) (integer) 2
+///          2) (integer) 3
+///       2) 1) (integer) 0
+///          2) (integer) 1
+/// 3) "len"
+/// 4) (integer) 6
+/// ```
+///
+/// Each run is reported with the inclusive offsets of its bytes in both
+/// strings. `MINMATCHLEN` leaves out the runs shorter than the given length,
+/// the length of the whole subsequence is not affected.
+#[derive(Debug)]
+pub struct Lcs {
+    key1: String,
+
+    key2: String,
+
+    reply: LcsReply,
+
+    min_match_len: u64,
+}
+
+/// What `LCS` replies with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LcsReply {
+    /// The subsequence itself.
+    Subsequence,
+
+    /// LEN
+    Len,
+
+    /// IDX
+    Idx,
+
+    /// Both LEN and IDX, which are not supported together. An error is
+    /// returned to the client instead of closing the connection.
+    LenAndIdx,
+}
+
+/// The reply to `LCS ... IDX`, see `Client::lcs_idx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LcsIdx {
+    /// The runs of the subsequence at least as long as the requested minimum,
+    /// last first.
+    pub matches: Vec<LcsMatch>,
+
+    /// Length of the whole subsequence.
+    pub len: u64,
+}
+
+impl Lcs {
+    /// Create a new `Lcs` command which returns the longest common
+    /// subsequence of the strings stored at `key1` and `key2`.
+    pub fn new(key1: impl ToString, key2: impl ToString) -> Lcs {
+        Lcs {
+            key1: key1.to_string(),
+            key2: key2.to_string(),
+            reply: LcsReply::Subsequence,
+            min_match_len: 0,
+        }
+    }
+
+    /// Only return the length of the subsequence, as with `LEN`.
+    pub fn with_len(mut self) -> Lcs {
+        self.reply = LcsReply::Len;
+        self
+    }
+
+    /// Return the runs of the subsequence at least `min_match_len` bytes long,
+    /// as with `IDX MINMATCHLEN min_match_len`.
+    pub fn with_idx(mut self, min_match_len: u64) -> Lcs {
+        self.reply = LcsReply::Idx;
+        self.min_match_len = min_match_len;
+        self
+    }
+
+    /// Get the first key
+    pub fn key1(&self) -> &str {
+        &self.key1
+    }
+
+    /// Get the second key
+    pub fn key2(&self) -> &str {
+        &self.key2
+    }
+
+    /// Parse a `Lcs` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LCS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Lcs` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LCS key1 key2 [LEN] [IDX] [MINMATCHLEN len]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Lcs> {
+        use ParseError::EndOfStream;
+
+        let mut lcs = Lcs::new(parse.next_string()?, parse.next_string()?);
+
+        let mut len = false;
+        let mut idx = false;
+
+        loop {
+            match parse.next_token() {
+                Ok(s) if s.is("LEN") => len = true,
+                Ok(s) if s.is("IDX") => idx = true,
+                Ok(s) if s.is("MINMATCHLEN") => lcs.min_match_len = parse.next_int()?,
+                Ok(_) => return Err("ERR syntax error".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        lcs.reply = match (len, idx) {
+            (true, true) => LcsReply::LenAndIdx,
+            (true, false) => LcsReply::Len,
+            (false, true) => LcsReply::Idx,
+            (false, false) => LcsReply::Subsequence,
+        };
+
+        Ok(lcs)
+    }
+
+    /// Apply the `Lcs` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // 只在锁内读取两个值，耗时的计算在锁外进行
+        let values = db.atomic(|view| self.read(view));
+        let response = self.reply(values);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Lcs` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        let values = self.read(view);
+        self.reply(values)
+    }
+
+    /// Reads the values of the two keys, a missing key being an empty string.
+    fn read(&self, view: &mut StateView<'_>) -> Result<(Bytes, Bytes), &'static str> {
+        let a = view.get_string(&self.key1)?.unwrap_or_default();
+        let b = view.get_string(&self.key2)?.unwrap_or_default();
+        Ok((a, b))
+    }
+
+    /// Builds the reply from the values of the two keys.
+    fn reply(&self, values: Result<(Bytes, Bytes), &'static str>) -> Frame {
+        match values {
+            Ok((a, b)) => self.compute(&a, &b),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Computes the longest common subsequence of `a` and `b`, returning the
+    /// reply.
+    fn compute(&self, a: &[u8], b: &[u8]) -> Frame {
+        // 与Redis一样，计算用的表不能超过`proto-max-bulk-len`
+        let fits = lcs::table_len(a.len(), b.len())
+            .and_then(|cells| cells.checked_mul(4))
+            .map(|bytes| bytes <= DEFAULT_MAX_FRAME_LEN)
+            .unwrap_or(false);
+
+        if self.reply == LcsReply::LenAndIdx {
+            Frame::Error("ERR If you want both the length and indexes, please just use IDX.".to_string())
+        } else if !fits {
+            Frame::Error("ERR Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len".to_string())
+        } else {
+            self.reply_frame(lcs::longest_common_subsequence(a, b))
+        }
+    }
+
+    /// Builds the reply for `subsequence`, as requested by the options.
+    fn reply_frame(&self, subsequence: Subsequence) -> Frame {
+        match self.reply {
+            LcsReply::Subsequence => Frame::Bulk(Bytes::from(subsequence.bytes)),
+            LcsReply::Len => Frame::Integer(subsequence.bytes.len() as u64),
+            LcsReply::Idx | LcsReply::LenAndIdx => {
+                let matches = subsequence
+                    .matches
+                    .iter()
+                    .filter(|run| run.match_len() as u64 >= self.min_match_len)
+                    .map(|run| {
+                        Frame::Array(vec![
+                            Frame::Array(vec![Frame::Integer(run.a.0 as u64), Frame::Integer(run.a.1 as u64)]),
+                            Frame::Array(vec![Frame::Integer(run.b.0 as u64), Frame::Integer(run.b.1 as u64)]),
+                        ])
+                    })
+                    .collect();
+
+                Frame::Array(vec![
+                    Frame::Bulk(Bytes::from_static(b"matches")),
+                    Frame::Array(matches),
+                    Frame::Bulk(Bytes::from_static(b"len")),
+                    Frame::Integer(subsequence.bytes.len() as u64),
+                ])
+            }
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Lcs` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lcs".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key1.into_bytes()));
+        frame.push_bulk(Bytes::from(self.key2.into_bytes()));
+        match self.reply {
+            LcsReply::Subsequence => {}
+            LcsReply::Len => frame.push_bulk(Bytes::from("len".as_bytes())),
+            LcsReply::Idx => frame.push_bulk(Bytes::from("idx".as_bytes())),
+            LcsReply::LenAndIdx => {
+                frame.push_bulk(Bytes::from("len".as_bytes()));
+                frame.push_bulk(Bytes::from("idx".as_bytes()));
+            }
+        }
+        if self.min_match_len > 0 {
+            frame.push_bulk(Bytes::from("minmatchlen".as_bytes()));
+            frame.push_int(self.min_match_len);
+        }
+        frame
+    }
+}
+
+impl FromFrame for LcsMatch {
+    fn from_frame(frame: Frame) -> crate::Result<LcsMatch> {
+        let ((a_start, a_end), (b_start, b_end)) = <((u64, u64), (u64, u64))>::from_frame(frame)?;
+
+        Ok(LcsMatch {
+            a: (a_start as usize, a_end as usize),
+            b: (b_start as usize, b_end as usize),
+        })
+    }
+}
+
+impl FromFrame for LcsIdx {
+    fn from_frame(frame: Frame) -> crate::Result<LcsIdx> {
+        let mut fields = Vec::<Frame>::from_frame(frame)?.into_iter();
+
+        match (fields.next(), fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(matches_name), Some(matches), Some(len_name), Some(len), None)
+                if matches_name == "matches" && len_name == "len" =>
+            {
+                Ok(LcsIdx {
+                    matches: Vec::<LcsMatch>::from_frame(matches)?,
+                    len: u64::from_frame(len)?,
+                })
+            }
+            _ => Err("protocol error; invalid LCS IDX reply".into()),
+        }
+    }
+}
@@ -0,0 +1,503 @@
+use crate::db::{ListEnd, StateView};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Insert values at the head of the list stored at key.
+///
+/// The values are inserted one after the other, so `LPUSH key a b c` leaves
+/// `c` at the head. A missing key is created holding an empty list first.
+/// Replies with the length of the list after the push, or an error if the key
+/// holds a value which is not a list.
+#[derive(Debug)]
+pub struct LPush {
+    key: String,
+
+    values: Vec<Bytes>,
+}
+
+/// Insert values at the tail of the list stored at key.
+///
+/// Like `LPUSH`, but at the tail of the list, so `RPUSH key a b c` leaves the
+/// values in the order they are given.
+#[derive(Debug)]
+pub struct RPush {
+    key: String,
+
+    values: Vec<Bytes>,
+}
+
+/// Remove and return the first values of the list stored at key.
+///
+/// Without a count, the first value is replied as a bulk string. With a count,
+/// up to that many values are replied as an array. Nil is replied if the key
+/// does not exist. The key is removed along with the last value of the list.
+#[derive(Debug)]
+pub struct LPop {
+    key: String,
+
+    /// Number of values to pop, `None` for a single value replied as a bulk
+    /// string. Negative counts are rejected when applying the command,
+    /// replying with an error instead of closing the connection.
+    count: Option<i64>,
+}
+
+/// Remove and return the last values of the list stored at key.
+///
+/// Like `LPOP`, but from the tail of the list.
+#[derive(Debug)]
+pub struct RPop {
+    key: String,
+
+    /// See `LPop::count`.
+    count: Option<i64>,
+}
+
+/// Returns the values of the list stored at key between the indexes `start`
+/// and `stop`, both inclusive.
+///
+/// Negative indexes count from the end of the list, so `LRANGE key 0 -1`
+/// returns the whole list. Indexes out of range are clamped to the list, and
+/// an empty array is replied if the key does not exist.
+#[derive(Debug)]
+pub struct LRange {
+    key: String,
+
+    start: i64,
+
+    stop: i64,
+}
+
+impl LPush {
+    /// Create a new `LPush` command which pushes `values` at the head of the
+    /// list stored at `key`.
+    pub fn new(key: impl ToString, values: Vec<Bytes>) -> LPush {
+        LPush {
+            key: key.to_string(),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `LPush` instance from a received frame.
+    ///
+    /// The `LPUSH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LPUSH key value [value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPush> {
+        let (key, values) = parse_push(parse)?;
+
+        Ok(LPush { key, values })
+    }
+
+    /// Apply the `LPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `LPush` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        execute_push(self.key, self.values, ListEnd::Left, view)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LPush` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("lpush", self.key, self.values)
+    }
+}
+
+impl RPush {
+    /// Create a new `RPush` command which pushes `values` at the tail of the
+    /// list stored at `key`.
+    pub fn new(key: impl ToString, values: Vec<Bytes>) -> RPush {
+        RPush {
+            key: key.to_string(),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `RPush` instance from a received frame.
+    ///
+    /// The `RPUSH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// RPUSH key value [value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPush> {
+        let (key, values) = parse_push(parse)?;
+
+        Ok(RPush { key, values })
+    }
+
+    /// Apply the `RPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `RPush` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        execute_push(self.key, self.values, ListEnd::Right, view)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `RPush` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("rpush", self.key, self.values)
+    }
+}
+
+impl LPop {
+    /// Create a new `LPop` command which pops the first value of the list
+    /// stored at `key`.
+    pub fn new(key: impl ToString) -> LPop {
+        LPop {
+            key: key.to_string(),
+            count: None,
+        }
+    }
+
+    /// Pop up to `count` values, replied as an array, instead of a single
+    /// one.
+    pub fn with_count(mut self, count: u64) -> LPop {
+        self.count = Some(count as i64);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `LPop` instance from a received frame.
+    ///
+    /// The `LPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// LPOP key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPop> {
+        let (key, count) = parse_pop(parse)?;
+
+        Ok(LPop { key, count })
+    }
+
+    /// Apply the `LPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `LPop` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        execute_pop(&self.key, self.count, ListEnd::Left, view)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LPop` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pop_frame("lpop", self.key, self.count)
+    }
+}
+
+impl RPop {
+    /// Create a new `RPop` command which pops the last value of the list
+    /// stored at `key`.
+    pub fn new(key: impl ToString) -> RPop {
+        RPop {
+            key: key.to_string(),
+            count: None,
+        }
+    }
+
+    /// Pop up to `count` values, replied as an array, instead of a single
+    /// one.
+    pub fn with_count(mut self, count: u64) -> RPop {
+        self.count = Some(count as i64);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `RPop` instance from a received frame.
+    ///
+    /// The `RPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// RPOP key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPop> {
+        let (key, count) = parse_pop(parse)?;
+
+        Ok(RPop { key, count })
+    }
+
+    /// Apply the `RPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `RPop` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        execute_pop(&self.key, self.count, ListEnd::Right, view)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `RPop` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pop_frame("rpop", self.key, self.count)
+    }
+}
+
+impl LRange {
+    /// Create a new `LRange` command which reads the list stored at `key`
+    /// from `start` to `stop`.
+    pub fn new(key: impl ToString, start: i64, stop: i64) -> LRange {
+        LRange {
+            key: key.to_string(),
+            start,
+            stop,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the index of the first element, negative from the end of the list
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the index of the last element, negative from the end of the list
+    pub fn stop(&self) -> i64 {
+        self.stop
+    }
+
+    /// Parse an `LRange` instance from a received frame.
+    ///
+    /// The `LRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LRANGE key start stop
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_int_signed()?;
+        let stop = parse.next_int_signed()?;
+
+        Ok(LRange { key, start, stop })
+    }
+
+    /// Apply the `LRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `LRange` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.lrange(&self.key, self.start, self.stop) {
+            Ok(values) => Frame::Array(values.into_iter().map(Frame::Bulk).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string()));
+        frame.push_bulk(Bytes::from(self.stop.to_string()));
+        frame
+    }
+}
+
+/// Parses the arguments of `LPUSH` and `RPUSH`: a key and at least one value.
+fn parse_push(parse: &mut Parse) -> crate::Result<(String, Vec<Bytes>)> {
+    use ParseError::EndOfStream;
+
+    let key = parse.next_string()?;
+
+    let mut values = vec![parse.next_bytes()?];
+
+    loop {
+        match parse.next_bytes() {
+            Ok(value) => values.push(value),
+            Err(EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok((key, values))
+}
+
+/// Parses the arguments of `LPOP` and `RPOP`: a key and an optional count.
+fn parse_pop(parse: &mut Parse) -> crate::Result<(String, Option<i64>)> {
+    use ParseError::EndOfStream;
+
+    let key = parse.next_string()?;
+
+    let count = match parse.next_int_signed() {
+        Ok(count) => Some(count),
+        Err(EndOfStream) => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok((key, count))
+}
+
+fn execute_push(key: String, values: Vec<Bytes>, end: ListEnd, view: &mut StateView<'_>) -> Frame {
+    match view.push(key, values, end) {
+        Ok(len) => Frame::Integer(len as u64),
+        Err(err) => Frame::Error(err.to_string()),
+    }
+}
+
+fn execute_pop(key: &str, count: Option<i64>, end: ListEnd, view: &mut StateView<'_>) -> Frame {
+    match count {
+        Some(count) if count < 0 => Frame::Error("ERR value is out of range, must be positive".to_string()),
+        Some(count) => match view.pop(key, count as usize, end) {
+            Ok(Some(values)) => Frame::Array(values.into_iter().map(Frame::Bulk).collect()),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        },
+        // 没有指定数量时回复单个值而不是数组
+        None => match view.pop(key, 1, end) {
+            Ok(Some(mut values)) => Frame::Bulk(values.remove(0)),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        },
+    }
+}
+
+fn push_frame(name: &str, key: String, values: Vec<Bytes>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.to_string()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    for value in values {
+        frame.push_bulk(value);
+    }
+    frame
+}
+
+fn pop_frame(name: &str, key: String, count: Option<i64>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.to_string()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    if let Some(count) = count {
+        frame.push_bulk(Bytes::from(count.to_string()));
+    }
+    frame
+}
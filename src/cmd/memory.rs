@@ -0,0 +1,222 @@
+use crate::clients::FromFrame;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Report how the memory is used by the values.
+///
+/// Only the `HISTOGRAM` subcommand is supported, and is an extension. It
+/// replies with a histogram of the lengths of the values, per value type:
+///
+/// ```text
+/// 1) 1) "string"
+///    2) (integer) 1200
+///    3) (integer) 5300000
+///    4) 1) 1) (integer) 1023
+///          2) (integer) 1100
+///       2) 1) (integer) 2097151
+///          2) (integer) 100
+/// ```
+///
+/// The fields of a type are its number of keys, the total length of its
+/// values in bytes, and its buckets. The buckets are powers of two: a bucket
+/// holds the values whose length has the same number of bits, and is reported
+/// with the largest length it may hold and its number of keys. Only the
+/// non-empty buckets are reported, smallest first.
+///
+/// The keys are visited in chunks without holding the lock of the database
+/// for long, see `Db::memory_histogram`. A single histogram is computed at a
+/// time, a `BUSY` error is replied while another one is in progress.
+#[derive(Debug)]
+pub struct Memory {
+    subcommand: MemorySubcommand,
+}
+
+#[derive(Debug)]
+enum MemorySubcommand {
+    /// MEMORY HISTOGRAM
+    Histogram,
+
+    /// Any subcommand that is not supported. An error is returned to the
+    /// client instead of closing the connection.
+    Unknown(String),
+}
+
+/// The histogram of the lengths of the values of a type, reported by
+/// `MEMORY HISTOGRAM`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeHistogram {
+    /// The name of the type, as reported by `TYPE`.
+    pub type_name: String,
+
+    /// Number of keys holding a value of this type.
+    pub keys: u64,
+
+    /// Total length of the values, in bytes.
+    pub bytes: u64,
+
+    /// The non-empty buckets, smallest first, as the largest length a bucket
+    /// may hold and its number of keys. A bucket holds the values whose length
+    /// is greater than the largest length of the previous power of two, e.g.
+    /// `(1023, n)` counts the values of 512 to 1023 bytes.
+    pub buckets: Vec<(u64, u64)>,
+}
+
+impl Memory {
+    /// Create a new `Memory` command which computes the histogram of the
+    /// lengths of the values.
+    pub fn histogram() -> Memory {
+        Memory {
+            subcommand: MemorySubcommand::Histogram,
+        }
+    }
+
+    /// Parse a `Memory` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MEMORY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Memory` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a subcommand.
+    ///
+    /// ```text
+    /// MEMORY HISTOGRAM
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Memory> {
+        let subcommand = parse.next_string()?;
+
+        let subcommand = match &subcommand.to_uppercase()[..] {
+            "HISTOGRAM" => MemorySubcommand::Histogram,
+            _ => {
+                // 忽略剩下的参数，以便`finish()`不会失败
+                while parse.next().is_ok() {}
+                MemorySubcommand::Unknown(subcommand)
+            }
+        };
+
+        Ok(Memory { subcommand })
+    }
+
+    /// Apply the `Memory` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            MemorySubcommand::Histogram => match db.memory_histogram().await {
+                Some(histograms) => {
+                    let types = histograms
+                        .into_iter()
+                        .map(|histogram| {
+                            let buckets = histogram
+                                .buckets
+                                .into_iter()
+                                .map(|(max_len, keys)| {
+                                    let mut bucket = Frame::array();
+                                    bucket.push_int(max_len);
+                                    bucket.push_int(keys);
+                                    bucket
+                                })
+                                .collect();
+
+                            Frame::Array(vec![
+                                Frame::Bulk(Bytes::from(histogram.type_name.into_bytes())),
+                                Frame::Integer(histogram.keys),
+                                Frame::Integer(histogram.bytes),
+                                Frame::Array(buckets),
+                            ])
+                        })
+                        .collect();
+
+                    Frame::Array(types)
+                }
+                None => Frame::Error("BUSY a memory histogram is already being computed".to_string()),
+            },
+            MemorySubcommand::Unknown(name) => {
+                Frame::Error(format!("ERR unknown subcommand '{}'", name))
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Memory` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("memory".as_bytes()));
+        match self.subcommand {
+            MemorySubcommand::Histogram => {
+                frame.push_bulk(Bytes::from("histogram".as_bytes()));
+            }
+            MemorySubcommand::Unknown(name) => {
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+        }
+        frame
+    }
+}
+
+impl TypeHistogram {
+    /// Create an empty histogram for the type `type_name`.
+    pub(crate) fn new(type_name: &str) -> TypeHistogram {
+        TypeHistogram {
+            type_name: type_name.to_string(),
+            keys: 0,
+            bytes: 0,
+            buckets: vec![],
+        }
+    }
+
+    /// Count a value of `len` bytes.
+    pub(crate) fn record(&mut self, len: usize) {
+        let len = len as u64;
+
+        // 桶的上界是与`len`位数相同的最大值，空值单独一个桶
+        let max_len = match len {
+            0 => 0,
+            len => u64::MAX >> len.leading_zeros(),
+        };
+
+        match self.buckets.binary_search_by_key(&max_len, |(max_len, _)| *max_len) {
+            Ok(i) => self.buckets[i].1 += 1,
+            Err(i) => self.buckets.insert(i, (max_len, 1)),
+        }
+
+        self.keys += 1;
+        self.bytes += len;
+    }
+}
+
+impl FromFrame for TypeHistogram {
+    fn from_frame(frame: Frame) -> crate::Result<TypeHistogram> {
+        let mut fields = Vec::<Frame>::from_frame(frame)?.into_iter();
+
+        match (fields.next(), fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(type_name), Some(keys), Some(bytes), Some(buckets), None) => Ok(TypeHistogram {
+                type_name: String::from_frame(type_name)?,
+                keys: u64::from_frame(keys)?,
+                bytes: u64::from_frame(bytes)?,
+                buckets: Vec::<(u64, u64)>::from_frame(buckets)?,
+            }),
+            _ => Err("protocol error; invalid memory histogram".into()),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Get the values of all the given keys.
+///
+/// For every key that does not exist, the special value nil is returned.
+#[derive(Debug)]
+pub struct MGet {
+    keys: Vec<String>,
+}
+
+impl MGet {
+    /// Create a new `MGet` command which fetches `keys`.
+    pub fn new(keys: Vec<String>) -> MGet {
+        MGet { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `MGet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MGET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `MGet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// MGET key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MGet> {
+        use ParseError::EndOfStream;
+
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(MGet { keys })
+    }
+
+    /// Apply the `MGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `MGet` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        // 所有的key在同一次加锁中读取，这样回复的是同一时刻的值
+        let values = self
+            .keys
+            .iter()
+            .map(|key| match view.get(key) {
+                Some(value) => Frame::Bulk(value),
+                None => Frame::Null,
+            })
+            .collect();
+
+        Frame::Array(values)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `MGet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("mget".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
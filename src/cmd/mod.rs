@@ -1,31 +1,308 @@
+mod append;
+pub use append::Append;
+
+mod cas;
+pub use cas::Cas;
+
+mod command_info;
+pub use command_info::CommandInfo;
+
+mod compress;
+pub use compress::Compress;
+
+mod config;
+pub use config::Config;
+
+mod dbsize;
+pub use dbsize::DbSize;
+
+mod debug;
+pub use debug::Debug;
+
+mod exchange;
+pub use exchange::Exchange;
+
+mod del;
+pub use del::Del;
+
+mod dump;
+pub use dump::{Dump, Restore};
+
+mod exists;
+pub use exists::Exists;
+
+mod expiremany;
+pub use expiremany::{ExpireCondition, ExpireMany};
+
+mod flushdb;
+pub use flushdb::FlushDb;
+
 mod get;
 pub use get::Get;
 
+mod getex;
+pub use getex::GetEx;
+
+pub(crate) mod getrange;
+pub use getrange::GetRange;
+
+mod hash;
+pub use hash::{HDel, HGet, HGetAll, HSet};
+
+mod health;
+pub use health::{Health, HealthReport};
+
+mod hello;
+pub use hello::Hello;
+
+mod hotkeys;
+pub use hotkeys::{HotKey, HotKeys};
+
+mod incr;
+pub use incr::IncrBy;
+
+pub(crate) mod incrbyfloat;
+pub use incrbyfloat::IncrByFloat;
+
+mod info;
+pub use info::Info;
+
+mod list;
+pub use list::{LPop, LPush, LRange, RPop, RPush};
+
+mod key_type;
+pub use key_type::Type;
+
+mod keys;
+pub use keys::Keys;
+
+mod lcs;
+pub use lcs::{Lcs, LcsIdx};
+
+mod memory;
+pub use memory::{Memory, TypeHistogram};
+
+mod mget;
+pub use mget::MGet;
+
+mod mset;
+pub use mset::MSet;
+
+mod multi;
+pub use multi::{Discard, Exec, Multi};
+
+mod object;
+pub use object::Object;
+
 mod ping;
 pub use ping::Ping;
 
+mod pttl;
+pub use pttl::{PTtl, Ttl};
+
 mod publish;
 pub use publish::Publish;
 
+mod pubsub_stats;
+pub use pubsub_stats::{ChannelStats, PubSub};
+
+mod replication;
+pub use replication::{PSync, ReplConf};
+
+mod scan;
+pub use scan::Scan;
+
 mod set;
-pub use set::Set;
+pub use set::{Set, SetCondition};
+
+mod setex;
+pub use setex::SetEx;
+
+mod setrange;
+pub use setrange::SetRange;
+
+mod stream;
+pub use stream::{StreamEntry, StreamId, XAdd, XRange, XSubscribe};
+pub(crate) use stream::{fields_from_frame, fields_to_frame};
+
+mod strlen;
+pub use strlen::Strlen;
 
 mod subscribe;
-pub use subscribe::{Subscribe, Unsubscribe};
+pub use subscribe::{PSubscribe, PUnsubscribe, Subscribe, Unsubscribe};
+
+mod touch;
+pub use touch::Touch;
+
+mod unlink;
+pub use unlink::Unlink;
 
 mod unknown;
 pub use unknown::Unknown;
 
-use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
+mod wait;
+pub use wait::Wait;
+
+use crate::db::StateView;
+use crate::server::Stats;
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+/// Parses the arguments of a command into a `Command`.
+type Parser = fn(&mut Parse) -> crate::Result<Command>;
+
+/// Flags describing how the server runs a command, see `COMMANDS`.
+pub(crate) type CommandFlags = u8;
+
+/// The command may wait before replying. See `Command::may_block`.
+pub(crate) const MAY_BLOCK: CommandFlags = 1 << 0;
+
+/// The command manages its own lifetime, and is exempt from the execution
+/// deadline. See `Command::exempt_from_deadline`.
+pub(crate) const NO_DEADLINE: CommandFlags = 1 << 1;
+
+/// The commands supported by the server: the name, the arity, the flags and
+/// the parser of each command.
+///
+/// The arity is the number of arguments, including the name of the command.
+/// A negative arity `-n` means that the command takes at least `n` arguments.
+/// This table is the single list of commands, shared by `Command::from_frame`,
+/// the `COMMAND` introspection command and the server, which classifies the
+/// commands by their flags.
+pub(crate) const COMMANDS: &[(&str, i64, CommandFlags, Parser)] = &[
+    ("get", 2, 0, |parse| Ok(Command::Get(Get::parse_frames(parse)?))),
+    ("publish", 3, 0, |parse| Ok(Command::Publish(Publish::parse_frames(parse)?))),
+    ("set", -3, 0, |parse| Ok(Command::Set(Set::parse_frames(parse)?))),
+    ("strlen", 2, 0, |parse| Ok(Command::Strlen(Strlen::parse_frames(parse)?))),
+    ("subscribe", -2, MAY_BLOCK | NO_DEADLINE, |parse| Ok(Command::Subscribe(Subscribe::parse_frames(parse)?))),
+    ("unsubscribe", -1, 0, |parse| Ok(Command::Unsubscribe(Unsubscribe::parse_frames(parse)?))),
+    ("psubscribe", -2, MAY_BLOCK | NO_DEADLINE, |parse| Ok(Command::PSubscribe(PSubscribe::parse_frames(parse)?))),
+    ("punsubscribe", -1, 0, |parse| Ok(Command::PUnsubscribe(PUnsubscribe::parse_frames(parse)?))),
+    ("ping", -1, 0, |parse| Ok(Command::Ping(Ping::parse_frames(parse)?))),
+    ("append", 3, 0, |parse| Ok(Command::Append(Append::parse_frames(parse)?))),
+    ("setrange", 4, 0, |parse| Ok(Command::SetRange(SetRange::parse_frames(parse)?))),
+    ("object", -2, 0, |parse| Ok(Command::Object(Object::parse_frames(parse)?))),
+    ("incrbyfloat", 3, 0, |parse| Ok(Command::IncrByFloat(IncrByFloat::parse_frames(parse)?))),
+    ("mget", -2, 0, |parse| Ok(Command::MGet(MGet::parse_frames(parse)?))),
+    ("keys", 2, 0, |parse| Ok(Command::Keys(Keys::parse_frames(parse)?))),
+    ("scan", -2, 0, |parse| Ok(Command::Scan(Scan::parse_frames(parse)?))),
+    ("dbsize", 1, 0, |parse| Ok(Command::DbSize(DbSize::parse_frames(parse)?))),
+    ("debug", -2, MAY_BLOCK, |parse| Ok(Command::Debug(Debug::parse_frames(parse)?))),
+    ("flushdb", 1, 0, |parse| Ok(Command::FlushDb(FlushDb::parse_frames(parse)?))),
+    ("exchange", 3, 0, |parse| Ok(Command::Exchange(Exchange::parse_frames(parse)?))),
+    ("type", 2, 0, |parse| Ok(Command::Type(Type::parse_frames(parse)?))),
+    ("setex", 4, 0, |parse| Ok(Command::SetEx(SetEx::parse_setex_frames(parse)?))),
+    ("psetex", 4, 0, |parse| Ok(Command::SetEx(SetEx::parse_psetex_frames(parse)?))),
+    ("health", 1, 0, |parse| Ok(Command::Health(Health::parse_frames(parse)?))),
+    ("command", -1, 0, |parse| Ok(Command::CommandInfo(CommandInfo::parse_frames(parse)?))),
+    ("cas", 4, 0, |parse| Ok(Command::Cas(Cas::parse_frames(parse)?))),
+    ("mset", -3, 0, |parse| Ok(Command::MSet(MSet::parse_frames(parse)?))),
+    ("wait", 3, 0, |parse| Ok(Command::Wait(Wait::parse_frames(parse)?))),
+    ("pubsub", -2, 0, |parse| Ok(Command::PubSub(PubSub::parse_frames(parse)?))),
+    ("getex", -2, 0, |parse| Ok(Command::GetEx(GetEx::parse_frames(parse)?))),
+    ("pttl", 2, 0, |parse| Ok(Command::PTtl(PTtl::parse_frames(parse)?))),
+    ("touch", -2, 0, |parse| Ok(Command::Touch(Touch::parse_frames(parse)?))),
+    ("hello", -1, 0, |parse| Ok(Command::Hello(Hello::parse_frames(parse)?))),
+    ("info", -1, 0, |parse| Ok(Command::Info(Info::parse_frames(parse)?))),
+    ("config", -3, 0, |parse| Ok(Command::Config(Config::parse_frames(parse)?))),
+    ("hotkeys", -1, 0, |parse| Ok(Command::HotKeys(HotKeys::parse_frames(parse)?))),
+    ("incr", 2, 0, |parse| Ok(Command::IncrBy(IncrBy::parse_incr_frames(parse)?))),
+    ("decr", 2, 0, |parse| Ok(Command::IncrBy(IncrBy::parse_decr_frames(parse)?))),
+    ("incrby", 3, 0, |parse| Ok(Command::IncrBy(IncrBy::parse_incrby_frames(parse)?))),
+    ("decrby", 3, 0, |parse| Ok(Command::IncrBy(IncrBy::parse_decrby_frames(parse)?))),
+    ("del", -2, 0, |parse| Ok(Command::Del(Del::parse_frames(parse)?))),
+    ("exists", -2, 0, |parse| Ok(Command::Exists(Exists::parse_frames(parse)?))),
+    ("getrange", 4, 0, |parse| Ok(Command::GetRange(GetRange::parse_frames(parse)?))),
+    ("memory", -2, 0, |parse| Ok(Command::Memory(Memory::parse_frames(parse)?))),
+    ("unlink", -2, 0, |parse| Ok(Command::Unlink(Unlink::parse_frames(parse)?))),
+    ("lcs", -3, 0, |parse| Ok(Command::Lcs(Lcs::parse_frames(parse)?))),
+    ("multi", 1, 0, |parse| Ok(Command::Multi(Multi::parse_frames(parse)?))),
+    ("exec", 1, 0, |parse| Ok(Command::Exec(Exec::parse_frames(parse)?))),
+    ("discard", 1, 0, |parse| Ok(Command::Discard(Discard::parse_frames(parse)?))),
+    ("dump", 2, 0, |parse| Ok(Command::Dump(Dump::parse_frames(parse)?))),
+    ("restore", -4, 0, |parse| Ok(Command::Restore(Restore::parse_frames(parse)?))),
+    ("expiremany", -4, 0, |parse| Ok(Command::ExpireMany(ExpireMany::parse_frames(parse)?))),
+    ("lpush", -3, 0, |parse| Ok(Command::LPush(LPush::parse_frames(parse)?))),
+    ("rpush", -3, 0, |parse| Ok(Command::RPush(RPush::parse_frames(parse)?))),
+    ("lpop", -2, 0, |parse| Ok(Command::LPop(LPop::parse_frames(parse)?))),
+    ("rpop", -2, 0, |parse| Ok(Command::RPop(RPop::parse_frames(parse)?))),
+    ("lrange", 4, 0, |parse| Ok(Command::LRange(LRange::parse_frames(parse)?))),
+    ("hset", -4, 0, |parse| Ok(Command::HSet(HSet::parse_frames(parse)?))),
+    ("hget", 3, 0, |parse| Ok(Command::HGet(HGet::parse_frames(parse)?))),
+    ("hdel", -3, 0, |parse| Ok(Command::HDel(HDel::parse_frames(parse)?))),
+    ("hgetall", 2, 0, |parse| Ok(Command::HGetAll(HGetAll::parse_frames(parse)?))),
+    ("compress", -2, 0, |parse| Ok(Command::Compress(Compress::parse_frames(parse)?))),
+    ("psync", 3, MAY_BLOCK | NO_DEADLINE, |parse| Ok(Command::PSync(PSync::parse_frames(parse)?))),
+    ("replconf", -2, 0, |parse| Ok(Command::ReplConf(ReplConf::parse_frames(parse)?))),
+    ("xadd", -5, 0, |parse| Ok(Command::XAdd(XAdd::parse_frames(parse)?))),
+    ("xrange", 4, 0, |parse| Ok(Command::XRange(XRange::parse_frames(parse)?))),
+    ("xsubscribe", 3, MAY_BLOCK | NO_DEADLINE, |parse| Ok(Command::XSubscribe(XSubscribe::parse_frames(parse)?))),
+];
 
 #[derive(Debug)]
 pub enum Command {
     Get(Get),
     Publish(Publish),
     Set(Set),
-    Subcribe(Subscribe),
+    Strlen(Strlen),
+    Subscribe(Subscribe),
     Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
     Ping(Ping),
+    Append(Append),
+    SetRange(SetRange),
+    Object(Object),
+    IncrByFloat(IncrByFloat),
+    MGet(MGet),
+    Keys(Keys),
+    Scan(Scan),
+    DbSize(DbSize),
+    Debug(Debug),
+    FlushDb(FlushDb),
+    Exchange(Exchange),
+    Type(Type),
+    SetEx(SetEx),
+    Health(Health),
+    Cas(Cas),
+    MSet(MSet),
+    CommandInfo(CommandInfo),
+    Wait(Wait),
+    PubSub(PubSub),
+    GetEx(GetEx),
+    PTtl(PTtl),
+    Touch(Touch),
+    Hello(Hello),
+    Compress(Compress),
+    PSync(PSync),
+    ReplConf(ReplConf),
+    Config(Config),
+    HotKeys(HotKeys),
+    IncrBy(IncrBy),
+    Exists(Exists),
+    Del(Del),
+    GetRange(GetRange),
+    Memory(Memory),
+    Unlink(Unlink),
+    Lcs(Lcs),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Dump(Dump),
+    Restore(Restore),
+    ExpireMany(ExpireMany),
+    LPush(LPush),
+    RPush(RPush),
+    LPop(LPop),
+    RPop(RPop),
+    LRange(LRange),
+    HSet(HSet),
+    HGet(HGet),
+    HDel(HDel),
+    HGetAll(HGetAll),
+    XAdd(XAdd),
+    XRange(XRange),
+    XSubscribe(XSubscribe),
+    Info(Info),
     Unknown(Unknown)
 }
 
@@ -41,17 +318,17 @@ impl Command {
     pub fn from_frame(frame:Frame) -> crate::Result<Command> {
         let mut parse = Parse::new(frame)?;
 
-        let command_name = parse.next_string()?.to_lowercase();
-
-        let command = match &command_name[..] {
-            "get" => Command::Get(Get::parse_frames(&mut parse)?),
-            "publish" => Command::Publish(Publish::parse_frames(&mut parse)?),
-            "set" => Command::Set(Set::parse_frames(&mut parse)?),
-            "subscribe" => Command::Subcribe(Subscribe::parse_frames(&mut parse)?),
-            "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
-            "ping" => Command::Ping(Ping::parse_frames(&mut parse)?),
-            _ => {
-                return Ok(Command::Unknown(Unknown::new(command_name)));
+        // 命令名只和已知的名字比较字节，不要求是合法的UTF-8
+        let command_name = parse.next_token()?;
+
+        let command = match COMMANDS.iter().find(|(name, ..)| command_name.is(name)) {
+            Some((_, _, _, parse_frames)) => parse_frames(&mut parse)?,
+            None => {
+                let name = match command_name.as_str() {
+                    Ok(name) => name.to_lowercase(),
+                    Err(_) => String::from_utf8_lossy(command_name.as_bytes()).to_lowercase(),
+                };
+                return Ok(Command::Unknown(Unknown::new(name)));
             }
         };
 
@@ -68,7 +345,9 @@ impl Command {
         self,
         db: &Db,
         dst: &mut Connection,
-        shutdown: &mut Shutdown
+        shutdown: &mut Shutdown,
+        stats: &Stats,
+        max_subscribe_churn: Option<u32>,
     ) -> crate::Result<()> {
         use Command::*;
 
@@ -76,23 +355,330 @@ impl Command {
             Get(cmd) => cmd.apply(db, dst).await,
             Publish(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
-            Subcribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            Strlen(cmd) => cmd.apply(db, dst).await,
+            Subscribe(cmd) => cmd.apply(db, dst, shutdown, max_subscribe_churn).await,
+            PSubscribe(cmd) => cmd.apply(db, dst, shutdown, max_subscribe_churn).await,
             Ping(cmd) => cmd.apply(dst).await,
+            Append(cmd) => cmd.apply(db, dst).await,
+            SetRange(cmd) => cmd.apply(db, dst).await,
+            Object(cmd) => cmd.apply(db, dst).await,
+            IncrByFloat(cmd) => cmd.apply(db, dst).await,
+            MGet(cmd) => cmd.apply(db, dst).await,
+            Keys(cmd) => cmd.apply(db, dst).await,
+            Scan(cmd) => cmd.apply(db, dst).await,
+            DbSize(cmd) => cmd.apply(db, dst).await,
+            Debug(cmd) => cmd.apply(db, dst).await,
+            FlushDb(cmd) => cmd.apply(db, dst).await,
+            Exchange(cmd) => cmd.apply(db, dst).await,
+            Type(cmd) => cmd.apply(db, dst).await,
+            SetEx(cmd) => cmd.apply(db, dst).await,
+            Health(cmd) => cmd.apply(stats, dst).await,
+            Cas(cmd) => cmd.apply(db, dst).await,
+            MSet(cmd) => cmd.apply(db, dst).await,
+            CommandInfo(cmd) => cmd.apply(dst).await,
+            Wait(cmd) => cmd.apply(dst).await,
+            PubSub(cmd) => cmd.apply(db, dst).await,
+            GetEx(cmd) => cmd.apply(db, dst).await,
+            PTtl(cmd) => cmd.apply(db, dst).await,
+            Touch(cmd) => cmd.apply(db, dst).await,
+            Hello(cmd) => cmd.apply(db, dst).await,
+            Compress(cmd) => cmd.apply(dst).await,
+            PSync(cmd) => cmd.apply(db, dst, shutdown).await,
+            ReplConf(cmd) => cmd.apply(dst).await,
+            Config(cmd) => cmd.apply(db, dst).await,
+            HotKeys(cmd) => cmd.apply(db, dst).await,
+            IncrBy(cmd) => cmd.apply(db, dst).await,
+            Exists(cmd) => cmd.apply(db, dst).await,
+            Del(cmd) => cmd.apply(db, dst).await,
+            GetRange(cmd) => cmd.apply(db, dst).await,
+            Memory(cmd) => cmd.apply(db, dst).await,
+            Unlink(cmd) => cmd.apply(db, dst).await,
+            Lcs(cmd) => cmd.apply(db, dst).await,
+            Exec(cmd) => cmd.apply(db, dst).await,
+            Dump(cmd) => cmd.apply(db, dst).await,
+            Restore(cmd) => cmd.apply(db, dst).await,
+            ExpireMany(cmd) => cmd.apply(db, dst).await,
+            LPush(cmd) => cmd.apply(db, dst).await,
+            RPush(cmd) => cmd.apply(db, dst).await,
+            LPop(cmd) => cmd.apply(db, dst).await,
+            RPop(cmd) => cmd.apply(db, dst).await,
+            LRange(cmd) => cmd.apply(db, dst).await,
+            HSet(cmd) => cmd.apply(db, dst).await,
+            HGet(cmd) => cmd.apply(db, dst).await,
+            HDel(cmd) => cmd.apply(db, dst).await,
+            HGetAll(cmd) => cmd.apply(db, dst).await,
+            XAdd(cmd) => cmd.apply(db, dst).await,
+            XRange(cmd) => cmd.apply(db, dst).await,
+            XSubscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            Info(cmd) => cmd.apply(db, dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
+            // `MULTI`和`DISCARD`只改变连接的事务状态，由`Handler`处理
+            Multi(_) | Discard(_) => Err("transaction commands are handled by the connection".into()),
             // `Unsubscribe` 无法被执行，它只能在`Subscribe`指令
             // 执行时，被收到
             Unsubscribe(_) => Err("`Unsubscribe` is unsupported in this context.".into()),
+            PUnsubscribe(_) => Err("`PUnsubscribe` is unsupported in this context.".into()),
+        }
+    }
+
+    /// Returns whether the command can be queued in a transaction.
+    ///
+    /// The commands of a transaction are executed by `execute`, on the
+    /// keyspace alone. Commands changing the state of the connection, e.g.
+    /// `SUBSCRIBE` or `COMPRESS`, commands which may block, and the
+    /// administrative ones are rejected when queued.
+    pub(crate) fn is_transactional(&self) -> bool {
+        use Command::*;
+
+        !matches!(
+            self,
+            Subscribe(_)
+                | Unsubscribe(_)
+                | PSubscribe(_)
+                | PUnsubscribe(_)
+                | XSubscribe(_)
+                | Debug(_)
+                | Health(_)
+                | PubSub(_)
+                | Hello(_)
+                | Info(_)
+                | Compress(_)
+                | PSync(_)
+                | ReplConf(_)
+                | Config(_)
+                | HotKeys(_)
+                | Memory(_)
+                | Multi(_)
+                | Exec(_)
+                | Discard(_)
+        )
+    }
+
+    /// Executes a command queued in a transaction on the keyspace, and
+    /// returns its reply.
+    ///
+    /// `EXEC` calls it for every queued command under the same lock of the
+    /// `Db`, so the other clients see all of the transaction or none of it.
+    /// Nothing is written to the connection while the lock is held.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        use Command::*;
+
+        match self {
+            Get(cmd) => cmd.execute(view),
+            Publish(cmd) => cmd.execute(view),
+            Set(cmd) => cmd.execute(view),
+            Strlen(cmd) => cmd.execute(view),
+            Ping(cmd) => cmd.execute(),
+            Append(cmd) => cmd.execute(view),
+            SetRange(cmd) => cmd.execute(view),
+            Object(cmd) => cmd.execute(view),
+            IncrByFloat(cmd) => cmd.execute(view),
+            MGet(cmd) => cmd.execute(view),
+            Keys(cmd) => cmd.execute(view),
+            Scan(cmd) => cmd.execute(view),
+            DbSize(cmd) => cmd.execute(view),
+            FlushDb(cmd) => cmd.execute(view),
+            Exchange(cmd) => cmd.execute(view),
+            Type(cmd) => cmd.execute(view),
+            SetEx(cmd) => cmd.execute(view),
+            Cas(cmd) => cmd.execute(view),
+            MSet(cmd) => cmd.execute(view),
+            CommandInfo(cmd) => cmd.execute(),
+            Wait(cmd) => cmd.execute(),
+            GetEx(cmd) => cmd.execute(view),
+            PTtl(cmd) => cmd.execute(view),
+            Touch(cmd) => cmd.execute(view),
+            IncrBy(cmd) => cmd.execute(view),
+            Exists(cmd) => cmd.execute(view),
+            Del(cmd) => cmd.execute(view),
+            GetRange(cmd) => cmd.execute(view),
+            Unlink(cmd) => cmd.execute(view),
+            Lcs(cmd) => cmd.execute(view),
+            Dump(cmd) => cmd.execute(view),
+            Restore(cmd) => cmd.execute(view),
+            ExpireMany(cmd) => cmd.execute(view),
+            LPush(cmd) => cmd.execute(view),
+            RPush(cmd) => cmd.execute(view),
+            LPop(cmd) => cmd.execute(view),
+            RPop(cmd) => cmd.execute(view),
+            LRange(cmd) => cmd.execute(view),
+            HSet(cmd) => cmd.execute(view),
+            HGet(cmd) => cmd.execute(view),
+            HDel(cmd) => cmd.execute(view),
+            HGetAll(cmd) => cmd.execute(view),
+            XAdd(cmd) => cmd.execute(view),
+            XRange(cmd) => cmd.execute(view),
+            Unknown(cmd) => cmd.execute(),
+            // 这些命令在入队时已经被拒绝，见`is_transactional`
+            _ => Frame::Error("ERR Command not allowed inside a transaction".to_string()),
+        }
+    }
+
+    /// Returns the keys the command reads or writes, in the order of its
+    /// arguments.
+    ///
+    /// Channels and patterns are not keys: `PUBLISH` or `KEYS` touch none.
+    /// This tells where a command must be sent when the keys are spread
+    /// across several servers, see `cluster::crc16_hash_slot`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_mini_redis::cmd::{Command, Del};
+    ///
+    /// let cmd = Command::Del(Del::new(vec!["foo".to_string(), "bar".to_string()]));
+    /// assert_eq!(vec![&b"foo"[..], &b"bar"[..]], cmd.keys());
+    /// ```
+    pub fn keys(&self) -> Vec<&[u8]> {
+        use Command::*;
+
+        fn all(keys: &[String]) -> Vec<&[u8]> {
+            keys.iter().map(|key| key.as_bytes()).collect()
+        }
+
+        match self {
+            Get(cmd) => vec![cmd.key().as_bytes()],
+            Set(cmd) => vec![cmd.key().as_bytes()],
+            Strlen(cmd) => vec![cmd.key().as_bytes()],
+            Append(cmd) => vec![cmd.key().as_bytes()],
+            SetRange(cmd) => vec![cmd.key().as_bytes()],
+            Object(cmd) => cmd.key().map(str::as_bytes).into_iter().collect(),
+            IncrByFloat(cmd) => vec![cmd.key().as_bytes()],
+            MGet(cmd) => all(cmd.keys()),
+            Exchange(cmd) => vec![cmd.key1().as_bytes(), cmd.key2().as_bytes()],
+            Type(cmd) => vec![cmd.key().as_bytes()],
+            SetEx(cmd) => vec![cmd.key().as_bytes()],
+            Cas(cmd) => vec![cmd.key().as_bytes()],
+            MSet(cmd) => cmd.pairs().iter().map(|(key, _)| key.as_bytes()).collect(),
+            GetEx(cmd) => vec![cmd.key().as_bytes()],
+            PTtl(cmd) => vec![cmd.key().as_bytes()],
+            Touch(cmd) => all(cmd.keys()),
+            IncrBy(cmd) => vec![cmd.key().as_bytes()],
+            Exists(cmd) => all(cmd.keys()),
+            Del(cmd) => all(cmd.keys()),
+            GetRange(cmd) => vec![cmd.key().as_bytes()],
+            Unlink(cmd) => all(cmd.keys()),
+            Lcs(cmd) => vec![cmd.key1().as_bytes(), cmd.key2().as_bytes()],
+            Exec(cmd) => cmd.commands().iter().flat_map(Command::keys).collect(),
+            Dump(cmd) => vec![cmd.key().as_bytes()],
+            Restore(cmd) => vec![cmd.key().as_bytes()],
+            ExpireMany(cmd) => all(cmd.keys()),
+            LPush(cmd) => vec![cmd.key().as_bytes()],
+            RPush(cmd) => vec![cmd.key().as_bytes()],
+            LPop(cmd) => vec![cmd.key().as_bytes()],
+            RPop(cmd) => vec![cmd.key().as_bytes()],
+            LRange(cmd) => vec![cmd.key().as_bytes()],
+            HSet(cmd) => vec![cmd.key().as_bytes()],
+            HGet(cmd) => vec![cmd.key().as_bytes()],
+            HDel(cmd) => vec![cmd.key().as_bytes()],
+            HGetAll(cmd) => vec![cmd.key().as_bytes()],
+            XAdd(cmd) => vec![cmd.key().as_bytes()],
+            XRange(cmd) => vec![cmd.key().as_bytes()],
+            XSubscribe(cmd) => vec![cmd.key().as_bytes()],
+            Publish(_) | Subscribe(_) | Unsubscribe(_) | PSubscribe(_) | PUnsubscribe(_) | Ping(_) | Keys(_) | Scan(_) | DbSize(_) | Debug(_)
+            | FlushDb(_) | Health(_) | CommandInfo(_) | Wait(_) | PubSub(_) | Hello(_) | Compress(_) | PSync(_) | ReplConf(_) | Config(_) | HotKeys(_)
+            | Memory(_) | Multi(_) | Discard(_) | Info(_) | Unknown(_) => vec![],
         }
     }
 
+    /// Returns `true` if the command is exempt from the server's per-command
+    /// deadline.
+    ///
+    /// This is the case of the commands which are expected to run for a long
+    /// time and manage their own lifetime, like `SUBSCRIBE` which keeps the
+    /// connection in pub/sub mode until the client leaves it, or `PSYNC`.
+    /// They are flagged `NO_DEADLINE` in `COMMANDS`.
+    pub(crate) fn exempt_from_deadline(&self) -> bool {
+        self.flags() & NO_DEADLINE != 0
+    }
+
+    /// Returns `true` if the command may wait before replying, like
+    /// `SUBSCRIBE` or `DEBUG SLEEP`. They are flagged `MAY_BLOCK` in
+    /// `COMMANDS`.
+    ///
+    /// The replies to the commands pipelined before it are flushed first, so
+    /// the client does not wait for them as well.
+    pub(crate) fn may_block(&self) -> bool {
+        self.flags() & MAY_BLOCK != 0
+    }
+
+    /// Returns the flags of the command in `COMMANDS`, none for an unknown
+    /// command.
+    fn flags(&self) -> CommandFlags {
+        let name = self.get_name();
+
+        COMMANDS
+            .iter()
+            .find(|(command, ..)| *command == name)
+            .map(|(_, _, flags, _)| *flags)
+            .unwrap_or(0)
+    }
+
     pub(crate) fn get_name(&self) -> &str {
         match self {
             Command::Get(_) => "get",
             Command::Publish(_) => "publish",
             Command::Set(_) => "set",
-            Command::Subcribe(_) => "subscribe",
+            Command::Strlen(_) => "strlen",
+            Command::Subscribe(_) => "subscribe",
             Command::Unsubscribe(_) => "unsubscribe",
+            Command::PSubscribe(_) => "psubscribe",
+            Command::PUnsubscribe(_) => "punsubscribe",
             Command::Ping(_) => "ping",
+            Command::Append(_) => "append",
+            Command::SetRange(_) => "setrange",
+            Command::Object(_) => "object",
+            Command::IncrByFloat(_) => "incrbyfloat",
+            Command::MGet(_) => "mget",
+            Command::Keys(_) => "keys",
+            Command::Scan(_) => "scan",
+            Command::DbSize(_) => "dbsize",
+            Command::Debug(_) => "debug",
+            Command::FlushDb(_) => "flushdb",
+            Command::Exchange(_) => "exchange",
+            Command::Type(_) => "type",
+            Command::SetEx(cmd) => cmd.get_name(),
+            Command::Health(_) => "health",
+            Command::Cas(_) => "cas",
+            Command::MSet(_) => "mset",
+            Command::CommandInfo(_) => "command",
+            Command::Wait(_) => "wait",
+            Command::PubSub(_) => "pubsub",
+            Command::GetEx(_) => "getex",
+            Command::PTtl(_) => "pttl",
+            Command::Touch(_) => "touch",
+            Command::Hello(_) => "hello",
+            Command::Compress(_) => "compress",
+            Command::PSync(_) => "psync",
+            Command::ReplConf(_) => "replconf",
+            Command::Config(_) => "config",
+            Command::HotKeys(_) => "hotkeys",
+            Command::IncrBy(cmd) => cmd.get_name(),
+            Command::Exists(_) => "exists",
+            Command::Del(_) => "del",
+            Command::GetRange(_) => "getrange",
+            Command::Memory(_) => "memory",
+            Command::Unlink(_) => "unlink",
+            Command::Lcs(_) => "lcs",
+            Command::Multi(_) => "multi",
+            Command::Exec(_) => "exec",
+            Command::Discard(_) => "discard",
+            Command::Dump(_) => "dump",
+            Command::Restore(_) => "restore",
+            Command::ExpireMany(_) => "expiremany",
+            Command::LPush(_) => "lpush",
+            Command::RPush(_) => "rpush",
+            Command::LPop(_) => "lpop",
+            Command::RPop(_) => "rpop",
+            Command::LRange(_) => "lrange",
+            Command::HSet(_) => "hset",
+            Command::HGet(_) => "hget",
+            Command::HDel(_) => "hdel",
+            Command::HGetAll(_) => "hgetall",
+            Command::XAdd(_) => "xadd",
+            Command::XRange(_) => "xrange",
+            Command::XSubscribe(_) => "xsubscribe",
+            Command::Info(_) => "info",
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
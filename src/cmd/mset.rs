@@ -0,0 +1,109 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Set all the given keys to their respective values.
+///
+/// The keys are set atomically: other clients never observe only some of them
+/// being updated. As with `SET`, any time to live associated with the keys is
+/// discarded.
+#[derive(Debug)]
+pub struct MSet {
+    pairs: Vec<(String, Bytes)>,
+}
+
+impl MSet {
+    /// Create a new `MSet` command which sets each key to its value.
+    pub fn new(pairs: Vec<(String, Bytes)>) -> MSet {
+        MSet { pairs }
+    }
+
+    /// Get the keys and their values
+    pub fn pairs(&self) -> &[(String, Bytes)] {
+        &self.pairs
+    }
+
+    /// Parse a `MSet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MSET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `MSet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing an odd number of entries, at least
+    /// three.
+    ///
+    /// ```text
+    /// MSET key value [key value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MSet> {
+        use ParseError::EndOfStream;
+
+        let mut pairs = vec![(parse.next_string()?, parse.next_bytes()?)];
+
+        loop {
+            let key = match parse.next_string() {
+                Ok(key) => key,
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            // 键之后必须跟着值
+            pairs.push((key, parse.next_bytes()?));
+        }
+
+        Ok(MSet { pairs })
+    }
+
+    /// Apply the `MSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `MSet` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        // 所有的key在同一次加锁中写入
+        for (key, value) in self.pairs {
+            view.set(&key, value);
+        }
+
+        Frame::Simple("OK".to_string())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `MSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("mset".as_bytes()));
+        for (key, value) in self.pairs {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+            frame.push_bulk(value);
+        }
+        frame
+    }
+}
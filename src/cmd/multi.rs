@@ -0,0 +1,124 @@
+use crate::cmd::Command;
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Starts a transaction.
+///
+/// The commands received afterwards are not applied but queued, and answered
+/// with `QUEUED`, until `EXEC` applies them or `DISCARD` drops them. The
+/// transaction is a state of the connection, kept by the server's handler.
+#[derive(Debug, Default)]
+pub struct Multi;
+
+/// Applies the commands queued since `MULTI`.
+///
+/// The commands are applied in order under a single lock of the keyspace, so
+/// the other clients see all of their changes or none of them, and their
+/// replies are returned as an array. A command failing, e.g.
+/// `INCR` on a value which is not an integer, does not stop the next ones:
+/// its error is returned in the array. Keys may still expire during the
+/// transaction.
+#[derive(Debug, Default)]
+pub struct Exec {
+    /// The commands queued since `MULTI`, set by the handler of the
+    /// connection when `EXEC` is received.
+    commands: Vec<Command>,
+}
+
+/// Drops the commands queued since `MULTI`, ending the transaction.
+#[derive(Debug, Default)]
+pub struct Discard;
+
+impl Multi {
+    /// Create a new `Multi` command.
+    pub fn new() -> Multi {
+        Multi
+    }
+
+    /// Parse a `Multi` instance from a received frame.
+    ///
+    /// The `MULTI` string has already been consumed, and the command takes no
+    /// arguments. Extra arguments are rejected by `Parse::finish`.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// MULTI
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Multi> {
+        Ok(Multi)
+    }
+}
+
+impl Exec {
+    /// Create a new `Exec` command.
+    pub fn new() -> Exec {
+        Exec::default()
+    }
+
+    /// Returns the command applying the queued `commands`.
+    pub(crate) fn with_commands(commands: Vec<Command>) -> Exec {
+        Exec { commands }
+    }
+
+    /// Returns the commands applied by the transaction.
+    pub(crate) fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Parse an `Exec` instance from a received frame.
+    ///
+    /// The `EXEC` string has already been consumed, and the command takes no
+    /// arguments. Extra arguments are rejected by `Parse::finish`.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// EXEC
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Exec> {
+        Ok(Exec::new())
+    }
+
+    /// Apply the queued commands to the specified `Db` instance.
+    ///
+    /// The commands are executed under the lock of the `Db`, which is released
+    /// before the replies are written to `dst` as the elements of a single
+    /// array.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        debug!(commands = self.commands.len());
+
+        // 所有命令在同一次加锁中执行，写回复时锁已经释放
+        let replies = db.atomic(|view| self.commands.into_iter().map(|cmd| cmd.execute(view)).collect());
+        let response = Frame::Array(replies);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl Discard {
+    /// Create a new `Discard` command.
+    pub fn new() -> Discard {
+        Discard
+    }
+
+    /// Parse a `Discard` instance from a received frame.
+    ///
+    /// The `DISCARD` string has already been consumed, and the command takes
+    /// no arguments. Extra arguments are rejected by `Parse::finish`.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// DISCARD
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Discard> {
+        Ok(Discard)
+    }
+}
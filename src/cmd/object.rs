@@ -0,0 +1,181 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspect the internals of the value stored at a key.
+///
+/// The `ENCODING`, `REFCOUNT` and `IDLETIME` subcommands are supported. Values are always
+/// stored as raw bytes; a value is reported as `int` when it is the canonical
+/// form of a 64 bit signed integer, which is when Redis would store it as an
+/// integer. The encoding is derived from the value itself, so commands
+/// modifying a value in place, like `APPEND` or `SETRANGE`, can never leave a
+/// stale `int` encoding behind.
+///
+/// Small integers written by `SET` and `INCRBYFLOAT` share a single
+/// allocation, and `REFCOUNT` reports them with the maximum count, like Redis
+/// does for its shared integers. Other values have a count of 1.
+///
+/// `IDLETIME` reports the number of seconds since the key was last written,
+/// read by `GET` or touched by `TOUCH`. Inspecting a key with `OBJECT` does
+/// not count as an access.
+#[derive(Debug)]
+pub struct Object {
+    subcommand: ObjectSubcommand,
+}
+
+#[derive(Debug)]
+enum ObjectSubcommand {
+    /// OBJECT ENCODING key
+    Encoding(String),
+
+    /// OBJECT REFCOUNT key
+    Refcount(String),
+
+    /// OBJECT IDLETIME key
+    IdleTime(String),
+
+    /// Any subcommand that is not supported. An error is returned to the
+    /// client instead of closing the connection.
+    Unknown(String),
+}
+
+impl Object {
+    /// Create a new `Object` command which fetches the encoding of `key`.
+    pub fn encoding(key: impl ToString) -> Object {
+        Object {
+            subcommand: ObjectSubcommand::Encoding(key.to_string()),
+        }
+    }
+
+    /// Create a new `Object` command which fetches the reference count of
+    /// `key`.
+    pub fn refcount(key: impl ToString) -> Object {
+        Object {
+            subcommand: ObjectSubcommand::Refcount(key.to_string()),
+        }
+    }
+
+    /// Create a new `Object` command which fetches the idle time of `key`.
+    pub fn idle_time(key: impl ToString) -> Object {
+        Object {
+            subcommand: ObjectSubcommand::IdleTime(key.to_string()),
+        }
+    }
+
+    /// Get the key inspected, `None` for an unknown subcommand.
+    pub fn key(&self) -> Option<&str> {
+        match &self.subcommand {
+            ObjectSubcommand::Encoding(key) | ObjectSubcommand::Refcount(key) | ObjectSubcommand::IdleTime(key) => {
+                Some(key)
+            }
+            ObjectSubcommand::Unknown(_) => None,
+        }
+    }
+
+    /// Parse an `Object` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `OBJECT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Object` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a subcommand and its arguments.
+    ///
+    /// ```text
+    /// OBJECT ENCODING key
+    /// OBJECT REFCOUNT key
+    /// OBJECT IDLETIME key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Object> {
+        let subcommand = parse.next_string()?;
+
+        let subcommand = match &subcommand.to_uppercase()[..] {
+            "ENCODING" => ObjectSubcommand::Encoding(parse.next_string()?),
+            "REFCOUNT" => ObjectSubcommand::Refcount(parse.next_string()?),
+            "IDLETIME" => ObjectSubcommand::IdleTime(parse.next_string()?),
+            _ => {
+                // 忽略剩下的参数，以便`finish()`不会失败
+                while parse.next().is_ok() {}
+                ObjectSubcommand::Unknown(subcommand)
+            }
+        };
+
+        Ok(Object { subcommand })
+    }
+
+    /// Apply the `Object` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Object` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match self.subcommand {
+            ObjectSubcommand::Encoding(key) => match view.encoding(&key) {
+                Some(encoding) => Frame::Bulk(Bytes::from_static(encoding.as_bytes())),
+                None => Frame::Null,
+            },
+            ObjectSubcommand::Refcount(key) => match view.refcount(&key) {
+                Some(count) => Frame::Integer(count),
+                None => Frame::Null,
+            },
+            ObjectSubcommand::IdleTime(key) => match view.idle_time(&key) {
+                Some(idle) => Frame::Integer(idle.as_secs()),
+                None => Frame::Null,
+            },
+            ObjectSubcommand::Unknown(name) => {
+                Frame::Error(format!("ERR unknown subcommand '{}'", name))
+            }
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Object` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("object".as_bytes()));
+        match self.subcommand {
+            ObjectSubcommand::Encoding(key) => {
+                frame.push_bulk(Bytes::from("encoding".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            ObjectSubcommand::Refcount(key) => {
+                frame.push_bulk(Bytes::from("refcount".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            ObjectSubcommand::IdleTime(key) => {
+                frame.push_bulk(Bytes::from("idletime".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            ObjectSubcommand::Unknown(name) => {
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+        }
+        frame
+    }
+}
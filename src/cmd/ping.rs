@@ -1,3 +1,5 @@
+use crate::connection::Protocol;
+use crate::pubsub::PubSubReply;
 use crate::{Connection, Frame, Parse, ParseError};
 use bytes::Bytes;
 use tracing::{debug, instrument};
@@ -53,18 +55,51 @@ impl Ping {
     /// to execute a received command.
     #[instrument(skip(self, dst))]
     pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
-        let response = match self.msg {
+        let response = self.execute();
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Ping` command, and returns the reply.
+    ///
+    /// The reply does not depend on the keyspace, so the command can be queued
+    /// in a transaction as well.
+    pub(crate) fn execute(self) -> Frame {
+        match self.msg {
             None => Frame::Simple("PONG".to_string()),
             Some(msg) => Frame::Bulk(msg),
-        };
-        
+        }
+    }
+
+    /// Apply the `Ping` command received while the connection is in pub/sub
+    /// mode.
+    ///
+    /// Over RESP2 the reply is a pub/sub array, `[ "pong", message ]`, so that
+    /// subscribers reading every frame as a pub/sub reply can parse it. Over
+    /// RESP3 the reply is the same as outside of pub/sub mode.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply_subscribed(self, dst: &mut Connection) -> crate::Result<()> {
+        if dst.protocol() == Protocol::Resp3 {
+            return self.apply(dst).await;
+        }
+
+        let response = PubSubReply::Pong {
+            content: self.msg.unwrap_or_default(),
+        }
+        .to_frame();
+
         debug!(?response);
 
         dst.write_frame(&response).await?;
 
         Ok(())
     }
-    /// Converts the command into an equivalent `Frame`.
+
+
     ///
     /// This is called by the client when encoding a `Ping` command to send
     /// to the server.
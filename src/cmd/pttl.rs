@@ -0,0 +1,117 @@
+use crate::clients::FromFrame;
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Returns the remaining time to live of a key, in milliseconds.
+///
+/// As in Redis, a key without a time to live is reported with the integer
+/// `-1`, and a missing key with `-2`.
+#[derive(Debug)]
+pub struct PTtl {
+    key: String,
+}
+
+/// Time to live of a key, as replied to a `PTTL` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ttl {
+    /// The key does not exist.
+    Missing,
+
+    /// The key exists and does not expire.
+    Persistent,
+
+    /// The key expires after the given duration.
+    Expires(Duration),
+}
+
+impl PTtl {
+    /// Create a new `PTtl` command which fetches the time to live of `key`.
+    pub fn new(key: impl ToString) -> PTtl {
+        PTtl {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `PTtl` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PTTL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `PTtl` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// PTTL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PTtl> {
+        let key = parse.next_string()?;
+        Ok(PTtl { key })
+    }
+
+    /// Apply the `PTtl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `PTtl` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.ttl(&self.key) {
+            Ttl::Missing => Frame::SignedInteger(-2),
+            Ttl::Persistent => Frame::SignedInteger(-1),
+            Ttl::Expires(ttl) => Frame::Integer(ttl.as_millis() as u64),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PTtl` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pttl".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
+
+impl FromFrame for Ttl {
+    fn from_frame(frame: Frame) -> crate::Result<Ttl> {
+        match i64::from_frame(frame)? {
+            -2 => Ok(Ttl::Missing),
+            -1 => Ok(Ttl::Persistent),
+            ms if ms >= 0 => Ok(Ttl::Expires(Duration::from_millis(ms as u64))),
+            _ => Err("protocol error; invalid time to live".into()),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::db::StateView;
 use crate::{Connection, Db, Frame, Parse};
 use bytes::Bytes;
 
@@ -64,7 +65,17 @@ impl Publish {
         dst.write_frame(&response).await?;
 
         Ok(())
-    } 
+    }
+
+    /// Executes the `Publish` command inside a transaction, and returns the
+    /// reply.
+    ///
+    /// The message is delivered while the lock of the `Db` is held, so it is
+    /// ordered with the other commands of the transaction.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        Frame::Integer(view.publish(&self.channel, self.message) as u64)
+    }
+
     /// Converts the command into an equivalent `Frame`.
     ///
     /// This is called by the client when encoding a `Publish` command to send
@@ -0,0 +1,224 @@
+use crate::clients::FromFrame;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument};
+
+/// Inspect the pub/sub channels.
+///
+/// The `CHANNELS` subcommand lists the channels with at least one subscriber,
+/// those matching a glob-style pattern if one is given. Channels are forgotten
+/// once their last subscriber leaves.
+///
+/// The `STATS` subcommand is an extension which reports the traffic on a
+/// channel, so that a subscriber reconnecting can tell
+/// whether it missed messages. The reply is an array of field names each
+/// followed by its value:
+///
+/// ```text
+/// 1) "published"
+/// 2) (integer) 12
+/// 3) "last_published_ms"
+/// 4) (integer) 1700000000000
+/// 5) "peak_receivers"
+/// 6) (integer) 3
+/// 7) "receivers"
+/// 8) (integer) 2
+/// ```
+///
+/// `last_published_ms` is a Unix timestamp in milliseconds, 0 if nothing was
+/// ever published on the channel.
+#[derive(Debug)]
+pub struct PubSub {
+    subcommand: PubSubSubcommand,
+}
+
+#[derive(Debug)]
+enum PubSubSubcommand {
+    /// PUBSUB CHANNELS [pattern]
+    Channels(Option<String>),
+
+    /// PUBSUB STATS channel
+    Stats(String),
+
+    /// Any subcommand that is not supported. An error is returned to the
+    /// client instead of closing the connection.
+    Unknown(String),
+}
+
+/// Statistics of the traffic on a channel, as replied to `PUBSUB STATS`.
+///
+/// Only channels which have been subscribed to at least once are tracked.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChannelStats {
+    /// Number of messages published on the channel.
+    pub published: u64,
+
+    /// When the last message was published, with a millisecond precision.
+    pub last_published: Option<SystemTime>,
+
+    /// Highest number of subscribers the channel had at once.
+    pub peak_receivers: u64,
+
+    /// Number of subscribers the channel currently has.
+    pub receivers: u64,
+}
+
+impl PubSub {
+    /// Create a new `PubSub` command which lists the channels with
+    /// subscribers, matching `pattern` if given.
+    pub fn channels(pattern: Option<String>) -> PubSub {
+        PubSub {
+            subcommand: PubSubSubcommand::Channels(pattern),
+        }
+    }
+
+    /// Create a new `PubSub` command which fetches the statistics of
+    /// `channel`.
+    pub fn stats(channel: impl ToString) -> PubSub {
+        PubSub {
+            subcommand: PubSubSubcommand::Stats(channel.to_string()),
+        }
+    }
+
+    /// Parse a `PubSub` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PUBSUB` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `PubSub` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a subcommand and its arguments.
+    ///
+    /// ```text
+    /// PUBSUB CHANNELS [pattern]
+    /// PUBSUB STATS channel
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PubSub> {
+        use ParseError::EndOfStream;
+
+        let subcommand = parse.next_string()?;
+
+        let subcommand = match &subcommand.to_uppercase()[..] {
+            "CHANNELS" => match parse.next_string() {
+                Ok(pattern) => PubSubSubcommand::Channels(Some(pattern)),
+                Err(EndOfStream) => PubSubSubcommand::Channels(None),
+                Err(err) => return Err(err.into()),
+            },
+            "STATS" => PubSubSubcommand::Stats(parse.next_string()?),
+            _ => {
+                // 忽略剩下的参数，以便`finish()`不会失败
+                while parse.next().is_ok() {}
+                PubSubSubcommand::Unknown(subcommand)
+            }
+        };
+
+        Ok(PubSub { subcommand })
+    }
+
+    /// Apply the `PubSub` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            PubSubSubcommand::Channels(pattern) => {
+                let mut response = Frame::array();
+                for channel in db.channels(pattern.as_deref()) {
+                    response.push_bulk(Bytes::from(channel.into_bytes()));
+                }
+                response
+            }
+            PubSubSubcommand::Stats(channel) => {
+                let stats = db.channel_stats(&channel);
+
+                let last_published = stats
+                    .last_published
+                    .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+                    .map(|at| at.as_millis() as u64)
+                    .unwrap_or(0);
+
+                let mut response = Frame::array();
+                response.push_bulk(Bytes::from("published".as_bytes()));
+                response.push_int(stats.published);
+                response.push_bulk(Bytes::from("last_published_ms".as_bytes()));
+                response.push_int(last_published);
+                response.push_bulk(Bytes::from("peak_receivers".as_bytes()));
+                response.push_int(stats.peak_receivers);
+                response.push_bulk(Bytes::from("receivers".as_bytes()));
+                response.push_int(stats.receivers);
+                response
+            }
+            PubSubSubcommand::Unknown(name) => {
+                Frame::Error(format!("ERR unknown subcommand '{}'", name))
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PubSub` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pubsub".as_bytes()));
+        match self.subcommand {
+            PubSubSubcommand::Channels(pattern) => {
+                frame.push_bulk(Bytes::from("channels".as_bytes()));
+                if let Some(pattern) = pattern {
+                    frame.push_bulk(Bytes::from(pattern.into_bytes()));
+                }
+            }
+            PubSubSubcommand::Stats(channel) => {
+                frame.push_bulk(Bytes::from("stats".as_bytes()));
+                frame.push_bulk(Bytes::from(channel.into_bytes()));
+            }
+            PubSubSubcommand::Unknown(name) => {
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+        }
+        frame
+    }
+}
+
+impl FromFrame for ChannelStats {
+    fn from_frame(frame: Frame) -> crate::Result<ChannelStats> {
+        let mut fields = Vec::<Frame>::from_frame(frame)?.into_iter();
+
+        let mut stats = ChannelStats::default();
+
+        // 忽略未知的字段，以便之后可以添加新的字段
+        while let (Some(name), Some(value)) = (fields.next(), fields.next()) {
+            match &String::from_frame(name)?[..] {
+                "published" => stats.published = u64::from_frame(value)?,
+                "last_published_ms" => {
+                    stats.last_published = match u64::from_frame(value)? {
+                        0 => None,
+                        at => Some(UNIX_EPOCH + Duration::from_millis(at)),
+                    }
+                }
+                "peak_receivers" => stats.peak_receivers = u64::from_frame(value)?,
+                "receivers" => stats.receivers = u64::from_frame(value)?,
+                _ => {}
+            }
+        }
+
+        Ok(stats)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::fanout::RecvError;
+use crate::replication::{self, Change};
+use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
+
+use bytes::Bytes;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::select;
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{debug, info, instrument, warn};
+
+/// Interval at which the primary pings the replica, so that the replica does
+/// not time out while no key changes. Same as `repl-ping-replica-period` in
+/// Redis.
+const PING_PERIOD: Duration = Duration::from_secs(10);
+
+/// Start the replication of the keyspace to the replica sending the command,
+/// usually a Redis server started with `--replicaof`.
+///
+/// Partial resynchronizations are not supported: the reply is always
+/// `+FULLRESYNC <replid> 0`, followed by a snapshot of the keyspace in the RDB
+/// format, then by the changes made afterwards, see `replication`. The
+/// connection stays a replication link until either side closes it. Only
+/// string values are supported, an error is replied if another type is
+/// stored, and the link is closed if one is written later.
+#[derive(Debug)]
+pub struct PSync {
+    replid: String,
+
+    offset: i64,
+}
+
+/// Configure the replication link, sent by a replica before `PSYNC`.
+///
+/// The options, like `listening-port` or `capa`, are accepted and ignored, as
+/// the primary always performs a full resynchronization. `ACK`, sent by the
+/// replica to report the offset it processed, is not replied to.
+#[derive(Debug)]
+pub struct ReplConf {
+    option: String,
+}
+
+impl PSync {
+    /// Parse a `PSync` instance from a received frame.
+    ///
+    /// The `PSYNC` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// PSYNC replid offset
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSync> {
+        let replid = parse.next_string()?;
+        let offset = parse.next_int_signed()?;
+
+        Ok(PSync { replid, offset })
+    }
+
+    /// Apply the `PSync` command, turning the connection into a replication
+    /// link until the replica disconnects or the server shuts down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let (snapshot, mut changes) = match db.replicate() {
+            Ok(replication) => replication,
+            Err(err) => {
+                let response = Frame::Error(err);
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        debug!(replid = %self.replid, offset = self.offset, "full resynchronization requested");
+        info!(keys = snapshot.len(), "full resynchronization of a replica");
+
+        let response = Frame::Simple(format!("FULLRESYNC {} 0", replication_id()));
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        dst.write_rdb(&replication::encode_rdb(&snapshot)).await?;
+        drop(snapshot);
+
+        // 和Redis一样，在第一个命令之前选择数据库
+        dst.write_frame(&command(&["SELECT", "0"])).await?;
+
+        let mut ping = time::interval(PING_PERIOD);
+        ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ping.tick().await;
+
+        loop {
+            select! {
+                change = changes.recv() => match change {
+                    Ok(Change::Command(frame)) => dst.write_frame(&frame).await?,
+                    Ok(Change::Unsupported(reason)) => {
+                        warn!(%reason, "replica disconnected");
+                        return Err(reason.into());
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                    Err(err) => {
+                        warn!(cause = %err, "replica disconnected, it missed changes");
+                        return Err(err.into());
+                    }
+                },
+                _ = ping.tick() => dst.write_frame(&command(&["PING"])).await?,
+                // 副本会定期发送`REPLCONF ACK`，不需要处理
+                res = dst.read_frame() => {
+                    if res?.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+    }
+}
+
+impl ReplConf {
+    /// Parse a `ReplConf` instance from a received frame.
+    ///
+    /// The `REPLCONF` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// REPLCONF option [arg ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ReplConf> {
+        let option = parse.next_string()?.to_lowercase();
+
+        // 选项的参数都被忽略
+        loop {
+            match parse.next_bytes() {
+                Ok(_) => {}
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ReplConf { option })
+    }
+
+    /// Apply the `ReplConf` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        if self.option == "ack" {
+            return Ok(());
+        }
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Returns a new replication ID, 40 hexadecimal characters like those of
+/// Redis. It only has to differ between resynchronizations, so the random
+/// keys of the standard hasher are enough.
+fn replication_id() -> String {
+    let state = RandomState::new();
+
+    let mut id: String = (0..3u64)
+        .map(|i| {
+            let mut hasher = state.build_hasher();
+            hasher.write_u64(i);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect();
+
+    id.truncate(40);
+    id
+}
+
+/// Returns the frame of a command sent on the replication stream.
+fn command(args: &[&'static str]) -> Frame {
+    let mut frame = Frame::array();
+    for arg in args {
+        frame.push_bulk(Bytes::from_static(arg.as_bytes()));
+    }
+    frame
+}
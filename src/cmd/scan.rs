@@ -0,0 +1,168 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Number of keys inspected by a `SCAN` call when `COUNT` is not given.
+const DEFAULT_COUNT: usize = 10;
+
+/// Incrementally iterates over the keys.
+///
+/// Each call returns a new cursor and a batch of keys. The iteration starts
+/// with cursor 0 and is complete when the server returns cursor 0 again.
+///
+/// Unlike `KEYS`, the lock is only held for one batch at a time, so it is safe
+/// to use on large key spaces. The price is weak consistency:
+///
+/// * a key present during the whole iteration is returned at least once;
+/// * a key added or removed during the iteration may or may not be returned.
+///
+/// The keys are visited in the order of a hash of their name and the cursor is
+/// the hash of the next key to visit, so adding or removing other keys never
+/// moves the keys which remain to be visited.
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+}
+
+impl Scan {
+    /// Create a new `Scan` command which resumes the iteration at `cursor`.
+    pub fn new(cursor: u64) -> Scan {
+        Scan {
+            cursor,
+            pattern: None,
+            count: DEFAULT_COUNT,
+        }
+    }
+
+    /// Only return the keys matching the glob-style `pattern`.
+    pub fn with_pattern(mut self, pattern: impl ToString) -> Scan {
+        self.pattern = Some(pattern.to_string());
+        self
+    }
+
+    /// Inspect `count` keys per call instead of the default.
+    pub fn with_count(mut self, count: usize) -> Scan {
+        self.count = count;
+        self
+    }
+
+    /// Get the cursor
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Get the pattern
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+
+    /// Get the count
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Parse a `Scan` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SCAN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Scan` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// SCAN cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Scan> {
+        use ParseError::EndOfStream;
+
+        let cursor = parse
+            .next_int()
+            .map_err(|_| "ERR invalid cursor")?;
+
+        let mut scan = Scan::new(cursor);
+
+        loop {
+            match parse.next_token() {
+                Ok(s) if s.is("MATCH") => {
+                    scan.pattern = Some(parse.next_string()?);
+                },
+                Ok(s) if s.is("COUNT") => {
+                    // 和redis一样，COUNT必须大于0
+                    match parse.next_int()? {
+                        0 => return Err("ERR syntax error".into()),
+                        count => scan.count = count as usize,
+                    }
+                },
+                Ok(_) => return Err("ERR syntax error".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(scan)
+    }
+
+    /// Apply the `Scan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Scan` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        let (cursor, keys) = view.scan(self.cursor, self.count, self.pattern.as_deref());
+
+        let mut batch = Frame::array();
+        for key in keys {
+            batch.push_bulk(Bytes::from(key));
+        }
+
+        // 和redis一样，游标以bulk string的形式返回
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from(cursor.to_string())),
+            batch,
+        ])
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Scan` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("scan".as_bytes()));
+        frame.push_bulk(Bytes::from(self.cursor.to_string()));
+        if let Some(pattern) = self.pattern {
+            frame.push_bulk(Bytes::from("match".as_bytes()));
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+        frame.push_bulk(Bytes::from("count".as_bytes()));
+        frame.push_int(self.count as u64);
+        frame
+    }
+}
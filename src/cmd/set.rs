@@ -1,7 +1,8 @@
+use crate::db::{SetOptions, StateView};
 use crate::{Parse, ParseError, Connection, Db, Frame};
 
 use bytes::Bytes;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::{debug, instrument};
 
 /// Set `key` to hold the string `value`.
@@ -16,6 +17,15 @@ use tracing::{debug, instrument};
 /// 
 /// * EX `seconds` -- Set the specified expire time, in seconds.
 /// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp` -- Expire at the specified Unix time, in seconds.
+/// * PXAT `timestamp` -- Expire at the specified Unix time, in milliseconds.
+/// * NX -- Only set the key if it does not already exist.
+/// * XX -- Only set the key if it already exists.
+/// * KEEPTTL -- Retain the time to live associated with the key. Cannot be
+///   combined with the other expire options.
+/// * GET -- Reply with the value previously stored at the key, or nil, instead
+///   of `OK`. When combined with NX or XX, the previous value is returned
+///   even if the write is skipped.
 #[derive(Debug)]
 pub struct Set {
     key: String,
@@ -23,6 +33,27 @@ pub struct Set {
     value: Bytes,
 
     expire: Option<Duration>,
+
+    expire_at: Option<SystemTime>,
+
+    condition: Option<SetCondition>,
+
+    keep_ttl: bool,
+
+    get: bool,
+}
+
+/// Condition under which a `SET` is performed.
+///
+/// When the condition does not hold, the key is left untouched and the server
+/// replies with `(nil)` instead of `OK`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    /// NX -- only set the key if it does not already exist.
+    Nx,
+
+    /// XX -- only set the key if it already exists.
+    Xx,
 }
 
 impl Set {
@@ -34,9 +65,45 @@ impl Set {
         Set {
             key: key.to_string(),
             value,
-            expire
+            expire,
+            expire_at: None,
+            condition: None,
+            keep_ttl: false,
+            get: false,
         }
     }
+
+    /// Only perform the `Set` if `condition` holds.
+    pub fn with_condition(mut self, condition: SetCondition) -> Set {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Expire the key at the time `at` instead of after a duration.
+    ///
+    /// The expire given to `new` is ignored. A time in the past expires the
+    /// key immediately.
+    pub fn with_expire_at(mut self, at: SystemTime) -> Set {
+        self.expire = None;
+        self.expire_at = Some(at);
+        self
+    }
+
+    /// Retain the time to live of the key instead of discarding it.
+    ///
+    /// The expire given to `new` is ignored.
+    pub fn with_keep_ttl(mut self) -> Set {
+        self.expire = None;
+        self.expire_at = None;
+        self.keep_ttl = true;
+        self
+    }
+
+    /// Reply with the previous value of the key instead of `OK`.
+    pub fn with_get(mut self) -> Set {
+        self.get = true;
+        self
+    }
     /// Get the key
     pub fn key(&self) -> &str {
         &self.key
@@ -49,6 +116,22 @@ impl Set {
     pub fn expire(&self) -> Option<Duration> {
         self.expire
     }
+    /// Get the time at which the key expires
+    pub fn expire_at(&self) -> Option<SystemTime> {
+        self.expire_at
+    }
+    /// Get the condition
+    pub fn condition(&self) -> Option<SetCondition> {
+        self.condition
+    }
+    /// Get whether the time to live of the key is retained
+    pub fn keep_ttl(&self) -> bool {
+        self.keep_ttl
+    }
+    /// Get whether the previous value is returned
+    pub fn get(&self) -> bool {
+        self.get
+    }
     /// Parse a `Set` instance from a received frame.
     ///
     /// The `Parse` argument provides a cursor-like API to read fields from the
@@ -67,8 +150,10 @@ impl Set {
     /// Expects an array frame containing at least 3 entries.
     ///
     /// ```text
-    /// SET key value [EX seconds|PX milliseconds]
+    /// SET key value [EX seconds|PX milliseconds|EXAT timestamp|PXAT timestamp|KEEPTTL] [NX|XX] [GET]
     /// ```
+    ///
+    /// Options may be given in any order.
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Set> {
         use ParseError::EndOfStream;
 
@@ -78,21 +163,54 @@ impl Set {
 
         let mut expire = None;
 
-        match parse.next_string() {
-            Ok(s) if s.to_uppercase() == "EX" => {
-                let secs = parse.next_int()?;
-                expire = Some(Duration::from_secs(secs));
-            },
-            Ok(s) if s.to_uppercase() == "PX" => {
-                let ms = parse.next_int()?;
-                expire = Some(Duration::from_millis(ms));
-            },
-            Ok(_) => return Err("currently `SET` only supports the expiration option".into()),
-            Err(EndOfStream) => {},
-            Err(err) => return Err(err.into()),
+        let mut expire_at = None;
+
+        let mut condition = None;
+
+        let mut keep_ttl = false;
+
+        let mut get = false;
+
+        loop {
+            // 同一时间只能给出一个过期选项
+            let no_ttl = expire.is_none() && expire_at.is_none() && !keep_ttl;
+
+            match parse.next_token() {
+                Ok(s) if s.is("EX") && no_ttl => {
+                    let secs = parse.next_int()?;
+                    expire = Some(Duration::from_secs(secs));
+                },
+                Ok(s) if s.is("PX") && no_ttl => {
+                    let ms = parse.next_int()?;
+                    expire = Some(Duration::from_millis(ms));
+                },
+                Ok(s) if s.is("EXAT") && no_ttl => {
+                    let secs = parse.next_int()?;
+                    expire_at = Some(UNIX_EPOCH + Duration::from_secs(secs));
+                },
+                Ok(s) if s.is("PXAT") && no_ttl => {
+                    let ms = parse.next_int()?;
+                    expire_at = Some(UNIX_EPOCH + Duration::from_millis(ms));
+                },
+                Ok(s) if s.is("NX") && condition.is_none() => {
+                    condition = Some(SetCondition::Nx);
+                },
+                Ok(s) if s.is("XX") && condition.is_none() => {
+                    condition = Some(SetCondition::Xx);
+                },
+                Ok(s) if s.is("KEEPTTL") && no_ttl => {
+                    keep_ttl = true;
+                },
+                Ok(s) if s.is("GET") && !get => {
+                    get = true;
+                },
+                Ok(_) => return Err("ERR syntax error".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
         }
 
-        Ok(Set { key, value, expire})
+        Ok(Set { key, value, expire, expire_at, condition, keep_ttl, get })
 
     }
 
@@ -102,14 +220,43 @@ impl Set {
     /// to execute a received command.
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        db.set(self.key, self.value, self.expire);
+        let response = db.atomic(|view| self.execute(view));
 
-        let response = Frame::Simple("OK".to_string());
         debug!(?response);
         dst.write_frame(&response).await?;
 
         Ok(())
     }
+
+    /// Executes the `Set` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        // 绝对过期时间转换为相对时长，已经过去的时间使key立即过期
+        let expire = self.expire.or_else(|| {
+            self.expire_at
+                .map(|at| at.duration_since(SystemTime::now()).unwrap_or_default())
+        });
+
+        let options = SetOptions {
+            expire,
+            condition: self.condition,
+            keep_ttl: self.keep_ttl,
+            get: self.get,
+        };
+
+        match view.set_with_options(self.key, self.value, options) {
+            Ok(outcome) if self.get => match outcome.previous {
+                Some(value) => Frame::Bulk(value),
+                None => Frame::Null,
+            },
+            Ok(outcome) if outcome.written => Frame::Simple("OK".to_string()),
+            Ok(_) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
     /// Converts the command into an equivalent `Frame`.
     ///
     /// This is called by the client when encoding a `Set` command to send to
@@ -125,6 +272,22 @@ impl Set {
             frame.push_bulk(Bytes::from("px".as_bytes()));
             frame.push_int(ms.as_millis() as u64);
         }
+        if let Some(at) = self.expire_at {
+            let ms = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+            frame.push_bulk(Bytes::from("pxat".as_bytes()));
+            frame.push_int(ms as u64);
+        }
+        match self.condition {
+            Some(SetCondition::Nx) => frame.push_bulk(Bytes::from("nx".as_bytes())),
+            Some(SetCondition::Xx) => frame.push_bulk(Bytes::from("xx".as_bytes())),
+            None => {}
+        }
+        if self.keep_ttl {
+            frame.push_bulk(Bytes::from("keepttl".as_bytes()));
+        }
+        if self.get {
+            frame.push_bulk(Bytes::from("get".as_bytes()));
+        }
         frame
     }
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+use crate::db::{SetOptions, StateView};
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Set `key` to hold the string `value` and expire after a time to live.
+///
+/// Handles both `SETEX`, which takes the time to live in seconds, and
+/// `PSETEX`, which takes it in milliseconds. They behave as
+/// `SET key value EX seconds` and `SET key value PX milliseconds`, except that
+/// the time to live must be strictly positive.
+#[derive(Debug)]
+pub struct SetEx {
+    key: String,
+
+    value: Bytes,
+
+    /// `None` when the time to live received is not strictly positive, in which
+    /// case an error is replied instead of setting the key.
+    expire: Option<Duration>,
+
+    /// Whether the command was received as `PSETEX`.
+    millis: bool,
+}
+
+impl SetEx {
+    /// Create a new `SetEx` command which sets `key` to `value`, expiring
+    /// after `expire`.
+    ///
+    /// The command is sent as `PSETEX` so that the time to live does not lose
+    /// precision.
+    pub fn new(key: impl ToString, value: Bytes, expire: Duration) -> SetEx {
+        SetEx {
+            key: key.to_string(),
+            value,
+            expire: Some(expire),
+            millis: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Get the expire, `None` if the time to live is not strictly positive
+    pub fn expire(&self) -> Option<Duration> {
+        self.expire
+    }
+
+    /// Returns the name of the command, `setex` or `psetex`.
+    pub(crate) fn get_name(&self) -> &'static str {
+        if self.millis {
+            "psetex"
+        } else {
+            "setex"
+        }
+    }
+
+    /// Parse a `SetEx` instance from a received `SETEX` frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETEX` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetEx` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETEX key seconds value
+    /// ```
+    pub(crate) fn parse_setex_frames(parse: &mut Parse) -> crate::Result<SetEx> {
+        SetEx::parse_frames(parse, false)
+    }
+
+    /// Parse a `SetEx` instance from a received `PSETEX` frame.
+    ///
+    /// The `PSETEX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// PSETEX key milliseconds value
+    /// ```
+    pub(crate) fn parse_psetex_frames(parse: &mut Parse) -> crate::Result<SetEx> {
+        SetEx::parse_frames(parse, true)
+    }
+
+    fn parse_frames(parse: &mut Parse, millis: bool) -> crate::Result<SetEx> {
+        let key = parse.next_string()?;
+
+        // 过期时间可能为负数，所以不能使用`next_int`解析
+        let ttl = parse.next_int_signed()?;
+
+        let value = parse.next_bytes()?;
+
+        let expire = match ttl {
+            ttl if ttl <= 0 => None,
+            ttl if millis => Some(Duration::from_millis(ttl as u64)),
+            ttl => Some(Duration::from_secs(ttl as u64)),
+        };
+
+        Ok(SetEx { key, value, expire, millis })
+    }
+
+    /// Apply the `SetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `SetEx` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match self.expire {
+            Some(expire) => {
+                let options = SetOptions {
+                    expire: Some(expire),
+                    ..SetOptions::default()
+                };
+                match view.set_with_options(self.key, self.value, options) {
+                    Ok(_) => Frame::Simple("OK".to_string()),
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            }
+            None => Frame::Error(format!(
+                "ERR invalid expire time in '{}' command",
+                self.get_name()
+            )),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetEx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from(self.get_name().as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        let expire = self.expire.unwrap_or_default();
+        if self.millis {
+            frame.push_bulk(Bytes::from(expire.as_millis().to_string()));
+        } else {
+            frame.push_bulk(Bytes::from(expire.as_secs().to_string()));
+        }
+
+        frame.push_bulk(self.value);
+        frame
+    }
+}
@@ -0,0 +1,124 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Largest string `SETRANGE` may create, same as the Redis default
+/// `proto-max-bulk-len`.
+const MAX_STRING_LEN: u64 = 512 * 1024 * 1024;
+
+/// Overwrite part of the string stored at `key`, starting at `offset`, with
+/// `value`.
+///
+/// If the string is shorter than `offset`, it is padded with zero bytes. A
+/// missing key is treated as an empty string.
+#[derive(Debug)]
+pub struct SetRange {
+    key: String,
+
+    offset: u64,
+
+    value: Bytes,
+}
+
+impl SetRange {
+    /// Create a new `SetRange` command which writes `value` at `offset` in
+    /// `key`.
+    pub fn new(key: impl ToString, offset: u64, value: Bytes) -> SetRange {
+        SetRange {
+            key: key.to_string(),
+            offset,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetRange` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETRANGE key offset value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetRange> {
+        let key = parse.next_string()?;
+        let offset = parse.next_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(SetRange { key, offset, value })
+    }
+
+    /// Apply the `SetRange` command to the specified `Db` instance.
+    ///
+    /// The length of the string after the write is written to `dst`. This is
+    /// called by the server in order to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `SetRange` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        // `offset`由客户端指定，相加可能溢出
+        let end = self.offset.checked_add(self.value.len() as u64);
+
+        if end.is_none_or(|end| end > MAX_STRING_LEN) {
+            Frame::Error("ERR string exceeds maximum allowed size (proto-max-bulk-len)".to_string())
+        } else {
+            match view.setrange(self.key, self.offset as usize, self.value) {
+                Ok(len) => Frame::Integer(len as u64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_int(self.offset);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
@@ -0,0 +1,569 @@
+use crate::cmd::Unknown;
+use crate::db::StateView;
+use crate::pubsub::PubSubReply;
+use crate::{Command, Connection, Db, Frame, Parse, ParseError, Shutdown};
+
+use bytes::Bytes;
+use std::fmt;
+use std::str::FromStr;
+use tokio::select;
+use tokio::sync::watch;
+use tracing::{debug, instrument};
+
+/// Number of entries `XSUBSCRIBE` reads from the stream under a single lock of
+/// the `Db`, so that replaying a long stream does not hold it for long.
+const REPLAY_BATCH: usize = 128;
+
+/// Identifier of a stream entry: the Unix time in milliseconds at which it
+/// was added, and a sequence number telling apart the entries added within
+/// the same millisecond.
+///
+/// Entries are ordered by id, and written as `<ms>-<seq>`.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::cmd::StreamId;
+///
+/// let id: StreamId = "1700000000000-3".parse().unwrap();
+/// assert_eq!(StreamId::new(1700000000000, 3), id);
+/// assert_eq!("1700000000000-3", id.to_string());
+///
+/// // 省略的序号为0
+/// assert_eq!(StreamId::new(5, 0), "5".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+/// An entry of a stream: its id, and its fields along with their values, in
+/// the order given to `XADD`.
+pub type StreamEntry = (StreamId, Vec<(String, Bytes)>);
+
+/// Append an entry to the stream stored at key.
+///
+/// The id of the entry is generated from the current time when given as `*`,
+/// otherwise it must be greater than the id of the last entry. A missing key
+/// is created holding an empty stream first. Replies with the id of the entry,
+/// or an error if the id is too small or the key holds a value which is not a
+/// stream.
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+
+    /// `None` for `*`.
+    id: Option<StreamId>,
+
+    fields: Vec<(String, Bytes)>,
+}
+
+/// Returns the entries of the stream stored at key within a range of ids.
+///
+/// The reply is an array of entries in the order of their ids, each an array
+/// of its id and of its fields, each field followed by its value. An empty
+/// array is replied if the key does not exist.
+#[derive(Debug)]
+pub struct XRange {
+    key: String,
+
+    start: StreamId,
+
+    end: StreamId,
+}
+
+/// Subscribes the client to the entries of the stream stored at key, from the
+/// one after `last-id` on.
+///
+/// The entries already in the stream are replayed first, then the ones added
+/// with `XADD` are delivered as they come. Each entry is sent along with its
+/// id, so that a client which persists the id of the last entry it processed
+/// resumes with the following one, none missed or received twice, see
+/// `PubSubReply::XMessage`.
+///
+/// Like `SUBSCRIBE`, the connection is then in subscribed state, and only
+/// `PING` is allowed.
+#[derive(Debug)]
+pub struct XSubscribe {
+    key: String,
+
+    /// `None` for `$`, the last entry of the stream.
+    last_id: Option<StreamId>,
+}
+
+impl StreamId {
+    /// The smallest id, which no entry can have.
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+
+    /// The greatest id.
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    /// Create the id `<ms>-<seq>`.
+    pub fn new(ms: u64, seq: u64) -> StreamId {
+        StreamId { ms, seq }
+    }
+
+    /// Returns the id following `self`, `None` for `StreamId::MAX`.
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl FromStr for StreamId {
+    type Err = crate::Error;
+
+    /// Parse an id written as `<ms>-<seq>`, or `<ms>` for `<ms>-0`.
+    fn from_str(s: &str) -> crate::Result<StreamId> {
+        parse_id(s, 0)
+    }
+}
+
+impl XAdd {
+    /// Create a new `XAdd` command which appends an entry holding `fields` to
+    /// the stream stored at `key`, with the given id, or a generated one if
+    /// `None`.
+    pub fn new(key: impl ToString, id: Option<StreamId>, fields: Vec<(String, Bytes)>) -> XAdd {
+        XAdd {
+            key: key.to_string(),
+            id,
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `XAdd` instance from a received frame.
+    ///
+    /// The `XADD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least five entries, with a value
+    /// for each field.
+    ///
+    /// ```text
+    /// XADD key *|id field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAdd> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        let id = match &parse.next_string()?[..] {
+            "*" => None,
+            id => Some(id.parse()?),
+        };
+
+        let mut fields = vec![];
+
+        loop {
+            let field = match parse.next_string() {
+                Ok(field) => field,
+                Err(EndOfStream) if !fields.is_empty() => break,
+                Err(EndOfStream) => return Err("ERR wrong number of arguments for 'xadd' command".into()),
+                Err(err) => return Err(err.into()),
+            };
+
+            match parse.next_bytes() {
+                Ok(value) => fields.push((field, value)),
+                Err(EndOfStream) => return Err("ERR wrong number of arguments for 'xadd' command".into()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(XAdd { key, id, fields })
+    }
+
+    /// Apply the `XAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `XAdd` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.xadd(self.key, self.id, self.fields) {
+            Ok(id) => Frame::Bulk(Bytes::from(id.to_string())),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `XAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xadd".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        match self.id {
+            Some(id) => frame.push_bulk(Bytes::from(id.to_string())),
+            None => frame.push_bulk(Bytes::from("*".as_bytes())),
+        }
+        for (field, value) in self.fields {
+            frame.push_bulk(Bytes::from(field.into_bytes()));
+            frame.push_bulk(value);
+        }
+        frame
+    }
+}
+
+impl XRange {
+    /// Create a new `XRange` command which reads the entries of the stream
+    /// stored at `key` with ids within `start..=end`.
+    pub fn new(key: impl ToString, start: StreamId, end: StreamId) -> XRange {
+        XRange {
+            key: key.to_string(),
+            start,
+            end,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `XRange` instance from a received frame.
+    ///
+    /// The `XRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries. `-` and `+` stand for
+    /// the smallest and the greatest ids, and an id without sequence number
+    /// covers all the entries of its millisecond.
+    ///
+    /// ```text
+    /// XRANGE key start end
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XRange> {
+        let key = parse.next_string()?;
+
+        let start = match &parse.next_string()?[..] {
+            "-" => StreamId::MIN,
+            start => parse_id(start, 0)?,
+        };
+
+        let end = match &parse.next_string()?[..] {
+            "+" => StreamId::MAX,
+            end => parse_id(end, u64::MAX)?,
+        };
+
+        Ok(XRange { key, start, end })
+    }
+
+    /// Apply the `XRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `XRange` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.xrange(&self.key, self.start, self.end, usize::MAX) {
+            Ok(entries) => {
+                let mut frame = Frame::array();
+                for (id, fields) in entries {
+                    let mut entry = Frame::array();
+                    entry.push_bulk(Bytes::from(id.to_string()));
+                    entry.push_frame(fields_to_frame(fields));
+                    frame.push_frame(entry);
+                }
+                frame
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `XRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string()));
+        frame.push_bulk(Bytes::from(self.end.to_string()));
+        frame
+    }
+}
+
+impl XSubscribe {
+    /// Create a new `XSubscribe` command which subscribes to the entries of
+    /// the stream stored at `key` after `last_id`, or to the entries added
+    /// from now on if `None`.
+    pub fn new(key: impl ToString, last_id: Option<StreamId>) -> XSubscribe {
+        XSubscribe {
+            key: key.to_string(),
+            last_id,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `XSubscribe` instance from a received frame.
+    ///
+    /// The `XSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries. `$` stands for the id
+    /// of the last entry of the stream.
+    ///
+    /// ```text
+    /// XSUBSCRIBE key last-id|$
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XSubscribe> {
+        let key = parse.next_string()?;
+
+        let last_id = match &parse.next_string()?[..] {
+            "$" => None,
+            id => Some(id.parse()?),
+        };
+
+        Ok(XSubscribe { key, last_id })
+    }
+
+    /// Apply the `XSubscribe` command to the specified `Db` instance.
+    ///
+    /// This function is the entry point and includes the replay of the entries
+    /// after `last-id`, then the live delivery of the following ones, until
+    /// the client leaves or the server shuts down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let (mut cursor, readers) = match db.xsubscribe(&self.key, self.last_id) {
+            Ok(subscribed) => subscribed,
+            Err(err) => {
+                // 和其他读命令一样回复错误，连接不进入订阅状态
+                dst.write_frame(&Frame::Error(err.to_string())).await?;
+                return Ok(());
+            }
+        };
+
+        let mut reader = Reader {
+            db,
+            key: self.key,
+            readers: Some(readers),
+        };
+
+        let response = PubSubReply::XSubscribe {
+            key: reader.key.clone(),
+            last_id: cursor,
+        };
+        dst.write_frame(&response.to_frame()).await?;
+
+        loop {
+            // 先标记通知已读再读取条目，之后添加的条目一定会再次唤醒
+            reader.readers().borrow_and_update();
+
+            let start = match cursor.next() {
+                Some(start) => start,
+                // 不会再有更大的id
+                None => return Ok(()),
+            };
+
+            let entries = match db.atomic(|view| view.xrange(&reader.key, start, StreamId::MAX, REPLAY_BATCH)) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    // 订阅期间key被另一种类型的值覆盖
+                    dst.write_frame(&Frame::Error(err.to_string())).await?;
+                    return Err(err.into());
+                }
+            };
+
+            let caught_up = entries.len() < REPLAY_BATCH;
+
+            for (id, fields) in entries {
+                cursor = id;
+
+                let response = PubSubReply::XMessage {
+                    key: reader.key.clone(),
+                    id,
+                    fields,
+                };
+                dst.write_frame(&response.to_frame()).await?;
+            }
+
+            // 重放还没有结束，继续读取下一批，除非服务器正在关闭
+            if !caught_up {
+                if shutdown.is_shutdown() {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            // 等待下面其中的一个事件发生：
+            //
+            // - 一个条目被添加到流中
+            // - 从客户端收到一个命令
+            // - 服务端关闭信号
+            select! {
+                res = reader.readers().changed() => {
+                    // 发送端只会随`Db`一起被drop
+                    if res.is_err() {
+                        return Ok(());
+                    }
+                }
+                res = dst.read_frame() => {
+                    let frame = match res? {
+                        Some(frame) => frame,
+                        None => return Ok(()),
+                    };
+
+                    handle_command(frame, dst).await?;
+                }
+                _ = shutdown.recv() => {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `XSubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xsubscribe".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        match self.last_id {
+            Some(id) => frame.push_bulk(Bytes::from(id.to_string())),
+            None => frame.push_bulk(Bytes::from("$".as_bytes())),
+        }
+        frame
+    }
+}
+
+/// The stream read by an `XSUBSCRIBE`, released from the `Db` when dropped.
+struct Reader<'a> {
+    db: &'a Db,
+    key: String,
+
+    /// Always `Some`, until dropped.
+    readers: Option<watch::Receiver<StreamId>>,
+}
+
+impl Reader<'_> {
+    fn readers(&mut self) -> &mut watch::Receiver<StreamId> {
+        self.readers.as_mut().unwrap()
+    }
+}
+
+impl Drop for Reader<'_> {
+    fn drop(&mut self) {
+        // `Receiver`先被drop，`Db`才能知道没有读者了
+        self.readers = None;
+        self.db.release_stream(&self.key);
+    }
+}
+
+/// Handles a command received while in `XSUBSCRIBE`: only `PING` is allowed.
+async fn handle_command(frame: Frame, dst: &mut Connection) -> crate::Result<()> {
+    if let Err(err) = frame.check_command() {
+        dst.write_frame(&Frame::Error(err.to_string())).await?;
+        return Err(err.into());
+    }
+
+    match Command::from_frame(frame)? {
+        Command::Ping(ping) => ping.apply_subscribed(dst).await?,
+        other => {
+            let cmd = Unknown::new(other.get_name());
+            cmd.apply(dst).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse an id written as `<ms>-<seq>`, or `<ms>` for `<ms>-<seq>` with the
+/// given sequence number.
+fn parse_id(s: &str, seq: u64) -> crate::Result<StreamId> {
+    let parsed = match s.split_once('-') {
+        Some((ms, seq)) => ms.parse().ok().zip(seq.parse().ok()),
+        None => s.parse().ok().map(|ms| (ms, seq)),
+    };
+
+    match parsed {
+        Some((ms, seq)) => Ok(StreamId::new(ms, seq)),
+        None => Err("ERR Invalid stream ID specified as stream command argument".into()),
+    }
+}
+
+/// Encodes the fields of an entry as an array frame, each field followed by
+/// its value.
+pub(crate) fn fields_to_frame(fields: Vec<(String, Bytes)>) -> Frame {
+    let mut frame = Frame::array();
+    for (field, value) in fields {
+        frame.push_bulk(Bytes::from(field.into_bytes()));
+        frame.push_bulk(value);
+    }
+    frame
+}
+
+/// Decodes the fields of an entry, encoded by `fields_to_frame`.
+pub(crate) fn fields_from_frame(frame: &Frame) -> crate::Result<Vec<(String, Bytes)>> {
+    let Frame::Array(parts) = frame else {
+        return Err(frame.to_error());
+    };
+
+    if parts.len() % 2 != 0 {
+        return Err(frame.to_error());
+    }
+
+    parts
+        .chunks(2)
+        .map(|pair| match pair {
+            [Frame::Bulk(field), Frame::Bulk(value)] => match std::str::from_utf8(field) {
+                Ok(field) => Ok((field.to_string(), value.clone())),
+                Err(_) => Err("protocol error; invalid field name".into()),
+            },
+            _ => Err(frame.to_error()),
+        })
+        .collect()
+}
@@ -0,0 +1,90 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the length of the string value stored at key.
+///
+/// 0 is returned when the key does not exist. The value itself is never sent
+/// back, which allows validating large payloads without transferring them.
+#[derive(Debug)]
+pub struct Strlen {
+    key: String,
+}
+
+impl Strlen {
+    /// Create a new `Strlen` command which measures `key`.
+    pub fn new(key: impl ToString) -> Strlen {
+        Strlen {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Strlen` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `STRLEN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Strlen` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// STRLEN key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Strlen> {
+        let key = parse.next_string()?;
+        Ok(Strlen { key })
+    }
+
+    /// Apply the `Strlen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Strlen` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        match view.strlen(&self.key) {
+            Ok(len) => Frame::Integer(len as u64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Strlen` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("strlen".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
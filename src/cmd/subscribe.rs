@@ -1,20 +1,31 @@
 use crate::cmd::Unknown;
+use crate::fanout::RecvError;
+use crate::pubsub::PubSubReply;
 use crate::{Command, Connection, Db, Frame, Shutdown, Parse, ParseError};
 
 use bytes::Bytes;
 use std::pin::Pin;
+use std::sync::Arc;
 use tokio::select;
-use tokio::sync::broadcast;
+use tokio::time::Instant;
 use tokio_stream::{Stream, StreamExt, StreamMap};
 
-/// Subcribes the client to one or more channels.
+/// Subscribes the client to one or more channels.
 /// 
-/// Once the client enters the subcribed state, it is not supposed to issue any
+/// Once the client enters the subscribed state, it is not supposed to issue any
 /// other commands, except for additional SUBSCRIBE, PSUBSCRIBE, UNSUBSCRIBE,
 /// PUNSUBSCRIBE, PING and QUIT commands.
+///
+/// With the `SINCE` extension, each subscription confirmation is followed by
+/// an `smeta` reply telling how many messages were published on the channel
+/// since a Unix timestamp in milliseconds, so that a client reconnecting can
+/// tell whether it missed messages.
 #[derive(Debug)]
 pub struct Subscribe {
     channels: Vec<String>,
+
+    /// Unix timestamp, in milliseconds, given with `SINCE`.
+    since: Option<u64>,
 }
 
 /// Unsubscribes the client from one or more channels.
@@ -26,16 +37,150 @@ pub struct Unsubscribe {
     channels: Vec<String>,
 }
 
+/// Subscribes the client to one or more glob-style patterns.
+///
+/// The client receives the messages published on every channel matching one
+/// of the patterns, as `pmessage` replies carrying the pattern and the name
+/// of the channel. A message published on a channel matching several patterns
+/// is received once per pattern. The connection enters the same subscribed
+/// state as with `SUBSCRIBE`.
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<String>,
+}
+
+/// Unsubscribes the client from one or more patterns.
+///
+/// When no patterns are specified, the client is unsubscribed from all the
+/// previously subscribed patterns.
+#[derive(Clone, Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<String>,
+}
+
 /// Stream of messages. The stream receives messages from the
-/// `broadcast::Receiver`. We use `stream!` to create a `Stream` that consumes
+/// `fanout::Receiver`. We use `stream!` to create a `Stream` that consumes
 /// messages. Because `stream!` values cannot be named, we box the stream using
 /// a trait object
-type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
+///
+/// The stream ends with `RecvError::Disconnected` if the subscriber is
+/// disconnected for falling behind.
+type Messages = Pin<Box<dyn Stream<Item = Result<Bytes, RecvError>> + Send>>;
+
+/// Stream of the messages published on the channels matching a pattern, along
+/// with the name of their channel.
+type PMessages = Pin<Box<dyn Stream<Item = Result<(Arc<str>, Bytes), RecvError>> + Send>>;
+
+/// The subscriptions of a connection in the subscribed state.
+///
+/// The channels and patterns left, including all of them when the connection
+/// leaves the subscribed state, are released from the `Db` so that the ones
+/// without subscribers are removed.
+struct Subscriptions {
+    channels: StreamMap<String, Messages>,
+
+    patterns: StreamMap<String, PMessages>,
+
+    db: Db,
+}
+
+impl Subscriptions {
+    fn new(db: &Db) -> Subscriptions {
+        Subscriptions {
+            channels: StreamMap::new(),
+            patterns: StreamMap::new(),
+            db: db.clone(),
+        }
+    }
+
+    /// Returns the number of channels and patterns subscribed to, as replied
+    /// in the subscription confirmations.
+    fn len(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    fn unsubscribe(&mut self, channel: &str) {
+        // 先丢弃接收端，这样它不再被计入频道的订阅者
+        if self.channels.remove(channel).is_some() {
+            self.db.release(&[channel.to_string()], &[]);
+        }
+    }
+
+    fn punsubscribe(&mut self, pattern: &str) {
+        if self.patterns.remove(pattern).is_some() {
+            self.db.release(&[], &[pattern.to_string()]);
+        }
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        let channels: Vec<String> = self.channels.keys().cloned().collect();
+        let patterns: Vec<String> = self.patterns.keys().cloned().collect();
+
+        self.channels.clear();
+        self.patterns.clear();
+
+        self.db.release(&channels, &patterns);
+    }
+}
+
+/// Token bucket limiting the number of channels a client may subscribe to or
+/// unsubscribe from per second once in the subscribed state, see
+/// `Config::max_subscribe_churn`.
+#[derive(Debug)]
+struct ChurnBudget {
+    /// Channels per second, also the capacity of the bucket.
+    rate: f64,
+
+    tokens: f64,
+
+    refilled_at: Instant,
+}
+
+impl ChurnBudget {
+    fn new(rate: u32) -> ChurnBudget {
+        ChurnBudget {
+            rate: rate as f64,
+            tokens: rate as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes `n` tokens from the bucket, returning `false` without taking any
+    /// when there are not enough of them.
+    fn try_acquire(&mut self, n: usize) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.refilled_at = now;
+
+        if n as f64 > self.tokens {
+            return false;
+        }
+
+        self.tokens -= n as f64;
+        true
+    }
+}
 
 impl Subscribe {
     /// Create a new `Subscribe` command to listen on the specified channels.
     pub(crate) fn new(channels: Vec<String>) -> Subscribe {
-        Subscribe { channels }
+        Subscribe {
+            channels,
+            since: None,
+        }
+    }
+
+    /// Create a new `Subscribe` command to listen on the specified channels,
+    /// asking for the number of messages published since `since`, a Unix
+    /// timestamp in milliseconds.
+    pub(crate) fn since(channels: Vec<String>, since: u64) -> Subscribe {
+        Subscribe {
+            channels,
+            since: Some(since),
+        }
     }
 
     /// Parse a `Subscribe` instance from a received frame.
@@ -49,19 +194,22 @@ impl Subscribe {
     /// # Returns
     ///
     /// On success, the `Subscribe` value is returned. If the frame is
-    /// malformed, `Err` is returned.
+    /// malformed, `Err` is returned. A frame without channels is parsed, so
+    /// that applying it replies with an error instead of closing the
+    /// connection.
     ///
     /// # Format
     ///
-    /// Expects an array frame containing two or more entries.
+    /// Expects an array frame containing two or more entries. `SINCE` is only
+    /// recognized as the second to last entry, followed by an integer.
     ///
     /// ```text
-    /// SUBSCRIBE channel [channel ...]
+    /// SUBSCRIBE channel [channel ...] [SINCE unix-ms]
     /// ```
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Subscribe> {
         use ParseError::EndOfStream;
 
-        let mut channels = vec![parse.next_string()?];
+        let mut channels = vec![];
 
         loop {
             match parse.next_string() {
@@ -71,7 +219,17 @@ impl Subscribe {
             }
         }
 
-        Ok(Subscribe { channels })
+        // 至少要留下一个频道，`SINCE`之后必须是一个整数
+        let since = match &channels[..] {
+            [_, .., flag, since] if flag.eq_ignore_ascii_case("since") => since.parse().ok(),
+            _ => None,
+        };
+
+        if since.is_some() {
+            channels.truncate(channels.len() - 2);
+        }
+
+        Ok(Subscribe { channels, since })
     }
 
     /// Apply the `Subscribe` command to the specified `Db` instance.
@@ -80,6 +238,11 @@ impl Subscribe {
     /// channels to subscribe to. Additional `subscribe` and `unsubscribe`
     /// commands may be received from the client and the list of subscriptions
     /// are updated accordingly.
+    ///
+    /// When `max_churn` is set, those additional commands may subscribe to or
+    /// unsubscribe from at most `max_churn` channels per second. Commands over
+    /// the budget are rejected with an error, leaving the subscriptions and
+    /// the connection untouched.
     /// 
     /// [here]: https://redis.io/topics/pubsub
     pub(crate) async fn apply (
@@ -87,53 +250,16 @@ impl Subscribe {
         db: &Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
+        max_churn: Option<u32>,
     ) -> crate::Result<()> {
-        // 每个单独的channel订阅都使用`sync::broadcast` channel被处理。
-        // 消息被发送给所有当前订阅channels的客户端。
-        //
-        // 一个单独的客户端可能订阅多个channels 可能动态从他们的subscription set中
-        // 添加或者移除channel。 为了处理这个，`StreamMap` 被用来跟踪有效订阅。
-        // `StreamMap` 会在接收到来自各个channels的messages时将其合并.
-        let mut subscriptions = StreamMap::new();
-
-        loop {
-            // `self.channels` 被用来跟踪要订阅的其他频道
-            // 当一个新的 `SUBSCRIBE` 命令在执行`apply`的过程中被收到，
-            // 新的channels 被放到这个vec中
-            // 这个表达式使用 drain 方法来移除 self.channels 中的所有元素
-            //并返回一个迭代器，该迭代器允许你遍历被移除的元素。
-            for channel_name in self.channels.drain(..) {
-                subscibe_to_channel(channel_name, &mut subscriptions, db, dst).await?;
-            }
+        if self.channels.is_empty() {
+            return write_wrong_arity("subscribe", dst).await;
+        }
 
-            // 等待下面其中的一个事件发生：
-            //
-            // - 从其中一个subscribed channels中收到一个消息
-            // - 从客户端收到一个 subscribe 或者 unsubscribe 命令
-            // - 服务端关闭信号
-            select!{
-                Some((channel_name, msg)) = subscriptions.next() => {
-                    dst.write_frame(&make_message_frame(channel_name, msg)).await?;
-                }
-                res = dst.read_frame() => {
-                    let frame = match res? {
-                        Some(frame) => frame,
-                        None => return Ok(())
-                    };
-
-                    handle_command(
-                        frame,
-                        &mut self.channels,
-                        &mut subscriptions,
-                        dst
-                    ).await?;
-                }
-                _ = shutdown.recv() => {
-                    return Ok(());
-                }
+        let since = self.since;
+        let pending = self.channels.drain(..).map(|channel| (channel, since)).collect();
 
-            };
-        }
+        subscribed(pending, vec![], db, dst, shutdown, max_churn).await
     }
     pub(crate) fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
@@ -141,54 +267,269 @@ impl Subscribe {
         for channel in self.channels {
             frame.push_bulk(Bytes::from(channel.into_bytes()));
         }
+        if let Some(since) = self.since {
+            frame.push_bulk(Bytes::from("since".as_bytes()));
+            frame.push_bulk(Bytes::from(since.to_string()));
+        }
         frame
     }
 }
 
-async fn subscibe_to_channel(
+/// Keeps the connection in the subscribed state, after subscribing to the
+/// `pending` channels and `pending_patterns`, until the client disconnects or
+/// the server shuts down. Shared by `SUBSCRIBE` and `PSUBSCRIBE`.
+async fn subscribed(
+    mut pending: Vec<(String, Option<u64>)>,
+    mut pending_patterns: Vec<String>,
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+    max_churn: Option<u32>,
+) -> crate::Result<()> {
+    // 每个单独的channel订阅都使用`fanout` channel被处理。
+    // 消息被发送给所有当前订阅channels的客户端。
+    //
+    // 一个单独的客户端可能订阅多个channels 可能动态从他们的subscription set中
+    // 添加或者移除channel。 为了处理这个，`StreamMap` 被用来跟踪有效订阅。
+    // `StreamMap` 会在接收到来自各个channels的messages时将其合并.
+    // 模式的订阅同样放在一个`StreamMap`中
+    let mut subscriptions = Subscriptions::new(db);
+
+    // 初始订阅的频道不计入预算
+    let mut budget = max_churn.map(ChurnBudget::new);
+
+    loop {
+        // `pending` 被用来跟踪要订阅的其他频道
+        // 当一个新的 `SUBSCRIBE` 命令在执行的过程中被收到，
+        // 新的channels 被放到这个vec中
+        // 这个表达式使用 drain 方法来移除 pending 中的所有元素
+        //并返回一个迭代器，该迭代器允许你遍历被移除的元素。
+        for (channel_name, since) in pending.drain(..) {
+            subscribe_to_channel(channel_name, since, &mut subscriptions, db, dst).await?;
+        }
+
+        for pattern in pending_patterns.drain(..) {
+            subscribe_to_pattern(pattern, &mut subscriptions, db, dst).await?;
+        }
+
+        // 等待下面其中的一个事件发生：
+        //
+        // - 从其中一个subscribed channels中收到一个消息
+        // - 从其中一个模式匹配的频道中收到一个消息
+        // - 从客户端收到一个 subscribe 或者 unsubscribe 命令
+        // - 服务端关闭信号
+        select!{
+            Some((channel_name, msg)) = subscriptions.channels.next() => {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(err) => return disconnect(err, dst).await,
+                };
+                let response = PubSubReply::Message {
+                    channel: channel_name,
+                    content: msg,
+                };
+                dst.write_frame(&response.to_frame()).await?;
+            }
+            Some((pattern, msg)) = subscriptions.patterns.next() => {
+                let (channel, msg) = match msg {
+                    Ok(msg) => msg,
+                    Err(err) => return disconnect(err, dst).await,
+                };
+                let response = PubSubReply::PMessage {
+                    pattern,
+                    channel: channel.to_string(),
+                    content: msg,
+                };
+                dst.write_frame(&response.to_frame()).await?;
+            }
+            res = dst.read_frame() => {
+                let frame = match res? {
+                    Some(frame) => frame,
+                    None => return Ok(())
+                };
+
+                handle_command(
+                    frame,
+                    &mut pending,
+                    &mut pending_patterns,
+                    &mut subscriptions,
+                    &mut budget,
+                    dst
+                ).await?;
+            }
+            _ = shutdown.recv() => {
+                return Ok(());
+            }
+
+        };
+    }
+}
+
+/// Tells the client its subscription was dropped, and returns the error
+/// closing the connection: the messages it missed cannot be recovered.
+async fn disconnect(err: RecvError, dst: &mut Connection) -> crate::Result<()> {
+    dst.write_frame(&Frame::Error(format!("ERR {}", err))).await?;
+    Err(err.into())
+}
+
+async fn subscribe_to_channel(
     channel_name: String,
-    subscriptions: &mut StreamMap<String, Messages>,
+    since: Option<u64>,
+    subscriptions: &mut Subscriptions,
     db: &Db,
     dst: &mut Connection
 ) -> crate::Result<()> {
-    let mut rx = db.subscibe(channel_name.clone());
+    // 拒绝非法的频道名。这里回复一个错误而不是返回`Err`，
+    // 这样连接不会被关闭，其他频道的订阅也不受影响
+    if let Err(err) = db.pubsub_strictness().check_channel(&channel_name) {
+        let response = Frame::Error(err.to_string());
+        dst.write_frame(&response).await?;
+        return Ok(());
+    }
+
+    let (mut rx, backlog) = match since {
+        Some(since) => {
+            let (rx, published, truncated) = db.subscribe_since(channel_name.clone(), since);
+            let backlog = PubSubReply::Backlog {
+                channel: channel_name.clone(),
+                published,
+                truncated,
+            };
+            (rx, Some(backlog))
+        }
+        None => (db.subscribe(channel_name.clone()), None),
+    };
     //async_stream::stream! 是一个宏，用于方便地创建一个实现 Stream trait 的异步流。
     let rx = Box::pin(async_stream::stream! {
         loop {
             match rx.recv().await {
                 //如果接收操作成功（即 Ok(msg)），
                 //则使用 yield 关键字将消息放入流中。yield 用于生成流中的下一个值。
-                Ok(msg) => yield msg,
+                Ok(msg) => yield Ok(msg),
                 // 如果消费消息之后，请继续
-                Err(broadcast::error::RecvError::Lagged(_)) => {},
-                Err(_) => break,
+                Err(RecvError::Lagged(_)) => {},
+                // 订阅者被断开，把错误交给连接处理
+                Err(RecvError::Disconnected) => {
+                    yield Err(RecvError::Disconnected);
+                    break;
+                }
+                Err(RecvError::Closed) => break,
             }
         }
     });
 
-    subscriptions.insert(channel_name.clone(), rx);
+    // 先写出确认（以及积压的消息），再把接收端加入`subscriptions`。
+    // 在此期间发布的消息缓存在fanout接收端中，
+    // 因此客户端总是先收到确认，再收到该频道的第一条消息
+    let num_subs = subscriptions.len() + !subscriptions.channels.contains_key(&channel_name) as usize;
 
-    let response = make_subscribe_frame(channel_name, subscriptions.len());
-    dst.write_frame(&response).await?;
+    let response = PubSubReply::Subscribe {
+        channel: channel_name.clone(),
+        num_subs: num_subs as u64,
+    };
+    dst.write_frame(&response.to_frame()).await?;
+
+    if let Some(backlog) = backlog {
+        dst.write_frame(&backlog.to_frame()).await?;
+    }
+
+    subscriptions.channels.insert(channel_name, rx);
+
+    Ok(())
+}
+
+async fn subscribe_to_pattern(
+    pattern: String,
+    subscriptions: &mut Subscriptions,
+    db: &Db,
+    dst: &mut Connection
+) -> crate::Result<()> {
+    let mut rx = db.psubscribe(pattern.clone());
+
+    let rx = Box::pin(async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(msg) => yield Ok(msg),
+                Err(RecvError::Lagged(_)) => {},
+                Err(RecvError::Disconnected) => {
+                    yield Err(RecvError::Disconnected);
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+
+    // 和频道一样，先写出确认，再把接收端加入`subscriptions`
+    let num_subs = subscriptions.len() + !subscriptions.patterns.contains_key(&pattern) as usize;
+
+    let response = PubSubReply::PSubscribe {
+        pattern: pattern.clone(),
+        num_subs: num_subs as u64,
+    };
+    dst.write_frame(&response.to_frame()).await?;
+
+    subscriptions.patterns.insert(pattern, rx);
 
     Ok(())
 }
-/// Handle a command received while inside `Subscribe::apply`. Only subscribe
-/// and unsubscribe commands are permitted in this context.
+
+/// Handle a command received in the subscribed state. Only subscribe,
+/// unsubscribe, their pattern variants and ping commands are permitted in
+/// this context.
 /// 
-/// Any new subscriptions are appended to `subscribe_to` instead of modifying
-/// `subscriptions`
+/// Any new subscriptions are appended to `subscribe_to`, along with their
+/// `SINCE` timestamp, or to `psubscribe_to`, instead of modifying
+/// `subscriptions`. Commands exceeding `budget` are rejected with an error
+/// frame.
 async fn handle_command (
     frame: Frame,
-    subscibe_to: &mut Vec<String>,
-    subscriptions: &mut StreamMap<String, Messages>,
+    subscribe_to: &mut Vec<(String, Option<u64>)>,
+    psubscribe_to: &mut Vec<String>,
+    subscriptions: &mut Subscriptions,
+    budget: &mut Option<ChurnBudget>,
     dst: &mut Connection
 ) -> crate::Result<()> {
+    if let Err(err) = frame.check_command() {
+        dst.write_frame(&Frame::Error(err.to_string())).await?;
+        return Err(err.into());
+    }
+
     // 一个指令从客户端收到
-    // 只有`SUBSCRIBE`和`UNSUBSCRIBE`命令允许被处理
-    match Command::from_frame(frame)? {
-        Command::Subcribe(subscibe) => {
-            subscibe_to.extend(subscibe.channels.into_iter())
+    // 只有`SUBSCRIBE`、`UNSUBSCRIBE`、它们的模式版本和`PING`命令允许被处理
+    let command = Command::from_frame(frame)?;
+
+    // 每个订阅或者取消订阅的频道或模式消耗一个令牌，不带参数的
+    // `UNSUBSCRIBE`和`PUNSUBSCRIBE`至少消耗一个
+    let churn = match &command {
+        Command::Subscribe(subscribe) => subscribe.channels.len(),
+        Command::Unsubscribe(unsubscribe) if unsubscribe.channels.is_empty() => {
+            subscriptions.channels.len().max(1)
+        }
+        Command::Unsubscribe(unsubscribe) => unsubscribe.channels.len(),
+        Command::PSubscribe(psubscribe) => psubscribe.patterns.len(),
+        Command::PUnsubscribe(punsubscribe) if punsubscribe.patterns.is_empty() => {
+            subscriptions.patterns.len().max(1)
+        }
+        Command::PUnsubscribe(punsubscribe) => punsubscribe.patterns.len(),
+        _ => 0,
+    };
+
+    if let Some(budget) = budget {
+        if churn > 0 && !budget.try_acquire(churn) {
+            let response = Frame::Error("ERR subscribe rate limit exceeded".to_string());
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+    }
+
+    match command {
+        Command::Subscribe(subscribe) if subscribe.channels.is_empty() => {
+            write_wrong_arity("subscribe", dst).await?
+        }
+        Command::Subscribe(subscribe) => {
+            let since = subscribe.since;
+            subscribe_to.extend(subscribe.channels.into_iter().map(|channel| (channel, since)))
         },
         Command::Unsubscribe(mut unsubscribe) => {
             // 如果没有channels被指定，会请求所有channels取消订阅。
@@ -196,18 +537,43 @@ async fn handle_command (
             // 当前订阅的列表
             if unsubscribe.channels.is_empty() {
                 unsubscribe.channels = subscriptions
+                    .channels
                     .keys()
                     .map(|channel_name| channel_name.to_string())
                     .collect();
             }
 
             for channel_name in unsubscribe.channels {
-                subscriptions.remove(&channel_name);
+                subscriptions.unsubscribe(&channel_name);
 
-                let response = make_unsubscribe_frame(channel_name, subscriptions.len());
-                dst.write_frame(&response).await?;
+                let response = PubSubReply::Unsubscribe {
+                    channel: channel_name,
+                    num_subs: subscriptions.len() as u64,
+                };
+                dst.write_frame(&response.to_frame()).await?;
             }
         },
+        Command::PSubscribe(psubscribe) if psubscribe.patterns.is_empty() => {
+            write_wrong_arity("psubscribe", dst).await?
+        }
+        Command::PSubscribe(psubscribe) => psubscribe_to.extend(psubscribe.patterns),
+        Command::PUnsubscribe(mut punsubscribe) => {
+            // 和`UNSUBSCRIBE`一样，没有参数时取消所有模式的订阅
+            if punsubscribe.patterns.is_empty() {
+                punsubscribe.patterns = subscriptions.patterns.keys().cloned().collect();
+            }
+
+            for pattern in punsubscribe.patterns {
+                subscriptions.punsubscribe(&pattern);
+
+                let response = PubSubReply::PUnsubscribe {
+                    pattern,
+                    num_subs: subscriptions.len() as u64,
+                };
+                dst.write_frame(&response.to_frame()).await?;
+            }
+        },
+        Command::Ping(ping) => ping.apply_subscribed(dst).await?,
         other => {
             let cmd = Unknown::new(other.get_name());
             cmd.apply(dst).await?;
@@ -216,38 +582,12 @@ async fn handle_command (
     Ok(())
 }
 
-
-/// Creates the response to a subscribe request
-/// 
-/// All of these functions take the `channel_name` as a `String` instead of
-/// a `&str` since `Bytes::from` can reuse the allocation in the `String`,
-/// and taking a `&str` would require copying the data. This allows the caller
-/// to decide whether to clone the channel name or not.
-/// 重要的是，这个过程可以重用 String 中的内存分配。这意味着在将 String 转换为 Bytes 时，
-/// 不需要分配新的内存来存储字符串数据，从而提高效率。而使用`&str`会拷贝数据。
-/// 这允许调用者是否需要clone channel name
-fn make_subscribe_frame(channel_name: String, num_subs: usize) -> Frame {
-    let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"subscribe"));
-    response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
-    response
-}
-
-fn make_unsubscribe_frame(channel_name: String, num_subs:usize) -> Frame {
-    let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"unsubscribe"));
-    response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
-    response
-}
-
-fn make_message_frame(channel_name: String, msg: Bytes) -> Frame {
-    let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"message"));
-    response.push_bulk(Bytes::from(channel_name));
-    response.push_bulk(msg);
-    response
+/// Replies to a `SUBSCRIBE` or `PSUBSCRIBE` without channels or patterns,
+/// leaving the connection, and its subscriptions, as they are.
+async fn write_wrong_arity(name: &str, dst: &mut Connection) -> crate::Result<()> {
+    let response = Frame::Error(format!("ERR wrong number of arguments for '{}' command", name));
+    dst.write_frame(&response).await?;
+    Ok(())
 }
 
 impl Unsubscribe {
@@ -308,4 +648,100 @@ impl Unsubscribe {
 
         frame
     }
-}
\ No newline at end of file
+}
+impl PSubscribe {
+    /// Create a new `PSubscribe` command to listen on the channels matching
+    /// the specified patterns.
+    pub(crate) fn new(patterns: Vec<String>) -> PSubscribe {
+        PSubscribe { patterns }
+    }
+
+    /// Parse a `PSubscribe` instance from a received frame.
+    ///
+    /// The `PSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries. As with
+    /// `SUBSCRIBE`, a frame without patterns is parsed and rejected when
+    /// applied.
+    ///
+    /// ```text
+    /// PSUBSCRIBE pattern [pattern ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSubscribe> {
+        use ParseError::EndOfStream;
+
+        let mut patterns = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PSubscribe { patterns })
+    }
+
+    /// Apply the `PSubscribe` command to the specified `Db` instance.
+    ///
+    /// The connection enters the subscribed state, like with `SUBSCRIBE`, see
+    /// `Subscribe::apply`.
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+        max_churn: Option<u32>,
+    ) -> crate::Result<()> {
+        if self.patterns.is_empty() {
+            return write_wrong_arity("psubscribe", dst).await;
+        }
+
+        subscribed(vec![], self.patterns, db, dst, shutdown, max_churn).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PSubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("psubscribe".as_bytes()));
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl PUnsubscribe {
+    /// Parse a `PUnsubscribe` instance from a received frame.
+    ///
+    /// The `PUNSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// PUNSUBSCRIBE [pattern [pattern ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<PUnsubscribe, ParseError> {
+        use ParseError::EndOfStream;
+
+        let mut patterns = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(PUnsubscribe { patterns })
+    }
+}
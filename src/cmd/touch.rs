@@ -0,0 +1,98 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Refresh the last access time of the given keys.
+///
+/// Replies with the number of keys that exist. The access time is reported by
+/// `OBJECT IDLETIME`.
+#[derive(Debug)]
+pub struct Touch {
+    keys: Vec<String>,
+}
+
+impl Touch {
+    /// Create a new `Touch` command which touches `keys`.
+    pub fn new(keys: Vec<String>) -> Touch {
+        Touch { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `Touch` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `TOUCH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Touch` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// TOUCH key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Touch> {
+        use ParseError::EndOfStream;
+
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Touch { keys })
+    }
+
+    /// Apply the `Touch` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.atomic(|view| self.execute(view));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Touch` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        Frame::Integer(view.touch(&self.keys) as u64)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Touch` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("touch".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
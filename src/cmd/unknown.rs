@@ -21,11 +21,19 @@ impl Unknown {
 
     #[instrument(skip(self, dst))]
     pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
-        let response = Frame::Error(format!("ERR unknown command '{}'", self.get_name()));
+        let response = self.execute();
 
         debug!(?response);
 
         dst.write_frame(&response).await?;
         Ok(())
     }
+
+    /// Executes the `Unknown` command, and returns the reply.
+    ///
+    /// The reply does not depend on the keyspace, so the command can be queued
+    /// in a transaction as well.
+    pub(crate) fn execute(self) -> Frame {
+        Frame::Error(format!("ERR unknown command '{}'", self.get_name()))
+    }
 }
\ No newline at end of file
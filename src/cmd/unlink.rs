@@ -0,0 +1,101 @@
+use crate::db::StateView;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Remove the given keys, without freeing their values while holding the
+/// lock of the database.
+///
+/// Like `DEL`, replies with the number of keys that were removed. Keys that do
+/// not exist, or have expired, are ignored. The values are released once the
+/// keys are removed, see `Db::unlink`.
+#[derive(Debug)]
+pub struct Unlink {
+    keys: Vec<String>,
+}
+
+impl Unlink {
+    /// Create a new `Unlink` command which removes `keys`.
+    pub fn new(keys: Vec<String>) -> Unlink {
+        Unlink { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `Unlink` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `UNLINK` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Unlink` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// UNLINK key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Unlink> {
+        use ParseError::EndOfStream;
+
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Unlink { keys })
+    }
+
+    /// Apply the `Unlink` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // 值的释放交给后台任务，而不是在锁内进行
+        let response = Frame::Integer(db.unlink(&self.keys) as u64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Unlink` command on the keyspace, and returns the reply.
+    ///
+    /// Called under the lock of the `Db`, held for this command alone or for
+    /// the whole transaction it is part of.
+    pub(crate) fn execute(self, view: &mut StateView<'_>) -> Frame {
+        Frame::Integer(view.del_many(&self.keys) as u64)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Unlink` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("unlink".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
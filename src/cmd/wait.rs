@@ -0,0 +1,108 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Wait for the previous writes to be acknowledged by replicas.
+///
+/// The server runs as a single node without replicas, so there is nothing to
+/// wait for: the arguments are validated, then ignored, and the reply is
+/// always 0, the number of replicas which acknowledged the writes. Clients
+/// issuing `WAIT` after their writes are answered immediately instead of
+/// hanging until their timeout.
+#[derive(Debug)]
+pub struct Wait {
+    numreplicas: u64,
+
+    timeout: Duration,
+}
+
+impl Wait {
+    /// Create a new `Wait` command which waits for `numreplicas` replicas for
+    /// at most `timeout`.
+    pub fn new(numreplicas: u64, timeout: Duration) -> Wait {
+        Wait {
+            numreplicas,
+            timeout,
+        }
+    }
+
+    /// Get the number of replicas to wait for
+    pub fn numreplicas(&self) -> u64 {
+        self.numreplicas
+    }
+
+    /// Get the timeout, zero meaning no timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `Wait` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `WAIT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Wait` value on success. If the frame is malformed, or
+    /// either argument is not a non-negative integer, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// WAIT numreplicas timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Wait> {
+        let numreplicas = parse.next_int()?;
+
+        // 超时时间以毫秒为单位
+        let timeout = Duration::from_millis(parse.next_int()?);
+
+        Ok(Wait {
+            numreplicas,
+            timeout,
+        })
+    }
+
+    /// Apply the `Wait` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute();
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Executes the `Wait` command, and returns the reply.
+    ///
+    /// The reply does not depend on the keyspace, so the command can be queued
+    /// in a transaction as well.
+    pub(crate) fn execute(self) -> Frame {
+        // 没有副本，不需要等待
+        Frame::Integer(0)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Wait` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("wait".as_bytes()));
+        frame.push_int(self.numreplicas);
+        frame.push_int(self.timeout.as_millis() as u64);
+        frame
+    }
+}
@@ -1,15 +1,21 @@
 use crate::frame::{self, Frame};
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
+use std::fmt;
+use std::future::{self as future, Future};
 use std::io::{self, Cursor};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
-use tokio::net::TcpStream;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter, ReadBuf};
+use tokio::time;
+use tracing::debug;
 
 /// Send and receive `Frame` value from a remote peer.
 ///
 /// When implementing networking protocol, message on that protocol is
 /// often comoposed of several smaller messages known as frames. The purpose of
-/// `Connection` is to read and write frames on the underlying `TcpStream`.
+/// `Connection` is to read and write frames on the underlying stream.
 ///
 /// To read frames, the `Connection` use an internal buffer, which is filled up
 /// until there are enough bytes to create a full frame. Once this happens,
@@ -17,30 +23,252 @@ use tokio::net::TcpStream;
 ///
 /// When sending frames, the frame is first encoded into the write buffer.
 /// The contents of the write buffer are then written to the socket.
+///
+/// Any bidirectional byte stream can be used, for example a `TcpStream`, a TLS
+/// stream wrapping it, an in-memory `tokio::io::duplex` pipe, or a stream
+/// driven by another I/O backend. The client and the server box the stream,
+/// see `Transport`, so that they handle every transport the same way.
 
 #[derive(Debug)]
-pub struct Connection {
+pub struct Connection<S = Box<dyn Transport>> {
     //  `TcpStream` 被一个提供了写入级别缓冲的 `BufWriter` 所装饰。
     // 由Tokio提供的 `BufWriter` 实现可以满足我们的需要。
-    stream: BufWriter<TcpStream>,
+    stream: BufWriter<S>,
 
     // 用来读frame的buffer
     buffer: BytesMut,
+
+    // 根据读取的统计调整每次读取的大小
+    sizing: ReadBufferSizing,
+
+    // 上一次读取的字节数，以及从那之后解析出的frame数量。
+    // 在下一次读取之前被记录到`sizing`中
+    last_read: Option<usize>,
+    frames_since_read: usize,
+
+    // 连续多少次读取之前buffer都远大于需要，且几乎是空的
+    oversized_reads: u32,
+
+    // 接受的bulk string和array的最大长度
+    max_frame_len: usize,
+
+    // 通过`HELLO`协商的协议版本
+    protocol: Protocol,
+
+    // 写入一个frame的超时时间，`None`表示没有限制
+    write_timeout: Option<Duration>,
+
+    // 为true时`write_frame`只将frame写入写buffer，不flush
+    defer_flush: bool,
+
+    // 通过`poll_read_bulk`流式读取的bulk string剩余的字节数，包括结尾的"\r\n"
+    bulk_left: usize,
+
+    // 协商压缩之后，写入时被压缩的bulk string的最小长度。`None`表示没有协商压缩
+    compression: Option<usize>,
+}
+
+/// The beginning of a frame read by `Connection::read_bulk_header`.
+#[derive(Debug)]
+pub enum BulkHeader {
+    /// The header of a bulk string of the given length. Its payload is read
+    /// with `Connection::poll_read_bulk`.
+    Bulk(usize),
+
+    /// Any other frame, read in full.
+    Frame(Frame),
+}
+
+/// Version of the protocol spoken on a `Connection`, negotiated by the client
+/// with `HELLO`.
+///
+/// Frames are encoded the same way in both versions. The version only changes
+/// the shape of some replies, like the reply to `PING` in pub/sub mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    /// RESP2, spoken by connections until they send `HELLO 3`.
+    #[default]
+    Resp2,
+
+    /// RESP3.
+    Resp3,
+}
+
+impl Protocol {
+    /// Returns the version number used by `HELLO`.
+    pub fn version(self) -> u64 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        }
+    }
+}
+
+/// A bidirectional byte stream the client and the server can run on.
+///
+/// Implemented for every stream satisfying the bounds, like `TcpStream` or
+/// `tokio_rustls::TlsStream<TcpStream>`.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug> Transport for T {}
+
+/// Adaptive sizing of the read buffer of a `Connection`.
+///
+/// Tracks the moving averages of the bytes and frames received per read of
+/// the socket. The buffer doubles, up to the maximum capacity, when reads
+/// keep filling it, and halves, down to the initial capacity, when they keep
+/// using less than a quarter of it. Both decisions need a streak of reads, and
+/// a halved buffer is still twice as large as the reads which shrank it, so
+/// the size does not oscillate.
+#[derive(Debug, Clone)]
+pub struct ReadBufferSizing {
+    capacity: usize,
+    min_capacity: usize,
+    max_capacity: usize,
+
+    avg_bytes_per_read: f64,
+    avg_frames_per_read: f64,
+
+    full_reads: u32,
+    sparse_reads: u32,
 }
 
-impl Connection {
+/// Default capacity of the read buffer, see `Connection::with_capacity`.
+pub const DEFAULT_READ_BUFFER_CAPACITY: usize = 4 * 1024;
+
+/// Default capacity the read buffer may grow to, see
+/// `Connection::set_max_read_buffer_capacity`.
+pub const DEFAULT_MAX_READ_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// Minimum length of the bulk strings compressed once compression is
+/// negotiated, see `Connection::set_compression`. Shorter ones gain too little.
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Weight of the latest read in the moving averages of `ReadBufferSizing`.
+const READ_AVERAGE_WEIGHT: f64 = 0.125;
+
+/// Number of consecutive reads filling the buffer after which it grows.
+const GROW_AFTER_READS: u32 = 4;
+
+/// Number of consecutive reads using less than a quarter of the buffer after
+/// which it shrinks.
+const SHRINK_AFTER_READS: u32 = 32;
+
+/// Number of consecutive reads with an oversized and mostly empty read buffer
+/// after which its memory is reclaimed, see `Connection::prepare_read`.
+const RECLAIM_AFTER_READS: u32 = 8;
+
+/// Length of the longest decimal written by `write_decimal`: both `u64::MAX`
+/// and `i64::MIN`, with its sign, are 20 characters long.
+const MAX_DECIMAL_LEN: usize = 20;
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
     /// Create a new `Connection`, backed by `socket`, Read an write buffers
     /// are initialized
-    pub fn new(socket: TcpStream) -> Connection {
+    pub fn new(socket: S) -> Connection<S> {
+        // read buffer 默认大小为4KB 对于mini redis的使用情景这样是可以的
+        // 但是真实的应用会因为他们特别的使用情景而调整这个值。
+        // 很有可能 read buffer 越大，效果越好
+        Connection::with_capacity(socket, DEFAULT_READ_BUFFER_CAPACITY)
+    }
+
+    /// Create a new `Connection`, backed by `socket`, with a read buffer of
+    /// `capacity` bytes.
+    ///
+    /// A small buffer saves memory on connections sending tiny commands, a
+    /// large one saves reads on connections sending big bulk values. Frames
+    /// larger than the buffer are still read, the buffer grows as needed.
+    ///
+    /// The buffer then adapts to the traffic, see `ReadBufferSizing`.
+    pub fn with_capacity(socket: S, capacity: usize) -> Connection<S> {
         Connection {
             stream: BufWriter::new(socket),
-            // read buffer 默认大小为4KB 对于mini redis的使用情景这样是可以的
-            // 但是真实的应用会因为他们特别的使用情景而调整这个值。
-            // 很有可能 read buffer 越大，效果越好
-            buffer: BytesMut::with_capacity(4 * 1024),
+            buffer: BytesMut::with_capacity(capacity),
+            sizing: ReadBufferSizing::new(capacity, DEFAULT_MAX_READ_BUFFER_CAPACITY),
+            last_read: None,
+            frames_since_read: 0,
+            oversized_reads: 0,
+            max_frame_len: frame::DEFAULT_MAX_FRAME_LEN,
+            protocol: Protocol::default(),
+            write_timeout: None,
+            defer_flush: false,
+            bulk_left: 0,
+            compression: None,
         }
     }
 
+    /// Sets the maximum length of the bulk strings, and number of elements of
+    /// the arrays, read from the peer. Defaults to
+    /// `frame::DEFAULT_MAX_FRAME_LEN`.
+    ///
+    /// A frame declaring a larger length makes `read_frame` fail with
+    /// `frame::Error::TooLarge`, without the frame being buffered.
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len;
+    }
+
+    /// Sets the time after which writing a frame, or shutting down the
+    /// connection, fails with an `io::ErrorKind::TimedOut` error. `None`, the
+    /// default, waits as long as the peer takes to read.
+    ///
+    /// This bounds how long a peer which stopped reading can block the writer.
+    pub fn set_write_timeout(&mut self, write_timeout: Option<Duration>) {
+        self.write_timeout = write_timeout;
+    }
+
+    /// Sets whether `write_frame` leaves the frame in the write buffer instead
+    /// of flushing it. The frame is then written along with the next flushed
+    /// one, or by `flush`.
+    ///
+    /// This lets the replies to pipelined commands be written at once.
+    pub fn set_defer_flush(&mut self, defer_flush: bool) {
+        self.defer_flush = defer_flush;
+    }
+
+    /// Returns the protocol version negotiated on the connection.
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Sets the protocol version negotiated on the connection.
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.protocol = protocol;
+    }
+
+    /// Enables the compression negotiated with `COMPRESS`: bulk strings of at
+    /// least `threshold` bytes are written LZ4 compressed, and compressed bulk
+    /// strings are accepted from the peer. `None`, the default, disables it.
+    ///
+    /// A bulk string which does not shrink is written as is, and so are the
+    /// payloads streamed by `write_array_with_reader`.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression = threshold;
+    }
+
+    /// Returns the compression threshold, `None` if compression was not
+    /// negotiated on the connection.
+    pub fn compression(&self) -> Option<usize> {
+        self.compression
+    }
+
+    /// Sets the capacity the read buffer may grow to. A maximum below the
+    /// initial capacity keeps the buffer at its initial capacity.
+    pub fn set_max_read_buffer_capacity(&mut self, max_capacity: usize) {
+        self.sizing.set_max_capacity(max_capacity);
+    }
+
+    /// Returns the number of bytes the read buffer holds, or can hold without
+    /// reallocating.
+    pub fn read_buffer_capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Returns the sizing of the read buffer, along with the statistics of
+    /// the reads it is based on.
+    pub fn read_buffer_sizing(&self) -> &ReadBufferSizing {
+        &self.sizing
+    }
+
     /// Read a single `Frame` value from the underlying stream.
     ///
     /// The function waits until it has retrieved enough data to parse a frame.
@@ -53,32 +281,286 @@ impl Connection {
     /// is closed in a way that doesn't break a frame in half, it returns
     /// `None`. Otherwise, an error is returned
     pub async fn read_frame(&mut self) -> crate::Result<Option<Frame>> {
+        self.skip_bulk().await?;
+
         loop {
             // 尝试从buffer中解析出一个frame。如果buffer中有足够的数据，返回一个frame
-            if let Some(frame) = self.parse_frame()? {
+            if let Some((frame, len)) = self.parse_frame()? {
+                // 摒弃已经解析过的frame data
+                // 这个操作经常通过移动内部cursor实现，但有些时候
+                // 可能会通过重新分配内存和copy数据来实现
+                self.buffer.advance(len);
+
+                self.frames_since_read += 1;
+                return Ok(Some(frame));
+            }
+
+            if !self.fill_buffer().await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Read the next `Frame` value without consuming it.
+    ///
+    /// Waits for a full frame as `read_frame` does, but the frame is left in
+    /// the read buffer, so the next call to `read_frame` returns it again. This
+    /// lets a caller, like a proxy, inspect a frame before deciding how to
+    /// handle it. Peeking several times in a row returns the same frame and
+    /// does not read from the socket again.
+    ///
+    /// # Returns
+    ///
+    /// Same as `read_frame`.
+    pub async fn peek_frame(&mut self) -> crate::Result<Option<Frame>> {
+        self.skip_bulk().await?;
+
+        loop {
+            if let Some((frame, _)) = self.parse_frame()? {
                 return Ok(Some(frame));
             }
 
-            // 如果没有读到足够的数据，尝试从socket中读取更多数据
-            // 如果成功，会返回读取的字节数量，0代表TcpStream的结尾
-            // await等待read_buf做完
-            if 0 == self.stream.read_buf(&mut self.buffer).await? {
-                // 远程关闭了连接。若要干净的关闭，buffer中不应该有数据
-                // 如果有，这表示远程在发送frame时关闭了socket
-                if self.buffer.is_empty() {
-                    return Ok(None);
-                } else {
-                    return Err("connection reset by peer".into());
+            if !self.fill_buffer().await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Returns `true` if the read buffer holds a complete frame, which the next
+    /// call to `read_frame` returns without reading from the socket.
+    pub fn has_buffered_frame(&self) -> bool {
+        if self.bulk_left > 0 {
+            return false;
+        }
+
+        let mut cursor = Cursor::new(&self.buffer[..]);
+        self.check_frame(&mut cursor).is_ok()
+    }
+
+    /// Read the next frame, stopping after the header if it is a bulk string.
+    ///
+    /// This lets a large bulk string be read in pieces, without buffering it
+    /// in full, and regardless of the maximum frame length. Its payload is
+    /// then read with `poll_read_bulk`. Any part of it left unread is
+    /// discarded by the next call to `read_frame`, `peek_frame` or
+    /// `read_bulk_header`.
+    ///
+    /// # Returns
+    ///
+    /// Same as `read_frame`, except that a bulk string is returned as
+    /// `BulkHeader::Bulk`, and any other frame as `BulkHeader::Frame`.
+    pub async fn read_bulk_header(&mut self) -> crate::Result<Option<BulkHeader>> {
+        use frame::Error::Incomplete;
+
+        self.skip_bulk().await?;
+
+        loop {
+            let mut cursor = Cursor::new(&self.buffer[..]);
+
+            match Frame::parse_bulk_header(&mut cursor) {
+                Ok(Some(len)) => {
+                    let header_len = cursor.position() as usize;
+                    self.buffer.advance(header_len);
+
+                    self.frames_since_read += 1;
+                    self.bulk_left = len + 2;
+                    return Ok(Some(BulkHeader::Bulk(len)));
+                }
+                // 不是bulk string，按完整的frame读取
+                Ok(None) => {
+                    return match self.read_frame().await? {
+                        // 只有压缩的bulk string会在这里被读成`Frame::Bulk`，
+                        // 解压后放回读buffer中，和其他bulk string一样流式读取
+                        Some(Frame::Bulk(data)) => {
+                            self.unread_bulk(&data);
+                            Ok(Some(BulkHeader::Bulk(data.len())))
+                        }
+                        frame => Ok(frame.map(BulkHeader::Frame)),
+                    };
+                }
+                Err(Incomplete) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            if !self.fill_buffer().await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Returns the number of bytes of the payload of the bulk string being
+    /// streamed left to read, see `read_bulk_header`.
+    pub fn bulk_remaining(&self) -> usize {
+        self.bulk_left.saturating_sub(2)
+    }
+
+    /// Read the payload of the bulk string whose header was returned by
+    /// `read_bulk_header` into `buf`, following the `AsyncRead` contract.
+    ///
+    /// The bytes already in the read buffer are returned first, the rest is
+    /// read from the socket straight into `buf`. Once the payload has been
+    /// read, its trailing `\r\n` is consumed and nothing more is returned.
+    pub fn poll_read_bulk(&mut self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if self.bulk_left > 2 {
+            let payload = self.bulk_left - 2;
+
+            if buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let n = if !self.buffer.is_empty() {
+                let n = payload.min(self.buffer.len()).min(buf.remaining());
+                buf.put_slice(&self.buffer[..n]);
+                self.buffer.advance(n);
+                n
+            } else {
+                // 读buffer为空时直接读入调用者的buffer，避免多复制一次
+                let mut dst = ReadBuf::new(buf.initialize_unfilled_to(payload.min(buf.remaining())));
+                ready!(Pin::new(&mut self.stream).poll_read(cx, &mut dst))?;
+                let n = dst.filled().len();
+                buf.advance(n);
+                n
+            };
+
+            if n == 0 {
+                return Poll::Ready(Err(reset_by_peer()));
+            }
+
+            self.bulk_left -= n;
+            return Poll::Ready(Ok(()));
+        }
+
+        // payload已经读完，消费结尾的"\r\n"
+        while self.bulk_left > 0 {
+            if self.buffer.is_empty() {
+                let mut trailer = [0u8; 2];
+                let mut dst = ReadBuf::new(&mut trailer[..self.bulk_left]);
+                ready!(Pin::new(&mut self.stream).poll_read(cx, &mut dst))?;
+
+                if dst.filled().is_empty() {
+                    return Poll::Ready(Err(reset_by_peer()));
                 }
+
+                self.buffer.extend_from_slice(dst.filled());
+            }
+
+            if self.buffer[0] != b"\r\n"[2 - self.bulk_left] {
+                let err = io::Error::new(io::ErrorKind::InvalidData, "protocol error; bulk string not terminated by CRLF");
+                return Poll::Ready(Err(err));
+            }
+
+            self.buffer.advance(1);
+            self.bulk_left -= 1;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Put the payload of a bulk string back in front of the read buffer, to be
+    /// read by `poll_read_bulk`.
+    fn unread_bulk(&mut self, data: &[u8]) {
+        let mut buffer = BytesMut::with_capacity(data.len() + 2 + self.buffer.len());
+        buffer.extend_from_slice(data);
+        buffer.extend_from_slice(b"\r\n");
+        buffer.extend_from_slice(&self.buffer);
+
+        self.buffer = buffer;
+        self.bulk_left = data.len() + 2;
+    }
+
+    /// Discard what is left of the bulk string being streamed, if any.
+    async fn skip_bulk(&mut self) -> crate::Result<()> {
+        let mut scratch = [0u8; 1024];
+
+        while self.bulk_left > 0 {
+            let mut buf = ReadBuf::new(&mut scratch);
+            future::poll_fn(|cx| self.poll_read_bulk(cx, &mut buf)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Read more data from the socket into the read buffer.
+    ///
+    /// Returns `false` if the peer closed the connection cleanly, between two
+    /// frames.
+    async fn fill_buffer(&mut self) -> crate::Result<bool> {
+        self.prepare_read();
+
+        // 如果没有读到足够的数据，尝试从socket中读取更多数据
+        // 如果成功，会返回读取的字节数量，0代表TcpStream的结尾
+        // await等待read_buf做完
+        //
+        // 每次读取不超过`sizing`给出的大小
+        let capacity = self.sizing.capacity();
+        let n = self.stream.read_buf(&mut (&mut self.buffer).limit(capacity)).await?;
+        self.last_read = Some(n);
+
+        if n == 0 {
+            // 远程关闭了连接。若要干净的关闭，buffer中不应该有数据
+            // 如果有，这表示远程在发送frame时关闭了socket
+            if self.buffer.is_empty() {
+                return Ok(false);
+            } else {
+                return Err(reset_by_peer().into());
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Records the statistics of the previous read, then makes room in the
+    /// buffer for the next one.
+    ///
+    /// The buffer keeps the capacity it grew to for a large frame. When it
+    /// stays more than twice as large as the sizing asks for, while holding
+    /// less than that, for `RECLAIM_AFTER_READS` reads in a row, it is
+    /// replaced by a smaller one. A connection occasionally sending a large
+    /// value thus gives the memory back, while one regularly sending them
+    /// does not reallocate the buffer for each.
+    fn prepare_read(&mut self) {
+        if let Some(bytes) = self.last_read.take() {
+            let previous = self.sizing.capacity();
+            self.sizing.record(bytes, self.frames_since_read);
+
+            if self.sizing.capacity() != previous {
+                debug!(
+                    from = previous,
+                    to = self.sizing.capacity(),
+                    "resized read buffer"
+                );
             }
         }
+        self.frames_since_read = 0;
+
+        let capacity = self.sizing.capacity();
+
+        // `reserve`在可能时把剩余的数据移到开头，重用整个已分配的内存，
+        // 之后`capacity()`才反映buffer实际占用的大小
+        self.buffer.reserve(capacity);
+
+        // 需要的数据较多时buffer的大小是合理的，重新开始计数
+        if self.buffer.len() > capacity {
+            self.oversized_reads = 0;
+        } else if self.buffer.capacity() > 2 * capacity {
+            self.oversized_reads += 1;
+        }
+
+        // 只复制剩余的少量数据
+        if self.oversized_reads >= RECLAIM_AFTER_READS {
+            let mut buffer = BytesMut::with_capacity(self.buffer.len() + capacity);
+            buffer.extend_from_slice(&self.buffer);
+            self.buffer = buffer;
+            self.oversized_reads = 0;
+        }
     }
 
     /// Tries to parse a frame from buffer. If the buffer contains enough
-    /// data. the frame is returned and the data removed from the buffer.If not
-    /// enough data has been buffered yet, `Ok(None)` is returned. If the
-    /// buffered data does not represent a valid frame, `Err` is returned
-    fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
+    /// data. the frame is returned along with its length in the buffer, the
+    /// data is left in the buffer. If not enough data has been buffered yet,
+    /// `Ok(None)` is returned. If the buffered data does not represent a
+    /// valid frame, `Err` is returned
+    fn parse_frame(&self) -> crate::Result<Option<(Frame, usize)>> {
         use frame::Error::Incomplete;
 
         // Cursor用来跟踪在buffer中的当前位置。 Cursor也实现了`bytes`包中的`Buf`
@@ -88,7 +570,7 @@ impl Connection {
         // 首先快速判断buffer中数据是否合法，这比解析buffer中的数据要快很多
         // 在我们知道这是一个完整的frame之前，我们不需要为保存frame data的数据
         // 结构分配空间
-        match Frame::check(&mut cursor) {
+        match self.check_frame(&mut cursor) {
             Ok(_) => {
                 // check过后，len会是一个完整frame的长度包括 ”\r\n“
                 let len = cursor.position() as usize;
@@ -97,15 +579,13 @@ impl Connection {
                 // 此处分配空间来保存frame数据是必要的
                 // 如果编码frame表示是非法的，错误被返回。
                 // 这种情况应该终止当前连接，而不是影响到其他连接
-                let frame = Frame::parse(&mut cursor)?;
+                let frame = match self.compression {
+                    Some(_) => Frame::parse_compressed(&mut cursor, self.max_frame_len)?,
+                    None => Frame::parse_with_max_len(&mut cursor, self.max_frame_len)?,
+                };
 
-                // 摒弃已经解析过的frame data
-                // 这个操作经常通过移动内部cursor实现，但有些时候
-                // 可能会通过重新分配内存和copy数据来实现
-                self.buffer.advance(len);
-
-                // 返回解析的frame
-                Ok(Some(frame))
+                // 返回解析的frame，由调用者决定是否摒弃frame data
+                Ok(Some((frame, len)))
             }
             // 如果没有足够的数据来解析成一个frame。我们必须等待更多的数据
             // 从socket中被接收。在这个match结束后，从socket中读数据将会被执行
@@ -118,6 +598,14 @@ impl Connection {
         }
     }
 
+    /// 协商压缩之后，压缩的bulk string也是合法的frame
+    fn check_frame(&self, src: &mut Cursor<&[u8]>) -> Result<(), frame::Error> {
+        match self.compression {
+            Some(_) => Frame::check_compressed(src, self.max_frame_len),
+            None => Frame::check_with_max_len(src, self.max_frame_len),
+        }
+    }
+
     /// Write a single `Frame` value to the underlying stream
     ///
     /// The `Frame` value is written to the socket using various `write_*`
@@ -126,25 +614,125 @@ impl Connection {
     /// syscalls. However, it is fine to call these function on a *buffered*
     /// write stream. The data will be written to the buffer. Once the buffer is
     /// full, it is flushed to the underlying socket.
+    ///
+    /// The frame is flushed to the socket, unless flushing is deferred, see
+    /// `set_defer_flush`.
+    ///
+    /// Fails with an `io::ErrorKind::TimedOut` error if the frame is not fully
+    /// written within the write timeout, see `set_write_timeout`.
     pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        // Array通过编码其他entry来编码。 其他frame type被认为是字面量。
-        // 现在，mini redis还不能编码recursive frame structures。
-        match frame {
-            Frame::Array(vec) => {
-                self.stream.write_u8(b'*').await?;
+        let write_timeout = self.write_timeout;
+        let defer_flush = self.defer_flush;
 
-                self.write_decimal(vec.len() as u64).await?;
+        with_write_timeout(write_timeout, async {
+            self.write_value(frame).await?;
 
-                for entry in &*vec {
-                    self.write_value(entry).await?;
-                }
+            if defer_flush {
+                return Ok(());
+            }
+
+            // 确保encode frame 被写入socket。上面的调用是将数据写入buffered stream。
+            // 调用`flush`将在buffer中剩余的内容写入到socket中
+            self.stream.flush().await
+        })
+        .await
+    }
+
+    /// Write the header of an array frame of `len` elements, which must then
+    /// be written one by one, e.g. with `write_frame`.
+    ///
+    /// The header is left in the write buffer, and sent along with the first
+    /// element. This lets the elements be produced one at a time, like the
+    /// replies to the commands of a transaction. The header of an empty array
+    /// is flushed like a frame written with `write_frame`.
+    pub async fn write_array_header(&mut self, len: usize) -> io::Result<()> {
+        let write_timeout = self.write_timeout;
+        let defer_flush = self.defer_flush;
+
+        with_write_timeout(write_timeout, async {
+            self.stream.write_u8(b'*').await?;
+            self.write_decimal(len).await?;
+
+            if len > 0 || defer_flush {
+                return Ok(());
             }
-            _ => self.write_value(frame).await?,
-        }
 
-        // 确保encode frame 被写入socket。上面的调用是将数据写入buffered stream。
-        // 调用`flush`将在buffer中剩余的内容写入到socket中
-        self.stream.flush().await
+            self.stream.flush().await
+        })
+        .await
+    }
+
+    /// Write the snapshot sent to a replica during a full resynchronization,
+    /// see `PSYNC`, and flush it.
+    ///
+    /// The payload is encoded like a bulk string, except that it is not
+    /// followed by `\r\n`: the replica reads the length, the payload, then
+    /// the commands of the replication stream right after it.
+    pub async fn write_rdb(&mut self, payload: &[u8]) -> io::Result<()> {
+        let write_timeout = self.write_timeout;
+
+        with_write_timeout(write_timeout, async {
+            self.stream.write_u8(b'$').await?;
+            self.write_decimal(payload.len()).await?;
+            self.stream.write_all(payload).await?;
+            self.stream.flush().await
+        })
+        .await
+    }
+
+    /// Write an array frame whose last element is a bulk string of `len`
+    /// bytes read from `reader`, the other elements being `head`.
+    ///
+    /// The bulk string is copied from `reader` to the socket as it is read,
+    /// without being buffered in full. This lets a command carrying a large
+    /// value, like `SET`, be sent from a file. The frame is flushed, like with
+    /// `write_frame`, and bounded by the write timeout.
+    ///
+    /// Fails with an `io::ErrorKind::UnexpectedEof` error if `reader` ends
+    /// before `len` bytes. On any error, the frame may have been partially
+    /// written and the peer can no longer parse the stream.
+    pub async fn write_array_with_reader<R>(&mut self, head: &[Frame], len: u64, reader: R) -> io::Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let write_timeout = self.write_timeout;
+
+        with_write_timeout(write_timeout, async {
+            self.stream.write_u8(b'*').await?;
+            self.write_decimal(head.len() + 1).await?;
+
+            for entry in head {
+                self.write_value(entry).await?;
+            }
+
+            self.stream.write_u8(b'$').await?;
+            self.write_decimal(len).await?;
+
+            let copied = tokio::io::copy(&mut reader.take(len), &mut self.stream).await?;
+            if copied < len {
+                let err = io::Error::new(io::ErrorKind::UnexpectedEof, "reader ended before the announced length");
+                return Err(err);
+            }
+
+            self.stream.write_all(b"\r\n").await?;
+            self.stream.flush().await
+        })
+        .await
+    }
+
+    /// Write the frames left in the write buffer to the socket.
+    ///
+    /// Bounded by the write timeout, like `write_frame`.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        with_write_timeout(self.write_timeout, self.stream.flush()).await
+    }
+
+    /// Flush any pending write and shut down the write half of the stream.
+    ///
+    /// The peer reads EOF once it has received everything written before.
+    /// Bounded by the write timeout, like `write_frame`.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        with_write_timeout(self.write_timeout, self.stream.shutdown()).await
     }
 
     /// Write a frame literal to the stream
@@ -164,29 +752,64 @@ impl Connection {
                 self.stream.write_u8(b':').await?;
                 self.write_decimal(*val).await?;
             }
+            Frame::SignedInteger(val) => {
+                self.stream.write_u8(b':').await?;
+                self.write_decimal(*val).await?;
+            }
             Frame::Null => {
                 self.stream.write_all(b"$-1\r\n").await?;
             }
-            Frame::Bulk(val) => {
-                let len = val.len();
+            // 协商压缩之后，足够长的bulk string被压缩写入
+            Frame::Bulk(val) => match self.compress(val) {
+                Some(compressed) => {
+                    self.stream.write_u8(b'&').await?;
+                    self.write_decimal(compressed.len() as u64).await?;
+                    self.stream.write_all(&compressed).await?;
+                    self.stream.write_all(b"\r\n").await?;
+                }
+                None => {
+                    self.stream.write_u8(b'$').await?;
+                    self.write_decimal(val.len() as u64).await?;
+                    self.stream.write_all(val).await?;
+                    self.stream.write_all(b"\r\n").await?;
+                }
+            },
+            // Array通过编码其他entry来编码，entry本身也可能是Array(例如`SCAN`的回复)。
+            // 异步函数的递归调用需要被boxed，否则future的大小无法确定
+            Frame::Array(val) => {
+                self.stream.write_u8(b'*').await?;
+                self.write_decimal(val.len() as u64).await?;
 
-                self.stream.write_u8(b'$').await?;
-                self.write_decimal(len as u64).await?;
-                self.stream.write_all(val).await?;
-                self.stream.write_all(b"\r\n").await?;
+                for entry in val {
+                    Box::pin(self.write_value(entry)).await?;
+                }
             }
-            // 不能使用递归策略从一个值内部对Array进行编码。一般来说异步函数
-            // 不支持递归。Mini-redis还不需要对nested(嵌套)arrays进行编码
-            // 所以暂时跳过
-            Frame::Array(_val) => unreachable!(),
         }
         Ok(())
     }
 
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
+    /// Returns `val` compressed, if compression was negotiated, `val` reaches
+    /// the threshold and actually shrinks.
+    fn compress(&self, val: &[u8]) -> Option<Vec<u8>> {
+        let threshold = self.compression?;
+
+        // 前置的原始长度是`u32`，更长的不压缩
+        if val.len() < threshold || u32::try_from(val.len()).is_err() {
+            return None;
+        }
+
+        Some(frame::compress(val)).filter(|compressed| compressed.len() < val.len())
+    }
+
+    /// Write `val` in decimal, followed by `\r\n`.
+    ///
+    /// Any integer of up to 64 bits, signed or not, fits in the buffer. A
+    /// longer value fails with an `io::ErrorKind::WriteZero` error instead of
+    /// being truncated, and nothing is written.
+    async fn write_decimal(&mut self, val: impl fmt::Display) -> io::Result<()> {
         use std::io::Write;
 
-        let mut buf = [0u8; 20];
+        let mut buf = [0u8; MAX_DECIMAL_LEN];
         let mut buf = Cursor::new(&mut buf[..]);
         write!(&mut buf, "{}", val)?;
 
@@ -197,3 +820,88 @@ impl Connection {
         Ok(())
     }
 }
+
+/// Runs the write `fut` to completion, or fails with an
+/// `io::ErrorKind::TimedOut` error if it takes longer than `write_timeout`.
+async fn with_write_timeout<F>(write_timeout: Option<Duration>, fut: F) -> io::Result<()>
+where
+    F: Future<Output = io::Result<()>>,
+{
+    match write_timeout {
+        Some(write_timeout) => time::timeout(write_timeout, fut)
+            .await
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::TimedOut, "write timed out"))),
+        None => fut.await,
+    }
+}
+
+/// The error of a peer closing the connection in the middle of a frame.
+fn reset_by_peer() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionReset, "connection reset by peer")
+}
+
+impl ReadBufferSizing {
+    /// Create the sizing of a buffer starting at, and never shrinking below,
+    /// `capacity` bytes, and growing up to `max_capacity` bytes.
+    pub fn new(capacity: usize, max_capacity: usize) -> ReadBufferSizing {
+        let capacity = capacity.max(1);
+
+        ReadBufferSizing {
+            capacity,
+            min_capacity: capacity,
+            max_capacity: max_capacity.max(capacity),
+            avg_bytes_per_read: 0.0,
+            avg_frames_per_read: 0.0,
+            full_reads: 0,
+            sparse_reads: 0,
+        }
+    }
+
+    /// Sets the capacity the buffer may grow to. If the buffer is already
+    /// larger, it shrinks to it right away.
+    pub fn set_max_capacity(&mut self, max_capacity: usize) {
+        self.max_capacity = max_capacity.max(self.min_capacity);
+        self.capacity = self.capacity.min(self.max_capacity);
+    }
+
+    /// Returns the number of bytes to read from the socket at once.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the moving average of the bytes received per read.
+    pub fn avg_bytes_per_read(&self) -> f64 {
+        self.avg_bytes_per_read
+    }
+
+    /// Returns the moving average of the frames parsed per read.
+    pub fn avg_frames_per_read(&self) -> f64 {
+        self.avg_frames_per_read
+    }
+
+    /// Records a read of `bytes` bytes, out of which `frames` frames were
+    /// parsed, and resizes the buffer if needed.
+    pub fn record(&mut self, bytes: usize, frames: usize) {
+        self.avg_bytes_per_read += (bytes as f64 - self.avg_bytes_per_read) * READ_AVERAGE_WEIGHT;
+        self.avg_frames_per_read += (frames as f64 - self.avg_frames_per_read) * READ_AVERAGE_WEIGHT;
+
+        if bytes >= self.capacity {
+            self.full_reads += 1;
+            self.sparse_reads = 0;
+        } else if self.avg_bytes_per_read < (self.capacity / 4) as f64 {
+            self.full_reads = 0;
+            self.sparse_reads += 1;
+        } else {
+            self.full_reads = 0;
+            self.sparse_reads = 0;
+        }
+
+        if self.full_reads >= GROW_AFTER_READS && self.capacity < self.max_capacity {
+            self.capacity = (self.capacity * 2).min(self.max_capacity);
+            self.full_reads = 0;
+        } else if self.sparse_reads >= SHRINK_AFTER_READS && self.capacity > self.min_capacity {
+            self.capacity = (self.capacity / 2).max(self.min_capacity);
+            self.sparse_reads = 0;
+        }
+    }
+}
@@ -1,16 +1,69 @@
-use tokio::sync::{broadcast, Notify};
+use crate::cmd::{ChannelStats, ExpireCondition, HotKey, SetCondition, StreamEntry, StreamId, Ttl, TypeHistogram};
+use crate::fanout::{self, Overflow};
+use crate::pubsub::Strictness;
+use crate::replication::{self, Change, SnapshotEntry};
+
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
 use tokio::time::{self, Duration, Instant};
 
-use bytes::Bytes;
-use std::collections::{BTreeSet, HashMap};
-use std::sync::{Arc, Mutex};
-use tracing::debug;
+use bytes::{Bytes, BytesMut};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// Values `0..SHARED_INTEGERS` are stored as shared `Bytes`, so that keys
+/// holding the same small integer point to the same allocation.
+const SHARED_INTEGERS: usize = 10000;
+
+/// Reference count reported by `OBJECT REFCOUNT` for shared integers. As in
+/// Redis, shared values are never freed, so the count is pinned at the maximum.
+const SHARED_REFCOUNT: u64 = i32::MAX as u64;
+
+/// Number of keys `Db::expire_many` updates per acquisition of the lock, so
+/// that a large batch does not keep the other clients waiting.
+const EXPIRE_BATCH: usize = 1024;
+
+/// Number of publish timestamps remembered per channel, used to count the
+/// messages published since a `SUBSCRIBE ... SINCE` timestamp.
+const CHANNEL_HISTORY: usize = 1024;
+
+/// Number of keys counted by the hot keys tracker. Keys beyond this number
+/// replace the least accessed one, see `HotKeySketch`.
+const HOTKEYS_CAPACITY: usize = 2048;
+
+/// Default time `Db::memory_histogram` may hold the lock for at once.
+pub(crate) const DEFAULT_HISTOGRAM_SLICE: Duration = Duration::from_millis(1);
+
+/// Number of keys `Db::memory_histogram` visits between two checks of the
+/// time spent holding the lock.
+const HISTOGRAM_CHECK_INTERVAL: usize = 64;
+
+/// Total length of the values removed by `Db::unlink` from which they are
+/// released on a blocking thread rather than by the task handling `UNLINK`.
+const UNLINK_BLOCKING_THRESHOLD: usize = 1024 * 1024;
+
+/// Error replied to a command applied to a key holding a value of another
+/// type, e.g. `GET` on a list.
+pub(crate) const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// Server name reported by `HELLO` by default: the name of the crate.
+pub(crate) const DEFAULT_SERVER_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Server version reported by `HELLO` by default: the version of the crate.
+pub(crate) const DEFAULT_SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// A wrapper around a `Db` instance. This exists to allow orderly cleanup
 /// of the `Db` by signalling the background purge task to shut down when
 /// this struct is dropped.
 #[derive(Debug)]
-pub(crate) struct DbDropGuard {
+pub struct DbDropGuard {
     /// The `Db` instance that will be shut down when this `DbHolder` struct
     /// is dropped.
     db: Db,
@@ -19,7 +72,7 @@ pub(crate) struct DbDropGuard {
 /// Server state shared across al connections.
 ///
 /// `Db` conntains a `HashMap` storing the key/value data and all
-/// `broadcast::Sender` values for active pub/sub channels.
+/// `fanout::Sender` values for active pub/sub channels.
 ///
 /// A `Db` instance is a handle to shared state. Cloning `Db` is shallow and
 /// only incurs an atomic ref count increment.
@@ -29,12 +82,18 @@ pub(crate) struct DbDropGuard {
 /// runs until all instances of `Db` are dropped, at which point the task
 /// terminates.
 #[derive(Debug, Clone)]
-pub(crate) struct Db {
+pub struct Db {
     /// Handle to shared state. The background task will also have an
     /// `Arc<Shared>`
     shared: Arc<Shared>,
 }
 
+/// Handle to a bridge between two pub/sub channels, returned by `Db::bridge`.
+#[derive(Debug)]
+pub struct BridgeHandle {
+    task: JoinHandle<()>,
+}
+
 #[derive(Debug)]
 struct Shared {
     /// The shared state is guarded by a mutex. This is a `std::sync::Mutex` and
@@ -55,6 +114,15 @@ struct Shared {
     /// task waits on this to be notified, then checks for expired values or the
     /// shutdown signal.
     background_task: Notify,
+
+    /// True while `Db::memory_histogram` is running, so that a single
+    /// histogram is computed at a time.
+    histogram_running: AtomicBool,
+
+    /// Set to `true` when the `Db` shuts down, stopping the tasks spawned by
+    /// `Db::bridge`. Unlike `background_task`, the value is kept, so a bridge
+    /// busy republishing a message does not miss the signal.
+    bridges_shutdown: watch::Sender<bool>,
 }
 
 #[derive(Debug)]
@@ -65,7 +133,27 @@ struct State {
 
     /// The pub/sub key-space. Redis uses a **separate** key space for key-value
     /// and pub/sub. `mini-redis` handles this by using a separate `HashMap`.
-    pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
+    ///
+    /// Only the channels with subscribers are kept: a channel is removed when
+    /// its last subscriber leaves, see `State::release_channel`.
+    pub_sub: HashMap<String, fanout::Sender<Bytes>>,
+
+    /// The statistics of the traffic on the channels which have been
+    /// subscribed to at least once. Unlike `pub_sub`, they are kept once the
+    /// subscribers leave, so that `SUBSCRIBE ... SINCE` can count the messages
+    /// they missed.
+    channel_traffic: HashMap<String, ChannelTraffic>,
+
+    /// The glob-style patterns subscribed to with `PSUBSCRIBE`. A message
+    /// published on a channel matching a pattern is sent along with the name
+    /// of the channel. Like channels, a pattern is removed when its last
+    /// subscriber leaves.
+    pub_sub_patterns: HashMap<String, fanout::Sender<(Arc<str>, Bytes)>>,
+
+    /// The streams read with `XSUBSCRIBE`, notified of the id of each entry
+    /// added by `XADD`. Like channels, a stream is removed when its last
+    /// reader leaves.
+    stream_readers: HashMap<String, watch::Sender<StreamId>>,
 
     /// Tracks key TTLs
     ///
@@ -79,36 +167,291 @@ struct State {
     /// break these ties.
     expirations: BTreeSet<(Instant, String)>,
 
+    /// Classes of keyspace events published on writes and expirations.
+    keyspace_events: KeyspaceEvents,
+
+    /// Callbacks registered with `Db::on_expire`.
+    expire_callbacks: ExpireCallbacks,
+
+    /// How counters overflowing an `i64` are handled by `StateView::incr_by`.
+    counter_overflow: CounterOverflow,
+
+    /// Number of messages each pub/sub subscriber may have pending, and what
+    /// happens to the next ones. Applies to the channels and patterns created
+    /// afterwards.
+    pubsub_queue_capacity: usize,
+    pubsub_overflow: Overflow,
+
+    /// How the channel names of `SUBSCRIBE` are checked.
+    pubsub_strictness: Strictness,
+
+    /// Access counts of the hottest keys, `None` when hot key tracking is
+    /// disabled.
+    hotkeys: Option<HotKeySketch>,
+
+    /// Maximum time `Db::memory_histogram` holds the lock for at once.
+    histogram_slice: Duration,
+
+    /// Name and version of the server reported by `HELLO`.
+    server_name: String,
+    server_version: String,
+
+    /// The changes of the keyspace sent to the replicas, see
+    /// `Db::replicate`.
+    replicas: fanout::Sender<Change>,
+
     /// True when the Db instance is shutting down. This happens when all `Db`
     /// values drop. Setting this to `true` signals to the background task to
     /// exit.
     shutdown: bool,
 }
 
+/// The statistics of the traffic on a pub/sub channel.
+#[derive(Debug, Default)]
+struct ChannelTraffic {
+    /// Number of messages published on the channel.
+    published: u64,
+
+    /// Highest number of subscribers the channel had at once.
+    peak_receivers: usize,
+
+    /// Unix timestamps, in milliseconds, of the last `CHANNEL_HISTORY`
+    /// messages published, oldest first.
+    history: VecDeque<u64>,
+}
+
+/// Callback invoked with the key of each expired entry, along with its value
+/// when it was a string.
+type ExpireCallback = dyn Fn(&str, Option<&Bytes>) + Send + Sync;
+
+/// Callbacks registered with `Db::on_expire`.
+///
+/// Stored as `Arc`s so the background task can clone them out of the lock
+/// and run them once it is released.
+#[derive(Default)]
+struct ExpireCallbacks(Vec<Arc<ExpireCallback>>);
+
+/// Approximate access counts of the most accessed keys, maintained with the
+/// Space-Saving algorithm.
+///
+/// At most `HOTKEYS_CAPACITY` keys are counted. When an untracked key is
+/// accessed while the sketch is full, it replaces the key with the lowest
+/// count and inherits that count. A key accessed more often than
+/// `1 / HOTKEYS_CAPACITY` of the time is therefore always tracked, and its
+/// count is overestimated by at most the count it inherited.
+///
+/// The counters form a binary min-heap on their counts, so the key with the
+/// lowest count is always the first one. Recording an access is therefore
+/// O(log n) rather than a scan of all the counters, which matters as it runs
+/// under the lock of the `Db` on every read and write.
+///
+/// Once full, the sketch does not allocate: a replaced key reuses the buffers
+/// of the key it replaces.
+#[derive(Debug)]
+struct HotKeySketch {
+    /// Position of each tracked key in `counters`.
+    index: HashMap<String, usize>,
+
+    /// Min-heap of the counters, ordered by `hits`.
+    counters: Vec<HotKeyCounter>,
+}
+
+#[derive(Debug)]
+struct HotKeyCounter {
+    key: String,
+    hits: u64,
+    last_hit: Instant,
+}
+
 /// Entry in the key-value store
 #[derive(Debug)]
 struct Entry {
     /// Stored data
-    data: Bytes,
+    data: Value,
 
     /// Instant at which the entry expires and should be removed from the database
     expires_at: Option<Instant>,
+
+    /// Instant at which the entry was last written, read by `GET` or touched
+    /// by `TOUCH`. Reported by `OBJECT IDLETIME`.
+    accessed_at: Instant,
+}
+
+/// Value stored in an entry.
+#[derive(Debug)]
+enum Value {
+    String(Bytes),
+
+    /// A list, the head being the left end, as pushed by `LPUSH`. A list is
+    /// never empty: the key is removed along with its last element.
+    List(VecDeque<Bytes>),
+
+    /// A hash, mapping fields to values. Like a list, a hash is never empty.
+    Hash(HashMap<String, Bytes>),
+
+    /// A stream, its entries ordered by id, each with its fields and values in
+    /// the order given to `XADD`. Entries are only ever appended.
+    Stream(BTreeMap<StreamId, Vec<(String, Bytes)>>),
+}
+
+/// End of a list pushed to or popped from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListEnd {
+    Left,
+    Right,
+}
+
+/// Clears `Shared::histogram_running` when dropped.
+struct HistogramRunning<'a>(&'a AtomicBool);
+
+impl Drop for HistogramRunning<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Access to the key-value data inside `Db::atomic`.
+///
+/// All the operations performed through a `StateView` happen while holding
+/// the lock of the `Db`, so no other operation can be interleaved with them.
+#[derive(Debug)]
+pub struct StateView<'a> {
+    state: &'a mut State,
+
+    /// Keyspace events of the operations performed, published once the lock
+    /// is released.
+    events: Vec<KeyspaceEvent>,
+
+    /// Set when an operation changed the next expiration, so the background
+    /// task is notified once the lock is released.
+    notify: bool,
+}
+
+/// Options of `StateView::set_with_options`, mirroring those of `SET`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SetOptions {
+    /// Duration after which the key expires. `None` discards any time to live,
+    /// unless `keep_ttl` is set.
+    pub(crate) expire: Option<Duration>,
+
+    /// Condition under which the value is written.
+    pub(crate) condition: Option<SetCondition>,
+
+    /// Keep the time to live of the key. Only meaningful when `expire` is
+    /// `None`.
+    pub(crate) keep_ttl: bool,
+
+    /// The previous value is replied, so the key must not hold a value of
+    /// another type than string.
+    pub(crate) get: bool,
+}
+
+/// Update of the time to live of a key by `StateView::get_ex`, mirroring the options
+/// of `GETEX`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TtlUpdate {
+    /// Expire the key after the duration.
+    Expire(Duration),
+
+    /// Remove the time to live of the key.
+    Persist,
+}
+
+/// Behavior of `INCR`, `DECR`, `INCRBY` and `DECRBY` when the result does not
+/// fit in an `i64`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CounterOverflow {
+    /// Reply with an error and leave the value unchanged, as Redis does.
+    #[default]
+    Error,
+
+    /// Clamp the result to `i64::MIN` or `i64::MAX`.
+    Saturating,
+}
+
+/// Classes of keyspace events published by the server.
+///
+/// Parsed from the syntax of the Redis `notify-keyspace-events` setting: `K`
+/// publishes on `__keyspace@0__:<key>` channels, with the event as message,
+/// and `E` publishes on `__keyevent@0__:<event>` channels, with the key as
+/// message. At least one of them must be combined with the classes of events:
+///
+/// * `g` -- Generic commands: `del`.
+/// * `$` -- String commands: `set`, `append`, `setrange`, `incrby` and
+///   `incrbyfloat`.
+/// * `l` -- List commands: `lpush`, `rpush`, `lpop` and `rpop`.
+/// * `h` -- Hash commands: `hset` and `hdel`.
+/// * `t` -- Stream commands: `xadd`.
+/// * `x` -- Expired keys: `expired`.
+/// * `A` -- Alias for `g$lhtx`.
+///
+/// The other classes of Redis are accepted but have no effect, as the
+/// server only stores strings, lists, hashes and streams. No event is
+/// published by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyspaceEvents {
+    keyspace: bool,
+    keyevent: bool,
+    generic: bool,
+    string: bool,
+    list: bool,
+    hash: bool,
+    stream: bool,
+    expired: bool,
+}
+
+/// Class of a keyspace event, see `KeyspaceEvents`.
+#[derive(Debug, Clone, Copy)]
+enum EventClass {
+    Generic,
+    String,
+    List,
+    Hash,
+    Stream,
+    Expired,
+}
+
+/// A keyspace event waiting to be published.
+#[derive(Debug)]
+struct KeyspaceEvent {
+    name: &'static str,
+    key: String,
+}
+
+/// Result of `StateView::set_with_options`.
+#[derive(Debug)]
+pub(crate) struct SetOutcome {
+    /// `true` if the value was written.
+    pub(crate) written: bool,
+
+    /// The string associated with the key before the call, whether or not the
+    /// new value was written.
+    pub(crate) previous: Option<Bytes>,
 }
 
 impl DbDropGuard {
     /// Create a new `DbHolder`, wrapping a `Db` instance. When this is dropped
     /// the `Db`'s purge task will be shut down.
-    pub(crate) fn new() -> DbDropGuard {
+    ///
+    /// Must be called from within a Tokio runtime, as the purge task is
+    /// spawned on it.
+    pub fn new() -> DbDropGuard {
         DbDropGuard { db: Db::new() }
     }
 
     /// Get the shared database. Internally, this is an
     /// `Arc`, so a clone only increments the ref count.
-    pub(crate) fn db(&self) -> Db {
+    pub fn db(&self) -> Db {
         self.db.clone()
     }
 }
 
+impl Default for DbDropGuard {
+    fn default() -> DbDropGuard {
+        DbDropGuard::new()
+    }
+}
+
 impl Drop for DbDropGuard {
     fn drop(&mut self) {
         // 向`Db`实例发送信号，关闭清除过期密钥的任务
@@ -124,10 +467,26 @@ impl Db {
             state: Mutex::new(State {
                 entries: HashMap::new(),
                 pub_sub: HashMap::new(),
+                channel_traffic: HashMap::new(),
+                pub_sub_patterns: HashMap::new(),
+                stream_readers: HashMap::new(),
                 expirations: BTreeSet::new(),
+                keyspace_events: KeyspaceEvents::default(),
+                expire_callbacks: ExpireCallbacks::default(),
+                counter_overflow: CounterOverflow::default(),
+                pubsub_queue_capacity: fanout::DEFAULT_CAPACITY,
+                pubsub_overflow: Overflow::default(),
+                pubsub_strictness: Strictness::Strict,
+                hotkeys: None,
+                histogram_slice: DEFAULT_HISTOGRAM_SLICE,
+                server_name: DEFAULT_SERVER_NAME.to_string(),
+                server_version: DEFAULT_SERVER_VERSION.to_string(),
+                replicas: fanout::Sender::new(replication::REPLICA_QUEUE_CAPACITY, Overflow::Disconnect),
                 shutdown: false,
             }),
             background_task: Notify::new(),
+            histogram_running: AtomicBool::new(false),
+            bridges_shutdown: watch::channel(false).0,
         });
 
         // Start the background task.
@@ -136,176 +495,2361 @@ impl Db {
         Db { shared }
     }
 
-    /// Get the value associated with a key.
+    /// Remove the given keys, like `del`, but release their values after the
+    /// lock has been released, so that other clients do not wait for large
+    /// values to be freed. Returns the number of keys that were removed.
+    pub(crate) fn unlink(&self, keys: &[String]) -> usize {
+        let removed: Vec<Entry> = self.atomic(|view| {
+            keys.iter()
+                .filter_map(|key| if view.contains(key) { view.remove(key) } else { None })
+                .collect()
+        });
+
+        let count = removed.len();
+
+        // 值很大时在阻塞线程上释放，不占用处理命令的任务
+        let bytes: usize = removed.iter().map(|entry| entry.data.size()).sum();
+        if bytes >= UNLINK_BLOCKING_THRESHOLD {
+            tokio::task::spawn_blocking(move || drop(removed));
+        }
+
+        count
+    }
+
+    /// Set the keys to expire after `ttl` if `condition` holds, as with an
+    /// `EXPIRE` per key. Returns, for each key, whether its time to live was
+    /// set.
     ///
-    /// Returns `None` if there is no value associated with the key. This may be
-    /// due to never having assigned a value to the key or previously assigned
-    /// value expired.
-    pub(crate) fn get(&self, key: &str) -> Option<Bytes> {
-        // 需要先获得锁， 拿到entry并clone
-        //
-        // 由于数据用`Bytes`存储，clone is shallow clone
-        // 数据并没有被copied
-        let state = self.shared.state.lock().unwrap();
-        state.entries.get(key).map(|entry| entry.data.clone())
+    /// The keys are updated `EXPIRE_BATCH` at a time, each batch under a
+    /// single acquisition of the lock, so that a long list of keys does not
+    /// keep the other clients waiting.
+    pub(crate) fn expire_many(&self, keys: &[String], ttl: Duration, condition: Option<ExpireCondition>) -> Vec<bool> {
+        let mut results = Vec::with_capacity(keys.len());
+
+        for batch in keys.chunks(EXPIRE_BATCH) {
+            results.extend(self.atomic(|view| view.expire_many(batch, ttl, condition)));
+        }
+
+        results
     }
 
-    /// Set the value associated with a key along with an optional expiration
-    /// Duration.
+    /// Runs `f` while holding the lock, and returns its result.
+    ///
+    /// This allows performing several operations atomically, for example a
+    /// read-modify-write across several keys, without another command
+    /// observing or modifying the keys in between.
+    ///
+    /// `f` must not block: every other connection waits for the lock while it
+    /// runs.
     ///
-    /// If a value is already associated with the key,it is removed.
-    pub(crate) fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
+    /// # Examples
+    ///
+    /// ```
+    /// use my_mini_redis::db::DbDropGuard;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let guard = DbDropGuard::new();
+    ///     let db = guard.db();
+    ///
+    ///     db.atomic(|view| {
+    ///         let a = view.get("a").unwrap_or_default();
+    ///         let b = view.get("b").unwrap_or_default();
+    ///         view.set("a", b);
+    ///         view.set("b", a);
+    ///     });
+    /// }
+    /// ```
+    pub fn atomic<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut StateView) -> R,
+    {
         let mut state = self.shared.state.lock().unwrap();
 
-        // If this `set` becomes the key that expires **next**, the background
-        // task needs to be notified so it can update its state.
-        //
-        // Whether or not the task needs to be notified is computed during the
-        // `set` routine
-        let mut notify = false;
+        let mut view = StateView {
+            state: &mut state,
+            events: vec![],
+            notify: false,
+        };
+        let ret = f(&mut view);
 
-        let expires_at = expire.map(|duration| {
-            // `Instant` at which the key expires.
-            let when = Instant::now() + duration;
+        let StateView { events, notify, .. } = view;
+        drop(state);
 
-            // state.next_expiration()获取当前等待过期的第一个entry的时间戳when。
-            // map函数将新entry的过期时间when与最近一个要过期的entry的expiration进行比较。
-            // 如果expiration更大,说明新entry是下一个过期的,返回true。
-            // 否则expiration小于或等于when,返回false。
-            // unwrap_or(true)是为了处理next_expiration()可能返回None的情况,
-            // 如果是None，证明set中没有即将过期的entry，则直接返回true。
-            notify = state
-                .next_expiration()
-                .map(|expiration| expiration > when)
-                .unwrap_or(true);
+        if notify {
+            self.shared.background_task.notify_one();
+        }
 
-            when
-        });
-        //state.entries是一个HashMap,键是String,值是Entry结构。
-        //当调用insert方法向HashMap插入一对键值对时,如果该键之前存在,insert方法会返回之前的值。
-        //如果键不存在,insert方法会返回None。
-        let prev = state.entries.insert(
-            key.clone(),
-            Entry {
-                data: value,
-                expires_at,
-            },
-        );
+        self.shared.publish_keyspace_events(events);
 
-        // 如果之前有值，则需要讲之前的key从set也就是expirations中移除，避免缺少数据
-        if let Some(prev) = prev {
-            if let Some(when) = prev.expires_at {
-                // key 后面要用所以不能将所有权给元组
-                state.expirations.remove(&(when, key.clone()));
+        ret
+    }
+
+    /// Registers a replica: returns a snapshot of the keyspace, along with the
+    /// receiver of the changes made after it.
+    ///
+    /// Both are taken under the lock, so the replica misses no change and
+    /// receives none twice. Only string values can be replicated, an error is
+    /// returned if another type is stored. Copying the keys holds the lock
+    /// for a time proportional to their number, the values are not copied.
+    pub(crate) fn replicate(&self) -> Result<(Vec<SnapshotEntry>, fanout::Receiver<Change>), String> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let now = Instant::now();
+
+        let mut snapshot = Vec::with_capacity(state.entries.len());
+
+        for (key, entry) in &state.entries {
+            // 已过期但还未被清除的key不在快照中
+            if entry.expires_at.map(|when| when <= now).unwrap_or(false) {
+                continue;
             }
-        }
-        // 如果在插入前删除在(when, key)相等时会造成bug
-        //
-        if let Some(when) = expires_at {
-            state.expirations.insert((when, key));
-        }
 
-        // 在唤醒任务之前释放锁，这样可以使得任务被唤醒就可以拿到锁，
-        // 而不是被唤醒后等待当前作用域释放锁
-        drop(state);
+            let Value::String(data) = &entry.data else {
+                return Err(format!("ERR key '{}' holds a {}, only strings can be replicated", key, entry.type_name()));
+            };
 
-        if notify {
-            // 如果当前任务需要被唤醒，则唤醒任务
-            self.shared.background_task.notify_one();
+            snapshot.push(SnapshotEntry {
+                key: key.clone(),
+                value: data.clone(),
+                expires_at: entry.expires_at.map(|when| unix_millis_at(when, now)),
+            });
         }
+
+        Ok((snapshot, state.replicas.subscribe()))
+    }
+
+    /// Sets how counters overflowing an `i64` are handled by `incr_by`.
+    pub(crate) fn set_counter_overflow(&self, overflow: CounterOverflow) {
+        self.shared.state.lock().unwrap().counter_overflow = overflow;
+    }
+
+    /// Sets the number of messages each pub/sub subscriber may have pending,
+    /// and what happens to a message sent to a subscriber with a full queue.
+    ///
+    /// Channels and patterns with subscribers keep their settings until their
+    /// last subscriber leaves.
+    pub(crate) fn set_pubsub_queue(&self, capacity: usize, overflow: Overflow) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.pubsub_queue_capacity = capacity;
+        state.pubsub_overflow = overflow;
+    }
+
+    /// Sets how the channel names of `SUBSCRIBE` are checked.
+    pub(crate) fn set_pubsub_strictness(&self, strictness: Strictness) {
+        self.shared.state.lock().unwrap().pubsub_strictness = strictness;
+    }
+
+    /// Returns how the channel names of `SUBSCRIBE` are checked.
+    pub(crate) fn pubsub_strictness(&self) -> Strictness {
+        self.shared.state.lock().unwrap().pubsub_strictness
+    }
+
+    /// Sets the name and version the server reports itself as.
+    pub(crate) fn set_server_info(&self, name: &str, version: &str) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.server_name = name.to_string();
+        state.server_version = version.to_string();
+    }
+
+    /// Returns the name and version the server reports itself as.
+    pub(crate) fn server_info(&self) -> (String, String) {
+        let state = self.shared.state.lock().unwrap();
+        (state.server_name.clone(), state.server_version.clone())
     }
 
     /// Returns a `Receiver` for the requested channel.
     ///
     /// The returned `Receiver` is used to receive values broadcast by `PUBLISH`
     /// commands
-    pub(crate) fn subscibe(&self, key: String) -> broadcast::Receiver<Bytes> {
-        use std::collections::hash_map::Entry;
+    pub(crate) fn subscribe(&self, key: String) -> fanout::Receiver<Bytes> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.subscribe(key)
+    }
 
+    /// Returns a `Receiver` for the requested channel, along with the number
+    /// of messages published on it since `since`, a Unix timestamp in
+    /// milliseconds.
+    ///
+    /// Only the last `CHANNEL_HISTORY` publishes are remembered. When older
+    /// ones may have been published since `since`, the count is a lower bound
+    /// and `true` is returned along with it.
+    ///
+    /// The count is taken while subscribing, so every message published
+    /// afterwards is received and none is counted twice.
+    pub(crate) fn subscribe_since(&self, key: String, since: u64) -> (fanout::Receiver<Bytes>, u64, bool) {
         let mut state = self.shared.state.lock().unwrap();
 
-        // 如果当前请求channel中没有entry，那么创建一个新的broadcast channel 并且将其和key联系起来
-        // 如果已经存在了，那么返回一个已经和key联系起来的receiver
-        match state.pub_sub.entry(key) {
-            Entry::Occupied(e) => e.get().subscribe(),
-            Entry::Vacant(e) => {
-                let (tx, rx) = broadcast::channel(1024);
-                e.insert(tx);
-                rx
-            }
-        }
-    }
+        let rx = state.subscribe(key.clone());
 
-    /// Publish a message to the channel. Returns the number of subscribers
-    /// listening on the channel
-    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
-        let state = self.shared.state.lock().unwrap();
+        // 上面已经创建了频道的统计，`unwrap()`是安全的
+        let channel = &state.channel_traffic[&key];
 
-        state
-            .pub_sub
-            .get(key)
-            // 一个成功在broadcast channel上发送的message，订阅者的数量被返回
-            // 一个错误表示这里没有接受者，在这种情况下应该返回0
-            .map(|tx| tx.send(value).unwrap_or(0))
-            // 如果当前key没有相应的entry， 所以这里也是没有订阅者，所以也返回0
-            .unwrap_or(0)
+        // 时间戳是有序的，从最新的开始数
+        let count = channel.history.iter().rev().take_while(|&&at| at >= since).count();
+
+        // 所有记住的消息都在`since`之后，更早的消息可能也在`since`之后
+        let forgotten = channel.published > channel.history.len() as u64;
+        let truncated = count == channel.history.len() && forgotten;
+
+        (rx, count as u64, truncated)
     }
 
-    /// Signals the purge background task to shut down. This is called by the
-    /// `DbShutdown`s `Drop` implementation
-    fn shutdown_purge_task(&self) {
-        // 后台任务必须被告知关闭，这个件事通过将`State::shutdown` to  `true` 并且告知task
+    /// Returns a `Receiver` for the messages published on the channels
+    /// matching the glob-style `pattern`, along with the name of their
+    /// channel.
+    pub(crate) fn psubscribe(&self, pattern: String) -> fanout::Receiver<(Arc<str>, Bytes)> {
         let mut state = self.shared.state.lock().unwrap();
-        state.shutdown = true;
+        let state = &mut *state;
 
-        // 同样在notify task之前先drop锁，使得任务不用等待
-        drop(state);
-        self.shared.background_task.notify_one();
+        let (capacity, overflow) = (state.pubsub_queue_capacity, state.pubsub_overflow);
+
+        state
+            .pub_sub_patterns
+            .entry(pattern)
+            .or_insert_with(|| fanout::Sender::new(capacity, overflow))
+            .subscribe()
     }
-}
 
-impl Shared {
-    /// Purge all expired keys and return the `Instant` at which the **next**
-    /// key will expire. The background task will sleep until this instant
-    fn purge_expired_keys(&self) -> Option<Instant> {
-        let mut state = self.state.lock().unwrap();
+    /// Removes the given channels and patterns which have no subscribers
+    /// left. Called once their `Receiver`s have been dropped.
+    pub(crate) fn release(&self, channels: &[String], patterns: &[String]) {
+        let mut state = self.shared.state.lock().unwrap();
 
-        if state.shutdown {
-            // db正在关闭，所有handles to the stared state已经释放。
-            // 后台任务应该退出
-            return None;
+        for channel in channels {
+            state.release_channel(channel);
         }
 
-        //关于 lock() 方法： 在 Rust 中，当你使用一个互斥锁（Mutex）来保护共享数据时，
-        //你通常会调用 lock() 方法来访问这些数据。调用 lock() 会返回一个 MutexGuard，
-        //这是一个智能指针，它提供对被互斥锁保护的数据的访问。
-        //MutexGuard 和借用检查器： 当你持有一个 MutexGuard，你实际上持有对受保护数据的独占访问权。
-        //但是，Rust 的借用检查器有时不能完全理解 MutexGuard 背后的复杂性。
-        //特别是当你尝试在同一个作用域中访问同一个互斥锁保护的多个不同字段时，
-        //借用检查器可能会错误地认为这造成了数据竞争。
-        //解决方案 - 在循环外获取“真实”可变引用： 为了解决这个问题，注释中提到的方法是
-        //在循环之外获取对 State 的一个“真实”可变引用。这意味着你先锁定互斥锁，
-        //然后在进入循环之前获取一个对受保护数据的可变引用。
-        //这样做可以确保借用检查器能够正确地理解你在循环中对这些数据的访问是安全的。
-        let state = &mut *state;
+        for pattern in patterns {
+            state.release_pattern(pattern);
+        }
+    }
+
+    /// Starts reading the stream associated with a key for `XSUBSCRIBE`, after
+    /// `last_id`, or after the last entry of the stream if `None`.
+    ///
+    /// Returns the id the reading starts after, along with a `Receiver`
+    /// notified of the id of every entry added to the stream from now on. A
+    /// missing key is read as an empty stream. Returns the `WRONGTYPE` error
+    /// if the value is not a stream.
+    pub(crate) fn xsubscribe(
+        &self,
+        key: &str,
+        last_id: Option<StreamId>,
+    ) -> Result<(StreamId, watch::Receiver<StreamId>), &'static str> {
+        let mut state = self.shared.state.lock().unwrap();
 
         let now = Instant::now();
 
-        while let Some(&(when, ref key)) = state.expirations.iter().next() {
-            if when > now {
-                return Some(when);
-            }
-            state.entries.remove(key);
-            state.expirations.remove(&(when, key.clone()));
+        let last = match state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        {
+            Some(Entry { data: Value::Stream(stream), .. }) => stream.keys().next_back().copied().unwrap_or(StreamId::MIN),
+            Some(_) => return Err(WRONGTYPE),
+            None => StreamId::MIN,
+        };
+
+        let readers = state
+            .stream_readers
+            .entry(key.to_string())
+            .or_insert_with(|| watch::channel(last).0)
+            .subscribe();
+
+        Ok((last_id.unwrap_or(last), readers))
+    }
+
+    /// Removes the stream associated with a key from the streams being read,
+    /// if it has no readers left. Called once its `Receiver` has been dropped.
+    pub(crate) fn release_stream(&self, key: &str) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if state.stream_readers.get(key).is_some_and(|readers| readers.receiver_count() == 0) {
+            state.stream_readers.remove(key);
         }
-        None
     }
+
+    /// Returns the channels with at least one subscriber, matching the
+    /// glob-style `pattern` if given.
+    pub(crate) fn channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let state = self.shared.state.lock().unwrap();
+
+        state
+            .pub_sub
+            .keys()
+            .filter(|channel| pattern.map(|p| glob_match(p.as_bytes(), channel.as_bytes())).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+
+    /// Publish a message to the channel. Returns the number of subscribers
+    /// listening on the channel, including the subscribers of the patterns
+    /// matching it.
+    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
+        let mut state = self.shared.state.lock().unwrap();
+        state.publish(key, value)
+    }
+
+    /// Returns the statistics of the traffic on a channel.
+    ///
+    /// Only channels which have been subscribed to at least once are tracked;
+    /// the statistics of other channels are all zero.
+    pub(crate) fn channel_stats(&self, key: &str) -> ChannelStats {
+        let state = self.shared.state.lock().unwrap();
+
+        match state.channel_traffic.get(key) {
+            Some(channel) => ChannelStats {
+                published: channel.published,
+                last_published: channel
+                    .history
+                    .back()
+                    .map(|&at| UNIX_EPOCH + Duration::from_millis(at)),
+                peak_receivers: channel.peak_receivers as u64,
+                receivers: state.pub_sub.get(key).map(|tx| tx.receiver_count()).unwrap_or(0) as u64,
+            },
+            None => ChannelStats::default(),
+        }
+    }
+
+    /// Sets the classes of keyspace events to publish.
+    pub(crate) fn set_keyspace_events(&self, events: KeyspaceEvents) {
+        self.shared.state.lock().unwrap().keyspace_events = events;
+    }
+
+    /// Enables or disables hot key tracking. Disabling it discards the counts.
+    ///
+    /// While enabled, every `GET` and `SET` of a key is counted.
+    pub(crate) fn set_hotkeys_tracking(&self, enabled: bool) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        match (enabled, &state.hotkeys) {
+            (true, None) => state.hotkeys = Some(HotKeySketch::new()),
+            (false, Some(_)) => state.hotkeys = None,
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if hot key tracking is enabled.
+    pub(crate) fn hotkeys_tracking(&self) -> bool {
+        self.shared.state.lock().unwrap().hotkeys.is_some()
+    }
+
+    /// Returns the `count` most accessed keys, the most accessed first.
+    ///
+    /// Returns `None` if hot key tracking is disabled.
+    pub(crate) fn hotkeys(&self, count: usize) -> Option<Vec<HotKey>> {
+        let state = self.shared.state.lock().unwrap();
+        let hotkeys = state.hotkeys.as_ref()?;
+
+        let mut counters: Vec<_> = hotkeys.counters.iter().collect();
+        counters.sort_unstable_by_key(|counter| std::cmp::Reverse(counter.hits));
+
+        // `Instant`不能直接转换成时间戳，通过和当前时间的差值计算
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+
+        let top = counters
+            .into_iter()
+            .take(count)
+            .map(|counter| HotKey {
+                key: counter.key.clone(),
+                hits: counter.hits,
+                last_hit: system_now - now.duration_since(counter.last_hit),
+            })
+            .collect();
+
+        Some(top)
+    }
+
+    /// Sets the maximum time `memory_histogram` holds the lock for at once.
+    pub(crate) fn set_histogram_slice(&self, slice: Duration) {
+        self.shared.state.lock().unwrap().histogram_slice = slice;
+    }
+
+    /// Computes the histogram of the lengths of the values, per value type,
+    /// reported by `MEMORY HISTOGRAM`.
+    ///
+    /// The keys are visited in chunks, in the order of `scan`. The lock is
+    /// released, and the task yields, once a chunk held it for the histogram
+    /// slice, so that other commands are not blocked by a large keyspace. As
+    /// with `SCAN`, the keys added or removed in the meantime may or may not be
+    /// counted.
+    ///
+    /// Only one histogram is computed at a time. Returns `None` if another one
+    /// is in progress.
+    pub(crate) async fn memory_histogram(&self) -> Option<Vec<TypeHistogram>> {
+        if self.shared.histogram_running.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+
+        // 计算被取消时（例如超过了命令的截止时间）也要清除标志
+        let _running = HistogramRunning(&self.shared.histogram_running);
+
+        let mut histograms = BTreeMap::new();
+        let mut cursor = 0;
+
+        loop {
+            cursor = self.histogram_chunk(cursor, &mut histograms);
+
+            if cursor == 0 {
+                break;
+            }
+
+            tokio::task::yield_now().await;
+        }
+
+        Some(histograms.into_values().collect())
+    }
+
+    /// Counts the keys from `cursor` in `histograms` until the histogram slice
+    /// is elapsed. Returns the cursor to continue from, or 0 once all the keys
+    /// have been counted.
+    fn histogram_chunk(&self, cursor: u64, histograms: &mut BTreeMap<&'static str, TypeHistogram>) -> u64 {
+        let state = self.shared.state.lock().unwrap();
+
+        // 测量的是持有锁的真实时间，不受暂停的Tokio时钟影响
+        let started = std::time::Instant::now();
+        let now = Instant::now();
+
+        let mut entries: Vec<(u64, &Entry)> = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(|(key, entry)| (scan_position(key), entry))
+            .filter(|(position, _)| *position >= cursor)
+            .collect();
+        entries.sort_unstable_by_key(|(position, _)| *position);
+
+        for (i, (position, entry)) in entries.into_iter().enumerate() {
+            if i > 0 && i % HISTOGRAM_CHECK_INTERVAL == 0 && started.elapsed() >= state.histogram_slice {
+                return position;
+            }
+
+            let type_name = entry.type_name();
+
+            histograms
+                .entry(type_name)
+                .or_insert_with(|| TypeHistogram::new(type_name))
+                .record(entry.data.size());
+        }
+
+        0
+    }
+
+    /// Clears the hot key counts, keeping the tracking enabled.
+    ///
+    /// Returns `false` if hot key tracking is disabled.
+    pub(crate) fn reset_hotkeys(&self) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+
+        match &mut state.hotkeys {
+            Some(hotkeys) => {
+                hotkeys.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Registers a callback invoked with the key and value of every entry
+    /// removed by the background expiration task, e.g. to write the value
+    /// back to another store.
+    ///
+    /// The callback fires for keys of every type. The value is only passed
+    /// for strings, it is `None` for lists and hashes.
+    ///
+    /// Callbacks run on the background task once the lock is released, so
+    /// they may access the `Db`. They should return quickly: expiration is
+    /// delayed while they run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_mini_redis::db::DbDropGuard;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let guard = DbDropGuard::new();
+    ///     let db = guard.db();
+    ///
+    ///     db.on_expire(|key, value| {
+    ///         println!("{} expired, was {:?}", key, value);
+    ///     });
+    /// }
+    /// ```
+    pub fn on_expire<F>(&self, f: F)
+    where
+        F: Fn(&str, Option<&Bytes>) + Send + Sync + 'static,
+    {
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_callbacks.0.push(Arc::new(f));
+    }
+
+    /// Returns the instant the earliest key expires at, when the background
+    /// task wakes up next. `None` if no key has a time to live.
+    ///
+    /// Only available with the `test-util` feature, for tests of the
+    /// expiration to wait for exactly the right time.
+    #[cfg(feature = "test-util")]
+    pub fn next_expiration(&self) -> Option<Instant> {
+        let state = self.shared.state.lock().unwrap();
+        state.next_expiration()
+    }
+
+    /// Republish the messages published on channel `src` to channel `dst`.
+    ///
+    /// A task subscribes to `src` and publishes every message it receives to
+    /// `dst`, in order, until the returned handle is stopped or the `Db` shuts
+    /// down. Dropping the handle leaves the bridge running. This lets several
+    /// channels be merged into one, or messages be routed to the channels
+    /// their consumers subscribe to.
+    ///
+    /// The bridge counts as a subscriber of `src`, including in the replies to
+    /// `PUBLISH`. Like a slow subscriber, it skips the oldest messages when it
+    /// falls too far behind. Bridges forming a cycle, e.g. a channel bridged
+    /// to itself, republish their messages forever.
+    ///
+    /// Must be called from within a Tokio runtime, as the task is spawned on
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_mini_redis::db::DbDropGuard;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let guard = DbDropGuard::new();
+    ///     let db = guard.db();
+    ///
+    ///     let bridge = db.bridge("orders:eu", "orders");
+    ///     bridge.stop();
+    /// }
+    /// ```
+    pub fn bridge(&self, src: &str, dst: &str) -> BridgeHandle {
+        // 在启动任务之前订阅，调用返回之后发布的消息都会被转发
+        let mut rx = self.subscribe(src.to_string());
+        let mut shutdown = self.shared.bridges_shutdown.subscribe();
+
+        let db = self.clone();
+        let src = src.to_string();
+        let dst = dst.to_string();
+
+        let task = tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    res = rx.recv() => res,
+                    _ = shutdown.wait_for(|shutdown| *shutdown) => break,
+                };
+
+                match message {
+                    Ok(message) => {
+                        db.publish(&dst, message);
+                    }
+                    Err(fanout::RecvError::Lagged(skipped)) => {
+                        debug!(%src, %dst, skipped, "bridge lagged behind, messages skipped");
+                    }
+                    Err(fanout::RecvError::Disconnected) => {
+                        warn!(%src, %dst, "bridge disconnected, too many messages pending");
+                        break;
+                    }
+                    Err(fanout::RecvError::Closed) => break,
+                }
+            }
+
+            drop(rx);
+            db.release(std::slice::from_ref(&src), &[]);
+
+            debug!(%src, %dst, "bridge stopped");
+        });
+
+        BridgeHandle { task }
+    }
+
+    /// Signals the purge background task and the bridges to shut down. This is
+    /// called by the `DbShutdown`s `Drop` implementation
+    fn shutdown_purge_task(&self) {
+        // 后台任务必须被告知关闭，这个件事通过将`State::shutdown` to  `true` 并且告知task
+        let mut state = self.shared.state.lock().unwrap();
+        state.shutdown = true;
+
+        // 同样在notify task之前先drop锁，使得任务不用等待
+        drop(state);
+        self.shared.background_task.notify_one();
+        self.shared.bridges_shutdown.send_replace(true);
+    }
+}
+
+impl BridgeHandle {
+    /// Stop republishing messages. Messages received by the bridge but not
+    /// yet republished are dropped.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    /// Returns `true` once the bridge has stopped, because the `Db` shut down.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Shared {
+    /// Purge all expired keys and return the `Instant` at which the **next**
+    /// key will expire. The background task will sleep until this instant
+    fn purge_expired_keys(&self) -> Option<Instant> {
+        let mut guard = self.state.lock().unwrap();
+
+        if guard.shutdown {
+            // db正在关闭，所有handles to the stared state已经释放。
+            // 后台任务应该退出
+            return None;
+        }
+
+        //关于 lock() 方法： 在 Rust 中，当你使用一个互斥锁（Mutex）来保护共享数据时，
+        //你通常会调用 lock() 方法来访问这些数据。调用 lock() 会返回一个 MutexGuard，
+        //这是一个智能指针，它提供对被互斥锁保护的数据的访问。
+        //MutexGuard 和借用检查器： 当你持有一个 MutexGuard，你实际上持有对受保护数据的独占访问权。
+        //但是，Rust 的借用检查器有时不能完全理解 MutexGuard 背后的复杂性。
+        //特别是当你尝试在同一个作用域中访问同一个互斥锁保护的多个不同字段时，
+        //借用检查器可能会错误地认为这造成了数据竞争。
+        //解决方案 - 在循环外获取“真实”可变引用： 为了解决这个问题，注释中提到的方法是
+        //在循环之外获取对 State 的一个“真实”可变引用。这意味着你先锁定互斥锁，
+        //然后在进入循环之前获取一个对受保护数据的可变引用。
+        //这样做可以确保借用检查器能够正确地理解你在循环中对这些数据的访问是安全的。
+        let state = &mut *guard;
+
+        let now = Instant::now();
+
+        let mut events = vec![];
+        let mut expired = vec![];
+        let mut next = None;
+
+        while let Some(&(when, ref key)) = state.expirations.iter().next() {
+            if when > now {
+                next = Some(when);
+                break;
+            }
+            events.extend(state.keyspace_event(EventClass::Expired, "expired", key));
+
+            let entry = state.entries.remove(key);
+            let key = key.clone();
+            state.expirations.remove(&(when, key.clone()));
+            state.replicate(&key, now);
+
+            // 只有注册了回调时才需要保留过期的值，所有类型的key都要回调
+            if let Some(entry) = entry.filter(|_| !state.expire_callbacks.0.is_empty()) {
+                expired.push((key, entry.data));
+            }
+        }
+
+        // 回调在锁外执行，这样回调中可以访问`Db`
+        let callbacks = state.expire_callbacks.0.clone();
+        drop(guard);
+
+        // 和`Db::atomic`一样，释放锁之后再发布事件
+        self.publish_keyspace_events(events);
+
+        for (key, value) in &expired {
+            // 列表、哈希和流没有单个的值可以传给回调
+            let value = match value {
+                Value::String(data) => Some(data),
+                Value::List(_) | Value::Hash(_) | Value::Stream(_) => None,
+            };
+
+            for callback in &callbacks {
+                callback(key, value);
+            }
+        }
+
+        next
+    }
+
+    /// Publish keyspace events recorded while holding the lock.
+    ///
+    /// The lock is taken again, so that publishing never happens in the middle
+    /// of the operation which produced the events.
+    fn publish_keyspace_events(&self, events: impl IntoIterator<Item = KeyspaceEvent>) {
+        let mut events = events.into_iter().peekable();
+
+        // 大多数情况下通知是关闭的，不需要再次加锁
+        if events.peek().is_none() {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        for event in events {
+            state.publish_keyspace_event(&event);
+        }
+    }
+
     fn is_shutdown(&self) -> bool {
         self.state.lock().unwrap().shutdown
     }
 }
 
+impl HotKeySketch {
+    fn new() -> HotKeySketch {
+        HotKeySketch {
+            index: HashMap::with_capacity(HOTKEYS_CAPACITY),
+            counters: Vec::with_capacity(HOTKEYS_CAPACITY),
+        }
+    }
+
+    /// Counts an access to `key`.
+    fn record(&mut self, key: &str, now: Instant) {
+        if let Some(&position) = self.index.get(key) {
+            let counter = &mut self.counters[position];
+            counter.hits += 1;
+            counter.last_hit = now;
+
+            // 计数增加了，只可能需要向下调整
+            self.sift_down(position);
+        } else if self.counters.len() < HOTKEYS_CAPACITY {
+            let position = self.counters.len();
+            self.index.insert(key.to_string(), position);
+            self.counters.push(HotKeyCounter {
+                key: key.to_string(),
+                hits: 1,
+                last_hit: now,
+            });
+
+            self.sift_up(position);
+        } else {
+            // 堆顶是计数最小的key，新的key替换它并继承它的计数
+            let counter = &mut self.counters[0];
+
+            // 复用被替换的key的内存，避免分配
+            let (mut indexed, _) = self.index.remove_entry(&counter.key).unwrap();
+            indexed.clear();
+            indexed.push_str(key);
+            self.index.insert(indexed, 0);
+
+            counter.key.clear();
+            counter.key.push_str(key);
+            counter.hits += 1;
+            counter.last_hit = now;
+
+            self.sift_down(0);
+        }
+    }
+
+    /// Moves the counter at `position` towards the top of the heap until its
+    /// parent's count is not greater than its own.
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+
+            if self.counters[parent].hits <= self.counters[position].hits {
+                break;
+            }
+
+            self.swap(parent, position);
+            position = parent;
+        }
+    }
+
+    /// Moves the counter at `position` towards the bottom of the heap until
+    /// none of its children has a lower count.
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let mut smallest = position;
+
+            for child in [2 * position + 1, 2 * position + 2] {
+                if child < self.counters.len() && self.counters[child].hits < self.counters[smallest].hits {
+                    smallest = child;
+                }
+            }
+
+            if smallest == position {
+                break;
+            }
+
+            self.swap(position, smallest);
+            position = smallest;
+        }
+    }
+
+    /// Swaps two counters, keeping `index` in sync.
+    fn swap(&mut self, a: usize, b: usize) {
+        self.counters.swap(a, b);
+        *self.index.get_mut(&self.counters[a].key).unwrap() = a;
+        *self.index.get_mut(&self.counters[b].key).unwrap() = b;
+    }
+
+    fn clear(&mut self) {
+        self.index.clear();
+        self.counters.clear();
+    }
+}
+
+impl fmt::Debug for ExpireCallbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpireCallbacks").field("len", &self.0.len()).finish()
+    }
+}
+
+impl KeyspaceEvents {
+    /// Returns `true` if the events of `class` are published on at least one
+    /// kind of channel.
+    fn is_enabled(self, class: EventClass) -> bool {
+        let enabled = match class {
+            EventClass::Generic => self.generic,
+            EventClass::String => self.string,
+            EventClass::List => self.list,
+            EventClass::Hash => self.hash,
+            EventClass::Stream => self.stream,
+            EventClass::Expired => self.expired,
+        };
+
+        enabled && (self.keyspace || self.keyevent)
+    }
+}
+
+impl FromStr for KeyspaceEvents {
+    type Err = crate::Error;
+
+    /// Parse the classes of events from the syntax of `notify-keyspace-events`.
+    /// An empty string disables the notifications.
+    fn from_str(s: &str) -> crate::Result<KeyspaceEvents> {
+        let mut events = KeyspaceEvents::default();
+
+        for c in s.chars() {
+            match c {
+                'K' => events.keyspace = true,
+                'E' => events.keyevent = true,
+                'g' => events.generic = true,
+                '$' => events.string = true,
+                'l' => events.list = true,
+                'h' => events.hash = true,
+                't' => events.stream = true,
+                'x' => events.expired = true,
+                'A' => {
+                    events.generic = true;
+                    events.string = true;
+                    events.list = true;
+                    events.hash = true;
+                    events.stream = true;
+                    events.expired = true;
+                }
+                // 没有其他类型的值，这些类型的事件永远不会发生
+                's' | 'z' | 'e' | 'd' | 'm' | 'n' => {}
+                c => return Err(format!("invalid keyspace event class '{}'", c).into()),
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+impl FromStr for CounterOverflow {
+    type Err = crate::Error;
+
+    /// Parse the overflow behavior, `error` or `saturating`.
+    fn from_str(s: &str) -> crate::Result<CounterOverflow> {
+        match &s.to_lowercase()[..] {
+            "error" => Ok(CounterOverflow::Error),
+            "saturating" => Ok(CounterOverflow::Saturating),
+            _ => Err(format!("invalid counter overflow behavior '{}'", s).into()),
+        }
+    }
+}
+
+impl Entry {
+    /// Returns the name of the type of the stored value.
+    fn type_name(&self) -> &'static str {
+        match self.data {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Stream(_) => "stream",
+        }
+    }
+}
+
+impl Value {
+    /// Returns the string, or the `WRONGTYPE` error if the value is of
+    /// another type.
+    fn as_string(&self) -> Result<&Bytes, &'static str> {
+        match self {
+            Value::String(data) => Ok(data),
+            Value::List(_) | Value::Hash(_) | Value::Stream(_) => Err(WRONGTYPE),
+        }
+    }
+
+    /// Returns the number of bytes of data held by the value.
+    fn size(&self) -> usize {
+        match self {
+            Value::String(data) => data.len(),
+            Value::List(list) => list.iter().map(Bytes::len).sum(),
+            Value::Hash(hash) => hash.iter().map(|(field, value)| field.len() + value.len()).sum(),
+            Value::Stream(stream) => stream
+                .values()
+                .flatten()
+                .map(|(field, value)| field.len() + value.len())
+                .sum(),
+        }
+    }
+}
+
+impl StateView<'_> {
+    /// Get the value associated with a key.
+    ///
+    /// Returns `None` if there is no value associated with the key, if it
+    /// has expired, or if it is not a string.
+    pub fn get(&self, key: &str) -> Option<Bytes> {
+        let now = Instant::now();
+
+        self.state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .and_then(|entry| entry.data.as_string().ok().cloned())
+    }
+
+    /// Returns `true` if a value of any type is associated with the key.
+    fn contains(&self, key: &str) -> bool {
+        let now = Instant::now();
+
+        self.state
+            .entries
+            .get(key)
+            .map(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .unwrap_or(false)
+    }
+
+    /// Set the value associated with a key.
+    ///
+    /// As with `SET`, any time to live previously associated with the key is
+    /// discarded.
+    pub fn set(&mut self, key: &str, value: Bytes) {
+        let prev = self.state.entries.insert(
+            key.to_string(),
+            Entry {
+                data: Value::String(shared_integer(value)),
+                expires_at: None,
+                accessed_at: Instant::now(),
+            },
+        );
+
+        if let Some(when) = prev.and_then(|prev| prev.expires_at) {
+            self.state.expirations.remove(&(when, key.to_string()));
+        }
+
+        self.events.extend(self.state.keyspace_event(EventClass::String, "set", key));
+        self.state.replicate(key, Instant::now());
+    }
+
+    /// Set a time to live on a key, as with `EXPIRE`. Returns `false` if the
+    /// key does not exist.
+    pub fn expire(&mut self, key: &str, ttl: Duration) -> bool {
+        let now = Instant::now();
+        let when = now + ttl;
+
+        let next = self.state.next_expiration();
+        if !self.state.expire_at(key, when, None, now) {
+            return false;
+        }
+
+        // 新的过期时间早于后台任务等待的时间时需要唤醒任务
+        self.notify |= next.map(|expiration| expiration > when).unwrap_or(true);
+        self.state.replicate(key, now);
+        true
+    }
+
+    /// Remove a key. Returns `true` if the key existed.
+    pub fn del(&mut self, key: &str) -> bool {
+        self.remove(key).is_some()
+    }
+
+    /// Remove a key, and return its entry if it existed.
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let prev = self.state.entries.remove(key)?;
+
+        if let Some(when) = prev.expires_at {
+            self.state.expirations.remove(&(when, key.to_string()));
+        }
+
+        self.events.extend(self.state.keyspace_event(EventClass::Generic, "del", key));
+        self.state.replicate(key, Instant::now());
+        Some(prev)
+    }
+
+    /// Get the string associated with a key, as `GET` does.
+    ///
+    /// Returns `None` if there is no value associated with the key. This may be
+    /// due to never having assigned a value to the key or previously assigned
+    /// value expired. Returns the `WRONGTYPE` error if the value is not a
+    /// string. Unlike `get`, the access is recorded.
+    pub(crate) fn get_string(&mut self, key: &str) -> Result<Option<Bytes>, &'static str> {
+        // 由于数据用`Bytes`存储，clone is shallow clone
+        // 数据并没有被copied
+        let state = &mut *self.state;
+
+        // 已过期但还未被清除的key被当作不存在
+        let now = Instant::now();
+
+        if let Some(hotkeys) = &mut state.hotkeys {
+            hotkeys.record(key, now);
+        }
+
+        state
+            .entries
+            .get_mut(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(|entry| {
+                entry.accessed_at = now;
+                entry.data.as_string().cloned()
+            })
+            .transpose()
+    }
+
+    /// Get the value associated with a key, and update its time to live as
+    /// requested by `ttl`.
+    ///
+    /// Returns `None`, leaving the keyspace untouched, if there is no value
+    /// associated with the key, or the `WRONGTYPE` error if the value is not
+    /// a string.
+    pub(crate) fn get_ex(&mut self, key: &str, ttl: Option<TtlUpdate>) -> Result<Option<Bytes>, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        let Some(entry) = state
+            .entries
+            .get_mut(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        else {
+            return Ok(None);
+        };
+
+        let value = entry.data.as_string()?.clone();
+        entry.accessed_at = now;
+
+        let expires_at = match ttl {
+            None => return Ok(Some(value)),
+            Some(TtlUpdate::Expire(duration)) => Some(now + duration),
+            Some(TtlUpdate::Persist) => None,
+        };
+
+        // 新旧过期时间都需要在expirations中同步更新
+        if let Some(prev) = std::mem::replace(&mut entry.expires_at, expires_at) {
+            state.expirations.remove(&(prev, key.to_string()));
+        }
+
+        let mut notify = false;
+
+        if let Some(when) = expires_at {
+            notify = state
+                .next_expiration()
+                .map(|expiration| expiration > when)
+                .unwrap_or(true);
+
+            state.expirations.insert((when, key.to_string()));
+        }
+
+        state.replicate(key, now);
+
+        self.notify |= notify;
+
+        Ok(Some(value))
+    }
+
+    /// Returns how many of the given keys exist. A key given several times is
+    /// counted each time.
+    pub(crate) fn exists(&self, keys: &[String]) -> usize {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        keys.iter()
+            .filter(|key| {
+                state
+                    .entries
+                    .get(key.as_str())
+                    .map(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+                    .unwrap_or(false)
+            })
+            .count()
+    }
+
+    /// Removes the given keys, and returns how many of them existed.
+    pub(crate) fn del_many(&mut self, keys: &[String]) -> usize {
+        keys.iter().filter(|key| self.contains(key) && self.del(key)).count()
+    }
+
+    /// Refresh the last access time of the given keys, and return how many of
+    /// them exist. A key given several times is counted each time.
+    pub(crate) fn touch(&mut self, keys: &[String]) -> usize {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        keys.iter()
+            .filter(|key| {
+                match state
+                    .entries
+                    .get_mut(key.as_str())
+                    .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+                {
+                    Some(entry) => {
+                        entry.accessed_at = now;
+                        true
+                    }
+                    None => false,
+                }
+            })
+            .count()
+    }
+
+    /// Returns how long ago a key was last accessed, or `None` if there is no
+    /// value associated with the key.
+    ///
+    /// Does not count as an access itself.
+    pub(crate) fn idle_time(&self, key: &str) -> Option<Duration> {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(|entry| now.saturating_duration_since(entry.accessed_at))
+    }
+
+    /// Returns the remaining time to live of a key.
+    pub(crate) fn ttl(&self, key: &str) -> Ttl {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        match state.entries.get(key).map(|entry| entry.expires_at) {
+            None => Ttl::Missing,
+            Some(None) => Ttl::Persistent,
+            Some(Some(when)) if when > now => Ttl::Expires(when - now),
+            Some(Some(_)) => Ttl::Missing,
+        }
+    }
+
+    /// Returns all the keys matching the glob-style `pattern`.
+    ///
+    /// Keys that have expired but were not purged yet are skipped.
+    pub(crate) fn keys(&self, pattern: &str) -> Vec<String> {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .filter(|(key, _)| glob_match(pattern.as_bytes(), key.as_bytes()))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Returns the next batch of keys of a `SCAN` iteration, along with the
+    /// cursor to resume it at.
+    ///
+    /// `HashMap` has no stable order, so the keys are iterated in the order of
+    /// their `scan_position`, and the cursor is the position of the next key
+    /// to inspect. Unlike an offset, a position does not move when other keys
+    /// are added or removed, so no key present during the whole iteration is
+    /// skipped. Up to `count` keys are inspected, and only those matching
+    /// `pattern` are returned, so a batch may be empty while the iteration is
+    /// not complete. The returned cursor is 0 once all the keys have been
+    /// inspected.
+    pub(crate) fn scan(&self, cursor: u64, count: usize, pattern: Option<&str>) -> (u64, Vec<String>) {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        // 只对剩余的key的引用做快照并排序，避免clone所有的key
+        let mut keys: Vec<(u64, &String)> = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(|(key, _)| (scan_position(key), key))
+            .filter(|(position, _)| *position >= cursor)
+            .collect();
+        keys.sort_unstable();
+
+        let next = keys.get(count).map(|(position, _)| *position).unwrap_or(0);
+
+        let batch = keys
+            .iter()
+            .take(count)
+            .filter(|(_, key)| pattern.map(|p| glob_match(p.as_bytes(), key.as_bytes())).unwrap_or(true))
+            .map(|(_, key)| key.to_string())
+            .collect();
+
+        (next, batch)
+    }
+
+    /// Replace the value of `key` with `new` if its current value is
+    /// `expected`. Returns `true` if the value was replaced.
+    ///
+    /// `None` as `expected` requires the key to be missing, and `None` as
+    /// `new` removes the key. As with `SET`, any time to live associated with
+    /// the key is discarded when it is replaced. A key holding a list never
+    /// matches.
+    pub(crate) fn compare_and_swap(&mut self, key: &str, expected: Option<&Bytes>, new: Option<Bytes>) -> bool {
+        // 持有其他类型的值的key不匹配任何期望的值
+        if self.get(key).as_ref() != expected || (expected.is_none() && self.contains(key)) {
+            return false;
+        }
+
+        match new {
+            Some(value) => self.set(key, value),
+            None => {
+                self.del(key);
+            }
+        }
+
+        true
+    }
+
+    /// Returns the number of keys.
+    ///
+    /// Keys that have expired but were not purged yet are not counted.
+    pub(crate) fn dbsize(&self) -> usize {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        state
+            .entries
+            .values()
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .count()
+    }
+
+    /// Removes all the keys.
+    ///
+    /// The pub/sub channels are left intact. The background task may still be
+    /// sleeping until the expiration of a removed key; when it wakes up, it
+    /// finds no expired key and waits for the next one as usual.
+    pub(crate) fn flush(&mut self) {
+        let state = &mut *self.state;
+
+        state.entries.clear();
+        state.expirations.clear();
+        state.replicate_change(Change::flush);
+    }
+
+    /// Swaps the values of two keys, along with their expirations.
+    ///
+    /// If one of the keys does not exist, nothing is modified and the missing
+    /// key is returned as `Err`.
+    pub(crate) fn exchange<'a>(&mut self, key1: &'a str, key2: &'a str) -> Result<(), &'a str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        for key in [key1, key2] {
+            let live = state
+                .entries
+                .get(key)
+                .map(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+                .unwrap_or(false);
+
+            if !live {
+                return Err(key);
+            }
+        }
+
+        if key1 == key2 {
+            return Ok(());
+        }
+
+        // 上面已经检查过两个key都存在，`unwrap()`是安全的
+        let mut entry1 = state.entries.remove(key1).unwrap();
+        let mut entry2 = state.entries.remove(key2).unwrap();
+
+        // 过期时间跟着值一起交换，expirations中的(when, key)需要同步更新。
+        // 过期的时间点集合没有变化，所以不需要唤醒后台任务
+        // 先全部移除再插入，否则两个key的过期时间相同时会删掉刚插入的元组
+        if let Some(when) = entry1.expires_at {
+            state.expirations.remove(&(when, key1.to_string()));
+        }
+        if let Some(when) = entry2.expires_at {
+            state.expirations.remove(&(when, key2.to_string()));
+        }
+        if let Some(when) = entry1.expires_at {
+            state.expirations.insert((when, key2.to_string()));
+        }
+        if let Some(when) = entry2.expires_at {
+            state.expirations.insert((when, key1.to_string()));
+        }
+
+        std::mem::swap(&mut entry1, &mut entry2);
+
+        state.entries.insert(key1.to_string(), entry1);
+        state.entries.insert(key2.to_string(), entry2);
+
+        state.replicate(key1, now);
+        state.replicate(key2, now);
+
+        Ok(())
+    }
+
+    /// Returns the name of the type of the value associated with a key, as
+    /// reported by `TYPE`.
+    ///
+    /// Returns `"none"` if there is no value associated with the key.
+    pub(crate) fn key_type(&self, key: &str) -> &'static str {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(Entry::type_name)
+            .unwrap_or("none")
+    }
+
+    /// Returns the length in bytes of the value associated with a key.
+    ///
+    /// Returns 0 if there is no value associated with the key or it has
+    /// expired, or the `WRONGTYPE` error if the value is not a string.
+    pub fn strlen(&self, key: &str) -> Result<usize, &'static str> {
+        // 只读取长度，不clone数据
+        let state = &*self.state;
+
+        // 与`get`相同，已过期但还未被清除的key被当作不存在
+        let now = Instant::now();
+
+        match state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        {
+            Some(entry) => Ok(entry.data.as_string()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Append `value` to the value associated with a key, and return the new
+    /// length of the value.
+    ///
+    /// A missing or expired key is created with `value`. The expiration of
+    /// the key, if any, is kept. Returns the `WRONGTYPE` error if the value is not a
+    /// string.
+    pub fn append(&mut self, key: String, value: Bytes) -> Result<usize, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        // 已过期但还未被清除的key被当作不存在
+        state.remove_expired(&key, now);
+
+        let event = state.keyspace_event(EventClass::String, "append", &key);
+
+        let len = match state.entries.get_mut(&key) {
+            Some(entry) => {
+                let current = entry.data.as_string()?;
+
+                let mut data = BytesMut::with_capacity(current.len() + value.len());
+                data.extend_from_slice(current);
+                data.extend_from_slice(&value);
+
+                let len = data.len();
+                entry.data = Value::String(data.freeze());
+                entry.accessed_at = now;
+                len
+            }
+            None => {
+                let len = value.len();
+                state.entries.insert(
+                    key.clone(),
+                    Entry {
+                        data: Value::String(value),
+                        expires_at: None,
+                        accessed_at: now,
+                    },
+                );
+                len
+            }
+        };
+
+        state.replicate(&key, now);
+
+        self.events.extend(event);
+
+        Ok(len)
+    }
+
+    /// Overwrite the value associated with a key starting at `offset`, and
+    /// return the new length of the value.
+    ///
+    /// The value is padded with zero bytes if it is shorter than `offset`. A
+    /// missing or expired key is treated as an empty value, but is not
+    /// created when `value` is empty. The expiration of the key, if any, is kept. Returns
+    /// the `WRONGTYPE` error if the value is not a string.
+    pub fn setrange(&mut self, key: String, offset: usize, value: Bytes) -> Result<usize, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        // 已过期但还未被清除的key被当作不存在
+        state.remove_expired(&key, now);
+
+        let current = match state.entries.get(&key) {
+            Some(entry) => Some(entry.data.as_string()?.clone()),
+            None => None,
+        };
+
+        if value.is_empty() {
+            return Ok(current.map(|data| data.len()).unwrap_or(0));
+        }
+
+        let event = state.keyspace_event(EventClass::String, "setrange", &key);
+
+        let current = current.unwrap_or_default();
+        let len = current.len().max(offset + value.len());
+
+        let mut data = BytesMut::with_capacity(len);
+        data.extend_from_slice(&current);
+        data.resize(len, 0);
+        data[offset..offset + value.len()].copy_from_slice(&value);
+
+        match state.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.data = Value::String(data.freeze());
+                entry.accessed_at = now;
+            }
+            None => {
+                state.entries.insert(
+                    key.clone(),
+                    Entry {
+                        data: Value::String(data.freeze()),
+                        expires_at: None,
+                        accessed_at: now,
+                    },
+                );
+            }
+        }
+
+        state.replicate(&key, now);
+
+        self.events.extend(event);
+
+        Ok(len)
+    }
+
+    /// Increment the integer associated with a key by `delta`, which may be
+    /// negative.
+    ///
+    /// A missing key is treated as 0, and its time to live is kept otherwise.
+    /// Returns the value after the increment, or an error if the value is not
+    /// an integer, including when it is not a string, or, unless the overflow
+    /// behavior is `Saturating`, if the result overflows.
+    pub(crate) fn incr_by(&mut self, key: String, delta: i64) -> Result<i64, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        // 已过期但还未被清除的key被当作不存在
+        let current: i64 = match state.entries.get(&key) {
+            Some(entry) if entry.expires_at.map(|when| when > now).unwrap_or(true) => {
+                let data = entry.data.as_string()?;
+
+                if !is_int_encodable(data) {
+                    return Err("ERR value is not an integer or out of range");
+                }
+
+                std::str::from_utf8(data).unwrap().parse().unwrap()
+            }
+            Some(entry) => {
+                if let Some(when) = entry.expires_at {
+                    state.expirations.remove(&(when, key.clone()));
+                }
+                state.entries.remove(&key);
+                0
+            }
+            None => 0,
+        };
+
+        let value = match (current.checked_add(delta), state.counter_overflow) {
+            (Some(value), _) => value,
+            (None, CounterOverflow::Error) => {
+                return Err("ERR increment or decrement would overflow");
+            }
+            (None, CounterOverflow::Saturating) => current.saturating_add(delta),
+        };
+
+        let data = shared_integer(Bytes::from(value.to_string()));
+
+        let event = state.keyspace_event(EventClass::String, "incrby", &key);
+
+        match state.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.data = Value::String(data);
+                entry.accessed_at = now;
+            }
+            None => {
+                state.entries.insert(
+                    key.clone(),
+                    Entry {
+                        data: Value::String(data),
+                        expires_at: None,
+                        accessed_at: now,
+                    },
+                );
+            }
+        }
+
+        state.replicate(&key, now);
+
+        self.events.extend(event);
+
+        Ok(value)
+    }
+
+    /// Increment the floating point number associated with a key by
+    /// `increment`, and return the new value.
+    ///
+    /// A missing or expired key is treated as 0. The expiration of the key,
+    /// if any, is kept. An error message is returned if the value is not a
+    /// valid float, including when it is not a string, or if the result would
+    /// not be finite.
+    pub fn incr_by_float(&mut self, key: String, increment: f64) -> Result<f64, &'static str> {
+        use crate::cmd::incrbyfloat::{format_float, parse_float};
+
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        // 与`incr_by`相同，已过期但还未被清除的key被当作不存在
+        state.remove_expired(&key, now);
+
+        let current = match state.entries.get(&key) {
+            Some(entry) => parse_float(entry.data.as_string()?).ok_or("ERR value is not a valid float")?,
+            None => 0.0,
+        };
+
+        let value = current + increment;
+
+        if !value.is_finite() {
+            return Err("ERR increment would produce NaN or Infinity");
+        }
+
+        let data = shared_integer(Bytes::from(format_float(value)));
+
+        let event = state.keyspace_event(EventClass::String, "incrbyfloat", &key);
+
+        match state.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.data = Value::String(data);
+                entry.accessed_at = now;
+            }
+            None => {
+                state.entries.insert(
+                    key.clone(),
+                    Entry {
+                        data: Value::String(data),
+                        expires_at: None,
+                        accessed_at: now,
+                    },
+                );
+            }
+        }
+
+        state.replicate(&key, now);
+
+        self.events.extend(event);
+
+        Ok(value)
+    }
+
+    /// Returns the encoding Redis would use for the value associated with a
+    /// key, or `None` if there is no value associated with the key or it has
+    /// expired.
+    ///
+    /// Strings are always stored as raw bytes. Their encoding is computed
+    /// from the bytes on every call so it cannot go stale when a value is
+    /// modified in place. Lists are reported as `quicklist`.
+    pub fn encoding(&self, key: &str) -> Option<&'static str> {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(|entry| match &entry.data {
+                Value::String(data) if is_int_encodable(data) => "int",
+                Value::String(_) => "raw",
+                Value::List(_) => "quicklist",
+                Value::Hash(_) => "hashtable",
+                Value::Stream(_) => "stream",
+            })
+    }
+
+    /// Returns the number of references to the value associated with a key,
+    /// as reported by `OBJECT REFCOUNT`.
+    ///
+    /// Values are not reference counted by the store, so this is 1 unless the
+    /// value is a shared integer. Returns `None` if there is no value
+    /// associated with the key or it has expired.
+    pub fn refcount(&self, key: &str) -> Option<u64> {
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(|entry| match &entry.data {
+                Value::String(data) if is_shared_integer(data) => SHARED_REFCOUNT,
+                _ => 1,
+            })
+    }
+
+    /// Set the value associated with a key along with an optional expiration
+    /// Duration, if the condition of `options` holds.
+    ///
+    /// If a value is already associated with the key,it is removed. Its time
+    /// to live is discarded unless `options.keep_ttl` is set.
+    ///
+    /// The check and the write happen while holding the lock, so no other
+    /// command can create or remove the key in between. The returned
+    /// `SetOutcome` tells whether the value was written, along with the value
+    /// previously associated with the key. With `options.get`, the `WRONGTYPE`
+    /// error is returned, and nothing written, if that value is not a string.
+    pub(crate) fn set_with_options(&mut self, key: String, value: Bytes, options: SetOptions) -> Result<SetOutcome, &'static str> {
+        let value = shared_integer(value);
+
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        if let Some(hotkeys) = &mut state.hotkeys {
+            hotkeys.record(&key, now);
+        }
+
+        // 已过期但还未被清除的key被当作不存在
+        let current = state
+            .entries
+            .get(&key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true));
+
+        let exists = current.is_some();
+
+        let previous = match current.map(|entry| entry.data.as_string()) {
+            Some(Ok(data)) => Some(data.clone()),
+            Some(Err(err)) if options.get => return Err(err),
+            _ => None,
+        };
+
+        let kept_expiration = current.and_then(|entry| entry.expires_at).filter(|_| options.keep_ttl);
+
+        match options.condition {
+            Some(SetCondition::Nx) if exists => {
+                return Ok(SetOutcome { written: false, previous });
+            }
+            Some(SetCondition::Xx) if !exists => {
+                return Ok(SetOutcome { written: false, previous });
+            }
+            _ => {}
+        }
+
+        // If this `set` becomes the key that expires **next**, the background
+        // task needs to be notified so it can update its state.
+        //
+        // Whether or not the task needs to be notified is computed during the
+        // `set` routine
+        let mut notify = false;
+
+        let expires_at = options.expire.map(|duration| {
+            // `Instant` at which the key expires.
+            let when = Instant::now() + duration;
+
+            // state.next_expiration()获取当前等待过期的第一个entry的时间戳when。
+            // map函数将新entry的过期时间when与最近一个要过期的entry的expiration进行比较。
+            // 如果expiration更大,说明新entry是下一个过期的,返回true。
+            // 否则expiration小于或等于when,返回false。
+            // unwrap_or(true)是为了处理next_expiration()可能返回None的情况,
+            // 如果是None，证明set中没有即将过期的entry，则直接返回true。
+            notify = state
+                .next_expiration()
+                .map(|expiration| expiration > when)
+                .unwrap_or(true);
+
+            when
+        });
+
+        // 保留的过期时间已经在expirations中，后台任务无需被唤醒
+        let expires_at = expires_at.or(kept_expiration);
+        //state.entries是一个HashMap,键是String,值是Entry结构。
+        //当调用insert方法向HashMap插入一对键值对时,如果该键之前存在,insert方法会返回之前的值。
+        //如果键不存在,insert方法会返回None。
+        let prev = state.entries.insert(
+            key.clone(),
+            Entry {
+                data: Value::String(value),
+                expires_at,
+                accessed_at: Instant::now(),
+            },
+        );
+
+        // 如果之前有值，则需要讲之前的key从set也就是expirations中移除，避免缺少数据
+        if let Some(prev) = prev {
+            if let Some(when) = prev.expires_at {
+                // key 后面要用所以不能将所有权给元组
+                state.expirations.remove(&(when, key.clone()));
+            }
+        }
+        // 如果在插入前删除在(when, key)相等时会造成bug
+        //
+        let event = state.keyspace_event(EventClass::String, "set", &key);
+
+        state.replicate(&key, now);
+
+        if let Some(when) = expires_at {
+            state.expirations.insert((when, key));
+        }
+
+        // 释放锁之后才唤醒任务，这样任务被唤醒就可以拿到锁，
+        // 而不是被唤醒后等待当前作用域释放锁
+        self.notify |= notify;
+        self.events.extend(event);
+
+        Ok(SetOutcome {
+            written: true,
+            previous,
+        })
+    }
+
+    /// Push `values`, one after the other, to an end of the list associated
+    /// with a key, and return the length of the list after the push.
+    ///
+    /// A missing key is created holding an empty list first. Returns the
+    /// `WRONGTYPE` error if the value is not a list.
+    pub(crate) fn push(&mut self, key: String, values: Vec<Bytes>, end: ListEnd) -> Result<usize, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        // 已过期但还未被清除的key被当作不存在
+        state.remove_expired(&key, now);
+
+        let name = match end {
+            ListEnd::Left => "lpush",
+            ListEnd::Right => "rpush",
+        };
+        let event = state.keyspace_event(EventClass::List, name, &key);
+
+        let entry = state.entries.entry(key.clone()).or_insert_with(|| Entry {
+            data: Value::List(VecDeque::new()),
+            expires_at: None,
+            accessed_at: now,
+        });
+
+        let Value::List(list) = &mut entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        for value in values {
+            match end {
+                ListEnd::Left => list.push_front(value),
+                ListEnd::Right => list.push_back(value),
+            }
+        }
+
+        let len = list.len();
+        entry.accessed_at = now;
+
+        state.replicate(&key, now);
+
+        self.events.extend(event);
+
+        Ok(len)
+    }
+
+    /// Pop up to `count` values from an end of the list associated with a
+    /// key, and return them in the order they were popped.
+    ///
+    /// The key is removed along with the last value of the list. Returns
+    /// `None` if there is no value associated with the key, or the `WRONGTYPE`
+    /// error if the value is not a list.
+    pub(crate) fn pop(&mut self, key: &str, count: usize, end: ListEnd) -> Result<Option<Vec<Bytes>>, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        state.remove_expired(key, now);
+
+        let Some(entry) = state.entries.get_mut(key) else {
+            return Ok(None);
+        };
+
+        let Value::List(list) = &mut entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        let count = count.min(list.len());
+        let values: Vec<Bytes> = match end {
+            ListEnd::Left => list.drain(..count).collect(),
+            ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+        };
+
+        let emptied = list.is_empty();
+        entry.accessed_at = now;
+
+        let mut events = vec![];
+
+        if !values.is_empty() {
+            let name = match end {
+                ListEnd::Left => "lpop",
+                ListEnd::Right => "rpop",
+            };
+            events.extend(state.keyspace_event(EventClass::List, name, key));
+        }
+
+        // 列表不会为空，弹出最后一个元素时删除key
+        if emptied {
+            if let Some(when) = state.entries.remove(key).and_then(|entry| entry.expires_at) {
+                state.expirations.remove(&(when, key.to_string()));
+            }
+            events.extend(state.keyspace_event(EventClass::Generic, "del", key));
+        }
+
+        if !values.is_empty() {
+            state.replicate(key, now);
+        }
+
+        self.events.extend(events);
+
+        Ok(Some(values))
+    }
+
+    /// Returns the values of the list associated with a key between the
+    /// indexes `start` and `stop`, both inclusive. Negative indexes count from
+    /// the end of the list.
+    ///
+    /// Returns an empty list if there is no value associated with the key, or
+    /// the `WRONGTYPE` error if the value is not a list.
+    pub(crate) fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<Bytes>, &'static str> {
+        use crate::cmd::getrange::range;
+
+        let state = &*self.state;
+
+        let now = Instant::now();
+
+        let Some(entry) = state
+            .entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        else {
+            return Ok(vec![]);
+        };
+
+        let Value::List(list) = &entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        Ok(match range(list.len(), start, stop) {
+            Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+            None => vec![],
+        })
+    }
+
+    /// Set `fields` of the hash associated with a key, and return the number
+    /// of fields which were not in the hash before.
+    ///
+    /// A missing key is created holding an empty hash first. A field given
+    /// several times takes the last value. Returns the `WRONGTYPE` error if
+    /// the value is not a hash.
+    pub(crate) fn hset(&mut self, key: String, fields: Vec<(String, Bytes)>) -> Result<usize, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        // 已过期但还未被清除的key被当作不存在
+        state.remove_expired(&key, now);
+
+        let event = state.keyspace_event(EventClass::Hash, "hset", &key);
+
+        let entry = state.entries.entry(key.clone()).or_insert_with(|| Entry {
+            data: Value::Hash(HashMap::new()),
+            expires_at: None,
+            accessed_at: now,
+        });
+
+        let Value::Hash(hash) = &mut entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        let mut added = 0;
+        for (field, value) in fields {
+            if hash.insert(field, value).is_none() {
+                added += 1;
+            }
+        }
+
+        entry.accessed_at = now;
+
+        state.replicate(&key, now);
+
+        self.events.extend(event);
+
+        Ok(added)
+    }
+
+    /// Returns the value of a field of the hash associated with a key.
+    ///
+    /// Returns `None` if there is no value associated with the key, or no such
+    /// field, and the `WRONGTYPE` error if the value is not a hash.
+    pub(crate) fn hget(&mut self, key: &str, field: &str) -> Result<Option<Bytes>, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        let Some(entry) = state
+            .entries
+            .get_mut(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        else {
+            return Ok(None);
+        };
+
+        let Value::Hash(hash) = &entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        let value = hash.get(field).cloned();
+        entry.accessed_at = now;
+
+        Ok(value)
+    }
+
+    /// Remove `fields` from the hash associated with a key, and return the
+    /// number of fields removed.
+    ///
+    /// The key is removed along with the last field of the hash. Returns the
+    /// `WRONGTYPE` error if the value is not a hash.
+    pub(crate) fn hdel(&mut self, key: &str, fields: &[String]) -> Result<usize, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        state.remove_expired(key, now);
+
+        let Some(entry) = state.entries.get_mut(key) else {
+            return Ok(0);
+        };
+
+        let Value::Hash(hash) = &mut entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        let removed = fields.iter().filter(|field| hash.remove(*field).is_some()).count();
+
+        let emptied = hash.is_empty();
+        entry.accessed_at = now;
+
+        let mut events = vec![];
+
+        if removed > 0 {
+            events.extend(state.keyspace_event(EventClass::Hash, "hdel", key));
+        }
+
+        // 和列表一样，删除最后一个字段时删除key
+        if emptied {
+            if let Some(when) = state.entries.remove(key).and_then(|entry| entry.expires_at) {
+                state.expirations.remove(&(when, key.to_string()));
+            }
+            events.extend(state.keyspace_event(EventClass::Generic, "del", key));
+        }
+
+        if removed > 0 {
+            state.replicate(key, now);
+        }
+
+        self.events.extend(events);
+
+        Ok(removed)
+    }
+
+    /// Returns the fields of the hash associated with a key, along with their
+    /// values, in no particular order.
+    ///
+    /// Returns an empty list if there is no value associated with the key, or
+    /// the `WRONGTYPE` error if the value is not a hash.
+    pub(crate) fn hgetall(&mut self, key: &str) -> Result<Vec<(String, Bytes)>, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        let Some(entry) = state
+            .entries
+            .get_mut(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        else {
+            return Ok(vec![]);
+        };
+
+        let Value::Hash(hash) = &entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        let fields = hash.iter().map(|(field, value)| (field.clone(), value.clone())).collect();
+        entry.accessed_at = now;
+
+        Ok(fields)
+    }
+
+    /// Append an entry holding `fields` to the stream associated with a key,
+    /// and return its id.
+    ///
+    /// The id is generated from the current time if `id` is `None`, and is
+    /// greater than the one of the last entry in any case. A given id must be
+    /// greater than it, and than `0-0`. A missing key is created holding an
+    /// empty stream first. Returns the `WRONGTYPE` error if the value is not a
+    /// stream.
+    pub(crate) fn xadd(
+        &mut self,
+        key: String,
+        id: Option<StreamId>,
+        fields: Vec<(String, Bytes)>,
+    ) -> Result<StreamId, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        state.remove_expired(&key, now);
+
+        // 先检查类型和id，出错时不创建key
+        let last = match state.entries.get(&key) {
+            Some(Entry { data: Value::Stream(stream), .. }) => stream.keys().next_back().copied(),
+            Some(_) => return Err(WRONGTYPE),
+            None => None,
+        };
+
+        let id = match id {
+            Some(StreamId::MIN) => return Err("ERR The ID specified in XADD must be greater than 0-0"),
+            Some(id) if last.is_some_and(|last| id <= last) => {
+                return Err("ERR The ID specified in XADD is equal or smaller than the target stream top item")
+            }
+            Some(id) => id,
+            None => {
+                let ms = unix_millis(SystemTime::now());
+
+                // 时钟回拨或同一毫秒内，沿用最后一个条目的毫秒数
+                match last {
+                    Some(last) if last.ms >= ms => last
+                        .next()
+                        .ok_or("ERR The stream has exhausted the last possible ID, unable to add more items")?,
+                    _ => StreamId::new(ms, 0),
+                }
+            }
+        };
+
+        let event = state.keyspace_event(EventClass::Stream, "xadd", &key);
+
+        let entry = state.entries.entry(key.clone()).or_insert_with(|| Entry {
+            data: Value::Stream(BTreeMap::new()),
+            expires_at: None,
+            accessed_at: now,
+        });
+
+        let Value::Stream(stream) = &mut entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        stream.insert(id, fields);
+        entry.accessed_at = now;
+
+        // 唤醒`XSUBSCRIBE`的读者，它们在锁释放之后读取新的条目
+        if let Some(readers) = state.stream_readers.get(&key) {
+            readers.send_replace(id);
+        }
+
+        state.replicate(&key, now);
+
+        self.events.extend(event);
+
+        Ok(id)
+    }
+
+    /// Returns the entries of the stream associated with a key whose ids are
+    /// within `start..=end`, at most `count` of them, in the order of their
+    /// ids.
+    ///
+    /// Returns an empty list if there is no value associated with the key, or
+    /// the `WRONGTYPE` error if the value is not a stream.
+    pub(crate) fn xrange(
+        &mut self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>, &'static str> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+
+        let Some(entry) = state
+            .entries
+            .get_mut(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        else {
+            return Ok(vec![]);
+        };
+
+        let Value::Stream(stream) = &entry.data else {
+            return Err(WRONGTYPE);
+        };
+
+        // `BTreeMap::range`在`start > end`时panic
+        if start > end {
+            return Ok(vec![]);
+        }
+
+        let entries = stream
+            .range(start..=end)
+            .take(count)
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect();
+        entry.accessed_at = now;
+
+        Ok(entries)
+    }
+
+    /// Set the keys to expire after `ttl` if `condition` holds, as with an
+    /// `EXPIRE` per key. Returns, for each key, whether its time to live was
+    /// set.
+    pub(crate) fn expire_many(&mut self, keys: &[String], ttl: Duration, condition: Option<ExpireCondition>) -> Vec<bool> {
+        let state = &mut *self.state;
+
+        let now = Instant::now();
+        let when = now + ttl;
+
+        let mut results = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            let next = state.next_expiration();
+            let set = state.expire_at(key, when, condition, now);
+
+            if set {
+                self.notify |= next.map(|next| next > when).unwrap_or(true);
+                self.events.extend(state.keyspace_event(EventClass::Generic, "expire", key));
+                state.replicate(key, now);
+            }
+
+            results.push(set);
+        }
+
+        results
+    }
+
+    /// Publish a message to the channel, see `Db::publish`.
+    pub(crate) fn publish(&mut self, key: &str, value: Bytes) -> usize {
+        self.state.publish(key, value)
+    }
+}
+
 impl State {
+    /// Returns the keyspace event `name` on `key`, or `None` if the events of
+    /// `class` are not published.
+    fn keyspace_event(&self, class: EventClass, name: &'static str, key: &str) -> Option<KeyspaceEvent> {
+        self.keyspace_events.is_enabled(class).then(|| KeyspaceEvent {
+            name,
+            key: key.to_string(),
+        })
+    }
+
+    /// Returns a `Receiver` for the channel, creating the channel if needed.
+    fn subscribe(&mut self, key: String) -> fanout::Receiver<Bytes> {
+        let (capacity, overflow) = (self.pubsub_queue_capacity, self.pubsub_overflow);
+
+        // 如果当前请求channel中没有entry，那么创建一个新的channel 并且将其和key联系起来
+        // 如果已经存在了，那么返回一个已经和key联系起来的receiver
+        let tx = self
+            .pub_sub
+            .entry(key.clone())
+            .or_insert_with(|| fanout::Sender::new(capacity, overflow));
+
+        let rx = tx.subscribe();
+        let receivers = tx.receiver_count();
+
+        let channel = self.channel_traffic.entry(key).or_default();
+        channel.peak_receivers = channel.peak_receivers.max(receivers);
+        rx
+    }
+
+    /// Removes the channel if it has no subscribers left.
+    ///
+    /// `Receiver`s are only created under the lock, so a subscriber cannot
+    /// join a channel between the check and its removal. One arriving later
+    /// creates the channel again.
+    fn release_channel(&mut self, key: &str) {
+        if self.pub_sub.get(key).map(|tx| tx.receiver_count() == 0).unwrap_or(false) {
+            self.pub_sub.remove(key);
+        }
+    }
+
+    /// Removes the pattern if it has no subscribers left, see
+    /// `release_channel`.
+    fn release_pattern(&mut self, pattern: &str) {
+        if self.pub_sub_patterns.get(pattern).map(|tx| tx.receiver_count() == 0).unwrap_or(false) {
+            self.pub_sub_patterns.remove(pattern);
+        }
+    }
+
+    /// Publish a message to the channel, recording it in the statistics of the
+    /// channel, and to the patterns matching the channel. Returns the number of
+    /// subscribers it was sent to.
+    fn publish(&mut self, key: &str, value: Bytes) -> usize {
+        let mut receivers = 0;
+
+        // 从未被订阅过的频道没有统计
+        if let Some(channel) = self.channel_traffic.get_mut(key) {
+            channel.published += 1;
+
+            if channel.history.len() == CHANNEL_HISTORY {
+                channel.history.pop_front();
+            }
+            channel.history.push_back(unix_millis(SystemTime::now()));
+        }
+
+        // 如果当前key没有相应的entry，这里也是没有订阅者
+        if let Some(tx) = self.pub_sub.get_mut(key) {
+            // 返回消息送达的订阅者数量，发送时顺便移除了离开的订阅者，
+            // 如果一个都不剩，同时移除这个频道
+            receivers += tx.send(value.clone());
+
+            if tx.receiver_count() == 0 {
+                self.pub_sub.remove(key);
+            }
+        }
+
+        // 每个匹配的模式都要发送一次，消息带上频道名，所有模式共享同一个名字
+        let mut abandoned = vec![];
+        let mut channel: Option<Arc<str>> = None;
+        for (pattern, tx) in &mut self.pub_sub_patterns {
+            if glob_match(pattern.as_bytes(), key.as_bytes()) {
+                let channel = channel.get_or_insert_with(|| Arc::from(key)).clone();
+                receivers += tx.send((channel, value.clone()));
+
+                if tx.receiver_count() == 0 {
+                    abandoned.push(pattern.clone());
+                }
+            }
+        }
+
+        for pattern in abandoned {
+            self.pub_sub_patterns.remove(&pattern);
+        }
+
+        receivers
+    }
+
+    /// Publish `event` on the keyspace and keyevent channels enabled.
+    fn publish_keyspace_event(&mut self, event: &KeyspaceEvent) {
+        let mut messages = vec![];
+
+        if self.keyspace_events.keyspace {
+            let channel = format!("__keyspace@0__:{}", event.key);
+            messages.push((channel, Bytes::from_static(event.name.as_bytes())));
+        }
+
+        if self.keyspace_events.keyevent {
+            let channel = format!("__keyevent@0__:{}", event.name);
+            messages.push((channel, Bytes::from(event.key.clone())));
+        }
+
+        for (channel, message) in messages {
+            self.publish(&channel, message);
+        }
+    }
+
+    /// Set `key` to expire at `when` if `condition` holds. Returns `false`,
+    /// leaving the key untouched, if it does not exist or the condition does
+    /// not hold.
+    ///
+    /// The caller notifies the background task if needed.
+    fn expire_at(&mut self, key: &str, when: Instant, condition: Option<ExpireCondition>, now: Instant) -> bool {
+        let Some(entry) = self
+            .entries
+            .get_mut(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        else {
+            return false;
+        };
+
+        // 没有过期时间的key被当作永不过期
+        let holds = match (condition, entry.expires_at) {
+            (None, _) => true,
+            (Some(ExpireCondition::Nx), current) => current.is_none(),
+            (Some(ExpireCondition::Xx), current) => current.is_some(),
+            (Some(ExpireCondition::Gt), current) => current.map(|current| when > current).unwrap_or(false),
+            (Some(ExpireCondition::Lt), current) => current.map(|current| when < current).unwrap_or(true),
+        };
+
+        if !holds {
+            return false;
+        }
+
+        if let Some(prev) = entry.expires_at.replace(when) {
+            self.expirations.remove(&(prev, key.to_string()));
+        }
+
+        self.expirations.insert((when, key.to_string()));
+        true
+    }
+
+    /// Removes `key` if it has expired but was not purged yet, so that it can
+    /// be created again.
+    fn remove_expired(&mut self, key: &str, now: Instant) {
+        let expired = self
+            .entries
+            .get(key)
+            .and_then(|entry| entry.expires_at)
+            .filter(|when| *when <= now);
+
+        if let Some(when) = expired {
+            self.entries.remove(key);
+            self.expirations.remove(&(when, key.to_string()));
+            self.replicate(key, now);
+        }
+    }
+
+    /// Sends the value `key` is left with to the replicas, or its removal.
+    /// Must be called after every change of the key, while holding the lock,
+    /// so that the replicas apply the changes in the same order.
+    fn replicate(&mut self, key: &str, now: Instant) {
+        if self.replicas.receiver_count() == 0 {
+            return;
+        }
+
+        let change = match self.entries.get(key).filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true)) {
+            None => Change::del(key),
+            Some(Entry {
+                data: Value::String(data),
+                expires_at,
+                ..
+            }) => Change::set(key, data.clone(), expires_at.map(|when| unix_millis_at(when, now))),
+            Some(entry) => Change::unsupported(key, entry.type_name()),
+        };
+
+        self.replicas.send(change);
+    }
+
+    /// Sends a change which is not about a single key to the replicas.
+    fn replicate_change(&mut self, change: impl FnOnce() -> Change) {
+        if self.replicas.receiver_count() > 0 {
+            self.replicas.send(change());
+        }
+    }
+
     fn next_expiration(&self) -> Option<Instant> {
         self.expirations
             .iter()
@@ -314,6 +2858,169 @@ impl State {
     }
 }
 
+/// Matches `string` against a Redis glob-style `pattern`.
+///
+/// `*` matches any sequence, `?` matches a single byte, `[...]` matches a set
+/// of bytes which may contain ranges (`a-z`) and be negated (`[^...]`), and
+/// `\` escapes the next byte.
+pub(crate) fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+
+    // 最近一个`*`的位置，以及当时`string`的位置。匹配失败时回溯到这里，
+    // 让`*`多匹配一个字节
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        let matched = match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, s));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => Some(p + 1),
+            Some(b'[') => match_class(pattern, p, string[s]),
+            Some(b'\\') if p + 1 < pattern.len() => {
+                if pattern[p + 1] == string[s] {
+                    Some(p + 2)
+                } else {
+                    None
+                }
+            }
+            Some(&c) if c == string[s] => Some(p + 1),
+            _ => None,
+        };
+
+        match matched {
+            Some(next) => {
+                p = next;
+                s += 1;
+            }
+            None => match backtrack {
+                Some((star, star_s)) => {
+                    p = star + 1;
+                    s = star_s + 1;
+                    backtrack = Some((star, star_s + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    // `string`已经用完，剩下的pattern只能是`*`
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Matches `c` against the `[...]` class starting at `pattern[start]`.
+///
+/// Returns the position following the class if `c` matches it.
+fn match_class(pattern: &[u8], start: usize, c: u8) -> Option<usize> {
+    let mut p = start + 1;
+
+    let negate = pattern.get(p) == Some(&b'^');
+    if negate {
+        p += 1;
+    }
+
+    let mut matched = false;
+
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            p += 1;
+            matched |= pattern[p] == c;
+            p += 1;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' && pattern[p + 2] != b']' {
+            let (lo, hi) = if pattern[p] <= pattern[p + 2] {
+                (pattern[p], pattern[p + 2])
+            } else {
+                (pattern[p + 2], pattern[p])
+            };
+            matched |= lo <= c && c <= hi;
+            p += 3;
+        } else {
+            matched |= pattern[p] == c;
+            p += 1;
+        }
+    }
+
+    // 没有闭合的`]`时，和Redis一样把pattern的结尾当作类的结尾
+    let next = if p < pattern.len() { p + 1 } else { p };
+
+    if matched != negate {
+        Some(next)
+    } else {
+        None
+    }
+}
+
+/// Returns the position of `key` in the order of a `SCAN` iteration.
+///
+/// The position is a hash of the key, so it does not depend on the other keys.
+/// 0 is reserved for the cursor starting and ending an iteration.
+fn scan_position(key: &str) -> u64 {
+    // `DefaultHasher::new()`使用固定的密钥，同一个key在进程内的hash总是相同的
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish().max(1)
+}
+
+/// Returns the shared `Bytes` holding the same integer as `value`, or `value`
+/// itself when it is not a small integer in canonical form.
+fn shared_integer(value: Bytes) -> Bytes {
+    static POOL: OnceLock<Vec<Bytes>> = OnceLock::new();
+
+    // 只有规范形式的整数才能共享，例如"007"和"-0"不能被替换为"7"和"0"
+    if value.is_empty() || value.len() > 4 || (value.len() > 1 && value[0] == b'0') {
+        return value;
+    }
+
+    let num = match atoi::atoi::<usize>(&value) {
+        Some(num) if num < SHARED_INTEGERS && value.iter().all(u8::is_ascii_digit) => num,
+        _ => return value,
+    };
+
+    let pool = POOL.get_or_init(|| {
+        (0..SHARED_INTEGERS)
+            .map(|num| Bytes::from(num.to_string()))
+            .collect()
+    });
+
+    // clone是浅拷贝，所有的clone指向同一块内存
+    pool[num].clone()
+}
+
+/// Returns `true` if `data` points to the allocation of a shared integer.
+fn is_shared_integer(data: &Bytes) -> bool {
+    // 对数据的拷贝调用`shared_integer`：如果是共享整数，返回的是共享池中的那份内存
+    !data.is_empty() && shared_integer(Bytes::copy_from_slice(data)).as_ptr() == data.as_ptr()
+}
+
+/// Returns `true` if `data` is the canonical representation of an `i64`, that
+/// is one that formats back to the same bytes. "12" is, but "012" and "+12"
+/// are not.
+fn is_int_encodable(data: &[u8]) -> bool {
+    // i64 最多20个字符，包括负号
+    if data.is_empty() || data.len() > 20 {
+        return false;
+    }
+
+    std::str::from_utf8(data)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .map(|num| num.to_string().as_bytes() == data)
+        .unwrap_or(false)
+}
+
+/// Returns `time` as a Unix timestamp in milliseconds.
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// Returns the Unix timestamp, in milliseconds, of `when`, `now` being the
+/// current instant.
+fn unix_millis_at(when: Instant, now: Instant) -> u64 {
+    unix_millis(SystemTime::now() + when.saturating_duration_since(now))
+}
+
 /// Routine executed by the background task
 ///
 /// Wait to be notified. On notification, purge any expired keys from the shared
@@ -0,0 +1,276 @@
+//! Delivery of pub/sub messages to the subscribers of a channel.
+//!
+//! `tokio::sync::broadcast` allocates a ring of messages per channel, idle or
+//! not, and only tells a subscriber falling behind that it skipped messages.
+//! `Sender` keeps a bounded queue per subscriber instead: publishing appends
+//! the message to the queue of each subscriber, cloning only a handle to its
+//! payload, and the queues only allocate once messages are pending. What
+//! happens when a queue is full is chosen per channel with `Overflow`, and
+//! every message a subscriber misses is counted.
+//!
+//! # Examples
+//!
+//! ```
+//! use my_mini_redis::fanout::{Overflow, RecvError, Sender};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let mut tx = Sender::new(2, Overflow::DropOldest);
+//!     let mut rx = tx.subscribe();
+//!
+//!     for message in ["a", "b", "c"] {
+//!         assert_eq!(1, tx.send(message));
+//!     }
+//!
+//!     // The oldest message made room for the last one
+//!     assert_eq!(Err(RecvError::Lagged(1)), rx.recv().await);
+//!     assert_eq!(Ok("b"), rx.recv().await);
+//!     assert_eq!(Ok("c"), rx.recv().await);
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Notify;
+
+/// Number of messages a subscriber may have pending by default, as many as
+/// the ring of the `broadcast` channels used before.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Sending half of a channel, delivering each message to every subscriber.
+///
+/// Unlike `broadcast::Sender`, subscribing needs mutable access: the
+/// subscribers are registered in the sender, which the server keeps under the
+/// lock of the `Db` anyway. Dropping the sender closes the channel, once the
+/// subscribers have received the messages still queued.
+#[derive(Debug)]
+pub struct Sender<T> {
+    /// The queues of the subscribers. A queue is freed when its `Receiver` is
+    /// dropped, and removed from the list on the next `send`.
+    subscribers: Vec<Weak<Queue<T>>>,
+
+    /// Maximum number of messages pending in each queue.
+    capacity: usize,
+
+    overflow: Overflow,
+}
+
+/// Receiving half of a channel, created by `Sender::subscribe`.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    queue: Arc<Queue<T>>,
+}
+
+/// What happens to a message sent to a subscriber whose queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// The oldest pending message is dropped to make room for the new one, as
+    /// `broadcast` does for a lagging receiver.
+    #[default]
+    DropOldest,
+
+    /// The new message is dropped, the pending ones are kept.
+    DropNewest,
+
+    /// The subscriber is disconnected: its pending messages are dropped and
+    /// it receives no other message.
+    Disconnect,
+}
+
+/// Error returned by `Receiver::recv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// The subscriber missed that many messages, as its queue was full, since
+    /// the previous error. The next messages can still be received.
+    Lagged(u64),
+
+    /// The subscriber was disconnected by `Overflow::Disconnect`.
+    Disconnected,
+
+    /// The `Sender` was dropped and all the messages were received.
+    Closed,
+}
+
+#[derive(Debug)]
+struct Queue<T> {
+    state: Mutex<QueueState<T>>,
+
+    /// Wakes up the receiver waiting for a message. Only a single task waits
+    /// on a queue, so `notify_one` keeps the wake up if it is not waiting yet.
+    notify: Notify,
+}
+
+#[derive(Debug)]
+struct QueueState<T> {
+    messages: VecDeque<T>,
+
+    /// Messages dropped since the last `RecvError::Lagged`.
+    lagged: u64,
+
+    disconnected: bool,
+
+    closed: bool,
+}
+
+impl<T: Clone> Sender<T> {
+    /// Create a channel without subscribers, each of them having up to
+    /// `capacity` messages pending, and `overflow` deciding what happens to
+    /// the next ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize, overflow: Overflow) -> Sender<T> {
+        assert!(capacity > 0, "a subscriber must be able to hold a message");
+
+        Sender {
+            subscribers: vec![],
+            capacity,
+            overflow,
+        }
+    }
+
+    /// Register a new subscriber, receiving the messages sent from now on.
+    pub fn subscribe(&mut self) -> Receiver<T> {
+        let queue = Arc::new(Queue {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::new(),
+                lagged: 0,
+                disconnected: false,
+                closed: false,
+            }),
+            notify: Notify::new(),
+        });
+
+        self.subscribers.push(Arc::downgrade(&queue));
+
+        Receiver { queue }
+    }
+
+    /// Send `value` to every subscriber, and return the number of subscribers
+    /// it was queued for.
+    ///
+    /// The subscribers whose queue is full are not counted, unless the oldest
+    /// message is dropped to make room for it.
+    pub fn send(&mut self, value: T) -> usize {
+        let mut sent = 0;
+
+        // 顺便移除已经离开或者被断开的订阅者
+        self.subscribers.retain(|subscriber| {
+            let Some(queue) = subscriber.upgrade() else {
+                return false;
+            };
+
+            let mut state = queue.state.lock().unwrap();
+
+            if state.messages.len() == self.capacity {
+                state.lagged += 1;
+
+                match self.overflow {
+                    Overflow::DropOldest => {
+                        state.messages.pop_front();
+                    }
+                    Overflow::DropNewest => return true,
+                    Overflow::Disconnect => {
+                        state.messages = VecDeque::new();
+                        state.disconnected = true;
+                        drop(state);
+                        queue.notify.notify_one();
+                        return false;
+                    }
+                }
+            }
+
+            state.messages.push_back(value.clone());
+            sent += 1;
+
+            drop(state);
+            queue.notify.notify_one();
+
+            true
+        });
+
+        sent
+    }
+
+    /// Returns the number of subscribers, not counting the disconnected ones.
+    pub fn receiver_count(&self) -> usize {
+        self.subscribers
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|queue| !queue.state.lock().unwrap().disconnected)
+            .count()
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        for queue in self.subscribers.iter().filter_map(Weak::upgrade) {
+            queue.state.lock().unwrap().closed = true;
+            queue.notify.notify_one();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Wait for the next message.
+    ///
+    /// Messages missed since the previous call are reported first, with
+    /// `RecvError::Lagged`. Once the subscriber is disconnected, or the sender
+    /// dropped and the pending messages received, an error is returned by
+    /// every call.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            {
+                let mut state = self.queue.state.lock().unwrap();
+
+                if state.disconnected {
+                    return Err(RecvError::Disconnected);
+                }
+
+                if state.lagged > 0 {
+                    return Err(RecvError::Lagged(std::mem::take(&mut state.lagged)));
+                }
+
+                if let Some(message) = state.messages.pop_front() {
+                    return Ok(message);
+                }
+
+                if state.closed {
+                    return Err(RecvError::Closed);
+                }
+            }
+
+            self.queue.notify.notified().await;
+        }
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecvError::Lagged(skipped) => write!(fmt, "subscriber lagged behind, {} messages skipped", skipped),
+            RecvError::Disconnected => "subscriber disconnected, too many messages pending".fmt(fmt),
+            RecvError::Closed => "channel closed".fmt(fmt),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+impl FromStr for Overflow {
+    type Err = crate::Error;
+
+    /// Parse the overflow behavior, `drop-oldest`, `drop-newest` or
+    /// `disconnect`.
+    fn from_str(s: &str) -> crate::Result<Overflow> {
+        match &s.to_lowercase()[..] {
+            "drop-oldest" => Ok(Overflow::DropOldest),
+            "drop-newest" => Ok(Overflow::DropNewest),
+            "disconnect" => Ok(Overflow::Disconnect),
+            _ => Err(format!("invalid pub/sub overflow behavior '{}'", s).into()),
+        }
+    }
+}
@@ -3,7 +3,7 @@
 
 use bytes::{Buf, Bytes};
 use std::convert::TryInto;
-use std::fmt::{self, write};
+use std::fmt;
 use std::io::Cursor;
 use std::num::TryFromIntError;
 use std::string::FromUtf8Error;
@@ -14,16 +14,47 @@ pub enum Frame {
     Simple(String),
     Error(String),
     Integer(u64),
+    /// A negative integer, e.g. the `-2` replied by `PTTL` for a missing key.
+    /// Written as an `Integer`, and parsed from integers with a leading `-`.
+    SignedInteger(i64),
     Bulk(Bytes),
     Null,
     Array(Vec<Frame>),
 }
 
+/// Default maximum length of a bulk string, and number of elements of an
+/// array, accepted by `Frame::check` and `Frame::parse`. Same as the default
+/// `proto-max-bulk-len` of Redis.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 512 * 1024 * 1024;
+
+/// Maximum number of arrays nested in one another accepted by `Frame::check`
+/// and `Frame::parse`. Both recurse into nested arrays, so without a limit a
+/// peer could exhaust the stack with a few bytes per level. The replies of
+/// the server are nested a few levels at most.
+pub const MAX_ARRAY_DEPTH: usize = 128;
+
+/// The error of `Frame::check` and `Frame::parse` on malformed input.
+///
+/// - Empty input, a line missing its `\r\n`, a lone `\r`, or a frame cut
+///   short: `Incomplete`, more data may complete it.
+/// - A length header with no digits, like `$\r\n`, or a negative length
+///   other than `-1`: "protocol error; invalid bulk length", followed by the
+///   line quoted, with `array length` or `integer` for the other headers.
+/// - A length above the maximum: `TooLarge`.
+/// - A bulk string not followed by `\r\n`: "protocol error; bulk string not
+///   terminated by CRLF".
+/// - A type byte other than `+ - : $ *`, inline commands such as `PING\r\n`
+///   included: "protocol error; invalid frame type byte `P`".
+/// - Arrays nested deeper than `MAX_ARRAY_DEPTH`: "protocol error; arrays
+///   nested too deeply".
 #[derive(Debug)]
 pub enum Error {
     /// Not enough data is available to parse a message
     Incomplete,
 
+    /// A bulk string or an array declares a length above the maximum
+    TooLarge,
+
     /// Invalid message encoding
     Other(crate::Error),
 }
@@ -57,109 +88,122 @@ impl Frame {
         }
     }
 
-    /// Checks if an entire message can be decoded from `src`
-    pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
-        match get_u8(src)? {
-            // Simple strings: +OK\r\n
-            b'+' => {
-                get_line(src)?;
-                Ok(())
-            }
-            // Simple errors: -Error message\r\n
-            b'-' => {
-                get_line(src)?;
-                Ok(())
-            }
-            // Integers: :[<+|->]<value>\r\n
-            b':' => {
-                let _ = get_decimal(src)?;
-                Ok(())
-            }
-            // Bulk strings: $<length>\r\n<data>\r\n
-            b'$' => {
-                if b'-' == peek_u8(src)? {
-                    // 跳过'-1\r\n'
-                    skip(src, 4)
-                } else {
-                    // 这里需要实现 From<TryFromIntError> for Error
-                    // 读取bulk string长度
-                    let len: usize = get_decimal(src)?.try_into()?;
-
-                    // 跳过字节数+2(\r\n)
-                    skip(src, len + 2)
-                }
-            }
-            // Arrays: *<number-of-elements>\r\n<element-1>...<element-n>
-            b'*' => {
-                let len = get_decimal(src)?;
-
-                for _ in 0..len {
-                    Frame::check(src)?;
-                }
-
-                Ok(())
+    /// Push a nested frame, e.g. an array, into the array. `self` must be an
+    /// Array frame.
+    ///
+    /// # Panics
+    ///
+    /// panics if `self` is not an array
+    pub(crate) fn push_frame(&mut self, frame: Frame) {
+        match self {
+            Frame::Array(vec) => {
+                vec.push(frame);
             }
-            // 其他任意字符
-            actual => Err(format!("protocol error: invalid frame type byte `{}`", actual).into()),
+            _ => panic!("not an array frame"),
         }
     }
 
-    pub fn parse(src: &mut Cursor<&[u8]>) -> Result<Frame, Error> {
-        match get_u8(src)? {
-            b'+' => {
-                let line = get_line(src)?.to_vec();
-                // 需要实现 impl From<FromUtf8Error> for Error
-                let string = String::from_utf8(line)?;
-
-                Ok(Frame::Simple(string))
-            }
-            b'-' => {
-                let line = get_line(src)?.to_vec();
-
-                let string = String::from_utf8(line)?;
+    /// Checks if an entire message can be decoded from `src`
+    ///
+    /// Lengths above `DEFAULT_MAX_FRAME_LEN` are rejected, see
+    /// `check_with_max_len`.
+    pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
+        Frame::check_with_max_len(src, DEFAULT_MAX_FRAME_LEN)
+    }
 
-                Ok(Frame::Error(string))
-            }
-            b':' => {
-                let len = get_decimal(src)?;
-                Ok(Frame::Integer(len))
-            }
-            b'$' => {
-                if b'-' == peek_u8(src)? {
-                    let line = get_line(src)?;
+    /// Checks if an entire message can be decoded from `src`, rejecting with
+    /// `Error::TooLarge` bulk strings longer than `max_len` bytes and arrays of
+    /// more than `max_len` elements.
+    ///
+    /// The lengths are checked as soon as the headers are read, so a peer
+    /// cannot make the caller wait for, or allocate, an arbitrary amount of
+    /// data.
+    pub fn check_with_max_len(src: &mut Cursor<&[u8]>, max_len: usize) -> Result<(), Error> {
+        check_frame(src, max_len, false, 0)
+    }
 
-                    if line != b"-1" {
-                        return Err("protocol error; invalid frame format".into());
-                    }
+    /// Same as `check_with_max_len`, also accepting the compressed bulk
+    /// strings sent once a connection negotiated compression, see
+    /// `Connection::set_compression`.
+    pub(crate) fn check_compressed(src: &mut Cursor<&[u8]>, max_len: usize) -> Result<(), Error> {
+        check_frame(src, max_len, true, 0)
+    }
 
-                    Ok(Frame::Null)
-                } else {
-                    let len: usize = get_decimal(src)?.try_into()?;
-                    let n = len + 2;
+    /// Parses a message from `src`, which should have been validated with
+    /// `check` first.
+    ///
+    /// Lengths above `DEFAULT_MAX_FRAME_LEN` are rejected, see
+    /// `parse_with_max_len`.
+    pub fn parse(src: &mut Cursor<&[u8]>) -> Result<Frame, Error> {
+        Frame::parse_with_max_len(src, DEFAULT_MAX_FRAME_LEN)
+    }
 
-                    if src.remaining() < n {
-                        return Err(Error::Incomplete);
-                    }
+    /// Parses a message from `src`, rejecting with `Error::TooLarge` bulk
+    /// strings longer than `max_len` bytes and arrays of more than `max_len`
+    /// elements before allocating them.
+    pub fn parse_with_max_len(src: &mut Cursor<&[u8]>, max_len: usize) -> Result<Frame, Error> {
+        parse_frame(src, max_len, false, 0)
+    }
 
-                    let data = Bytes::copy_from_slice(&src.chunk()[..len]);
+    /// Same as `parse_with_max_len`, also decompressing the compressed bulk
+    /// strings sent once a connection negotiated compression. They are
+    /// returned as plain `Frame::Bulk`.
+    pub(crate) fn parse_compressed(src: &mut Cursor<&[u8]>, max_len: usize) -> Result<Frame, Error> {
+        parse_frame(src, max_len, true, 0)
+    }
 
-                    skip(src, n)?;
+    /// Reads the header of a bulk string, `$<length>\r\n`, from `src` and
+    /// returns its length. `src` is left at the start of the payload, which
+    /// does not need to be available yet.
+    ///
+    /// Returns `None`, and leaves `src` untouched, if `src` starts with any
+    /// other frame, a null bulk string included. The length is not bounded,
+    /// as the payload is not buffered by the caller.
+    pub fn parse_bulk_header(src: &mut Cursor<&[u8]>) -> Result<Option<usize>, Error> {
+        let start = src.position();
+
+        if get_u8(src)? != b'$' {
+            src.set_position(start);
+            return Ok(None);
+        }
 
-                    Ok(Frame::Bulk(data))
-                }
+        match get_len(src, usize::MAX, "bulk length")? {
+            Some(len) => Ok(Some(len)),
+            None => {
+                src.set_position(start);
+                Ok(None)
             }
-            b'*' => {
-                let len: usize = get_decimal(src)?.try_into()?;
-                let mut out = Vec::with_capacity(len);
-
-                for _ in 0..len {
-                    out.push(Frame::parse(src)?);
-                }
+        }
+    }
 
-                Ok(Frame::Array(out))
+    /// Checks that the frame has the shape of a command sent by a client: an
+    /// array whose entries are not arrays themselves.
+    ///
+    /// `parse` trusts the element count declared by the array header, so a
+    /// wrong count is not detected while parsing. Instead it shows up in the
+    /// shape of the frames: a count too small leaves the remaining entries to
+    /// be read as frames outside of any array, and a count too large pulls the
+    /// header of the next command into the array.
+    pub(crate) fn check_command(&self) -> Result<(), Error> {
+        let parts = match self {
+            Frame::Array(parts) => parts,
+            frame => {
+                return Err(format!(
+                    "ERR Protocol error: expected command array, got `{}`; \
+                     the previous array may have declared fewer elements than it contained",
+                    frame
+                )
+                .into())
             }
-            _ => unimplemented!(),
+        };
+
+        if parts.iter().any(|part| matches!(part, Frame::Array(_))) {
+            return Err("ERR Protocol error: nested array in command; \
+                        the array may have declared more elements than it contained"
+                .into());
         }
+
+        Ok(())
     }
 
     /// 将frame转换为一个"unexpected frame" error
@@ -186,6 +230,7 @@ impl fmt::Display for Frame {
             Frame::Simple(response) => response.fmt(f),
             Frame::Error(msg) => write!(f, "error: {}", msg),
             Frame::Integer(num) => num.fmt(f),
+            Frame::SignedInteger(num) => num.fmt(f),
             Frame::Bulk(msg) => match std::str::from_utf8(msg) {
                 Ok(string) => string.fmt(f),
                 Err(_) => write!(f, "{:?}", msg),
@@ -206,62 +251,244 @@ impl fmt::Display for Frame {
     }
 }
 
-/// 取Cursor当前指向的第一个字节,但Cursor不向前移动
-fn peek_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
-    // https://docs.rs/bytes/latest/bytes/buf/trait.Buf.html
-    // 如果还有字节可以消费，返回true
+/// 取Cursor当前指向的第一个字节，Cursor向后移动一个字节
+fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
     if !src.has_remaining() {
         return Err(Error::Incomplete);
     }
-    // fn remaining(&self) -> &[u8]
-    // 返回一个从当前位置开始，到buffer结束的字节数
 
-    // chunk()返回一个从当前位置的切片，长度在0到 `Buf::remaining()` 之间
-    // 注意这个函数可以返回一个长度比 `Buf::remaining()` 更短的切片
-    // (这允许不连续的内部表示)
-    Ok(src.chunk()[0])
+    Ok(src.get_u8())
 }
 
-/// 取Cursor当前指向的第一个字节，Cursor向后移动一个字节
-fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
-    if !src.has_remaining() {
+/// 读取`len`个字节的payload和结尾的\r\n，返回payload
+fn get_bulk<'a>(src: &mut Cursor<&'a [u8]>, len: usize) -> Result<&'a [u8], Error> {
+    let start = src.position() as usize;
+    let data = &src.get_ref()[start..];
+
+    // `len`可能接近usize::MAX，不能直接加2
+    if data.len() < 2 || data.len() - 2 < len {
         return Err(Error::Incomplete);
     }
 
-    Ok(src.get_u8())
+    if &data[len..len + 2] != b"\r\n" {
+        return Err("protocol error; bulk string not terminated by CRLF".into());
+    }
+
+    src.advance(len + 2);
+    Ok(&data[..len])
 }
 
-/// 使Cursor向后移动n个字节
-fn skip(src: &mut Cursor<&[u8]>, n: usize) -> Result<(), Error> {
-    if src.remaining() < n {
-        return Err(Error::Incomplete);
+/// 压缩的bulk string只有在`compressed`时才被接受，`depth`是外层array的数量
+fn check_frame(src: &mut Cursor<&[u8]>, max_len: usize, compressed: bool, depth: usize) -> Result<(), Error> {
+    match get_u8(src)? {
+        // Simple strings: +OK\r\n
+        b'+' => {
+            get_line(src)?;
+            Ok(())
+        }
+        // Simple errors: -Error message\r\n
+        b'-' => {
+            get_line(src)?;
+            Ok(())
+        }
+        // Integers: :<value>\r\n, or :-<value>\r\n
+        b':' => {
+            let _ = get_integer(src)?;
+            Ok(())
+        }
+        // Bulk strings: $<length>\r\n<data>\r\n, or $-1\r\n
+        b'$' => {
+            match get_len(src, max_len, "bulk length")? {
+                Some(len) => get_bulk(src, len).map(|_| ()),
+                None => Ok(()),
+            }
+        }
+        // Compressed bulk strings: &<length>\r\n<data>\r\n, only after
+        // compression was negotiated, and never null
+        b'&' if compressed => {
+            let len = get_len(src, max_len, "compressed bulk length")?.ok_or_else(invalid_compressed)?;
+            get_bulk(src, len).map(|_| ())
+        }
+        // Arrays: *<number-of-elements>\r\n<element-1>...<element-n>, or *-1\r\n
+        b'*' => {
+            let len = get_len(src, max_len, "array length")?.unwrap_or(0);
+
+            if len > 0 && depth >= MAX_ARRAY_DEPTH {
+                return Err("protocol error; arrays nested too deeply".into());
+            }
+
+            for _ in 0..len {
+                check_frame(src, max_len, compressed, depth + 1)?;
+            }
+
+            Ok(())
+        }
+        // 其他任意字符，包括inline命令的第一个字母
+        actual => Err(invalid_type(actual)),
+    }
+}
+
+fn parse_frame(src: &mut Cursor<&[u8]>, max_len: usize, compressed: bool, depth: usize) -> Result<Frame, Error> {
+    match get_u8(src)? {
+        b'+' => {
+            let line = get_line(src)?.to_vec();
+            // 需要实现 impl From<FromUtf8Error> for Error
+            let string = String::from_utf8(line)?;
+
+            Ok(Frame::Simple(string))
+        }
+        b'-' => {
+            let line = get_line(src)?.to_vec();
+
+            let string = String::from_utf8(line)?;
+
+            Ok(Frame::Error(string))
+        }
+        b':' => get_integer(src),
+        b'$' => {
+            let Some(len) = get_len(src, max_len, "bulk length")? else {
+                return Ok(Frame::Null);
+            };
+
+            let data = Bytes::copy_from_slice(get_bulk(src, len)?);
+
+            Ok(Frame::Bulk(data))
+        }
+        b'&' if compressed => {
+            let len = get_len(src, max_len, "compressed bulk length")?.ok_or_else(invalid_compressed)?;
+
+            let data = decompress(get_bulk(src, len)?, max_len)?;
+
+            Ok(Frame::Bulk(data))
+        }
+        b'*' => {
+            let Some(len) = get_len(src, max_len, "array length")? else {
+                return Ok(Frame::Null);
+            };
+
+            if len > 0 && depth >= MAX_ARRAY_DEPTH {
+                return Err("protocol error; arrays nested too deeply".into());
+            }
+
+            // 每个元素至少3个字节("+\r\n")，`src`没有经过check时不会按声明的长度分配
+            let mut out = Vec::with_capacity(len.min(src.remaining() / 3));
+
+            for _ in 0..len {
+                out.push(parse_frame(src, max_len, compressed, depth + 1)?);
+            }
+
+            Ok(Frame::Array(out))
+        }
+        actual => Err(invalid_type(actual)),
+    }
+}
+
+fn invalid_type(actual: u8) -> Error {
+    format!("protocol error; invalid frame type byte `{}`", actual.escape_ascii()).into()
+}
+
+/// Compresses the payload of a bulk string: the length of `data`, a
+/// little-endian `u32`, followed by `data` as an LZ4 block.
+///
+/// `data` must not be longer than `u32::MAX` bytes.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    lz4_flex::block::compress_prepend_size(data)
+}
+
+/// Decompresses the payload of a compressed bulk string, rejecting with
+/// `Error::TooLarge` a declared length above `max_len` before allocating it.
+fn decompress(src: &[u8], max_len: usize) -> Result<Bytes, Error> {
+    use lz4_flex::block;
+
+    let (len, src) = block::uncompressed_size(src).map_err(|_| invalid_compressed())?;
+
+    if len > max_len {
+        return Err(Error::TooLarge);
     }
 
-    src.advance(n);
-    Ok(())
+    let mut data = vec![0; len];
+
+    // 解压出的长度必须和声明的一致
+    match block::decompress_into(src, &mut data) {
+        Ok(n) if n == len => Ok(data.into()),
+        _ => Err(invalid_compressed()),
+    }
 }
-/// 将一行转换为u64
-fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
+
+fn invalid_compressed() -> Error {
+    "protocol error; invalid compressed bulk string".into()
+}
+
+/// 读取bulk string或array的长度，超过`max_len`时返回`Error::TooLarge`。
+///
+/// 只有`-1`这一个负数是合法的，表示null，此时返回`None`
+fn get_len(src: &mut Cursor<&[u8]>, max_len: usize, header: &str) -> Result<Option<usize>, Error> {
+    let line = get_line(src)?;
+
+    if line == b"-1" {
+        return Ok(None);
+    }
+
+    let len = parse_decimal(line).ok_or_else(|| invalid_header(header, line))?;
+
+    // 这里需要实现 From<TryFromIntError> for Error
+    let len: usize = len.try_into()?;
+
+    if len > max_len {
+        return Err(Error::TooLarge);
+    }
+
+    Ok(Some(len))
+}
+
+/// 整行必须都是数字，不能为空，除了`0`本身以外不能有前导零，并且不能溢出
+fn parse_decimal(line: &[u8]) -> Option<u64> {
     use atoi::atoi;
 
+    if line.is_empty() || !line.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    if line.len() > 1 && line[0] == b'0' {
+        return None;
+    }
+
+    // 溢出时`atoi`返回`None`
+    atoi::<u64>(line)
+}
+
+/// 读取一个整数，负数返回`SignedInteger`，其他返回`Integer`。`-0`不合法
+fn get_integer(src: &mut Cursor<&[u8]>) -> Result<Frame, Error> {
     let line = get_line(src)?;
-    atoi::<u64>(line).ok_or_else(|| "protocol error: invalid frame format".into())
+
+    let frame = match line.strip_prefix(b"-") {
+        Some(digits) => parse_decimal(digits)
+            .filter(|&num| num > 0)
+            .and_then(|num| 0i64.checked_sub_unsigned(num))
+            .map(Frame::SignedInteger),
+        None => parse_decimal(line).map(Frame::Integer),
+    };
+
+    frame.ok_or_else(|| invalid_header("integer", line))
+}
+
+fn invalid_header(header: &str, line: &[u8]) -> Error {
+    format!("protocol error; invalid {} `{}`", header, String::from_utf8_lossy(line)).into()
 }
 
 /// 获取一行(\r\n)
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     let start = src.position() as usize;
-    // get_ref()获得当前Cursor的底层数据结构的引用
-    let end = src.get_ref().len() - 1;
-    for i in start..end {
-        if src.get_ref()[i] == b'\r' && src.get_ref()[i + 1] == b'\n' {
-            src.set_position((i + 2) as u64);
-            // []将get_ref()获得的引用deref了，所以变成了[u8]，需要加&
-            return Ok(&src.get_ref()[start..i]);
+    // get_ref()获得当前Cursor的底层数据结构的引用。只剩一个`\r`时还不完整
+    let rest = src.get_ref().get(start..).unwrap_or_default();
+
+    match rest.windows(2).position(|pair| pair == b"\r\n") {
+        Some(i) => {
+            src.set_position((start + i + 2) as u64);
+            Ok(&rest[..i])
         }
+        None => Err(Error::Incomplete),
     }
-
-    Err(Error::Incomplete)
 }
 
 impl From<String> for Error {
@@ -294,6 +521,7 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Incomplete => "stream ended early".fmt(f),
+            Error::TooLarge => "protocol error; frame too large".fmt(f),
             Error::Other(err) => err.fmt(f),
         }
     }
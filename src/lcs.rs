@@ -0,0 +1,121 @@
+//! Longest common subsequence of two byte strings, computed by `LCS`.
+//!
+//! The algorithm does not depend on the server: it is a dynamic programming
+//! table of the lengths of the common subsequences of every pair of prefixes,
+//! walked back from its last cell to recover the subsequence.
+
+/// A run of bytes of the subsequence which are contiguous in both strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LcsMatch {
+    /// Offsets of the first and last bytes of the run in the first string,
+    /// both inclusive.
+    pub a: (usize, usize),
+
+    /// Offsets of the first and last bytes of the run in the second string,
+    /// both inclusive.
+    pub b: (usize, usize),
+}
+
+/// The longest common subsequence of two strings, along with where it is
+/// found in them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subsequence {
+    /// The bytes of the subsequence.
+    pub bytes: Vec<u8>,
+
+    /// The runs the subsequence is made of, last first, as reported by
+    /// `LCS ... IDX`.
+    pub matches: Vec<LcsMatch>,
+}
+
+impl LcsMatch {
+    /// Returns the number of bytes of the run.
+    pub fn match_len(&self) -> usize {
+        self.a.1 - self.a.0 + 1
+    }
+}
+
+/// Returns the number of cells of the table computed by
+/// `longest_common_subsequence` for strings of `a_len` and `b_len` bytes, or
+/// `None` on overflow. Each cell takes 4 bytes.
+pub fn table_len(a_len: usize, b_len: usize) -> Option<usize> {
+    (a_len + 1).checked_mul(b_len + 1)
+}
+
+/// Computes the longest common subsequence of `a` and `b`.
+///
+/// When several subsequences are the longest, the one Redis picks is
+/// returned. The table takes `table_len` cells, which the caller is expected
+/// to bound.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::lcs::{longest_common_subsequence, LcsMatch};
+///
+/// let lcs = longest_common_subsequence(b"ohmytext", b"mynewtext");
+///
+/// assert_eq!(b"mytext", &lcs.bytes[..]);
+/// assert_eq!(LcsMatch { a: (4, 7), b: (5, 8) }, lcs.matches[0]);
+/// assert_eq!(LcsMatch { a: (2, 3), b: (0, 1) }, lcs.matches[1]);
+/// ```
+pub fn longest_common_subsequence(a: &[u8], b: &[u8]) -> Subsequence {
+    let width = b.len() + 1;
+
+    // `table[i * width + j]`是`a[..i]`与`b[..j]`的最长公共子序列的长度
+    let mut table = vec![0u32; (a.len() + 1) * width];
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i * width + j] = if a[i - 1] == b[j - 1] {
+                table[(i - 1) * width + j - 1] + 1
+            } else {
+                table[(i - 1) * width + j].max(table[i * width + j - 1])
+            };
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(table[a.len() * width + b.len()] as usize);
+    let mut matches = vec![];
+    let mut current: Option<LcsMatch> = None;
+
+    // 从表的最后一格往回走，子序列和匹配都是从后往前得到的
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 && j > 0 {
+        if a[i - 1] == b[j - 1] {
+            bytes.push(a[i - 1]);
+
+            match &mut current {
+                // 与当前的匹配在两个字符串中都相邻，向前扩展
+                Some(run) if run.a.0 == i && run.b.0 == j => {
+                    run.a.0 -= 1;
+                    run.b.0 -= 1;
+                }
+                _ => {
+                    matches.extend(current.take());
+                    current = Some(LcsMatch {
+                        a: (i - 1, i - 1),
+                        b: (j - 1, j - 1),
+                    });
+                }
+            }
+
+            i -= 1;
+            j -= 1;
+        } else {
+            matches.extend(current.take());
+
+            // 与Redis一样，相等时优先缩短`b`
+            if table[(i - 1) * width + j] > table[i * width + j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+        }
+    }
+
+    matches.extend(current);
+    bytes.reverse();
+
+    Subsequence { bytes, matches }
+}
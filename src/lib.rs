@@ -1,5 +1,12 @@
 pub mod clients;
-pub use clients::{BlockingClient, BufferedClient, Client};
+pub use clients::{BufferedClient, Client};
+#[cfg(feature = "blocking")]
+pub use clients::BlockingClient;
+
+/// The `rustls` crate used by `Client::connect_tls`, to build its
+/// `ClientConfig` with the same version.
+#[cfg(feature = "tls")]
+pub use tokio_rustls::rustls;
 
 pub mod cmd;
 pub use cmd::Command;
@@ -13,13 +20,31 @@ pub use connection::Connection;
 pub mod shutdown;
 use shutdown::Shutdown;
 
+pub mod fanout;
+
+pub mod pubsub;
+pub use pubsub::PubSubReply;
+
 pub mod parse;
 use parse::{Parse, ParseError};
 
 pub mod db;
 use db::{Db, DbDropGuard};
 
+pub mod lcs;
+
+pub mod cluster;
+
+pub mod serialize;
+
+pub mod replication;
+
+pub mod logging;
+
 pub mod server;
+
+#[cfg(feature = "uring")]
+mod uring;
 /// Default port that a redis server listens on
 ///
 /// Used if no port is specified
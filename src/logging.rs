@@ -0,0 +1,107 @@
+//! Rate limiting of log lines.
+//!
+//! A port scanner, or a misbehaving load balancer health check, can make the
+//! server log thousands of connection errors per second, drowning the ones
+//! which matter. `RateLimitedLog` lets the first errors of each category
+//! through in every interval, and counts the others so that a single summary
+//! line can report them.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Per-category limiter of log lines.
+///
+/// At most `burst` events of each category are logged per `interval`. The
+/// events over the budget are suppressed and counted. The first event logged
+/// once the interval has elapsed carries the number of events suppressed
+/// before it, to be reported as a summary.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::logging::{Admission, RateLimitedLog};
+/// use std::time::Duration;
+///
+/// let log = RateLimitedLog::new(2, Duration::from_secs(10));
+///
+/// assert_eq!(Admission::Log { suppressed: 0 }, log.admit("protocol"));
+/// assert_eq!(Admission::Log { suppressed: 0 }, log.admit("protocol"));
+/// assert_eq!(Admission::Suppress, log.admit("protocol"));
+///
+/// // Each category has its own budget
+/// assert_eq!(Admission::Log { suppressed: 0 }, log.admit("io"));
+/// ```
+#[derive(Debug)]
+pub struct RateLimitedLog {
+    burst: u32,
+
+    interval: Duration,
+
+    windows: Mutex<HashMap<&'static str, Window>>,
+}
+
+/// What to do with an event submitted to `RateLimitedLog::admit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// Log the event. If `suppressed` is not zero, that many events of the
+    /// category were suppressed since the previous summary, and should be
+    /// reported first.
+    Log { suppressed: u64 },
+
+    /// Drop the event, it is counted in the next summary.
+    Suppress,
+}
+
+/// The events of a category in the current interval.
+#[derive(Debug)]
+struct Window {
+    started_at: Instant,
+
+    logged: u32,
+
+    suppressed: u64,
+}
+
+impl RateLimitedLog {
+    /// Create a limiter logging up to `burst` events of each category per
+    /// `interval`.
+    pub fn new(burst: u32, interval: Duration) -> RateLimitedLog {
+        RateLimitedLog {
+            burst,
+            interval,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records an event of `category`, and returns whether it should be
+    /// logged.
+    pub fn admit(&self, category: &'static str) -> Admission {
+        let mut windows = self.windows.lock().unwrap();
+        let now = Instant::now();
+
+        let window = windows.entry(category).or_insert(Window {
+            started_at: now,
+            logged: 0,
+            suppressed: 0,
+        });
+
+        // 新的时间窗口开始，重新计数。被抑制的数量保留到下一条被记录的日志
+        if now.duration_since(window.started_at) >= self.interval {
+            window.started_at = now;
+            window.logged = 0;
+        }
+
+        if window.logged >= self.burst {
+            window.suppressed += 1;
+            return Admission::Suppress;
+        }
+
+        window.logged += 1;
+
+        Admission::Log {
+            suppressed: std::mem::take(&mut window.suppressed),
+        }
+    }
+}
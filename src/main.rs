@@ -1,9 +1,10 @@
-mod frame;
-use my_mini_redis::Error;
+use my_mini_redis::frame;
+
 fn main() {
     let e: frame::Error = "string".into();
     match e {
         frame::Error::Incomplete => println!("aaa"),
+        frame::Error::TooLarge => println!("bbb"),
         frame::Error::Other(e) => println!("{e} 1111"),
     }
 }
@@ -1,6 +1,7 @@
 use crate::Frame;
 
 use bytes::Bytes;
+use std::cell::OnceCell;
 use std::{fmt, str, vec};
 
 /// Utility for parsing a command
@@ -13,6 +14,26 @@ use std::{fmt, str, vec};
 pub(crate) struct Parse {
     /// Array frame iterator
     parts: vec::IntoIter<Frame>,
+
+    /// Number of entries consumed, the name of the command included.
+    position: usize,
+}
+
+/// An entry of a command, kept as the bytes received.
+///
+/// Entries which are only compared, like the options of a command, are never
+/// required to be valid UTF-8. The others are validated when first viewed as
+/// a `str`, and the error names the position of the entry.
+#[derive(Debug)]
+pub(crate) struct Token {
+    bytes: Bytes,
+
+    /// Position of the entry in the command, the name of the command being 0.
+    position: usize,
+
+    /// The entry as a string, set by the first call to `as_str`. `None` if the
+    /// entry is not valid UTF-8.
+    utf8: OnceCell<Option<String>>,
 }
 
 /// Error encountered while parsing a frame
@@ -40,6 +61,7 @@ impl Parse {
 
         Ok(Parse {
             parts: array.into_iter(),
+            position: 0,
         })
     }
     /// Return the next entry. Array frame are array of frames, so the next
@@ -47,24 +69,40 @@ impl Parse {
     pub(crate) fn next(&mut self) -> Result<Frame, ParseError> {
         // ok_or()直接返回一个静态默认值。
         // ok_or_else()可以通过闭包产生默认值,支持更复杂的错误处理逻辑。
-        self.parts.next().ok_or(ParseError::EndOfStream)
+        let frame = self.parts.next().ok_or(ParseError::EndOfStream)?;
+        self.position += 1;
+        Ok(frame)
+    }
+
+    /// Return the next entry as a `Token`, without checking it is valid UTF-8.
+    ///
+    /// If the next entry is not a `Simple` or `Bulk` frame, an error is
+    /// returned.
+    pub(crate) fn next_token(&mut self) -> Result<Token, ParseError> {
+        let bytes = match self.next()? {
+            Frame::Simple(s) => Bytes::from(s.into_bytes()),
+            Frame::Bulk(data) => data,
+            other => {
+                return Err(format!(
+                    "protocol error: expected simple frame or bulk frame, get {:?}",
+                    other
+                )
+                .into())
+            }
+        };
+
+        Ok(Token {
+            bytes,
+            position: self.position - 1,
+            utf8: OnceCell::new(),
+        })
     }
 
     /// Return the entry as a string
     ///
     /// If the next entry cannot be represented as a String, then an error is returned.
     pub(crate) fn next_string(&mut self) -> Result<String, ParseError> {
-        match self.next()? {
-            Frame::Simple(s) => Ok(s),
-            Frame::Bulk(data) => str::from_utf8(&data[..])
-                .map(|s| s.to_string())
-                .map_err(|_| "protocol error: invalid string".into()),
-            other => Err(format!(
-                "protocol error: expected simple frame or bulk frame, get {:?}",
-                other
-            )
-            .into()),
-        }
+        self.next_token()?.into_string()
     }
 
     /// Return the next entry as raw bytes.
@@ -87,34 +125,95 @@ impl Parse {
 
     /// Return the next entry as an integer.
     ///
-    /// This include `Simple`, `Bulk` and `Integer` frame types.
-    /// `Simple` and `Bulk` frame types are parsed.
-    ///
-    /// if the next entry cannot be represented as an integer, then an error is returned
+    /// Like `next_int_signed`, but negative values are rejected as well.
     pub(crate) fn next_int(&mut self) -> Result<u64, ParseError> {
-        use atoi::atoi;
-        match self.next()? {
-            Frame::Simple(s) => {
-                atoi::<u64>(s.as_bytes()).ok_or_else(|| "protocol error: invalid number".into())
-            }
-            Frame::Bulk(data) => {
-                atoi::<u64>(&data).ok_or_else(|| "protocol error: invalid number".into())
-            }
-            Frame::Integer(num) => Ok(num),
-            other => Err(format!("protocol error; expected int frame but got {:?}", other).into()),
-        }
+        let num = match self.next()? {
+            Frame::Integer(num) => Some(num),
+            Frame::Simple(s) => canonical_int(s.as_bytes()),
+            Frame::Bulk(data) => canonical_int(&data),
+            _ => None,
+        };
+
+        num.ok_or_else(invalid_int)
+    }
+
+    /// Return the next entry as a signed integer.
+    ///
+    /// This include `Simple`, `Bulk` and `Integer` frame types. `Simple` and
+    /// `Bulk` frame types are parsed strictly, as Redis does: only the
+    /// canonical decimal form is accepted, so `12abc`, `+12` and `012` are
+    /// rejected.
+    ///
+    /// If the next entry cannot be represented as an integer, the error is
+    /// the `ERR` reply of Redis, which the server sends back to the client.
+    pub(crate) fn next_int_signed(&mut self) -> Result<i64, ParseError> {
+        int_from_frame(self.next()?).ok_or_else(invalid_int)
+    }
+
+    /// Return the next entry as a float.
+    ///
+    /// `Simple` and `Bulk` frame types are parsed with `f64::from_str`, so
+    /// `inf` and `nan` are accepted; commands which do not support them must
+    /// reject them.
+    ///
+    /// If the next entry cannot be represented as a float, the error is the
+    /// `ERR` reply of Redis.
+    pub(crate) fn next_float(&mut self) -> Result<f64, ParseError> {
+        let parsed = match self.next()? {
+            Frame::Simple(s) => s.parse::<f64>().ok(),
+            Frame::Bulk(data) => str::from_utf8(&data).ok().and_then(|s| s.parse::<f64>().ok()),
+            _ => None,
+        };
+
+        parsed.ok_or_else(|| "ERR value is not a valid float".into())
     }
 
     /// Ensure there are no more entries in the array
     pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
         if self.parts.next().is_none() {
-            return Ok(());
+            Ok(())
         } else {
             Err("protocol error; expected end of frame, but there was more".into())
         }
     }
 }
 
+impl Token {
+    /// Returns the bytes of the entry.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the entry as a `str`, or an error naming the entry if it is
+    /// not valid UTF-8. The entry is only validated once.
+    pub(crate) fn as_str(&self) -> Result<&str, ParseError> {
+        self.utf8
+            .get_or_init(|| str::from_utf8(&self.bytes).ok().map(str::to_string))
+            .as_deref()
+            .ok_or_else(|| invalid_utf8(self.position))
+    }
+
+    /// Converts the entry into a `String`, see `as_str`.
+    pub(crate) fn into_string(self) -> Result<String, ParseError> {
+        let utf8 = match self.utf8.into_inner() {
+            Some(utf8) => utf8,
+            None => String::from_utf8(Vec::from(self.bytes)).ok(),
+        };
+
+        utf8.ok_or_else(|| invalid_utf8(self.position))
+    }
+
+    /// Returns true if the entry is `keyword`, ignoring ASCII case.
+    pub(crate) fn is(&self, keyword: &str) -> bool {
+        self.bytes.eq_ignore_ascii_case(keyword.as_bytes())
+    }
+}
+
+/// Returns the error for an entry at `position` which is not valid UTF-8.
+fn invalid_utf8(position: usize) -> ParseError {
+    format!("protocol error: argument {} is not valid UTF-8", position).into()
+}
+
 impl From<String> for ParseError {
     fn from(src: String) -> ParseError {
         ParseError::Other(src.into())
@@ -127,6 +226,27 @@ impl From<&str> for ParseError {
     }
 }
 
+fn int_from_frame(frame: Frame) -> Option<i64> {
+    match frame {
+        Frame::Integer(num) => i64::try_from(num).ok(),
+        Frame::SignedInteger(num) => Some(num),
+        Frame::Simple(s) => canonical_int(s.as_bytes()),
+        Frame::Bulk(data) => canonical_int(&data),
+        _ => None,
+    }
+}
+
+/// 整数只接受规范的形式："12"可以，"012"、"+12"、"-0"和"12abc"都不行
+fn canonical_int<T: str::FromStr + ToString>(data: &[u8]) -> Option<T> {
+    let s = str::from_utf8(data).ok()?;
+    let num = s.parse::<T>().ok()?;
+    (num.to_string() == s).then_some(num)
+}
+
+fn invalid_int() -> ParseError {
+    "ERR value is not an integer or out of range".into()
+}
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
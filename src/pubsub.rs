@@ -0,0 +1,365 @@
+//! Wire shapes of the pub/sub replies.
+//!
+//! The server pushes these frames from `cmd::subscribe` and the client parses
+//! them back in `clients::Subscriber`. Both sides go through `PubSubReply` so
+//! each shape is defined exactly once. The replies of `XSUBSCRIBE`, sent from
+//! `cmd::stream` and parsed by `clients::StreamSubscriber`, share them.
+
+use crate::cmd::{fields_from_frame, fields_to_frame, StreamId};
+use crate::Frame;
+
+use bytes::Bytes;
+use std::str::FromStr;
+
+/// A reply sent by the server while a connection is in pub/sub mode.
+///
+/// Every reply is encoded as an array frame whose first entry names the kind
+/// of reply:
+///
+/// ```text
+/// [ "subscribe", channel, num-subscribed ]
+/// [ "unsubscribe", channel, num-subscribed ]
+/// [ "psubscribe", pattern, num-subscribed ]
+/// [ "punsubscribe", pattern, num-subscribed ]
+/// [ "message", channel, payload ]
+/// [ "pmessage", pattern, channel, payload ]
+/// [ "smessage", channel, payload ]
+/// [ "lagged", channel, num-skipped ]
+/// [ "smeta", channel, num-published, truncated ]
+/// [ "pong", payload ]
+/// [ "xsubscribe", key, last-id ]
+/// [ "xmessage", key, id, [ field, value, ... ] ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PubSubReply {
+    /// Confirms a subscription to `channel`.
+    Subscribe { channel: String, num_subs: u64 },
+
+    /// Confirms the removal of the subscription to `channel`.
+    Unsubscribe { channel: String, num_subs: u64 },
+
+    /// Confirms a subscription to the channels matching `pattern`.
+    PSubscribe { pattern: String, num_subs: u64 },
+
+    /// Confirms the removal of the subscription to `pattern`.
+    PUnsubscribe { pattern: String, num_subs: u64 },
+
+    /// A message published on `channel`.
+    Message { channel: String, content: Bytes },
+
+    /// A message published on `channel`, received through `pattern`.
+    PMessage {
+        pattern: String,
+        channel: String,
+        content: Bytes,
+    },
+
+    /// A message published on the shard channel `channel`.
+    SMessage { channel: String, content: Bytes },
+
+    /// The subscriber fell behind and `skipped` messages on `channel` were
+    /// dropped.
+    Lagged { channel: String, skipped: u64 },
+
+    /// Sent after the confirmation of a `SUBSCRIBE ... SINCE` subscription:
+    /// `published` messages were published on `channel` since the timestamp.
+    /// When `truncated` is set, the server did not remember all the messages
+    /// and `published` is a lower bound.
+    Backlog {
+        channel: String,
+        published: u64,
+        truncated: bool,
+    },
+
+    /// Reply to a `PING` received in pub/sub mode over RESP2. `content` is the
+    /// message of the `PING`, empty when it had none.
+    Pong { content: Bytes },
+
+    /// Confirms an `XSUBSCRIBE` to the stream `key`: its entries are sent from
+    /// the one after `last_id` on.
+    XSubscribe { key: String, last_id: StreamId },
+
+    /// An entry of the stream `key` sent to an `XSUBSCRIBE`, whether it was
+    /// added before or after the subscription.
+    XMessage {
+        key: String,
+        id: StreamId,
+        fields: Vec<(String, Bytes)>,
+    },
+}
+
+/// How strictly pub/sub requests and replies are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Replies must have exactly the number of entries of their kind, and
+    /// invalid channel names are rejected before being sent.
+    Strict,
+
+    /// Trailing entries after the expected ones are ignored, and channel names
+    /// are left for the server to validate. This is the behavior the client
+    /// has always had.
+    #[default]
+    Lenient,
+}
+
+impl Strictness {
+    /// Checks `channel` before it is sent in a `SUBSCRIBE` request.
+    ///
+    /// Only `Strict` performs the check. The server uses the same setting,
+    /// `Strict` unless `Config::pubsub_strictness` says otherwise, see
+    /// `check_channel_name`.
+    pub fn check_channel(self, channel: &str) -> crate::Result<()> {
+        match self {
+            Strictness::Strict => check_channel_name(channel),
+            Strictness::Lenient => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Strictness {
+    type Err = crate::Error;
+
+    /// Parse the strictness, `strict` or `lenient`.
+    fn from_str(s: &str) -> crate::Result<Strictness> {
+        match &s.to_lowercase()[..] {
+            "strict" => Ok(Strictness::Strict),
+            "lenient" => Ok(Strictness::Lenient),
+            _ => Err(format!("invalid pub/sub strictness '{}'", s).into()),
+        }
+    }
+}
+
+/// Checks that `channel` is a valid channel name.
+///
+/// Subscribing to an empty channel name is almost certainly a client bug, as
+/// nothing can meaningfully be published on it.
+pub fn check_channel_name(channel: &str) -> crate::Result<()> {
+    if channel.is_empty() {
+        return Err("ERR channel name must not be empty".into());
+    }
+
+    Ok(())
+}
+
+impl PubSubReply {
+    /// Converts the reply into the frame written to the socket.
+    ///
+    /// `self` is consumed since `Bytes::from` can reuse the allocation of the
+    /// `String` names, while taking `&self` would require copying the data.
+    pub fn to_frame(self) -> Frame {
+        let mut frame = Frame::array();
+
+        match self {
+            PubSubReply::Subscribe { channel, num_subs } => {
+                frame.push_bulk(Bytes::from_static(b"subscribe"));
+                frame.push_bulk(Bytes::from(channel));
+                frame.push_int(num_subs);
+            }
+            PubSubReply::Unsubscribe { channel, num_subs } => {
+                frame.push_bulk(Bytes::from_static(b"unsubscribe"));
+                frame.push_bulk(Bytes::from(channel));
+                frame.push_int(num_subs);
+            }
+            PubSubReply::PSubscribe { pattern, num_subs } => {
+                frame.push_bulk(Bytes::from_static(b"psubscribe"));
+                frame.push_bulk(Bytes::from(pattern));
+                frame.push_int(num_subs);
+            }
+            PubSubReply::PUnsubscribe { pattern, num_subs } => {
+                frame.push_bulk(Bytes::from_static(b"punsubscribe"));
+                frame.push_bulk(Bytes::from(pattern));
+                frame.push_int(num_subs);
+            }
+            PubSubReply::Message { channel, content } => {
+                frame.push_bulk(Bytes::from_static(b"message"));
+                frame.push_bulk(Bytes::from(channel));
+                frame.push_bulk(content);
+            }
+            PubSubReply::PMessage {
+                pattern,
+                channel,
+                content,
+            } => {
+                frame.push_bulk(Bytes::from_static(b"pmessage"));
+                frame.push_bulk(Bytes::from(pattern));
+                frame.push_bulk(Bytes::from(channel));
+                frame.push_bulk(content);
+            }
+            PubSubReply::SMessage { channel, content } => {
+                frame.push_bulk(Bytes::from_static(b"smessage"));
+                frame.push_bulk(Bytes::from(channel));
+                frame.push_bulk(content);
+            }
+            PubSubReply::Lagged { channel, skipped } => {
+                frame.push_bulk(Bytes::from_static(b"lagged"));
+                frame.push_bulk(Bytes::from(channel));
+                frame.push_int(skipped);
+            }
+            PubSubReply::Backlog {
+                channel,
+                published,
+                truncated,
+            } => {
+                frame.push_bulk(Bytes::from_static(b"smeta"));
+                frame.push_bulk(Bytes::from(channel));
+                frame.push_int(published);
+                frame.push_int(truncated as u64);
+            }
+            PubSubReply::Pong { content } => {
+                frame.push_bulk(Bytes::from_static(b"pong"));
+                frame.push_bulk(content);
+            }
+            PubSubReply::XSubscribe { key, last_id } => {
+                frame.push_bulk(Bytes::from_static(b"xsubscribe"));
+                frame.push_bulk(Bytes::from(key));
+                frame.push_bulk(Bytes::from(last_id.to_string()));
+            }
+            PubSubReply::XMessage { key, id, fields } => {
+                frame.push_bulk(Bytes::from_static(b"xmessage"));
+                frame.push_bulk(Bytes::from(key));
+                frame.push_bulk(Bytes::from(id.to_string()));
+                frame.push_frame(fields_to_frame(fields));
+            }
+        }
+
+        frame
+    }
+
+    /// Parses a reply from `frame`, requiring the exact shape.
+    ///
+    /// Returns `Err` if the frame is not one of the pub/sub reply shapes.
+    pub fn try_from_frame(frame: &Frame) -> crate::Result<PubSubReply> {
+        PubSubReply::try_from_frame_with(frame, Strictness::Strict)
+    }
+
+    /// Parses a reply from `frame`, checking its shape according to
+    /// `strictness`.
+    pub fn try_from_frame_with(
+        frame: &Frame,
+        strictness: Strictness,
+    ) -> crate::Result<PubSubReply> {
+        let parts = match frame {
+            Frame::Array(parts) => parts.as_slice(),
+            frame => return Err(frame.to_error()),
+        };
+
+        let kind = match parts.first() {
+            Some(kind) => kind,
+            None => return Err(frame.to_error()),
+        };
+
+        // 每种回复的entry个数是固定的，包含第一个表示类型的entry
+        let expected = if *kind == "pmessage" || *kind == "smeta" || *kind == "xmessage" {
+            4
+        } else if *kind == "pong" {
+            2
+        } else {
+            3
+        };
+
+        let shape_ok = match strictness {
+            Strictness::Strict => parts.len() == expected,
+            Strictness::Lenient => parts.len() >= expected,
+        };
+
+        if !shape_ok {
+            return Err(frame.to_error());
+        }
+
+        let reply = if *kind == "subscribe" {
+            PubSubReply::Subscribe {
+                channel: to_string(&parts[1])?,
+                num_subs: to_int(&parts[2])?,
+            }
+        } else if *kind == "unsubscribe" {
+            PubSubReply::Unsubscribe {
+                channel: to_string(&parts[1])?,
+                num_subs: to_int(&parts[2])?,
+            }
+        } else if *kind == "psubscribe" {
+            PubSubReply::PSubscribe {
+                pattern: to_string(&parts[1])?,
+                num_subs: to_int(&parts[2])?,
+            }
+        } else if *kind == "punsubscribe" {
+            PubSubReply::PUnsubscribe {
+                pattern: to_string(&parts[1])?,
+                num_subs: to_int(&parts[2])?,
+            }
+        } else if *kind == "message" {
+            PubSubReply::Message {
+                channel: to_string(&parts[1])?,
+                content: to_bytes(&parts[2])?,
+            }
+        } else if *kind == "pmessage" {
+            PubSubReply::PMessage {
+                pattern: to_string(&parts[1])?,
+                channel: to_string(&parts[2])?,
+                content: to_bytes(&parts[3])?,
+            }
+        } else if *kind == "smessage" {
+            PubSubReply::SMessage {
+                channel: to_string(&parts[1])?,
+                content: to_bytes(&parts[2])?,
+            }
+        } else if *kind == "lagged" {
+            PubSubReply::Lagged {
+                channel: to_string(&parts[1])?,
+                skipped: to_int(&parts[2])?,
+            }
+        } else if *kind == "smeta" {
+            PubSubReply::Backlog {
+                channel: to_string(&parts[1])?,
+                published: to_int(&parts[2])?,
+                truncated: to_int(&parts[3])? != 0,
+            }
+        } else if *kind == "pong" {
+            PubSubReply::Pong {
+                content: to_bytes(&parts[1])?,
+            }
+        } else if *kind == "xsubscribe" {
+            PubSubReply::XSubscribe {
+                key: to_string(&parts[1])?,
+                last_id: to_string(&parts[2])?.parse()?,
+            }
+        } else if *kind == "xmessage" {
+            PubSubReply::XMessage {
+                key: to_string(&parts[1])?,
+                id: to_string(&parts[2])?.parse()?,
+                fields: fields_from_frame(&parts[3])?,
+            }
+        } else {
+            return Err(frame.to_error());
+        };
+
+        Ok(reply)
+    }
+}
+
+/// Extracts a channel or pattern name from a `Simple` or `Bulk` entry.
+fn to_string(frame: &Frame) -> crate::Result<String> {
+    match frame {
+        Frame::Simple(s) => Ok(s.clone()),
+        Frame::Bulk(data) => std::str::from_utf8(data)
+            .map(|s| s.to_string())
+            .map_err(|_| "protocol error; invalid channel name".into()),
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Extracts a payload from a `Simple` or `Bulk` entry.
+fn to_bytes(frame: &Frame) -> crate::Result<Bytes> {
+    match frame {
+        Frame::Simple(s) => Ok(Bytes::from(s.clone())),
+        Frame::Bulk(data) => Ok(data.clone()),
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Extracts a counter from an `Integer` entry.
+fn to_int(frame: &Frame) -> crate::Result<u64> {
+    match frame {
+        Frame::Integer(num) => Ok(*num),
+        frame => Err(frame.to_error()),
+    }
+}
@@ -0,0 +1,169 @@
+//! Replication of the keyspace to a Redis replica.
+//!
+//! A real Redis server started with `--replicaof` the address of a mini-redis
+//! server performs a full synchronization with it, see `PSYNC`: it receives a
+//! snapshot of the keyspace in the RDB format, then every change made
+//! afterwards, as the commands leaving it in the same state. This lets a
+//! dataset be migrated off mini-redis without downtime.
+//!
+//! Only string values are supported. The RDB payload written by `encode_rdb`
+//! is the smallest one Redis loads: the header, a single database, the string
+//! entries with their expiration, and the checksum.
+//!
+//! The changes are sent as the state the key is left in rather than as the
+//! command received: `SET` with `PXAT` when the key holds a string, `DEL`
+//! when it is gone. They are queued under the lock of the `Db`, so the
+//! replica applies them in the order they were made, whatever command made
+//! them, expirations included.
+
+use crate::serialize::crc64;
+use crate::Frame;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Version of the RDB format written by `encode_rdb`, loaded by Redis 5.0 and
+/// later.
+pub const RDB_VERSION: u16 = 9;
+
+/// Number of changes a replica may have pending before it is disconnected.
+/// A replica missing changes would diverge from the primary.
+pub(crate) const REPLICA_QUEUE_CAPACITY: usize = 64 * 1024;
+
+const OPCODE_EXPIRETIME_MS: u8 = 0xfc;
+const OPCODE_RESIZEDB: u8 = 0xfb;
+const OPCODE_SELECTDB: u8 = 0xfe;
+const OPCODE_EOF: u8 = 0xff;
+
+const TYPE_STRING: u8 = 0;
+
+/// A string entry of the keyspace, as written to the RDB payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotEntry {
+    pub key: String,
+
+    pub value: Bytes,
+
+    /// Unix timestamp, in milliseconds, at which the entry expires.
+    pub expires_at: Option<u64>,
+}
+
+/// A change of the keyspace, queued for the replicas.
+#[derive(Debug, Clone)]
+pub(crate) enum Change {
+    /// The command making the same change on the replica.
+    Command(Frame),
+
+    /// A change which cannot be replicated, with the reason. The replica is
+    /// disconnected, as it would diverge from the primary.
+    Unsupported(String),
+}
+
+impl Change {
+    /// `key` now holds `value`, until `expires_at`, a Unix timestamp in
+    /// milliseconds.
+    pub(crate) fn set(key: &str, value: Bytes, expires_at: Option<u64>) -> Change {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"SET"));
+        frame.push_bulk(Bytes::copy_from_slice(key.as_bytes()));
+        frame.push_bulk(value);
+        if let Some(expires_at) = expires_at {
+            frame.push_bulk(Bytes::from_static(b"PXAT"));
+            frame.push_bulk(Bytes::from(expires_at.to_string()));
+        }
+        Change::Command(frame)
+    }
+
+    /// `key` no longer exists.
+    pub(crate) fn del(key: &str) -> Change {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"DEL"));
+        frame.push_bulk(Bytes::copy_from_slice(key.as_bytes()));
+        Change::Command(frame)
+    }
+
+    /// All the keys were removed.
+    pub(crate) fn flush() -> Change {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"FLUSHDB"));
+        Change::Command(frame)
+    }
+
+    /// `key` now holds a value of type `type_name`, which is not replicated.
+    pub(crate) fn unsupported(key: &str, type_name: &str) -> Change {
+        Change::Unsupported(format!("key '{}' holds a {}, only strings can be replicated", key, type_name))
+    }
+}
+
+/// Returns the RDB payload of a keyspace holding `entries`, in database 0.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::replication::{encode_rdb, SnapshotEntry};
+///
+/// let entry = SnapshotEntry {
+///     key: "foo".to_string(),
+///     value: "bar".into(),
+///     expires_at: None,
+/// };
+///
+/// let payload = encode_rdb(&[entry]);
+/// assert!(payload.starts_with(b"REDIS0009"));
+/// ```
+pub fn encode_rdb(entries: &[SnapshotEntry]) -> Bytes {
+    let size: usize = entries.iter().map(|entry| entry.key.len() + entry.value.len() + 24).sum();
+    let mut payload = BytesMut::with_capacity(size + 32);
+
+    payload.put_slice(format!("REDIS{:04}", RDB_VERSION).as_bytes());
+
+    payload.put_u8(OPCODE_SELECTDB);
+    put_length(&mut payload, 0);
+
+    // 只是让Redis预先分配空间，不影响加载的结果
+    let expires = entries.iter().filter(|entry| entry.expires_at.is_some()).count();
+    payload.put_u8(OPCODE_RESIZEDB);
+    put_length(&mut payload, entries.len() as u64);
+    put_length(&mut payload, expires as u64);
+
+    for entry in entries {
+        if let Some(expires_at) = entry.expires_at {
+            payload.put_u8(OPCODE_EXPIRETIME_MS);
+            payload.put_u64_le(expires_at);
+        }
+
+        payload.put_u8(TYPE_STRING);
+        put_string(&mut payload, entry.key.as_bytes());
+        put_string(&mut payload, &entry.value);
+    }
+
+    payload.put_u8(OPCODE_EOF);
+
+    // 校验和覆盖之前所有的字节，包括EOF
+    let crc = crc64(&payload);
+    payload.put_u64_le(crc);
+
+    payload.freeze()
+}
+
+/// Writes a length in the encoding of the RDB format: 6 bits, 14 bits, or
+/// 32 or 64 bits after a marker byte, big-endian.
+fn put_length(dst: &mut BytesMut, len: u64) {
+    if len < 1 << 6 {
+        dst.put_u8(len as u8);
+    } else if len < 1 << 14 {
+        dst.put_u16(0x4000 | len as u16);
+    } else if len <= u32::MAX as u64 {
+        dst.put_u8(0x80);
+        dst.put_u32(len as u32);
+    } else {
+        dst.put_u8(0x81);
+        dst.put_u64(len);
+    }
+}
+
+/// Writes a string as its length followed by its bytes, never using the
+/// integer or compressed encodings.
+fn put_string(dst: &mut BytesMut, data: &[u8]) {
+    put_length(dst, data.len() as u64);
+    dst.put_slice(data);
+}
@@ -0,0 +1,151 @@
+//! Serialization of values for `DUMP` and `RESTORE`.
+//!
+//! The payload is not compatible with the RDB format of Redis, it only has to
+//! be understood by another mini-redis server. It is laid out like the RDB
+//! payloads though, with the version and a checksum at the end:
+//!
+//! ```text
+//! +------+-------+---------+--------+
+//! | type | value | version | CRC64  |
+//! | 1    | n     | 2, LE   | 8, LE  |
+//! +------+-------+---------+--------+
+//! ```
+//!
+//! The checksum covers all the bytes before it, so a truncated or corrupted
+//! payload is rejected rather than restored as a different value.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::fmt;
+
+/// Version of the payloads written by `encode`. Payloads of other versions
+/// are rejected by `decode`.
+pub const DUMP_VERSION: u16 = 1;
+
+/// Type byte of a string value, the only type the server stores.
+const TYPE_STRING: u8 = 0;
+
+/// Number of bytes added to the value: the type, the version and the
+/// checksum.
+const OVERHEAD: usize = 1 + 2 + 8;
+
+/// Lookup table of the CRC64 variant used by Redis (Jones: reflected
+/// polynomial `0x95ac9329ac4bc9b5`, initial value `0`), indexed by the low
+/// byte of the CRC xor the next input byte.
+const CRC64_TABLE: [u64; 256] = crc64_table();
+
+const fn crc64_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x95ac9329ac4bc9b5 } else { crc >> 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// Errors returned by `decode` for a payload which is not the output of
+/// `encode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The payload is too short to hold the type, version and checksum.
+    Truncated,
+
+    /// The checksum does not match the payload, which was corrupted.
+    Checksum,
+
+    /// The payload was written by another version of the format.
+    Version(u16),
+
+    /// The type of the value is unknown.
+    UnknownType(u8),
+}
+
+/// Computes the CRC64 of `data` used by Redis.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::serialize::crc64;
+///
+/// // The reference value of Redis
+/// assert_eq!(0xe9c6d914c4b8d9ca, crc64(b"123456789"));
+/// ```
+pub fn crc64(data: &[u8]) -> u64 {
+    data.iter().fold(0u64, |crc, &byte| {
+        CRC64_TABLE[((crc as u8) ^ byte) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Serializes the string `value` into a payload `decode` turns back into it.
+///
+/// # Examples
+///
+/// ```
+/// use my_mini_redis::serialize::{decode, encode};
+///
+/// let payload = encode(b"hello");
+/// assert_eq!(&b"hello"[..], &decode(payload).unwrap()[..]);
+/// ```
+pub fn encode(value: &[u8]) -> Bytes {
+    let mut payload = BytesMut::with_capacity(value.len() + OVERHEAD);
+
+    payload.put_u8(TYPE_STRING);
+    payload.put_slice(value);
+    payload.put_u16_le(DUMP_VERSION);
+
+    let crc = crc64(&payload);
+    payload.put_u64_le(crc);
+
+    payload.freeze()
+}
+
+/// Returns the value serialized in `payload` by `encode`.
+///
+/// The value shares the memory of `payload`. The checksum is verified before
+/// anything else is read, so any corruption is reported as
+/// `DecodeError::Checksum` rather than as an unknown type or version.
+pub fn decode(payload: Bytes) -> Result<Bytes, DecodeError> {
+    if payload.len() < OVERHEAD {
+        return Err(DecodeError::Truncated);
+    }
+
+    let (data, crc) = payload.split_at(payload.len() - 8);
+    if crc64(data) != u64::from_le_bytes(crc.try_into().unwrap()) {
+        return Err(DecodeError::Checksum);
+    }
+
+    let (_, version) = data.split_at(data.len() - 2);
+    let version = u16::from_le_bytes(version.try_into().unwrap());
+    if version != DUMP_VERSION {
+        return Err(DecodeError::Version(version));
+    }
+
+    match payload[0] {
+        TYPE_STRING => Ok(payload.slice(1..payload.len() - 10)),
+        kind => Err(DecodeError::UnknownType(kind)),
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        // 和Redis一样，版本和校验和的错误使用同一条消息
+        match self {
+            DecodeError::Checksum | DecodeError::Version(_) => {
+                "ERR DUMP payload version or checksum are wrong".fmt(fmt)
+            }
+            DecodeError::Truncated | DecodeError::UnknownType(_) => "ERR Bad data format".fmt(fmt),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
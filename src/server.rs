@@ -1,16 +1,193 @@
 //! Minimal Redis server implementation
 //! 
 //! Provides an async `run` function that listens for inbound connections,
-//! spwaning a task per connection.
-
-use crate::{Command, Connection, Db, DbDropGuard, Shutdown};
-
+//! spawning a task per connection.
+
+use crate::cmd::Exec;
+use crate::connection::{Transport, DEFAULT_MAX_READ_BUFFER_CAPACITY, DEFAULT_READ_BUFFER_CAPACITY};
+use crate::db::{CounterOverflow, KeyspaceEvents, DEFAULT_HISTOGRAM_SLICE, DEFAULT_SERVER_NAME, DEFAULT_SERVER_VERSION};
+use crate::fanout::{self, Overflow};
+use crate::frame::{self, DEFAULT_MAX_FRAME_LEN};
+use crate::logging::{Admission, RateLimitedLog};
+use crate::pubsub::Strictness;
+use crate::{Command, Connection, Db, DbDropGuard, Frame, ParseError, Shutdown};
+
+use std::fmt;
 use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, Semaphore};
-use tokio::time::{self, Duration};
-use tracing::{debug, error, info, instrument};
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, error, info, instrument, warn};
+
+/// Server configuration, passed to `run_with_config`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Maximum number of concurrent connections.
+    ///
+    /// When this limit is reached, the server stops accepting connections
+    /// until an active connection terminates. Defaults to 250.
+    pub max_connections: usize,
+
+    /// Period over which the connection limit grows from
+    /// `initial_connections` to `max_connections` after the server starts.
+    ///
+    /// A freshly started server, e.g. after a restart, may otherwise be hit by
+    /// all its clients reconnecting at once. The connections over the limit
+    /// wait to be accepted, as they do when `max_connections` is reached.
+    /// `None`, the default, applies `max_connections` right away.
+    pub connection_warm_up: Option<Duration>,
+
+    /// Connection limit right after the server starts, when
+    /// `connection_warm_up` is set. Defaults to 10.
+    pub initial_connections: usize,
+
+    /// Maximum wall-clock time a single command may take to execute.
+    ///
+    /// When a command exceeds it, the client receives a `TIMEOUT` error and
+    /// the connection is closed, as the command may have been partially
+    /// applied. Commands managing their own lifetime, like `SUBSCRIBE`, are
+    /// exempt. `None` disables the deadline.
+    pub command_deadline: Option<Duration>,
+
+    /// Classes of keyspace events published to the subscribers, parsed from
+    /// the Redis `notify-keyspace-events` syntax. Disabled by default.
+    pub notify_keyspace_events: KeyspaceEvents,
+
+    /// Behavior of `INCR` and friends when the result overflows an `i64`.
+    /// Defaults to replying with an error, as Redis does.
+    pub counter_overflow: CounterOverflow,
+
+    /// Number of messages a pub/sub subscriber may have pending, published
+    /// but not yet written to its connection. Defaults to
+    /// `fanout::DEFAULT_CAPACITY`, 1024.
+    pub pubsub_queue_capacity: usize,
+
+    /// What happens to a message published to a subscriber whose queue is
+    /// full. Defaults to dropping its oldest pending message.
+    ///
+    /// With `Overflow::Disconnect`, the subscriber receives an error and its
+    /// connection is closed.
+    pub pubsub_overflow: Overflow,
+
+    /// Maximum number of channels a subscribed client may subscribe to or
+    /// unsubscribe from per second.
+    ///
+    /// `SUBSCRIBE` and `UNSUBSCRIBE` commands over this budget receive an
+    /// error, without closing the connection. The channels of the command
+    /// entering the subscribed state are not counted. `None`, the default,
+    /// disables the limit.
+    pub max_subscribe_churn: Option<u32>,
+
+    /// Whether the channel names of `SUBSCRIBE` are checked, see
+    /// `Strictness::check_channel`. Defaults to `Strictness::Strict`, which
+    /// replies with an error to a subscription to an empty channel name.
+    pub pubsub_strictness: Strictness,
+
+    /// Initial capacity, in bytes, of the read buffer of each connection.
+    ///
+    /// Defaults to `connection::DEFAULT_READ_BUFFER_CAPACITY`, 4KB.
+    pub read_buffer_capacity: usize,
+
+    /// Capacity, in bytes, the read buffer of a connection may grow to when
+    /// reads keep filling it.
+    ///
+    /// Defaults to `connection::DEFAULT_MAX_READ_BUFFER_CAPACITY`, 64KB.
+    pub max_read_buffer_capacity: usize,
+
+    /// Maximum length, in bytes, of a bulk string sent by a client, also
+    /// bounding the number of elements of an array.
+    ///
+    /// A client declaring a larger length is disconnected before the frame is
+    /// buffered. Defaults to `frame::DEFAULT_MAX_FRAME_LEN`, 512MB.
+    pub max_frame_len: usize,
+
+    /// Time after which a connection waiting for a command is closed.
+    ///
+    /// Closing idle connections releases their slot, see `max_connections`.
+    /// Subscribed clients are never considered idle, as they wait for
+    /// messages rather than commands. `None`, the default, keeps idle
+    /// connections open.
+    pub idle_timeout: Option<Duration>,
+
+    /// Time after which writing a reply to a client is abandoned.
+    ///
+    /// A client which stops reading its replies is considered dead once a
+    /// write takes longer, and its connection is closed instead of staying
+    /// blocked forever. The same bound applies to flushing the last replies
+    /// when a client half-closes its connection. `None`, the default, waits
+    /// indefinitely.
+    pub write_timeout: Option<Duration>,
+
+    /// Whether hot key tracking, reported by `HOTKEYS`, starts enabled. It can
+    /// be toggled at runtime with `CONFIG SET hotkeys`. Disabled by default.
+    pub hotkeys: bool,
+
+    /// Maximum time `MEMORY HISTOGRAM` may hold the lock of the database for
+    /// at once, before letting other commands run. Defaults to 1ms.
+    pub memory_histogram_slice: Duration,
+
+    /// Server name reported by `HELLO`. Defaults to the name of the crate.
+    pub server_name: String,
+
+    /// Server version reported by `HELLO`. Defaults to the version of the
+    /// crate.
+    ///
+    /// Reporting the version of a given Redis release lets clients which
+    /// check it be tested against this server.
+    pub server_version: String,
+
+    /// Pub/sub bridges started with the server, as `(src, dst)` pairs: every
+    /// message published on `src` is republished on `dst`. See `Db::bridge`.
+    /// Empty by default.
+    pub bridges: Vec<(String, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            max_connections: MAX_CONNECTIONS,
+            connection_warm_up: None,
+            initial_connections: INITIAL_CONNECTIONS,
+            command_deadline: None,
+            notify_keyspace_events: KeyspaceEvents::default(),
+            counter_overflow: CounterOverflow::default(),
+            pubsub_queue_capacity: fanout::DEFAULT_CAPACITY,
+            pubsub_overflow: Overflow::default(),
+            max_subscribe_churn: None,
+            pubsub_strictness: Strictness::Strict,
+            read_buffer_capacity: DEFAULT_READ_BUFFER_CAPACITY,
+            max_read_buffer_capacity: DEFAULT_MAX_READ_BUFFER_CAPACITY,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            idle_timeout: None,
+            write_timeout: None,
+            hotkeys: false,
+            memory_histogram_slice: DEFAULT_HISTOGRAM_SLICE,
+            server_name: DEFAULT_SERVER_NAME.to_string(),
+            server_version: DEFAULT_SERVER_VERSION.to_string(),
+            bridges: vec![],
+        }
+    }
+}
+
+/// Statistics about the server, shared by the listener and all the handlers.
+///
+/// Reported to the clients by the `HEALTH` command.
+#[derive(Debug)]
+pub(crate) struct Stats {
+    /// Instant at which the server started.
+    started_at: Instant,
+
+    /// Number of connections currently being processed.
+    connected_clients: AtomicU64,
+
+    /// Number of commands which exceeded `Config::command_deadline`.
+    timed_out_commands: AtomicU64,
+}
 
 /// Server listener state. Created in the `run` call. It includes a `run` method
 /// which performs the TCP listening and initialization of per-connection state.
@@ -25,8 +202,8 @@ struct Listener {
     /// retrieved(检索) and passed into the per connection state (`Handler`).
     db_holder: DbDropGuard,
 
-    /// Tcp listener supplied by the `run` caller.
-    listener: TcpListener,
+    /// Listener supplied by the `run` caller.
+    listener: Incoming,
 
     /// Limit the max number of connections.
     /// 
@@ -61,9 +238,61 @@ struct Listener {
     /// handler tasks complete, all clones of the `Sender` are also dropped. 
     /// This results in `shutdown_complete_rx.recv()` completing with `None`. At
     /// this point, it is safe to exit the server process.
-    shutdown_complete_tx: mpsc::Sender<()>
+    shutdown_complete_tx: mpsc::Sender<()>,
+
+    /// Configuration supplied by the `run_with_config` caller.
+    config: Config,
+
+    /// Wraps the accepted sockets, `None` to serve them as is. Supplied by the
+    /// `run_with_acceptor` caller.
+    acceptor: Option<Acceptor>,
+
+    /// Shared with all the handlers.
+    stats: Arc<Stats>,
+
+    /// Limits the errors logged by the listener and the handlers, see
+    /// `ERROR_LOG_BURST`.
+    error_log: Arc<RateLimitedLog>,
+
+    /// Identifier of the next accepted connection, reported in its logs.
+    next_client_id: u64,
+}
+
+/// Number of errors of each category logged per `ERROR_LOG_INTERVAL`. The
+/// others are only counted, and reported by a summary line along with the
+/// next error logged.
+const ERROR_LOG_BURST: u32 = 10;
+
+/// See `ERROR_LOG_BURST`.
+const ERROR_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Source of the inbound connections of a `Listener`.
+#[derive(Debug)]
+pub(crate) enum Incoming {
+    /// Sockets accepted and served with epoll by the runtime of the caller.
+    Tcp(TcpListener),
+
+    /// Sockets accepted and served with io_uring, see `uring::run`.
+    #[cfg(feature = "uring")]
+    Uring(crate::uring::Listener),
 }
 
+/// Socket accepted from an `Incoming`.
+enum Socket {
+    Tcp(TcpStream),
+
+    #[cfg(feature = "uring")]
+    Uring(crate::uring::Stream),
+}
+
+/// Future returned by an `Acceptor`, resolving to the stream to serve.
+type Accepting = Pin<Box<dyn Future<Output = io::Result<Box<dyn Transport>>> + Send>>;
+
+/// Wraps each accepted socket, e.g. to perform a TLS handshake, see
+/// `run_with_acceptor`.
+#[derive(Clone)]
+pub(crate) struct Acceptor(Arc<dyn Fn(TcpStream) -> Accepting + Send + Sync>);
+
 /// Per-connection handler. Reads requests from `connection` and applies the
 /// commands to `db`
 #[derive(Debug)]
@@ -75,12 +304,13 @@ struct Handler {
     /// will need to interact with `db` in order to complete the work.
     db: Db,
 
-    /// The TCP connection decorated with the redis protocol encoder / decoder
-    /// implemented using a buffered `TcpStream`
+    /// The connection decorated with the redis protocol encoder / decoder
+    /// implemented using a buffered stream, the `TcpStream` or the stream
+    /// wrapping it returned by the acceptor.
     /// 
-    /// When `Listener` receives an inbound connection, the `TcpStream` is 
-    /// passed to `Connection::new`, which initializes the associated buffers.
-    /// `Connection` allows the handler to operate at the "frame" level and keep
+    /// When `Listener` receives an inbound connection, the stream is 
+    /// passed to `Connection::with_capacity`, which initializes the associated
+    /// buffers. `Connection` allows the handler to operate at the "frame" level and keep
     /// the byte level protocol parsing details encapsulated(封装) in `Connection`.
     connection: Connection,
 
@@ -100,21 +330,38 @@ struct Handler {
     /// Not used directly. Instead, when `Handler` is dropped...?
     _shutdown_complete: mpsc::Sender<()>,
 
+    /// See `Config::command_deadline`.
+    command_deadline: Option<Duration>,
+
+    /// See `Config::max_subscribe_churn`.
+    max_subscribe_churn: Option<u32>,
+
+    /// See `Config::idle_timeout`.
+    idle_timeout: Option<Duration>,
+
+    /// Shared with the `Listener` and the other handlers.
+    stats: Arc<Stats>,
+
+    /// The commands queued since `MULTI`, `None` outside of a transaction.
+    transaction: Option<Vec<Command>>,
 }
 
-/// Maximum number of concurrent connections the redis server will accept.
-/// 
-/// When this limit is reached, the server will stop accepting connections until
-/// an active connection terminates.
+/// Default maximum number of concurrent connections the redis server will
+/// accept, see `Config::max_connections`.
 /// 
-/// A real application will want to make this value configurable, but for this 
-/// example, it is hard coded.
-/// 
-/// This is also set tot a pretty low value to discourage using this in 
+/// This is set tot a pretty low value to discourage using this in 
 /// production (you'd think that all the disclaimers would make it obvious that
 /// this is not a serious project.. but I thought that about mini-http as well).
 const MAX_CONNECTIONS: usize = 250;
 
+/// Default connection limit right after startup, see
+/// `Config::initial_connections`.
+const INITIAL_CONNECTIONS: usize = 10;
+
+/// Maximum number of times the connection limit is raised during the warm-up,
+/// see `Config::connection_warm_up`.
+const WARM_UP_STEPS: usize = 20;
+
 /// Run the mini-redis server.
 /// 
 /// Accepts connections from the supplied listener. For each inbound connection,
@@ -125,19 +372,122 @@ const MAX_CONNECTIONS: usize = 250;
 /// `tokio::signal::ctrl_c()` can be used as the `shutdown` argument. This will
 /// listen for a SIGINT signal.
 pub async fn run(listener: TcpListener, shutdown: impl Future) {
+    run_with_config(listener, shutdown, Config::default()).await
+}
+
+/// Run the mini-redis server with the given `config`.
+///
+/// Same as `run`, with the behavior of the server tuned by `config`.
+///
+/// # io_uring
+///
+/// With the `uring` feature, the connections are accepted and served with
+/// io_uring instead of epoll. This is meant for Linux deployments where the
+/// cost of the syscalls matters, and comes with caveats:
+///
+/// - It needs Linux 5.10 or later. io_uring may also be disabled, by the
+///   `kernel.io_uring_disabled` sysctl or by a seccomp profile, as the
+///   default one of Docker. The server then logs an error and this function
+///   returns right away.
+/// - The server runs on a dedicated thread, with a current-thread runtime of
+///   its own. It uses a single core, whatever the runtime of the caller, and a
+///   command blocking its thread, e.g. `DEBUG SLEEP`, stalls every
+///   connection. Its clock is not the one of the caller either, so
+///   `tokio::time::pause` does not affect it.
+/// - The server logs to the default subscriber of the caller, captured when
+///   this function is called.
+/// - Before Linux 5.12, the memory of the ring counts towards
+///   `RLIMIT_MEMLOCK`, which may need to be raised.
+/// - `run_with_acceptor` keeps serving with epoll.
+pub async fn run_with_config(listener: TcpListener, shutdown: impl Future, config: Config) {
+    #[cfg(feature = "uring")]
+    crate::uring::run(listener, shutdown, config).await;
+
+    #[cfg(not(feature = "uring"))]
+    serve(Incoming::Tcp(listener), shutdown, config, None).await
+}
+
+/// Run the mini-redis server, serving the streams returned by `accept`.
+///
+/// Same as `run_with_config`, except that each accepted socket is passed to
+/// `accept`, and the stream it resolves to is served instead. This allows
+/// serving another transport than plain TCP, for example TLS by performing
+/// the handshake with `tokio_rustls::TlsAcceptor::accept`. `accept` runs on
+/// the task of the connection, so a slow handshake does not delay the other
+/// connections. When it fails, the connection is closed.
+///
+/// The connections are served with epoll, including with the `uring` feature.
+pub async fn run_with_acceptor<F, Fut, S>(
+    listener: TcpListener,
+    shutdown: impl Future,
+    config: Config,
+    accept: F,
+) where
+    F: Fn(TcpStream) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = io::Result<S>> + Send + 'static,
+    S: Transport + 'static,
+{
+    let acceptor = Acceptor(Arc::new(move |socket| {
+        let accepting = accept(socket);
+        Box::pin(async move { Ok(Box::new(accepting.await?) as Box<dyn Transport>) })
+    }));
+
+    serve(Incoming::Tcp(listener), shutdown, config, Some(acceptor)).await
+}
+
+pub(crate) async fn serve(
+    listener: Incoming,
+    shutdown: impl Future,
+    config: Config,
+    acceptor: Option<Acceptor>,
+) {
     // 当提供的`shutdown` future完成，我们必须给所有活跃连接发送一个关闭信号
     // 为了这个目的我们使用一个 broadcst channel。
     // 下面的调用无视了broadcast pair中的接收者，当接收者被需要时，
     // 使用subscribe()方法创建一个接收者
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
+    let db_holder = DbDropGuard::new();
+    db_holder.db().set_keyspace_events(config.notify_keyspace_events);
+    db_holder.db().set_counter_overflow(config.counter_overflow);
+    db_holder.db().set_pubsub_queue(config.pubsub_queue_capacity, config.pubsub_overflow);
+    db_holder.db().set_pubsub_strictness(config.pubsub_strictness);
+    db_holder.db().set_hotkeys_tracking(config.hotkeys);
+    db_holder.db().set_histogram_slice(config.memory_histogram_slice);
+    db_holder.db().set_server_info(&config.server_name, &config.server_version);
+
+    // 桥接任务在`db_holder`被drop时随服务器一起停止
+    for (src, dst) in &config.bridges {
+        db_holder.db().bridge(src, dst);
+    }
+
+    // 预热期间从较少的permit开始，由后台任务逐步增加到`max_connections`
+    let limit_connections = match config.connection_warm_up {
+        Some(warm_up) if config.initial_connections < config.max_connections => {
+            let limit_connections = Arc::new(Semaphore::new(config.initial_connections));
+            tokio::spawn(warm_up_connections(
+                limit_connections.clone(),
+                config.initial_connections,
+                config.max_connections,
+                warm_up,
+            ));
+            limit_connections
+        }
+        _ => Arc::new(Semaphore::new(config.max_connections)),
+    };
+
     // 初始化Listener
     let mut server = Listener {
         listener,
-        db_holder: DbDropGuard::new(),
-        limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+        db_holder,
+        limit_connections,
         notify_shutdown,
         shutdown_complete_tx,
+        config,
+        acceptor,
+        stats: Arc::new(Stats::new()),
+        error_log: Arc::new(RateLimitedLog::new(ERROR_LOG_BURST, ERROR_LOG_INTERVAL)),
+        next_client_id: 1,
     };
 
     // 同时运行server并监听 `shutdown` 信号。server task 直到遇到错误发生
@@ -189,6 +539,89 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
     let _ = shutdown_complete_rx.recv().await;
 }
 
+/// Raises the number of permits of `limit_connections` from `initial` to
+/// `max`, in steps evenly spread over `warm_up`.
+async fn warm_up_connections(limit_connections: Arc<Semaphore>, initial: usize, max: usize, warm_up: Duration) {
+    let steps = (max - initial).min(WARM_UP_STEPS);
+    let start = Instant::now();
+    let mut granted = initial;
+
+    for step in 1..=steps {
+        time::sleep_until(start + warm_up * step as u32 / steps as u32).await;
+
+        let target = initial + (max - initial) * step / steps;
+        limit_connections.add_permits(target - granted);
+        granted = target;
+
+        debug!(connections = granted, "raised connection limit");
+    }
+
+    info!(max_connections = max, "connection warm-up complete");
+}
+
+impl fmt::Debug for Acceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Acceptor")
+    }
+}
+
+/// Returns the category of an error which ended a connection, under which it
+/// is rate limited and logged.
+fn error_category(err: &crate::Error) -> &'static str {
+    if let Some(err) = err.downcast_ref::<io::Error>() {
+        return match err.kind() {
+            io::ErrorKind::TimedOut => "timeout",
+            _ => "io",
+        };
+    }
+
+    if err.is::<frame::Error>() || err.is::<ParseError>() {
+        return "protocol";
+    }
+
+    "other"
+}
+
+/// Returns whether an error of `category` should be logged. If errors of the
+/// category were suppressed before it, a summary line is logged first.
+fn should_log(error_log: &RateLimitedLog, category: &'static str) -> bool {
+    match error_log.admit(category) {
+        Admission::Log { suppressed } => {
+            if suppressed > 0 {
+                warn!(category, suppressed, "suppressed {} similar errors", suppressed);
+            }
+            true
+        }
+        Admission::Suppress => false,
+    }
+}
+
+impl Stats {
+    fn new() -> Stats {
+        Stats {
+            started_at: Instant::now(),
+            connected_clients: AtomicU64::new(0),
+            timed_out_commands: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns how long the server has been running.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Returns the number of connections currently being processed.
+    pub(crate) fn connected_clients(&self) -> u64 {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of commands which exceeded
+    /// `Config::command_deadline`.
+    pub(crate) fn timed_out_commands(&self) -> u64 {
+        self.timed_out_commands.load(Ordering::Relaxed)
+    }
+}
+
 impl Listener {
     /// Run the server
     /// 
@@ -226,25 +659,85 @@ impl Listener {
             // 接收一个新的socket。这将会尝试执行错误处理。
             // The `accept` method internally attempts to recover errors, so an
             // error here is non-recoverable.(没看懂)
-            let socket = self.accept().await?;
+            let (socket, peer) = self.accept().await?;
 
-            // 为每一个连接创建必要的处理程序状态
-            let mut handler = Handler {
-                db: self.db_holder.db(),
+            let client_id = self.next_client_id;
+            self.next_client_id += 1;
+            let error_log = self.error_log.clone();
 
-                connection: Connection::new(socket),
+            let acceptor = self.acceptor.clone();
+            let read_buffer_capacity = self.config.read_buffer_capacity;
+            let max_read_buffer_capacity = self.config.max_read_buffer_capacity;
+            let max_frame_len = self.config.max_frame_len;
+            let write_timeout = self.config.write_timeout;
 
-                shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
+            let db = self.db_holder.db();
+            let shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+            let shutdown_complete = self.shutdown_complete_tx.clone();
+            let command_deadline = self.config.command_deadline;
+            let max_subscribe_churn = self.config.max_subscribe_churn;
+            let idle_timeout = self.config.idle_timeout;
+            let stats = self.stats.clone();
 
-                _shutdown_complete: self.shutdown_complete_tx.clone(),
-            };
+            self.stats.connected_clients.fetch_add(1, Ordering::Relaxed);
 
             // 创建一个新任务来执行连接。Tokio 任务就像 异步绿色线程，并发执行。
-            tokio::spawn(async move {
+            self.listener.spawn(async move {
+                // 握手在连接自己的任务中进行，不会阻塞接收其他连接
+                let stream: Box<dyn Transport> = match (socket, acceptor) {
+                    (Socket::Tcp(socket), Some(Acceptor(accept))) => match accept(socket).await {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            if should_log(&error_log, "handshake") {
+                                error!(category = "handshake", ?peer, client_id, cause = ?err, "failed to accept connection");
+                            }
+                            stats.connected_clients.fetch_sub(1, Ordering::Relaxed);
+                            return;
+                        }
+                    },
+                    (Socket::Tcp(socket), None) => Box::new(socket),
+                    #[cfg(feature = "uring")]
+                    (Socket::Uring(stream), _) => Box::new(stream),
+                };
+
+                let mut connection = Connection::with_capacity(stream, read_buffer_capacity);
+                connection.set_max_read_buffer_capacity(max_read_buffer_capacity);
+                connection.set_max_frame_len(max_frame_len);
+                connection.set_write_timeout(write_timeout);
+
+                // 为每一个连接创建必要的处理程序状态
+                let mut handler = Handler {
+                    db,
+
+                    connection,
+
+                    shutdown,
+
+                    _shutdown_complete: shutdown_complete,
+
+                    command_deadline,
+
+                    max_subscribe_churn,
+
+                    idle_timeout,
+
+                    stats,
+
+                    transaction: None,
+                };
+
                 // 执行连接，如果遇到错误，打log
                 if let Err(err) = handler.run().await {
-                    error!(cause = ?err, "connection error");
+                    let category = error_category(&err);
+
+                    if should_log(&error_log, category) {
+                        match category {
+                            "timeout" => info!(category, ?peer, client_id, cause = ?err, "closing client not reading its replies"),
+                            _ => error!(category, ?peer, client_id, cause = ?err, "connection error"),
+                        }
+                    }
                 }
+                handler.stats.connected_clients.fetch_sub(1, Ordering::Relaxed);
                 // 将permit移动到任务中，当完成时将其drop。
                 // 会将permit返回给semaphore
                 drop(permit);
@@ -259,18 +752,22 @@ impl Listener {
     /// After the second failure, the task waits for 2 seconds. Each subsequent
     /// failure doubles the wait time. If accepting fails on the 6th try after 
     /// waiting for 64 seconds, then this function returns with an error.
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
+    async fn accept(&mut self) -> crate::Result<(Socket, SocketAddr)> {
         let mut backoff = 1;
 
         loop {
             // 执行建立连接操作。如果一个socket被成功接收了，返回这个socket
             // 否则保存错误
             match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+                Ok(accepted) => return Ok(accepted),
                 Err(err) => {
                     if backoff > 64 {
                         return Err(err.into());
                     }
+
+                    if should_log(&self.error_log, "accept") {
+                        warn!(category = "accept", cause = %err, backoff, "failed to accept, retrying");
+                    }
                 }
             }
 
@@ -282,25 +779,79 @@ impl Listener {
     }
 }
 
+impl Incoming {
+    async fn accept(&self) -> io::Result<(Socket, SocketAddr)> {
+        match self {
+            Incoming::Tcp(listener) => {
+                let (socket, peer) = listener.accept().await?;
+                Ok((Socket::Tcp(socket), peer))
+            }
+            #[cfg(feature = "uring")]
+            Incoming::Uring(listener) => {
+                let (stream, peer) = listener.accept().await?;
+                Ok((Socket::Uring(stream), peer))
+            }
+        }
+    }
+
+    /// Spawns the task serving a connection accepted from `self`.
+    ///
+    /// The io_uring streams must stay on the thread of their ring, see
+    /// `uring::Stream`, so their tasks are spawned as local tasks of its
+    /// runtime.
+    fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+        match self {
+            Incoming::Tcp(_) => {
+                tokio::spawn(task);
+            }
+            #[cfg(feature = "uring")]
+            Incoming::Uring(_) => {
+                tokio_uring::spawn(task);
+            }
+        }
+    }
+}
+
 impl  Handler {
     /// Process a single connection
     /// 
     /// Request frames are read from the socket and processed. Responses are
     /// written back to the socket
     /// 
-    /// Currently, pipelining is not implemented. Pipelining is the ability to
-    /// process more than one request concurrently per connection without
-    /// interleaving frames. See for more details:
-    /// zzh_todo()
+    /// Pipelining is supported. Pipelining is the ability to send more than
+    /// one request at once, before reading the responses. The requests are
+    /// processed in order, and while more of them are already buffered, their
+    /// responses are kept in the write buffer, then written together once the
+    /// last one is processed. See for more details:
     /// http://redis.io/topics/pipelining
     /// 
     /// When the shutdown signal is received, the connection is processed until
     /// it reaches a safe state, at which point it is terminated.
     #[instrument(skip(self))]
     async fn run(&mut self) -> crate::Result<()> {
+        let res = self.process_frames().await;
+
+        // 连接因为错误或者服务端关闭而结束时，之前pipeline的命令的回复可能还在写buffer中
+        if let Err(err) = self.connection.flush().await {
+            debug!(cause = ?err, "failed to flush pending replies");
+        }
+
+        res
+    }
+
+    /// Read and process the frames until the connection ends, see `run`.
+    async fn process_frames(&mut self) -> crate::Result<()> {
         while !self.shutdown.is_shutdown() {
+            // 订阅的客户端在`Subscribe::apply`中等待消息，不会经过这里，
+            // 所以不会因为空闲而被关闭
             let maybe_frame = tokio::select! {
-                res = self.connection.read_frame() => res?,
+                res = with_idle_timeout(self.idle_timeout, self.connection.read_frame()) => match res {
+                    Some(res) => res?,
+                    None => {
+                        debug!(idle_timeout = ?self.idle_timeout, "closing idle connection");
+                        return Ok(());
+                    }
+                },
                 _ = self.shutdown.recv() => {
                     return Ok(());
                 }
@@ -308,15 +859,141 @@ impl  Handler {
 
             let frame = match maybe_frame {
                 Some(frame) => frame,
-                None => return Ok(()),
+                None => {
+                    // 对端关闭了写入端。之前收到的完整frame都已经被执行，回复也都已写入，
+                    // 关闭写入端，对端读到EOF时就知道收到了所有回复
+                    if let Err(err) = self.connection.shutdown().await {
+                        debug!(cause = ?err, "failed to shut down connection");
+                    }
+                    return Ok(());
+                }
             };
 
-            let cmd = Command::from_frame(frame)?;
+            // 数组声明的元素个数和实际不符时，回复一个协议错误并关闭连接，
+            // 因为之后的数据已经无法被正确地划分为frame
+            if let Err(err) = frame.check_command() {
+                self.connection.set_defer_flush(false);
+                self.connection.write_frame(&Frame::Error(err.to_string())).await?;
+                return Err(err.into());
+            }
+
+            // frame的边界已经检查过，参数无法解析时回复错误，连接仍然可用
+            let cmd = match Command::from_frame(frame) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    let defer_flush = self.connection.has_buffered_frame();
+                    self.connection.set_defer_flush(defer_flush);
+                    self.connection.write_frame(&parse_error(err)).await?;
+                    continue;
+                }
+            };
 
             debug!(?cmd);
 
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown).await?;
+            // 读buffer中还有完整的frame，说明客户端pipeline了多个命令。回复先留在写buffer中，
+            // 执行完最后一个命令再一起写入。可能阻塞的命令不延迟，之前的回复也随它的回复写入
+            let defer_flush = !cmd.may_block() && self.connection.has_buffered_frame();
+            self.connection.set_defer_flush(defer_flush);
+
+            let cmd = match self.handle_transaction(cmd).await? {
+                Some(cmd) => cmd,
+                None => continue,
+            };
+
+            let deadline = match self.command_deadline {
+                Some(deadline) if !cmd.exempt_from_deadline() => deadline,
+                _ => {
+                    cmd.apply(&self.db, &mut self.connection, &mut self.shutdown, &self.stats, self.max_subscribe_churn).await?;
+                    continue;
+                }
+            };
+
+            let name = cmd.get_name().to_string();
+
+            let res = time::timeout(
+                deadline,
+                cmd.apply(&self.db, &mut self.connection, &mut self.shutdown, &self.stats, self.max_subscribe_churn),
+            )
+            .await;
+
+            match res {
+                Ok(res) => res?,
+                Err(_) => {
+                    let total = self.stats.timed_out_commands.fetch_add(1, Ordering::Relaxed) + 1;
+                    error!(command = %name, ?deadline, total, "command exceeded execution deadline");
+
+                    // 命令可能已经被部分执行，连接的状态无法确定，回复错误后关闭连接
+                    self.connection.set_defer_flush(false);
+                    let response = Frame::Error("TIMEOUT command exceeded execution deadline".to_string());
+                    self.connection.write_frame(&response).await?;
+                    return Ok(());
+                }
+            }
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Handles `MULTI`, `EXEC` and `DISCARD`, and queues the commands received
+    /// in between, replying `QUEUED`.
+    ///
+    /// Returns the command to apply: a command received outside of a
+    /// transaction, or `EXEC` carrying the queued commands. `None` is returned
+    /// when the command has already been replied to.
+    async fn handle_transaction(&mut self, cmd: Command) -> crate::Result<Option<Command>> {
+        let response = match (cmd, self.transaction.as_mut()) {
+            (Command::Multi(_), None) => {
+                self.transaction = Some(vec![]);
+                Frame::Simple("OK".to_string())
+            }
+            (Command::Multi(_), Some(_)) => Frame::Error("ERR MULTI calls can not be nested".to_string()),
+            (Command::Exec(_), Some(_)) => {
+                let commands = self.transaction.take().unwrap_or_default();
+                return Ok(Some(Command::Exec(Exec::with_commands(commands))));
+            }
+            (Command::Exec(_), None) => Frame::Error("ERR EXEC without MULTI".to_string()),
+            (Command::Discard(_), Some(_)) => {
+                self.transaction = None;
+                Frame::Simple("OK".to_string())
+            }
+            (Command::Discard(_), None) => Frame::Error("ERR DISCARD without MULTI".to_string()),
+            // 订阅会让连接进入另一种模式，压缩会改变之后回复的编码，可能阻塞的和管理命令也不能在事务中执行
+            (cmd, Some(_)) if !cmd.is_transactional() => {
+                Frame::Error("ERR Command not allowed inside a transaction".to_string())
+            }
+            (cmd, Some(queue)) => {
+                queue.push(cmd);
+                Frame::Simple("QUEUED".to_string())
+            }
+            (cmd, None) => return Ok(Some(cmd)),
+        };
+
+        debug!(?response);
+
+        self.connection.write_frame(&response).await?;
+
+        Ok(None)
+    }
+}
+
+/// Returns the reply to a command whose arguments could not be parsed. The
+/// errors of the parsers are `ERR` replies already, other ones are prefixed.
+fn parse_error(err: crate::Error) -> Frame {
+    let message = err.to_string();
+
+    debug!(cause = %message, "failed to parse command");
+
+    if message.starts_with("ERR ") {
+        Frame::Error(message)
+    } else {
+        Frame::Error(format!("ERR {}", message))
+    }
+}
+
+/// Runs `fut` to completion, or returns `None` if it takes longer than
+/// `idle_timeout`.
+async fn with_idle_timeout<F: Future>(idle_timeout: Option<Duration>, fut: F) -> Option<F::Output> {
+    match idle_timeout {
+        Some(idle_timeout) => time::timeout(idle_timeout, fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
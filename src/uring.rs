@@ -0,0 +1,258 @@
+//! io_uring backend of the server, enabled by the `uring` feature.
+//!
+//! tokio-uring drives its ring from a current-thread runtime of its own, and
+//! its sockets are `!Send`. The server is therefore started on a dedicated
+//! thread running that runtime, while `run` stays a `Send` future which the
+//! caller awaits on its own runtime. The accepted sockets are adapted to
+//! `AsyncRead` / `AsyncWrite` by `Stream`, so that `Connection`, the
+//! handlers and the commands are shared with the epoll backend.
+
+use crate::server::{self, Config};
+
+use send_wrapper::SendWrapper;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{self, SocketAddr};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll};
+use std::thread;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::oneshot;
+use tracing::{error, info};
+
+/// Minimum number of bytes asked to the kernel by a read, so that small
+/// `ReadBuf`s do not turn into a read per byte.
+const MIN_READ_LEN: usize = 4 * 1024;
+
+/// Operation submitted to the ring, resolving to its result and its buffer.
+type Op = Pin<Box<dyn Future<Output = (io::Result<usize>, Vec<u8>)>>>;
+
+/// Listener of the io_uring backend.
+///
+/// It is wrapped like `Stream`, so that the future of `server::serve` stays
+/// `Send` whatever its listener, as `server::run_with_acceptor` needs.
+pub(crate) struct Listener(SendWrapper<tokio_uring::net::TcpListener>);
+
+/// Socket accepted by `Listener`, read and written through the ring.
+///
+/// The wrapper panics if the stream is used or dropped on another thread than
+/// the one of the ring. The connection tasks are spawned with
+/// `tokio_uring::spawn`, which keeps them on that thread.
+pub(crate) struct Stream(SendWrapper<Inner>);
+
+struct Inner {
+    socket: Rc<tokio_uring::net::TcpStream>,
+
+    /// Read submitted to the ring. It is kept when the caller stops polling,
+    /// e.g. when a `select!` completes with another branch, and the next
+    /// `poll_read` resumes it, so that the bytes it reads are not lost.
+    read: Option<Op>,
+
+    /// Bytes read from the socket, not yet returned from `read_pos` on.
+    read_buf: Vec<u8>,
+    read_pos: usize,
+
+    /// Write submitted to the ring. `poll_write` accepts the bytes as soon as
+    /// they are submitted, and its completion, or error, is awaited by the
+    /// next write, flush or shutdown.
+    write: Option<Op>,
+
+    /// Buffer of the last completed write, reused by the next one.
+    write_buf: Vec<u8>,
+}
+
+/// Runs `server::serve` on a dedicated io_uring thread, until `shutdown`
+/// completes, see `server::run_with_config`.
+pub(crate) async fn run(listener: tokio::net::TcpListener, shutdown: impl Future, config: Config) {
+    // 提交给ring的accept由内核等待，socket需要是阻塞模式
+    let listener = match listener.into_std().and_then(|listener| {
+        listener.set_nonblocking(false)?;
+        Ok(listener)
+    }) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(cause = %err, "failed to hand the listener over to io_uring");
+            return;
+        }
+    };
+
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let (done_tx, mut done_rx) = oneshot::channel::<()>();
+
+    // 服务器的日志仍然交给调用者的subscriber
+    let dispatch = tracing::dispatcher::get_default(Clone::clone);
+
+    let spawned = thread::Builder::new()
+        .name("my-mini-redis-uring".to_string())
+        .spawn(move || {
+            let _guard = tracing::dispatcher::set_default(&dispatch);
+
+            let runtime = match tokio_uring::Runtime::new(&tokio_uring::builder()) {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    error!(cause = %err, "failed to start the io_uring runtime");
+                    return;
+                }
+            };
+
+            runtime.block_on(async move {
+                let listener = Listener(SendWrapper::new(tokio_uring::net::TcpListener::from_std(listener)));
+                // `stop_tx`被drop时同样停止服务器
+                server::serve(server::Incoming::Uring(listener), stop_rx, config, None).await;
+            });
+            drop(runtime);
+
+            let _ = done_tx.send(());
+        });
+
+    if let Err(err) = spawned {
+        error!(cause = %err, "failed to spawn the io_uring thread");
+        return;
+    }
+
+    tokio::select! {
+        _ = shutdown => {
+            let _ = stop_tx.send(());
+        }
+        // 服务器自行停止，例如接收连接一直失败
+        _ = &mut done_rx => return,
+    }
+
+    // 等待所有连接结束，与epoll后端相同
+    let _ = done_rx.await;
+    info!("io_uring thread stopped");
+}
+
+impl Listener {
+    pub(crate) async fn accept(&self) -> io::Result<(Stream, SocketAddr)> {
+        let (socket, peer) = SendWrapper::new(self.0.accept()).await?;
+
+        let stream = Inner {
+            socket: Rc::new(socket),
+            read: None,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            write: None,
+            write_buf: Vec::new(),
+        };
+        Ok((Stream(SendWrapper::new(stream)), peer))
+    }
+}
+
+impl Inner {
+    /// Waits for the write in flight, and resubmits the rest of its buffer
+    /// until all of it is written.
+    fn poll_written(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while let Some(op) = self.write.as_mut() {
+            let (res, mut buf) = ready!(op.as_mut().poll(cx));
+            self.write = None;
+
+            match res {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(n) if n < buf.len() => {
+                    buf.drain(..n);
+                    self.write = Some(submit_write(&self.socket, buf));
+                }
+                Ok(_) => self.write_buf = buf,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn submit_read(socket: &Rc<tokio_uring::net::TcpStream>, buf: Vec<u8>) -> Op {
+    let socket = socket.clone();
+    Box::pin(async move { socket.read(buf).await })
+}
+
+fn submit_write(socket: &Rc<tokio_uring::net::TcpStream>, buf: Vec<u8>) -> Op {
+    let socket = socket.clone();
+    Box::pin(async move { socket.write(buf).submit().await })
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self.get_mut().0;
+
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        if this.read_pos == this.read_buf.len() {
+            let op = match this.read.as_mut() {
+                Some(op) => op,
+                None => {
+                    // 内核从buffer的开头写入，最多写满其容量
+                    let mut read_buf = std::mem::take(&mut this.read_buf);
+                    this.read_pos = 0;
+                    read_buf.clear();
+                    read_buf.reserve(buf.remaining().max(MIN_READ_LEN));
+                    this.read.insert(submit_read(&this.socket, read_buf))
+                }
+            };
+
+            let (res, read_buf) = ready!(op.as_mut().poll(cx));
+            this.read = None;
+            this.read_buf = read_buf;
+            this.read_pos = 0;
+
+            // 读到0字节即EOF，此时`buf`保持不变
+            res?;
+        }
+
+        let n = buf.remaining().min(this.read_buf.len() - this.read_pos);
+        buf.put_slice(&this.read_buf[this.read_pos..this.read_pos + n]);
+        this.read_pos += n;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self.get_mut().0;
+        ready!(this.poll_written(cx))?;
+
+        let mut write_buf = std::mem::take(&mut this.write_buf);
+        write_buf.clear();
+        write_buf.extend_from_slice(buf);
+        this.write = Some(submit_write(&this.socket, write_buf));
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().0.poll_written(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self.get_mut().0;
+        ready!(this.poll_written(cx))?;
+
+        Poll::Ready(this.socket.shutdown(net::Shutdown::Write))
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // 提交给ring的操作持有socket，在完成之前不会关闭它。对端不再写入时，
+        // 进行中的读永远不会完成，所以需要shutdown让它结束
+        let _ = self.socket.shutdown(net::Shutdown::Both);
+    }
+}
+
+impl fmt::Debug for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Listener")
+    }
+}
+
+impl fmt::Debug for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Stream")
+    }
+}
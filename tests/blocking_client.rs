@@ -0,0 +1,40 @@
+#![cfg(feature = "blocking")]
+
+use my_mini_redis::clients::{BlockingClient, ClientError};
+use my_mini_redis::server;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// On a `multi_thread` runtime the blocking client works from within a task.
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_client_in_multi_thread_runtime() {
+    let addr = start_server().await;
+
+    let mut client = BlockingClient::connect(addr).unwrap();
+
+    client.set("hello", "world".into()).unwrap();
+    assert_eq!(Some("world".into()), client.get("hello").unwrap());
+}
+
+/// On a `current_thread` runtime blocking would stall the runtime, so an error
+/// is returned instead of panicking.
+#[tokio::test]
+async fn blocking_client_in_current_thread_runtime() {
+    let addr = start_server().await;
+
+    let err = BlockingClient::connect(addr).err().unwrap();
+
+    assert_eq!(
+        Some(&ClientError::CalledFromAsyncContext),
+        err.downcast_ref::<ClientError>()
+    );
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
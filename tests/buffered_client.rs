@@ -1,10 +1,13 @@
 use my_mini_redis::{
-    clients::{BufferedClient, Client, self},
-    server,
+    clients::{BufferedClient, BufferedStats, Client, ClientError},
+    server, Connection, Frame,
 };
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::TcpListener;
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
 
 /// A basic "hello world" style test. A server instance is started in a 
 /// background task. A client instance is then established and used to intialize
@@ -23,6 +26,141 @@ async fn pool_key_value_get_set() {
     assert_eq!(b"world", &value[..])
 }
 
+/// A request whose caller timed out while it was still queued is never sent
+/// to the server.
+#[tokio::test]
+async fn cancelled_request_is_not_sent() {
+    let (addr, mut received) = start_slow_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut client = BufferedClient::buffer(client);
+
+    // 占用连接，使后面的请求排队
+    let mut in_flight = client.clone();
+    let first = tokio::spawn(async move { in_flight.get("first").await });
+    assert_eq!("first", received.recv().await.unwrap());
+
+    let res = time::timeout(Duration::from_millis(50), client.get("cancelled")).await;
+    assert!(res.is_err());
+
+    first.await.unwrap().unwrap();
+    assert!(client.get("last").await.unwrap().is_none());
+
+    assert_eq!("last", received.recv().await.unwrap());
+    assert_eq!(
+        BufferedStats {
+            cancelled: 1,
+            expired: 0
+        },
+        client.stats()
+    );
+}
+
+/// A request whose deadline elapsed while it was still queued is never sent
+/// to the server.
+#[tokio::test]
+async fn expired_request_is_not_sent() {
+    let (addr, mut received) = start_slow_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut client = BufferedClient::buffer(client);
+
+    let mut in_flight = client.clone();
+    let first = tokio::spawn(async move { in_flight.get("first").await });
+    assert_eq!("first", received.recv().await.unwrap());
+
+    let deadline = Instant::now() + Duration::from_millis(50);
+    assert!(client.get_with_deadline("expired", deadline).await.is_err());
+
+    first.await.unwrap().unwrap();
+    assert!(client.get("last").await.unwrap().is_none());
+
+    assert_eq!("last", received.recv().await.unwrap());
+    assert_eq!(
+        BufferedStats {
+            cancelled: 0,
+            expired: 1
+        },
+        client.stats()
+    );
+}
+
+/// A panic of the connection task is returned by the requests sent after it,
+/// through every handle, instead of leaving them hanging.
+#[tokio::test]
+async fn panicked_task_is_reported() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut client = BufferedClient::buffer(client);
+    let mut other = client.clone();
+
+    client.set("hello", "world".into()).await.unwrap();
+
+    client.panic_on_next_request();
+
+    let expected = ClientError::BackgroundTaskPanicked(
+        "panic injected by `BufferedClient::panic_on_next_request`".to_string(),
+    );
+
+    let err = time::timeout(Duration::from_secs(1), client.get("hello")).await.unwrap().unwrap_err();
+    assert_eq!(Some(&expected), err.downcast_ref::<ClientError>());
+
+    let err = time::timeout(Duration::from_secs(1), other.get("hello")).await.unwrap().unwrap_err();
+    assert_eq!(Some(&expected), err.downcast_ref::<ClientError>());
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    let err = client.get_with_deadline("hello", deadline).await.unwrap_err();
+    assert_eq!(Some(&expected), err.downcast_ref::<ClientError>());
+}
+
+/// Dropping the last handle stops the connection task, which closes the
+/// connection.
+#[tokio::test]
+async fn dropping_handles_closes_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = Client::connect(addr).await.unwrap();
+    let (socket, _) = listener.accept().await.unwrap();
+    let mut connection = Connection::new(socket);
+
+    let client = BufferedClient::buffer(client);
+    let other = client.clone();
+
+    drop(client);
+    assert!(time::timeout(Duration::from_millis(100), connection.read_frame()).await.is_err());
+
+    drop(other);
+    let frame = time::timeout(Duration::from_secs(1), connection.read_frame()).await.unwrap();
+    assert!(frame.unwrap().is_none());
+}
+
+/// A server replying `(nil)` to every command after a delay. The key of every
+/// received command is forwarded to the returned channel.
+async fn start_slow_server() -> (SocketAddr, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut connection = Connection::new(socket);
+
+        while let Some(frame) = connection.read_frame().await.unwrap() {
+            if let Frame::Array(parts) = frame {
+                let _ = tx.send(parts[1].to_string());
+            }
+
+            time::sleep(Duration::from_millis(200)).await;
+            connection.write_frame(&Frame::Null).await.unwrap();
+        }
+    });
+
+    (addr, rx)
+}
+
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
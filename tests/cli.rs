@@ -0,0 +1,50 @@
+use my_mini_redis::{clients::Client, server};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::process::Command;
+use tokio::time;
+
+/// `subscribe --format json` prints one JSON object per received message, with
+/// the payload encoded in base64.
+#[tokio::test]
+async fn subscribe_json_lines() {
+    let addr = start_server().await;
+
+    let mut cli = Command::new(env!("CARGO_BIN_EXE_my-mini-redis-cli"))
+        .args(["--port", &addr.port().to_string()])
+        .args(["subscribe", "--format", "json", "news"])
+        .env("RUST_LOG", "off")
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    let mut lines = BufReader::new(cli.stdout.take().unwrap()).lines();
+
+    // 等待CLI订阅频道
+    let mut client = Client::connect(addr).await.unwrap();
+    while client.publish("news", "hello world".into()).await.unwrap() == 0 {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let line = lines.next_line().await.unwrap().unwrap();
+    assert_eq!(r#"{"channel":"news","payload":"aGVsbG8gd29ybGQ="}"#, line);
+
+    client.publish("news", Bytes::from_static(b"\xff\x00")).await.unwrap();
+    let line = lines.next_line().await.unwrap().unwrap();
+    assert_eq!(r#"{"channel":"news","payload":"/wA="}"#, line);
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -1,9 +1,18 @@
-use my_mini_redis::clients;
-use my_mini_redis::{clients::Client, server};
-use tracing::subscriber;
+use my_mini_redis::pubsub::Strictness;
+use my_mini_redis::clients::{Backlog, Client, ClientError, ModifyError, SetOptions, SetReply};
+use my_mini_redis::cmd::Ttl;
+use my_mini_redis::{server, Frame};
+use bytes::Bytes;
+use std::collections::HashSet;
 use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
+use tokio::time;
+use tokio_stream::StreamExt;
 
 /// A PING PONG test without message provided.
 /// It should return "PONG"
@@ -38,6 +47,1080 @@ async fn key_value_get_set() {
     assert_eq!(b"bar", &value[..])
 }
 
+/// NX only writes a missing key, XX only writes an existing key.
+#[tokio::test]
+async fn set_nx_xx() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // NX on a missing key
+    assert!(client.set_nx("nx", "one".into()).await.unwrap());
+    assert_eq!(b"one", &client.get("nx").await.unwrap().unwrap()[..]);
+
+    // NX on an existing key
+    assert!(!client.set_nx("nx", "two".into()).await.unwrap());
+    assert_eq!(b"one", &client.get("nx").await.unwrap().unwrap()[..]);
+
+    // XX on a missing key
+    assert!(!client.set_xx("xx", "one".into()).await.unwrap());
+    assert!(client.get("xx").await.unwrap().is_none());
+
+    // XX on an existing key
+    client.set("xx", "one".into()).await.unwrap();
+    assert!(client.set_xx("xx", "two".into()).await.unwrap());
+    assert_eq!(b"two", &client.get("xx").await.unwrap().unwrap()[..]);
+}
+
+/// KEEPTTL retains the time to live of the key, while a plain SET discards
+/// it.
+#[tokio::test]
+async fn set_keep_ttl() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set_expires("kept", "1".into(), Duration::from_secs(1)).await.unwrap();
+    client.set_keep_ttl("kept", "2".into()).await.unwrap();
+
+    client.set_expires("discarded", "1".into(), Duration::from_secs(1)).await.unwrap();
+    client.set("discarded", "2".into()).await.unwrap();
+
+    // 没有过期时间的key使用KEEPTTL后仍然没有过期时间
+    client.set_keep_ttl("persistent", "1".into()).await.unwrap();
+
+    advance_server_clock(Duration::from_secs(2)).await;
+
+    assert!(client.get("kept").await.unwrap().is_none());
+    assert_eq!(b"2", &client.get("discarded").await.unwrap().unwrap()[..]);
+    assert_eq!(b"1", &client.get("persistent").await.unwrap().unwrap()[..]);
+}
+
+/// The SET options combine in any order, and KEEPTTL cannot be combined
+/// with EX or PX.
+#[tokio::test]
+async fn set_option_combinations() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let set = |args: &[&str]| -> Vec<Bytes> {
+        let mut cmd = vec![Bytes::from("set")];
+        cmd.extend(args.iter().map(|arg| Bytes::copy_from_slice(arg.as_bytes())));
+        cmd
+    };
+
+    // NX GET：key不存在时写入，并返回nil
+    let reply: Option<Bytes> = client.query(&set(&["foo", "1", "GET", "NX"])).await.unwrap();
+    assert_eq!(None, reply);
+
+    // NX GET：key存在时不写入，返回旧值
+    let reply: Option<Bytes> = client.query(&set(&["foo", "2", "NX", "GET"])).await.unwrap();
+    assert_eq!(Some(Bytes::from("1")), reply);
+    assert_eq!(b"1", &client.get("foo").await.unwrap().unwrap()[..]);
+
+    // XX GET KEEPTTL：key存在时写入，返回旧值
+    let reply: Option<Bytes> = client
+        .query(&set(&["foo", "3", "keepttl", "xx", "get"]))
+        .await
+        .unwrap();
+    assert_eq!(Some(Bytes::from("1")), reply);
+    assert_eq!(b"3", &client.get("foo").await.unwrap().unwrap()[..]);
+
+    // XX：key不存在时不写入
+    let reply: Option<String> = client.query(&set(&["bar", "1", "XX", "KEEPTTL"])).await.unwrap();
+    assert_eq!(None, reply);
+    assert!(client.get("bar").await.unwrap().is_none());
+
+    // NX PX：key不存在时写入
+    let reply: Option<String> = client.query(&set(&["bar", "1", "PX", "1000", "NX"])).await.unwrap();
+    assert_eq!(Some("OK".to_string()), reply);
+
+    for args in [
+        &["foo", "1", "KEEPTTL", "EX", "10"][..],
+        &["foo", "1", "PX", "10", "KEEPTTL"][..],
+        &["foo", "1", "NX", "XX"][..],
+    ] {
+        let mut client = Client::connect(addr).await.unwrap();
+        assert!(client.query::<Frame>(&set(args)).await.is_err(), "{:?}", args);
+    }
+}
+
+/// A pipeline of 100 `SET`s is replied to in order, and the keys are all set.
+#[tokio::test]
+async fn pipeline_of_sets() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let mut pipeline = client.pipeline();
+    for i in 0..100 {
+        pipeline.set(&format!("key-{}", i), i.to_string().into());
+    }
+    assert_eq!(100, pipeline.len());
+
+    let responses = pipeline.execute().await.unwrap();
+    assert_eq!(100, responses.len());
+    assert!(responses.iter().all(|response| *response == "OK"));
+
+    let keys: Vec<String> = (0..100).map(|i| format!("key-{}", i)).collect();
+    let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+    let values = client.mget(&keys).await.unwrap();
+    for (i, value) in values.into_iter().enumerate() {
+        assert_eq!(Some(i.to_string().into()), value);
+    }
+}
+
+/// A command failing in the middle of a pipeline gets an error reply, and the
+/// replies to the following commands are still collected.
+#[tokio::test]
+async fn pipeline_with_failing_command() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let mut pipeline = client.pipeline();
+    pipeline
+        .set("foo", "bar".into())
+        .incr_by("foo", 1)
+        .command(&["nosuchcommand".into()])
+        .get("foo")
+        .ping(None);
+
+    let responses = pipeline.execute().await.unwrap();
+    assert_eq!(5, responses.len());
+    assert_eq!(responses[0], "OK");
+    assert!(matches!(&responses[1], Frame::Error(msg) if msg == "ERR value is not an integer or out of range"));
+    assert!(matches!(&responses[2], Frame::Error(_)));
+    assert_eq!(responses[3], "bar");
+    assert_eq!(responses[4], "PONG");
+
+    // 连接仍然可用
+    assert!(!client.is_poisoned());
+    assert!(client.pipeline().execute().await.unwrap().is_empty());
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+}
+
+/// `exists_map` tells which of the keys exist, position by position, while
+/// `exists` only counts them.
+#[tokio::test]
+async fn exists_map() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("a", "1".into()).await.unwrap();
+    client.set("c", "3".into()).await.unwrap();
+    client
+        .set_expires("expired", "4".into(), Duration::from_millis(1))
+        .await
+        .unwrap();
+    time::sleep(Duration::from_millis(10)).await;
+
+    let keys = ["a", "b", "c", "expired", "a", "d"];
+    assert_eq!(3, client.exists(&keys).await.unwrap());
+    assert_eq!(
+        vec![true, false, true, false, true, false],
+        client.exists_map(&keys).await.unwrap()
+    );
+
+    assert!(client.exists_map(&[]).await.unwrap().is_empty());
+}
+
+/// HELLO switches between the supported protocol versions and rejects the
+/// others, leaving the connection usable.
+#[tokio::test]
+async fn hello_protocol_version() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let info = client.hello(None).await.unwrap();
+    assert_eq!(info[4], "proto");
+    assert!(matches!(info[5], Frame::Integer(2)));
+
+    let info = client.hello(Some(3)).await.unwrap();
+    assert!(matches!(info[5], Frame::Integer(3)));
+
+    let err = client.hello(Some(4)).await.unwrap_err();
+    assert_eq!("NOPROTO unsupported protocol version", err.to_string());
+
+    let info = client.hello(None).await.unwrap();
+    assert!(matches!(info[5], Frame::Integer(3)));
+
+    client.set("foo", "bar".into()).await.unwrap();
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+}
+
+/// Under a skewed workload touching more keys than the tracker counts, the
+/// hottest keys are reported first.
+#[tokio::test]
+async fn hotkeys_skewed_workload() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.hotkeys(10).await.is_err());
+    assert_eq!(Some("no".to_string()), client.config_get("hotkeys").await.unwrap());
+
+    client.config_set("hotkeys", "yes").await.unwrap();
+    assert_eq!(Some("yes".to_string()), client.config_get("hotkeys").await.unwrap());
+    assert_eq!(None, client.config_get("missing").await.unwrap());
+
+    // 第i个key被访问约1000 / (i + 1)次，每一轮访问所有还有剩余次数的key，
+    // 使冷门的key和热门的key交错
+    let hits = |i: u64| (1000 / (i + 1)).max(1);
+    for round in 0..hits(0) {
+        for i in (0..3000).take_while(|&i| hits(i) > round) {
+            let key = format!("key-{}", i);
+            if round == 0 {
+                client.set(&key, "value".into()).await.unwrap();
+            } else {
+                client.get(&key).await.unwrap();
+            }
+        }
+    }
+
+    let hotkeys = client.hotkeys(10).await.unwrap();
+    let keys: HashSet<_> = hotkeys.iter().map(|hotkey| hotkey.key.clone()).collect();
+    let expected: HashSet<_> = (0..10).map(|i| format!("key-{}", i)).collect();
+    assert_eq!(expected, keys);
+
+    assert_eq!("key-0", hotkeys[0].key);
+    assert!(hotkeys[0].hits >= 1000);
+    assert!(hotkeys[0].last_hit <= SystemTime::now());
+    assert!(hotkeys.windows(2).all(|pair| pair[0].hits >= pair[1].hits));
+
+    client.hotkeys_reset().await.unwrap();
+    assert!(client.hotkeys(10).await.unwrap().is_empty());
+
+    client.config_set("hotkeys", "no").await.unwrap();
+    assert!(client.hotkeys(10).await.is_err());
+    assert!(client.config_set("hotkeys", "maybe").await.is_err());
+}
+
+/// The time to live reported by PTTL stays consistent across the commands
+/// setting, keeping or removing it.
+#[tokio::test]
+async fn ttl_consistency() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let expires = |ttl: Ttl| match ttl {
+        Ttl::Expires(ttl) => ttl > Duration::from_secs(5) && ttl <= Duration::from_secs(10),
+        _ => false,
+    };
+
+    assert_eq!(Ttl::Missing, client.pttl("foo").await.unwrap());
+
+    // SET EX 然后 GETEX PERSIST：没有过期时间
+    client.set_expires("foo", "1".into(), Duration::from_secs(10)).await.unwrap();
+    assert!(expires(client.pttl("foo").await.unwrap()));
+    assert_eq!(Some("1".into()), client.get_persist("foo").await.unwrap());
+    assert_eq!(Ttl::Persistent, client.pttl("foo").await.unwrap());
+
+    // SET 然后 GETEX EX：设置过期时间
+    client.set("bar", "2".into()).await.unwrap();
+    assert_eq!(Ttl::Persistent, client.pttl("bar").await.unwrap());
+    let value: Option<Bytes> = client
+        .query(&["getex".into(), "bar".into(), "EX".into(), "10".into()])
+        .await
+        .unwrap();
+    assert_eq!(Some("2".into()), value);
+    assert!(expires(client.pttl("bar").await.unwrap()));
+
+    // SET KEEPTTL 保留过期时间，而SET丢弃它
+    client.set_keep_ttl("bar", "3".into()).await.unwrap();
+    assert!(expires(client.pttl("bar").await.unwrap()));
+    client.set("bar", "4".into()).await.unwrap();
+    assert_eq!(Ttl::Persistent, client.pttl("bar").await.unwrap());
+
+    // GETEX 不带选项不修改过期时间，对不存在的key不做任何事
+    client.set_expires("baz", "5".into(), Duration::from_secs(10)).await.unwrap();
+    let value: Option<Bytes> = client.query(&["getex".into(), "baz".into()]).await.unwrap();
+    assert_eq!(Some("5".into()), value);
+    assert!(expires(client.pttl("baz").await.unwrap()));
+    assert_eq!(None, client.get_persist("missing").await.unwrap());
+    assert_eq!(Ttl::Missing, client.pttl("missing").await.unwrap());
+
+    // 负数以整数的形式返回，和Redis一样
+    let ttl: Frame = client.query(&["pttl".into(), "bar".into()]).await.unwrap();
+    assert!(matches!(ttl, Frame::SignedInteger(-1)), "{:?}", ttl);
+    let ttl: Frame = client.query(&["pttl".into(), "missing".into()]).await.unwrap();
+    assert!(matches!(ttl, Frame::SignedInteger(-2)), "{:?}", ttl);
+
+    // GETEX 设置的更短的过期时间会唤醒后台任务
+    client.get_expire("foo", Duration::from_millis(20)).await.unwrap();
+    time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(None, client.get("foo").await.unwrap());
+    assert_eq!(Ttl::Missing, client.pttl("foo").await.unwrap());
+
+    let reply = client.query::<Frame>(&["getex".into(), "bar".into(), "KEEPTTL".into()]).await;
+    assert!(reply.is_err());
+}
+
+/// The idle time of a key grows until the key is read or touched, while
+/// asking for it does not reset it.
+#[tokio::test]
+async fn touch_resets_idle_time() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "1".into()).await.unwrap();
+    client.set("bar", "2".into()).await.unwrap();
+
+    advance_server_clock(Duration::from_secs(5)).await;
+
+    assert_eq!(Some(Duration::from_secs(5)), client.object_idletime("foo").await.unwrap());
+    assert_eq!(Some(Duration::from_secs(5)), client.object_idletime("foo").await.unwrap());
+
+    // 不存在的key不被计数，重复的key每次都被计数
+    assert_eq!(2, client.touch(&["foo", "missing", "foo"]).await.unwrap());
+    assert_eq!(Some(Duration::ZERO), client.object_idletime("foo").await.unwrap());
+    assert_eq!(Some(Duration::from_secs(5)), client.object_idletime("bar").await.unwrap());
+
+    client.get("bar").await.unwrap();
+    assert_eq!(Some(Duration::ZERO), client.object_idletime("bar").await.unwrap());
+
+    assert_eq!(None, client.object_idletime("missing").await.unwrap());
+    assert_eq!(0, client.touch(&["missing"]).await.unwrap());
+}
+
+/// SETEX and PSETEX set a key which expires, and reject a time to live which
+/// is not strictly positive.
+#[tokio::test]
+async fn setex_psetex() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let ok: String = client
+        .query(&["setex".into(), "foo".into(), "10".into(), "bar".into()])
+        .await
+        .unwrap();
+    assert_eq!("OK", ok);
+    assert_eq!(b"bar", &client.get("foo").await.unwrap().unwrap()[..]);
+
+    client.psetex("short", "1".into(), Duration::from_millis(1)).await.unwrap();
+    time::sleep(Duration::from_millis(20)).await;
+    assert!(client.get("short").await.unwrap().is_none());
+
+    let err = client
+        .query::<String>(&["setex".into(), "foo".into(), "0".into(), "baz".into()])
+        .await
+        .err()
+        .unwrap();
+    assert_eq!("ERR invalid expire time in 'setex' command", err.to_string());
+
+    let err = client
+        .query::<String>(&["psetex".into(), "foo".into(), "-5".into(), "baz".into()])
+        .await
+        .err()
+        .unwrap();
+    assert_eq!("ERR invalid expire time in 'psetex' command", err.to_string());
+
+    // 被拒绝的命令不会修改key
+    assert_eq!(b"bar", &client.get("foo").await.unwrap().unwrap()[..]);
+}
+
+/// A value set as an integer is int encoded, and modifying it in place turns
+/// it back into a raw string.
+#[tokio::test]
+async fn append_setrange_convert_int_encoding_to_raw() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "123".into()).await.unwrap();
+    assert_eq!(Some("int"), client.object_encoding("foo").await.unwrap().as_deref());
+
+    assert_eq!(6, client.append("foo", "abc".into()).await.unwrap());
+    assert_eq!(b"123abc", &client.get("foo").await.unwrap().unwrap()[..]);
+    assert_eq!(Some("raw"), client.object_encoding("foo").await.unwrap().as_deref());
+
+    client.set("bar", "456".into()).await.unwrap();
+    assert_eq!(5, client.setrange("bar", 3, "xy".into()).await.unwrap());
+    assert_eq!(b"456xy", &client.get("bar").await.unwrap().unwrap()[..]);
+    assert_eq!(Some("raw"), client.object_encoding("bar").await.unwrap().as_deref());
+
+    assert_eq!(None, client.object_encoding("missing").await.unwrap());
+}
+
+/// SETRANGE rejects an offset past the maximum string length, even when
+/// adding the length of the value would overflow.
+#[tokio::test]
+async fn setrange_rejects_huge_offset() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for offset in [u64::MAX, u64::MAX - 1, 512 * 1024 * 1024] {
+        let err = client.setrange("foo", offset, "abc".into()).await.unwrap_err();
+        assert_eq!(
+            "ERR string exceeds maximum allowed size (proto-max-bulk-len)",
+            err.to_string()
+        );
+    }
+
+    // 连接仍然可用，key没有被创建
+    assert_eq!(None, client.get("foo").await.unwrap());
+}
+
+/// INCRBYFLOAT stores results formatted like Redis: no scientific notation
+/// and no trailing zeros.
+#[tokio::test]
+async fn incr_by_float_formatting() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let cases: &[(&str, f64, &str)] = &[
+        ("0.1", 0.2, "0.30000000000000004"),
+        ("10.5", 0.1, "10.6"),
+        ("5", -10.5, "-5.5"),
+        ("-1.5", 1.5, "0"),
+        ("3.0", 2.0, "5"),
+        ("1e20", 1.0, "100000000000000000000"),
+        ("0.0000001", 0.0, "0.0000001"),
+    ];
+
+    for (initial, increment, expected) in cases {
+        client.set("foo", Bytes::from(*initial)).await.unwrap();
+        client.incr_by_float("foo", *increment).await.unwrap();
+
+        let value = client.get("foo").await.unwrap().unwrap();
+        assert_eq!(expected.as_bytes(), &value[..], "{} + {}", initial, increment);
+    }
+
+    // 非常大的数也不会使用科学计数法
+    client.set("foo", "-1e300".into()).await.unwrap();
+    client.incr_by_float("foo", -1e300).await.unwrap();
+    let value = client.get("foo").await.unwrap().unwrap();
+    assert_eq!(format!("-2{}", "0".repeat(300)).as_bytes(), &value[..]);
+
+    assert_eq!(1.5, client.incr_by_float("missing", 1.5).await.unwrap());
+
+    client.set("foo", "bar".into()).await.unwrap();
+    let err = client.incr_by_float("foo", 1.0).await.unwrap_err();
+    assert_eq!("ERR value is not a valid float", err.to_string());
+
+    // 无法解析的增量回复错误，而不是关闭连接
+    let err = client
+        .query::<Bytes>(&["incrbyfloat".into(), "foo".into(), "abc".into()])
+        .await
+        .unwrap_err();
+    assert_eq!("ERR value is not a valid float", err.to_string());
+    let err = client.query::<i64>(&["incrby".into(), "foo".into(), "12abc".into()]).await.unwrap_err();
+    assert_eq!("ERR value is not an integer or out of range", err.to_string());
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+
+    client.set("foo", "1e308".into()).await.unwrap();
+    let err = client.incr_by_float("foo", 1e308).await.unwrap_err();
+    assert_eq!("ERR increment would produce NaN or Infinity", err.to_string());
+}
+
+/// INCRBYFLOAT keeps the time to live of the key.
+#[tokio::test]
+async fn incr_by_float_keeps_ttl() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .set_expires("foo", "1".into(), Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(2.5, client.incr_by_float("foo", 1.5).await.unwrap());
+
+    advance_server_clock(Duration::from_secs(2)).await;
+
+    assert!(client.get("foo").await.unwrap().is_none());
+}
+
+/// HEALTH reports the uptime of the server and the connected clients, while
+/// PING keeps replying PONG.
+#[tokio::test]
+async fn health_reports_uptime_and_clients() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+    other.ping(None).await.unwrap();
+
+    advance_server_clock(Duration::from_secs(2)).await;
+
+    let health = client.health().await.unwrap();
+    assert_eq!("ok", health.status);
+    assert!(health.uptime >= Duration::from_secs(2));
+    assert_eq!(2, health.connected_clients);
+
+    assert_eq!(b"PONG", &client.ping(None).await.unwrap()[..]);
+}
+
+/// Replies to raw commands are decoded into the requested type.
+#[tokio::test]
+async fn query_decodes_typed_replies() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let pong: String = client.query(&["ping".into()]).await.unwrap();
+    assert_eq!("PONG", pong);
+
+    client.set("foo", "bar".into()).await.unwrap();
+
+    let values: Vec<Option<Bytes>> = client
+        .query(&["mget".into(), "foo".into(), "missing".into()])
+        .await
+        .unwrap();
+    assert_eq!(vec![Some(Bytes::from("bar")), None], values);
+    assert_eq!(values, client.mget(&["foo", "missing"]).await.unwrap());
+
+    // 类型不匹配时返回错误
+    let res: my_mini_redis::Result<u64> = client.query(&["get".into(), "foo".into()]).await;
+    assert!(res.is_err());
+}
+
+/// KEYS matches keys against Redis glob-style patterns.
+#[tokio::test]
+async fn keys_glob_matching() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for key in ["hello", "hallo", "hillo", "hxllo", "hllo", "heeeello", "h*llo"] {
+        client.set(key, "v".into()).await.unwrap();
+    }
+
+    let cases: &[(&str, &[&str])] = &[
+        ("h?llo", &["hallo", "hello", "hillo", "hxllo", "h*llo"]),
+        ("h*o", &["hallo", "heeeello", "hello", "hillo", "hllo", "hxllo", "h*llo"]),
+        ("h[ae]llo", &["hallo", "hello"]),
+        ("h[^e]llo", &["hallo", "hillo", "hxllo", "h*llo"]),
+        ("h[a-e]llo", &["hallo", "hello"]),
+        ("h\\*llo", &["h*llo"]),
+        ("hello", &["hello"]),
+        ("x*", &[]),
+    ];
+
+    for (pattern, expected) in cases {
+        let mut keys = client.keys(pattern).await.unwrap();
+        keys.sort();
+
+        let mut expected = expected.to_vec();
+        expected.sort();
+
+        assert_eq!(expected, keys, "{}", pattern);
+    }
+}
+
+/// SCAN returns every key exactly once over an iteration of several calls,
+/// and MATCH filters the returned keys.
+#[tokio::test]
+async fn scan_iterates_all_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let mut expected = vec![];
+    for i in 0..25 {
+        let key = format!("key:{}", i);
+        client.set(&key, "v".into()).await.unwrap();
+        expected.push(key);
+    }
+    client.set("other", "v".into()).await.unwrap();
+
+    let mut keys = vec![];
+    let mut cursor = 0;
+    let mut calls = 0;
+    loop {
+        let (next, batch) = client.scan(cursor, Some("key:*"), Some(10)).await.unwrap();
+        keys.extend(batch);
+        calls += 1;
+
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+
+    keys.sort();
+    expected.sort();
+    assert_eq!(expected, keys);
+    assert_eq!(3, calls);
+}
+
+/// `scan_iter` yields every key of a few hundred, and keys expiring during the
+/// iteration do not cause the remaining keys to be skipped.
+#[tokio::test]
+async fn scan_iter_yields_all_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let mut expected = HashSet::new();
+    for i in 0..300 {
+        let key = format!("key:{}", i);
+        client.set(&key, "v".into()).await.unwrap();
+        expected.insert(key);
+    }
+
+    let keys: Vec<String> = client
+        .scan_iter(None, Some(7))
+        .collect::<Result<_, _>>()
+        .await
+        .unwrap();
+    assert_eq!(expected.len(), keys.len());
+    assert_eq!(expected, keys.into_iter().collect());
+
+    // 迭代过程中有key过期，剩余的key仍然全部被返回
+    for i in 0..100 {
+        let key = format!("tmp:{}", i);
+        client.psetex(&key, "v".into(), Duration::from_millis(50)).await.unwrap();
+    }
+
+    let (mut cursor, first) = client.scan(0, Some("key:*"), Some(100)).await.unwrap();
+    let mut keys: HashSet<String> = first.into_iter().collect();
+
+    time::sleep(Duration::from_millis(100)).await;
+
+    while cursor != 0 {
+        let (next, batch) = client.scan(cursor, Some("key:*"), Some(100)).await.unwrap();
+        keys.extend(batch);
+        cursor = next;
+    }
+    assert_eq!(expected, keys);
+}
+
+/// DBSIZE counts the keys, skipping the expired ones, and takes no
+/// arguments.
+#[tokio::test]
+async fn dbsize_counts_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(0, client.dbsize().await.unwrap());
+
+    client.set("one", "1".into()).await.unwrap();
+    client.set("two", "2".into()).await.unwrap();
+    client.set("two", "2".into()).await.unwrap();
+    client.set_expires("three", "3".into(), Duration::from_millis(1)).await.unwrap();
+    assert_eq!(3, client.dbsize().await.unwrap());
+
+    time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(2, client.dbsize().await.unwrap());
+
+    let res: my_mini_redis::Result<u64> = client.query(&["dbsize".into(), "extra".into()]).await;
+    assert!(res.is_err());
+}
+
+/// FLUSHDB removes all the keys, including the ones with a TTL, and the
+/// keys set afterwards expire as usual.
+#[tokio::test]
+async fn flushdb_removes_all_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("one", "1".into()).await.unwrap();
+    client.set_expires("two", "2".into(), Duration::from_millis(50)).await.unwrap();
+
+    client.flushdb().await.unwrap();
+    assert_eq!(0, client.dbsize().await.unwrap());
+    assert!(client.get("one").await.unwrap().is_none());
+
+    client.set_expires("three", "3".into(), Duration::from_millis(100)).await.unwrap();
+    assert_eq!(1, client.dbsize().await.unwrap());
+
+    time::sleep(Duration::from_millis(200)).await;
+    assert!(client.get("three").await.unwrap().is_none());
+}
+
+/// EXCHANGE swaps the values and the TTLs, and refuses to create a missing
+/// key.
+#[tokio::test]
+async fn exchange_swaps_values() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("blue", "1".into()).await.unwrap();
+    client.set_expires("green", "2".into(), Duration::from_millis(100)).await.unwrap();
+
+    client.exchange("blue", "green").await.unwrap();
+    assert_eq!(b"2", &client.get("blue").await.unwrap().unwrap()[..]);
+    assert_eq!(b"1", &client.get("green").await.unwrap().unwrap()[..]);
+
+    let err = client.exchange("blue", "missing").await.unwrap_err();
+    assert_eq!("ERR no such key 'missing'", err.to_string());
+    assert!(client.get("missing").await.unwrap().is_none());
+
+    // TTL跟着值移动到了"blue"
+    time::sleep(Duration::from_millis(200)).await;
+    assert!(client.get("blue").await.unwrap().is_none());
+    assert_eq!(b"1", &client.get("green").await.unwrap().unwrap()[..]);
+}
+
+/// Readers never observe both keys holding the same value while EXCHANGE is
+/// being called concurrently.
+#[tokio::test(flavor = "multi_thread")]
+async fn exchange_is_atomic() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("blue", "1".into()).await.unwrap();
+    client.set("green", "2".into()).await.unwrap();
+
+    let mut writers = vec![];
+    for _ in 0..2 {
+        let mut client = Client::connect(addr).await.unwrap();
+        writers.push(tokio::spawn(async move {
+            for _ in 0..200 {
+                client.exchange("blue", "green").await.unwrap();
+            }
+        }));
+    }
+
+    let mut readers = vec![];
+    for _ in 0..2 {
+        let mut client = Client::connect(addr).await.unwrap();
+        readers.push(tokio::spawn(async move {
+            for _ in 0..200 {
+                let values = client.mget(&["blue", "green"]).await.unwrap();
+                assert_ne!(values[0], values[1]);
+            }
+        }));
+    }
+
+    for task in writers.into_iter().chain(readers) {
+        task.await.unwrap();
+    }
+}
+
+/// Small integers share a single allocation, which OBJECT REFCOUNT reports,
+/// while other values are not shared.
+#[tokio::test]
+async fn shared_integers_refcount() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("a", "1".into()).await.unwrap();
+    client.set("b", "1".into()).await.unwrap();
+    client.set("big", "10000".into()).await.unwrap();
+    client.set("padded", "01".into()).await.unwrap();
+    client.set("text", "hello".into()).await.unwrap();
+    client.incr_by_float("counter", 2.0).await.unwrap();
+
+    let shared = i32::MAX as u64;
+    assert_eq!(Some(shared), client.object_refcount("a").await.unwrap());
+    assert_eq!(Some(shared), client.object_refcount("b").await.unwrap());
+    assert_eq!(Some(shared), client.object_refcount("counter").await.unwrap());
+    assert_eq!(Some(1), client.object_refcount("big").await.unwrap());
+    assert_eq!(Some(1), client.object_refcount("padded").await.unwrap());
+    assert_eq!(Some(1), client.object_refcount("text").await.unwrap());
+    assert_eq!(None, client.object_refcount("missing").await.unwrap());
+
+    // 修改共享的值不会影响其他key
+    client.append("a", "0".into()).await.unwrap();
+    assert_eq!(b"10", &client.get("a").await.unwrap().unwrap()[..]);
+    assert_eq!(b"1", &client.get("b").await.unwrap().unwrap()[..]);
+    assert_eq!(Some(1), client.object_refcount("a").await.unwrap());
+}
+
+/// TYPE reports `string` for existing keys and `none` for missing ones.
+#[tokio::test]
+async fn key_type() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+
+    assert_eq!("string", client.key_type("foo").await.unwrap());
+    assert_eq!("none", client.key_type("missing").await.unwrap());
+}
+
+/// `set_get_old` returns the old value and stores the new one.
+#[tokio::test]
+async fn set_get_old_swaps_value() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.set_get_old("foo", "one".into()).await.unwrap().is_none());
+    assert_eq!(b"one", &client.get("foo").await.unwrap().unwrap()[..]);
+
+    let old = client.set_get_old("foo", "two".into()).await.unwrap().unwrap();
+    assert_eq!(b"one", &old[..]);
+    assert_eq!(b"two", &client.get("foo").await.unwrap().unwrap()[..]);
+}
+
+/// SET with GET replies with the previous value and still writes the new one,
+/// unless NX skips the write.
+#[tokio::test]
+async fn set_and_get_previous_value() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.set_and_get("foo", "one".into()).await.unwrap().is_none());
+
+    let prev = client.set_and_get("foo", "two".into()).await.unwrap().unwrap();
+    assert_eq!(b"one", &prev[..]);
+    assert_eq!(b"two", &client.get("foo").await.unwrap().unwrap()[..]);
+
+    // NX跳过写入，但仍然返回之前的值
+    let prev: Option<Bytes> = client
+        .query(&["set".into(), "foo".into(), "three".into(), "nx".into(), "get".into()])
+        .await
+        .unwrap();
+    assert_eq!(Some(Bytes::from("two")), prev);
+    assert_eq!(b"two", &client.get("foo").await.unwrap().unwrap()[..]);
+}
+
+/// `set_options` combines NX, an expire and GET in a single SET: the first
+/// call writes the value with its time to live, the second is skipped but
+/// still returns the value.
+#[tokio::test]
+async fn set_options_nx_expire_get() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let options = SetOptions::new().expire(Duration::from_secs(10)).nx().get();
+
+    let reply = client.set_options("foo", "one".into(), options.clone()).await.unwrap();
+    assert_eq!(SetReply::Previous(None), reply);
+    assert!(matches!(
+        client.pttl("foo").await.unwrap(),
+        Ttl::Expires(ttl) if ttl > Duration::from_secs(5) && ttl <= Duration::from_secs(10)
+    ));
+
+    let reply = client.set_options("foo", "two".into(), options).await.unwrap();
+    assert_eq!(SetReply::Previous(Some("one".into())), reply);
+    assert_eq!(b"one", &client.get("foo").await.unwrap().unwrap()[..]);
+
+    // 绝对过期时间以PXAT发送
+    let at = SystemTime::now() + Duration::from_secs(10);
+    let reply = client
+        .set_options("bar", "one".into(), SetOptions::new().expire_at(at).xx())
+        .await
+        .unwrap();
+    assert_eq!(SetReply::Skipped, reply);
+
+    client.set("bar", "one".into()).await.unwrap();
+    let reply = client
+        .set_options("bar", "two".into(), SetOptions::new().expire_at(at).xx())
+        .await
+        .unwrap();
+    assert_eq!(SetReply::Written, reply);
+    assert!(matches!(client.pttl("bar").await.unwrap(), Ttl::Expires(_)));
+
+    let reply = client
+        .set_options("bar", "three".into(), SetOptions::new().keep_ttl())
+        .await
+        .unwrap();
+    assert_eq!(SetReply::Written, reply);
+    assert!(matches!(client.pttl("bar").await.unwrap(), Ttl::Expires(_)));
+}
+
+/// STRLEN reports the byte length of the value, and 0 for a missing key.
+#[tokio::test]
+async fn strlen_reports_value_length() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "你好".into()).await.unwrap();
+
+    assert_eq!(6, client.strlen("foo").await.unwrap());
+    assert_eq!(0, client.strlen("missing").await.unwrap());
+}
+
+/// GETRANGE clamps out of range offsets and counts negative ones from the end.
+/// An empty range, or a missing key, is an empty string, never nil.
+#[tokio::test]
+async fn getrange_clamps_offsets() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "This is a string".into()).await.unwrap();
+
+    assert_eq!(&b"This"[..], &client.getrange("foo", 0, 3).await.unwrap()[..]);
+    assert_eq!(&b"ing"[..], &client.getrange("foo", -3, -1).await.unwrap()[..]);
+    assert_eq!(&b"This is a string"[..], &client.getrange("foo", 0, -1).await.unwrap()[..]);
+    assert_eq!(&b"string"[..], &client.getrange("foo", 10, 100).await.unwrap()[..]);
+    assert_eq!(&b"This"[..], &client.getrange("foo", -100, 3).await.unwrap()[..]);
+    assert_eq!(&b"T"[..], &client.getrange("foo", 0, -100).await.unwrap()[..]);
+
+    // start > end
+    assert!(client.getrange("foo", 5, 2).await.unwrap().is_empty());
+    assert!(client.getrange("foo", -1, -3).await.unwrap().is_empty());
+    assert!(client.getrange("foo", 100, 200).await.unwrap().is_empty());
+
+    // 不存在的key回复空字符串而不是nil
+    assert!(client.getrange("missing", 0, -1).await.unwrap().is_empty());
+}
+
+/// A 32MB value is read in 64KB chunks, as the server sends it.
+#[tokio::test]
+async fn get_streaming_large_value() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let len = 32 * 1024 * 1024;
+    let value: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+    client.set("big", Bytes::from(value)).await.unwrap();
+
+    let mut reader = client.get_streaming("big").await.unwrap().unwrap();
+    assert_eq!(len, reader.len());
+
+    let mut received = Vec::with_capacity(len);
+    let mut chunk = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        received.extend_from_slice(&chunk[..n]);
+    }
+    assert_eq!(0, reader.remaining());
+
+    assert_eq!(len, received.len());
+    assert!(received.iter().enumerate().all(|(i, byte)| *byte == (i % 251) as u8));
+
+    assert!(client.get_streaming("missing").await.unwrap().is_none());
+
+    client.set("empty", Bytes::new()).await.unwrap();
+    let mut reader = client.get_streaming("empty").await.unwrap().unwrap();
+    assert!(reader.is_empty());
+    let mut empty = vec![];
+    reader.read_to_end(&mut empty).await.unwrap();
+    assert!(empty.is_empty());
+}
+
+/// Dropping a `ValueReader` before the end of the value leaves the connection
+/// usable.
+#[tokio::test]
+async fn get_streaming_dropped_early() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("big", Bytes::from(vec![b'x'; 4 * 1024 * 1024])).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+
+    {
+        let mut reader = client.get_streaming("big").await.unwrap().unwrap();
+        let mut chunk = vec![0u8; 64 * 1024];
+        reader.read_exact(&mut chunk).await.unwrap();
+    }
+
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+
+    // 没有读取任何字节就被丢弃
+    assert!(client.get_streaming("big").await.unwrap().is_some());
+    assert_eq!("PONG", client.ping(None).await.unwrap());
+    assert!(!client.is_poisoned());
+}
+
+/// A 32MB value generated on the fly is streamed to the server.
+#[tokio::test]
+async fn set_streaming_large_value() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let len = 32 * 1024 * 1024;
+    client.set_streaming("big", len as u64, Pattern::new(None)).await.unwrap();
+
+    let value = client.get("big").await.unwrap().unwrap();
+    assert_eq!(len, value.len());
+    assert!(value.iter().enumerate().all(|(i, byte)| *byte == (i % 251) as u8));
+
+    client.set_streaming("empty", 0, Pattern::new(None)).await.unwrap();
+    assert_eq!(Some(Bytes::new()), client.get("empty").await.unwrap());
+}
+
+/// A reader failing in the middle of the value poisons the client, and the
+/// incomplete command is not applied.
+#[tokio::test]
+async fn set_streaming_reader_fails() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("foo", "before".into()).await.unwrap();
+
+    let err = client
+        .set_streaming("foo", 4 * 1024 * 1024, Pattern::new(Some(1024 * 1024)))
+        .await
+        .unwrap_err();
+    assert_eq!("pattern failed", err.to_string());
+    assert!(client.is_poisoned());
+    assert!(client.get("foo").await.is_err());
+
+    // 读取端提前结束也一样
+    let mut client = Client::connect(addr).await.unwrap();
+    let err = client
+        .set_streaming("foo", 1024, tokio::io::repeat(b'x').take(10))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        Some(std::io::ErrorKind::UnexpectedEof),
+        err.downcast_ref::<std::io::Error>().map(|err| err.kind())
+    );
+    assert!(client.is_poisoned());
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(Some("before".into()), client.get("foo").await.unwrap());
+}
+
+/// Generates the bytes `i % 251`, failing after `fail_at` bytes if set.
+struct Pattern {
+    pos: usize,
+
+    fail_at: Option<usize>,
+}
+
+impl Pattern {
+    fn new(fail_at: Option<usize>) -> Pattern {
+        Pattern { pos: 0, fail_at }
+    }
+}
+
+impl AsyncRead for Pattern {
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let mut n = buf.remaining().min(64 * 1024);
+        if let Some(fail_at) = self.fail_at {
+            if self.pos >= fail_at {
+                return Poll::Ready(Err(std::io::Error::other("pattern failed")));
+            }
+            n = n.min(fail_at - self.pos);
+        }
+
+        let start = self.pos;
+        let bytes: Vec<u8> = (start..start + n).map(|i| (i % 251) as u8).collect();
+        buf.put_slice(&bytes);
+        self.pos += n;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// `UNLINK` removes large values while another client keeps being served.
+#[tokio::test]
+async fn unlink_large_values() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let keys: Vec<String> = (0..8).map(|i| format!("big:{}", i)).collect();
+    for key in &keys {
+        client.set(key, Bytes::from(vec![b'x'; 8 * 1024 * 1024])).await.unwrap();
+    }
+
+    // 另一个客户端在`UNLINK`期间持续发送`PING`
+    let (done_tx, mut done_rx) = tokio::sync::oneshot::channel::<()>();
+    let pinger = tokio::spawn(async move {
+        let mut client = Client::connect(addr).await.unwrap();
+        let mut pings = 0;
+        loop {
+            time::timeout(Duration::from_secs(1), client.ping(None))
+                .await
+                .expect("PING stalled")
+                .unwrap();
+            pings += 1;
+
+            if done_rx.try_recv().is_ok() {
+                return pings;
+            }
+        }
+    });
+
+    let mut args: Vec<&str> = keys.iter().map(|key| &key[..]).collect();
+    args.push("missing");
+    args.push("big:0");
+    assert_eq!(8, client.unlink(&args).await.unwrap());
+
+    done_tx.send(()).unwrap();
+    assert!(pinger.await.unwrap() > 0);
+
+    assert_eq!(0, client.exists(&args).await.unwrap());
+    assert_eq!(0, client.unlink(&["big:0"]).await.unwrap());
+}
+
 #[tokio::test]
 async fn receive_message_multiple_subscribed_channels() {
     let (addr, _) = start_server().await;
@@ -64,6 +1147,100 @@ async fn receive_message_multiple_subscribed_channels() {
     assert_eq!(b"howdy?", &message2.content[..]);
 }
 
+/// A subscriber can subscribe to more channels and receives the messages
+/// published on them.
+#[tokio::test]
+async fn subscriber_subscribes_to_more_channels() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+    subscriber.subscribe(&["world".into()]).await.unwrap();
+    assert_eq!(&["hello".to_string(), "world".to_string()], subscriber.get_subscribed());
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(1, client.publish("world", "howdy?".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("world", &message.channel);
+    assert_eq!(b"howdy?", &message.content[..]);
+}
+
+/// Subscribing to more channels while messages are published on an already
+/// subscribed channel never fails the handshake, and no message is lost: the
+/// ones interleaved with the confirmations are queued.
+#[tokio::test]
+async fn subscribe_while_receiving_messages() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hot".into()]).await.unwrap();
+
+    let publisher = tokio::spawn(async move {
+        let mut client = Client::connect(addr).await.unwrap();
+        for i in 0..500 {
+            client.publish("hot", i.to_string().into()).await.unwrap();
+        }
+    });
+
+    let first = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(b"0", &first.content[..]);
+
+    for i in 0..50 {
+        subscriber.subscribe(&[format!("cold-{}", i)]).await.unwrap();
+    }
+    subscriber.unsubscribe(&["cold-0".into()]).await.unwrap();
+
+    publisher.await.unwrap();
+
+    for i in 1..500 {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!("hot", &message.channel);
+        assert_eq!(i.to_string().as_bytes(), &message.content[..]);
+    }
+}
+
+/// A subscriber to both a channel and a pattern receives the messages of
+/// both, and can tell which subscription each one came through.
+#[tokio::test]
+async fn subscribe_mixed_channels_and_patterns() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client
+        .subscribe_mixed(vec!["alerts".into()], vec!["news.*".into()])
+        .await
+        .unwrap();
+    assert_eq!(&["alerts".to_string()], subscriber.get_subscribed());
+    assert_eq!(&["news.*".to_string()], subscriber.get_subscribed_patterns());
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(1, publisher.publish("alerts", "fire".into()).await.unwrap());
+    assert_eq!(1, publisher.publish("news.tech", "rust".into()).await.unwrap());
+    assert_eq!(0, publisher.publish("weather", "rain".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("alerts", &message.channel);
+    assert_eq!(b"fire", &message.content[..]);
+    assert_eq!(None, message.pattern);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("news.tech", &message.channel);
+    assert_eq!(b"rust", &message.content[..]);
+    assert_eq!(Some("news.*"), message.pattern.as_deref());
+
+    // 只订阅模式的订阅者
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe_patterns(vec!["user.*".into()]).await.unwrap();
+    assert!(subscriber.get_subscribed().is_empty());
+
+    assert_eq!(1, publisher.publish("user.1", "joined".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("user.1", &message.channel);
+    assert_eq!(Some("user.*"), message.pattern.as_deref());
+}
+
 /// test that a client accurately removes its own subscribed channel list
 /// when unsubscribing to all subscribed channels by submitting an empty vec
 #[tokio::test]
@@ -77,6 +1254,424 @@ async fn unsubscribes_from_channels() {
     assert_eq!(subscriber.get_subscribed().len(), 0);
 }
 
+/// Subscribing to an empty channel name is rejected, by the server in lenient
+/// mode and by the client itself in strict mode. The server rejects it unless
+/// configured otherwise, see `Config::pubsub_strictness`.
+#[tokio::test]
+async fn subscribe_to_empty_channel_is_rejected() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let err = client.subscribe(vec!["".into()]).await.err().unwrap();
+    assert_eq!("ERR channel name must not be empty", err.to_string());
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set_strictness(Strictness::Strict);
+    let err = client.subscribe(vec!["".into()]).await.err().unwrap();
+    assert_eq!("ERR channel name must not be empty", err.to_string());
+}
+
+/// A reply of an unexpected shape poisons the connection, so that the reply
+/// meant for a later request is never attributed to the wrong one.
+#[tokio::test]
+async fn unexpected_reply_poisons_connection() {
+    // GET的回复本该是bulk，这里先回复一个整数，然后是下一个请求的回复
+    let addr = start_scripted_server(vec![b":1\r\n", b"$3\r\nbar\r\n"]).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.get("foo").await.is_err());
+    assert!(client.is_poisoned());
+
+    let err = client.get("baz").await.err().unwrap();
+    assert_eq!(
+        Some(&ClientError::ConnectionPoisoned),
+        err.downcast_ref::<ClientError>()
+    );
+}
+
+/// A malformed reply poisons the connection.
+#[tokio::test]
+async fn malformed_reply_poisons_connection() {
+    let addr = start_scripted_server(vec![b"!oops\r\n", b"+PONG\r\n"]).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.ping(None).await.is_err());
+
+    let err = client.ping(None).await.err().unwrap();
+    assert_eq!(
+        Some(&ClientError::ConnectionPoisoned),
+        err.downcast_ref::<ClientError>()
+    );
+}
+
+/// Requests to a server which never replies fail once the timeout elapses,
+/// and poison the connection.
+#[tokio::test]
+async fn timeout_on_silent_server() {
+    let addr = start_silent_server().await;
+
+    // 默认没有超时
+    let mut client = Client::connect(addr).await.unwrap();
+    assert!(time::timeout(Duration::from_millis(200), client.get("foo")).await.is_err());
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set_timeout(Some(Duration::from_millis(100)));
+
+    let started = time::Instant::now();
+    let err = client.get("foo").await.unwrap_err();
+    assert_eq!(Some(&ClientError::TimedOut), err.downcast_ref::<ClientError>());
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert!(client.is_poisoned());
+
+    let err = client.ping(None).await.unwrap_err();
+    assert_eq!(
+        Some(&ClientError::ConnectionPoisoned),
+        err.downcast_ref::<ClientError>()
+    );
+
+    // 订阅的确认同样受超时限制
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set_timeout(Some(Duration::from_millis(100)));
+    let err = client.subscribe(vec!["news".into()]).await.err().unwrap();
+    assert_eq!(Some(&ClientError::TimedOut), err.downcast_ref::<ClientError>());
+}
+
+/// Batches larger than the chunk size are split and reassembled in order.
+#[tokio::test]
+async fn mset_mget_large_batches() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // 10000不是块大小的整数倍，最后一块不满
+    client.set_chunk_size(300);
+
+    let keys: Vec<String> = (0..10_000).map(|i| format!("key:{}", i)).collect();
+    let pairs: Vec<(&str, Bytes)> = keys
+        .iter()
+        .map(|key| (&key[..], Bytes::from(format!("value of {}", key))))
+        .collect();
+
+    client.mset(&pairs).await.unwrap();
+    assert_eq!(10_000, client.dbsize().await.unwrap());
+
+    // 在请求中穿插不存在的key
+    let mut requested = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        requested.push(&key[..]);
+        if i % 7 == 0 {
+            requested.push("missing");
+        }
+    }
+
+    let values = client.mget(&requested).await.unwrap();
+    assert_eq!(requested.len(), values.len());
+
+    for (key, value) in requested.iter().zip(values) {
+        match *key {
+            "missing" => assert_eq!(None, value),
+            key => assert_eq!(Some(Bytes::from(format!("value of {}", key))), value),
+        }
+    }
+
+    assert_eq!(Vec::<Option<Bytes>>::new(), client.mget(&[]).await.unwrap());
+}
+
+#[tokio::test]
+async fn command_count_and_docs() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let known = [
+        "get", "publish", "set", "strlen", "subscribe", "unsubscribe", "ping", "append",
+        "setrange", "object", "incrbyfloat", "mget", "keys", "scan", "dbsize", "debug",
+        "flushdb", "exchange", "type", "setex", "psetex", "health", "command", "cas", "mset",
+        "wait", "pubsub", "getex", "pttl", "touch", "hello",
+        "config", "hotkeys", "incr", "decr", "incrby", "decrby",
+        "exists", "del", "getrange", "memory", "unlink", "lcs",
+        "multi", "exec", "discard", "psubscribe", "punsubscribe", "dump", "restore", "expiremany", "lpush", "rpush", "lpop", "rpop", "lrange", "hset", "hget", "hdel", "hgetall", "info",
+        "compress", "psync", "replconf", "xadd", "xrange", "xsubscribe",
+    ];
+
+    assert_eq!(known.len() as u64, client.command_count().await.unwrap());
+
+    let docs: Vec<Frame> = client.query(&["command".into(), "docs".into()]).await.unwrap();
+    let names: HashSet<_> = docs
+        .iter()
+        .step_by(2)
+        .map(|name| match name {
+            Frame::Bulk(name) => std::str::from_utf8(name).unwrap().to_string(),
+            frame => panic!("unexpected frame {:?}", frame),
+        })
+        .collect();
+    assert_eq!(known.iter().map(|name| name.to_string()).collect::<HashSet<_>>(), names);
+
+    let docs: Vec<Frame> = client
+        .query(&["command".into(), "docs".into(), "SET".into(), "missing".into()])
+        .await
+        .unwrap();
+    match &docs[..] {
+        [Frame::Bulk(name), Frame::Array(doc)] => {
+            assert_eq!("set", name);
+            assert!(matches!(
+                &doc[..],
+                [Frame::Bulk(arity), Frame::Integer(3), Frame::Bulk(variadic), Frame::Integer(1)]
+                    if arity == "arity" && variadic == "variadic"
+            ));
+        }
+        frames => panic!("unexpected frames {:?}", frames),
+    }
+}
+
+/// WAIT replies immediately, as there are no replicas to wait for.
+#[tokio::test]
+async fn wait_replies_without_replicas() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+
+    // 超时为0表示一直等待，服务器必须立刻回复
+    let replicas = time::timeout(Duration::from_secs(1), client.wait(3, Duration::ZERO))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(0, replicas);
+
+    // 非整数的参数是协议错误，连接会被关闭
+    let reply: my_mini_redis::Result<u64> = client
+        .query(&["wait".into(), "one".into(), "0".into()])
+        .await;
+    assert!(reply.is_err());
+}
+
+#[tokio::test]
+async fn pubsub_stats_counts_publishes() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // 从未被订阅的频道没有统计
+    let stats = client.pubsub_stats("news").await.unwrap();
+    assert_eq!(0, stats.published);
+    assert_eq!(None, stats.last_published);
+
+    let first = Client::connect(addr).await.unwrap().subscribe(vec!["news".into()]).await.unwrap();
+    let second = Client::connect(addr).await.unwrap().subscribe(vec!["news".into()]).await.unwrap();
+
+    let before = SystemTime::now() - Duration::from_millis(1);
+    for _ in 0..3 {
+        client.publish("news", "hello".into()).await.unwrap();
+    }
+
+    drop(second);
+    time::sleep(Duration::from_millis(50)).await;
+
+    let stats = client.pubsub_stats("news").await.unwrap();
+    assert_eq!(3, stats.published);
+    assert!(stats.last_published.unwrap() >= before);
+    assert_eq!(2, stats.peak_receivers);
+    assert_eq!(1, stats.receivers);
+
+    drop(first);
+}
+
+/// `SUBSCRIBE ... SINCE` reports the number of messages published since the
+/// timestamp.
+#[tokio::test]
+async fn subscribe_since_reports_backlog() {
+    let (addr, _) = start_server().await;
+    let mut publisher = Client::connect(addr).await.unwrap();
+
+    // 频道需要被订阅过才会被统计
+    let subscriber = Client::connect(addr).await.unwrap().subscribe(vec!["news".into()]).await.unwrap();
+
+    for _ in 0..3 {
+        publisher.publish("news", "old".into()).await.unwrap();
+    }
+
+    time::sleep(Duration::from_millis(20)).await;
+    let disconnected_at = SystemTime::now();
+    drop(subscriber);
+
+    for _ in 0..2 {
+        publisher.publish("news", "missed".into()).await.unwrap();
+    }
+
+    let client = Client::connect(addr).await.unwrap();
+    let (mut subscriber, backlogs) = client
+        .subscribe_since(vec!["news".into(), "sports".into()], disconnected_at)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec![
+            Backlog {
+                channel: "news".into(),
+                published: 2,
+                truncated: false,
+            },
+            Backlog {
+                channel: "sports".into(),
+                published: 0,
+                truncated: false,
+            },
+        ],
+        backlogs
+    );
+    assert_eq!(&["news".to_string(), "sports".to_string()][..], subscriber.get_subscribed());
+
+    // 之后发布的消息正常收到
+    publisher.publish("news", "new".into()).await.unwrap();
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("new", message.content);
+}
+
+/// Channels are forgotten once their last subscriber unsubscribes or
+/// disconnects, while their statistics are kept for `SUBSCRIBE ... SINCE`.
+#[tokio::test]
+async fn empty_channels_are_removed() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let channels = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<HashSet<_>>();
+
+    let mut first = Client::connect(addr)
+        .await
+        .unwrap()
+        .subscribe(vec!["news".into(), "sports".into(), "weather".into()])
+        .await
+        .unwrap();
+    let second = Client::connect(addr).await.unwrap().subscribe(vec!["news".into()]).await.unwrap();
+
+    let listed = client.pubsub_channels(None).await.unwrap();
+    assert_eq!(channels(&["news", "sports", "weather"]), listed.into_iter().collect());
+    assert_eq!(vec!["news".to_string()], client.pubsub_channels(Some("n*")).await.unwrap());
+
+    first.unsubscribe(&["weather".into()]).await.unwrap();
+    let listed = client.pubsub_channels(None).await.unwrap();
+    assert_eq!(channels(&["news", "sports"]), listed.into_iter().collect());
+
+    // 断开连接释放剩下的频道，仍有订阅者的频道保留
+    drop(first);
+    time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(vec!["news".to_string()], client.pubsub_channels(None).await.unwrap());
+
+    drop(second);
+    time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(0, client.publish("news", "hello".into()).await.unwrap());
+    assert!(client.pubsub_channels(None).await.unwrap().is_empty());
+
+    let stats = client.pubsub_stats("news").await.unwrap();
+    assert_eq!(1, stats.published);
+    assert_eq!(2, stats.peak_receivers);
+    assert_eq!(0, stats.receivers);
+}
+
+/// Subscribers joining a channel while others leave it keep receiving its
+/// messages.
+#[tokio::test(flavor = "multi_thread")]
+async fn channel_release_races_with_subscribe() {
+    let (addr, _) = start_server().await;
+    let mut publisher = Client::connect(addr).await.unwrap();
+
+    let mut tasks = vec![];
+    for _ in 0..4 {
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..25 {
+                let mut subscriber = Client::connect(addr).await.unwrap().subscribe(vec!["hot".into()]).await.unwrap();
+                subscriber.unsubscribe(&[]).await.unwrap();
+            }
+        }));
+    }
+
+    let mut subscriber = Client::connect(addr).await.unwrap().subscribe(vec!["hot".into()]).await.unwrap();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(1, publisher.publish("hot", "still here".into()).await.unwrap());
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("still here", message.content);
+    assert_eq!(vec!["hot".to_string()], publisher.pubsub_channels(None).await.unwrap());
+}
+
+#[tokio::test]
+async fn cas_compares_current_value() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.cas("foo", None, Some("1".into())).await.unwrap());
+    assert!(!client.cas("foo", None, Some("2".into())).await.unwrap());
+    assert!(!client.cas("foo", Some("2".into()), Some("3".into())).await.unwrap());
+    assert_eq!(Some("1".into()), client.get("foo").await.unwrap());
+
+    // 新值为nil时删除键
+    assert!(client.cas("foo", Some("1".into()), None).await.unwrap());
+    assert_eq!(None, client.get("foo").await.unwrap());
+}
+
+/// Concurrent read-modify-write cycles on a structured value do not lose
+/// updates.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn modify_concurrent_counter() {
+    let (addr, _) = start_server().await;
+
+    let mut tasks = Vec::new();
+    for _ in 0..20 {
+        tasks.push(tokio::spawn(async move {
+            let mut client = Client::connect(addr).await.unwrap();
+
+            for _ in 0..100 {
+                client
+                    .modify("counter", 1000, Duration::from_micros(100), |value| {
+                        let count = match value {
+                            Some(value) => parse_count(&value)?,
+                            None => 0,
+                        };
+                        Ok(Some(format!("{{\"count\":{}}}", count + 1).into()))
+                    })
+                    .await
+                    .unwrap();
+            }
+        }));
+    }
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let value = client.get("counter").await.unwrap().unwrap();
+    assert_eq!(2000, parse_count(&value).unwrap());
+}
+
+#[tokio::test]
+async fn modify_reports_aborts() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("counter", "not a count".into()).await.unwrap();
+
+    let err = client
+        .modify("counter", 3, Duration::from_millis(1), |value| {
+            parse_count(&value.unwrap_or_default())?;
+            Ok(None)
+        })
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ModifyError::Aborted(_)));
+    assert_eq!(Some("not a count".into()), client.get("counter").await.unwrap());
+}
+
+fn parse_count(value: &[u8]) -> my_mini_redis::Result<u64> {
+    let value = std::str::from_utf8(value)?;
+
+    match value.strip_prefix("{\"count\":").and_then(|v| v.strip_suffix('}')) {
+        Some(count) => Ok(count.parse()?),
+        None => Err(format!("invalid counter {:?}", value).into()),
+    }
+}
 
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -86,3 +1681,58 @@ async fn start_server() -> (SocketAddr, JoinHandle<()>) {
 
     (addr, handle)
 }
+
+/// Moves the clock of the server started by `start_server` forward.
+///
+/// With the `uring` feature, the server runs on a runtime of its own, whose
+/// clock is not paused by `time::pause`, so the time really passes.
+async fn advance_server_clock(duration: Duration) {
+    if cfg!(feature = "uring") {
+        time::sleep(duration).await;
+    } else {
+        time::pause();
+        time::advance(duration).await;
+        time::resume();
+    }
+}
+
+/// Start a fake server which reads the requests of its clients but never
+/// replies.
+async fn start_silent_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            tokio::spawn(async move {
+                let mut buf = [0; 1024];
+                while socket.read(&mut buf).await.unwrap_or(0) > 0 {}
+            });
+        }
+    });
+
+    addr
+}
+
+/// Start a fake server which answers each request it receives with the next
+/// of `replies`, written as is.
+async fn start_scripted_server(replies: Vec<&'static [u8]>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 1024];
+
+        for reply in replies {
+            if socket.read(&mut buf).await.unwrap_or(0) == 0 {
+                return;
+            }
+            socket.write_all(reply).await.unwrap();
+        }
+    });
+
+    addr
+}
@@ -0,0 +1,154 @@
+use my_mini_redis::clients::{Client, ClientError, ShardedClient};
+use my_mini_redis::cluster::{crc16, crc16_hash_slot, hash_tag, SLOT_COUNT};
+use my_mini_redis::cmd::{Command, Get};
+use my_mini_redis::{server, Frame};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// The reference values of the Redis cluster specification.
+#[test]
+fn reference_slots() {
+    assert_eq!(0x31c3, crc16(b"123456789"));
+    assert_eq!(0, crc16(b""));
+
+    assert_eq!(12739, crc16_hash_slot(b"123456789"));
+    assert_eq!(12182, crc16_hash_slot(b"foo"));
+    assert_eq!(5061, crc16_hash_slot(b"bar"));
+    assert_eq!(3443, crc16_hash_slot(b"{user1000}.following"));
+    assert_eq!(3443, crc16_hash_slot(b"{user1000}.followers"));
+    assert_eq!(crc16_hash_slot(b"user1000"), crc16_hash_slot(b"{user1000}.following"));
+
+    assert!((0..1000).all(|i| crc16_hash_slot(format!("key:{}", i).as_bytes()) < SLOT_COUNT));
+}
+
+/// The hash tag examples of the specification.
+#[test]
+fn hash_tags() {
+    assert_eq!(b"user1000", hash_tag(b"{user1000}.following"));
+    assert_eq!(b"user1000", hash_tag(b"foo{user1000}"));
+
+    // 只有第一个`{`和它之后的第一个`}`
+    assert_eq!(b"bar", hash_tag(b"foo{bar}{zap}"));
+    assert_eq!(b"{bar", hash_tag(b"foo{{bar}}zap"));
+
+    // `{}`为空或者没有闭合时哈希整个key
+    assert_eq!(b"foo{}{bar}", hash_tag(b"foo{}{bar}"));
+    assert_eq!(b"foo{bar", hash_tag(b"foo{bar"));
+    assert_eq!(b"foo}bar{", hash_tag(b"foo}bar{"));
+}
+
+#[test]
+fn command_keys() {
+    let cmd = Command::Get(Get::new("foo"));
+    assert_eq!(vec![&b"foo"[..]], cmd.keys());
+
+    let frame = |args: &[&'static str]| {
+        Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::from_static(arg.as_bytes()))).collect())
+    };
+
+    let cmd = Command::from_frame(frame(&["mset", "a", "1", "b", "2"])).unwrap();
+    assert_eq!(vec![&b"a"[..], &b"b"[..]], cmd.keys());
+
+    let cmd = Command::from_frame(frame(&["lcs", "a", "b", "len"])).unwrap();
+    assert_eq!(vec![&b"a"[..], &b"b"[..]], cmd.keys());
+
+    let cmd = Command::from_frame(frame(&["object", "encoding", "a"])).unwrap();
+    assert_eq!(vec![&b"a"[..]], cmd.keys());
+
+    // 频道和模式都不是key
+    for args in [&["publish", "news", "hello"][..], &["keys", "*"], &["ping"], &["dbsize"]] {
+        assert!(Command::from_frame(frame(args)).unwrap().keys().is_empty());
+    }
+}
+
+/// Keys are spread evenly across the shards, and each is only stored on the
+/// shard it is routed to.
+#[tokio::test]
+async fn keys_spread_across_shards() {
+    let addrs = start_servers(3).await;
+    let mut client = ShardedClient::connect(addrs.clone()).await.unwrap();
+    assert_eq!(3, client.shard_count());
+
+    let mut counts = [0usize; 3];
+    for i in 0..3000 {
+        let key = format!("key:{}", i);
+        counts[client.shard_of(key.as_bytes())] += 1;
+        client.set(&key, Bytes::from(key.clone())).await.unwrap();
+    }
+
+    // 每个分片大约1000个key
+    for count in counts {
+        assert!((800..1200).contains(&count), "{:?}", counts);
+    }
+
+    for (shard, addr) in addrs.iter().enumerate() {
+        let mut direct = Client::connect(addr).await.unwrap();
+        assert_eq!(counts[shard] as u64, direct.dbsize().await.unwrap());
+    }
+
+    for i in (0..3000).step_by(97) {
+        let key = format!("key:{}", i);
+        assert_eq!(Some(Bytes::from(key.clone())), client.get(&key).await.unwrap());
+    }
+
+    // 槽是按范围划分的
+    assert_eq!(2, client.shard_of(b"foo"));
+    assert_eq!(0, client.shard_of(b"bar"));
+}
+
+/// Keys sharing a hash tag are on the same shard, so multi-key commands on
+/// them succeed, while the others are rejected.
+#[tokio::test]
+async fn hash_tags_colocate_keys() {
+    let addrs = start_servers(4).await;
+    let mut client = ShardedClient::connect(addrs).await.unwrap();
+
+    let keys: Vec<String> = (0..100).map(|i| format!("{{user1000}}.field{}", i)).collect();
+    let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+
+    for key in &keys {
+        client.set(key, "x".into()).await.unwrap();
+    }
+    assert_eq!(100, client.exists(&keys).await.unwrap());
+
+    let args: Vec<Bytes> = ["mget", keys[0], keys[1], keys[2]]
+        .iter()
+        .map(|arg| Bytes::copy_from_slice(arg.as_bytes()))
+        .collect();
+    let values: Vec<Option<Bytes>> = client.query(&args).await.unwrap();
+    assert_eq!(3, values.len());
+
+    assert_eq!(100, client.del(&keys).await.unwrap());
+
+    // "foo"和"bar"在不同的分片上
+    let err = client.del(&["foo", "bar"]).await.unwrap_err();
+    assert_eq!(Some(&ClientError::CrossShard), err.downcast_ref::<ClientError>());
+
+    let args = [Bytes::from("mset"), "foo".into(), "1".into(), "bar".into(), "2".into()];
+    let err = client.query::<Frame>(&args).await.unwrap_err();
+    assert_eq!(Some(&ClientError::CrossShard), err.downcast_ref::<ClientError>());
+
+    // 被拒绝的命令没有发送，客户端仍然可用
+    assert_eq!(Bytes::from("PONG"), client.query::<Bytes>(&[Bytes::from("ping")]).await.unwrap());
+    assert!(client.get("foo").await.unwrap().is_none());
+}
+
+#[test]
+fn no_shards() {
+    assert!(ShardedClient::new(vec![]).is_err());
+}
+
+async fn start_servers(count: usize) -> Vec<SocketAddr> {
+    let mut addrs = vec![];
+
+    for _ in 0..count {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        addrs.push(listener.local_addr().unwrap());
+
+        tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+    }
+
+    addrs
+}
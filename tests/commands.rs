@@ -0,0 +1,156 @@
+use my_mini_redis::clients::{
+    BufferedClient, Client, Commands, ConnectOptions, MockClient, Namespaced, Pool,
+};
+use my_mini_redis::server;
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time::{self, Instant};
+
+/// A small service caching rendered pages, written against `Commands` as an
+/// application would.
+struct PageCache<C> {
+    client: C,
+}
+
+impl<C: Commands> PageCache<C> {
+    /// Returns the page from the cache, rendering and caching it on a miss.
+    async fn render(&mut self, page: &str) -> my_mini_redis::Result<Bytes> {
+        let key = format!("page:{}", page);
+
+        if let Some(html) = self.client.get(&key).await? {
+            return Ok(html);
+        }
+
+        let html = Bytes::from(format!("<h1>{}</h1>", page));
+        self.client
+            .set_expires(&key, html.clone(), Duration::from_secs(60))
+            .await?;
+        self.client.publish("rendered", page.to_string().into()).await?;
+        Ok(html)
+    }
+
+    /// Removes the page from the cache, returns `true` if it was cached.
+    async fn invalidate(&mut self, page: &str) -> my_mini_redis::Result<bool> {
+        let key = format!("page:{}", page);
+        Ok(self.client.del(&[&key]).await? > 0)
+    }
+
+    async fn is_cached(&mut self, page: &str) -> my_mini_redis::Result<bool> {
+        let key = format!("page:{}", page);
+        Ok(self.client.exists(&[&key]).await? > 0)
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn service_on_mock_client() {
+    let mock = MockClient::new();
+    let mut cache = PageCache { client: mock.clone() };
+
+    assert!(!cache.is_cached("home").await.unwrap());
+    assert_eq!("<h1>home</h1>", cache.render("home").await.unwrap());
+    assert!(cache.is_cached("home").await.unwrap());
+
+    // 第二次从缓存中读取，不再渲染
+    cache.render("home").await.unwrap();
+    assert_eq!(vec![Bytes::from("home")], mock.published("rendered"));
+
+    assert!(cache.invalidate("home").await.unwrap());
+    assert!(!cache.invalidate("home").await.unwrap());
+
+    // 缓存的页面在60秒后过期
+    cache.render("about").await.unwrap();
+    time::advance(Duration::from_secs(61)).await;
+    assert!(!cache.is_cached("about").await.unwrap());
+}
+
+/// Errors scripted on the mock reach the caller of the service, and only
+/// affect the scripted calls.
+#[tokio::test]
+async fn injected_errors_propagate() {
+    let mock = MockClient::new();
+    let mut cache = PageCache { client: mock.clone() };
+
+    mock.fail_next("get", "ERR cache unavailable");
+    let err = cache.render("home").await.unwrap_err();
+    assert_eq!("ERR cache unavailable", err.to_string());
+    assert!(!cache.is_cached("home").await.unwrap());
+
+    // 渲染成功但无法写入缓存
+    mock.fail_next("set_expires", "ERR out of memory");
+    let err = cache.render("home").await.unwrap_err();
+    assert_eq!("ERR out of memory", err.to_string());
+    assert!(mock.published("rendered").is_empty());
+
+    assert_eq!("<h1>home</h1>", cache.render("home").await.unwrap());
+    assert!(cache.is_cached("home").await.unwrap());
+}
+
+#[tokio::test(start_paused = true)]
+async fn injected_latency() {
+    let mock = MockClient::new();
+    mock.set_latency(Some(Duration::from_millis(100)));
+
+    let mut cache = PageCache { client: mock.clone() };
+
+    let start = Instant::now();
+    cache.is_cached("home").await.unwrap();
+    assert_eq!(Duration::from_millis(100), start.elapsed());
+
+    // 调用方的超时先于回复到达
+    let res = time::timeout(Duration::from_millis(50), cache.render("home")).await;
+    assert!(res.is_err());
+
+    mock.set_latency(None);
+    let start = Instant::now();
+    cache.render("home").await.unwrap();
+    assert_eq!(Duration::ZERO, start.elapsed());
+}
+
+/// The same code runs on every client, through a trait object.
+#[tokio::test]
+async fn every_client_implements_commands() {
+    let addr = start_server().await;
+
+    let pool = Pool::new(addr, ConnectOptions::default());
+
+    let mut clients: Vec<(&str, Box<dyn Commands>)> = vec![
+        ("client", Box::new(Client::connect(addr).await.unwrap())),
+        ("buffered", Box::new(BufferedClient::buffer(Client::connect(addr).await.unwrap()))),
+        ("pooled", Box::new(pool.get().await.unwrap())),
+        ("namespaced", Box::new(Namespaced::new(Client::connect(addr).await.unwrap(), "ns"))),
+        ("mock", Box::new(MockClient::new())),
+    ];
+
+    for (name, client) in &mut clients {
+        let key = format!("{}-key", name);
+        let client = client.as_mut();
+
+        assert_eq!(&b"PONG"[..], &client.ping(None).await.unwrap()[..]);
+        client.set(&key, "1".into()).await.unwrap();
+        client
+            .set_expires("expiring", "2".into(), Duration::from_secs(100))
+            .await
+            .unwrap();
+        assert_eq!(Some("1".into()), client.get(&key).await.unwrap());
+        assert_eq!(2, client.exists(&[&key, "expiring", "missing"]).await.unwrap());
+        assert_eq!(1, client.del(&[&key, "missing"]).await.unwrap());
+        assert_eq!(None, client.get(&key).await.unwrap());
+        assert_eq!(0, client.publish("channel", "hi".into()).await.unwrap());
+    }
+
+    // 命名空间中的key带有前缀
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(1, client.exists(&["ns:expiring"]).await.unwrap());
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
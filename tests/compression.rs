@@ -0,0 +1,181 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::connection::DEFAULT_COMPRESSION_THRESHOLD;
+use my_mini_redis::{server, Connection, Frame};
+
+use bytes::Bytes;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A large, repetitive value is written and read back in far fewer bytes once
+/// compression is negotiated, and arrives unchanged.
+#[tokio::test]
+async fn large_value_round_trips_compressed() {
+    let addr = start_server().await;
+    let value = Bytes::from("the quick brown fox jumps over the lazy dog. ".repeat(20_000));
+
+    let (mut plain, plain_bytes) = counting_client(addr).await;
+    plain.set("plain", value.clone()).await.unwrap();
+    assert_eq!(Some(value.clone()), plain.get("plain").await.unwrap());
+
+    let (mut compressed, compressed_bytes) = counting_client(addr).await;
+    assert!(compressed.compress(DEFAULT_COMPRESSION_THRESHOLD).await.unwrap());
+    compressed.set("compressed", value.clone()).await.unwrap();
+    assert_eq!(Some(value.clone()), compressed.get("compressed").await.unwrap());
+
+    // 两个方向都传输了两次value
+    let plain_bytes = plain_bytes.load(Ordering::Relaxed);
+    let compressed_bytes = compressed_bytes.load(Ordering::Relaxed);
+    assert!(plain_bytes > 2 * value.len() as u64);
+    assert!(compressed_bytes * 10 < plain_bytes, "{} bytes compressed, {} plain", compressed_bytes, plain_bytes);
+
+    // 另一个连接写入的值被解压后保存
+    assert_eq!(Some(value.clone()), plain.get("compressed").await.unwrap());
+    assert_eq!(Some(value.clone()), compressed.get("plain").await.unwrap());
+
+    // 压缩的回复也能被流式读取
+    let mut reader = compressed.get_streaming("plain").await.unwrap().unwrap();
+    assert_eq!(value.len(), reader.len());
+    let mut streamed = vec![];
+    reader.read_to_end(&mut streamed).await.unwrap();
+    assert_eq!(value, streamed);
+}
+
+/// Values below the threshold, and values which do not shrink, are sent as
+/// plain bulk strings, the others as compressed bulk strings.
+#[tokio::test]
+async fn only_values_which_shrink_are_compressed() {
+    let (client, mut server) = tokio::io::duplex(64 * 1024);
+    let mut client = Connection::new(client);
+    client.set_compression(Some(16));
+
+    let short = Bytes::from("aaaaaaaa");
+    let incompressible: Bytes = (0..64u64).map(|i| (i.wrapping_mul(0x9e37_79b9) >> 7) as u8).collect();
+    let repetitive = Bytes::from("a".repeat(64));
+
+    for value in [&short, &incompressible, &repetitive] {
+        client.write_frame(&Frame::Bulk(value.clone())).await.unwrap();
+    }
+    drop(client);
+
+    let mut raw = vec![];
+    server.read_to_end(&mut raw).await.unwrap();
+
+    let mut expected = b"$8\r\naaaaaaaa\r\n$64\r\n".to_vec();
+    expected.extend_from_slice(&incompressible);
+    expected.extend_from_slice(b"\r\n&");
+    assert!(raw.starts_with(&expected));
+    assert!(raw.len() < expected.len() + 64);
+
+    // 协商了压缩的一端读出原始的值
+    let (mut client, server) = tokio::io::duplex(64 * 1024);
+    let mut server = Connection::new(server);
+    server.set_compression(Some(16));
+    client.write_all(&raw).await.unwrap();
+
+    for value in [short, incompressible, repetitive] {
+        match server.read_frame().await.unwrap() {
+            Some(Frame::Bulk(data)) => assert_eq!(value, data),
+            frame => panic!("unexpected frame {:?}", frame),
+        }
+    }
+}
+
+/// Compression is disabled again with `COMPRESS none`, an unknown algorithm is
+/// rejected, and the command is not allowed in a transaction.
+#[tokio::test]
+async fn compress_command_errors() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client.query::<Frame>(&["compress".into(), "zstd".into()]).await.unwrap_err();
+    assert_eq!("ERR unsupported compression algorithm 'zstd'", err.to_string());
+
+    let reply: Frame = client.query(&["compress".into(), "lz4".into(), "0".into()]).await.unwrap();
+    assert_eq!(reply, "OK");
+    let reply: Frame = client.query(&["compress".into(), "none".into()]).await.unwrap();
+    assert_eq!(reply, "OK");
+
+    // 服务器不再压缩回复，客户端也没有启用压缩
+    client.set("foo", "a".repeat(4096).into()).await.unwrap();
+    assert_eq!(Some(Bytes::from("a".repeat(4096))), client.get("foo").await.unwrap());
+
+    let reply: Frame = client.query(&["multi".into()]).await.unwrap();
+    assert_eq!(reply, "OK");
+    let err = client.query::<Frame>(&["compress".into(), "lz4".into()]).await.unwrap_err();
+    assert_eq!("ERR Command not allowed inside a transaction", err.to_string());
+}
+
+/// A compressed bulk string sent without negotiating compression is a
+/// protocol error, so the server closes the connection.
+#[tokio::test]
+async fn compressed_bulk_rejected_unless_negotiated() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*2\r\n$3\r\nGET\r\n&3\r\nfoo\r\n").await.unwrap();
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    assert!(response.is_empty());
+}
+
+/// Creates a client whose traffic, in both directions, is counted by the
+/// returned counter.
+async fn counting_client(addr: SocketAddr) -> (Client, Arc<AtomicU64>) {
+    let bytes = Arc::new(AtomicU64::new(0));
+    let stream = CountingStream {
+        inner: TcpStream::connect(addr).await.unwrap(),
+        bytes: bytes.clone(),
+    };
+
+    (Client::new(stream), bytes)
+}
+
+#[derive(Debug)]
+struct CountingStream {
+    inner: TcpStream,
+
+    bytes: Arc<AtomicU64>,
+}
+
+impl AsyncRead for CountingStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.bytes.fetch_add((buf.filled().len() - before) as u64, Ordering::Relaxed);
+        poll
+    }
+}
+
+impl AsyncWrite for CountingStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            self.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -0,0 +1,279 @@
+use my_mini_redis::connection::ReadBufferSizing;
+use my_mini_redis::frame::{self, DEFAULT_MAX_FRAME_LEN};
+use my_mini_redis::{Connection, Frame};
+
+use std::io::Cursor;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Reads filling the buffer make it grow, but only after a streak of them,
+/// and never beyond the maximum capacity.
+#[test]
+fn read_buffer_grows_on_full_reads() {
+    let mut sizing = ReadBufferSizing::new(1024, 4096);
+
+    for _ in 0..3 {
+        sizing.record(1024, 10);
+    }
+    assert_eq!(1024, sizing.capacity());
+
+    sizing.record(1024, 10);
+    assert_eq!(2048, sizing.capacity());
+
+    for _ in 0..100 {
+        let capacity = sizing.capacity();
+        sizing.record(capacity, 10);
+    }
+    assert_eq!(4096, sizing.capacity());
+    assert!(sizing.avg_frames_per_read() > 9.0);
+}
+
+/// A single full read among sparse ones does not make the buffer grow.
+#[test]
+fn read_buffer_ignores_isolated_full_reads() {
+    let mut sizing = ReadBufferSizing::new(1024, 4096);
+
+    for i in 0..100 {
+        if i % 3 == 0 {
+            sizing.record(1024, 10);
+        } else {
+            sizing.record(100, 1);
+        }
+    }
+    assert_eq!(1024, sizing.capacity());
+}
+
+/// Reads using a small part of a grown buffer make it shrink back, down to
+/// the initial capacity, after which it does not grow again.
+#[test]
+fn read_buffer_shrinks_on_sparse_reads() {
+    let mut sizing = ReadBufferSizing::new(1024, 8192);
+
+    for _ in 0..100 {
+        let capacity = sizing.capacity();
+        sizing.record(capacity, 10);
+    }
+    assert_eq!(8192, sizing.capacity());
+
+    // 平均值需要一些读取才会降下来，之后每一连串稀疏的读取将buffer减半
+    for _ in 0..31 {
+        sizing.record(100, 1);
+    }
+    assert_eq!(8192, sizing.capacity());
+
+    for _ in 0..1000 {
+        sizing.record(100, 1);
+    }
+    assert_eq!(1024, sizing.capacity());
+    assert!(sizing.avg_bytes_per_read() < 101.0);
+}
+
+/// Reads between a quarter of the buffer and a full buffer leave it alone.
+#[test]
+fn read_buffer_is_stable_on_moderate_reads() {
+    let mut sizing = ReadBufferSizing::new(1024, 8192);
+
+    for _ in 0..8 {
+        let capacity = sizing.capacity();
+        sizing.record(capacity, 10);
+    }
+    assert_eq!(4096, sizing.capacity());
+
+    for _ in 0..1000 {
+        sizing.record(2000, 5);
+    }
+    assert_eq!(4096, sizing.capacity());
+}
+
+/// A pipelined burst makes the read buffer of the connection grow, and all
+/// the frames are still parsed.
+#[tokio::test]
+async fn read_buffer_grows_on_pipelined_burst() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let writer = tokio::spawn(async move {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+
+        let burst = b"+PING\r\n".repeat(10_000);
+        stream.write_all(&burst).await.unwrap();
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let mut connection = Connection::with_capacity(socket, 64);
+    connection.set_max_read_buffer_capacity(4096);
+
+    let mut frames = 0;
+    while let Some(frame) = connection.read_frame().await.unwrap() {
+        assert!(matches!(frame, Frame::Simple(ref s) if s == "PING"));
+        frames += 1;
+    }
+    writer.await.unwrap();
+
+    assert_eq!(10_000, frames);
+    assert!(connection.read_buffer_sizing().capacity() > 64);
+}
+
+/// After a large frame, the read buffer keeps its grown capacity only until a
+/// few reads show it is no longer needed, even if it never becomes empty.
+#[tokio::test]
+async fn read_buffer_reclaimed_after_large_frame() {
+    let (mut client, server) = tokio::io::duplex(64 * 1024);
+    let mut server = Connection::new(server);
+
+    // 每次写入都带上下一个frame的第一个字节，buffer在读取之前总是有数据
+    let mut large = format!("${}\r\n", 1024 * 1024).into_bytes();
+    large.extend_from_slice(&[b'x'; 1024 * 1024]);
+    large.extend_from_slice(b"\r\n+");
+
+    let (written, received) = tokio::join!(client.write_all(&large), server.read_frame());
+    written.unwrap();
+    assert!(matches!(received.unwrap(), Some(Frame::Bulk(value)) if value.len() == 1024 * 1024));
+
+    // 下一次读取之后buffer还保留着大frame的容量
+    client.write_all(b"PING\r\n+").await.unwrap();
+    server.read_frame().await.unwrap().unwrap();
+    assert!(server.read_buffer_capacity() > 512 * 1024);
+
+    for _ in 0..10 {
+        client.write_all(b"PING\r\n+").await.unwrap();
+        assert!(matches!(server.read_frame().await.unwrap(), Some(Frame::Simple(s)) if s == "PING"));
+    }
+
+    let capacity = server.read_buffer_sizing().capacity();
+    assert!(server.read_buffer_capacity() <= 2 * capacity, "{}", server.read_buffer_capacity());
+}
+
+/// A length prefix above the maximum is rejected as soon as the header is
+/// read, while lengths up to the maximum are accepted.
+#[test]
+fn frame_length_limit() {
+    for src in [&b"$11\r\n"[..], b"*11\r\n", b"*1\r\n$11\r\n"] {
+        let err = Frame::check_with_max_len(&mut Cursor::new(src), 10).unwrap_err();
+        assert!(matches!(err, frame::Error::TooLarge), "{:?}", src);
+
+        let err = Frame::parse_with_max_len(&mut Cursor::new(src), 10).unwrap_err();
+        assert!(matches!(err, frame::Error::TooLarge), "{:?}", src);
+    }
+
+    let src = b"*2\r\n$10\r\n0123456789\r\n$1\r\nx\r\n";
+    Frame::check_with_max_len(&mut Cursor::new(&src[..]), 10).unwrap();
+    Frame::parse_with_max_len(&mut Cursor::new(&src[..]), 10).unwrap();
+
+    let src = format!("${}\r\n", DEFAULT_MAX_FRAME_LEN + 1);
+    let err = Frame::check(&mut Cursor::new(src.as_bytes())).unwrap_err();
+    assert!(matches!(err, frame::Error::TooLarge));
+}
+
+/// `read_frame` fails with `TooLarge` on an oversized length prefix.
+#[tokio::test]
+async fn read_frame_too_large() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let writer = tokio::spawn(async move {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"$99999999999\r\n").await.unwrap();
+        stream
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let mut connection = Connection::new(socket);
+    connection.set_max_frame_len(1024);
+
+    let err = connection.read_frame().await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<frame::Error>(), Some(frame::Error::TooLarge)));
+
+    drop(writer.await.unwrap());
+}
+
+/// A peeked frame is returned again by `read_frame`, and peeking does not
+/// skip or duplicate data.
+#[tokio::test]
+async fn peek_then_read_frame() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let writer = tokio::spawn(async move {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"*2\r\n$3\r\nGET\r\n").await.unwrap();
+        tokio::task::yield_now().await;
+        stream.write_all(b"$5\r\nhello\r\n+NEXT\r\n").await.unwrap();
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let mut connection = Connection::new(socket);
+
+    let peeked = connection.peek_frame().await.unwrap().unwrap();
+    assert_eq!("GET hello", peeked.to_string());
+
+    let again = connection.peek_frame().await.unwrap().unwrap();
+    assert_eq!(peeked.to_string(), again.to_string());
+
+    let read = connection.read_frame().await.unwrap().unwrap();
+    assert_eq!(peeked.to_string(), read.to_string());
+    assert!(matches!(read, Frame::Array(ref parts) if parts.len() == 2));
+
+    let next = connection.peek_frame().await.unwrap().unwrap();
+    assert!(matches!(next, Frame::Simple(ref s) if s == "NEXT"));
+    let next = connection.read_frame().await.unwrap().unwrap();
+    assert!(matches!(next, Frame::Simple(ref s) if s == "NEXT"));
+
+    writer.await.unwrap();
+
+    assert!(connection.peek_frame().await.unwrap().is_none());
+    assert!(connection.read_frame().await.unwrap().is_none());
+}
+
+/// `Connection` works over any byte stream, not only a `TcpStream`.
+#[tokio::test]
+async fn connection_over_duplex_stream() {
+    let (client, server) = tokio::io::duplex(64);
+
+    let mut client = Connection::new(client);
+    let mut server = Connection::new(server);
+
+    let request = Frame::Array(vec![Frame::Bulk("GET".into()), Frame::Bulk("hello".into())]);
+    client.write_frame(&request).await.unwrap();
+
+    let received = server.read_frame().await.unwrap().unwrap();
+    assert_eq!("GET hello", received.to_string());
+
+    server.write_frame(&Frame::Null).await.unwrap();
+    assert!(matches!(client.read_frame().await.unwrap(), Some(Frame::Null)));
+
+    drop(client);
+    assert!(server.read_frame().await.unwrap().is_none());
+}
+
+/// The widest integers are encoded without truncation: `u64::MAX` as an
+/// integer frame, and `i64::MIN`, which integer frames cannot hold, as the
+/// simple string negative integers are replied with.
+#[tokio::test]
+async fn extreme_integers_round_trip() {
+    let (client, server) = tokio::io::duplex(64);
+
+    let mut client = Connection::new(client);
+    let mut server = Connection::new(server);
+
+    server.write_frame(&Frame::Integer(u64::MAX)).await.unwrap();
+    let frame = client.read_frame().await.unwrap().unwrap();
+    assert!(matches!(frame, Frame::Integer(u64::MAX)), "{:?}", frame);
+
+    server.write_frame(&Frame::Simple(i64::MIN.to_string())).await.unwrap();
+    match client.read_frame().await.unwrap().unwrap() {
+        Frame::Simple(value) => assert_eq!(i64::MIN, value.parse::<i64>().unwrap()),
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+
+    // 数组的长度同样经过`write_decimal`
+    let array = Frame::Array(vec![Frame::Integer(u64::MAX), Frame::Integer(0)]);
+    server.write_frame(&array).await.unwrap();
+    let frame = client.read_frame().await.unwrap().unwrap();
+    assert!(
+        matches!(&frame, Frame::Array(parts) if matches!(parts[..], [Frame::Integer(u64::MAX), Frame::Integer(0)])),
+        "{:?}",
+        frame
+    );
+}
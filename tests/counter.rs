@@ -0,0 +1,122 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::cmd::Ttl;
+use my_mini_redis::db::CounterOverflow;
+use my_mini_redis::server::{self, Config};
+use my_mini_redis::Frame;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn incr_and_decr() {
+    let addr = start_server(CounterOverflow::Error).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // 不存在的key被当作0
+    assert_eq!(1, client.incr("counter").await.unwrap());
+    assert_eq!(11, client.incr_by("counter", 10).await.unwrap());
+    assert_eq!(10, client.decr("counter").await.unwrap());
+    assert_eq!(-5, client.incr_by("counter", -15).await.unwrap());
+    assert_eq!(Some("-5".into()), client.get("counter").await.unwrap());
+
+    assert_eq!(-1, client.decr("missing").await.unwrap());
+
+    let decrby: i64 = client
+        .query(&["decrby".into(), "counter".into(), "5".into()])
+        .await
+        .unwrap();
+    assert_eq!(-10, decrby);
+
+    // 负数也以整数的形式返回
+    let reply: Frame = client.query(&["incrby".into(), "counter".into(), "-1".into()]).await.unwrap();
+    assert!(matches!(reply, Frame::SignedInteger(-11)), "{:?}", reply);
+    let reply: Frame = client.query(&["incrby".into(), "counter".into(), "20".into()]).await.unwrap();
+    assert!(matches!(reply, Frame::Integer(9)), "{:?}", reply);
+
+    for value in ["abc", "1.5", "012", "+1", ""] {
+        client.set("counter", value.into()).await.unwrap();
+        let err = client.incr("counter").await.unwrap_err();
+        assert_eq!("ERR value is not an integer or out of range", err.to_string());
+    }
+}
+
+#[tokio::test]
+async fn incr_keeps_ttl() {
+    let addr = start_server(CounterOverflow::Error).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .set_expires("counter", "1".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    assert_eq!(2, client.incr("counter").await.unwrap());
+    assert!(matches!(client.pttl("counter").await.unwrap(), Ttl::Expires(_)));
+}
+
+/// By default an overflowing result is an error, and leaves the value
+/// unchanged, at both boundaries.
+#[tokio::test]
+async fn overflow_is_an_error_by_default() {
+    let addr = start_server(CounterOverflow::Error).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("max", i64::MAX.to_string().into()).await.unwrap();
+    let err = client.incr("max").await.unwrap_err();
+    assert_eq!("ERR increment or decrement would overflow", err.to_string());
+    assert_eq!(Some(i64::MAX.to_string().into()), client.get("max").await.unwrap());
+
+    client.set("min", i64::MIN.to_string().into()).await.unwrap();
+    let err = client.decr("min").await.unwrap_err();
+    assert_eq!("ERR increment or decrement would overflow", err.to_string());
+    assert_eq!(Some(i64::MIN.to_string().into()), client.get("min").await.unwrap());
+
+    client.set("counter", "-10".into()).await.unwrap();
+    assert!(client.incr_by("counter", i64::MIN).await.is_err());
+    assert_eq!(i64::MIN, client.incr_by("counter", i64::MIN + 10).await.unwrap());
+
+    // `i64::MIN`的相反数无法表示
+    let err = client
+        .query::<i64>(&["decrby".into(), "zero".into(), i64::MIN.to_string().into()])
+        .await
+        .unwrap_err();
+    assert_eq!("ERR decrement would overflow", err.to_string());
+    assert_eq!(None, client.get("zero").await.unwrap());
+
+    // 连接在错误之后仍然可用
+    assert_eq!(1, client.incr("other").await.unwrap());
+}
+
+/// In saturating mode, an overflowing result is clamped to the boundary.
+#[tokio::test]
+async fn overflow_saturates() {
+    let addr = start_server(CounterOverflow::Saturating).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("max", i64::MAX.to_string().into()).await.unwrap();
+    assert_eq!(i64::MAX, client.incr("max").await.unwrap());
+    assert_eq!(i64::MAX, client.incr_by("max", i64::MAX).await.unwrap());
+    assert_eq!(i64::MAX - 1, client.decr("max").await.unwrap());
+
+    client.set("min", i64::MIN.to_string().into()).await.unwrap();
+    assert_eq!(i64::MIN, client.decr("min").await.unwrap());
+    assert_eq!(i64::MIN, client.incr_by("min", i64::MIN).await.unwrap());
+    assert_eq!(i64::MIN + 1, client.incr("min").await.unwrap());
+
+    assert_eq!(Some((i64::MIN + 1).to_string().into()), client.get("min").await.unwrap());
+}
+
+async fn start_server(counter_overflow: CounterOverflow) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        counter_overflow,
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    addr
+}
@@ -0,0 +1,223 @@
+use my_mini_redis::db::DbDropGuard;
+
+use bytes::Bytes;
+use tokio::time::{self, Duration, Instant};
+
+/// Two keys are swapped atomically: concurrent readers never observe both
+/// keys holding the same value.
+#[tokio::test(flavor = "multi_thread")]
+async fn atomic_swap() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    db.atomic(|view| {
+        view.set("a", Bytes::from_static(b"1"));
+        view.set("b", Bytes::from_static(b"2"));
+    });
+
+    let mut tasks = vec![];
+    for _ in 0..4 {
+        let db = db.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..1000 {
+                db.atomic(|view| {
+                    let a = view.get("a").unwrap();
+                    let b = view.get("b").unwrap();
+                    view.set("a", b);
+                    view.set("b", a);
+                });
+
+                let (a, b) = db.atomic(|view| (view.get("a"), view.get("b")));
+                assert_ne!(a, b);
+            }
+        }));
+    }
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // 交换了偶数次，值回到初始状态
+    let (a, b) = db.atomic(|view| (view.get("a"), view.get("b")));
+    assert_eq!(Some(Bytes::from_static(b"1")), a);
+    assert_eq!(Some(Bytes::from_static(b"2")), b);
+
+    assert!(db.atomic(|view| view.del("a")));
+    assert!(!db.atomic(|view| view.del("a")));
+    assert!(db.atomic(|view| view.get("a")).is_none());
+}
+
+/// Callbacks registered with `on_expire` receive the key and value of the
+/// entries removed by the background task.
+#[tokio::test]
+async fn on_expire_callback() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    db.on_expire(move |key, value| {
+        tx.send((key.to_string(), value.cloned())).unwrap();
+    });
+
+    db.atomic(|view| {
+        view.set("hello", Bytes::from_static(b"world"));
+        view.set("other", Bytes::from_static(b"value"));
+        assert!(view.expire("hello", Duration::from_millis(50)));
+        assert!(!view.expire("missing", Duration::from_millis(50)));
+    });
+
+    let (key, value) = time::timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap();
+    assert_eq!("hello", key);
+    assert_eq!(Some(Bytes::from_static(b"world")), value);
+
+    assert!(db.atomic(|view| view.get("hello")).is_none());
+    assert!(db.atomic(|view| view.get("other")).is_some());
+}
+
+/// STRLEN treats an expired key as missing, even before the background task
+/// purges it.
+#[tokio::test]
+async fn strlen_ignores_expired_key() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    // 在同一个atomic中等待过期，后台任务无法在此期间删除key
+    let len = db.atomic(|view| {
+        view.set("foo", Bytes::from_static(b"bar"));
+        assert_eq!(Ok(3), view.strlen("foo"));
+
+        view.expire("foo", Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(10));
+        view.strlen("foo")
+    });
+    assert_eq!(Ok(0), len);
+}
+
+/// APPEND and SETRANGE start from an empty value when the key has expired,
+/// even before the background task purges it, and the new value does not
+/// inherit the past expiration.
+#[tokio::test]
+async fn append_setrange_ignore_expired_key() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    let (append, setrange) = db.atomic(|view| {
+        view.set("foo", Bytes::from_static(b"stale"));
+        view.set("bar", Bytes::from_static(b"stale"));
+        view.expire("foo", Duration::from_millis(1));
+        view.expire("bar", Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(10));
+
+        let append = view.append("foo".to_string(), Bytes::from_static(b"abc"));
+        let setrange = view.setrange("bar".to_string(), 1, Bytes::from_static(b"xy"));
+        (append, setrange)
+    });
+    assert_eq!(Ok(3), append);
+    assert_eq!(Ok(3), setrange);
+
+    let (foo, bar) = db.atomic(|view| (view.get("foo"), view.get("bar")));
+    assert_eq!(Some(Bytes::from_static(b"abc")), foo);
+    assert_eq!(Some(Bytes::from_static(b"\0xy")), bar);
+}
+
+/// INCRBYFLOAT starts from 0 when the key has expired, even before the
+/// background task purges it, and the result does not inherit the past
+/// expiration. A live key keeps its time to live.
+#[tokio::test]
+async fn incr_by_float_ignores_expired_key() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    let value = db.atomic(|view| {
+        view.set("foo", Bytes::from_static(b"10.5"));
+        view.expire("foo", Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(10));
+
+        view.incr_by_float("foo".to_string(), 1.5)
+    });
+    assert_eq!(Ok(1.5), value);
+    assert_eq!(Some(Bytes::from_static(b"1.5")), db.atomic(|view| view.get("foo")));
+    assert_eq!(None, db.next_expiration());
+
+    let value = db.atomic(|view| {
+        view.set("bar", Bytes::from_static(b"10.5"));
+        view.expire("bar", Duration::from_secs(60));
+
+        view.incr_by_float("bar".to_string(), 1.5)
+    });
+    assert_eq!(Ok(12.0), value);
+    assert!(db.next_expiration().is_some());
+}
+
+/// OBJECT ENCODING and OBJECT REFCOUNT report nothing for an expired key, even
+/// before the background task purges it, as GET does.
+#[tokio::test]
+async fn object_ignores_expired_key() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    let (encoding, refcount) = db.atomic(|view| {
+        view.set("foo", Bytes::from_static(b"123"));
+        assert_eq!(Some("int"), view.encoding("foo"));
+        assert!(view.refcount("foo").is_some());
+
+        view.expire("foo", Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(10));
+        (view.encoding("foo"), view.refcount("foo"))
+    });
+    assert_eq!(None, encoding);
+    assert_eq!(None, refcount);
+}
+
+/// The next expiration is the earliest deadline among the keys with a time to
+/// live, and moves to the next one when that key goes away.
+#[tokio::test(start_paused = true)]
+async fn next_expiration_is_earliest_deadline() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    assert_eq!(None, db.next_expiration());
+
+    let now = Instant::now();
+    db.atomic(|view| {
+        for key in ["persistent", "late", "early", "middle"] {
+            view.set(key, Bytes::from_static(b"value"));
+        }
+        view.expire("late", Duration::from_secs(30));
+        view.expire("early", Duration::from_secs(10));
+        view.expire("middle", Duration::from_secs(20));
+    });
+
+    assert_eq!(Some(now + Duration::from_secs(10)), db.next_expiration());
+
+    db.atomic(|view| view.del("early"));
+    assert_eq!(Some(now + Duration::from_secs(20)), db.next_expiration());
+
+    // 过期之后，后台任务删除key，下一个过期时间随之更新
+    time::sleep(Duration::from_secs(21)).await;
+    assert_eq!(Some(now + Duration::from_secs(30)), db.next_expiration());
+    assert!(db.atomic(|view| view.get("middle")).is_none());
+}
+
+/// A bridge runs until it is stopped, or until the `Db` shuts down.
+#[tokio::test]
+async fn bridge_stops_on_shutdown() {
+    let guard = DbDropGuard::new();
+    let db = guard.db();
+
+    let stopped = db.bridge("a", "b");
+    let running = db.bridge("b", "c");
+
+    stopped.stop();
+    assert!(!running.is_finished());
+
+    drop(guard);
+
+    time::timeout(Duration::from_secs(1), async {
+        while !running.is_finished() {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
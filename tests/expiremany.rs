@@ -0,0 +1,111 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::cmd::{ExpireCondition, Ttl};
+use my_mini_redis::server::{self, Config};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time;
+
+/// Each condition is evaluated per key, on a batch mixing keys without a
+/// TTL, keys expiring before and after the new TTL, and a missing key.
+#[tokio::test]
+async fn mixed_batch_per_key_outcomes() {
+    let mut client = Client::connect(start_server(Config::default()).await).await.unwrap();
+
+    let keys = ["persistent", "short", "long", "missing"];
+    let cases = [
+        (None, [true, true, true, false]),
+        (Some(ExpireCondition::Nx), [true, false, false, false]),
+        (Some(ExpireCondition::Xx), [false, true, true, false]),
+        (Some(ExpireCondition::Gt), [false, true, false, false]),
+        (Some(ExpireCondition::Lt), [true, false, true, false]),
+    ];
+
+    for (condition, expected) in cases {
+        client.set("persistent", "a".into()).await.unwrap();
+        client.set_expires("short", "b".into(), Duration::from_secs(10)).await.unwrap();
+        client.set_expires("long", "c".into(), Duration::from_secs(1000)).await.unwrap();
+
+        let set = client.expire_many(&keys, Duration::from_secs(100), condition).await.unwrap();
+        assert_eq!(expected.to_vec(), set, "{:?}", condition);
+
+        // 只有条件成立的key被修改
+        for (key, set) in keys.iter().zip(expected) {
+            let ttl = client.pttl(key).await.unwrap();
+            match (*key, set) {
+                ("missing", _) => assert_eq!(Ttl::Missing, ttl),
+                (_, true) => assert!(matches!(ttl, Ttl::Expires(ttl) if ttl > Duration::from_secs(90) && ttl <= Duration::from_secs(100))),
+                ("persistent", false) => assert_eq!(Ttl::Persistent, ttl),
+                ("short", false) => assert!(matches!(ttl, Ttl::Expires(ttl) if ttl <= Duration::from_secs(10))),
+                ("long", false) => assert!(matches!(ttl, Ttl::Expires(ttl) if ttl > Duration::from_secs(100))),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// A batch larger than the number of keys updated per lock acquisition is
+/// answered in the order of the request.
+#[tokio::test]
+async fn large_batch_keeps_request_order() {
+    let mut client = Client::connect(start_server(Config::default()).await).await.unwrap();
+
+    let keys: Vec<String> = (0..3000).map(|i| format!("session:{}", i)).collect();
+    for key in keys.iter().step_by(3) {
+        client.set(key, "x".into()).await.unwrap();
+    }
+
+    let keys: Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+    let set = client.expire_many(&keys, Duration::from_secs(60), None).await.unwrap();
+
+    assert_eq!(3000, set.len());
+    for (i, set) in set.into_iter().enumerate() {
+        assert_eq!(i % 3 == 0, set, "key {}", i);
+    }
+    assert!(matches!(client.pttl("session:2997").await.unwrap(), Ttl::Expires(_)));
+}
+
+/// Keys given a TTL earlier than any other wake up the purge task, which
+/// removes them on time instead of at the previous nearest deadline.
+#[tokio::test]
+async fn batch_wakes_purge_task() {
+    let config = Config {
+        notify_keyspace_events: "Ex".parse().unwrap(),
+        ..Config::default()
+    };
+    let addr = start_server(config).await;
+
+    let channels = vec!["__keyevent@0__:expired".to_string()];
+    let mut subscriber = Client::connect(addr).await.unwrap().subscribe(channels).await.unwrap();
+
+    // 后台任务等待这个key过期
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set_expires("far", "x".into(), Duration::from_secs(1000)).await.unwrap();
+
+    for key in ["a", "b", "c"] {
+        client.set(key, "x".into()).await.unwrap();
+    }
+    let set = client.expire_many(&["a", "b", "missing", "c"], Duration::from_secs(1), None).await.unwrap();
+    assert_eq!(vec![true, true, false, true], set);
+
+    let mut expired = vec![];
+    for _ in 0..3 {
+        let message = time::timeout(Duration::from_secs(3), subscriber.next_message()).await.unwrap().unwrap().unwrap();
+        expired.push(message.content);
+    }
+    expired.sort();
+    assert_eq!(vec![Bytes::from("a"), Bytes::from("b"), Bytes::from("c")], expired);
+
+    assert_eq!(1, client.dbsize().await.unwrap());
+}
+
+async fn start_server(config: Config) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run_with_config(listener, tokio::signal::ctrl_c(), config).await });
+
+    addr
+}
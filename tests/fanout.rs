@@ -0,0 +1,101 @@
+use my_mini_redis::fanout::{Overflow, RecvError, Sender};
+
+use std::time::Duration;
+use tokio::time;
+
+/// A subscriber falling behind is told exactly how many messages it missed,
+/// then receives the newest ones.
+#[tokio::test]
+async fn drop_oldest_counts_lag() {
+    let mut tx = Sender::new(3, Overflow::DropOldest);
+    let mut slow = tx.subscribe();
+
+    for i in 0..10 {
+        assert_eq!(1, tx.send(i));
+    }
+
+    assert_eq!(Err(RecvError::Lagged(7)), slow.recv().await);
+    assert_eq!(Ok(7), slow.recv().await);
+    assert_eq!(Ok(8), slow.recv().await);
+    assert_eq!(Ok(9), slow.recv().await);
+
+    // 追上之后不再报告延迟
+    tx.send(10);
+    assert_eq!(Ok(10), slow.recv().await);
+}
+
+/// With `DropNewest`, the pending messages are kept and the new ones are
+/// neither queued nor counted as sent.
+#[tokio::test]
+async fn drop_newest_keeps_pending() {
+    let mut tx = Sender::new(2, Overflow::DropNewest);
+    let mut slow = tx.subscribe();
+
+    assert_eq!(1, tx.send(0));
+    assert_eq!(1, tx.send(1));
+    assert_eq!(0, tx.send(2));
+    assert_eq!(0, tx.send(3));
+
+    assert_eq!(Err(RecvError::Lagged(2)), slow.recv().await);
+    assert_eq!(Ok(0), slow.recv().await);
+    assert_eq!(Ok(1), slow.recv().await);
+
+    assert_eq!(1, tx.send(4));
+    assert_eq!(Ok(4), slow.recv().await);
+}
+
+/// With `Disconnect`, only the subscriber with a full queue is dropped; the
+/// others keep receiving every message.
+#[tokio::test]
+async fn disconnect_only_slow_subscriber() {
+    let mut tx = Sender::new(2, Overflow::Disconnect);
+    let mut slow = tx.subscribe();
+    let mut fast = tx.subscribe();
+
+    for i in 0..3 {
+        tx.send(i);
+        assert_eq!(Ok(i), fast.recv().await);
+    }
+
+    assert_eq!(1, tx.receiver_count());
+    assert_eq!(Err(RecvError::Disconnected), slow.recv().await);
+    assert_eq!(Err(RecvError::Disconnected), slow.recv().await);
+
+    assert_eq!(1, tx.send(3));
+    assert_eq!(Ok(3), fast.recv().await);
+}
+
+/// Dropped receivers stop being counted, and a waiting receiver is woken up
+/// when the sender goes away, after the pending messages.
+#[tokio::test]
+async fn receivers_and_close() {
+    let mut tx = Sender::new(4, Overflow::DropOldest);
+    let rx1 = tx.subscribe();
+    let mut rx2 = tx.subscribe();
+    assert_eq!(2, tx.receiver_count());
+
+    drop(rx1);
+    assert_eq!(1, tx.receiver_count());
+    assert_eq!(1, tx.send("pending"));
+
+    let waiting = tokio::spawn(async move {
+        let first = rx2.recv().await;
+        let second = rx2.recv().await;
+        (first, second)
+    });
+
+    drop(tx);
+
+    let (first, second) = time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+    assert_eq!(Ok("pending"), first);
+    assert_eq!(Err(RecvError::Closed), second);
+}
+
+/// Overflow behaviors are parsed from their configuration names.
+#[test]
+fn parse_overflow() {
+    assert_eq!(Overflow::DropOldest, "drop-oldest".parse().unwrap());
+    assert_eq!(Overflow::DropNewest, "DROP-NEWEST".parse().unwrap());
+    assert_eq!(Overflow::Disconnect, "disconnect".parse().unwrap());
+    assert!("drop".parse::<Overflow>().is_err());
+}
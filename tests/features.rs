@@ -0,0 +1,44 @@
+//! Uses the public API available in every combination of features. Run with
+//! `--no-default-features` as well to check the crate builds without the
+//! blocking client.
+
+use my_mini_redis::clients::{Client, Message, Subscriber};
+use my_mini_redis::server;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// The asynchronous client does not depend on the `blocking` feature.
+#[tokio::test]
+async fn async_client_without_blocking() {
+    let addr = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber: Subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    publisher.publish("hello", "world".into()).await.unwrap();
+
+    let message: Message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("hello", message.channel);
+    assert_eq!(&b"world"[..], &message.content[..]);
+}
+
+/// The blocking client is re-exported at the root of the crate, next to the
+/// asynchronous one.
+#[cfg(feature = "blocking")]
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_client_reexported() {
+    let addr = start_server().await;
+
+    let mut client = my_mini_redis::BlockingClient::connect(addr).unwrap();
+    assert_eq!(None, client.get("hello").unwrap());
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -0,0 +1,164 @@
+use my_mini_redis::frame::{self, Frame};
+
+use std::io::Cursor;
+
+/// Malformed numeric headers are rejected by both `check` and `parse`, with
+/// an error naming the header.
+#[test]
+fn malformed_numeric_lines_are_rejected() {
+    let cases: &[(&[u8], &str)] = &[
+        (b":12abc\r\n", "integer `12abc`"),
+        (b":\r\n", "integer ``"),
+        (b":-0\r\n", "integer `-0`"),
+        (b":--5\r\n", "integer `--5`"),
+        (b":-9223372036854775809\r\n", "integer `-9223372036854775809`"),
+        (b":+5\r\n", "integer `+5`"),
+        (b": 5\r\n", "integer ` 5`"),
+        (b":007\r\n", "integer `007`"),
+        (b":18446744073709551616\r\n", "integer `18446744073709551616`"),
+        (b"$3abc\r\nfoo\r\n", "bulk length `3abc`"),
+        (b"$\r\n", "bulk length ``"),
+        (b"$-2\r\n", "bulk length `-2`"),
+        (b"$-0\r\n", "bulk length `-0`"),
+        (b"$--1\r\n", "bulk length `--1`"),
+        (b"$03\r\nfoo\r\n", "bulk length `03`"),
+        (b"$99999999999999999999999\r\n", "bulk length `99999999999999999999999`"),
+        (b"*1x\r\n", "array length `1x`"),
+        (b"*\r\n", "array length ``"),
+        (b"*-2\r\n", "array length `-2`"),
+        (b"*01\r\n:1\r\n", "array length `01`"),
+        (b"*1\r\n$2x\r\nab\r\n", "bulk length `2x`"),
+    ];
+
+    for (src, header) in cases {
+        let err = Frame::check(&mut Cursor::new(src)).unwrap_err();
+        assert!(matches!(err, frame::Error::Other(_)), "{:?}", src);
+        assert_eq!(format!("protocol error; invalid {}", header), err.to_string());
+
+        let err = Frame::parse(&mut Cursor::new(src)).unwrap_err();
+        assert_eq!(format!("protocol error; invalid {}", header), err.to_string());
+    }
+}
+
+/// The valid numeric forms, including the `$-1` and `*-1` nulls, parse.
+#[test]
+fn valid_numeric_lines_parse() {
+    let cases: &[(&[u8], &str)] = &[
+        (b":0\r\n", "0"),
+        (b":18446744073709551615\r\n", "18446744073709551615"),
+        (b":-5\r\n", "-5"),
+        (b":-9223372036854775808\r\n", "-9223372036854775808"),
+        (b"$0\r\n\r\n", ""),
+        (b"$3\r\nfoo\r\n", "foo"),
+        (b"$-1\r\n", "(nil)"),
+        (b"*-1\r\n", "(nil)"),
+        (b"*0\r\n", ""),
+        (b"*2\r\n:10\r\n$-1\r\n", "10 (nil)"),
+    ];
+
+    for (src, display) in cases {
+        let mut cursor = Cursor::new(*src);
+        Frame::check(&mut cursor).unwrap();
+        assert_eq!(src.len() as u64, cursor.position(), "{:?}", src);
+
+        let frame = Frame::parse(&mut Cursor::new(*src)).unwrap();
+        assert_eq!(*display, frame.to_string());
+    }
+}
+
+/// A header whose line is not terminated yet is incomplete, not malformed.
+#[test]
+fn unterminated_numeric_lines_are_incomplete() {
+    for src in [&b":12"[..], b"$-1", b"$3\r\nfo", b"*2\r\n:1\r\n"] {
+        let err = Frame::check(&mut Cursor::new(src)).unwrap_err();
+        assert!(matches!(err, frame::Error::Incomplete), "{:?}", src);
+    }
+}
+
+/// Each malformed input is rejected by both `check` and `parse` with the
+/// documented error, and none of them panics.
+#[test]
+fn malformed_frames_are_rejected() {
+    let nested = format!("{}:1\r\n", "*1\r\n".repeat(frame::MAX_ARRAY_DEPTH + 1));
+    let cases: &[(&[u8], &str)] = &[
+        (b"$3\r\nfooXY", "protocol error; bulk string not terminated by CRLF"),
+        (b"$3\r\nfoo\n\r", "protocol error; bulk string not terminated by CRLF"),
+        (b"$0\r\nab", "protocol error; bulk string not terminated by CRLF"),
+        (b"*1\r\n$1\r\nab\r\n", "protocol error; bulk string not terminated by CRLF"),
+        (b"PING\r\n", "protocol error; invalid frame type byte `P`"),
+        (b"\r", "protocol error; invalid frame type byte `\\r`"),
+        (b"\r\n", "protocol error; invalid frame type byte `\\r`"),
+        (b"\x00", "protocol error; invalid frame type byte `\\x00`"),
+        (b"*1\r\nGET\r\n", "protocol error; invalid frame type byte `G`"),
+        (b"&3\r\nfoo\r\n", "protocol error; invalid frame type byte `&`"),
+        (nested.as_bytes(), "protocol error; arrays nested too deeply"),
+    ];
+
+    for (src, message) in cases {
+        let err = Frame::check(&mut Cursor::new(src)).unwrap_err();
+        assert!(matches!(err, frame::Error::Other(_)), "{:?}", src);
+        assert_eq!(*message, err.to_string());
+
+        // 没有经过check的数据也不会panic
+        let err = Frame::parse(&mut Cursor::new(src)).unwrap_err();
+        assert_eq!(*message, err.to_string());
+    }
+}
+
+/// Empty input, a line ending with a lone `\r` and frames cut short are
+/// incomplete, for `parse` as well as `check`.
+#[test]
+fn truncated_frames_are_incomplete() {
+    let cases: &[&[u8]] = &[b"", b"+", b"+OK\r", b"$", b"$3", b"$3\r", b"$3\r\nfoo", b"$3\r\nfoo\r", b"*", b"*1\r\n"];
+
+    for src in cases {
+        let err = Frame::check(&mut Cursor::new(*src)).unwrap_err();
+        assert!(matches!(err, frame::Error::Incomplete), "{:?}", src);
+
+        let err = Frame::parse(&mut Cursor::new(*src)).unwrap_err();
+        assert!(matches!(err, frame::Error::Incomplete), "{:?}", src);
+    }
+
+    // 声明了很多元素但数据很少的array不会按声明的长度分配
+    let err = Frame::parse(&mut Cursor::new(&b"*100000000\r\n:1\r\n"[..])).unwrap_err();
+    assert!(matches!(err, frame::Error::Incomplete));
+}
+
+/// Empty arrays and nested arrays, up to `MAX_ARRAY_DEPTH` levels, parse.
+#[test]
+fn nested_arrays_parse() {
+    let cases: &[(&[u8], &str)] = &[
+        (b"*0\r\n", "[]"),
+        (b"*1\r\n*0\r\n", "[[]]"),
+        (b"*2\r\n*2\r\n:1\r\n:2\r\n*1\r\n$3\r\nfoo\r\n", "[[1, 2], [foo]]"),
+        (b"*3\r\n+OK\r\n*-1\r\n*1\r\n-ERR x\r\n", "[OK, (nil), [error: ERR x]]"),
+    ];
+
+    for (src, expected) in cases {
+        let mut cursor = Cursor::new(*src);
+        Frame::check(&mut cursor).unwrap();
+        assert_eq!(src.len() as u64, cursor.position(), "{:?}", src);
+
+        let frame = Frame::parse(&mut Cursor::new(*src)).unwrap();
+        assert_eq!(*expected, shape(&frame));
+    }
+
+    let deepest = format!("{}:1\r\n", "*1\r\n".repeat(frame::MAX_ARRAY_DEPTH));
+    Frame::check(&mut Cursor::new(deepest.as_bytes())).unwrap();
+    let mut frame = Frame::parse(&mut Cursor::new(deepest.as_bytes())).unwrap();
+    for _ in 0..frame::MAX_ARRAY_DEPTH {
+        frame = match frame {
+            Frame::Array(mut parts) => parts.pop().unwrap(),
+            frame => panic!("unexpected frame {:?}", frame),
+        };
+    }
+    assert_eq!("1", frame.to_string());
+}
+
+/// Writes the frame with its arrays bracketed, as `Display` flattens them.
+fn shape(frame: &Frame) -> String {
+    match frame {
+        Frame::Array(parts) => format!("[{}]", parts.iter().map(shape).collect::<Vec<_>>().join(", ")),
+        frame => frame.to_string(),
+    }
+}
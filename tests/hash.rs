@@ -0,0 +1,94 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::server;
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// Several fields are set at once, read back one by one and all together,
+/// and only the new fields are counted.
+#[tokio::test]
+async fn set_and_read_fields() {
+    let mut client = Client::connect(start_server().await).await.unwrap();
+
+    let added = client
+        .hset("user:1", &[("name", "ada".into()), ("lang", "en".into()), ("born", "1815".into())])
+        .await
+        .unwrap();
+    assert_eq!(3, added);
+
+    // 已存在的字段被更新，不计入新增数量
+    let added = client.hset("user:1", &[("lang", "fr".into()), ("role", "admin".into())]).await.unwrap();
+    assert_eq!(1, added);
+
+    assert_eq!(Some(Bytes::from("ada")), client.hget("user:1", "name").await.unwrap());
+    assert_eq!(Some(Bytes::from("fr")), client.hget("user:1", "lang").await.unwrap());
+    assert_eq!(None, client.hget("user:1", "missing").await.unwrap());
+    assert_eq!(None, client.hget("missing", "name").await.unwrap());
+
+    let mut fields = client.hgetall("user:1").await.unwrap();
+    fields.sort();
+    assert_eq!(
+        vec![
+            ("born".to_string(), Bytes::from("1815")),
+            ("lang".to_string(), Bytes::from("fr")),
+            ("name".to_string(), Bytes::from("ada")),
+            ("role".to_string(), Bytes::from("admin")),
+        ],
+        fields
+    );
+
+    assert!(client.hgetall("missing").await.unwrap().is_empty());
+    assert_eq!("hash", client.key_type("user:1").await.unwrap());
+}
+
+/// Only the existing fields are counted as removed, and the key goes away
+/// with the last field.
+#[tokio::test]
+async fn delete_fields_removes_empty_hash() {
+    let mut client = Client::connect(start_server().await).await.unwrap();
+
+    client.hset("user:1", &[("name", "ada".into()), ("lang", "en".into())]).await.unwrap();
+
+    assert_eq!(1, client.hdel("user:1", &["name", "missing"]).await.unwrap());
+    assert_eq!(None, client.hget("user:1", "name").await.unwrap());
+    assert_eq!(Some(Bytes::from("en")), client.hget("user:1", "lang").await.unwrap());
+
+    assert_eq!(1, client.hdel("user:1", &["lang"]).await.unwrap());
+    assert_eq!("none", client.key_type("user:1").await.unwrap());
+    assert_eq!(0, client.hdel("user:1", &["lang"]).await.unwrap());
+}
+
+/// Hash commands on another type, and other commands on a hash, reply with
+/// an error.
+#[tokio::test]
+async fn wrong_type_errors() {
+    let mut client = Client::connect(start_server().await).await.unwrap();
+
+    client.set("string", "x".into()).await.unwrap();
+    client.rpush("list", &["a".into()]).await.unwrap();
+    client.hset("hash", &[("field", "value".into())]).await.unwrap();
+
+    for key in ["string", "list"] {
+        assert_eq!(WRONGTYPE, client.hset(key, &[("field", "value".into())]).await.unwrap_err().to_string());
+        assert_eq!(WRONGTYPE, client.hget(key, "field").await.unwrap_err().to_string());
+        assert_eq!(WRONGTYPE, client.hdel(key, &["field"]).await.unwrap_err().to_string());
+        assert_eq!(WRONGTYPE, client.hgetall(key).await.unwrap_err().to_string());
+    }
+
+    assert_eq!(WRONGTYPE, client.get("hash").await.unwrap_err().to_string());
+    assert_eq!(WRONGTYPE, client.lpop("hash").await.unwrap_err().to_string());
+
+    assert_eq!(Some(Bytes::from("value")), client.hget("hash", "field").await.unwrap());
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
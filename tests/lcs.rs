@@ -0,0 +1,153 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::lcs::{longest_common_subsequence, LcsMatch};
+use my_mini_redis::server;
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// The example of the Redis documentation.
+#[test]
+fn subsequence_and_matches() {
+    let lcs = longest_common_subsequence(b"ohmytext", b"mynewtext");
+
+    assert_eq!(b"mytext", &lcs.bytes[..]);
+    assert_eq!(
+        vec![
+            LcsMatch { a: (4, 7), b: (5, 8) },
+            LcsMatch { a: (2, 3), b: (0, 1) },
+        ],
+        lcs.matches
+    );
+    assert_eq!(4, lcs.matches[0].match_len());
+}
+
+#[test]
+fn degenerate_strings() {
+    let lcs = longest_common_subsequence(b"", b"");
+    assert!(lcs.bytes.is_empty());
+    assert!(lcs.matches.is_empty());
+
+    let lcs = longest_common_subsequence(b"abc", b"");
+    assert!(lcs.bytes.is_empty());
+
+    let lcs = longest_common_subsequence(b"abc", b"xyz");
+    assert!(lcs.bytes.is_empty());
+    assert!(lcs.matches.is_empty());
+
+    let lcs = longest_common_subsequence(b"same", b"same");
+    assert_eq!(b"same", &lcs.bytes[..]);
+    assert_eq!(vec![LcsMatch { a: (0, 3), b: (0, 3) }], lcs.matches);
+}
+
+/// On many small strings, the subsequence is as long as the longest one found
+/// by brute force, and the matches spell it in both strings.
+#[test]
+fn matches_brute_force() {
+    // 线性同余生成器，保证测试可以复现
+    let mut seed: u64 = 42;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize
+    };
+
+    for _ in 0..500 {
+        let a: Vec<u8> = (0..next() % 9).map(|_| b"abc"[next() % 3]).collect();
+        let b: Vec<u8> = (0..next() % 9).map(|_| b"abc"[next() % 3]).collect();
+
+        let lcs = longest_common_subsequence(&a, &b);
+
+        assert_eq!(brute_force_len(&a, &b), lcs.bytes.len(), "{:?} {:?}", a, b);
+
+        // 匹配从后往前给出
+        let mut spelled_a = vec![];
+        let mut spelled_b = vec![];
+        for run in lcs.matches.iter().rev() {
+            assert_eq!(run.a.1 - run.a.0, run.b.1 - run.b.0);
+            spelled_a.extend_from_slice(&a[run.a.0..=run.a.1]);
+            spelled_b.extend_from_slice(&b[run.b.0..=run.b.1]);
+        }
+        assert_eq!(lcs.bytes, spelled_a);
+        assert_eq!(lcs.bytes, spelled_b);
+    }
+}
+
+#[tokio::test]
+async fn lcs_command() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("key1", "ohmytext".into()).await.unwrap();
+    client.set("key2", "mynewtext".into()).await.unwrap();
+
+    assert_eq!(&b"mytext"[..], &client.lcs("key1", "key2").await.unwrap()[..]);
+    assert_eq!(6, client.lcs_len("key1", "key2").await.unwrap());
+
+    let idx = client.lcs_idx("key1", "key2", 0).await.unwrap();
+    assert_eq!(6, idx.len);
+    assert_eq!(
+        vec![
+            LcsMatch { a: (4, 7), b: (5, 8) },
+            LcsMatch { a: (2, 3), b: (0, 1) },
+        ],
+        idx.matches
+    );
+
+    // `MINMATCHLEN`不影响整个子序列的长度
+    let idx = client.lcs_idx("key1", "key2", 4).await.unwrap();
+    assert_eq!(6, idx.len);
+    assert_eq!(vec![LcsMatch { a: (4, 7), b: (5, 8) }], idx.matches);
+
+    // 不存在的key被当作空字符串
+    assert!(client.lcs("key1", "missing").await.unwrap().is_empty());
+    assert_eq!(0, client.lcs_len("missing", "key2").await.unwrap());
+}
+
+#[tokio::test]
+async fn lcs_options_errors() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client.query::<u64>(&args(&["lcs", "key1", "key2", "len", "idx"])).await.unwrap_err();
+    assert_eq!(
+        "ERR If you want both the length and indexes, please just use IDX.",
+        err.to_string()
+    );
+
+    assert_eq!("PONG", client.ping(None).await.unwrap());
+
+    // 与其他命令一样，无法解析的选项回复错误，连接仍然可用
+    let err = client.query::<u64>(&args(&["lcs", "key1", "key2", "withfoo"])).await.unwrap_err();
+    assert_eq!("ERR syntax error", err.to_string());
+    assert_eq!("PONG", client.ping(None).await.unwrap());
+}
+
+fn args(args: &[&'static str]) -> Vec<Bytes> {
+    args.iter().map(|arg| Bytes::from_static(arg.as_bytes())).collect()
+}
+
+/// The length of the longest subsequence of `a` found in `b`, trying all the
+/// subsequences of `a`.
+fn brute_force_len(a: &[u8], b: &[u8]) -> usize {
+    (0..1u32 << a.len())
+        .filter_map(|mask| {
+            let candidate: Vec<u8> = (0..a.len()).filter(|i| mask & (1 << i) != 0).map(|i| a[i]).collect();
+
+            let mut rest = b.iter();
+            candidate
+                .iter()
+                .all(|byte| rest.any(|other| other == byte))
+                .then_some(candidate.len())
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -0,0 +1,126 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::server::{self, Config};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time;
+
+const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// Values pushed at the head come out in reverse order, values pushed at
+/// the tail in the order they were given, and both ends can be popped.
+#[tokio::test]
+async fn push_pop_ordering() {
+    let mut client = Client::connect(start_server(Config::default()).await).await.unwrap();
+
+    assert_eq!(3, client.lpush("list", &values(&["a", "b", "c"])).await.unwrap());
+    assert_eq!(values(&["c", "b", "a"]), client.lrange("list", 0, -1).await.unwrap());
+
+    assert_eq!(5, client.rpush("list", &values(&["d", "e"])).await.unwrap());
+    assert_eq!(values(&["c", "b", "a", "d", "e"]), client.lrange("list", 0, -1).await.unwrap());
+
+    assert_eq!(Some(Bytes::from("c")), client.lpop("list").await.unwrap());
+    assert_eq!(Some(Bytes::from("e")), client.rpop("list").await.unwrap());
+    assert_eq!(values(&["b", "a", "d"]), client.lrange("list", 0, -1).await.unwrap());
+}
+
+#[tokio::test]
+async fn lrange_indexes() {
+    let mut client = Client::connect(start_server(Config::default()).await).await.unwrap();
+
+    client.rpush("list", &values(&["a", "b", "c", "d"])).await.unwrap();
+
+    let cases = [
+        (0, -1, vec!["a", "b", "c", "d"]),
+        (1, 2, vec!["b", "c"]),
+        (-2, -1, vec!["c", "d"]),
+        (-100, 100, vec!["a", "b", "c", "d"]),
+        (2, 1, vec![]),
+        (4, 10, vec![]),
+    ];
+    for (start, stop, expected) in cases {
+        assert_eq!(values(&expected), client.lrange("list", start, stop).await.unwrap(), "{} {}", start, stop);
+    }
+
+    assert!(client.lrange("missing", 0, -1).await.unwrap().is_empty());
+}
+
+/// Popping with a count replies with an array, right pops starting from the
+/// tail, and removes the key along with its last value.
+#[tokio::test]
+async fn pop_count_removes_empty_list() {
+    let mut client = Client::connect(start_server(Config::default()).await).await.unwrap();
+
+    client.rpush("list", &values(&["a", "b", "c", "d", "e"])).await.unwrap();
+    client.expire_many(&["list"], Duration::from_secs(100), None).await.unwrap();
+
+    assert_eq!(Some(values(&["a", "b"])), client.lpop_count("list", 2).await.unwrap());
+    assert_eq!(Some(values(&["e", "d"])), client.rpop_count("list", 2).await.unwrap());
+    assert_eq!(Some(values(&[])), client.lpop_count("list", 0).await.unwrap());
+    assert_eq!(Some(values(&["c"])), client.lpop_count("list", 10).await.unwrap());
+
+    assert_eq!("none", client.key_type("list").await.unwrap());
+    assert_eq!(None, client.lpop("list").await.unwrap());
+    assert_eq!(None, client.rpop_count("list", 2).await.unwrap());
+
+    // 重新创建的列表没有继承之前的过期时间
+    client.rpush("list", &values(&["x"])).await.unwrap();
+    assert_eq!(my_mini_redis::cmd::Ttl::Persistent, client.pttl("list").await.unwrap());
+}
+
+/// String commands on a list and list commands on a string reply with an
+/// error, leaving the connection usable.
+#[tokio::test]
+async fn wrong_type_errors() {
+    let mut client = Client::connect(start_server(Config::default()).await).await.unwrap();
+
+    client.rpush("list", &values(&["a"])).await.unwrap();
+    client.set("string", "x".into()).await.unwrap();
+
+    assert_eq!(WRONGTYPE, client.get("list").await.unwrap_err().to_string());
+    assert_eq!(WRONGTYPE, client.append("list", "y".into()).await.unwrap_err().to_string());
+    assert_eq!(WRONGTYPE, client.lpush("string", &values(&["a"])).await.unwrap_err().to_string());
+    assert_eq!(WRONGTYPE, client.rpop("string").await.unwrap_err().to_string());
+    assert_eq!(WRONGTYPE, client.lrange("string", 0, -1).await.unwrap_err().to_string());
+
+    assert_eq!("list", client.key_type("list").await.unwrap());
+    assert_eq!(Some(Bytes::from("x")), client.get("string").await.unwrap());
+
+    // SET 覆盖任何类型的值
+    client.set("list", "z".into()).await.unwrap();
+    assert_eq!(Some(Bytes::from("z")), client.get("list").await.unwrap());
+}
+
+#[tokio::test]
+async fn negative_count_is_an_error_reply() {
+    let addr = start_server(Config::default()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*3\r\n$4\r\nLPOP\r\n$4\r\nlist\r\n$2\r\n-1\r\n").await.unwrap();
+    let expected = b"-ERR value is out of range, must be positive\r\n";
+    let mut response = vec![0; expected.len()];
+    time::timeout(Duration::from_secs(1), stream.read_exact(&mut response)).await.unwrap().unwrap();
+    assert_eq!(&expected[..], &response[..]);
+
+    // 连接仍然可用
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    let mut response = [0; 7];
+    time::timeout(Duration::from_secs(1), stream.read_exact(&mut response)).await.unwrap().unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+}
+
+fn values(values: &[&str]) -> Vec<Bytes> {
+    values.iter().map(|value| Bytes::copy_from_slice(value.as_bytes())).collect()
+}
+
+async fn start_server(config: Config) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run_with_config(listener, tokio::signal::ctrl_c(), config).await });
+
+    addr
+}
@@ -0,0 +1,157 @@
+use my_mini_redis::logging::{Admission, RateLimitedLog};
+use my_mini_redis::server;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// The first events of each interval are logged, the others counted and
+/// reported along with the first event of a later interval.
+#[tokio::test(start_paused = true)]
+async fn limiter_counts_suppressed_events() {
+    let log = RateLimitedLog::new(3, Duration::from_secs(10));
+
+    for _ in 0..3 {
+        assert_eq!(Admission::Log { suppressed: 0 }, log.admit("protocol"));
+    }
+    for _ in 0..5 {
+        assert_eq!(Admission::Suppress, log.admit("protocol"));
+    }
+
+    // 其他类别不受影响
+    assert_eq!(Admission::Log { suppressed: 0 }, log.admit("io"));
+
+    // 时间窗口结束之前仍然被抑制
+    time::advance(Duration::from_secs(9)).await;
+    assert_eq!(Admission::Suppress, log.admit("protocol"));
+
+    time::advance(Duration::from_secs(1)).await;
+    assert_eq!(Admission::Log { suppressed: 6 }, log.admit("protocol"));
+    assert_eq!(Admission::Log { suppressed: 0 }, log.admit("protocol"));
+
+    // 没有被抑制的事件时不需要汇总
+    time::advance(Duration::from_secs(10)).await;
+    assert_eq!(Admission::Log { suppressed: 0 }, log.admit("io"));
+}
+
+#[test]
+fn limiter_without_burst_suppresses_everything() {
+    let log = RateLimitedLog::new(0, Duration::from_secs(10));
+
+    for _ in 0..10 {
+        assert_eq!(Admission::Suppress, log.admit("protocol"));
+    }
+}
+
+/// Connections sending garbage log a bounded number of lines, no matter how
+/// many of them there are.
+#[tokio::test]
+async fn garbage_connections_bounded_log_volume() {
+    let captured = Captured::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(captured.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { server::run(listener, std::future::pending::<()>()).await });
+
+    for _ in 0..200 {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+
+        // 等待服务器关闭连接，这样它的错误已经被记录
+        let mut response = vec![];
+        let _ = stream.read_to_end(&mut response).await;
+    }
+
+    // 让最后一个连接的任务完成
+    time::sleep(Duration::from_millis(50)).await;
+
+    let logs = captured.to_string();
+    let errors: Vec<&str> = logs.lines().filter(|line| line.contains("connection error")).collect();
+
+    assert_eq!(10, errors.len(), "{}", logs);
+    assert!(errors.iter().all(|line| line.contains("category=\"protocol\"") && line.contains("client_id=")));
+    assert!(logs.lines().count() < 20, "{}", logs);
+}
+
+/// A command exceeding the execution deadline is logged with its name and the
+/// number of commands which timed out so far.
+#[tokio::test]
+async fn command_deadline_is_logged() {
+    let captured = Captured::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(captured.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        command_deadline: Some(Duration::from_millis(50)),
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, std::future::pending::<()>(), config).await
+    });
+
+    for _ in 0..2 {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"*3\r\n$5\r\nDEBUG\r\n$5\r\nSLEEP\r\n$1\r\n5\r\n")
+            .await
+            .unwrap();
+
+        // 等待服务器关闭连接，这样超时已经被记录
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await.unwrap();
+    }
+
+    let logs = captured.to_string();
+    let errors: Vec<&str> = logs
+        .lines()
+        .filter(|line| line.contains("command exceeded execution deadline"))
+        .collect();
+
+    assert_eq!(2, errors.len(), "{}", logs);
+    assert!(errors.iter().all(|line| line.contains("command=debug")), "{}", logs);
+    assert!(errors[0].contains("total=1"), "{}", logs);
+    assert!(errors[1].contains("total=2"), "{}", logs);
+}
+
+/// Log output captured in memory.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl std::fmt::Display for Captured {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).fmt(fmt)
+    }
+}
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Captured {
+    type Writer = Captured;
+
+    fn make_writer(&'a self) -> Captured {
+        self.clone()
+    }
+}
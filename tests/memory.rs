@@ -0,0 +1,116 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::cmd::TypeHistogram;
+use my_mini_redis::server::{self, Config};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// The values are counted in the power of two bucket of their length, and
+/// every key is counted once although the keys are visited in many chunks.
+#[tokio::test]
+async fn histogram_of_known_sizes() {
+    // 时间片为0时每个分块只包含最少的key，迫使计算分成多块进行
+    let addr = start_server(Duration::ZERO).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.memory_histogram().await.unwrap().is_empty());
+
+    seed(&mut client, "empty", 3, 0).await;
+    seed(&mut client, "small", 500, 100).await;
+    seed(&mut client, "kilo", 200, 1000).await;
+    seed(&mut client, "exact", 10, 1024).await;
+    seed(&mut client, "mega", 2, 1_500_000).await;
+
+    let histograms = client.memory_histogram().await.unwrap();
+
+    let expected = TypeHistogram {
+        type_name: "string".to_string(),
+        keys: 715,
+        bytes: 500 * 100 + 200 * 1000 + 10 * 1024 + 2 * 1_500_000,
+        buckets: vec![(0, 3), (127, 500), (1023, 200), (2047, 10), (2_097_151, 2)],
+    };
+    assert_eq!(vec![expected], histograms);
+
+    // 低于1KB的key的数量
+    let under_1kb: u64 = histograms[0]
+        .buckets
+        .iter()
+        .filter(|(max_len, _)| *max_len < 1024)
+        .map(|(_, keys)| keys)
+        .sum();
+    assert_eq!(703, under_1kb);
+}
+
+/// Expired keys are not counted.
+#[tokio::test]
+async fn histogram_skips_expired_keys() {
+    let addr = start_server(Duration::from_millis(1)).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+    client
+        .set_expires("gone", "soon".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let histograms = client.memory_histogram().await.unwrap();
+    assert_eq!(1, histograms[0].keys);
+    assert_eq!(vec![(3, 1)], histograms[0].buckets);
+}
+
+/// Concurrent histograms either complete or are rejected with `BUSY`, and a
+/// new histogram can be computed once they are done.
+#[tokio::test]
+async fn concurrent_histograms() {
+    let addr = start_server(Duration::ZERO).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    seed(&mut client, "key", 2000, 10).await;
+
+    let mut tasks = vec![];
+    for _ in 0..4 {
+        tasks.push(tokio::spawn(async move {
+            let mut client = Client::connect(addr).await.unwrap();
+            client.memory_histogram().await
+        }));
+    }
+
+    for task in tasks {
+        match task.await.unwrap() {
+            Ok(histograms) => assert_eq!(2000, histograms[0].keys),
+            Err(err) => assert!(err.to_string().starts_with("BUSY"), "{}", err),
+        }
+    }
+
+    let histograms = client.memory_histogram().await.unwrap();
+    assert_eq!(vec![(15, 2000)], histograms[0].buckets);
+}
+
+/// Set `count` keys prefixed by `prefix` to values of `len` bytes.
+async fn seed(client: &mut Client, prefix: &str, count: usize, len: usize) {
+    let value = Bytes::from(vec![b'x'; len]);
+
+    let keys: Vec<String> = (0..count).map(|i| format!("{}:{}", prefix, i)).collect();
+    let pairs: Vec<(&str, Bytes)> = keys.iter().map(|key| (&key[..], value.clone())).collect();
+
+    client.mset(&pairs).await.unwrap();
+}
+
+async fn start_server(memory_histogram_slice: Duration) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        memory_histogram_slice,
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    addr
+}
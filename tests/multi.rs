@@ -0,0 +1,150 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::{server, Frame};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time::{self, Instant};
+
+/// The commands of a transaction are queued, then applied by `EXEC`, which
+/// replies with their replies. A `GET` sees the `SET` before it.
+#[tokio::test]
+async fn get_sees_set_in_transaction() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(client.query::<Frame>(&args(&["multi"])).await.unwrap(), "OK");
+    assert_eq!(client.query::<Frame>(&args(&["set", "foo", "bar"])).await.unwrap(), "QUEUED");
+    assert_eq!(client.query::<Frame>(&args(&["get", "foo"])).await.unwrap(), "QUEUED");
+    assert_eq!(client.query::<Frame>(&args(&["incr", "foo"])).await.unwrap(), "QUEUED");
+
+    // 队列中的命令还没有执行
+    let mut other = Client::connect(addr).await.unwrap();
+    assert!(other.get("foo").await.unwrap().is_none());
+
+    let replies: Vec<Frame> = client.query(&args(&["exec"])).await.unwrap();
+    assert_eq!(3, replies.len());
+    assert_eq!(replies[0], "OK");
+    assert_eq!(replies[1], "bar");
+    // 失败的命令不影响其他命令，错误在数组中返回
+    assert!(matches!(&replies[2], Frame::Error(msg) if msg.starts_with("ERR")));
+
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+
+    // 空事务回复空数组
+    client.query::<Frame>(&args(&["multi"])).await.unwrap();
+    let replies: Vec<Frame> = client.query(&args(&["exec"])).await.unwrap();
+    assert!(replies.is_empty());
+}
+
+#[tokio::test]
+async fn discard_drops_queued_commands() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.query::<Frame>(&args(&["multi"])).await.unwrap();
+    client.query::<Frame>(&args(&["set", "foo", "bar"])).await.unwrap();
+    assert_eq!(client.query::<Frame>(&args(&["discard"])).await.unwrap(), "OK");
+
+    assert!(client.get("foo").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn transaction_errors() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client.query::<Frame>(&args(&["exec"])).await.unwrap_err();
+    assert_eq!("ERR EXEC without MULTI", err.to_string());
+
+    let err = client.query::<Frame>(&args(&["discard"])).await.unwrap_err();
+    assert_eq!("ERR DISCARD without MULTI", err.to_string());
+
+    client.query::<Frame>(&args(&["multi"])).await.unwrap();
+
+    let err = client.query::<Frame>(&args(&["multi"])).await.unwrap_err();
+    assert_eq!("ERR MULTI calls can not be nested", err.to_string());
+
+    let err = client.query::<Frame>(&args(&["subscribe", "news"])).await.unwrap_err();
+    assert_eq!("ERR Command not allowed inside a transaction", err.to_string());
+
+    // 可能阻塞的命令不能在事务中执行，否则会一直持有锁
+    let err = client.query::<Frame>(&args(&["debug", "sleep", "0"])).await.unwrap_err();
+    assert_eq!("ERR Command not allowed inside a transaction", err.to_string());
+
+    // 被拒绝的命令不会结束事务
+    client.query::<Frame>(&args(&["set", "foo", "bar"])).await.unwrap();
+    let replies: Vec<Frame> = client.query(&args(&["exec"])).await.unwrap();
+    assert_eq!(1, replies.len());
+}
+
+/// The other clients see all of the changes of a transaction or none of
+/// them.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn transaction_is_applied_as_a_whole() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let writer = tokio::spawn(async move {
+        for _ in 0..100 {
+            client.query::<Frame>(&args(&["multi"])).await.unwrap();
+            client.query::<Frame>(&args(&["incr", "a"])).await.unwrap();
+            client.query::<Frame>(&args(&["incr", "b"])).await.unwrap();
+            let replies: Vec<Frame> = client.query(&args(&["exec"])).await.unwrap();
+            assert_eq!(2, replies.len());
+        }
+    });
+
+    // 两个key总是在同一个事务中修改，不会读到不同的值
+    let mut other = Client::connect(addr).await.unwrap();
+    while !writer.is_finished() {
+        let values: Vec<Frame> = other.query(&args(&["mget", "a", "b"])).await.unwrap();
+        assert_eq!(format!("{:?}", values[0]), format!("{:?}", values[1]));
+    }
+    writer.await.unwrap();
+
+    assert_eq!(Some("100".into()), other.get("b").await.unwrap());
+}
+
+/// A client sleeping in `DEBUG SLEEP` does not keep the transactions of the
+/// other clients from being applied.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn debug_sleep_does_not_block_exec() {
+    let addr = start_server().await;
+
+    let sleeper = tokio::spawn(async move {
+        let mut client = Client::connect(addr).await.unwrap();
+        let reply: String = client.query(&args(&["debug", "sleep", "0.3"])).await.unwrap();
+        assert_eq!("OK", reply);
+    });
+
+    // 等待sleep开始
+    time::sleep(Duration::from_millis(50)).await;
+
+    let start = Instant::now();
+    let mut client = Client::connect(addr).await.unwrap();
+    client.query::<Frame>(&args(&["multi"])).await.unwrap();
+    client.query::<Frame>(&args(&["set", "foo", "bar"])).await.unwrap();
+    let replies: Vec<Frame> = client.query(&args(&["exec"])).await.unwrap();
+    assert_eq!(1, replies.len());
+
+    let mut other = Client::connect(addr).await.unwrap();
+    assert_eq!(Some("bar".into()), other.get("foo").await.unwrap());
+    assert!(start.elapsed() < Duration::from_millis(150));
+
+    sleeper.await.unwrap();
+}
+
+fn args(args: &[&'static str]) -> Vec<Bytes> {
+    args.iter().map(|arg| Bytes::from_static(arg.as_bytes())).collect()
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -0,0 +1,100 @@
+use my_mini_redis::clients::{BufferedClient, Client, Namespaced};
+use my_mini_redis::server;
+
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// Two namespaced clients sharing a server never see each other's keys, even
+/// when the keys have the same name.
+#[tokio::test]
+async fn namespaces_are_isolated() {
+    let addr = start_server().await;
+
+    let mut a = Namespaced::new(Client::connect(addr).await.unwrap(), "a");
+    let buffered = BufferedClient::buffer(Client::connect(addr).await.unwrap());
+    let mut b = Namespaced::new(buffered, "b");
+
+    for i in 0..15 {
+        let key = format!("key:{}", i);
+        a.set(&key, "from a".into()).await.unwrap();
+        b.set(&key, "from b".into()).await.unwrap();
+    }
+    a.set("only-a", "from a".into()).await.unwrap();
+
+    assert_eq!(b"from a", &a.get("key:0").await.unwrap().unwrap()[..]);
+    assert_eq!(b"from b", &b.get("key:0").await.unwrap().unwrap()[..]);
+    assert!(b.get("only-a").await.unwrap().is_none());
+
+    let mut keys = a.keys("*").await.unwrap();
+    keys.sort();
+    let mut expected: Vec<String> = (0..15).map(|i| format!("key:{}", i)).collect();
+    expected.push("only-a".to_string());
+    expected.sort();
+    assert_eq!(expected, keys);
+
+    let mut keys = vec![];
+    let mut cursor = 0;
+    loop {
+        let (next, batch) = b.scan(cursor, Some("key:*"), Some(4)).await.unwrap();
+        keys.extend(batch);
+
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    keys.sort();
+    expected.retain(|key| key != "only-a");
+    assert_eq!(expected, keys);
+
+    // 在server上，key带有namespace前缀
+    let mut raw = Client::connect(addr).await.unwrap();
+    assert_eq!(b"from b", &raw.get("b:key:0").await.unwrap().unwrap()[..]);
+    assert!(raw.get("key:0").await.unwrap().is_none());
+}
+
+/// Glob characters in the namespace are matched literally.
+#[tokio::test]
+async fn namespace_glob_characters_are_escaped() {
+    let addr = start_server().await;
+
+    let mut star = Namespaced::new(Client::connect(addr).await.unwrap(), "*");
+    let mut other = Namespaced::new(Client::connect(addr).await.unwrap(), "x");
+
+    other.set("hello", "world".into()).await.unwrap();
+
+    assert!(star.keys("*").await.unwrap().is_empty());
+    assert_eq!(vec!["hello".to_string()], other.keys("*").await.unwrap());
+}
+
+/// Channels are only prefixed when a channel prefix is set, with the same
+/// separator as the keys.
+#[tokio::test]
+async fn channel_prefix() {
+    let addr = start_server().await;
+
+    let subscriber = Client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["events/news".into()]).await.unwrap();
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut client = Namespaced::new(client, "a")
+        .with_separator("/")
+        .with_channel_prefix("events");
+    assert_eq!("a/foo", client.key_name("foo"));
+    assert_eq!("events/news", client.channel_name("news"));
+
+    assert_eq!(1, client.publish("news", "hello".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("events/news", message.channel);
+    assert_eq!(b"hello", &message.content[..]);
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
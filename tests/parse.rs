@@ -0,0 +1,128 @@
+use my_mini_redis::cmd::Command;
+use my_mini_redis::Frame;
+
+use bytes::Bytes;
+
+/// Negative integers are accepted where a command takes signed arguments,
+/// from bulk, simple and integer frames alike.
+#[test]
+fn signed_int_arguments() {
+    let frame = Frame::Array(vec![
+        bulk("lrange"),
+        bulk("list"),
+        Frame::Simple("-3".into()),
+        bulk("-1"),
+    ]);
+
+    let Command::LRange(cmd) = Command::from_frame(frame).unwrap() else {
+        panic!("expected LRANGE");
+    };
+    assert_eq!(-3, cmd.start());
+    assert_eq!(-1, cmd.stop());
+
+    let frame = Frame::Array(vec![bulk("lrange"), bulk("list"), Frame::Integer(0), Frame::Integer(5)]);
+
+    let Command::LRange(cmd) = Command::from_frame(frame).unwrap() else {
+        panic!("expected LRANGE");
+    };
+    assert_eq!(0, cmd.start());
+    assert_eq!(5, cmd.stop());
+
+    // 超出`i64`的整数frame无法表示为有符号整数
+    let frame = Frame::Array(vec![bulk("lrange"), bulk("list"), Frame::Integer(u64::MAX), bulk("1")]);
+    assert!(Command::from_frame(frame).is_err());
+}
+
+/// Integer arguments only accept the canonical decimal form, for every
+/// command, and the error is the `ERR` reply of Redis.
+#[test]
+fn strict_int_arguments() {
+    for invalid in ["12abc", "+12", "012", "-0", " 1", "", "1.5", "9223372036854775808"] {
+        let commands = [
+            vec![bulk("incrby"), bulk("foo"), bulk(invalid)],
+            vec![bulk("getrange"), bulk("foo"), bulk(invalid), bulk("1")],
+            vec![bulk("setex"), bulk("foo"), bulk(invalid), bulk("bar")],
+            vec![bulk("restore"), bulk("foo"), bulk(invalid), bulk("payload")],
+            vec![bulk("lrange"), bulk("foo"), bulk("0"), bulk(invalid)],
+        ];
+
+        for args in commands {
+            let err = Command::from_frame(Frame::Array(args)).unwrap_err();
+            assert_eq!("ERR value is not an integer or out of range", err.to_string(), "{:?}", invalid);
+        }
+    }
+
+    // 无符号的参数也一样
+    for invalid in ["12abc", "+12", "012", "-1"] {
+        let frame = Frame::Array(vec![bulk("set"), bulk("foo"), bulk("bar"), bulk("EX"), bulk(invalid)]);
+        let err = Command::from_frame(frame).unwrap_err();
+        assert_eq!("ERR value is not an integer or out of range", err.to_string(), "{:?}", invalid);
+    }
+
+    let frame = Frame::Array(vec![bulk("incrby"), bulk("foo"), bulk("-12")]);
+    let Command::IncrBy(cmd) = Command::from_frame(frame).unwrap() else {
+        panic!("expected INCRBY");
+    };
+    assert_eq!(Some(-12), cmd.delta());
+}
+
+/// Float arguments are parsed from bulk and simple frames, and invalid ones
+/// are rejected.
+#[test]
+#[allow(clippy::approx_constant)]
+fn float_arguments() {
+    for increment in [bulk("3.14"), Frame::Simple("3.14".into())] {
+        let frame = Frame::Array(vec![bulk("incrbyfloat"), bulk("foo"), increment]);
+
+        let Command::IncrByFloat(cmd) = Command::from_frame(frame).unwrap() else {
+            panic!("expected INCRBYFLOAT");
+        };
+        assert_eq!(3.14, cmd.increment());
+    }
+
+    let frame = Frame::Array(vec![bulk("incrbyfloat"), bulk("foo"), bulk("-1e3")]);
+    let Command::IncrByFloat(cmd) = Command::from_frame(frame).unwrap() else {
+        panic!("expected INCRBYFLOAT");
+    };
+    assert_eq!(-1000.0, cmd.increment());
+
+    for invalid in ["abc", "3.14x", "", "nan", "inf"] {
+        let frame = Frame::Array(vec![bulk("incrbyfloat"), bulk("foo"), bulk(invalid)]);
+        let err = Command::from_frame(frame).unwrap_err();
+        assert_eq!("ERR value is not a valid float", err.to_string(), "{:?}", invalid);
+    }
+}
+
+/// Entries which must be strings are only checked when parsed, and the error
+/// names the first invalid one. Values, and options which are only compared,
+/// may be any bytes.
+#[test]
+fn invalid_utf8_arguments() {
+    let parse = |args: &[&'static [u8]]| {
+        let frame = Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::from_static(arg))).collect());
+        Command::from_frame(frame).map(|_| ()).map_err(|err| err.to_string())
+    };
+
+    let invalid = |position| Err(format!("protocol error: argument {} is not valid UTF-8", position));
+
+    assert_eq!(invalid(1), parse(&[b"get", b"\xff"]));
+    assert_eq!(invalid(3), parse(&[b"mget", b"a", b"b", b"\xc3\x28", b"\xff"]));
+    assert_eq!(invalid(2), parse(&[b"hget", b"user:1", b"na\xffme"]));
+    assert_eq!(invalid(1), parse(&[b"publish", b"\xe2\x82", b"hello"]));
+
+    // 值不需要是合法的UTF-8
+    assert_eq!(Ok(()), parse(&[b"set", b"caf\xc3\xa9", b"\xff\xfe"]));
+    assert_eq!(Ok(()), parse(&[b"hset", b"user:1", b"name", b"\xff"]));
+
+    // 选项只和关键字比较，非法的字节是语法错误而不是协议错误
+    assert_eq!(Err("ERR syntax error".to_string()), parse(&[b"set", b"k", b"v", b"\xff"]));
+    assert_eq!(Err("ERR syntax error".to_string()), parse(&[b"getex", b"k", b"\xffEX"]));
+
+    // 未知的命令名也可以是任意字节
+    let frame = Frame::Array(vec![Frame::Bulk(Bytes::from_static(b"\xffoo"))]);
+    assert!(matches!(Command::from_frame(frame).unwrap(), Command::Unknown(_)));
+}
+
+fn bulk(arg: &'static str) -> Frame {
+    Frame::Bulk(Bytes::from_static(arg.as_bytes()))
+}
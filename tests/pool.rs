@@ -0,0 +1,159 @@
+use my_mini_redis::{
+    clients::{ConnectOptions, Pool, ResolveStrategy},
+    server,
+};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io;
+use tokio::net::{TcpListener, TcpStream};
+
+/// `warm_up` opens exactly the requested number of connections, and the
+/// following requests reuse them instead of opening new ones.
+#[tokio::test]
+async fn warm_up_opens_connections() {
+    let (port, connections) = start_counting_server().await;
+
+    let options = ConnectOptions {
+        resolve_strategy: ResolveStrategy::Cached {
+            ttl: Duration::from_secs(60),
+        },
+        warm_up_parallelism: 2,
+        ..ConnectOptions::default()
+    };
+    let pool = Pool::new(format!("localhost:{}", port), options);
+
+    assert_eq!(3, pool.warm_up(3).await);
+    assert_eq!(3, pool.idle());
+
+    // 代理在accept之后才计数，每个连接完成一次请求后计数才是准确的
+    let mut clients = vec![];
+    for _ in 0..3 {
+        let mut client = pool.get().await.unwrap();
+        assert!(client.get("hello").await.unwrap().is_none());
+        clients.push(client);
+    }
+    assert_eq!(3, connections.load(Ordering::SeqCst));
+    drop(clients);
+
+    for _ in 0..5 {
+        let mut client = pool.get().await.unwrap();
+        client.set("hello", "world".into()).await.unwrap();
+
+        let value = client.get("hello").await.unwrap().unwrap();
+        assert_eq!(b"world", &value[..]);
+    }
+
+    assert_eq!(3, connections.load(Ordering::SeqCst));
+}
+
+/// Connections are resolved and established on demand when the pool was not
+/// warmed up.
+#[tokio::test]
+async fn get_connects_on_demand() {
+    let (port, connections) = start_counting_server().await;
+
+    let pool = Pool::new(format!("localhost:{}", port), ConnectOptions::default());
+    assert_eq!(0, connections.load(Ordering::SeqCst));
+
+    let mut first = pool.get().await.unwrap();
+    let mut second = pool.get().await.unwrap();
+    assert!(first.get("hello").await.unwrap().is_none());
+    assert!(second.get("hello").await.unwrap().is_none());
+    assert_eq!(2, connections.load(Ordering::SeqCst));
+
+    drop(first);
+    drop(second);
+    assert_eq!(2, pool.idle());
+}
+
+/// Concurrent tasks sharing a pool of at most 2 connections all complete,
+/// waiting for a connection to be returned, and no more than 2 connections are
+/// established.
+#[tokio::test]
+async fn max_size_bounds_connections() {
+    let (port, connections) = start_counting_server().await;
+
+    let options = ConnectOptions {
+        max_size: Some(2),
+        ..ConnectOptions::default()
+    };
+    let pool = Pool::new(format!("localhost:{}", port), options);
+
+    let mut tasks = vec![];
+    for i in 0..10 {
+        let pool = pool.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut client = pool.get().await.unwrap();
+            let key = format!("key:{}", i);
+            client.set(&key, i.to_string().into()).await.unwrap();
+
+            // 持有连接一段时间，让其他任务必须等待
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            client.get(&key).await.unwrap().unwrap()
+        }));
+    }
+
+    for (i, task) in tasks.into_iter().enumerate() {
+        assert_eq!(i.to_string().as_bytes(), &task.await.unwrap()[..]);
+    }
+
+    assert_eq!(2, pool.size());
+    assert_eq!(2, pool.idle());
+    assert_eq!(2, connections.load(Ordering::SeqCst));
+}
+
+/// The pool establishes `min_size` connections on the first `get`.
+#[tokio::test]
+async fn min_size_keeps_connections() {
+    let (port, _) = start_counting_server().await;
+
+    let options = ConnectOptions {
+        min_size: 3,
+        ..ConnectOptions::default()
+    };
+    let pool = Pool::new(format!("localhost:{}", port), options);
+    assert_eq!(0, pool.size());
+
+    let mut client = pool.get().await.unwrap();
+    assert_eq!(3, pool.size());
+    assert_eq!(2, pool.idle());
+
+    assert!(client.get("hello").await.unwrap().is_none());
+    drop(client);
+
+    let _client = pool.get().await.unwrap();
+    assert_eq!(3, pool.size());
+}
+
+/// Starts a server behind a proxy that counts the accepted connections.
+async fn start_counting_server() -> (u16, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = proxy.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    let counter = connections.clone();
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = proxy.accept().await.unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+
+            tokio::spawn(forward(socket, server_addr));
+        }
+    });
+
+    (port, connections)
+}
+
+async fn forward(mut socket: TcpStream, server_addr: SocketAddr) -> io::Result<()> {
+    let mut server = TcpStream::connect(server_addr).await?;
+    io::copy_bidirectional(&mut socket, &mut server).await?;
+    Ok(())
+}
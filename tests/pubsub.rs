@@ -0,0 +1,106 @@
+use my_mini_redis::pubsub::{PubSubReply, Strictness};
+use my_mini_redis::Frame;
+
+use bytes::Bytes;
+
+/// Every reply survives a `to_frame` / `try_from_frame` round trip.
+#[test]
+fn reply_round_trip() {
+    let replies = vec![
+        PubSubReply::Subscribe {
+            channel: "hello".into(),
+            num_subs: 1,
+        },
+        PubSubReply::Unsubscribe {
+            channel: "hello".into(),
+            num_subs: 0,
+        },
+        PubSubReply::PSubscribe {
+            pattern: "h*".into(),
+            num_subs: 2,
+        },
+        PubSubReply::PUnsubscribe {
+            pattern: "h*".into(),
+            num_subs: 1,
+        },
+        PubSubReply::Message {
+            channel: "hello".into(),
+            content: Bytes::from_static(b"world"),
+        },
+        PubSubReply::PMessage {
+            pattern: "h*".into(),
+            channel: "hello".into(),
+            content: Bytes::from_static(b"world"),
+        },
+        PubSubReply::SMessage {
+            channel: "hello".into(),
+            content: Bytes::from_static(b"world"),
+        },
+        PubSubReply::Lagged {
+            channel: "hello".into(),
+            skipped: 3,
+        },
+        PubSubReply::Backlog {
+            channel: "hello".into(),
+            published: 5,
+            truncated: true,
+        },
+        PubSubReply::Pong {
+            content: Bytes::from_static(b"hello"),
+        },
+    ];
+
+    for reply in replies {
+        let frame = reply.clone().to_frame();
+        assert_eq!(reply, PubSubReply::try_from_frame(&frame).unwrap());
+    }
+}
+
+/// Trailing entries are only accepted in lenient mode.
+#[test]
+fn reply_strictness() {
+    let frame = Frame::Array(vec![
+        Frame::Bulk(Bytes::from_static(b"subscribe")),
+        Frame::Bulk(Bytes::from_static(b"hello")),
+        Frame::Integer(1),
+        Frame::Integer(1),
+    ]);
+
+    assert!(PubSubReply::try_from_frame_with(&frame, Strictness::Strict).is_err());
+
+    let reply = PubSubReply::try_from_frame_with(&frame, Strictness::Lenient).unwrap();
+    assert_eq!(
+        PubSubReply::Subscribe {
+            channel: "hello".into(),
+            num_subs: 1,
+        },
+        reply
+    );
+}
+
+/// Frames that are not pub/sub replies are rejected.
+#[test]
+fn reply_invalid_shape() {
+    let frames = vec![
+        Frame::Simple("OK".into()),
+        Frame::Array(vec![]),
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from_static(b"bogus")),
+            Frame::Bulk(Bytes::from_static(b"hello")),
+            Frame::Integer(1),
+        ]),
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from_static(b"message")),
+            Frame::Bulk(Bytes::from_static(b"hello")),
+        ]),
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from_static(b"subscribe")),
+            Frame::Bulk(Bytes::from_static(b"hello")),
+            Frame::Bulk(Bytes::from_static(b"1")),
+        ]),
+    ];
+
+    for frame in frames {
+        assert!(PubSubReply::try_from_frame(&frame).is_err());
+    }
+}
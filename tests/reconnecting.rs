@@ -0,0 +1,134 @@
+use my_mini_redis::clients::{Client, ReconnectingClient, RetryPolicy};
+use my_mini_redis::server;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+/// Commands issued while the server restarts wait for it to come back, and
+/// succeed on a new connection.
+#[tokio::test]
+async fn commands_survive_server_restart() {
+    let server = Server::start(None).await;
+    let addr = server.addr;
+
+    let mut client = ReconnectingClient::connect(addr, policy()).await.unwrap();
+    client.set("foo", "one".into()).await.unwrap();
+    assert_eq!(Some("one".into()), client.get("foo").await.unwrap());
+
+    server.kill().await;
+    let restarted = tokio::spawn(async move {
+        time::sleep(Duration::from_millis(200)).await;
+        Server::start(Some(addr)).await
+    });
+
+    // 服务器重启后数据为空
+    client.set("foo", "two".into()).await.unwrap();
+    assert_eq!(Some("two".into()), client.get("foo").await.unwrap());
+
+    let server = restarted.await.unwrap();
+
+    // 再次重启，这次在两次命令之间
+    server.kill().await;
+    let restarted = tokio::spawn(async move {
+        time::sleep(Duration::from_millis(200)).await;
+        Server::start(Some(addr)).await
+    });
+    assert!(client.get("foo").await.unwrap().is_none());
+    restarted.await.unwrap();
+}
+
+/// Retries are bounded: a server which does not come back is reported.
+#[tokio::test]
+async fn retries_are_exhausted() {
+    let server = Server::start(None).await;
+
+    let policy = RetryPolicy {
+        max_retries: 2,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(20),
+    };
+    let mut client = ReconnectingClient::connect(server.addr, policy).await.unwrap();
+    client.ping(None).await.unwrap();
+
+    server.kill().await;
+
+    assert!(client.ping(None).await.is_err());
+}
+
+/// A subscription is established again on the same channels after a restart.
+#[tokio::test]
+async fn subscription_survives_server_restart() {
+    let server = Server::start(None).await;
+    let addr = server.addr;
+
+    let client = ReconnectingClient::connect(addr, policy()).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["news".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(1, publisher.publish("news", "before".into()).await.unwrap());
+    assert_eq!(&b"before"[..], &subscriber.next_message().await.unwrap().content[..]);
+
+    server.kill().await;
+    let _server = Server::start(Some(addr)).await;
+
+    // 在订阅者重新订阅之后才发布消息
+    let publish = tokio::spawn(async move {
+        let mut publisher = Client::connect(addr).await.unwrap();
+        while publisher.publish("news", "after".into()).await.unwrap() == 0 {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+    });
+
+    let message = subscriber.next_message().await.unwrap();
+    assert_eq!("news", message.channel);
+    assert_eq!(&b"after"[..], &message.content[..]);
+    assert_eq!(&["news".to_string()], subscriber.get_subscribed());
+
+    publish.await.unwrap();
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_retries: 10,
+        base_delay: Duration::from_millis(20),
+        max_delay: Duration::from_millis(200),
+    }
+}
+
+/// A server which can be shut down, closing all its connections.
+struct Server {
+    addr: SocketAddr,
+
+    shutdown: oneshot::Sender<()>,
+
+    handle: JoinHandle<()>,
+}
+
+impl Server {
+    /// Start a server on `addr`, or on a free port if `None`.
+    async fn start(addr: Option<SocketAddr>) -> Server {
+        let addr = addr.unwrap_or_else(|| "127.0.0.1:0".parse().unwrap());
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (shutdown, rx) = oneshot::channel::<()>();
+        let handle = tokio::spawn(async move {
+            server::run(listener, async move {
+                let _ = rx.await;
+            })
+            .await
+        });
+
+        Server { addr, shutdown, handle }
+    }
+
+    /// Shut the server down and wait for its connections to be closed.
+    async fn kill(self) {
+        self.shutdown.send(()).unwrap();
+        self.handle.await.unwrap();
+    }
+}
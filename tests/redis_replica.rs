@@ -0,0 +1,107 @@
+//! Replication to a real Redis server, only run with the `redis-interop`
+//! feature and the path of `redis-server` in `REDIS_SERVER`:
+//!
+//!     REDIS_SERVER=/usr/bin/redis-server cargo test --features redis-interop --test redis_replica
+
+#![cfg(feature = "redis-interop")]
+
+use my_mini_redis::clients::Client;
+use my_mini_redis::server;
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::process::{Child, Command};
+use tokio::time::{sleep, Instant};
+
+/// A Redis server started with `--replicaof` loads the keyspace, then applies
+/// every change made afterwards.
+#[tokio::test]
+async fn redis_replicates_keyspace() {
+    let addr = start_server().await;
+    let mut primary = Client::connect(addr).await.unwrap();
+
+    let long = Bytes::from("x".repeat(100_000));
+    primary.set("foo", "bar".into()).await.unwrap();
+    primary.set("long", long.clone()).await.unwrap();
+    primary.set_expires("ttl", "soon".into(), Duration::from_secs(60)).await.unwrap();
+
+    let (_redis, replica_addr) = start_redis_replica(addr).await;
+    let mut replica = connect(replica_addr).await;
+
+    wait_for(&mut replica, "foo", Some("bar".into())).await;
+    assert_eq!(Some(long), replica.get("long").await.unwrap());
+    assert_eq!(Some(Bytes::from("soon")), replica.get("ttl").await.unwrap());
+    let ttl: i64 = replica.query(&["ttl".into(), "ttl".into()]).await.unwrap();
+    assert!(ttl > 50);
+
+    primary.set("foo", "baz".into()).await.unwrap();
+    wait_for(&mut replica, "foo", Some("baz".into())).await;
+
+    primary.incr_by("counter", 5).await.unwrap();
+    wait_for(&mut replica, "counter", Some("5".into())).await;
+
+    primary.del(&["foo"]).await.unwrap();
+    wait_for(&mut replica, "foo", None).await;
+
+    primary.set_expires("short", "gone".into(), Duration::from_millis(200)).await.unwrap();
+    wait_for(&mut replica, "short", Some("gone".into())).await;
+    wait_for(&mut replica, "short", None).await;
+
+    primary.flushdb().await.unwrap();
+    wait_for(&mut replica, "counter", None).await;
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
+
+/// Starts `redis-server` as a replica of `primary`, without persistence.
+async fn start_redis_replica(primary: SocketAddr) -> (Child, SocketAddr) {
+    let path = std::env::var("REDIS_SERVER").expect("set REDIS_SERVER to the path of redis-server");
+
+    // 先占用一个端口，再交给Redis
+    let port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+
+    let child = Command::new(path)
+        .args(["--port", &port.to_string(), "--bind", "127.0.0.1"])
+        .args(["--replicaof", "127.0.0.1", &primary.port().to_string()])
+        .args(["--save", "", "--appendonly", "no"])
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start redis-server");
+
+    (child, SocketAddr::from(([127, 0, 0, 1], port)))
+}
+
+async fn connect(addr: SocketAddr) -> Client {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match Client::connect(addr).await {
+            Ok(client) => return client,
+            Err(_) if Instant::now() < deadline => sleep(Duration::from_millis(50)).await,
+            Err(err) => panic!("redis-server did not start: {}", err),
+        }
+    }
+}
+
+/// Waits for `key` to hold `expected` on the replica.
+async fn wait_for(replica: &mut Client, key: &str, expected: Option<Bytes>) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let value = replica.get(key).await.unwrap();
+        if value == expected {
+            return;
+        }
+        assert!(Instant::now() < deadline, "'{}' is {:?} on the replica, expected {:?}", key, value, expected);
+        sleep(Duration::from_millis(50)).await;
+    }
+}
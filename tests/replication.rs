@@ -0,0 +1,184 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::replication::{encode_rdb, SnapshotEntry};
+use my_mini_redis::serialize::crc64;
+use my_mini_redis::{server, Connection, Frame};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// The payload holds the header, the entries with their expiration, long
+/// values included, and the checksum Redis verifies.
+#[test]
+fn rdb_payload_layout() {
+    let long = Bytes::from("x".repeat(20_000));
+    let entries = [
+        SnapshotEntry {
+            key: "foo".to_string(),
+            value: "bar".into(),
+            expires_at: None,
+        },
+        SnapshotEntry {
+            key: "long".to_string(),
+            value: long.clone(),
+            expires_at: Some(0x0102_0304_0506_0708),
+        },
+    ];
+
+    let payload = encode_rdb(&entries);
+
+    let mut expected = b"REDIS0009\xfe\x00\xfb\x02\x01".to_vec();
+    expected.extend_from_slice(b"\x00\x03foo\x03bar");
+    expected.extend_from_slice(b"\xfc\x08\x07\x06\x05\x04\x03\x02\x01");
+    expected.extend_from_slice(b"\x00\x04long\x80\x00\x00\x4e\x20");
+    expected.extend_from_slice(&long);
+    expected.push(0xff);
+    expected.extend_from_slice(&crc64(&expected).to_le_bytes());
+
+    assert_eq!(expected, payload);
+
+    // 空的数据库也有数据库选择和校验和
+    let payload = encode_rdb(&[]);
+    assert_eq!(&payload[..15], b"REDIS0009\xfe\x00\xfb\x00\x00\xff");
+    assert_eq!(crc64(&payload[..15]).to_le_bytes(), payload[15..]);
+}
+
+/// A replica going through the handshake of Redis receives a snapshot of the
+/// keyspace, then every change as a command.
+#[tokio::test]
+async fn replica_receives_snapshot_then_changes() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+    client.set_expires("ttl", "soon".into(), Duration::from_secs(60)).await.unwrap();
+
+    let mut replica = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    for (request, reply) in [
+        ("*1\r\n$4\r\nPING\r\n", "+PONG\r\n"),
+        ("*3\r\n$8\r\nREPLCONF\r\n$14\r\nlistening-port\r\n$4\r\n6380\r\n", "+OK\r\n"),
+        ("*5\r\n$8\r\nREPLCONF\r\n$4\r\ncapa\r\n$3\r\neof\r\n$4\r\ncapa\r\n$6\r\npsync2\r\n", "+OK\r\n"),
+    ] {
+        replica.write_all(request.as_bytes()).await.unwrap();
+        assert_eq!(reply, read_line(&mut replica).await);
+    }
+
+    replica.write_all(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n").await.unwrap();
+    let fullresync = read_line(&mut replica).await;
+    let replid = fullresync.strip_prefix("+FULLRESYNC ").unwrap().strip_suffix(" 0\r\n").unwrap();
+    assert_eq!(40, replid.len());
+    assert!(replid.bytes().all(|b| b.is_ascii_hexdigit()));
+
+    // RDB的内容后面没有CRLF
+    let header = read_line(&mut replica).await;
+    let len: usize = header.strip_prefix('$').unwrap().trim_end().parse().unwrap();
+    let mut payload = vec![0; len];
+    replica.read_exact(&mut payload).await.unwrap();
+
+    assert!(payload.starts_with(b"REDIS0009"));
+    let (content, crc) = payload.split_at(len - 8);
+    assert_eq!(crc64(content).to_le_bytes(), crc);
+    assert!(contains(content, b"\x00\x03foo\x03bar"));
+    assert!(contains(content, b"\x00\x03ttl\x04soon"));
+    assert!(contains(content, b"\xfc"));
+
+    let mut replica = Connection::new(replica);
+    assert_eq!(["SELECT", "0"], read_command(&mut replica).await[..]);
+
+    let before = unix_millis();
+    client.set_expires("foo", "baz".into(), Duration::from_secs(60)).await.unwrap();
+    let set = read_command(&mut replica).await;
+    assert_eq!(["SET", "foo", "baz", "PXAT"], set[..4]);
+    let expires_at: u64 = set[4].parse().unwrap();
+    assert!(expires_at >= before + 60_000 && expires_at <= unix_millis() + 60_000);
+
+    client.set("foo", "qux".into()).await.unwrap();
+    assert_eq!(["SET", "foo", "qux"], read_command(&mut replica).await[..]);
+
+    client.del(&["foo"]).await.unwrap();
+    assert_eq!(["DEL", "foo"], read_command(&mut replica).await[..]);
+
+    client.flushdb().await.unwrap();
+    assert_eq!(["FLUSHDB"], read_command(&mut replica).await[..]);
+}
+
+/// Only strings are replicated: `PSYNC` is rejected when another type is
+/// stored, and the link is closed when one is written later.
+#[tokio::test]
+async fn replication_is_limited_to_strings() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.lpush("list", &["a".into()]).await.unwrap();
+
+    let mut replica = Connection::new(TcpStream::connect(addr).await.unwrap());
+    replica.write_frame(&command(&["PSYNC", "?", "-1"])).await.unwrap();
+    match replica.read_frame().await.unwrap() {
+        Some(Frame::Error(err)) => assert_eq!("ERR key 'list' holds a list, only strings can be replicated", err),
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+
+    client.del(&["list"]).await.unwrap();
+
+    let mut replica = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    replica.write_all(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n").await.unwrap();
+    assert!(read_line(&mut replica).await.starts_with("+FULLRESYNC "));
+    let header = read_line(&mut replica).await;
+    let len: usize = header.strip_prefix('$').unwrap().trim_end().parse().unwrap();
+    let mut payload = vec![0; len];
+    replica.read_exact(&mut payload).await.unwrap();
+
+    client.lpush("list", &["a".into()]).await.unwrap();
+
+    let mut replica = Connection::new(replica);
+    assert_eq!(["SELECT", "0"], read_command(&mut replica).await[..]);
+    assert!(replica.read_frame().await.unwrap().is_none());
+
+    let reply: Frame = client.query(&["multi".into()]).await.unwrap();
+    assert_eq!(reply, "OK");
+    let err = client.query::<Frame>(&["psync".into(), "?".into(), "-1".into()]).await.unwrap_err();
+    assert_eq!("ERR Command not allowed inside a transaction", err.to_string());
+}
+
+async fn read_line(src: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    src.read_line(&mut line).await.unwrap();
+    line
+}
+
+/// Reads a command of the replication stream, as its arguments.
+async fn read_command(src: &mut Connection<BufReader<TcpStream>>) -> Vec<String> {
+    match src.read_frame().await.unwrap() {
+        Some(Frame::Array(parts)) => parts
+            .into_iter()
+            .map(|part| match part {
+                Frame::Bulk(data) => String::from_utf8(data.to_vec()).unwrap(),
+                frame => panic!("unexpected frame {:?}", frame),
+            })
+            .collect(),
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn command(args: &[&str]) -> Frame {
+    Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes()))).collect())
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -0,0 +1,135 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::cmd::Ttl;
+use my_mini_redis::serialize::{crc64, decode, encode, DecodeError, DUMP_VERSION};
+use my_mini_redis::server;
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+#[test]
+fn reference_checksum() {
+    assert_eq!(0xe9c6d914c4b8d9ca, crc64(b"123456789"));
+    assert_eq!(0, crc64(b""));
+}
+
+/// Any value, including the empty one and values looking like a payload,
+/// decodes back to itself.
+#[test]
+fn round_trip() {
+    let mut seed: u64 = 42;
+    let mut next = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize
+    };
+
+    let mut values = vec![Vec::new(), encode(b"nested").to_vec()];
+    for _ in 0..500 {
+        let len = next() % 300;
+        values.push((0..len).map(|_| next() as u8).collect());
+    }
+
+    for value in values {
+        let payload = encode(&value);
+        assert_eq!(value.len() + 11, payload.len());
+        assert_eq!(&value[..], &decode(payload).unwrap()[..]);
+    }
+}
+
+/// Flipping any bit of a payload, or truncating it, is detected.
+#[test]
+fn corruption_is_detected() {
+    let payload = encode(b"hello world");
+
+    for i in 0..payload.len() {
+        for bit in 0..8 {
+            let mut corrupted = payload.to_vec();
+            corrupted[i] ^= 1 << bit;
+            assert_eq!(Err(DecodeError::Checksum), decode(corrupted.into()), "byte {} bit {}", i, bit);
+        }
+    }
+
+    for len in 0..11 {
+        assert_eq!(Err(DecodeError::Truncated), decode(payload.slice(..len)));
+    }
+    assert_eq!(Err(DecodeError::Checksum), decode(payload.slice(..payload.len() - 1)));
+}
+
+/// Payloads of another version, or of an unknown type, are rejected even
+/// with a valid checksum.
+#[test]
+fn version_and_type_are_checked() {
+    let with_checksum = |data: Vec<u8>| {
+        let mut payload = data.clone();
+        payload.extend_from_slice(&crc64(&data).to_le_bytes());
+        Bytes::from(payload)
+    };
+
+    let mut data = vec![0, b'x'];
+    data.extend_from_slice(&(DUMP_VERSION + 1).to_le_bytes());
+    assert_eq!(Err(DecodeError::Version(DUMP_VERSION + 1)), decode(with_checksum(data)));
+
+    let mut data = vec![7, b'x'];
+    data.extend_from_slice(&DUMP_VERSION.to_le_bytes());
+    assert_eq!(Err(DecodeError::UnknownType(7)), decode(with_checksum(data)));
+}
+
+/// A key is moved from one server to another with `DUMP` and `RESTORE`.
+#[tokio::test]
+async fn migrate_key_between_servers() {
+    let mut src = Client::connect(start_server().await).await.unwrap();
+    let mut dst = Client::connect(start_server().await).await.unwrap();
+
+    let value = Bytes::from((0..=255u8).cycle().take(10_000).collect::<Vec<_>>());
+    src.set("foo", value.clone()).await.unwrap();
+
+    assert!(src.dump("missing").await.unwrap().is_none());
+
+    let payload = src.dump("foo").await.unwrap().unwrap();
+    dst.restore("foo", Some(Duration::from_secs(100)), payload.clone()).await.unwrap();
+    assert_eq!(1, src.del(&["foo"]).await.unwrap());
+
+    assert_eq!(Some(value.clone()), dst.get("foo").await.unwrap());
+    assert!(matches!(dst.pttl("foo").await.unwrap(), Ttl::Expires(_)));
+
+    // 已经存在的key只有在`REPLACE`时被覆盖，同时移除TTL
+    let err = dst.restore("foo", None, payload.clone()).await.unwrap_err();
+    assert_eq!("BUSYKEY Target key name already exists.", err.to_string());
+
+    dst.restore_replace("foo", None, payload.clone()).await.unwrap();
+    assert_eq!(Ttl::Persistent, dst.pttl("foo").await.unwrap());
+    assert_eq!(Some(value), dst.get("foo").await.unwrap());
+}
+
+#[tokio::test]
+async fn restore_rejects_bad_payloads() {
+    let mut client = Client::connect(start_server().await).await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+    let payload = client.dump("foo").await.unwrap().unwrap();
+
+    let mut corrupted = payload.to_vec();
+    corrupted[1] ^= 1;
+    let err = client.restore("bar", None, corrupted.into()).await.unwrap_err();
+    assert_eq!("ERR DUMP payload version or checksum are wrong", err.to_string());
+
+    let err = client.restore("bar", None, "short".into()).await.unwrap_err();
+    assert_eq!("ERR Bad data format", err.to_string());
+
+    let args = [Bytes::from("restore"), "bar".into(), "-1".into(), payload];
+    let err = client.query::<Bytes>(&args).await.unwrap_err();
+    assert_eq!("ERR Invalid TTL value, must be >= 0", err.to_string());
+
+    // 错误不会关闭连接，也不会创建key
+    assert!(client.get("bar").await.unwrap().is_none());
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -0,0 +1,993 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::fanout::Overflow;
+use my_mini_redis::pubsub::Strictness;
+use my_mini_redis::server::{self, Config};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{self, Instant};
+
+/// The exact bytes of the subscribe, message and unsubscribe replies. These
+/// guard the wire shapes defined in `pubsub`.
+#[tokio::test]
+async fn pub_sub_wire_format() {
+    let addr = start_server().await;
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+
+    subscriber
+        .write_all(b"*2\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 34];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    let mut response = [0; 39];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nworld\r\n"[..],
+        &response[..]
+    );
+
+    subscriber
+        .write_all(b"*2\r\n$11\r\nunsubscribe\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 37];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$11\r\nunsubscribe\r\n$5\r\nhello\r\n:0\r\n"[..],
+        &response[..]
+    );
+}
+
+/// A message published on `news.tech` reaches a `news.*` pattern subscriber,
+/// along with the pattern and the channel it was published on.
+#[tokio::test]
+async fn psubscribe_receives_matching_channels() {
+    let addr = start_server().await;
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+
+    subscriber
+        .write_all(b"*2\r\n$10\r\npsubscribe\r\n$6\r\nnews.*\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 37];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$10\r\npsubscribe\r\n$6\r\nnews.*\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(0, publisher.publish("weather", "sunny".into()).await.unwrap());
+    assert_eq!(1, publisher.publish("news.tech", "rust".into()).await.unwrap());
+
+    // 不匹配的频道上的消息不会被收到
+    let mut response = [0; 55];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*4\r\n$8\r\npmessage\r\n$6\r\nnews.*\r\n$9\r\nnews.tech\r\n$4\r\nrust\r\n"[..],
+        &response[..]
+    );
+
+    subscriber
+        .write_all(b"*1\r\n$12\r\npunsubscribe\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 39];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$12\r\npunsubscribe\r\n$6\r\nnews.*\r\n:0\r\n"[..],
+        &response[..]
+    );
+
+    assert_eq!(0, publisher.publish("news.tech", "go".into()).await.unwrap());
+}
+
+/// Over RESP2, PING in pub/sub mode replies with a `pong` pub/sub array, and
+/// the connection stays subscribed.
+#[tokio::test]
+async fn subscribed_ping_resp2() {
+    let addr = start_server().await;
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+    subscribe_hello(&mut subscriber).await;
+
+    subscriber.write_all(b"*1\r\n$4\r\nping\r\n").await.unwrap();
+
+    let mut response = [0; 20];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(&b"*2\r\n$4\r\npong\r\n$0\r\n\r\n"[..], &response[..]);
+
+    subscriber
+        .write_all(b"*2\r\n$4\r\nping\r\n$2\r\nhi\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 22];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(&b"*2\r\n$4\r\npong\r\n$2\r\nhi\r\n"[..], &response[..]);
+
+    assert_still_subscribed(addr, &mut subscriber).await;
+}
+
+/// Over RESP3, PING in pub/sub mode replies as it does outside of it, and the
+/// connection stays subscribed.
+#[tokio::test]
+async fn subscribed_ping_resp3() {
+    let addr = start_server().await;
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+
+    subscriber
+        .write_all(b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n")
+        .await
+        .unwrap();
+
+    let mut response = vec![0; 4];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*6\r\n", &response[..]);
+
+    // 跳过服务器信息，直到最后的`proto`字段
+    let mut response = Vec::new();
+    while !response.ends_with(b"$5\r\nproto\r\n:3\r\n") {
+        let mut byte = [0; 1];
+        subscriber.read_exact(&mut byte).await.unwrap();
+        response.push(byte[0]);
+    }
+
+    subscribe_hello(&mut subscriber).await;
+
+    subscriber.write_all(b"*1\r\n$4\r\nping\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    subscriber
+        .write_all(b"*2\r\n$4\r\nping\r\n$2\r\nhi\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 8];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$2\r\nhi\r\n", &response);
+
+    assert_still_subscribed(addr, &mut subscriber).await;
+}
+
+/// SUBSCRIBE without channels replies with an arity error, both before and in
+/// the subscribed state, and the connection keeps being served.
+#[tokio::test]
+async fn subscribe_without_channels() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    let expected = b"-ERR wrong number of arguments for 'subscribe' command\r\n";
+
+    stream.write_all(b"*1\r\n$9\r\nsubscribe\r\n").await.unwrap();
+
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(&expected[..], &response[..]);
+
+    // 连接没有进入订阅状态
+    stream.write_all(b"*1\r\n$4\r\nping\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    subscribe_hello(&mut stream).await;
+
+    stream.write_all(b"*1\r\n$9\r\nsubscribe\r\n").await.unwrap();
+
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(&expected[..], &response[..]);
+
+    assert_still_subscribed(addr, &mut stream).await;
+}
+
+/// Subscribes `subscriber` to the `hello` channel.
+async fn subscribe_hello(subscriber: &mut TcpStream) {
+    subscriber
+        .write_all(b"*2\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 34];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+}
+
+/// Checks that messages published on `hello` still reach `subscriber`.
+async fn assert_still_subscribed(addr: SocketAddr, subscriber: &mut TcpStream) {
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(1, publisher.publish("hello", "world".into()).await.unwrap());
+
+    let mut response = [0; 39];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nworld\r\n"[..],
+        &response[..]
+    );
+}
+
+/// An array declaring fewer elements than sent leaves the trailing ones
+/// outside of any command, which is reported as a protocol error.
+#[tokio::test]
+async fn array_length_too_small() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*1\r\n$4\r\nPING\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-ERR Protocol error: expected command array, got `foo`; \
+            the previous array may have declared fewer elements than it contained\r\n"[..],
+        &response[..]
+    );
+}
+
+/// An array declaring more elements than sent pulls the next command into
+/// it, which is reported as a protocol error.
+#[tokio::test]
+async fn array_length_too_large() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nGET\r\n$5\r\nhello\r\n*1\r\n$4\r\nPING\r\n")
+        .await
+        .unwrap();
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-ERR Protocol error: nested array in command; \
+            the array may have declared more elements than it contained\r\n"[..],
+        &response[..]
+    );
+}
+
+/// A length prefix above `max_frame_len` closes the connection right away,
+/// without waiting for, or buffering, the announced data.
+#[tokio::test]
+async fn frame_too_large() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        max_frame_len: 1024,
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    for request in [
+        &b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$99999999999\r\n"[..],
+        &b"*99999999999\r\n"[..],
+    ] {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request).await.unwrap();
+
+        let mut response = vec![];
+        time::timeout(Duration::from_secs(1), stream.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.is_empty());
+    }
+
+    // 在限制之内的值不受影响
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("foo", Bytes::from(vec![b'x'; 1024])).await.unwrap();
+    assert_eq!(1024, client.strlen("foo").await.unwrap());
+}
+
+/// Connections idle for longer than `idle_timeout` are closed, while
+/// subscribed clients waiting for messages are kept.
+#[tokio::test]
+async fn idle_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        idle_timeout: Some(Duration::from_millis(100)),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let subscriber = Client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["news".into()]).await.unwrap();
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+    let start = Instant::now();
+    let mut response = vec![];
+    time::timeout(Duration::from_secs(1), stream.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(b"+PONG\r\n", &response[..]);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    time::sleep(Duration::from_millis(100)).await;
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(1, publisher.publish("news", "hello".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(&b"hello"[..], &message.content[..]);
+}
+
+/// `HELLO` reports the configured server name and version.
+#[tokio::test]
+async fn hello_reports_configured_version() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        server_name: "redis".to_string(),
+        server_version: "7.2.4".to_string(),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let info = client.hello(None).await.unwrap();
+    assert_eq!(info[0], "server");
+    assert_eq!(info[1], "redis");
+    assert_eq!(info[2], "version");
+    assert_eq!(info[3], "7.2.4");
+
+    // 默认报告crate的名字和版本
+    let default = Config::default();
+    assert_eq!("my-mini-redis", default.server_name);
+    assert_eq!(env!("CARGO_PKG_VERSION"), default.server_version);
+}
+
+/// `INFO server` reports the configured server name and version.
+#[tokio::test]
+async fn info_reports_configured_version() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        server_name: "redis".to_string(),
+        server_version: "7.2.4".to_string(),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let info = client.info(Some("server")).await.unwrap();
+    assert!(info.starts_with("# Server\r\n"));
+    assert!(info.contains("redis_version:7.2.4\r\n"));
+    assert!(info.contains("server_name:redis\r\n"));
+
+    // 默认的部分包含server
+    assert_eq!(info, client.info(None).await.unwrap());
+
+    // 不支持的部分被忽略
+    assert_eq!("", client.info(Some("keyspace")).await.unwrap());
+}
+
+/// Messages published on a bridged channel reach the subscribers of the
+/// channel it is bridged to.
+#[tokio::test]
+async fn bridge_republishes_messages() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        bridges: vec![("a".to_string(), "b".to_string())],
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let subscriber = Client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["b".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+
+    // 桥接任务本身是`a`的订阅者
+    assert_eq!(1, publisher.publish("a", "hello".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("b", message.channel);
+    assert_eq!(&b"hello"[..], &message.content[..]);
+
+    // 只有一个方向
+    assert_eq!(1, publisher.publish("b", "world".into()).await.unwrap());
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(&b"world"[..], &message.content[..]);
+}
+
+/// A command running past the configured deadline gets a `TIMEOUT` error and
+/// its connection is closed, while the other connections keep being served.
+/// The timed out commands are counted in `HEALTH`.
+#[tokio::test]
+async fn command_deadline() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        command_deadline: Some(Duration::from_millis(100)),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$5\r\nDEBUG\r\n$5\r\nSLEEP\r\n$1\r\n5\r\n")
+        .await
+        .unwrap();
+
+    // 另一个连接不受影响
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(b"PONG", &client.ping(None).await.unwrap()[..]);
+    assert_eq!(0, client.health().await.unwrap().timed_out_commands);
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-TIMEOUT command exceeded execution deadline\r\n"[..],
+        &response[..]
+    );
+
+    assert_eq!(b"PONG", &client.ping(None).await.unwrap()[..]);
+    assert_eq!(1, client.health().await.unwrap().timed_out_commands);
+
+    // 在deadline之内完成的命令正常回复
+    let reply: String = client
+        .query(&["debug".into(), "sleep".into(), "0.01".into()])
+        .await
+        .unwrap();
+    assert_eq!("OK", reply);
+    assert_eq!(1, client.health().await.unwrap().timed_out_commands);
+}
+
+/// DEBUG SLEEP LOCKED holds the lock of the database while sleeping, which
+/// blocks a concurrent GET, while a plain DEBUG SLEEP does not.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn debug_sleep_locked_blocks_other_commands() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for (args, blocked) in [(&["0.3", "LOCKED"][..], true), (&["0.3"][..], false)] {
+        let mut cmd = vec!["debug".into(), "sleep".into()];
+        cmd.extend(args.iter().map(|arg| arg.to_string().into()));
+
+        let sleeper = tokio::spawn(async move {
+            let mut client = Client::connect(addr).await.unwrap();
+            let reply: String = client.query(&cmd).await.unwrap();
+            assert_eq!("OK", reply);
+        });
+
+        // 等待sleep开始
+        time::sleep(Duration::from_millis(50)).await;
+
+        let start = Instant::now();
+        client.get("foo").await.unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(blocked, elapsed >= Duration::from_millis(150), "{:?}: {:?}", args, elapsed);
+
+        sleeper.await.unwrap();
+    }
+}
+
+/// With keyspace events enabled, writes are published on the keyevent and
+/// keyspace channels of the classes enabled.
+#[tokio::test]
+async fn keyspace_events() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        notify_keyspace_events: "KE$".parse().unwrap(),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let channels = vec![
+        "__keyevent@0__:set".to_string(),
+        "__keyspace@0__:foo".to_string(),
+        "__keyevent@0__:expired".to_string(),
+    ];
+    let mut subscriber = Client::connect(addr).await.unwrap().subscribe(channels).await.unwrap();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+
+    // 不同频道的消息之间没有顺序
+    let mut messages = vec![];
+    for _ in 0..2 {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        messages.push((message.channel, message.content));
+    }
+    messages.sort();
+    assert_eq!(
+        vec![
+            ("__keyevent@0__:set".to_string(), Bytes::from("foo")),
+            ("__keyspace@0__:foo".to_string(), Bytes::from("set")),
+        ],
+        messages
+    );
+
+    // 没有开启`x`，过期不会发布事件，下一条消息来自之后的SET
+    client.psetex("tmp", "1".into(), Duration::from_millis(10)).await.unwrap();
+    time::sleep(Duration::from_millis(50)).await;
+    client.set("bar", "baz".into()).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyevent@0__:set", message.channel);
+    assert_eq!(&b"tmp"[..], &message.content[..]);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyevent@0__:set", message.channel);
+    assert_eq!(&b"bar"[..], &message.content[..]);
+
+    assert!("Kq".parse::<my_mini_redis::db::KeyspaceEvents>().is_err());
+}
+
+/// Connections beyond `max_connections` are not served until a prior
+/// connection closes.
+#[tokio::test]
+async fn max_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        max_connections: 1,
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut first = Client::connect(addr).await.unwrap();
+    assert_eq!(b"PONG", &first.ping(None).await.unwrap()[..]);
+
+    // TCP连接可以建立，但是服务器不会处理它
+    let mut second = Client::connect(addr).await.unwrap();
+    let ping = tokio::spawn(async move { second.ping(None).await.unwrap() });
+
+    time::sleep(Duration::from_millis(100)).await;
+    assert!(!ping.is_finished());
+
+    drop(first);
+
+    let pong = time::timeout(Duration::from_secs(1), ping).await.unwrap().unwrap();
+    assert_eq!(b"PONG", &pong[..]);
+}
+
+/// With a connection warm-up, only `initial_connections` connections are
+/// served right after startup, and more once the limit has grown.
+#[tokio::test]
+async fn connection_warm_up() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        max_connections: 4,
+        initial_connections: 2,
+        connection_warm_up: Some(Duration::from_millis(1000)),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let start = Instant::now();
+
+    let mut clients = vec![];
+    for _ in 0..4 {
+        clients.push(Client::connect(addr).await.unwrap());
+    }
+
+    for client in &mut clients[..2] {
+        assert_eq!(b"PONG", &client.ping(None).await.unwrap()[..]);
+    }
+
+    // 其他连接要等到连接数上限增加之后才被处理。客户端在回复之后仍然保持连接，
+    // 否则释放的permit会让下一个连接被接收
+    let mut pings = vec![];
+    for mut client in clients.drain(2..) {
+        pings.push(tokio::spawn(async move {
+            let pong = client.ping(None).await.unwrap();
+            (pong, client)
+        }));
+    }
+
+    time::sleep(Duration::from_millis(200)).await;
+    assert!(pings.iter().all(|ping| !ping.is_finished()));
+
+    let mut served = vec![];
+    for ping in pings {
+        let (pong, client) = time::timeout(Duration::from_secs(2), ping).await.unwrap().unwrap();
+        assert_eq!(b"PONG", &pong[..]);
+        served.push((start.elapsed(), client));
+    }
+
+    // 上限在预热期间分两次增加
+    assert!(served[0].0 >= Duration::from_millis(400), "{:?}", served[0].0);
+    assert!(served[1].0 >= Duration::from_millis(900), "{:?}", served[1].0);
+}
+
+/// Subscribed clients churning through channels faster than
+/// `max_subscribe_churn` get errors, but stay subscribed and connected.
+#[tokio::test]
+async fn subscribe_churn_is_throttled() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        max_subscribe_churn: Some(10),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["news".into()]).await.unwrap();
+
+    let mut accepted = 0;
+    let mut rejected = 0;
+
+    for i in 0..50 {
+        let channel = [format!("flood-{}", i)];
+
+        for res in [
+            subscriber.subscribe(&channel).await,
+            subscriber.unsubscribe(&channel).await,
+        ] {
+            match res {
+                Ok(()) => accepted += 1,
+                Err(err) => {
+                    assert_eq!("ERR subscribe rate limit exceeded", err.to_string());
+                    rejected += 1;
+                }
+            }
+        }
+    }
+
+    assert!(accepted >= 10, "accepted {}", accepted);
+    assert!(rejected > 0, "rejected {}", rejected);
+
+    // 预算会随着时间恢复
+    time::sleep(Duration::from_millis(300)).await;
+    subscriber.subscribe(&["later".into()]).await.unwrap();
+
+    // 连接没有被关闭，之前的订阅仍然有效
+    let mut publisher = Client::connect(addr).await.unwrap();
+    publisher.publish("news", "hello".into()).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("news", message.channel);
+    assert_eq!(&b"hello"[..], &message.content[..]);
+}
+
+/// A server with lenient `pubsub_strictness` accepts subscriptions to an empty
+/// channel name, which the default strict one rejects.
+#[tokio::test]
+async fn lenient_server_accepts_empty_channel() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    assert_eq!(Strictness::Strict, Config::default().pubsub_strictness);
+
+    let config = Config {
+        pubsub_strictness: Strictness::Lenient,
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["".into()]).await.unwrap();
+    assert_eq!(vec![String::new()], subscriber.get_subscribed());
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(1, publisher.publish("", "hello".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("", message.channel);
+    assert_eq!(&b"hello"[..], &message.content[..]);
+}
+
+/// The streams returned by the acceptor are served instead of the sockets.
+/// Here the "handshake" is a preamble the client sends before any command,
+/// standing in for a TLS handshake.
+#[tokio::test]
+async fn serve_accepted_streams() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server::run_with_acceptor(
+            listener,
+            tokio::signal::ctrl_c(),
+            Config::default(),
+            |mut socket: TcpStream| async move {
+                let mut preamble = [0; 6];
+                socket.read_exact(&mut preamble).await?;
+
+                if &preamble != b"HELLO\n" {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "bad preamble"));
+                }
+
+                Ok(socket)
+            },
+        )
+        .await
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"HELLO\n").await.unwrap();
+
+    let mut client = Client::new(stream);
+    client.set("foo", "bar".into()).await.unwrap();
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+
+    // 握手失败的连接被关闭
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"HOWDY\n").await.unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    assert!(response.is_empty());
+
+    // 其他连接不受影响
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+}
+
+/// A client half-closing its connection right after a pipelined burst gets
+/// the reply to every command, followed by EOF.
+#[tokio::test]
+async fn half_close_after_pipelined_burst() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    let mut request = Vec::new();
+    let mut expected = Vec::new();
+
+    for i in 0..100 {
+        let key = format!("key-{:02}", i);
+        request.extend_from_slice(format!("*3\r\n$3\r\nSET\r\n$6\r\n{}\r\n$1\r\nv\r\n", key).as_bytes());
+        request.extend_from_slice(format!("*2\r\n$3\r\nGET\r\n$6\r\n{}\r\n", key).as_bytes());
+        expected.extend_from_slice(b"+OK\r\n$1\r\nv\r\n");
+    }
+
+    stream.write_all(&request).await.unwrap();
+    stream.shutdown().await.unwrap();
+
+    let mut response = Vec::new();
+    time::timeout(Duration::from_secs(5), stream.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(expected, response);
+}
+
+/// Commands sent in a single write are all replied to, in order.
+#[tokio::test]
+async fn pipelined_commands() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n*1\r\n$4\r\nPING\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 21];
+    time::timeout(Duration::from_secs(1), stream.read_exact(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&b"+OK\r\n$3\r\nbar\r\n+PONG\r\n"[..], &response[..]);
+}
+
+/// A `SUBSCRIBE` in the middle of a pipeline switches the connection to
+/// pub/sub mode for the commands after it.
+#[tokio::test]
+async fn subscribe_in_pipeline() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n*2\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n*1\r\n$4\r\nPING\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 59];
+    time::timeout(Duration::from_secs(1), stream.read_exact(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &b"+OK\r\n*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n*2\r\n$4\r\npong\r\n$0\r\n\r\n"[..],
+        &response[..]
+    );
+
+    assert_still_subscribed(addr, &mut stream).await;
+}
+
+/// A client which stops reading its replies is disconnected once a write
+/// exceeds `write_timeout`, releasing its slot.
+#[tokio::test]
+async fn write_timeout_closes_dead_clients() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        write_timeout: Some(Duration::from_millis(100)),
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("big", Bytes::from(vec![b'x'; 1024 * 1024])).await.unwrap();
+
+    // 请求远超过socket缓冲区大小的回复，但从不读取
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut request = Vec::new();
+    for _ in 0..128 {
+        request.extend_from_slice(b"*2\r\n$3\r\nGET\r\n$3\r\nbig\r\n");
+    }
+    stream.write_all(&request).await.unwrap();
+
+    assert_eq!(2, client.health().await.unwrap().connected_clients);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client.health().await.unwrap().connected_clients > 1 {
+        assert!(Instant::now() < deadline, "dead client was not disconnected");
+        time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// With `Overflow::Disconnect`, a subscriber which stops reading is dropped
+/// once its queue is full: publishing stops reaching it, and its connection
+/// ends with an error after the messages already written.
+#[tokio::test]
+async fn pubsub_overflow_disconnects_slow_subscriber() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        pubsub_queue_capacity: 2,
+        pubsub_overflow: Overflow::Disconnect,
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+    subscriber
+        .write_all(b"*2\r\n$9\r\nsubscribe\r\n$4\r\nslow\r\n")
+        .await
+        .unwrap();
+
+    let expected = b"*3\r\n$9\r\nsubscribe\r\n$4\r\nslow\r\n:1\r\n";
+    let mut response = vec![0; expected.len()];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(&expected[..], &response[..]);
+
+    // 订阅者不再读取，写缓冲区填满后队列也会填满
+    let mut client = Client::connect(addr).await.unwrap();
+    let message = Bytes::from(vec![b'x'; 256 * 1024]);
+    let mut published = 0;
+    while client.publish("slow", message.clone()).await.unwrap() == 1 {
+        published += 1;
+        assert!(published < 10_000, "slow subscriber was not disconnected");
+    }
+
+    let mut response = vec![];
+    time::timeout(Duration::from_secs(5), subscriber.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(response.ends_with(b"-ERR subscriber disconnected, too many messages pending\r\n"));
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
+
+/// A bulk value much larger than `read_buffer_capacity` spans many reads of
+/// the socket, and is still parsed correctly.
+#[tokio::test]
+async fn large_value_with_small_read_buffer() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = Config {
+        read_buffer_capacity: 16,
+        ..Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let value: Bytes = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>().into();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("large", value.clone()).await.unwrap();
+    client.set("small", "value".into()).await.unwrap();
+
+    assert_eq!(Some(value), client.get("large").await.unwrap());
+    assert_eq!(Some("value".into()), client.get("small").await.unwrap());
+}
@@ -0,0 +1,193 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::cmd::{StreamEntry, StreamId};
+use my_mini_redis::{server, Frame};
+
+use bytes::Bytes;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::time::{self, Duration};
+
+const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// Entries are added with given and generated ids, which must keep growing,
+/// and read back by ranges of ids with their fields in order.
+#[tokio::test]
+async fn add_and_range_entries() {
+    let mut client = Client::connect(start_server().await).await.unwrap();
+
+    let first = client
+        .xadd("events", Some(StreamId::new(1, 1)), &[("kind", "login".into()), ("user", "ada".into())])
+        .await
+        .unwrap();
+    assert_eq!(StreamId::new(1, 1), first);
+
+    let second = client.xadd("events", Some(StreamId::new(2, 0)), &[("kind", "logout".into())]).await.unwrap();
+
+    // 生成的id来自服务器的时钟，大于已有的id
+    let third = client.xadd("events", None, &[("kind", "login".into())]).await.unwrap();
+    assert!(third > second);
+
+    let err = client.xadd("events", Some(second), &[("kind", "late".into())]).await.unwrap_err();
+    assert_eq!(
+        "ERR The ID specified in XADD is equal or smaller than the target stream top item",
+        err.to_string()
+    );
+    let err = client.xadd("other", Some(StreamId::MIN), &[("kind", "zero".into())]).await.unwrap_err();
+    assert_eq!("ERR The ID specified in XADD must be greater than 0-0", err.to_string());
+    assert_eq!("none", client.key_type("other").await.unwrap());
+
+    let entries = client.xrange("events", StreamId::MIN, StreamId::MAX).await.unwrap();
+    assert_eq!(
+        vec![
+            (first, vec![("kind".to_string(), Bytes::from("login")), ("user".to_string(), Bytes::from("ada"))]),
+            (second, vec![("kind".to_string(), Bytes::from("logout"))]),
+            (third, vec![("kind".to_string(), Bytes::from("login"))]),
+        ],
+        entries
+    );
+
+    let ids = |entries: Vec<StreamEntry>| entries.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+    assert_eq!(vec![second], ids(client.xrange("events", StreamId::new(1, 2), second).await.unwrap()));
+    assert!(client.xrange("events", third, second).await.unwrap().is_empty());
+    assert!(client.xrange("missing", StreamId::MIN, StreamId::MAX).await.unwrap().is_empty());
+
+    // `-`和`+`表示最小和最大的id，省略序号的结束id包含这一毫秒的所有条目
+    let entries: Vec<Frame> = client.query(&["xrange".into(), "events".into(), "-".into(), "1".into()]).await.unwrap();
+    assert_eq!(1, entries.len());
+    let entries: Vec<Frame> = client.query(&["xrange".into(), "events".into(), "2".into(), "+".into()]).await.unwrap();
+    assert_eq!(2, entries.len());
+
+    assert_eq!("stream", client.key_type("events").await.unwrap());
+}
+
+/// Stream commands on another type, and other commands on a stream, reply
+/// with an error.
+#[tokio::test]
+async fn wrong_type_errors() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("string", "x".into()).await.unwrap();
+    client.hset("hash", &[("field", "value".into())]).await.unwrap();
+    client.xadd("stream", None, &[("field", "value".into())]).await.unwrap();
+
+    for key in ["string", "hash"] {
+        assert_eq!(WRONGTYPE, client.xadd(key, None, &[("field", "value".into())]).await.unwrap_err().to_string());
+        assert_eq!(WRONGTYPE, client.xrange(key, StreamId::MIN, StreamId::MAX).await.unwrap_err().to_string());
+
+        let subscriber = Client::connect(addr).await.unwrap();
+        let err = subscriber.xsubscribe(key, StreamId::MIN).await.err().unwrap();
+        assert_eq!(WRONGTYPE, err.to_string());
+    }
+
+    assert_eq!(WRONGTYPE, client.get("stream").await.unwrap_err().to_string());
+    assert_eq!(WRONGTYPE, client.hget("stream", "field").await.unwrap_err().to_string());
+}
+
+/// The entries after the given id are replayed, then the ones added later are
+/// delivered live, each with its id.
+#[tokio::test]
+async fn replays_then_delivers_live() {
+    let addr = start_server().await;
+    let mut producer = Client::connect(addr).await.unwrap();
+
+    let mut ids = vec![];
+    for n in 0..3 {
+        ids.push(producer.xadd("events", None, &[("n", n.to_string().into())]).await.unwrap());
+    }
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.xsubscribe("events", ids[0]).await.unwrap();
+    assert_eq!(ids[0], subscriber.last_id());
+
+    for (n, id) in ids.iter().enumerate().skip(1) {
+        let entry = subscriber.next_entry().await.unwrap().unwrap();
+        assert_eq!((*id, vec![("n".to_string(), Bytes::from(n.to_string()))]), entry);
+        assert_eq!(*id, subscriber.last_id());
+    }
+
+    // 没有新的条目时，订阅者等待
+    assert!(time::timeout(Duration::from_millis(50), subscriber.next_entry()).await.is_err());
+
+    let id = producer.xadd("events", None, &[("n", "3".into())]).await.unwrap();
+    let entry = subscriber.next_entry().await.unwrap().unwrap();
+    assert_eq!((id, vec![("n".to_string(), Bytes::from("3"))]), entry);
+
+    // 订阅一个还不存在的流，从它的第一个条目开始
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.xsubscribe("later", StreamId::MIN).await.unwrap();
+
+    let id = producer.xadd("later", None, &[("n", "0".into())]).await.unwrap();
+    assert_eq!(id, subscriber.next_entry().await.unwrap().unwrap().0);
+}
+
+/// A consumer killed mid-stream, while entries keep being added, resumes from
+/// the id it persisted after processing each entry: every entry is processed
+/// exactly once, in order, including one received but not processed before a
+/// kill.
+#[tokio::test]
+async fn resume_from_checkpoint_after_kill() {
+    const ENTRIES: usize = 500;
+
+    let addr = start_server().await;
+
+    let producer = tokio::spawn(async move {
+        let mut producer = Client::connect(addr).await.unwrap();
+
+        for n in 0..ENTRIES {
+            producer.xadd("events", None, &[("n", n.to_string().into())]).await.unwrap();
+
+            if n % 50 == 0 {
+                time::sleep(Duration::from_millis(5)).await;
+            }
+        }
+    });
+
+    let mut processed: Vec<(StreamId, Bytes)> = vec![];
+    let mut checkpoint = StreamId::MIN;
+
+    // 每轮处理一些条目后杀掉消费者，最后一轮读完剩下的条目
+    for round in [40, 1, 120, 0, 75, usize::MAX] {
+        let client = Client::connect(addr).await.unwrap();
+        let mut subscriber = client.xsubscribe("events", checkpoint).await.unwrap();
+        assert_eq!(checkpoint, subscriber.last_id());
+
+        let mut taken = 0;
+        while taken < round && processed.len() < ENTRIES {
+            let (id, fields) = subscriber.next_entry().await.unwrap().unwrap();
+            processed.push((id, fields[0].1.clone()));
+            checkpoint = subscriber.last_id();
+            taken += 1;
+        }
+
+        // 收到了但还没有处理的条目，没有记录检查点，恢复后会再次收到
+        if round != usize::MAX {
+            subscriber.next_entry().await.unwrap().unwrap();
+        }
+
+        drop(subscriber);
+    }
+
+    producer.await.unwrap();
+
+    let mut reader = Client::connect(addr).await.unwrap();
+    let all = reader.xrange("events", StreamId::MIN, StreamId::MAX).await.unwrap();
+    assert_eq!(ENTRIES, all.len());
+
+    let expected: Vec<(StreamId, Bytes)> = all.into_iter().map(|(id, fields)| (id, fields[0].1.clone())).collect();
+    assert_eq!(expected, processed);
+
+    for (n, (_, value)) in processed.iter().enumerate() {
+        assert_eq!(Bytes::from(n.to_string()), value);
+    }
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -0,0 +1,81 @@
+use my_mini_redis::clients::Client;
+use my_mini_redis::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use my_mini_redis::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use my_mini_redis::server::{self, Config};
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+/// A client connected with `connect_tls` to a server fronted with a rustls
+/// acceptor runs commands as over plain TCP.
+#[tokio::test]
+async fn connect_tls_to_rustls_acceptor() {
+    let (addr, cert) = start_tls_server().await;
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert).unwrap();
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let mut client = Client::connect_tls(addr, "localhost", Arc::new(config)).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+}
+
+/// The handshake fails when the certificate of the server is not trusted, or
+/// does not match the domain, and a plain TCP client is not served.
+#[tokio::test]
+async fn connect_tls_rejects_untrusted_server() {
+    let (addr, cert) = start_tls_server().await;
+
+    let untrusted = ClientConfig::builder()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+    assert!(Client::connect_tls(addr, "localhost", Arc::new(untrusted)).await.is_err());
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert).unwrap();
+    let config = Arc::new(
+        ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    );
+    assert!(Client::connect_tls(addr, "example.com", config.clone()).await.is_err());
+    assert!(Client::connect_tls(addr, "not a domain", config).await.is_err());
+
+    // 服务器期待TLS握手，明文的命令不会得到回复
+    let mut client = Client::connect(addr).await.unwrap();
+    assert!(client.ping(None).await.is_err());
+}
+
+/// Starts a server fronted with TLS, using a self-signed certificate for
+/// `localhost`. Returns its address and the certificate to trust.
+async fn start_tls_server() -> (SocketAddr, CertificateDer<'static>) {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()));
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.clone()], key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server::run_with_acceptor(
+            listener,
+            std::future::pending::<()>(),
+            Config::default(),
+            move |socket| acceptor.accept(socket),
+        )
+        .await
+    });
+
+    (addr, cert)
+}
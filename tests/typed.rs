@@ -0,0 +1,93 @@
+use my_mini_redis::clients::{Client, Codec, TypedError};
+use my_mini_redis::server;
+use bytes::Bytes;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+#[derive(Debug, PartialEq)]
+struct Address {
+    city: String,
+    zip: u32,
+}
+
+#[derive(Debug, PartialEq)]
+struct User {
+    name: String,
+    address: Address,
+}
+
+/// Encodes a `User` as its fields separated by newlines.
+struct Lines;
+
+impl Codec<User> for Lines {
+    fn encode(user: &User) -> my_mini_redis::Result<Bytes> {
+        let encoded = format!("{}\n{}\n{}", user.name, user.address.city, user.address.zip);
+        Ok(encoded.into())
+    }
+
+    fn decode(src: &[u8]) -> my_mini_redis::Result<User> {
+        let src = std::str::from_utf8(src)?;
+
+        match src.split('\n').collect::<Vec<_>>()[..] {
+            [name, city, zip] => Ok(User {
+                name: name.to_string(),
+                address: Address {
+                    city: city.to_string(),
+                    zip: zip.parse()?,
+                },
+            }),
+            _ => Err("expected 3 fields".into()),
+        }
+    }
+}
+
+/// A struct with nested fields survives a round trip through the server.
+#[tokio::test]
+async fn typed_round_trip() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let user = User {
+        name: "alice".to_string(),
+        address: Address {
+            city: "paris".to_string(),
+            zip: 75001,
+        },
+    };
+
+    client.set_typed::<Lines, _>("user", &user).await.unwrap();
+
+    let value: User = client.get_typed::<Lines, _>("user").await.unwrap();
+    assert_eq!(user, value);
+}
+
+/// A missing key and a value which cannot be decoded are reported apart, with
+/// the name of the key.
+#[tokio::test]
+async fn typed_missing_and_schema_mismatch() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client.get_typed::<Lines, User>("user").await.unwrap_err();
+    assert!(err.is_missing());
+    assert_eq!("key 'user' does not exist", err.to_string());
+
+    // 值由另一种格式写入
+    client.set("user", "alice;paris;75001".into()).await.unwrap();
+
+    let err = client.get_typed::<Lines, User>("user").await.unwrap_err();
+    assert!(matches!(&err, TypedError::Decode { key, .. } if key == "user"));
+    assert_eq!(
+        "failed to decode the value of key 'user' (stored by a different schema?): expected 3 fields",
+        err.to_string()
+    );
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}